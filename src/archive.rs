@@ -0,0 +1,347 @@
+//! Portable index archives
+//!
+//! `export_index` packs a codebase's vector index, keyword index, metadata
+//! store and change snapshot into one file, so CI can index a repository
+//! once and developers import the result instead of indexing locally. The
+//! stores are located by a hash of the codebase path, so they are unpacked
+//! under the importing codebase's path rather than the exporting one.
+//!
+//! Layout: a magic line, then every file's bytes back to back, then the JSON
+//! manifest listing each file's path, size and SHA-256, then the manifest
+//! length as a little-endian u64. Writing the manifest last lets files be
+//! hashed while they are streamed in.
+
+use crate::metadata::MetadataStore;
+use crate::search::BM25Search;
+use crate::snapshot::CodebaseInfo;
+use crate::sync::FileSynchronizer;
+use crate::vectordb::USearchDatabase;
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use tracing::info;
+
+const MAGIC: &[u8] = b"CODE-SAGE-INDEX\n";
+
+/// Version of the archive layout; archives of a newer version are refused
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+/// Describes an archive and the index it holds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub format_version: u32,
+    /// Where the index was built
+    pub codebase_path: PathBuf,
+    pub created_at: String,
+    /// Provider and model that embedded the chunks, when recorded
+    #[serde(default)]
+    pub embedding_provider: Option<String>,
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+    /// Snapshot entry of the exported index
+    #[serde(default)]
+    pub info: Option<CodebaseInfo>,
+    pub files: Vec<ArchiveFile>,
+}
+
+/// A file of the archive, stored in manifest order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveFile {
+    /// Store name, then the path inside the store, `/`-separated
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+impl ArchiveManifest {
+    pub fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+}
+
+/// Per-codebase stores an archive holds, by the name they have inside it
+fn stores(codebase_path: &Path, data_dir: &Path) -> Vec<(&'static str, PathBuf)> {
+    let vectors = USearchDatabase::get_index_path_for_codebase(codebase_path, data_dir);
+    let mut stores = vec![
+        ("fulltext", BM25Search::get_index_path_for_codebase(codebase_path, data_dir)),
+        ("metadata", MetadataStore::get_db_path_for_codebase(codebase_path, data_dir)),
+        ("snapshot.json", FileSynchronizer::get_snapshot_path(codebase_path, data_dir)),
+    ];
+    if let Some(vectors_dir) = vectors.parent() {
+        stores.push(("vectors", vectors_dir.to_path_buf()));
+    }
+    stores
+}
+
+/// Write the codebase's stores to an archive at `archive_path`; `manifest`
+/// supplies everything but the file list
+pub fn export(
+    archive_path: &Path,
+    codebase_path: &Path,
+    data_dir: &Path,
+    mut manifest: ArchiveManifest,
+) -> Result<ArchiveManifest> {
+    let mut sources = Vec::new();
+    for (name, store) in stores(codebase_path, data_dir) {
+        if store.is_file() {
+            sources.push((name.to_string(), store));
+            continue;
+        }
+        if !store.is_dir() {
+            continue;
+        }
+        for entry in walkdir::WalkDir::new(&store).sort_by_file_name() {
+            let entry = entry.map_err(|e| Error::Io(std::io::Error::other(e)))?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(&store).unwrap_or(entry.path());
+            let parts: Vec<String> = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy().to_string())
+                .collect();
+            sources.push((format!("{}/{}", name, parts.join("/")), entry.path().to_path_buf()));
+        }
+    }
+
+    if let Some(parent) = archive_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // Written next to the target and renamed, so a failed export never
+    // leaves a truncated archive where a download script expects one
+    let temp_path = archive_path.with_extension("partial");
+    let mut writer = BufWriter::new(std::fs::File::create(&temp_path)?);
+    writer.write_all(MAGIC)?;
+
+    manifest.format_version = ARCHIVE_FORMAT_VERSION;
+    manifest.files.clear();
+    for (path, source) in sources {
+        let mut reader = BufReader::new(std::fs::File::open(&source)?);
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut size = 0u64;
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            writer.write_all(&buffer[..read])?;
+            size += read as u64;
+        }
+        manifest.files.push(ArchiveFile { path, size, sha256: format!("{:x}", hasher.finalize()) });
+    }
+
+    let manifest_json = serde_json::to_vec(&manifest)?;
+    writer.write_all(&manifest_json)?;
+    writer.write_all(&(manifest_json.len() as u64).to_le_bytes())?;
+    let file = writer.into_inner().map_err(|e| Error::Io(e.into_error()))?;
+    file.sync_all()?;
+    std::fs::rename(&temp_path, archive_path)?;
+
+    info!(
+        "[ARCHIVE] Exported {} files ({} bytes) of {} to {}",
+        manifest.files.len(),
+        manifest.total_bytes(),
+        codebase_path.display(),
+        archive_path.display()
+    );
+    Ok(manifest)
+}
+
+/// Manifest of the archive at `archive_path`, without unpacking it
+pub fn read_manifest(archive_path: &Path) -> Result<ArchiveManifest> {
+    let corrupted = |reason: &str| Error::Corrupted(format!("{}: {reason}", archive_path.display()));
+
+    let mut file = std::fs::File::open(archive_path)?;
+    let length = file.metadata()?.len();
+    let mut magic = vec![0u8; MAGIC.len()];
+    if length < (MAGIC.len() + 8) as u64 || file.read_exact(&mut magic).is_err() || magic != MAGIC {
+        return Err(corrupted("not an index archive"));
+    }
+
+    let mut manifest_len = [0u8; 8];
+    file.seek(SeekFrom::End(-8))?;
+    file.read_exact(&mut manifest_len)?;
+    let manifest_len = u64::from_le_bytes(manifest_len);
+    if manifest_len > length - (MAGIC.len() + 8) as u64 {
+        return Err(corrupted("truncated archive"));
+    }
+    let mut manifest_json = vec![0u8; manifest_len as usize];
+    file.seek(SeekFrom::End(-8 - manifest_len as i64))?;
+    file.read_exact(&mut manifest_json)?;
+    let manifest: ArchiveManifest =
+        serde_json::from_slice(&manifest_json).map_err(|e| corrupted(&format!("unreadable manifest ({e})")))?;
+
+    if manifest.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(corrupted(&format!(
+            "archive format {} is newer than the supported {}; upgrade code-sage",
+            manifest.format_version, ARCHIVE_FORMAT_VERSION
+        )));
+    }
+    if MAGIC.len() as u64 + manifest.total_bytes() + manifest_len + 8 != length {
+        return Err(corrupted("file sizes do not match the manifest"));
+    }
+    Ok(manifest)
+}
+
+/// Unpack the archive at `archive_path` as the stores of `codebase_path`,
+/// replacing any it has. Every file is checked against the manifest in a
+/// staging directory before the existing stores are touched.
+pub fn import(archive_path: &Path, codebase_path: &Path, data_dir: &Path) -> Result<ArchiveManifest> {
+    let manifest = read_manifest(archive_path)?;
+    let corrupted = |reason: String| Error::Corrupted(format!("{}: {reason}", archive_path.display()));
+
+    let staging = data_dir.join(format!("import-{}", uuid::Uuid::new_v4()));
+    let unpacked = unpack(archive_path, &manifest, &staging).map_err(|e| match e {
+        Error::Corrupted(reason) => corrupted(reason),
+        other => other,
+    });
+    if let Err(e) = unpacked {
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    for (name, store) in stores(codebase_path, data_dir) {
+        if store.is_dir() {
+            std::fs::remove_dir_all(&store)?;
+        } else if store.exists() {
+            std::fs::remove_file(&store)?;
+        }
+        let staged = staging.join(name);
+        if !staged.exists() {
+            continue;
+        }
+        if let Some(parent) = store.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::rename(&staged, &store)?;
+    }
+    std::fs::remove_dir_all(&staging)?;
+
+    info!(
+        "[ARCHIVE] Imported {} files of {} as {}",
+        manifest.files.len(),
+        manifest.codebase_path.display(),
+        codebase_path.display()
+    );
+    Ok(manifest)
+}
+
+/// Write the archive's files below `staging`, verifying their checksums
+fn unpack(archive_path: &Path, manifest: &ArchiveManifest, staging: &Path) -> Result<()> {
+    let mut reader = BufReader::new(std::fs::File::open(archive_path)?);
+    reader.seek(SeekFrom::Start(MAGIC.len() as u64))?;
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    for file in &manifest.files {
+        let target = staging.join(safe_relative_path(&file.path)?);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut writer = BufWriter::new(std::fs::File::create(&target)?);
+        let mut hasher = Sha256::new();
+        let mut remaining = file.size;
+        while remaining > 0 {
+            let chunk = remaining.min(buffer.len() as u64) as usize;
+            reader.read_exact(&mut buffer[..chunk])?;
+            hasher.update(&buffer[..chunk]);
+            writer.write_all(&buffer[..chunk])?;
+            remaining -= chunk as u64;
+        }
+        writer.flush()?;
+        if format!("{:x}", hasher.finalize()) != file.sha256 {
+            return Err(Error::Corrupted(format!("checksum mismatch for {}", file.path)));
+        }
+    }
+    Ok(())
+}
+
+/// `path` as a relative path that cannot leave the staging directory
+fn safe_relative_path(path: &str) -> Result<PathBuf> {
+    let relative = PathBuf::from_iter(path.split('/'));
+    let normal = relative.components().all(|component| matches!(component, Component::Normal(_)));
+    if !normal || path.split('/').any(|part| part.is_empty() || part == "." || part == "..") {
+        return Err(Error::Corrupted(format!("invalid path '{path}'")));
+    }
+    Ok(relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn manifest(codebase_path: &Path) -> ArchiveManifest {
+        ArchiveManifest {
+            format_version: ARCHIVE_FORMAT_VERSION,
+            codebase_path: codebase_path.to_path_buf(),
+            created_at: String::new(),
+            embedding_provider: None,
+            embedding_model: None,
+            info: None,
+            files: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_export_and_import_under_another_path() {
+        let dir = tempdir().unwrap();
+        let source_data = dir.path().join("ci");
+        let target_data = dir.path().join("laptop");
+        let source = dir.path().join("ci-checkout");
+        let target = dir.path().join("home-checkout");
+
+        let metadata_dir = MetadataStore::get_db_path_for_codebase(&source, &source_data);
+        std::fs::create_dir_all(metadata_dir.join("blobs")).unwrap();
+        std::fs::write(metadata_dir.join("db"), b"chunks").unwrap();
+        std::fs::write(metadata_dir.join("blobs").join("1"), b"blob").unwrap();
+        let snapshot_path = FileSynchronizer::get_snapshot_path(&source, &source_data);
+        std::fs::create_dir_all(snapshot_path.parent().unwrap()).unwrap();
+        std::fs::write(&snapshot_path, b"{}").unwrap();
+
+        let archive = dir.path().join("index.archive");
+        let exported = export(&archive, &source, &source_data, manifest(&source)).unwrap();
+        assert_eq!(exported.files.len(), 3);
+        assert_eq!(read_manifest(&archive).unwrap().total_bytes(), 12);
+
+        // Stale stores of the importing codebase are replaced
+        let stale = MetadataStore::get_db_path_for_codebase(&target, &target_data).join("stale");
+        std::fs::create_dir_all(stale.parent().unwrap()).unwrap();
+        std::fs::write(&stale, b"old").unwrap();
+
+        let imported = import(&archive, &target, &target_data).unwrap();
+        assert_eq!(imported.codebase_path, source);
+        let metadata_dir = MetadataStore::get_db_path_for_codebase(&target, &target_data);
+        assert_eq!(std::fs::read(metadata_dir.join("blobs").join("1")).unwrap(), b"blob");
+        assert!(!stale.exists());
+        assert_eq!(std::fs::read(FileSynchronizer::get_snapshot_path(&target, &target_data)).unwrap(), b"{}");
+    }
+
+    #[test]
+    fn test_damaged_archive_is_refused_before_replacing_stores() {
+        let dir = tempdir().unwrap();
+        let data_dir = dir.path().join("data");
+        let codebase = dir.path().join("project");
+        let metadata_dir = MetadataStore::get_db_path_for_codebase(&codebase, &data_dir);
+        std::fs::create_dir_all(&metadata_dir).unwrap();
+        std::fs::write(metadata_dir.join("db"), b"chunks").unwrap();
+
+        let archive = dir.path().join("index.archive");
+        export(&archive, &codebase, &data_dir, manifest(&codebase)).unwrap();
+        let mut bytes = std::fs::read(&archive).unwrap();
+        bytes[MAGIC.len()] ^= 0xff;
+        std::fs::write(&archive, &bytes).unwrap();
+
+        assert!(matches!(import(&archive, &codebase, &data_dir), Err(Error::Corrupted(_))));
+        assert_eq!(std::fs::read(metadata_dir.join("db")).unwrap(), b"chunks");
+        assert!(!std::fs::read_dir(&data_dir).unwrap().any(|entry| {
+            entry.unwrap().file_name().to_string_lossy().starts_with("import-")
+        }));
+
+        assert!(safe_relative_path("metadata/../../etc").is_err());
+        assert!(safe_relative_path("/etc/passwd").is_err());
+    }
+}
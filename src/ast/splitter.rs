@@ -1,9 +1,9 @@
 
-use crate::{CodeChunk, ChunkMetadata, Language, Error, Result};
+use crate::{CodeChunk, ChunkMetadata, ChunkOrigin, Language, ParseQuality, Error, Result};
 use sha2::{Sha256, Digest};
 use std::path::Path;
-use std::collections::HashSet;
-use tree_sitter::{Parser, Node};
+use std::collections::{HashMap, HashSet};
+use tree_sitter::{Parser, Node, Tree};
 
 /// Context for chunk creation operations
 struct ChunkContext<'a> {
@@ -32,32 +32,54 @@ impl AstSplitter {
         file_path: &Path,
         relative_path: &str,
     ) -> Result<Vec<CodeChunk>> {
-        let lang = language.parse::<Language>().unwrap_or(Language::Unknown);
-        
-        // For unknown languages, go directly to fallback
-        if lang == Language::Unknown {
-            tracing::info!("Unknown language for {:?}, using character-based fallback", file_path);
-            return self.split_with_fallback(content, lang, file_path, relative_path);
-        }
-        
-        // Try AST-based splitting first
-        match self.split_with_ast(content, lang, file_path, relative_path) {
-            Ok(chunks) if !chunks.is_empty() => Ok(chunks),
-            _ => {
-                // Fallback to character-based splitting
-                tracing::warn!("AST parsing failed for {:?}, using character-based fallback", file_path);
-                self.split_with_fallback(content, lang, file_path, relative_path)
-            }
-        }
+        self.chunk_code_with_origin(content, language, file_path, relative_path)
+            .map(|(chunks, _origin, _quality)| chunks)
     }
 
-    fn split_with_ast(
+    /// Like `chunk_code`, also reporting whether the AST or the fallback splitter
+    /// was used and, when a grammar parsed the file, how much of it failed to parse
+    pub fn chunk_code_with_origin(
         &self,
         content: &str,
-        language: Language,
+        language: &str,
         file_path: &Path,
         relative_path: &str,
-    ) -> Result<Vec<CodeChunk>> {
+    ) -> Result<(Vec<CodeChunk>, ChunkOrigin, Option<ParseQuality>)> {
+        let lang = language.parse::<Language>().unwrap_or(Language::Unknown);
+        
+        let (mut chunks, origin, quality) = if lang == Language::Unknown {
+            // For unknown languages, go directly to fallback
+            tracing::info!("Unknown language for {:?}, using character-based fallback", file_path);
+            (self.split_with_fallback(content, lang, file_path, relative_path)?, ChunkOrigin::Fallback, None)
+        } else {
+            let tree = Self::parse(content, lang);
+            let quality = tree.as_ref().ok().map(|tree| parse_quality(tree.root_node(), content.len()));
+            if let Some(quality) = quality.filter(ParseQuality::is_degraded) {
+                tracing::warn!(
+                    "{:.0}% of {:?} is inside {} parse error(s); the {} grammar may not support this syntax",
+                    quality.error_rate() * 100.0,
+                    file_path,
+                    quality.error_nodes,
+                    lang.as_str()
+                );
+            }
+            
+            // Try AST-based splitting first
+            match tree.and_then(|tree| self.split_with_ast(&tree, content, lang, file_path, relative_path)) {
+                Ok(chunks) if !chunks.is_empty() => (chunks, ChunkOrigin::Ast, quality),
+                _ => {
+                    // Fallback to character-based splitting
+                    tracing::warn!("AST parsing failed for {:?}, using character-based fallback", file_path);
+                    (self.split_with_fallback(content, lang, file_path, relative_path)?, ChunkOrigin::Fallback, quality)
+                }
+            }
+        };
+        
+        assign_chunk_ids(&mut chunks);
+        Ok((chunks, origin, quality))
+    }
+
+    fn parse(content: &str, language: Language) -> Result<Tree> {
         // Create parser for language
         let mut parser = Parser::new();
         
@@ -94,9 +116,18 @@ impl AstSplitter {
         parser.set_language(&ts_lang)
             .map_err(|e| Error::TreeSitter(format!("Failed to set language: {e}")))?;
         
-        let tree = parser.parse(content, None)
-            .ok_or_else(|| Error::TreeSitter("Failed to parse code".to_string()))?;
-        
+        parser.parse(content, None)
+            .ok_or_else(|| Error::TreeSitter("Failed to parse code".to_string()))
+    }
+
+    fn split_with_ast(
+        &self,
+        tree: &Tree,
+        content: &str,
+        language: Language,
+        file_path: &Path,
+        relative_path: &str,
+    ) -> Result<Vec<CodeChunk>> {
         let root_node = tree.root_node();
         
         let chunks = self.extract_chunks_from_ast(
@@ -173,19 +204,20 @@ impl AstSplitter {
         Ok(deduplicated)
     }
     
+    /// Nested AST nodes can yield the same line range twice; keep the first
     fn deduplicate_chunks(&self, chunks: Vec<CodeChunk>) -> Vec<CodeChunk> {
-        let mut seen_ids = HashSet::new();
+        let mut seen_ranges = HashSet::new();
         let original_count = chunks.len();
         
         let deduplicated: Vec<CodeChunk> = chunks
             .into_iter()
-            .filter(|chunk| seen_ids.insert(chunk.id.clone()))
+            .filter(|chunk| seen_ranges.insert((chunk.start_line, chunk.end_line)))
             .collect();
         
         let removed_count = original_count - deduplicated.len();
         if removed_count > 0 {
             tracing::debug!(
-                "Deduplicated {} chunks with duplicate IDs (kept {}/{})",
+                "Deduplicated {} chunks with duplicate line ranges (kept {}/{})",
                 removed_count,
                 deduplicated.len(),
                 original_count
@@ -438,20 +470,9 @@ impl AstSplitter {
         chunk_index: usize,
         ctx: &ChunkContext,
     ) -> Result<CodeChunk> {
-        let mut hasher = Sha256::new();
-        hasher.update(ctx.file_path.to_string_lossy().as_bytes());
-        hasher.update(b":");
-        hasher.update(start_line.to_string().as_bytes());
-        hasher.update(b":");
-        hasher.update(end_line.to_string().as_bytes());
-        let id = format!("{:x}", hasher.finalize());
-        
-        let mut content_hasher = Sha256::new();
-        content_hasher.update(&content);
-        let content_hash = format!("{:x}", content_hasher.finalize());
-        
         Ok(CodeChunk {
-            id,
+            // Assigned by `assign_chunk_ids` once the file's chunks are final
+            id: String::new(),
             content,
             file_path: ctx.file_path.to_path_buf(),
             relative_path: ctx.relative_path.to_string(),
@@ -465,7 +486,10 @@ impl AstSplitter {
                     .unwrap_or("")
                     .to_string(),
                 chunk_index,
-                hash: content_hash,
+                hash: String::new(),
+                modified_at: None,
+                file_hash: None,
+                sync_generation: None,
             },
         })
     }
@@ -503,16 +527,8 @@ impl AstSplitter {
                 new_start_line = new_start_line.saturating_sub(overlap_line_count);
             }
 
-            let mut hasher = Sha256::new();
-            hasher.update(chunk.file_path.to_string_lossy().as_bytes());
-            hasher.update(b":");
-            hasher.update(chunk.start_line.to_string().as_bytes());
-            hasher.update(b":");
-            hasher.update(chunk.end_line.to_string().as_bytes());
-            let new_id = format!("{:x}", hasher.finalize());
-
             overlapped.push(CodeChunk {
-                id: new_id,
+                id: String::new(),
                 content: new_content,
                 file_path: chunk.file_path.clone(),
                 relative_path: chunk.relative_path.clone(),
@@ -560,20 +576,8 @@ impl AstSplitter {
             let chunk_lines = chunk_content.lines().count();
             let end_line = start_line + chunk_lines.max(1) - 1;
             
-            let mut hasher = Sha256::new();
-            hasher.update(file_path.to_string_lossy().as_bytes());
-            hasher.update(b":");
-            hasher.update(start_line.to_string().as_bytes());
-            hasher.update(b":");
-            hasher.update(end_line.to_string().as_bytes());
-            let id = format!("{:x}", hasher.finalize());
-            
-            let mut content_hasher = Sha256::new();
-            content_hasher.update(chunk_content.as_bytes());
-            let content_hash = format!("{:x}", content_hasher.finalize());
-            
             let chunk = CodeChunk {
-                id,
+                id: String::new(),
                 content: chunk_content.to_string(),
                 file_path: file_path.to_path_buf(),
                 relative_path: relative_path.to_string(),
@@ -587,7 +591,10 @@ impl AstSplitter {
                         .unwrap_or("")
                         .to_string(),
                     chunk_index,
-                    hash: content_hash,
+                    hash: String::new(),
+                    modified_at: None,
+                    file_hash: None,
+                    sync_generation: None,
                 },
             };
             
@@ -616,6 +623,73 @@ impl AstSplitter {
     }
 }
 
+/// Version of the chunk ID scheme; stores written with an older one are
+/// migrated before the next incremental update
+///
+/// 1. sha256 of absolute path and line range
+/// 2. sha256 of relative path, content hash and ordinal
+pub const CHUNK_ID_VERSION: u32 = 2;
+
+/// Hex sha256 of a chunk's content
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// Chunk ID from the file, the content hash and the ordinal among chunks of
+/// that file with the same content
+///
+/// Line numbers are deliberately left out so that editing one part of a file
+/// keeps the IDs (and embeddings) of every chunk whose content is unchanged.
+pub fn chunk_id(relative_path: &str, content_hash: &str, ordinal: usize) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(relative_path.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(content_hash.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(ordinal.to_string().as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Set content hashes and IDs on one file's chunks, in file order
+pub fn assign_chunk_ids(chunks: &mut [CodeChunk]) {
+    let mut ordinals: HashMap<String, usize> = HashMap::new();
+    
+    for chunk in chunks.iter_mut() {
+        let hash = content_hash(&chunk.content);
+        let ordinal = ordinals.entry(hash.clone()).or_insert(0);
+        chunk.id = chunk_id(&chunk.relative_path, &hash, *ordinal);
+        chunk.metadata.hash = hash;
+        *ordinal += 1;
+    }
+}
+
+/// Bytes and nodes of a parsed file that the grammar could not parse
+fn parse_quality(root: Node, total_bytes: usize) -> ParseQuality {
+    let mut quality = ParseQuality { total_bytes, ..ParseQuality::default() };
+    if !root.has_error() {
+        return quality;
+    }
+    
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if node.is_error() {
+            // Nested errors are already inside this range
+            quality.error_bytes += node.end_byte() - node.start_byte();
+            quality.error_nodes += 1;
+            continue;
+        }
+        if node.is_missing() {
+            quality.error_nodes += 1;
+        }
+        if node.has_error() {
+            let mut cursor = node.walk();
+            stack.extend(node.children(&mut cursor));
+        }
+    }
+    
+    quality
+}
+
 pub fn split_code(
     content: &str,
     language: Language,
@@ -626,3 +700,61 @@ pub fn split_code(
     let splitter = AstSplitter::new(chunk_size, overlap);
     splitter.chunk_code(content, language.as_str(), file_path, "")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "fn first() -> u32 {\n    1\n}\n\nfn second() -> u32 {\n    2\n}\n";
+
+    fn chunk(content: &str) -> Vec<CodeChunk> {
+        AstSplitter::new(1000, 0)
+            .chunk_code(content, "rust", Path::new("/repo/src/lib.rs"), "src/lib.rs")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_chunk_ids_survive_line_shift() {
+        let before = chunk(SOURCE);
+        let after = chunk(&format!("// header\n{SOURCE}"));
+
+        let before_ids: HashSet<_> = before.iter().map(|c| c.id.clone()).collect();
+        let second_after = after.iter().find(|c| c.content.contains("second")).unwrap();
+        let second_before = before.iter().find(|c| c.content.contains("second")).unwrap();
+
+        assert_eq!(second_after.id, second_before.id);
+        assert_eq!(second_after.start_line, second_before.start_line + 1);
+        assert!(before_ids.contains(&second_after.id));
+    }
+
+    #[test]
+    fn test_duplicate_content_gets_distinct_ids() {
+        let mut chunks = chunk(SOURCE);
+        let mut duplicate = chunks[0].clone();
+        duplicate.start_line += 100;
+        chunks.push(duplicate);
+        assign_chunk_ids(&mut chunks);
+
+        let ids: HashSet<_> = chunks.iter().map(|c| c.id.clone()).collect();
+        assert_eq!(ids.len(), chunks.len());
+        assert_eq!(chunks[0].metadata.hash, content_hash(&chunks[0].content));
+    }
+
+    #[test]
+    fn test_parse_quality() {
+        let splitter = AstSplitter::new(1000, 0);
+        let path = Path::new("/repo/src/lib.rs");
+
+        let (_, _, clean) = splitter.chunk_code_with_origin(SOURCE, "rust", path, "src/lib.rs").unwrap();
+        assert_eq!(clean.unwrap().error_nodes, 0);
+
+        let broken = format!("{SOURCE}\nfn broken( {{ let = ; }} ]] @@ ?? !!\n");
+        let (_, _, quality) = splitter.chunk_code_with_origin(&broken, "rust", path, "src/lib.rs").unwrap();
+        let quality = quality.unwrap();
+        assert!(quality.error_nodes > 0);
+        assert!(quality.error_bytes > 0 && quality.error_bytes <= broken.len());
+
+        let (_, _, unknown) = splitter.chunk_code_with_origin("text", "unknown", path, "notes.txt").unwrap();
+        assert!(unknown.is_none());
+    }
+}
@@ -0,0 +1,145 @@
+//! Branch namespaces of codebase indexes
+//!
+//! A codebase can be indexed once per branch, e.g. a feature branch next to
+//! main, under the same data directory: the stores, snapshot and sync state
+//! of branch `name` live in `data_dir/branches/<dir>/`, laid out as the data
+//! directory itself, while the embedding cache stays shared so chunks common
+//! to both branches are embedded once. Each namespace is a complete index of
+//! its own, so every indexed branch takes about the disk space of the
+//! default index.
+
+use crate::{Error, Result};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// `name` trimmed, when it can be a git branch name
+pub fn validate_branch_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    let valid = !name.is_empty()
+        && !name.starts_with(['-', '/', '.'])
+        && !name.ends_with(['/', '.'])
+        && !name.contains("..")
+        && !name.contains("//")
+        && !name.ends_with(".lock")
+        && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | '+' | '@'));
+    if valid {
+        Ok(name)
+    } else {
+        Err(Error::Config(format!(
+            "Invalid branch name '{name}': use the name of a git branch, such as 'main' or 'feature/login'"
+        )))
+    }
+}
+
+/// File in a branch's data directory holding the branch name, which the
+/// directory name does not give back
+const BRANCH_NAME_FILE: &str = "branch_name";
+
+/// Data directory of the indexes of `branch`
+pub fn branch_data_dir(data_dir: &Path, branch: &str) -> PathBuf {
+    // Readable, with a hash so 'a/b' and 'a-b' do not share a directory
+    let readable: String = branch
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '-' })
+        .collect();
+    let hash = format!("{:x}", Sha256::digest(branch.as_bytes()));
+    data_dir.join("branches").join(format!("{readable}-{}", &hash[..8]))
+}
+
+/// Create the data directory of `branch`, recording its name for `known_branches`
+pub fn create_branch_data_dir(data_dir: &Path, branch: &str) -> Result<PathBuf> {
+    let dir = branch_data_dir(data_dir, branch);
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join(BRANCH_NAME_FILE), branch)?;
+    Ok(dir)
+}
+
+/// Branches with a data directory under `data_dir`
+pub fn known_branches(data_dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(data_dir.join("branches")) else {
+        return Vec::new();
+    };
+    let mut branches: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| std::fs::read_to_string(entry.path().join(BRANCH_NAME_FILE)).ok())
+        .filter(|branch| validate_branch_name(branch).is_ok())
+        .collect();
+    branches.sort();
+    branches
+}
+
+/// Branch checked out at `root`; `None` when it is not a git checkout or
+/// HEAD is detached
+pub fn checked_out_branch(root: &Path) -> Option<String> {
+    let dot_git = root.join(".git");
+    // A worktree's `.git` is a file pointing at its git directory
+    let git_dir = if dot_git.is_file() {
+        let content = std::fs::read_to_string(&dot_git).ok()?;
+        let target = PathBuf::from(content.trim().strip_prefix("gitdir:")?.trim());
+        if target.is_absolute() { target } else { root.join(target) }
+    } else {
+        dot_git
+    };
+    let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    head.trim().strip_prefix("ref: refs/heads/").map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_branch_names_are_validated() {
+        assert_eq!(validate_branch_name(" feature/login ").unwrap(), "feature/login");
+        assert!(validate_branch_name("release-1.2").is_ok());
+        for invalid in ["", "-rf", "../main", "a..b", "a b", "main.lock", "feature/"] {
+            assert!(validate_branch_name(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_similar_branches_get_their_own_directory() {
+        let data_dir = Path::new("/data");
+        let nested = branch_data_dir(data_dir, "a/b");
+        assert!(nested.starts_with("/data/branches"));
+        assert!(nested.file_name().unwrap().to_string_lossy().starts_with("a-b-"));
+        assert_ne!(nested, branch_data_dir(data_dir, "a-b"));
+        assert_eq!(nested, branch_data_dir(data_dir, "a/b"));
+    }
+
+    #[test]
+    fn test_branch_directories_are_listed_by_name() {
+        let dir = tempdir().unwrap();
+        assert!(known_branches(dir.path()).is_empty());
+
+        let login = create_branch_data_dir(dir.path(), "feature/login").unwrap();
+        assert_eq!(login, branch_data_dir(dir.path(), "feature/login"));
+        create_branch_data_dir(dir.path(), "main").unwrap();
+        // A directory without a recorded name is skipped
+        std::fs::create_dir_all(dir.path().join("branches/stray")).unwrap();
+        assert_eq!(known_branches(dir.path()), vec!["feature/login", "main"]);
+    }
+
+    #[test]
+    fn test_checked_out_branch_is_read_from_head() {
+        let dir = tempdir().unwrap();
+        assert_eq!(checked_out_branch(dir.path()), None);
+
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        std::fs::write(dir.path().join(".git/HEAD"), "ref: refs/heads/feature/login\n").unwrap();
+        assert_eq!(checked_out_branch(dir.path()).as_deref(), Some("feature/login"));
+
+        // A worktree points at its own git directory
+        let worktree = dir.path().join("worktree");
+        let git_dir = dir.path().join(".git/worktrees/main");
+        std::fs::create_dir_all(&git_dir).unwrap();
+        std::fs::create_dir(&worktree).unwrap();
+        std::fs::write(worktree.join(".git"), format!("gitdir: {}\n", git_dir.display())).unwrap();
+        std::fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        assert_eq!(checked_out_branch(&worktree).as_deref(), Some("main"));
+
+        std::fs::write(git_dir.join("HEAD"), "0123456789abcdef0123456789abcdef01234567\n").unwrap();
+        assert_eq!(checked_out_branch(&worktree), None);
+    }
+}
@@ -1,4 +1,5 @@
-use crate::Result;
+use crate::{Error, Result};
+use crate::embeddings::presets::{self, ModelPreset, PRESET_PREFIX};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -23,13 +24,215 @@ pub struct EmbeddingConfig {
     pub api_key: Option<String>,
     pub model: String,
     pub base_url: Option<String>,
+    /// Azure OpenAI deployment name; defaults to `model`
+    #[serde(default)]
+    pub azure_deployment: Option<String>,
+    /// Azure OpenAI REST API version
+    #[serde(default)]
+    pub azure_api_version: Option<String>,
+    /// AWS region of the Bedrock runtime endpoint
+    #[serde(default)]
+    pub aws_region: Option<String>,
+    /// Where local models are downloaded; defaults to `<data_dir>/models`
+    #[serde(default)]
+    pub model_cache_dir: Option<PathBuf>,
+    /// Longest input the model accepts, in tokens; set for OpenAI-compatible servers
+    #[serde(default)]
+    pub max_input_tokens: Option<usize>,
+    /// Shortened output size for models that support it (OpenAI text-embedding-3)
+    #[serde(default)]
+    pub dimensions: Option<usize>,
+    /// Scale embeddings to unit length before they are stored or searched
+    #[serde(default)]
+    pub normalize: bool,
+    /// Pull a missing Ollama model at startup instead of failing
+    #[serde(default)]
+    pub ollama_auto_pull: bool,
+    /// How long Ollama keeps the model loaded after a request ("30m", or seconds)
+    #[serde(default)]
+    pub ollama_keep_alive: Option<String>,
+    /// Timeouts and connection pooling of the providers' HTTP clients
+    #[serde(default)]
+    pub http: HttpConfig,
+    /// USD per million tokens for `estimate_index`; defaults to the model's list price
+    #[serde(default)]
+    pub price_per_million_tokens: Option<f64>,
+    /// Text put before search queries, replacing the prefix detected from the
+    /// model name; empty disables it
+    #[serde(default)]
+    pub query_prefix: Option<String>,
+    /// Text put before indexed chunks, replacing the detected prefix
+    #[serde(default)]
+    pub document_prefix: Option<String>,
+    /// Providers tried in order when this one fails to start or keeps failing
+    #[serde(default)]
+    pub fallbacks: Vec<EmbeddingConfig>,
+    /// Providers that `analyze_code` can index a codebase with instead of this one
+    #[serde(default)]
+    pub extra_providers: Vec<EmbeddingConfig>,
+    /// Model preset the settings came from (`EMBEDDING_MODEL=preset:<name>`)
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// Text model embedding documentation chunks (Markdown, reStructuredText,
+    /// plain text) into their own vector index, while this one embeds source
+    #[serde(default)]
+    pub docs_provider: Option<Box<EmbeddingConfig>>,
+    /// Keys rotated across when one hits its rate limit (OpenAI-family providers)
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+    /// Shell command printing an API token, run again when the token expires
+    #[serde(default)]
+    pub api_key_command: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl EmbeddingConfig {
+    /// Resolve a `preset:<name>` model into the preset's settings; those
+    /// configured explicitly are kept. With `choose_provider` the preset also
+    /// picks the provider.
+    fn resolve_preset(&mut self, choose_provider: bool) -> Result<()> {
+        let Some(name) = self.model.strip_prefix(PRESET_PREFIX) else {
+            return Ok(());
+        };
+        let preset = presets::find(name).ok_or_else(|| {
+            Error::Config(format!("Unknown model preset '{}'; available: {}", name, presets::names().join(", ")))
+        })?;
+        self.apply_preset(preset, choose_provider);
+        Ok(())
+    }
+
+    fn apply_preset(&mut self, preset: &ModelPreset, choose_provider: bool) {
+        if choose_provider && self.provider != preset.provider {
+            self.provider = preset.provider.clone();
+            self.api_key = std::env::var(self.provider.api_key_var()).ok();
+        }
+        if self.api_key.is_none() {
+            self.api_key = preset.api_key_var.and_then(|var| std::env::var(var).ok());
+        }
+        // The preset's endpoint only makes sense for the provider it was written for
+        if self.provider == preset.provider && self.base_url.is_none() {
+            self.base_url = preset.base_url.map(str::to_string);
+        }
+        self.model = preset.model.to_string();
+        self.max_input_tokens.get_or_insert(preset.max_input_tokens);
+        self.query_prefix.get_or_insert_with(|| preset.query_prefix.to_string());
+        self.document_prefix.get_or_insert_with(|| preset.document_prefix.to_string());
+        if self.price_per_million_tokens.is_none() {
+            self.price_per_million_tokens = preset.price_per_million_tokens;
+        }
+        self.preset = Some(preset.name.to_string());
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum EmbeddingProvider {
     OpenAI,
     Ollama,
+    Cohere,
+    Gemini,
+    #[serde(rename = "azure")]
+    AzureOpenAI,
+    Bedrock,
+    /// In-process ONNX models; requires the `local` feature
+    Local,
+    /// Self-hosted servers implementing `/v1/embeddings` (LM Studio, vLLM, llama.cpp)
+    #[serde(rename = "openai-compatible")]
+    OpenAICompatible,
+    /// Deterministic hash-based vectors for tests; requires the `mock` feature
+    Mock,
+}
+
+impl EmbeddingProvider {
+    /// Provider for a name as accepted by `EMBEDDING_PROVIDER`
+    pub fn from_name(name: &str) -> Option<Self> {
+        let provider = match name.to_lowercase().as_str() {
+            "openai" => EmbeddingProvider::OpenAI,
+            "ollama" => EmbeddingProvider::Ollama,
+            "cohere" => EmbeddingProvider::Cohere,
+            "gemini" | "google" => EmbeddingProvider::Gemini,
+            "azure" | "azure_openai" | "azure-openai" => EmbeddingProvider::AzureOpenAI,
+            "bedrock" | "aws" => EmbeddingProvider::Bedrock,
+            "local" | "fastembed" | "onnx" => EmbeddingProvider::Local,
+            "openai-compatible" | "openai_compatible" | "compatible" | "lmstudio" | "lm-studio"
+            | "vllm" | "llamacpp" | "llama.cpp" => EmbeddingProvider::OpenAICompatible,
+            "mock" => EmbeddingProvider::Mock,
+            _ => return None,
+        };
+        Some(provider)
+    }
+    
+    fn api_key_var(&self) -> &'static str {
+        match self {
+            EmbeddingProvider::Cohere => "COHERE_API_KEY",
+            EmbeddingProvider::Gemini => "GEMINI_API_KEY",
+            EmbeddingProvider::AzureOpenAI => "AZURE_OPENAI_API_KEY",
+            EmbeddingProvider::OpenAICompatible => "EMBEDDING_API_KEY",
+            _ => "OPENAI_API_KEY",
+        }
+    }
+    
+    /// Model used when none is configured
+    fn default_model(&self) -> Option<&'static str> {
+        match self {
+            EmbeddingProvider::Cohere => Some("embed-english-v3.0"),
+            EmbeddingProvider::Gemini => Some("text-embedding-004"),
+            EmbeddingProvider::Bedrock => Some("amazon.titan-embed-text-v2:0"),
+            EmbeddingProvider::Local => Some("bge-small"),
+            EmbeddingProvider::Mock => Some("mock-hash"),
+            _ => None,
+        }
+    }
+}
+
+/// Provider listed in `var` as `provider[:model][@base_url]`, e.g.
+/// `ollama:nomic-embed-text@http://localhost:11434`; the key comes from the
+/// provider's usual variable and the remaining settings from `primary`
+fn parse_provider_spec(spec: &str, var: &str, primary: &EmbeddingConfig) -> Result<EmbeddingConfig> {
+    let (head, base_url) = match spec.split_once('@') {
+        Some((head, base_url)) => (head, Some(base_url.trim().to_string())),
+        None => (spec, None),
+    };
+    let (name, model) = match head.split_once(':') {
+        Some((name, model)) => (name.trim(), Some(model.trim().to_string())),
+        None => (head.trim(), None),
+    };
+    
+    // `preset:<name>` lets the preset pick the provider too
+    let choose_provider = name.eq_ignore_ascii_case("preset");
+    let (provider, model) = if choose_provider {
+        let preset = model.as_deref().and_then(presets::find).ok_or_else(|| {
+            Error::Config(format!("{var} entry '{spec}' needs a known preset: {}", presets::names().join(", ")))
+        })?;
+        (preset.provider.clone(), format!("{PRESET_PREFIX}{}", preset.name))
+    } else {
+        let provider = EmbeddingProvider::from_name(name)
+            .ok_or_else(|| Error::Config(format!("Unknown provider '{name}' in {var}")))?;
+        let model = model
+            .filter(|model| !model.is_empty())
+            .or_else(|| provider.default_model().map(str::to_string))
+            .ok_or_else(|| Error::Config(format!("{var} entry '{spec}' needs a model, as in {name}:<model>")))?;
+        (provider, model)
+    };
+    
+    let mut embedding = EmbeddingConfig {
+        api_key: std::env::var(provider.api_key_var()).ok(),
+        provider,
+        model,
+        base_url,
+        dimensions: None,
+        price_per_million_tokens: None,
+        query_prefix: None,
+        document_prefix: None,
+        fallbacks: Vec::new(),
+        extra_providers: Vec::new(),
+        preset: None,
+        docs_provider: None,
+        api_keys: Vec::new(),
+        api_key_command: None,
+        ..primary.clone()
+    };
+    embedding.resolve_preset(false)?;
+    Ok(embedding)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,22 +241,552 @@ pub struct StorageConfig {
     pub vectors_dir: PathBuf,
     pub fulltext_dir: PathBuf,
     pub metadata_db: PathBuf,
+    /// Reuse embeddings of identical chunks across codebases
+    #[serde(default = "default_embedding_cache_enabled")]
+    pub embedding_cache_enabled: bool,
+    /// Size of the shared embedding cache before least recently used entries are evicted
+    #[serde(default = "default_embedding_cache_max_mb")]
+    pub embedding_cache_max_mb: u64,
+    /// How long deleted indexes stay restorable; 0 deletes them immediately
+    #[serde(default = "default_trash_retention_hours")]
+    pub trash_retention_hours: u64,
+    /// Precision of vectors in newly created vector indexes
+    #[serde(default)]
+    pub vector_quantization: VectorQuantization,
+    /// Distance newly created vector indexes compare vectors by
+    #[serde(default)]
+    pub vector_metric: VectorMetric,
+    /// Store holding the vectors
+    #[serde(default)]
+    pub vector_backend: VectorBackend,
+    /// Graph settings of USearch indexes
+    #[serde(default)]
+    pub hnsw: HnswConfig,
+    /// Serve searches from memory-mapped USearch indexes instead of reading them into RAM
+    #[serde(default)]
+    pub vector_mmap: bool,
+    /// USearch index files new vectors of a codebase are spread over
+    #[serde(default = "default_vector_shards")]
+    pub vector_shards: usize,
+    /// Seconds between background removals of deleted USearch vectors; 0
+    /// leaves them until they outgrow the index's compaction share
+    #[serde(default = "default_vacuum_interval_secs")]
+    pub vacuum_interval_secs: u64,
+}
+
+impl StorageConfig {
+    pub fn embedding_cache_dir(&self) -> PathBuf {
+        self.data_dir.join("embedding_cache")
+    }
+    
+    pub fn trash_retention(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.trash_retention_hours.saturating_mul(3600))
+    }
+}
+
+fn default_embedding_cache_enabled() -> bool {
+    true
+}
+
+fn default_embedding_cache_max_mb() -> u64 {
+    1024
+}
+
+fn default_trash_retention_hours() -> u64 {
+    168
+}
+
+fn default_vector_shards() -> usize {
+    1
+}
+
+fn default_vacuum_interval_secs() -> u64 {
+    300
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchConfig {
     pub default_top_k: usize,
+    /// Lowest relevance (0..=1) a search result may have: its cosine
+    /// similarity, or its keyword score as a share of the best keyword match
     pub min_score: f32,
     pub rrf_k: usize, // RRF parameter for hybrid search
+    pub analyzer: AnalyzerConfig,
+    /// Total characters of code snippets in one find_code response
+    pub response_char_budget: usize,
+    /// Most results one find_code call returns, whatever `limit` asks for
+    #[serde(default = "default_max_results")]
+    pub max_results: usize,
+    /// Candidates fetched from each retriever (vector, BM25, identifiers) before fusion
+    #[serde(default = "default_retrieval_depth")]
+    pub retrieval_depth: usize,
+    /// Deepest retrieval when filters discard candidates, and the most a request may ask for
+    #[serde(default = "default_max_retrieval_depth")]
+    pub max_retrieval_depth: usize,
+    /// Longest snippet a single result may show
+    #[serde(default = "default_max_snippet_chars")]
+    pub max_snippet_chars: usize,
+    /// Merge results from the same file whose line ranges touch into one snippet
+    #[serde(default = "default_stitch_adjacent")]
+    pub stitch_adjacent: bool,
+    /// Weight of the BM25 ranking in fusion when the query is mostly code
+    #[serde(default = "default_code_query_bm25_weight")]
+    pub code_query_bm25_weight: f32,
+    /// Multiplier of the vector ranking's RRF scores in fusion
+    #[serde(default = "default_leg_weight")]
+    pub vector_weight: f32,
+    /// Multiplier of the BM25 ranking's RRF scores in fusion
+    #[serde(default = "default_leg_weight")]
+    pub bm25_weight: f32,
+    /// Extra score share of a result from a file modified just now, halving
+    /// every `recency_half_life_days`; 0 ranks regardless of age
+    #[serde(default)]
+    pub recency_boost: f32,
+    #[serde(default = "default_recency_half_life_days")]
+    pub recency_half_life_days: f32,
+    /// Extra score share of a result defining a name the query spells out
+    /// exactly; half of it when the result only uses the name
+    #[serde(default = "default_exact_match_boost")]
+    pub exact_match_boost: f32,
+    /// Candidates fetched per wanted result from a quantized vector index,
+    /// re-scored with the full-precision vectors
+    #[serde(default = "default_rerank_oversample")]
+    pub rerank_oversample: usize,
+    /// Chat model re-scoring the top fused candidates against the query;
+    /// `None` keeps the fused order
+    #[serde(default)]
+    pub llm_rerank: Option<LlmRerankConfig>,
+}
+
+/// OpenAI-compatible chat model that reranks search results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmRerankConfig {
+    /// Base URL of the API, up to `/chat/completions`
+    pub base_url: String,
+    pub model: String,
+    #[serde(skip_serializing)]
+    pub api_key: Option<String>,
+    /// Top fused candidates the model scores
+    #[serde(default = "default_llm_rerank_candidates")]
+    pub candidates: usize,
+}
+
+/// Default `RERANK_LLM_BASE_URL`
+const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+
+fn default_llm_rerank_candidates() -> usize {
+    30
+}
+
+fn default_rerank_oversample() -> usize {
+    4
+}
+
+fn default_exact_match_boost() -> f32 {
+    1.0
+}
+
+fn default_recency_half_life_days() -> f32 {
+    30.0
+}
+
+fn default_leg_weight() -> f32 {
+    1.0
+}
+
+fn default_code_query_bm25_weight() -> f32 {
+    2.0
+}
+
+fn default_stitch_adjacent() -> bool {
+    true
+}
+
+fn default_max_results() -> usize {
+    50
+}
+
+fn default_retrieval_depth() -> usize {
+    50
+}
+
+fn default_max_retrieval_depth() -> usize {
+    1000
+}
+
+fn default_max_snippet_chars() -> usize {
+    5000
+}
+
+/// Version of the BM25 analysis; indexes built with an older one are rebuilt
+///
+/// 1: stopwords are only dropped from prose queries, every word is indexed
+pub const ANALYZER_VERSION: u32 = 1;
+
+/// Tantivy analyzer options for the BM25 content field
+///
+/// Applied when a BM25 index is created; changing them requires a forced re-index.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AnalyzerConfig {
+    /// How the index applies these options, `ANALYZER_VERSION` for new
+    /// indexes; 0 in indexes built before it was recorded, which removed
+    /// the stopwords from the index itself
+    #[serde(default)]
+    pub version: u32,
+    /// Default tokenizer; `analyze_code` can choose another one per codebase
+    #[serde(default)]
+    pub tokenizer: TokenizerKind,
+    pub stemming: bool,
+    pub stemmer_language: String,
+    pub stopwords: Vec<String>,
+    /// Also applied to text sent to the embedding provider
+    #[serde(default = "NormalizationConfig::disabled")]
+    pub normalization: NormalizationConfig,
+}
+
+impl Default for AnalyzerConfig {
+    fn default() -> Self {
+        Self {
+            version: ANALYZER_VERSION,
+            tokenizer: TokenizerKind::Simple,
+            stemming: false,
+            stemmer_language: "english".to_string(),
+            stopwords: vec![],
+            normalization: NormalizationConfig::default(),
+        }
+    }
+}
+
+/// Unicode normalization applied to content and queries before tokenizing and embedding
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NormalizationConfig {
+    /// Canonical composition (NFC), so precomposed and combining forms match
+    pub nfc: bool,
+    /// Fold fullwidth ASCII variants and the ideographic space to ASCII
+    pub fold_width: bool,
+    /// Strip combining marks, so "café" also matches "cafe"
+    pub fold_diacritics: bool,
+}
+
+impl Default for NormalizationConfig {
+    fn default() -> Self {
+        Self {
+            nfc: true,
+            fold_width: true,
+            fold_diacritics: false,
+        }
+    }
+}
+
+impl NormalizationConfig {
+    /// Behaviour of indexes built before normalization existed
+    pub fn disabled() -> Self {
+        Self {
+            nfc: false,
+            fold_width: false,
+            fold_diacritics: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenizerKind {
+    /// Split on non-alphanumeric characters
+    #[default]
+    Simple,
+    /// Like `Simple`, plus bigrams for Chinese/Japanese/Korean text
+    Cjk,
+}
+
+impl TokenizerKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenizerKind::Simple => "simple",
+            TokenizerKind::Cjk => "cjk",
+        }
+    }
+}
+
+impl std::str::FromStr for TokenizerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "simple" | "default" => Ok(TokenizerKind::Simple),
+            "cjk" => Ok(TokenizerKind::Cjk),
+            other => Err(format!("Unknown tokenizer '{other}'. Must be 'simple' or 'cjk'.")),
+        }
+    }
+}
+
+/// Precision of the vectors stored in the usearch index
+///
+/// Quantized indexes keep the full-precision vectors in the metadata store to
+/// re-score their candidates. Applied when a vector index is created.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum VectorQuantization {
+    /// 32-bit floats
+    #[default]
+    None,
+    /// One signed byte per dimension, a quarter of the size
+    Int8,
+    /// One bit per dimension (its sign), compared by Hamming distance; 1/32 of the size
+    Binary,
+}
+
+impl VectorQuantization {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VectorQuantization::None => "none",
+            VectorQuantization::Int8 => "int8",
+            VectorQuantization::Binary => "binary",
+        }
+    }
+}
+
+impl std::str::FromStr for VectorQuantization {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" | "f32" | "" => Ok(VectorQuantization::None),
+            "int8" | "i8" => Ok(VectorQuantization::Int8),
+            "binary" | "b1" => Ok(VectorQuantization::Binary),
+            other => Err(format!("Unknown vector quantization '{other}'. Must be 'none', 'int8' or 'binary'.")),
+        }
+    }
+}
+
+/// Distance the usearch index compares vectors by
+///
+/// Applied when a usearch index is created; binary indexes always compare by
+/// Hamming distance. Cosine and L2 scores are mapped onto the cosine range, so
+/// they read the same for unit-length vectors; Dot scores are the raw inner
+/// product, which is unbounded for vectors of other lengths.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum VectorMetric {
+    /// Cosine similarity, ignoring vector length
+    #[default]
+    Cosine,
+    /// Inner product, for models whose vector length carries meaning
+    Dot,
+    /// Squared Euclidean distance
+    L2,
+}
+
+impl VectorMetric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VectorMetric::Cosine => "cosine",
+            VectorMetric::Dot => "dot",
+            VectorMetric::L2 => "l2",
+        }
+    }
+}
+
+impl std::str::FromStr for VectorMetric {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cosine" | "cos" | "" => Ok(VectorMetric::Cosine),
+            "dot" | "ip" | "inner_product" => Ok(VectorMetric::Dot),
+            "l2" | "l2sq" | "euclidean" => Ok(VectorMetric::L2),
+            other => Err(format!("Unknown vector metric '{other}'. Must be 'cosine', 'dot' or 'l2'.")),
+        }
+    }
+}
+
+/// Store of a codebase's vectors
+///
+/// LanceDB and SQLite keep each chunk's path, language and content next to
+/// its vector and filter by extension inside the store; they require the
+/// `lancedb` and `sqlite` features and search exactly, without quantization.
+/// SQLite keeps all of a codebase's vectors in one file.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum VectorBackend {
+    #[default]
+    USearch,
+    LanceDb,
+    Sqlite,
+}
+
+impl VectorBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VectorBackend::USearch => "usearch",
+            VectorBackend::LanceDb => "lancedb",
+            VectorBackend::Sqlite => "sqlite",
+        }
+    }
+}
+
+impl std::str::FromStr for VectorBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "usearch" | "" => Ok(VectorBackend::USearch),
+            "lancedb" | "lance" => Ok(VectorBackend::LanceDb),
+            "sqlite" | "sqlite-vec" => Ok(VectorBackend::Sqlite),
+            other => Err(format!("Unknown vector backend '{other}'. Must be 'usearch', 'lancedb' or 'sqlite'.")),
+        }
+    }
+}
+
+/// Graph settings of the USearch HNSW index, trading recall against build
+/// time, query latency and memory. `connectivity` is fixed when an index is
+/// created; the expansion factors and the exact search threshold also apply
+/// to indexes opened from disk.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HnswConfig {
+    /// Neighbors each node links to; more raise recall and memory use
+    pub connectivity: usize,
+    /// Candidates considered while inserting; more build a better graph, slower
+    pub expansion_add: usize,
+    /// Candidates considered while searching; more raise recall and latency
+    pub expansion_search: usize,
+    /// Indexes holding at most this many vectors keep them in a flat store
+    /// and are searched by comparing the query with every vector; the graph
+    /// is only built once an index grows past it. 0 always builds the graph.
+    #[serde(default = "default_exact_search_max_chunks")]
+    pub exact_search_max_chunks: usize,
+}
+
+impl Default for HnswConfig {
+    fn default() -> Self {
+        Self {
+            connectivity: 16,
+            expansion_add: 128,
+            expansion_search: 64,
+            exact_search_max_chunks: default_exact_search_max_chunks(),
+        }
+    }
+}
+
+fn default_exact_search_max_chunks() -> usize {
+    20_000
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexingConfig {
     pub chunk_size: usize,
     pub chunk_overlap: usize,
-    pub batch_size: usize,
+    /// Most chunks sent in one embedding request; defaults to the provider's limit
+    #[serde(default)]
+    pub batch_size: Option<usize>,
+    /// Token budget per embedding request; defaults to the provider's limit
+    #[serde(default)]
+    pub batch_tokens: Option<usize>,
+    /// Embedding batches one indexing job keeps in flight at once
+    #[serde(default = "default_parallel_batches")]
+    pub parallel_batches: usize,
+    /// Embedding requests in flight at once, across all codebases being indexed
+    #[serde(default = "default_embedding_concurrency")]
+    pub embedding_concurrency: usize,
+    /// Provider request rate shared by all indexing jobs; unlimited when unset
+    #[serde(default)]
+    pub requests_per_minute: Option<u64>,
+    /// Provider token rate shared by all indexing jobs; unlimited when unset
+    #[serde(default)]
+    pub tokens_per_minute: Option<u64>,
+    /// Age after which an unfinished indexing run found at startup is taken as interrupted
+    #[serde(default = "default_stale_indexing_secs")]
+    pub stale_indexing_secs: u64,
+    /// Throttle indexing so the rest of the machine stays responsive
+    #[serde(default)]
+    pub low_priority: bool,
+    /// Share of wall time a low-priority job spends working, between 0.05 and 1
+    #[serde(default = "default_cpu_share")]
+    pub cpu_share: f64,
+    /// Extra sleep after each embedding or storage batch of a low-priority job
+    #[serde(default = "default_batch_pause_ms")]
+    pub batch_pause_ms: u64,
     pub supported_extensions: Vec<String>,
     pub ignore_patterns: Vec<String>,
+    #[serde(default)]
+    pub retry: RetryConfig,
+}
+
+fn default_parallel_batches() -> usize {
+    4
+}
+
+fn default_embedding_concurrency() -> usize {
+    4
+}
+
+fn default_stale_indexing_secs() -> u64 {
+    120
+}
+
+fn default_cpu_share() -> f64 {
+    0.25
+}
+
+fn default_batch_pause_ms() -> u64 {
+    200
+}
+
+/// HTTP client settings of the remote embedding providers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpConfig {
+    pub connect_timeout_secs: u64,
+    /// Limit on a whole request, including reading the response; 0 disables it
+    pub request_timeout_secs: u64,
+    /// Idle connections kept open per host
+    pub pool_max_idle_per_host: usize,
+    pub pool_idle_timeout_secs: u64,
+    /// Speak HTTP/2 without negotiating it, for servers known to support it
+    pub http2_prior_knowledge: bool,
+    /// Interval of HTTP/2 keep-alive pings; 0 disables them
+    pub http2_keep_alive_secs: u64,
+    /// Proxy URL for every request, overriding HTTP_PROXY/HTTPS_PROXY;
+    /// hosts in NO_PROXY still connect directly
+    pub proxy: Option<String>,
+    /// PEM file of extra trusted root certificates, e.g. a corporate
+    /// TLS-inspection CA
+    pub ca_cert: Option<PathBuf>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 10,
+            // Large batches on local servers such as Ollama take minutes
+            request_timeout_secs: 600,
+            pool_max_idle_per_host: 16,
+            pool_idle_timeout_secs: 90,
+            http2_prior_knowledge: false,
+            http2_keep_alive_secs: 0,
+            proxy: None,
+            ca_cert: None,
+        }
+    }
+}
+
+/// Retry policy for vector and full-text storage writes during indexing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryConfig {
+    /// Total attempts per batch, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubles on every further attempt
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff_ms: 250,
+            max_backoff_ms: 5_000,
+        }
+    }
 }
 
 impl Default for Config {
@@ -64,24 +797,78 @@ impl Default for Config {
                 api_key: None,
                 model: "text-embedding-3-small".to_string(),
                 base_url: None,
+                azure_deployment: None,
+                azure_api_version: None,
+                aws_region: None,
+                model_cache_dir: None,
+                max_input_tokens: None,
+                dimensions: None,
+                normalize: false,
+                ollama_auto_pull: false,
+                ollama_keep_alive: None,
+                http: HttpConfig::default(),
+                price_per_million_tokens: None,
+                query_prefix: None,
+                document_prefix: None,
+                fallbacks: Vec::new(),
+                extra_providers: Vec::new(),
+                preset: None,
+                docs_provider: None,
+                api_keys: Vec::new(),
+                api_key_command: None,
             },
             storage: StorageConfig {
                 data_dir: PathBuf::from("./data"),
                 vectors_dir: PathBuf::from("./data/vectors"),
                 fulltext_dir: PathBuf::from("./data/fulltext"),
                 metadata_db: PathBuf::from("./data/metadata.db"),
+                embedding_cache_enabled: default_embedding_cache_enabled(),
+                embedding_cache_max_mb: default_embedding_cache_max_mb(),
+                trash_retention_hours: default_trash_retention_hours(),
+                vector_quantization: VectorQuantization::default(),
+                vector_metric: VectorMetric::default(),
+                vector_backend: VectorBackend::default(),
+                hnsw: HnswConfig::default(),
+                vector_mmap: false,
+                vector_shards: default_vector_shards(),
+                vacuum_interval_secs: default_vacuum_interval_secs(),
             },
             search: SearchConfig {
                 default_top_k: 10,
-                min_score: 0.3,
+                min_score: 0.2,
                 rrf_k: 100,
+                analyzer: AnalyzerConfig::default(),
+                response_char_budget: 40_000,
+                max_results: default_max_results(),
+                retrieval_depth: default_retrieval_depth(),
+                max_retrieval_depth: default_max_retrieval_depth(),
+                max_snippet_chars: default_max_snippet_chars(),
+                stitch_adjacent: default_stitch_adjacent(),
+                code_query_bm25_weight: default_code_query_bm25_weight(),
+                vector_weight: default_leg_weight(),
+                bm25_weight: default_leg_weight(),
+                recency_boost: 0.0,
+                recency_half_life_days: default_recency_half_life_days(),
+                exact_match_boost: default_exact_match_boost(),
+                rerank_oversample: default_rerank_oversample(),
+                llm_rerank: None,
             },
             indexing: IndexingConfig {
                 chunk_size: 1000,
                 chunk_overlap: 200,
-                batch_size: 100,
+                batch_size: None,
+                batch_tokens: None,
+                parallel_batches: default_parallel_batches(),
+                embedding_concurrency: default_embedding_concurrency(),
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                stale_indexing_secs: default_stale_indexing_secs(),
+                low_priority: false,
+                cpu_share: default_cpu_share(),
+                batch_pause_ms: default_batch_pause_ms(),
                 supported_extensions: crate::types::Language::supported_extensions(),
                 ignore_patterns: vec![],
+                retry: RetryConfig::default(),
             },
         }
     }
@@ -96,25 +883,399 @@ impl Config {
         
         // Override with environment variables
         if let Ok(provider) = std::env::var("EMBEDDING_PROVIDER") {
-            config.embedding.provider = match provider.to_lowercase().as_str() {
-                "openai" => EmbeddingProvider::OpenAI,
-                "ollama" => EmbeddingProvider::Ollama,
-                _ => EmbeddingProvider::OpenAI,
-            };
+            config.embedding.provider = EmbeddingProvider::from_name(&provider).unwrap_or(EmbeddingProvider::OpenAI);
         }
         
-        if let Ok(api_key) = std::env::var("OPENAI_API_KEY") {
+        if let Ok(api_key) = std::env::var(config.embedding.provider.api_key_var()) {
             config.embedding.api_key = Some(api_key);
         }
         
+        // Several keys of the same account or organization, rotated on rate limits
+        if let Ok(api_keys) = std::env::var("EMBEDDING_API_KEYS") {
+            config.embedding.api_keys = api_keys
+                .split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        
+        if let Ok(command) = std::env::var("EMBEDDING_API_KEY_COMMAND") {
+            config.embedding.api_key_command = Some(command).filter(|command| !command.trim().is_empty());
+        }
+        
         if let Ok(model) = std::env::var("EMBEDDING_MODEL") {
             config.embedding.model = model;
+        } else if let Some(model) = config.embedding.provider.default_model() {
+            config.embedding.model = model.to_string();
         }
         
         if let Ok(base_url) = std::env::var("EMBEDDING_BASE_URL") {
             config.embedding.base_url = Some(base_url);
         }
         
+        // Azure OpenAI: https://<resource>.openai.azure.com plus a deployment name
+        if let Ok(endpoint) = std::env::var("AZURE_OPENAI_ENDPOINT") {
+            config.embedding.base_url = Some(endpoint);
+        }
+        
+        if let Ok(deployment) = std::env::var("AZURE_OPENAI_DEPLOYMENT") {
+            config.embedding.azure_deployment = Some(deployment);
+        }
+        
+        if let Ok(api_version) = std::env::var("AZURE_OPENAI_API_VERSION") {
+            config.embedding.azure_api_version = Some(api_version);
+        }
+        
+        // Bedrock credentials are read from the standard AWS variables at startup
+        if let Ok(region) = std::env::var("AWS_REGION").or_else(|_| std::env::var("AWS_DEFAULT_REGION")) {
+            config.embedding.aws_region = Some(region);
+        }
+        
+        if let Ok(cache_dir) = std::env::var("LOCAL_MODEL_CACHE_DIR") {
+            config.embedding.model_cache_dir = Some(PathBuf::from(cache_dir));
+        }
+        
+        if let Ok(max_tokens) = std::env::var("EMBEDDING_MAX_TOKENS") {
+            if let Ok(value) = max_tokens.parse::<usize>() {
+                config.embedding.max_input_tokens = Some(value.max(1));
+            }
+        }
+        
+        if let Ok(dimensions) = std::env::var("EMBEDDING_DIMENSIONS") {
+            if let Ok(value) = dimensions.parse::<usize>() {
+                config.embedding.dimensions = (value > 0).then_some(value);
+            }
+        }
+        
+        if let Ok(auto_pull) = std::env::var("OLLAMA_AUTO_PULL") {
+            config.embedding.ollama_auto_pull = matches!(auto_pull.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
+        if let Ok(keep_alive) = std::env::var("OLLAMA_KEEP_ALIVE") {
+            config.embedding.ollama_keep_alive = Some(keep_alive).filter(|value| !value.trim().is_empty());
+        }
+        
+        if let Ok(normalize) = std::env::var("EMBEDDING_NORMALIZE") {
+            config.embedding.normalize = matches!(normalize.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
+        if let Ok(price) = std::env::var("EMBEDDING_PRICE_PER_MILLION_TOKENS") {
+            if let Ok(price) = price.parse::<f64>() {
+                config.embedding.price_per_million_tokens = Some(price.max(0.0));
+            }
+        }
+        
+        // Prefixes of instruction-tuned models, for models the name detection misses
+        if let Ok(prefix) = std::env::var("EMBEDDING_QUERY_PREFIX") {
+            config.embedding.query_prefix = Some(prefix);
+        }
+        
+        if let Ok(prefix) = std::env::var("EMBEDDING_DOCUMENT_PREFIX") {
+            config.embedding.document_prefix = Some(prefix);
+        }
+        
+        config.embedding.resolve_preset(std::env::var("EMBEDDING_PROVIDER").is_err())?;
+        
+        // HTTP clients of the embedding providers
+        if let Ok(timeout) = std::env::var("EMBEDDING_CONNECT_TIMEOUT_SECS") {
+            if let Ok(timeout) = timeout.parse::<u64>() {
+                config.embedding.http.connect_timeout_secs = timeout.max(1);
+            }
+        }
+        
+        if let Ok(timeout) = std::env::var("EMBEDDING_REQUEST_TIMEOUT_SECS") {
+            if let Ok(timeout) = timeout.parse() {
+                config.embedding.http.request_timeout_secs = timeout;
+            }
+        }
+        
+        if let Ok(pool_size) = std::env::var("EMBEDDING_POOL_MAX_IDLE") {
+            if let Ok(pool_size) = pool_size.parse() {
+                config.embedding.http.pool_max_idle_per_host = pool_size;
+            }
+        }
+        
+        if let Ok(idle_timeout) = std::env::var("EMBEDDING_POOL_IDLE_TIMEOUT_SECS") {
+            if let Ok(idle_timeout) = idle_timeout.parse() {
+                config.embedding.http.pool_idle_timeout_secs = idle_timeout;
+            }
+        }
+        
+        if let Ok(http2) = std::env::var("EMBEDDING_HTTP2_PRIOR_KNOWLEDGE") {
+            config.embedding.http.http2_prior_knowledge = matches!(http2.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
+        if let Ok(interval) = std::env::var("EMBEDDING_HTTP2_KEEP_ALIVE_SECS") {
+            if let Ok(interval) = interval.parse() {
+                config.embedding.http.http2_keep_alive_secs = interval;
+            }
+        }
+        
+        if let Ok(proxy) = std::env::var("EMBEDDING_PROXY") {
+            config.embedding.http.proxy = Some(proxy).filter(|proxy| !proxy.is_empty());
+        }
+        
+        if let Ok(ca_cert) = std::env::var("EMBEDDING_CA_CERT") {
+            config.embedding.http.ca_cert = Some(PathBuf::from(ca_cert)).filter(|path| !path.as_os_str().is_empty());
+        }
+        
+        // Parsed last so fallbacks inherit the primary's remaining settings
+        if let Ok(fallbacks) = std::env::var("EMBEDDING_FALLBACKS") {
+            config.embedding.fallbacks = fallbacks
+                .split(',')
+                .filter(|spec| !spec.trim().is_empty())
+                .map(|spec| parse_provider_spec(spec, "EMBEDDING_FALLBACKS", &config.embedding))
+                .collect::<Result<_>>()?;
+        }
+        
+        if let Ok(extra_providers) = std::env::var("EMBEDDING_EXTRA_PROVIDERS") {
+            config.embedding.extra_providers = extra_providers
+                .split(',')
+                .filter(|spec| !spec.trim().is_empty())
+                .map(|spec| parse_provider_spec(spec, "EMBEDDING_EXTRA_PROVIDERS", &config.embedding))
+                .collect::<Result<_>>()?;
+        }
+        
+        if let Ok(docs_provider) = std::env::var("EMBEDDING_DOCS_PROVIDER") {
+            if !docs_provider.trim().is_empty() {
+                let docs = parse_provider_spec(&docs_provider, "EMBEDDING_DOCS_PROVIDER", &config.embedding)?;
+                config.embedding.docs_provider = Some(Box::new(docs));
+            }
+        }
+        
+        if let Ok(budget) = std::env::var("RESPONSE_CHAR_BUDGET") {
+            if let Ok(budget) = budget.parse() {
+                config.search.response_char_budget = budget;
+            }
+        }
+        
+        if let Ok(max_results) = std::env::var("SEARCH_MAX_RESULTS") {
+            if let Ok(max_results) = max_results.parse::<usize>() {
+                config.search.max_results = max_results.max(1);
+            }
+        }
+        
+        if let Ok(min_score) = std::env::var("MIN_SCORE") {
+            if let Ok(min_score) = min_score.parse::<f32>() {
+                config.search.min_score = min_score.clamp(0.0, 1.0);
+            }
+        }
+        
+        if let Ok(model) = std::env::var("RERANK_LLM_MODEL") {
+            if !model.trim().is_empty() {
+                let candidates = std::env::var("RERANK_LLM_CANDIDATES")
+                    .ok()
+                    .and_then(|candidates| candidates.parse::<usize>().ok())
+                    .unwrap_or_else(default_llm_rerank_candidates);
+                let base_url = std::env::var("RERANK_LLM_BASE_URL")
+                    .unwrap_or_else(|_| OPENAI_BASE_URL.to_string());
+                // The OpenAI key is only sent to OpenAI, never to another server
+                let api_key = std::env::var("RERANK_LLM_API_KEY").ok().or_else(|| {
+                    if base_url.trim_end_matches('/') == OPENAI_BASE_URL {
+                        std::env::var("OPENAI_API_KEY").ok()
+                    } else {
+                        None
+                    }
+                });
+                config.search.llm_rerank = Some(LlmRerankConfig {
+                    base_url,
+                    model: model.trim().to_string(),
+                    api_key,
+                    candidates: candidates.max(1),
+                });
+            }
+        }
+        
+        if let Ok(depth) = std::env::var("SEARCH_RETRIEVAL_DEPTH") {
+            if let Ok(depth) = depth.parse::<usize>() {
+                config.search.retrieval_depth = depth.max(1);
+            }
+        }
+        
+        if let Ok(depth) = std::env::var("SEARCH_MAX_RETRIEVAL_DEPTH") {
+            if let Ok(depth) = depth.parse::<usize>() {
+                config.search.max_retrieval_depth = depth.max(1);
+            }
+        }
+        
+        if let Ok(stitch) = std::env::var("SEARCH_STITCH_ADJACENT") {
+            config.search.stitch_adjacent = matches!(stitch.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
+        if let Ok(weight) = std::env::var("SEARCH_CODE_QUERY_BM25_WEIGHT") {
+            if let Ok(weight) = weight.parse::<f32>() {
+                config.search.code_query_bm25_weight = weight.max(0.0);
+            }
+        }
+        
+        for (var, weight) in [
+            ("SEARCH_VECTOR_WEIGHT", &mut config.search.vector_weight),
+            ("SEARCH_BM25_WEIGHT", &mut config.search.bm25_weight),
+        ] {
+            if let Ok(value) = std::env::var(var) {
+                if let Ok(value) = value.parse::<f32>() {
+                    if value.is_finite() {
+                        *weight = value.max(0.0);
+                    }
+                }
+            }
+        }
+        
+        if let Ok(boost) = std::env::var("SEARCH_EXACT_MATCH_BOOST") {
+            if let Ok(boost) = boost.parse::<f32>() {
+                if boost.is_finite() {
+                    config.search.exact_match_boost = boost.max(0.0);
+                }
+            }
+        }
+        
+        if let Ok(boost) = std::env::var("SEARCH_RECENCY_BOOST") {
+            if let Ok(boost) = boost.parse::<f32>() {
+                if boost.is_finite() {
+                    config.search.recency_boost = boost.max(0.0);
+                }
+            }
+        }
+        
+        if let Ok(days) = std::env::var("SEARCH_RECENCY_HALF_LIFE_DAYS") {
+            if let Ok(days) = days.parse::<f32>() {
+                if days.is_finite() && days > 0.0 {
+                    config.search.recency_half_life_days = days;
+                }
+            }
+        }
+        
+        if let Ok(max_chars) = std::env::var("MAX_SNIPPET_CHARS") {
+            if let Ok(max_chars) = max_chars.parse() {
+                config.search.max_snippet_chars = max_chars;
+            }
+        }
+        
+        // BM25 analyzer configuration
+        if let Ok(stemming) = std::env::var("BM25_STEMMING") {
+            config.search.analyzer.stemming = matches!(stemming.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
+        if let Ok(tokenizer) = std::env::var("BM25_TOKENIZER") {
+            config.search.analyzer.tokenizer = tokenizer.parse().map_err(Error::Config)?;
+        }
+        
+        if let Ok(language) = std::env::var("BM25_STEMMER_LANGUAGE") {
+            let language = language.to_lowercase();
+            crate::search::bm25::stemmer_language(&language)?;
+            config.search.analyzer.stemmer_language = language;
+        }
+        
+        if let Ok(stopwords) = std::env::var("BM25_STOPWORDS") {
+            config.search.analyzer.stopwords = stopwords
+                .split(',')
+                .map(|w| w.trim().to_lowercase())
+                .filter(|w| !w.is_empty())
+                .collect();
+        }
+        
+        // Unicode normalization
+        if let Ok(nfc) = std::env::var("NORMALIZE_NFC") {
+            config.search.analyzer.normalization.nfc = matches!(nfc.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
+        if let Ok(fold_width) = std::env::var("NORMALIZE_FOLD_WIDTH") {
+            config.search.analyzer.normalization.fold_width = matches!(fold_width.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
+        if let Ok(fold_diacritics) = std::env::var("NORMALIZE_FOLD_DIACRITICS") {
+            config.search.analyzer.normalization.fold_diacritics = matches!(fold_diacritics.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
+        // Gitignore-style patterns applied to every codebase
+        if let Ok(patterns) = std::env::var("IGNORE_PATTERNS") {
+            config.indexing.ignore_patterns = patterns
+                .split(',')
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect();
+        }
+        
+        // Embedding request sizing
+        if let Ok(batch_size) = std::env::var("BATCH_SIZE") {
+            if let Ok(batch_size) = batch_size.parse::<usize>() {
+                config.indexing.batch_size = Some(batch_size.max(1));
+            }
+        }
+        
+        if let Ok(batch_tokens) = std::env::var("EMBEDDING_BATCH_TOKENS") {
+            if let Ok(batch_tokens) = batch_tokens.parse::<usize>() {
+                config.indexing.batch_tokens = Some(batch_tokens.max(1));
+            }
+        }
+        
+        if let Ok(parallel) = std::env::var("EMBEDDING_PARALLEL_BATCHES") {
+            if let Ok(parallel) = parallel.parse::<usize>() {
+                config.indexing.parallel_batches = parallel.max(1);
+            }
+        }
+        
+        // Rate budget shared by concurrent indexing jobs; unless set, it
+        // leaves room for every batch a single job keeps in flight
+        match std::env::var("EMBEDDING_CONCURRENCY").ok().and_then(|concurrency| concurrency.parse::<usize>().ok()) {
+            Some(concurrency) => config.indexing.embedding_concurrency = concurrency.max(1),
+            None => {
+                config.indexing.embedding_concurrency =
+                    config.indexing.embedding_concurrency.max(config.indexing.parallel_batches);
+            }
+        }
+        
+        if let Ok(rpm) = std::env::var("EMBEDDING_REQUESTS_PER_MINUTE") {
+            if let Ok(rpm) = rpm.parse::<u64>() {
+                config.indexing.requests_per_minute = Some(rpm.max(1));
+            }
+        }
+        
+        if let Ok(tpm) = std::env::var("EMBEDDING_TOKENS_PER_MINUTE") {
+            if let Ok(tpm) = tpm.parse::<u64>() {
+                config.indexing.tokens_per_minute = Some(tpm.max(1));
+            }
+        }
+        
+        if let Ok(stale_secs) = std::env::var("STALE_INDEXING_SECS") {
+            if let Ok(stale_secs) = stale_secs.parse::<u64>() {
+                config.indexing.stale_indexing_secs = stale_secs;
+            }
+        }
+        
+        if let Ok(low_priority) = std::env::var("INDEX_LOW_PRIORITY") {
+            config.indexing.low_priority = matches!(low_priority.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
+        if let Ok(cpu_share) = std::env::var("INDEX_CPU_SHARE") {
+            if let Ok(cpu_share) = cpu_share.parse::<f64>() {
+                // NaN would survive the throttle's clamp and panic when scaling a pause
+                if cpu_share.is_finite() {
+                    config.indexing.cpu_share = cpu_share;
+                }
+            }
+        }
+        
+        if let Ok(pause_ms) = std::env::var("INDEX_BATCH_PAUSE_MS") {
+            if let Ok(pause_ms) = pause_ms.parse::<u64>() {
+                config.indexing.batch_pause_ms = pause_ms;
+            }
+        }
+        
+        // Indexing retry policy
+        if let Ok(attempts) = std::env::var("INDEX_RETRY_ATTEMPTS") {
+            if let Ok(attempts) = attempts.parse::<u32>() {
+                config.indexing.retry.max_attempts = attempts.max(1);
+            }
+        }
+        
+        if let Ok(backoff) = std::env::var("INDEX_RETRY_BACKOFF_MS") {
+            if let Ok(backoff) = backoff.parse() {
+                config.indexing.retry.initial_backoff_ms = backoff;
+            }
+        }
+        
         // Storage configuration
         if let Ok(data_dir) = std::env::var("DATA_DIR") {
             let data_path = PathBuf::from(data_dir);
@@ -124,6 +1285,88 @@ impl Config {
             config.storage.metadata_db = data_path.join("metadata.db");
         }
         
+        if let Ok(enabled) = std::env::var("EMBEDDING_CACHE") {
+            config.storage.embedding_cache_enabled = matches!(enabled.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
+        if let Ok(max_mb) = std::env::var("EMBEDDING_CACHE_MAX_MB") {
+            if let Ok(max_mb) = max_mb.parse::<u64>() {
+                config.storage.embedding_cache_max_mb = max_mb;
+            }
+        }
+        
+        if let Ok(hours) = std::env::var("TRASH_RETENTION_HOURS") {
+            if let Ok(hours) = hours.parse::<u64>() {
+                config.storage.trash_retention_hours = hours;
+            }
+        }
+        
+        if let Ok(quantization) = std::env::var("VECTOR_QUANTIZATION") {
+            config.storage.vector_quantization = quantization.parse().map_err(Error::Config)?;
+        }
+        
+        if let Ok(metric) = std::env::var("VECTOR_METRIC") {
+            config.storage.vector_metric = metric.parse().map_err(Error::Config)?;
+        }
+        
+        if let Ok(backend) = std::env::var("VECTOR_BACKEND") {
+            config.storage.vector_backend = backend.parse().map_err(Error::Config)?;
+        }
+        
+        if config.storage.vector_backend != VectorBackend::USearch && config.storage.vector_metric != VectorMetric::Cosine {
+            return Err(Error::Config(format!(
+                "VECTOR_METRIC={} requires VECTOR_BACKEND=usearch; the {} backend always compares by cosine",
+                config.storage.vector_metric.as_str(),
+                config.storage.vector_backend.as_str()
+            )));
+        }
+        
+        if let Ok(mmap) = std::env::var("VECTOR_MMAP") {
+            config.storage.vector_mmap = matches!(mmap.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
+        if let Ok(shards) = std::env::var("VECTOR_SHARDS") {
+            if let Ok(shards) = shards.parse::<usize>() {
+                config.storage.vector_shards = shards.max(1);
+            }
+        }
+        
+        if let Ok(secs) = std::env::var("VECTOR_VACUUM_INTERVAL_SECS") {
+            if let Ok(secs) = secs.parse::<u64>() {
+                config.storage.vacuum_interval_secs = secs;
+            }
+        }
+        
+        if let Ok(connectivity) = std::env::var("HNSW_CONNECTIVITY") {
+            if let Ok(connectivity) = connectivity.parse::<usize>() {
+                config.storage.hnsw.connectivity = connectivity.max(2);
+            }
+        }
+        
+        if let Ok(expansion) = std::env::var("HNSW_EXPANSION_ADD") {
+            if let Ok(expansion) = expansion.parse::<usize>() {
+                config.storage.hnsw.expansion_add = expansion.max(1);
+            }
+        }
+        
+        if let Ok(expansion) = std::env::var("HNSW_EXPANSION_SEARCH") {
+            if let Ok(expansion) = expansion.parse::<usize>() {
+                config.storage.hnsw.expansion_search = expansion.max(1);
+            }
+        }
+        
+        if let Ok(max_chunks) = std::env::var("VECTOR_EXACT_SEARCH_MAX_CHUNKS") {
+            if let Ok(max_chunks) = max_chunks.parse::<usize>() {
+                config.storage.hnsw.exact_search_max_chunks = max_chunks;
+            }
+        }
+        
+        if let Ok(oversample) = std::env::var("VECTOR_RERANK_OVERSAMPLE") {
+            if let Ok(oversample) = oversample.parse::<usize>() {
+                config.search.rerank_oversample = oversample.max(1);
+            }
+        }
+        
         Ok(config)
     }
 }
@@ -0,0 +1,249 @@
+//! Token-aware packing of texts into embedding requests
+//!
+//! Texts are counted with the cl100k tokenizer. That is exact for OpenAI
+//! models and a close, slightly pessimistic estimate for the BERT-style
+//! tokenizers most local models use, which matters more than raw character
+//! counts for minified code and very long lines.
+
+use crate::Error;
+use reqwest::StatusCode;
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tiktoken_rs::CoreBPE;
+use tracing::warn;
+
+fn tokenizer() -> Option<&'static CoreBPE> {
+    static TOKENIZER: OnceLock<Option<CoreBPE>> = OnceLock::new();
+    TOKENIZER
+        .get_or_init(|| match tiktoken_rs::cl100k_base() {
+            Ok(bpe) => Some(bpe),
+            Err(e) => {
+                warn!("[EMBEDDINGS] Tokenizer unavailable, estimating token counts: {}", e);
+                None
+            }
+        })
+        .as_ref()
+}
+
+/// Number of tokens in `text`, or a byte-based estimate without a tokenizer
+pub fn count_tokens(text: &str) -> usize {
+    match tokenizer() {
+        Some(bpe) => bpe.encode_ordinary(text).len(),
+        None => text.len().div_ceil(3),
+    }
+}
+
+/// Cut `text` to at most `max_tokens` tokens
+pub fn truncate_to_tokens(text: &str, max_tokens: usize) -> Cow<'_, str> {
+    if let Some(bpe) = tokenizer() {
+        let tokens = bpe.encode_ordinary(text);
+        if tokens.len() <= max_tokens {
+            return Cow::Borrowed(text);
+        }
+        // Decoding fails when the cut splits a multi-byte character; fall
+        // through to the estimate in that case
+        if let Ok(truncated) = bpe.decode(tokens[..max_tokens].to_vec()) {
+            return Cow::Owned(truncated);
+        }
+    }
+
+    let max_bytes = max_tokens * 3;
+    if text.len() <= max_bytes {
+        return Cow::Borrowed(text);
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    Cow::Owned(text[..end].to_string())
+}
+
+/// End of the batch starting at `start`: as many texts as fit in `max_tokens`
+/// and `max_items`, but always at least one
+pub fn next_batch(token_counts: &[usize], start: usize, max_tokens: usize, max_items: usize) -> Range<usize> {
+    let mut end = start;
+    let mut tokens = 0;
+
+    while end < token_counts.len() && end - start < max_items.max(1) {
+        let next = tokens + token_counts[end];
+        if end > start && next > max_tokens {
+            break;
+        }
+        tokens = next;
+        end += 1;
+    }
+
+    start..end
+}
+
+/// Time a batch may take before later batches are made smaller
+const SLOW_BATCH: Duration = Duration::from_secs(20);
+
+/// Fast batches in a row after which a reduced token budget grows again
+const GROW_AFTER: usize = 4;
+
+/// Token budget of the embedding requests of one indexing run
+///
+/// Starts at the provider's limit. A request rejected as too large lowers the
+/// limit for the rest of the run. A slow request halves the budget, which
+/// grows back by half after `GROW_AFTER` fast ones, so an overloaded local
+/// model gets smaller requests while hosted APIs keep sending full ones.
+#[derive(Debug)]
+pub struct BatchSizer {
+    limit: usize,
+    budget: usize,
+    fast_streak: usize,
+}
+
+impl BatchSizer {
+    pub fn new(limit: usize) -> Self {
+        let limit = limit.max(1);
+        Self { limit, budget: limit, fast_streak: 0 }
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// The provider rejected a batch of `batch_tokens` as too large
+    pub fn rejected(&mut self, batch_tokens: usize) {
+        self.limit = self.limit.min((batch_tokens / 2).max(1));
+        self.budget = self.budget.min(self.limit);
+        self.fast_streak = 0;
+    }
+
+    /// A batch of `batch_tokens` was embedded in `elapsed`; returns whether it
+    /// was slow enough to shrink the budget
+    pub fn completed(&mut self, batch_tokens: usize, elapsed: Duration) -> bool {
+        if elapsed > SLOW_BATCH && batch_tokens > 1 {
+            self.budget = self.budget.min((batch_tokens / 2).max(1));
+            self.fast_streak = 0;
+            return true;
+        }
+
+        self.fast_streak += 1;
+        if self.fast_streak >= GROW_AFTER && self.budget < self.limit {
+            self.budget = (self.budget + self.budget.div_ceil(2)).min(self.limit);
+            self.fast_streak = 0;
+        }
+        false
+    }
+}
+
+/// Map a non-success API response to an error, singling out requests that
+/// were rejected for their size so callers can split them
+pub(crate) fn api_error(provider: &str, status: StatusCode, body: String) -> Error {
+    let message = format!("{provider} API error ({status}): {body}");
+    if status == StatusCode::PAYLOAD_TOO_LARGE || (status == StatusCode::BAD_REQUEST && mentions_size_limit(&body)) {
+        Error::EmbeddingTooLarge(message)
+    } else {
+        Error::Embedding(message)
+    }
+}
+
+/// Error codes and messages the providers reject oversized inputs or batches with
+const SIZE_LIMIT_ERRORS: &[&str] = &[
+    // OpenAI and servers mimicking it (vLLM)
+    "context_length_exceeded",
+    "maximum context length",
+    "tokens per request",
+    // Ollama
+    "input length exceeds the context length",
+    // llama.cpp server
+    "input is too large to process",
+    // Voyage
+    "max allowed tokens per submitted batch",
+    // Gemini
+    "exceeds the maximum number of tokens",
+    "request payload size exceeds the limit",
+    // Bedrock Titan
+    "too many input tokens",
+];
+
+fn mentions_size_limit(body: &str) -> bool {
+    let body = body.to_lowercase();
+    SIZE_LIMIT_ERRORS.iter().any(|needle| body.contains(needle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_batch_packs_up_to_token_limit() {
+        let counts = [300, 300, 300, 900, 50, 50];
+
+        assert_eq!(next_batch(&counts, 0, 1000, 10), 0..3);
+        // A text over the limit still goes out on its own
+        assert_eq!(next_batch(&counts, 3, 800, 10), 3..4);
+        assert_eq!(next_batch(&counts, 4, 1000, 10), 4..6);
+        assert_eq!(next_batch(&counts, 0, 10_000, 2), 0..2);
+    }
+
+    #[test]
+    fn test_batch_sizer_adapts_to_slow_and_rejected_batches() {
+        let fast = Duration::from_millis(500);
+        let mut sizer = BatchSizer::new(8000);
+
+        assert!(sizer.completed(8000, SLOW_BATCH * 2));
+        assert_eq!(sizer.budget(), 4000);
+
+        for _ in 0..GROW_AFTER {
+            assert!(!sizer.completed(4000, fast));
+        }
+        assert_eq!(sizer.budget(), 6000);
+        for _ in 0..GROW_AFTER * 2 {
+            sizer.completed(6000, fast);
+        }
+        assert_eq!(sizer.budget(), 8000);
+
+        // A rejection caps the budget for good
+        sizer.rejected(3000);
+        for _ in 0..GROW_AFTER * 4 {
+            sizer.completed(1500, fast);
+        }
+        assert_eq!(sizer.budget(), 1500);
+    }
+
+    #[test]
+    fn test_api_error_detects_size_rejections() {
+        let too_large = api_error("OpenAI", StatusCode::PAYLOAD_TOO_LARGE, String::new());
+        assert!(matches!(too_large, Error::EmbeddingTooLarge(_)));
+
+        let context = api_error(
+            "OpenAI",
+            StatusCode::BAD_REQUEST,
+            "This model's maximum context length is 8192 tokens".to_string(),
+        );
+        assert!(matches!(context, Error::EmbeddingTooLarge(_)));
+
+        let ollama = api_error(
+            "Ollama",
+            StatusCode::BAD_REQUEST,
+            "{\"error\":\"the input length exceeds the context length\"}".to_string(),
+        );
+        assert!(matches!(ollama, Error::EmbeddingTooLarge(_)));
+
+        let bad_model = api_error("OpenAI", StatusCode::BAD_REQUEST, "Unknown model".to_string());
+        assert!(matches!(bad_model, Error::Embedding(_)));
+        // Other limits are no reason to split the batch
+        let bad_field = api_error(
+            "OpenAI",
+            StatusCode::BAD_REQUEST,
+            "'user' is too long; the value exceeds the limit of 256 characters".to_string(),
+        );
+        assert!(matches!(bad_field, Error::Embedding(_)));
+    }
+
+    #[test]
+    fn test_truncate_to_tokens() {
+        let text = "fn main() { println!(\"hello\"); }".repeat(100);
+        let truncated = truncate_to_tokens(&text, 50);
+
+        assert!(count_tokens(&truncated) <= 50);
+        assert!(text.starts_with(truncated.as_ref()));
+        assert!(matches!(truncate_to_tokens("short", 50), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,378 @@
+//! AWS Bedrock embedding provider
+//!
+//! Calls the Bedrock runtime `InvokeModel` API, signed with AWS Signature
+//! Version 4. Two model families are supported: Amazon Titan Text Embeddings,
+//! which take one text per request, and Cohere Embed on Bedrock, which takes
+//! batches and distinguishes queries from documents.
+
+use crate::{Error, Result};
+use super::EmbeddingProvider;
+use super::batching::api_error;
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Service name Bedrock runtime requests are signed for
+const SIGNING_SERVICE: &str = "bedrock";
+
+/// Most texts Cohere Embed on Bedrock accepts in one request
+const COHERE_TEXTS_PER_REQUEST: usize = 96;
+
+/// Static credentials, as exported by `aws configure export-credentials` or
+/// injected into ECS tasks and Lambda functions
+#[derive(Debug, Clone)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl AwsCredentials {
+    /// Credentials from `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and
+    /// `AWS_SESSION_TOKEN`
+    pub fn from_env() -> Result<Self> {
+        let access_key_id = std::env::var("AWS_ACCESS_KEY_ID")
+            .map_err(|_| Error::Config("Missing AWS_ACCESS_KEY_ID".to_string()))?;
+        let secret_access_key = std::env::var("AWS_SECRET_ACCESS_KEY")
+            .map_err(|_| Error::Config("Missing AWS_SECRET_ACCESS_KEY".to_string()))?;
+        let session_token = std::env::var("AWS_SESSION_TOKEN").ok().filter(|token| !token.is_empty());
+
+        Ok(Self { access_key_id, secret_access_key, session_token })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModelFamily {
+    Titan,
+    Cohere,
+}
+
+impl ModelFamily {
+    /// Family of a model ID such as `amazon.titan-embed-text-v2:0` or a
+    /// cross-region profile such as `us.cohere.embed-english-v3`
+    fn for_model(model: &str) -> Self {
+        if model.contains("cohere.") {
+            ModelFamily::Cohere
+        } else {
+            ModelFamily::Titan
+        }
+    }
+}
+
+pub struct BedrockEmbedding {
+    client: Client,
+    credentials: AwsCredentials,
+    region: String,
+    model: String,
+    family: ModelFamily,
+    /// Scheme and host, e.g. `https://bedrock-runtime.us-east-1.amazonaws.com`
+    endpoint: String,
+    dimension: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TitanRequest<'a> {
+    input_text: &'a str,
+}
+
+#[derive(Deserialize)]
+struct TitanResponse {
+    embedding: Vec<f32>,
+}
+
+#[derive(Serialize)]
+struct CohereRequest<'a> {
+    texts: &'a [String],
+    input_type: &'static str,
+    /// Cut over-long inputs instead of rejecting the request
+    truncate: &'static str,
+}
+
+#[derive(Deserialize)]
+struct CohereResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+impl BedrockEmbedding {
+    pub fn new(credentials: AwsCredentials, region: String, model: Option<String>, base_url: Option<String>) -> Self {
+        let model = model.unwrap_or_else(|| "amazon.titan-embed-text-v2:0".to_string());
+        let endpoint = base_url
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or_else(|| format!("https://bedrock-runtime.{region}.amazonaws.com"));
+
+        Self {
+            client: Client::new(),
+            credentials,
+            region,
+            family: ModelFamily::for_model(&model),
+            model,
+            endpoint,
+            dimension: 0,
+        }
+    }
+
+    /// Send requests through `client`, e.g. one built from the configured timeouts
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub async fn detect_dimension(&mut self) -> Result<usize> {
+        let result = self.embed_query("test").await?;
+        if result.is_empty() {
+            return Err(Error::Embedding("Failed to detect dimension".to_string()));
+        }
+        self.dimension = result.len();
+        Ok(self.dimension)
+    }
+
+    async fn embed_titan(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for text in texts {
+            // Titan rejects empty input
+            let input_text = if text.is_empty() { " " } else { text.as_str() };
+            let body = serde_json::to_vec(&TitanRequest { input_text })?;
+            let response: TitanResponse = self.invoke(body).await?;
+            embeddings.push(response.embedding);
+        }
+        Ok(embeddings)
+    }
+
+    async fn embed_cohere(&self, texts: &[String], input_type: &'static str) -> Result<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(COHERE_TEXTS_PER_REQUEST) {
+            let body = serde_json::to_vec(&CohereRequest { texts: batch, input_type, truncate: "END" })?;
+            let response: CohereResponse = self.invoke(body).await?;
+            embeddings.extend(response.embeddings);
+        }
+        Ok(embeddings)
+    }
+
+    /// Sign and send an `InvokeModel` request
+    async fn invoke<T: serde::de::DeserializeOwned>(&self, body: Vec<u8>) -> Result<T> {
+        let path = format!("/model/{}/invoke", uri_encode(&self.model));
+        let host = self.endpoint
+            .split_once("://")
+            .map_or(self.endpoint.as_str(), |(_, host)| host)
+            .to_string();
+        let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+        let mut headers = vec![
+            ("content-type", "application/json".to_string()),
+            ("host", host),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = &self.credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+
+        // Services other than S3 sign the path with each segment encoded twice
+        let canonical_uri = path.split('/').map(uri_encode).collect::<Vec<_>>().join("/");
+        let authorization = authorization_header(
+            &self.credentials,
+            &self.region,
+            SIGNING_SERVICE,
+            &amz_date,
+            &canonical_request("POST", &canonical_uri, &headers, &body),
+            &signed_headers(&headers),
+        );
+
+        let mut request = self.client
+            .post(format!("{}{}", self.endpoint, path))
+            .header("authorization", authorization)
+            .header("accept", "application/json");
+        for (name, value) in &headers {
+            if *name != "host" {
+                request = request.header(*name, value);
+            }
+        }
+
+        let response = request.body(body).send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(api_error("Bedrock", status, body));
+        }
+
+        Ok(response.json().await?)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for BedrockEmbedding {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed_query(text).await
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embed_documents(texts).await
+    }
+
+    async fn embed_query(&self, query: &str) -> Result<Vec<f32>> {
+        let texts = [query.to_string()];
+        let result = match self.family {
+            ModelFamily::Titan => self.embed_titan(&texts).await?,
+            ModelFamily::Cohere => self.embed_cohere(&texts, "search_query").await?,
+        };
+        result.into_iter().next()
+            .ok_or_else(|| Error::Embedding("No embedding returned".to_string()))
+    }
+
+    async fn embed_documents(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        match self.family {
+            ModelFamily::Titan => self.embed_titan(texts).await,
+            ModelFamily::Cohere => self.embed_cohere(texts, "search_document").await,
+        }
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn provider_name(&self) -> &str {
+        "Bedrock"
+    }
+
+    fn max_input_tokens(&self) -> usize {
+        match self.family {
+            ModelFamily::Titan => 8192,
+            ModelFamily::Cohere => 512,
+        }
+    }
+
+    fn max_batch_tokens(&self) -> usize {
+        match self.family {
+            // One request per text either way; larger batches only save bookkeeping
+            ModelFamily::Titan => 16 * self.max_input_tokens(),
+            ModelFamily::Cohere => COHERE_TEXTS_PER_REQUEST * self.max_input_tokens(),
+        }
+    }
+
+    fn max_batch_items(&self) -> usize {
+        match self.family {
+            ModelFamily::Titan => 16,
+            ModelFamily::Cohere => COHERE_TEXTS_PER_REQUEST,
+        }
+    }
+}
+
+/// Percent-encode everything except unreserved characters, as SigV4 requires
+fn uri_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// Canonical request for a request without query parameters; `headers` are
+/// lowercase and sorted by name
+fn canonical_request(method: &str, canonical_uri: &str, headers: &[(&str, String)], body: &[u8]) -> String {
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+
+    format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        method,
+        canonical_uri,
+        canonical_headers,
+        signed_headers(headers),
+        hex(&Sha256::digest(body))
+    )
+}
+
+fn signed_headers(headers: &[(&str, String)]) -> String {
+    headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";")
+}
+
+fn authorization_header(
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    amz_date: &str,
+    canonical_request: &str,
+    signed_headers: &str,
+) -> String {
+    let date = &amz_date[..8];
+    let scope = format!("{date}/{region}/{service}/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let mut key = hmac_sha256(format!("AWS4{}", credentials.secret_access_key).as_bytes(), date.as_bytes());
+    for part in [region, service, "aws4_request"] {
+        key = hmac_sha256(&key, part.as_bytes());
+    }
+    let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key_id, scope, signed_headers, signature
+    )
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_matches_aws_test_suite() {
+        // "get-vanilla" from the AWS Signature Version 4 test suite
+        let credentials = AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        };
+        let headers = vec![
+            ("host", "example.amazonaws.com".to_string()),
+            ("x-amz-date", "20150830T123600Z".to_string()),
+        ];
+
+        let authorization = authorization_header(
+            &credentials,
+            "us-east-1",
+            "service",
+            "20150830T123600Z",
+            &canonical_request("GET", "/", &headers, b""),
+            &signed_headers(&headers),
+        );
+
+        assert_eq!(
+            authorization,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn test_model_path_encoding() {
+        let model = uri_encode("amazon.titan-embed-text-v2:0");
+        assert_eq!(model, "amazon.titan-embed-text-v2%3A0");
+        assert_eq!(uri_encode(&model), "amazon.titan-embed-text-v2%253A0");
+        assert_eq!(ModelFamily::for_model("us.cohere.embed-english-v3"), ModelFamily::Cohere);
+    }
+}
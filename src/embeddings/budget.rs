@@ -0,0 +1,179 @@
+//! Shared rate budget for embedding requests
+//!
+//! Every indexing job of the server draws from one budget, so codebases
+//! indexed at the same time take turns batch by batch instead of one large
+//! repository holding the provider, and their combined traffic stays under
+//! the configured requests and tokens per minute. Waiters are served in
+//! arrival order: a job that has just sent a batch queues behind the others.
+
+use crate::config::IndexingConfig;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::debug;
+
+pub struct EmbeddingBudget {
+    /// Requests in flight; tokio semaphores hand out permits first come, first served
+    in_flight: Semaphore,
+    rate: Mutex<RateState>,
+}
+
+impl EmbeddingBudget {
+    pub fn new(config: &IndexingConfig) -> Self {
+        Self {
+            in_flight: Semaphore::new(config.embedding_concurrency.max(1)),
+            rate: Mutex::new(RateState {
+                requests: config.requests_per_minute.map(Bucket::per_minute),
+                tokens: config.tokens_per_minute.map(Bucket::per_minute),
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait for a turn to send a request of `tokens` tokens; the turn lasts
+    /// until the returned permit is dropped
+    pub async fn acquire(&self, tokens: usize) -> SemaphorePermit<'_> {
+        let permit = self
+            .in_flight
+            .acquire()
+            .await
+            .expect("embedding budget semaphore is never closed");
+
+        // Waiting for the rate while holding the permit keeps the arrival order
+        loop {
+            let wait = {
+                let mut rate = self.rate.lock().unwrap_or_else(|e| e.into_inner());
+                rate.refill(Instant::now());
+                let wait = rate.wait_for(tokens as f64);
+                if wait.is_zero() {
+                    rate.take(tokens as f64);
+                    break;
+                }
+                wait
+            };
+            debug!("[EMBEDDINGS] Rate budget exhausted, waiting {}ms", wait.as_millis());
+            tokio::time::sleep(wait).await;
+        }
+
+        permit
+    }
+}
+
+struct RateState {
+    requests: Option<Bucket>,
+    tokens: Option<Bucket>,
+    refilled_at: Instant,
+}
+
+impl RateState {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.refilled_at = now;
+        for bucket in [&mut self.requests, &mut self.tokens].into_iter().flatten() {
+            bucket.available = (bucket.available + elapsed * bucket.per_second).min(bucket.capacity);
+        }
+    }
+
+    /// Time until one request of `tokens` tokens fits in both buckets
+    fn wait_for(&self, tokens: f64) -> Duration {
+        let requests = self.requests.as_ref().map_or(Duration::ZERO, |b| b.wait_for(1.0));
+        let tokens = self.tokens.as_ref().map_or(Duration::ZERO, |b| b.wait_for(tokens));
+        requests.max(tokens)
+    }
+
+    fn take(&mut self, tokens: f64) {
+        if let Some(bucket) = &mut self.requests {
+            bucket.take(1.0);
+        }
+        if let Some(bucket) = &mut self.tokens {
+            bucket.take(tokens);
+        }
+    }
+}
+
+/// Token bucket refilled continuously up to one minute's worth
+struct Bucket {
+    capacity: f64,
+    available: f64,
+    per_second: f64,
+}
+
+impl Bucket {
+    fn per_minute(limit: u64) -> Self {
+        let capacity = limit.max(1) as f64;
+        Self {
+            capacity,
+            available: capacity,
+            per_second: capacity / 60.0,
+        }
+    }
+
+    fn wait_for(&self, amount: f64) -> Duration {
+        // A request larger than a minute's budget goes out once the bucket is full
+        let amount = amount.min(self.capacity);
+        if self.available >= amount {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((amount - self.available) / self.per_second)
+        }
+    }
+
+    fn take(&mut self, amount: f64) {
+        self.available = (self.available - amount.min(self.capacity)).max(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_state_waits_for_both_limits() {
+        let start = Instant::now();
+        let mut rate = RateState {
+            requests: Some(Bucket::per_minute(60)),
+            tokens: Some(Bucket::per_minute(6_000)),
+            refilled_at: start,
+        };
+
+        assert!(rate.wait_for(6_000.0).is_zero());
+        rate.take(6_000.0);
+
+        // 100 tokens refill per second; the request bucket is not the bottleneck
+        assert_eq!(rate.wait_for(500.0), Duration::from_secs(5));
+        rate.refill(start + Duration::from_secs(5));
+        assert!(rate.wait_for(500.0).is_zero());
+
+        // Oversized requests wait for a full bucket, not forever
+        rate.take(500.0);
+        assert_eq!(rate.wait_for(1_000_000.0), Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn test_turns_are_taken_in_arrival_order() {
+        let config = IndexingConfig {
+            embedding_concurrency: 1,
+            ..crate::Config::default().indexing
+        };
+        let budget = std::sync::Arc::new(EmbeddingBudget::new(&config));
+        let order = std::sync::Arc::new(Mutex::new(Vec::new()));
+
+        let first = budget.acquire(1).await;
+        let mut waiters = Vec::new();
+        for job in 0..3 {
+            let (budget, order) = (budget.clone(), order.clone());
+            waiters.push(tokio::spawn(async move {
+                let _turn = budget.acquire(1).await;
+                order.lock().unwrap().push(job);
+            }));
+            // Let the job queue up before the next one arrives
+            tokio::task::yield_now().await;
+        }
+        drop(first);
+
+        for waiter in waiters {
+            waiter.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2]);
+    }
+}
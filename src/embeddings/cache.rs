@@ -0,0 +1,215 @@
+//! Embedding cache shared by every codebase of the server
+//!
+//! Vendored dependencies, generated files, branches and re-indexed or
+//! rebuilt codebases produce the same chunks again. Embeddings are stored in
+//! a sled database under `<data_dir>/embedding_cache`, keyed by a hash of the
+//! model identity and the exact text sent to the provider, so such chunks are
+//! embedded once. That text starts with a header naming the chunk's file,
+//! which shapes the vector, so only copies at the same relative path share an
+//! entry. When the stored entries exceed the configured size, the least
+//! recently used ones are evicted.
+
+use crate::{Error, Result};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::info;
+
+/// Eviction stops once entries fit in this fraction of the limit, so it runs
+/// rarely instead of on every insert
+const EVICTION_TARGET: f64 = 0.9;
+
+pub struct EmbeddingCache {
+    db: sled::Db,
+    max_bytes: u64,
+    /// Key and value bytes of all entries
+    stored_bytes: Mutex<u64>,
+}
+
+impl EmbeddingCache {
+    pub fn open(path: &Path, max_bytes: u64) -> Result<Self> {
+        let db = sled::open(path).map_err(storage_error)?;
+
+        let mut stored_bytes = 0;
+        for entry in db.iter() {
+            let (key, value) = entry.map_err(storage_error)?;
+            stored_bytes += (key.len() + value.len()) as u64;
+        }
+
+        Ok(Self {
+            db,
+            max_bytes,
+            stored_bytes: Mutex::new(stored_bytes),
+        })
+    }
+
+    /// Cached embeddings of `texts` under `model`, `None` where there is none
+    pub fn get_many(&self, model: &str, texts: &[String]) -> Result<Vec<Option<Vec<f32>>>> {
+        let now = now_secs();
+        texts
+            .iter()
+            .map(|text| {
+                let key = cache_key(model, text);
+                let Some(value) = self.db.get(key).map_err(storage_error)? else {
+                    return Ok(None);
+                };
+                let Some(vector) = decode_vector(&value) else {
+                    return Ok(None);
+                };
+                // Refresh the access time that eviction orders by
+                self.db.insert(key, encode_entry(now, &vector)).map_err(storage_error)?;
+                Ok(Some(vector))
+            })
+            .collect()
+    }
+
+    /// Store embeddings of `texts` under `model`, evicting old entries if the
+    /// cache grew past its size limit
+    pub fn insert_many(&self, model: &str, texts: &[String], embeddings: &[Vec<f32>]) -> Result<()> {
+        let now = now_secs();
+        let mut added: i64 = 0;
+
+        for (text, embedding) in texts.iter().zip(embeddings) {
+            let key = cache_key(model, text);
+            let value = encode_entry(now, embedding);
+            added += (key.len() + value.len()) as i64;
+            if let Some(previous) = self.db.insert(key, value).map_err(storage_error)? {
+                added -= (key.len() + previous.len()) as i64;
+            }
+        }
+
+        let stored_bytes = {
+            let mut stored_bytes = self.stored_bytes.lock().unwrap_or_else(|e| e.into_inner());
+            *stored_bytes = stored_bytes.saturating_add_signed(added);
+            *stored_bytes
+        };
+
+        if stored_bytes > self.max_bytes {
+            self.evict()?;
+        }
+
+        self.db.flush().map_err(storage_error)?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.db.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.db.is_empty()
+    }
+
+    /// Remove the least recently used entries until the cache is back under
+    /// `EVICTION_TARGET` of its limit
+    fn evict(&self) -> Result<()> {
+        let mut entries = Vec::with_capacity(self.db.len());
+        for entry in self.db.iter() {
+            let (key, value) = entry.map_err(storage_error)?;
+            let used_at = value.get(..8).map_or(0, |b| u64::from_le_bytes(b.try_into().unwrap_or_default()));
+            entries.push((used_at, key, (key.len() + value.len()) as u64));
+        }
+        entries.sort_by_key(|(used_at, _, _)| *used_at);
+
+        let mut stored_bytes = self.stored_bytes.lock().unwrap_or_else(|e| e.into_inner());
+        let target = (self.max_bytes as f64 * EVICTION_TARGET) as u64;
+        let mut evicted = 0;
+
+        for (_, key, size) in entries {
+            if *stored_bytes <= target {
+                break;
+            }
+            self.db.remove(key).map_err(storage_error)?;
+            *stored_bytes = stored_bytes.saturating_sub(size);
+            evicted += 1;
+        }
+
+        info!("[EMBEDDING-CACHE] Evicted {} least recently used embeddings", evicted);
+        Ok(())
+    }
+}
+
+/// Key of the vector `model` returns for `text`. The whole text is hashed,
+/// file header included: the provider embeds them together, so the same
+/// content under another path has another vector.
+fn cache_key(model: &str, text: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(model.as_bytes());
+    hasher.update([0]);
+    hasher.update(text.as_bytes());
+    hasher.finalize().into()
+}
+
+/// Last access time in seconds, then the vector as little-endian `f32`s
+fn encode_entry(used_at: u64, vector: &[f32]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(8 + vector.len() * 4);
+    bytes.extend_from_slice(&used_at.to_le_bytes());
+    for value in vector {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes
+}
+
+fn decode_vector(bytes: &[u8]) -> Option<Vec<f32>> {
+    let data = bytes.get(8..)?;
+    if data.is_empty() || data.len() % 4 != 0 {
+        return None;
+    }
+    Some(
+        data.chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+    )
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn storage_error(e: sled::Error) -> Error {
+    Error::Io(std::io::Error::other(format!("Embedding cache error: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_hits_are_per_model() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = EmbeddingCache::open(&dir.path().join("cache"), u64::MAX).unwrap();
+
+        cache.insert_many("model-a", &texts(&["fn a() {}"]), &[vec![0.5, -1.0]]).unwrap();
+
+        let hits = cache.get_many("model-a", &texts(&["fn a() {}", "fn b() {}"])).unwrap();
+        assert_eq!(hits, vec![Some(vec![0.5, -1.0]), None]);
+        assert_eq!(cache.get_many("model-b", &texts(&["fn a() {}"])).unwrap(), vec![None]);
+    }
+
+    #[test]
+    fn test_eviction_keeps_recent_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        // Room for roughly three entries of 32 key + 8 time + 16 vector bytes
+        let cache = EmbeddingCache::open(&dir.path().join("cache"), 180).unwrap();
+        let vector = vec![1.0; 4];
+
+        for (i, name) in ["a", "b", "c", "d"].iter().enumerate() {
+            let key = cache_key("m", name);
+            // Distinct access times without sleeping
+            cache.db.insert(key, encode_entry(i as u64, &vector)).unwrap();
+            *cache.stored_bytes.lock().unwrap() += 56;
+        }
+        cache.insert_many("m", &texts(&["e"]), &[vector.clone()]).unwrap();
+
+        assert!(cache.len() <= 3);
+        let hits = cache.get_many("m", &texts(&["a", "e"])).unwrap();
+        assert_eq!(hits, vec![None, Some(vector)]);
+    }
+}
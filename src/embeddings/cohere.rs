@@ -0,0 +1,178 @@
+//! Cohere embedding provider
+//!
+//! The v3 models embed queries and documents differently, selected with the
+//! `input_type` request field rather than a text prefix.
+
+use crate::{Error, Result};
+use super::EmbeddingProvider;
+use super::batching::api_error;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+/// Most texts the embed endpoint accepts in one request
+const MAX_TEXTS_PER_REQUEST: usize = 96;
+
+#[derive(Debug, Clone, Copy)]
+enum InputType {
+    SearchQuery,
+    SearchDocument,
+}
+
+impl InputType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            InputType::SearchQuery => "search_query",
+            InputType::SearchDocument => "search_document",
+        }
+    }
+}
+
+pub struct CohereEmbedding {
+    client: Client,
+    api_key: String,
+    model: String,
+    base_url: String,
+    dimension: usize,
+}
+
+#[derive(Serialize)]
+struct EmbedRequest<'a> {
+    model: &'a str,
+    texts: &'a [String],
+    input_type: &'static str,
+    embedding_types: [&'static str; 1],
+    /// Cut over-long inputs instead of rejecting the request
+    truncate: &'static str,
+}
+
+#[derive(Deserialize)]
+struct EmbedResponse {
+    embeddings: EmbeddingsByType,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingsByType {
+    float: Vec<Vec<f32>>,
+}
+
+impl CohereEmbedding {
+    pub fn new(api_key: String, model: Option<String>, base_url: Option<String>) -> Self {
+        let model = model.unwrap_or_else(|| "embed-english-v3.0".to_string());
+        let base_url = base_url.unwrap_or_else(|| "https://api.cohere.com/v2".to_string());
+
+        Self {
+            client: Client::new(),
+            api_key,
+            model,
+            base_url,
+            dimension: 0,
+        }
+    }
+
+    /// Send requests through `client`, e.g. one built from the configured timeouts
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub async fn detect_dimension(&mut self) -> Result<usize> {
+        let result = self.embed_query("test").await?;
+        if result.is_empty() {
+            return Err(Error::Embedding("Failed to detect dimension".to_string()));
+        }
+        self.dimension = result.len();
+        Ok(self.dimension)
+    }
+
+    async fn embed_with_type(&self, texts: &[String], input_type: InputType) -> Result<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+
+        for batch in texts.chunks(MAX_TEXTS_PER_REQUEST) {
+            let request = EmbedRequest {
+                model: &self.model,
+                texts: batch,
+                input_type: input_type.as_str(),
+                embedding_types: ["float"],
+                truncate: "END",
+            };
+
+            let response = self.client
+                .post(format!("{}/embed", self.base_url))
+                .bearer_auth(&self.api_key)
+                .json(&request)
+                .send()
+                .await?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+                return Err(api_error("Cohere", status, body));
+            }
+
+            let embed_response: EmbedResponse = response.json().await?;
+            embeddings.extend(embed_response.embeddings.float);
+        }
+
+        Ok(embeddings)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for CohereEmbedding {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed_query(text).await
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embed_documents(texts).await
+    }
+
+    async fn embed_query(&self, query: &str) -> Result<Vec<f32>> {
+        let result = self.embed_with_type(&[query.to_string()], InputType::SearchQuery).await?;
+        result.into_iter().next()
+            .ok_or_else(|| Error::Embedding("No embedding returned".to_string()))
+    }
+
+    async fn embed_documents(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embed_with_type(texts, InputType::SearchDocument).await
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn provider_name(&self) -> &str {
+        "Cohere"
+    }
+
+    fn max_input_tokens(&self) -> usize {
+        512
+    }
+
+    fn max_batch_tokens(&self) -> usize {
+        MAX_TEXTS_PER_REQUEST * self.max_input_tokens()
+    }
+
+    fn max_batch_items(&self) -> usize {
+        MAX_TEXTS_PER_REQUEST
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_cohere_query_and_document() {
+        let api_key = std::env::var("COHERE_API_KEY").expect("COHERE_API_KEY not set");
+        let provider = CohereEmbedding::new(api_key, None, None);
+
+        let query = provider.embed_query("parse a config file").await.unwrap();
+        let documents = provider.embed_documents(&["fn parse_config() {}".to_string()]).await.unwrap();
+
+        assert_eq!(query.len(), 1024);
+        assert_eq!(documents[0].len(), 1024);
+    }
+}
@@ -0,0 +1,221 @@
+//! Ordered chain of embedding providers
+//!
+//! Requests go to the active provider. After it fails `FAILOVER_AFTER` times
+//! in a row with a provider error, the next provider in the chain becomes
+//! active. Failed requests are never answered by another provider: vectors of
+//! different models cannot share an index, so indexing jobs notice the switch
+//! through `active_model` and rebuild with the new provider.
+
+use crate::{Error, ErrorCode, Result};
+use super::EmbeddingProvider;
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Consecutive provider failures after which the chain moves on
+const FAILOVER_AFTER: usize = 3;
+
+pub struct FallbackEntry {
+    pub provider: Arc<dyn EmbeddingProvider>,
+    /// Model the provider was initialized with
+    pub model: String,
+}
+
+pub struct FallbackEmbedding {
+    entries: Vec<FallbackEntry>,
+    active: AtomicUsize,
+    consecutive_failures: AtomicUsize,
+}
+
+impl FallbackEmbedding {
+    /// Chain starting at the first entry; `entries` must not be empty
+    pub fn new(entries: Vec<FallbackEntry>) -> Self {
+        assert!(!entries.is_empty(), "a fallback chain needs at least one provider");
+        Self {
+            entries,
+            active: AtomicUsize::new(0),
+            consecutive_failures: AtomicUsize::new(0),
+        }
+    }
+
+    fn active_index(&self) -> usize {
+        self.active.load(Ordering::Acquire)
+    }
+
+    fn active_entry(&self) -> &FallbackEntry {
+        &self.entries[self.active_index()]
+    }
+
+    /// Count the outcome of a request sent to entry `index`
+    fn observe<T>(&self, index: usize, result: Result<T>) -> Result<T> {
+        match &result {
+            Ok(_) => self.consecutive_failures.store(0, Ordering::Release),
+            Err(e) if fails_over(e) => {
+                let failures = self.consecutive_failures.fetch_add(1, Ordering::AcqRel) + 1;
+                if failures >= FAILOVER_AFTER {
+                    self.fail_over(index, e);
+                }
+            }
+            Err(_) => {}
+        }
+        result
+    }
+
+    fn fail_over(&self, from: usize, error: &Error) {
+        let next = from + 1;
+        if next >= self.entries.len() {
+            return;
+        }
+        // Concurrent requests failing on the same provider switch only once
+        if self.active.compare_exchange(from, next, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+            self.consecutive_failures.store(0, Ordering::Release);
+            warn!(
+                "[EMBEDDING-FALLBACK] {} '{}' keeps failing ({}); switching to {} '{}'",
+                self.entries[from].provider.provider_name(),
+                self.entries[from].model,
+                error,
+                self.entries[next].provider.provider_name(),
+                self.entries[next].model
+            );
+        }
+    }
+}
+
+/// Whether an error says the provider is unusable, rather than that the input was bad
+fn fails_over(error: &Error) -> bool {
+    !matches!(error, Error::EmbeddingTooLarge(_)) && error.code() == ErrorCode::ProviderUnavailable
+}
+
+#[async_trait]
+impl EmbeddingProvider for FallbackEmbedding {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let index = self.active_index();
+        let result = self.entries[index].provider.embed(text).await;
+        self.observe(index, result)
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let index = self.active_index();
+        let result = self.entries[index].provider.embed_batch(texts).await;
+        self.observe(index, result)
+    }
+
+    async fn embed_query(&self, query: &str) -> Result<Vec<f32>> {
+        let index = self.active_index();
+        let result = self.entries[index].provider.embed_query(query).await;
+        self.observe(index, result)
+    }
+
+    async fn embed_documents(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let index = self.active_index();
+        let result = self.entries[index].provider.embed_documents(texts).await;
+        self.observe(index, result)
+    }
+
+    fn dimension(&self) -> usize {
+        self.active_entry().provider.dimension()
+    }
+
+    fn provider_name(&self) -> &str {
+        self.active_entry().provider.provider_name()
+    }
+
+    fn max_input_tokens(&self) -> usize {
+        self.active_entry().provider.max_input_tokens()
+    }
+
+    fn max_batch_tokens(&self) -> usize {
+        self.active_entry().provider.max_batch_tokens()
+    }
+
+    fn max_batch_items(&self) -> usize {
+        self.active_entry().provider.max_batch_items()
+    }
+
+    fn active_model(&self) -> Option<&str> {
+        Some(&self.active_entry().model)
+    }
+
+    fn provider_for(&self, provider_name: &str, model: &str) -> Option<&dyn EmbeddingProvider> {
+        self.entries
+            .iter()
+            .find(|entry| entry.provider.provider_name() == provider_name && entry.model == model)
+            .map(|entry| entry.provider.as_ref())
+    }
+
+    fn rate_limits(&self) -> Vec<(String, String)> {
+        self.active_entry().provider.rate_limits()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+
+    struct Fake {
+        name: &'static str,
+        dimension: usize,
+        down: AtomicBool,
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for Fake {
+        async fn embed(&self, _text: &str) -> Result<Vec<f32>> {
+            if self.down.load(Ordering::Relaxed) {
+                Err(Error::Embedding(format!("{} is down", self.name)))
+            } else {
+                Ok(vec![0.0; self.dimension])
+            }
+        }
+
+        async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            let mut embeddings = Vec::new();
+            for text in texts {
+                embeddings.push(self.embed(text).await?);
+            }
+            Ok(embeddings)
+        }
+
+        fn dimension(&self) -> usize {
+            self.dimension
+        }
+
+        fn provider_name(&self) -> &str {
+            self.name
+        }
+    }
+
+    fn entry(name: &'static str, dimension: usize, down: bool) -> FallbackEntry {
+        FallbackEntry {
+            provider: Arc::new(Fake { name, dimension, down: AtomicBool::new(down) }),
+            model: format!("{name}-model"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_switches_after_repeated_failures() {
+        let chain = FallbackEmbedding::new(vec![entry("primary", 4, true), entry("backup", 2, false)]);
+
+        for _ in 0..FAILOVER_AFTER {
+            assert_eq!(chain.provider_name(), "primary");
+            assert!(chain.embed("x").await.is_err());
+        }
+
+        assert_eq!(chain.provider_name(), "backup");
+        assert_eq!(chain.active_model(), Some("backup-model"));
+        assert_eq!(chain.embed("x").await.unwrap().len(), 2);
+        assert_eq!(chain.provider_for("primary", "primary-model").unwrap().dimension(), 4);
+        assert!(chain.provider_for("primary", "other-model").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_requests_do_not_switch() {
+        let chain = FallbackEmbedding::new(vec![entry("primary", 4, false), entry("backup", 2, false)]);
+        for _ in 0..FAILOVER_AFTER {
+            let _ = chain.observe::<()>(0, Err(Error::EmbeddingTooLarge("too long".to_string())));
+        }
+        assert_eq!(chain.provider_name(), "primary");
+    }
+}
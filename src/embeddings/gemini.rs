@@ -0,0 +1,188 @@
+//! Google Gemini embedding provider
+//!
+//! Uses the Generative Language API `batchEmbedContents` endpoint; queries and
+//! documents are told apart with the `taskType` field.
+
+use crate::{Error, Result};
+use super::EmbeddingProvider;
+use super::batching::api_error;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+/// Most requests `batchEmbedContents` accepts in one call
+const MAX_TEXTS_PER_REQUEST: usize = 100;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum TaskType {
+    RetrievalQuery,
+    RetrievalDocument,
+}
+
+pub struct GeminiEmbedding {
+    client: Client,
+    api_key: String,
+    model: String,
+    base_url: String,
+    dimension: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchEmbedRequest<'a> {
+    requests: Vec<EmbedContentRequest<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EmbedContentRequest<'a> {
+    model: &'a str,
+    content: Content<'a>,
+    task_type: TaskType,
+}
+
+#[derive(Serialize)]
+struct Content<'a> {
+    parts: [Part<'a>; 1],
+}
+
+#[derive(Serialize)]
+struct Part<'a> {
+    text: &'a str,
+}
+
+#[derive(Deserialize)]
+struct BatchEmbedResponse {
+    embeddings: Vec<ContentEmbedding>,
+}
+
+#[derive(Deserialize)]
+struct ContentEmbedding {
+    values: Vec<f32>,
+}
+
+impl GeminiEmbedding {
+    pub fn new(api_key: String, model: Option<String>, base_url: Option<String>) -> Self {
+        let model = model.unwrap_or_else(|| "text-embedding-004".to_string());
+        // The API names models "models/<id>"; accept either form
+        let model = if model.starts_with("models/") { model } else { format!("models/{model}") };
+        let base_url = base_url.unwrap_or_else(|| "https://generativelanguage.googleapis.com/v1beta".to_string());
+
+        Self {
+            client: Client::new(),
+            api_key,
+            model,
+            base_url,
+            dimension: 0,
+        }
+    }
+
+    /// Send requests through `client`, e.g. one built from the configured timeouts
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub async fn detect_dimension(&mut self) -> Result<usize> {
+        let result = self.embed_query("test").await?;
+        if result.is_empty() {
+            return Err(Error::Embedding("Failed to detect dimension".to_string()));
+        }
+        self.dimension = result.len();
+        Ok(self.dimension)
+    }
+
+    async fn embed_with_task(&self, texts: &[String], task_type: TaskType) -> Result<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        let url = format!("{}/{}:batchEmbedContents", self.base_url, self.model);
+
+        for batch in texts.chunks(MAX_TEXTS_PER_REQUEST) {
+            let request = BatchEmbedRequest {
+                requests: batch
+                    .iter()
+                    .map(|text| EmbedContentRequest {
+                        model: &self.model,
+                        // Empty parts are rejected
+                        content: Content { parts: [Part { text: if text.is_empty() { " " } else { text } }] },
+                        task_type,
+                    })
+                    .collect(),
+            };
+
+            let response = self.client
+                .post(&url)
+                .header("x-goog-api-key", &self.api_key)
+                .json(&request)
+                .send()
+                .await?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+                return Err(api_error("Gemini", status, body));
+            }
+
+            let embed_response: BatchEmbedResponse = response.json().await?;
+            embeddings.extend(embed_response.embeddings.into_iter().map(|e| e.values));
+        }
+
+        Ok(embeddings)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for GeminiEmbedding {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed_query(text).await
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embed_documents(texts).await
+    }
+
+    async fn embed_query(&self, query: &str) -> Result<Vec<f32>> {
+        let result = self.embed_with_task(&[query.to_string()], TaskType::RetrievalQuery).await?;
+        result.into_iter().next()
+            .ok_or_else(|| Error::Embedding("No embedding returned".to_string()))
+    }
+
+    async fn embed_documents(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embed_with_task(texts, TaskType::RetrievalDocument).await
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn provider_name(&self) -> &str {
+        "Gemini"
+    }
+
+    fn max_input_tokens(&self) -> usize {
+        2048
+    }
+
+    fn max_batch_tokens(&self) -> usize {
+        MAX_TEXTS_PER_REQUEST * self.max_input_tokens()
+    }
+
+    fn max_batch_items(&self) -> usize {
+        MAX_TEXTS_PER_REQUEST
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_gemini_embed() {
+        let api_key = std::env::var("GEMINI_API_KEY").expect("GEMINI_API_KEY not set");
+        let provider = GeminiEmbedding::new(api_key, None, None);
+
+        let embedding = provider.embed_query("Hello world").await.unwrap();
+        assert_eq!(embedding.len(), 768);
+    }
+}
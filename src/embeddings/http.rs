@@ -0,0 +1,118 @@
+//! HTTP client shared by the remote embedding providers
+//!
+//! reqwest's defaults have no request timeout and a small idle pool; long
+//! batches against local servers need generous limits, while hosted APIs
+//! benefit from keeping connections warm between batches. Proxies come from
+//! HTTP_PROXY/HTTPS_PROXY/NO_PROXY unless one is configured explicitly.
+
+use crate::config::HttpConfig;
+use crate::{Error, Result};
+use reqwest::header::HeaderMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Build a client with the configured timeouts, pool, HTTP/2, proxy and
+/// root certificate settings
+pub fn build_client(config: &HttpConfig) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .pool_max_idle_per_host(config.pool_max_idle_per_host)
+        .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout_secs));
+
+    if config.request_timeout_secs > 0 {
+        builder = builder.timeout(Duration::from_secs(config.request_timeout_secs));
+    }
+    if config.http2_prior_knowledge {
+        builder = builder.http2_prior_knowledge();
+    }
+    if config.http2_keep_alive_secs > 0 {
+        builder = builder
+            .http2_keep_alive_interval(Duration::from_secs(config.http2_keep_alive_secs))
+            .http2_keep_alive_while_idle(true);
+    }
+    if let Some(proxy) = &config.proxy {
+        let proxy = reqwest::Proxy::all(proxy)
+            .map_err(|e| Error::Config(format!("Invalid EMBEDDING_PROXY '{proxy}': {e}")))?
+            .no_proxy(reqwest::NoProxy::from_env());
+        builder = builder.proxy(proxy);
+    }
+    if let Some(path) = &config.ca_cert {
+        let pem = std::fs::read(path)
+            .map_err(|e| Error::Config(format!("Failed to read EMBEDDING_CA_CERT {}: {e}", path.display())))?;
+        let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| Error::Config(format!("Invalid certificate in EMBEDDING_CA_CERT {}: {e}", path.display())))?;
+        if certificates.is_empty() {
+            return Err(Error::Config(format!("No certificate found in EMBEDDING_CA_CERT {}", path.display())));
+        }
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    builder
+        .build()
+        .map_err(|e| Error::Config(format!("Failed to build embedding HTTP client: {e}")))
+}
+
+/// Rate-limit headers of the latest response a provider received
+#[derive(Default)]
+pub struct RateLimits {
+    latest: Mutex<Vec<(String, String)>>,
+}
+
+impl RateLimits {
+    /// Remember the rate-limit headers among `headers`, if it has any
+    pub fn record(&self, headers: &HeaderMap) {
+        let limits = rate_limit_headers(headers);
+        if !limits.is_empty() {
+            *self.latest.lock().unwrap_or_else(|e| e.into_inner()) = limits;
+        }
+    }
+
+    pub fn latest(&self) -> Vec<(String, String)> {
+        self.latest.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// `x-ratelimit-*`, `ratelimit-*` and `retry-after` headers, sorted by name
+fn rate_limit_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    let mut limits: Vec<(String, String)> = headers
+        .iter()
+        .filter(|(name, _)| name.as_str().contains("ratelimit") || name.as_str() == "retry-after")
+        .filter_map(|(name, value)| Some((name.as_str().to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    limits.sort();
+    limits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_client_rejects_unreadable_ca_cert() {
+        let config = HttpConfig {
+            ca_cert: Some("/nonexistent/corporate-ca.pem".into()),
+            ..HttpConfig::default()
+        };
+        assert!(matches!(build_client(&config), Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn test_rate_limits_keep_latest_limit_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining-tokens", "999000".parse().unwrap());
+        headers.insert("x-ratelimit-limit-requests", "3000".parse().unwrap());
+        headers.insert("content-type", "application/json".parse().unwrap());
+
+        let limits = RateLimits::default();
+        limits.record(&headers);
+        // A response without limit headers keeps the last known ones
+        limits.record(&HeaderMap::new());
+
+        assert_eq!(limits.latest(), vec![
+            ("x-ratelimit-limit-requests".to_string(), "3000".to_string()),
+            ("x-ratelimit-remaining-tokens".to_string(), "999000".to_string()),
+        ]);
+    }
+}
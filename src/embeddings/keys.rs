@@ -0,0 +1,156 @@
+//! API keys of the OpenAI-family providers
+//!
+//! `EMBEDDING_API_KEYS` lists several keys that requests rotate across when
+//! one hits its rate limit, so a large indexing job keeps going on the next
+//! key's quota. `EMBEDDING_API_KEY_COMMAND` instead runs a command printing
+//! a token (e.g. a cloud CLI's `print-access-token`), and runs it again when
+//! the provider rejects the token as expired.
+
+use crate::config::EmbeddingConfig;
+use crate::{Error, Result};
+use std::sync::Mutex;
+use tracing::info;
+
+#[derive(Default)]
+pub struct ApiKeys {
+    keys: Mutex<Vec<String>>,
+    /// Position of the key requests use
+    current: Mutex<usize>,
+    /// Shell command printing a fresh token
+    command: Option<String>,
+}
+
+impl ApiKeys {
+    pub fn new(keys: Vec<String>) -> Self {
+        Self {
+            keys: Mutex::new(keys.into_iter().filter(|key| !key.is_empty()).collect()),
+            ..Self::default()
+        }
+    }
+
+    /// Keys of `config`: the output of its key command if it has one, else
+    /// its key list, else its single key
+    pub async fn from_config(config: &EmbeddingConfig) -> Result<Self> {
+        if let Some(command) = &config.api_key_command {
+            let keys = Self {
+                command: Some(command.clone()),
+                ..Self::default()
+            };
+            keys.refresh().await?;
+            return Ok(keys);
+        }
+        if !config.api_keys.is_empty() {
+            return Ok(Self::new(config.api_keys.clone()));
+        }
+        Ok(Self::new(config.api_key.clone().into_iter().collect()))
+    }
+
+    /// Key requests should use; `None` without any key
+    pub fn current(&self) -> Option<String> {
+        let keys = self.keys.lock().unwrap_or_else(|e| e.into_inner());
+        let current = *self.current.lock().unwrap_or_else(|e| e.into_inner());
+        keys.get(current % keys.len().max(1)).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Move on from `exhausted` after it hit its rate limit. Requests that
+    /// fail together rotate once: only the one still holding the current
+    /// key advances it. False when there is no other key to move to.
+    pub fn rotate(&self, exhausted: &str) -> bool {
+        let keys = self.keys.lock().unwrap_or_else(|e| e.into_inner());
+        if keys.len() < 2 {
+            return false;
+        }
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        if keys.get(*current % keys.len()).is_some_and(|key| key == exhausted) {
+            *current = (*current + 1) % keys.len();
+            info!("[EMBEDDINGS] API key rate-limited; rotating to key {} of {}", *current + 1, keys.len());
+        }
+        true
+    }
+
+    /// Whether a new token can be fetched with `refresh`
+    pub fn refreshable(&self) -> bool {
+        self.command.is_some()
+    }
+
+    /// Run the key command and use the token it prints
+    pub async fn refresh(&self) -> Result<()> {
+        let Some(command) = &self.command else {
+            return Ok(());
+        };
+        let output = shell(command)
+            .output()
+            .await
+            .map_err(|e| Error::Config(format!("Failed to run EMBEDDING_API_KEY_COMMAND: {e}")))?;
+        if !output.status.success() {
+            return Err(Error::Config(format!(
+                "EMBEDDING_API_KEY_COMMAND exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if token.is_empty() {
+            return Err(Error::Config("EMBEDDING_API_KEY_COMMAND printed no token".to_string()));
+        }
+        *self.keys.lock().unwrap_or_else(|e| e.into_inner()) = vec![token];
+        *self.current.lock().unwrap_or_else(|e| e.into_inner()) = 0;
+        info!("[EMBEDDINGS] Fetched a fresh API token with EMBEDDING_API_KEY_COMMAND");
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> tokio::process::Command {
+    let mut shell = tokio::process::Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(not(unix))]
+fn shell(command: &str) -> tokio::process::Command {
+    let mut shell = tokio::process::Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate_moves_past_rate_limited_key_once() {
+        let keys = ApiKeys::new(vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+        assert_eq!(keys.current().as_deref(), Some("a"));
+
+        assert!(keys.rotate("a"));
+        // A second request that failed on the same key does not skip "b"
+        assert!(keys.rotate("a"));
+        assert_eq!(keys.current().as_deref(), Some("b"));
+
+        keys.rotate("b");
+        keys.rotate("c");
+        assert_eq!(keys.current().as_deref(), Some("a"));
+
+        assert!(!ApiKeys::new(vec!["only".to_string()]).rotate("only"));
+        assert!(ApiKeys::new(Vec::new()).current().is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_key_command_output_becomes_the_key() {
+        let mut config = crate::config::Config::default().embedding;
+        config.api_key_command = Some("echo ' token-123 '".to_string());
+        let keys = ApiKeys::from_config(&config).await.unwrap();
+        assert!(keys.refreshable());
+        assert_eq!(keys.current().as_deref(), Some("token-123"));
+    }
+}
@@ -0,0 +1,196 @@
+//! In-process embeddings with ONNX Runtime, through fastembed
+//!
+//! Runs small sentence-embedding models (BGE, MiniLM, nomic, ...) on the CPU,
+//! so indexing and search need neither network access nor an Ollama daemon.
+//! Model files are downloaded from Hugging Face on first use and cached; a
+//! populated cache directory can be copied to machines without network access.
+
+use crate::{Error, Result};
+use super::EmbeddingProvider;
+use super::prompts::InputPrompts;
+use async_trait::async_trait;
+use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Texts per ONNX inference call
+const INFERENCE_BATCH_SIZE: usize = 32;
+
+/// Tokens fastembed keeps of each input
+const MAX_INPUT_TOKENS: usize = 512;
+
+/// Short names accepted in addition to the Hugging Face model codes
+const MODEL_ALIASES: &[(&str, EmbeddingModel)] = &[
+    ("bge-small", EmbeddingModel::BGESmallENV15),
+    ("bge-base", EmbeddingModel::BGEBaseENV15),
+    ("bge-large", EmbeddingModel::BGELargeENV15),
+    ("all-minilm", EmbeddingModel::AllMiniLML6V2),
+    ("all-minilm-l12", EmbeddingModel::AllMiniLML12V2),
+    ("nomic-embed-text", EmbeddingModel::NomicEmbedTextV15),
+];
+
+pub struct LocalEmbedding {
+    model: Arc<TextEmbedding>,
+    model_code: String,
+    dimension: usize,
+    prompts: InputPrompts,
+}
+
+impl LocalEmbedding {
+    /// Load `model` (a fastembed model code, with or without its organisation,
+    /// or an alias such as `bge-small`), downloading it into `cache_dir` if it
+    /// is not there yet
+    pub async fn new(model: Option<String>, cache_dir: PathBuf) -> Result<Self> {
+        let name = model.unwrap_or_else(|| "bge-small".to_string());
+        let (model, model_code, dimension) = resolve_model(&name)?;
+
+        std::fs::create_dir_all(&cache_dir)?;
+        tracing::info!(
+            "[LOCAL] Loading '{}' from {} (downloaded on first use)",
+            model_code,
+            cache_dir.display()
+        );
+
+        // Loading reads (and may download) a few hundred MB; keep it off the async workers
+        let embedding = tokio::task::spawn_blocking(move || {
+            TextEmbedding::try_new(
+                InitOptions::new(model)
+                    .with_cache_dir(cache_dir)
+                    .with_show_download_progress(false),
+            )
+        })
+        .await?
+        .map_err(|e| Error::Embedding(format!("Failed to load local model '{model_code}': {e}")))?;
+
+        let prompts = InputPrompts::for_model(&model_code);
+
+        Ok(Self {
+            model: Arc::new(embedding),
+            model_code,
+            dimension,
+            prompts,
+        })
+    }
+
+    /// Use configured query and document prefixes instead of the ones
+    /// detected from the model name
+    pub fn with_prefixes(mut self, query: Option<&str>, document: Option<&str>) -> Self {
+        self.prompts = self.prompts.with_overrides(query, document);
+        self
+    }
+
+    pub fn model_code(&self) -> &str {
+        &self.model_code
+    }
+
+    async fn run(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let model = Arc::clone(&self.model);
+        tokio::task::spawn_blocking(move || model.embed(texts, Some(INFERENCE_BATCH_SIZE)))
+            .await?
+            .map_err(|e| Error::Embedding(format!("Local inference failed: {e}")))
+    }
+}
+
+/// Model, its Hugging Face code and its dimension for a configured name
+fn resolve_model(name: &str) -> Result<(EmbeddingModel, String, usize)> {
+    let supported = TextEmbedding::list_supported_models();
+    let wanted = MODEL_ALIASES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(name))
+        .map(|(_, model)| model.clone());
+
+    supported
+        .into_iter()
+        .find(|info| match &wanted {
+            Some(model) => &info.model == model,
+            None => {
+                let code = info.model_code.to_lowercase();
+                let name = name.to_lowercase();
+                code == name || code.rsplit('/').next() == Some(name.as_str())
+            }
+        })
+        .map(|info| (info.model, info.model_code, info.dim))
+        .ok_or_else(|| {
+            let codes: Vec<String> = TextEmbedding::list_supported_models()
+                .into_iter()
+                .map(|info| info.model_code)
+                .collect();
+            Error::Config(format!(
+                "Unknown local embedding model '{}'. Supported models: {}",
+                name,
+                codes.join(", ")
+            ))
+        })
+}
+
+#[async_trait]
+impl EmbeddingProvider for LocalEmbedding {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.run(vec![text.to_string()]).await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::Embedding("No embedding returned".to_string()))
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.run(texts.to_vec()).await
+    }
+
+    async fn embed_query(&self, query: &str) -> Result<Vec<f32>> {
+        self.embed(&self.prompts.query(query)).await
+    }
+
+    async fn embed_documents(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.run(self.prompts.documents(texts)).await
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn provider_name(&self) -> &str {
+        "Local"
+    }
+
+    fn max_input_tokens(&self) -> usize {
+        self.prompts.input_tokens(MAX_INPUT_TOKENS)
+    }
+
+    fn max_batch_tokens(&self) -> usize {
+        INFERENCE_BATCH_SIZE * MAX_INPUT_TOKENS
+    }
+
+    fn max_batch_items(&self) -> usize {
+        INFERENCE_BATCH_SIZE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_model_names() {
+        let (model, code, dimension) = resolve_model("bge-small").unwrap();
+        assert_eq!(model, EmbeddingModel::BGESmallENV15);
+        assert_eq!(dimension, 384);
+
+        // Codes match with or without their organisation prefix
+        let (by_code, _, _) = resolve_model(&code).unwrap();
+        let (by_name, _, _) = resolve_model("BGE-SMALL-EN-V1.5").unwrap();
+        assert_eq!(by_code, EmbeddingModel::BGESmallENV15);
+        assert_eq!(by_name, EmbeddingModel::BGESmallENV15);
+
+        assert!(matches!(resolve_model("no-such-model"), Err(Error::Config(_))));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_local_embed() {
+        let cache_dir = std::env::temp_dir().join("code-sage-models");
+        let provider = LocalEmbedding::new(Some("bge-small".to_string()), cache_dir).await.unwrap();
+
+        let embedding = provider.embed_query("parse a config file").await.unwrap();
+        assert_eq!(embedding.len(), 384);
+    }
+}
@@ -0,0 +1,98 @@
+//! Deterministic embeddings for tests, without a model
+//!
+//! Each word of a text is hashed into one of the vector's dimensions and the
+//! result is scaled to unit length, so equal texts always get equal vectors
+//! and texts sharing words score higher than unrelated ones. That is enough
+//! for integration tests and CI to run the whole index and search pipeline
+//! with neither network access nor a GPU.
+
+use crate::Result;
+use super::EmbeddingProvider;
+use async_trait::async_trait;
+
+/// Dimension used when `EMBEDDING_DIMENSIONS` is not set
+pub const DEFAULT_DIMENSION: usize = 384;
+
+pub struct MockEmbedding {
+    dimension: usize,
+}
+
+impl MockEmbedding {
+    pub fn new(dimension: Option<usize>) -> Self {
+        Self {
+            dimension: dimension.unwrap_or(DEFAULT_DIMENSION).max(1),
+        }
+    }
+
+    fn vector(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0f32; self.dimension];
+        for word in text
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .filter(|word| !word.is_empty())
+        {
+            let hash = fnv1a(word.to_lowercase().as_bytes());
+            let sign = if hash & (1 << 63) == 0 { 1.0 } else { -1.0 };
+            vector[(hash % self.dimension as u64) as usize] += sign;
+        }
+
+        let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|v| *v /= norm);
+        } else {
+            // Texts without words still need a valid, non-zero vector
+            vector[0] = 1.0;
+        }
+        vector
+    }
+}
+
+/// FNV-1a, stable across platforms and Rust versions unlike std's hasher
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[async_trait]
+impl EmbeddingProvider for MockEmbedding {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        Ok(self.vector(text))
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|text| self.vector(text)).collect())
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn provider_name(&self) -> &str {
+        "Mock"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[tokio::test]
+    async fn test_mock_vectors_are_deterministic_and_word_based() {
+        let provider = MockEmbedding::new(Some(64));
+
+        let parse = provider.embed("fn parse_config(path: &Path)").await.unwrap();
+        assert_eq!(parse.len(), 64);
+        assert_eq!(parse, MockEmbedding::new(Some(64)).embed("fn parse_config(path: &Path)").await.unwrap());
+
+        let query = provider.embed_query("parse_config path").await.unwrap();
+        let unrelated = provider.embed("render the html template").await.unwrap();
+        assert!(cosine(&query, &parse) > cosine(&query, &unrelated));
+
+        let empty = provider.embed("").await.unwrap();
+        assert!(empty.iter().any(|&v| v != 0.0));
+    }
+}
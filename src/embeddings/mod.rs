@@ -1,6 +1,25 @@
 
 pub mod openai;
 pub mod ollama;
+pub mod cohere;
+pub mod gemini;
+pub mod bedrock;
+#[cfg(feature = "local")]
+pub mod local;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod batching;
+pub mod budget;
+pub mod cache;
+pub mod fallback;
+pub mod http;
+pub mod keys;
+pub mod normalized;
+pub mod presets;
+pub mod pricing;
+pub mod prompts;
+pub mod telemetry;
+pub mod validate;
 
 use crate::Result;
 use async_trait::async_trait;
@@ -11,10 +30,67 @@ pub trait EmbeddingProvider: Send + Sync {
     
     async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
     
+    /// Embed a search query; providers whose models distinguish queries from
+    /// documents apply the query mode here
+    async fn embed_query(&self, query: &str) -> Result<Vec<f32>> {
+        self.embed(query).await
+    }
+    
+    /// Embed code chunks for storage in the index
+    async fn embed_documents(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embed_batch(texts).await
+    }
+    
     fn dimension(&self) -> usize;
     
     fn provider_name(&self) -> &str;
+    
+    /// Most tokens a single input may contain; longer inputs are truncated
+    fn max_input_tokens(&self) -> usize {
+        8192
+    }
+    
+    /// Token budget for one `embed_batch` request
+    fn max_batch_tokens(&self) -> usize {
+        self.max_input_tokens()
+    }
+    
+    /// Most texts worth sending in one `embed_batch` request
+    fn max_batch_items(&self) -> usize {
+        100
+    }
+    
+    /// Model currently embedding, when it can differ from `EMBEDDING_MODEL`
+    fn active_model(&self) -> Option<&str> {
+        None
+    }
+    
+    /// Member of a fallback chain with the given provider name and model,
+    /// e.g. the one that built an index
+    fn provider_for(&self, _provider_name: &str, _model: &str) -> Option<&dyn EmbeddingProvider> {
+        None
+    }
+    
+    /// Rate-limit headers (name, value) of the latest response, for providers
+    /// whose APIs send them
+    fn rate_limits(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
 }
 
 pub use openai::OpenAIEmbedding;
 pub use ollama::OllamaEmbedding;
+pub use cohere::CohereEmbedding;
+pub use gemini::GeminiEmbedding;
+pub use bedrock::{AwsCredentials, BedrockEmbedding};
+#[cfg(feature = "local")]
+pub use local::LocalEmbedding;
+#[cfg(feature = "mock")]
+pub use mock::MockEmbedding;
+pub use prompts::InputPrompts;
+pub use budget::EmbeddingBudget;
+pub use cache::EmbeddingCache;
+pub use fallback::{FallbackEmbedding, FallbackEntry};
+pub use keys::ApiKeys;
+pub use normalized::NormalizedEmbedding;
+pub use telemetry::{EmbeddingTelemetry, InstrumentedEmbedding, ProviderStats};
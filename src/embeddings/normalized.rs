@@ -0,0 +1,95 @@
+//! L2-normalizing wrapper around an embedding provider
+//!
+//! Some models (many served by Ollama) return vectors of arbitrary length.
+//! Scaling every document and query embedding to unit length makes cosine
+//! and dot-product similarity agree, so scores stay comparable whichever
+//! metric a vector index uses.
+
+use crate::Result;
+use super::EmbeddingProvider;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+pub struct NormalizedEmbedding {
+    inner: Arc<dyn EmbeddingProvider>,
+}
+
+impl NormalizedEmbedding {
+    pub fn new(inner: Arc<dyn EmbeddingProvider>) -> Self {
+        Self { inner }
+    }
+}
+
+/// Scale `embedding` to unit length; zero and non-finite vectors are left as
+/// they are for validation to reject
+pub fn l2_normalize(mut embedding: Vec<f32>) -> Vec<f32> {
+    let norm = embedding.iter().map(|value| value * value).sum::<f32>().sqrt();
+    if norm > 0.0 && norm.is_finite() {
+        for value in &mut embedding {
+            *value /= norm;
+        }
+    }
+    embedding
+}
+
+fn normalize_all(embeddings: Vec<Vec<f32>>) -> Vec<Vec<f32>> {
+    embeddings.into_iter().map(l2_normalize).collect()
+}
+
+#[async_trait]
+impl EmbeddingProvider for NormalizedEmbedding {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.inner.embed(text).await.map(l2_normalize)
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.inner.embed_batch(texts).await.map(normalize_all)
+    }
+
+    async fn embed_query(&self, query: &str) -> Result<Vec<f32>> {
+        self.inner.embed_query(query).await.map(l2_normalize)
+    }
+
+    async fn embed_documents(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.inner.embed_documents(texts).await.map(normalize_all)
+    }
+
+    fn dimension(&self) -> usize {
+        self.inner.dimension()
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn max_input_tokens(&self) -> usize {
+        self.inner.max_input_tokens()
+    }
+
+    fn max_batch_tokens(&self) -> usize {
+        self.inner.max_batch_tokens()
+    }
+
+    fn max_batch_items(&self) -> usize {
+        self.inner.max_batch_items()
+    }
+
+    fn rate_limits(&self) -> Vec<(String, String)> {
+        self.inner.rate_limits()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_l2_normalize() {
+        let normalized = l2_normalize(vec![3.0, 4.0]);
+        assert!((normalized[0] - 0.6).abs() < 1e-6);
+        assert!((normalized[1] - 0.8).abs() < 1e-6);
+
+        assert_eq!(l2_normalize(vec![0.0, 0.0]), vec![0.0, 0.0]);
+        assert!(l2_normalize(vec![f32::NAN, 1.0])[0].is_nan());
+    }
+}
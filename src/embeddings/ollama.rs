@@ -1,13 +1,29 @@
 
 use crate::{Error, Result};
 use super::EmbeddingProvider;
+use super::batching::api_error;
+use super::prompts::InputPrompts;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::info;
+
+/// Downloading a model can take far longer than the client's request timeout
+const PULL_TIMEOUT: Duration = Duration::from_secs(60 * 60);
 
 #[derive(Debug, Serialize)]
 struct EmbedRequest {
     model: String,
     input: serde_json::Value,
+    /// How long the server keeps the model loaded after the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct PullRequest<'a> {
+    model: &'a str,
+    stream: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -21,6 +37,9 @@ pub struct OllamaEmbedding {
     model: String,
     dimension: usize,
     max_tokens: usize,
+    prompts: InputPrompts,
+    keep_alive: Option<serde_json::Value>,
+    auto_pull: bool,
 }
 
 impl OllamaEmbedding {
@@ -30,6 +49,7 @@ impl OllamaEmbedding {
         
         let dimension = 768;
         let max_tokens = Self::default_max_tokens_for_model(&model_name);
+        let prompts = InputPrompts::for_model(&model_name);
         
         Self {
             client: reqwest::Client::new(),
@@ -37,9 +57,38 @@ impl OllamaEmbedding {
             model: model_name,
             dimension,
             max_tokens,
+            prompts,
+            keep_alive: None,
+            auto_pull: false,
         }
     }
     
+    /// Keep the model loaded this long after each request: a duration such as
+    /// "30m", or seconds, with a negative value keeping it loaded indefinitely
+    pub fn with_keep_alive(mut self, keep_alive: Option<String>) -> Self {
+        self.keep_alive = keep_alive.as_deref().map(keep_alive_value);
+        self
+    }
+    
+    /// Pull the model through `/api/pull` when the server does not have it
+    pub fn with_auto_pull(mut self, auto_pull: bool) -> Self {
+        self.auto_pull = auto_pull;
+        self
+    }
+    
+    /// Use configured query and document prefixes instead of the ones
+    /// detected from the model name
+    pub fn with_prefixes(mut self, query: Option<&str>, document: Option<&str>) -> Self {
+        self.prompts = self.prompts.with_overrides(query, document);
+        self
+    }
+    
+    /// Send requests through `client`, e.g. one built from the configured timeouts
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+    
     fn default_max_tokens_for_model(model: &str) -> usize {
         if model.contains("nomic-embed-text") || model.contains("snowflake-arctic-embed") {
             8192
@@ -49,11 +98,40 @@ impl OllamaEmbedding {
     }
     
     pub async fn initialize(&mut self) -> Result<()> {
-        let test_embedding = self.embed("test").await?;
+        let test_embedding = match self.embed("test").await {
+            Err(e) if self.auto_pull && is_missing_model(&e) => {
+                self.pull().await?;
+                self.embed("test").await?
+            }
+            result => result?,
+        };
         self.dimension = test_embedding.len();
         Ok(())
     }
     
+    /// Download the model, waiting until the server has it
+    async fn pull(&self) -> Result<()> {
+        info!("[OLLAMA] Model '{}' is not available, pulling it from {}", self.model, self.base_url);
+        
+        let url = format!("{}/api/pull", self.base_url);
+        let response = self.client
+            .post(&url)
+            .timeout(PULL_TIMEOUT)
+            .json(&PullRequest { model: &self.model, stream: false })
+            .send()
+            .await
+            .map_err(|e| Error::Embedding(format!("Ollama pull error: {e}")))?;
+        
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(api_error("Ollama", status, body));
+        }
+        
+        info!("[OLLAMA] Pulled model '{}'", self.model);
+        Ok(())
+    }
+    
     fn preprocess_text(&self, text: &str) -> String {
         if text.is_empty() {
             return " ".to_string();
@@ -82,6 +160,7 @@ impl EmbeddingProvider for OllamaEmbedding {
         let request = EmbedRequest {
             model: self.model.clone(),
             input: serde_json::Value::String(processed),
+            keep_alive: self.keep_alive.clone(),
         };
         
         let url = format!("{}/api/embed", self.base_url);
@@ -96,7 +175,7 @@ impl EmbeddingProvider for OllamaEmbedding {
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await.unwrap_or_default();
-            return Err(Error::Embedding(format!("Ollama API error {status}: {body}")));
+            return Err(api_error("Ollama", status, body));
         }
         
         let embed_response: EmbedResponse = response.json().await
@@ -114,6 +193,7 @@ impl EmbeddingProvider for OllamaEmbedding {
             input: serde_json::Value::Array(
                 processed.into_iter().map(serde_json::Value::String).collect()
             ),
+            keep_alive: self.keep_alive.clone(),
         };
         
         let url = format!("{}/api/embed", self.base_url);
@@ -128,7 +208,7 @@ impl EmbeddingProvider for OllamaEmbedding {
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await.unwrap_or_default();
-            return Err(Error::Embedding(format!("Ollama API error {status}: {body}")));
+            return Err(api_error("Ollama", status, body));
         }
         
         let embed_response: EmbedResponse = response.json().await
@@ -137,6 +217,14 @@ impl EmbeddingProvider for OllamaEmbedding {
         Ok(embed_response.embeddings)
     }
     
+    async fn embed_query(&self, query: &str) -> Result<Vec<f32>> {
+        self.embed(&self.prompts.query(query)).await
+    }
+    
+    async fn embed_documents(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embed_batch(&self.prompts.documents(texts)).await
+    }
+    
     fn dimension(&self) -> usize {
         self.dimension
     }
@@ -144,4 +232,84 @@ impl EmbeddingProvider for OllamaEmbedding {
     fn provider_name(&self) -> &str {
         "Ollama"
     }
+    
+    fn max_input_tokens(&self) -> usize {
+        self.prompts.input_tokens(self.max_tokens)
+    }
+    
+    fn max_batch_tokens(&self) -> usize {
+        // Inputs are embedded one after another; keep requests short enough
+        // not to hit client or server timeouts
+        self.max_tokens * 4
+    }
+
+    fn max_batch_items(&self) -> usize {
+        32
+    }
+}
+
+/// Ollama reads bare numbers as seconds and strings as Go durations ("30m")
+fn keep_alive_value(keep_alive: &str) -> serde_json::Value {
+    match keep_alive.trim().parse::<i64>() {
+        Ok(seconds) => serde_json::Value::from(seconds),
+        Err(_) => serde_json::Value::from(keep_alive.trim()),
+    }
+}
+
+/// Whether the server rejected a request because the model is not pulled:
+/// Ollama answers `{"error":"model \"<name>\" not found, try pulling it first"}`,
+/// while other 404s, e.g. from a proxy or a wrong URL, are not pull-worthy
+fn is_missing_model(error: &Error) -> bool {
+    let message = error.to_string();
+    let Some(body) = message.find('{').map(|start| &message[start..]) else {
+        return false;
+    };
+    let Ok(body) = serde_json::from_str::<serde_json::Value>(body) else {
+        return false;
+    };
+    body["error"]
+        .as_str()
+        .and_then(|error| error.strip_prefix("model \""))
+        .is_some_and(|rest| rest.contains("\" not found"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keep_alive_is_sent_when_set() {
+        let request = |keep_alive: Option<&str>| {
+            serde_json::to_value(EmbedRequest {
+                model: "nomic-embed-text".to_string(),
+                input: serde_json::Value::String("text".to_string()),
+                keep_alive: keep_alive.map(keep_alive_value),
+            })
+            .unwrap()
+        };
+
+        assert!(request(None).get("keep_alive").is_none());
+        assert_eq!(request(Some("30m"))["keep_alive"], "30m");
+        assert_eq!(request(Some("-1"))["keep_alive"], -1);
+    }
+
+    #[test]
+    fn test_missing_model_detection() {
+        let missing = api_error(
+            "Ollama",
+            reqwest::StatusCode::NOT_FOUND,
+            r#"{"error":"model \"nomic-embed-text\" not found, try pulling it first"}"#.to_string(),
+        );
+        assert!(is_missing_model(&missing));
+        assert!(!is_missing_model(&Error::Embedding("Ollama error: connection refused".to_string())));
+        // A 404 from something other than Ollama's model lookup
+        let wrong_url = api_error("Ollama", reqwest::StatusCode::NOT_FOUND, "404 page not found".to_string());
+        assert!(!is_missing_model(&wrong_url));
+        let other = api_error(
+            "Ollama",
+            reqwest::StatusCode::NOT_FOUND,
+            r#"{"error":"file not found"}"#.to_string(),
+        );
+        assert!(!is_missing_model(&other));
+    }
 }
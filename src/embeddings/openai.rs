@@ -1,35 +1,53 @@
 //! OpenAI embedding provider
+//!
+//! Also serves, in Azure mode, Azure OpenAI deployments and, in compatible
+//! mode, self-hosted servers that imitate the API (LM Studio, vLLM, llama.cpp).
 
 use crate::{Error, Result};
 use super::EmbeddingProvider;
+use super::batching::api_error;
+use super::http::RateLimits;
+use super::keys::ApiKeys;
+use super::prompts::InputPrompts;
 use async_trait::async_trait;
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use reqwest::{Client, StatusCode};
+use serde::Serialize;
 
 pub struct OpenAIEmbedding {
     client: Client,
-    api_key: String,
+    keys: ApiKeys,
     model: String,
     base_url: String,
     dimension: usize,
+    /// Output size requested from models that can shorten their embeddings
+    requested_dimensions: Option<usize>,
     max_tokens: usize,
+    prompts: InputPrompts,
+    api: ApiFlavor,
+    rate_limits: RateLimits,
+}
+
+/// How requests are addressed and authenticated
+enum ApiFlavor {
+    /// `{base_url}/embeddings` with a bearer token
+    OpenAI,
+    /// `{endpoint}/openai/deployments/{deployment}/embeddings?api-version=...`
+    /// with an `api-key` header
+    Azure { deployment: String, api_version: String },
+    /// `{base_url}/embeddings` on a self-hosted server; the key is optional
+    /// and only the fields every implementation accepts are sent
+    Compatible,
 }
 
 #[derive(Serialize)]
 struct EmbeddingRequest {
     model: String,
     input: Vec<String>,
-    encoding_format: String,
-}
-
-#[derive(Deserialize)]
-struct EmbeddingResponse {
-    data: Vec<EmbeddingData>,
-}
-
-#[derive(Deserialize)]
-struct EmbeddingData {
-    embedding: Vec<f32>,
+    /// Rejected by some compatible servers, which return floats anyway
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding_format: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<usize>,
 }
 
 impl OpenAIEmbedding {
@@ -39,14 +57,96 @@ impl OpenAIEmbedding {
         
         let dimension = 0;
         let max_tokens = 8192;
+        // OpenAI-compatible servers (LM Studio, vLLM) often host open models
+        // that expect task prefixes
+        let prompts = InputPrompts::for_model(&model);
         
         Self {
             client: Client::new(),
-            api_key,
+            keys: ApiKeys::new(vec![api_key]),
             model,
             base_url,
             dimension,
+            requested_dimensions: None,
             max_tokens,
+            prompts,
+            api: ApiFlavor::OpenAI,
+            rate_limits: RateLimits::default(),
+        }
+    }
+    
+    /// Client for an Azure OpenAI resource; `model` is the model the
+    /// deployment serves, used to pick input prompts
+    pub fn azure(
+        api_key: String,
+        endpoint: String,
+        deployment: String,
+        api_version: String,
+        model: String,
+    ) -> Self {
+        let mut client = Self::new(api_key, Some(model), Some(endpoint.trim_end_matches('/').to_string()));
+        client.api = ApiFlavor::Azure { deployment, api_version };
+        client
+    }
+    
+    /// Client for a self-hosted OpenAI-compatible server at `base_url`
+    /// (e.g. `http://localhost:1234/v1`); inputs are cut to `max_tokens`
+    pub fn compatible(api_key: Option<String>, model: String, base_url: String, max_tokens: usize) -> Self {
+        let mut client = Self::new(
+            api_key.unwrap_or_default(),
+            Some(model),
+            Some(base_url.trim_end_matches('/').to_string()),
+        );
+        client.max_tokens = max_tokens;
+        client.api = ApiFlavor::Compatible;
+        client
+    }
+    
+    /// Ask for embeddings shortened to `dimensions` (Matryoshka models such as
+    /// text-embedding-3), trading a little accuracy for a smaller index
+    pub fn with_dimensions(mut self, dimensions: Option<usize>) -> Self {
+        self.requested_dimensions = dimensions;
+        self
+    }
+    
+    /// Use configured query and document prefixes instead of the ones
+    /// detected from the model name
+    pub fn with_prefixes(mut self, query: Option<&str>, document: Option<&str>) -> Self {
+        self.prompts = self.prompts.with_overrides(query, document);
+        self
+    }
+    
+    /// Send requests through `client`, e.g. one built from the configured timeouts
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+    
+    /// Rotate across several keys on rate limits, or fetch tokens with a command
+    pub fn with_api_keys(mut self, keys: ApiKeys) -> Self {
+        if !keys.is_empty() {
+            self.keys = keys;
+        }
+        self
+    }
+    
+    fn request(&self, api_key: &str) -> reqwest::RequestBuilder {
+        match &self.api {
+            ApiFlavor::Compatible => {
+                let request = self.client.post(format!("{}/embeddings", self.base_url));
+                if api_key.is_empty() {
+                    request
+                } else {
+                    request.header("Authorization", format!("Bearer {}", api_key))
+                }
+            }
+            ApiFlavor::OpenAI => self.client
+                .post(format!("{}/embeddings", self.base_url))
+                .header("Authorization", format!("Bearer {}", api_key)),
+            ApiFlavor::Azure { deployment, api_version } => self.client
+                .post(format!("{}/openai/deployments/{}/embeddings", self.base_url, deployment))
+                .query(&[("api-version", api_version.as_str())])
+                .header("api-key", api_key),
         }
     }
     
@@ -55,6 +155,15 @@ impl OpenAIEmbedding {
         let result = self.embed_batch(&test_text).await?;
         
         if let Some(first) = result.first() {
+            if let Some(requested) = self.requested_dimensions.filter(|&requested| requested != first.len()) {
+                return Err(Error::Config(format!(
+                    "'{}' returned {}-dimensional embeddings although {} dimensions were requested; \
+                     only models trained for shortened embeddings (e.g. text-embedding-3) support EMBEDDING_DIMENSIONS",
+                    self.model,
+                    first.len(),
+                    requested
+                )));
+            }
             self.dimension = first.len();
             Ok(self.dimension)
         } else {
@@ -96,32 +205,69 @@ impl EmbeddingProvider for OpenAIEmbedding {
         let request = EmbeddingRequest {
             model: self.model.clone(),
             input: processed,
-            encoding_format: "float".to_string(),
+            encoding_format: match self.api {
+                ApiFlavor::Compatible => None,
+                _ => Some("float"),
+            },
+            dimensions: self.requested_dimensions,
         };
         
-        let url = format!("{}/embeddings", self.base_url);
-        
-        let response = self.client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await?;
-        
-        if !response.status().is_success() {
+        // A rate-limited key hands the request to the next one, and an
+        // expired token is fetched again once
+        let mut attempts = self.keys.len().max(1);
+        let mut refreshed = false;
+        let response = loop {
+            let api_key = self.keys.current().unwrap_or_default();
+            let response = self.request(&api_key)
+                .header("Content-Type", "application/json")
+                .json(&request)
+                .send()
+                .await?;
+            self.rate_limits.record(response.headers());
+            
             let status = response.status();
+            if status.is_success() {
+                break response;
+            }
+            attempts -= 1;
+            if status == StatusCode::TOO_MANY_REQUESTS && attempts > 0 && self.keys.rotate(&api_key) {
+                continue;
+            }
+            if status == StatusCode::UNAUTHORIZED && !refreshed && self.keys.refreshable() {
+                refreshed = true;
+                attempts += 1;
+                self.keys.refresh().await?;
+                continue;
+            }
             let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(api_error(self.provider_name(), status, error_text));
+        };
+        
+        let body: serde_json::Value = response.json().await?;
+        let embeddings = parse_embeddings(&body).ok_or_else(|| {
+            let mut excerpt = body.to_string();
+            excerpt.truncate(200);
+            Error::Embedding(format!("Unrecognized {} response: {}", self.provider_name(), excerpt))
+        })?;
+        
+        if embeddings.len() != texts.len() {
             return Err(Error::Embedding(format!(
-                "OpenAI API error ({status}): {error_text}"
+                "{} returned {} embeddings for {} inputs",
+                self.provider_name(),
+                embeddings.len(),
+                texts.len()
             )));
         }
         
-        let embedding_response: EmbeddingResponse = response.json().await?;
-        
-        Ok(embedding_response.data.into_iter()
-            .map(|d| d.embedding)
-            .collect())
+        Ok(embeddings)
+    }
+    
+    async fn embed_query(&self, query: &str) -> Result<Vec<f32>> {
+        self.embed(&self.prompts.query(query)).await
+    }
+    
+    async fn embed_documents(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embed_batch(&self.prompts.documents(texts)).await
     }
     
     fn dimension(&self) -> usize {
@@ -129,13 +275,109 @@ impl EmbeddingProvider for OpenAIEmbedding {
     }
     
     fn provider_name(&self) -> &str {
-        "OpenAI"
+        match self.api {
+            ApiFlavor::OpenAI => "OpenAI",
+            ApiFlavor::Azure { .. } => "Azure OpenAI",
+            ApiFlavor::Compatible => "OpenAI-compatible",
+        }
+    }
+    
+    fn max_input_tokens(&self) -> usize {
+        self.prompts.input_tokens(self.max_tokens)
+    }
+    
+    fn max_batch_tokens(&self) -> usize {
+        match self.api {
+            // Local servers evaluate a request in one pass over a small context
+            ApiFlavor::Compatible => self.max_tokens * COMPATIBLE_BATCH_INPUTS,
+            // The API accepts up to 300k tokens per request
+            _ => 250_000,
+        }
+    }
+
+    fn max_batch_items(&self) -> usize {
+        match self.api {
+            ApiFlavor::Compatible => COMPATIBLE_BATCH_INPUTS * 4,
+            // The API accepts up to 2048 inputs per request
+            _ => 2048,
+        }
+    }
+    
+    fn rate_limits(&self) -> Vec<(String, String)> {
+        self.rate_limits.latest()
+    }
+}
+
+/// Full-length inputs per request to a compatible server
+const COMPATIBLE_BATCH_INPUTS: usize = 8;
+
+/// Embeddings from an OpenAI-style response, in input order
+///
+/// Besides `{"data": [{"embedding": [...], "index": 0}]}` this accepts what
+/// compatible servers return instead: `data` entries out of order or without
+/// `index`, bare vectors in `data` or `embeddings`, a top-level array, and
+/// llama.cpp's nested `[[...]]` embedding.
+fn parse_embeddings(body: &serde_json::Value) -> Option<Vec<Vec<f32>>> {
+    let items = body
+        .get("data")
+        .or_else(|| body.get("embeddings"))
+        .unwrap_or(body)
+        .as_array()?;
+    
+    let mut indexed = Vec::with_capacity(items.len());
+    for (position, item) in items.iter().enumerate() {
+        let index = item.get("index").and_then(|i| i.as_u64()).map_or(position, |i| i as usize);
+        let vector = item.get("embedding").unwrap_or(item);
+        indexed.push((index, parse_vector(vector)?));
+    }
+    indexed.sort_by_key(|(index, _)| *index);
+    
+    Some(indexed.into_iter().map(|(_, vector)| vector).collect())
+}
+
+fn parse_vector(value: &serde_json::Value) -> Option<Vec<f32>> {
+    let values = value.as_array()?;
+    // Servers that return one vector per input wrap a pooled embedding in another array
+    if let [inner @ serde_json::Value::Array(_)] = values.as_slice() {
+        return parse_vector(inner);
     }
+    values.iter().map(|v| v.as_f64().map(|f| f as f32)).collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
+    
+    #[test]
+    fn test_parse_embeddings_variants() {
+        let expected = vec![vec![0.1, 0.2], vec![0.3, 0.4]];
+        
+        let openai = json!({"object": "list", "data": [
+            {"object": "embedding", "index": 1, "embedding": [0.3, 0.4]},
+            {"object": "embedding", "index": 0, "embedding": [0.1, 0.2]},
+        ]});
+        let bare = json!({"embeddings": [[0.1, 0.2], [0.3, 0.4]]});
+        let nested = json!([{"embedding": [[0.1, 0.2]]}, {"embedding": [[0.3, 0.4]]}]);
+        
+        for body in [openai, bare, nested] {
+            assert_eq!(parse_embeddings(&body), Some(expected.clone()), "{body}");
+        }
+        assert_eq!(parse_embeddings(&json!({"error": "model not loaded"})), None);
+    }
+    
+    #[test]
+    fn test_dimensions_only_sent_when_requested() {
+        let request = |dimensions| EmbeddingRequest {
+            model: "text-embedding-3-small".to_string(),
+            input: vec!["x".to_string()],
+            encoding_format: Some("float"),
+            dimensions,
+        };
+        
+        assert_eq!(serde_json::to_value(request(Some(512))).unwrap()["dimensions"], 512);
+        assert!(serde_json::to_value(request(None)).unwrap().get("dimensions").is_none());
+    }
     
     #[tokio::test]
     #[ignore]
@@ -0,0 +1,100 @@
+//! Named settings of embedding models trained for code
+//!
+//! `EMBEDDING_MODEL=preset:<name>` picks the provider, model, base URL,
+//! token limit, prefixes and price of a known code model in one go. Any of
+//! them set explicitly (`EMBEDDING_PROVIDER`, `EMBEDDING_BASE_URL`,
+//! `EMBEDDING_MAX_TOKENS`, the prefix and price variables) still wins.
+
+use crate::config::EmbeddingProvider;
+
+/// Prefix of `EMBEDDING_MODEL` values naming a preset
+pub const PRESET_PREFIX: &str = "preset:";
+
+#[derive(Debug)]
+pub struct ModelPreset {
+    pub name: &'static str,
+    /// Other names accepted for the preset
+    pub aliases: &'static [&'static str],
+    pub provider: EmbeddingProvider,
+    pub model: &'static str,
+    pub base_url: Option<&'static str>,
+    /// Variable holding the API key when the provider's usual one is unset
+    pub api_key_var: Option<&'static str>,
+    /// Output size of the model, checked against the detected one at startup
+    pub dimension: usize,
+    pub max_input_tokens: usize,
+    pub query_prefix: &'static str,
+    pub document_prefix: &'static str,
+    /// USD per million tokens; `None` when billed otherwise or self-hosted
+    pub price_per_million_tokens: Option<f64>,
+}
+
+pub const PRESETS: &[ModelPreset] = &[
+    // Voyage's API follows OpenAI's; its input_type modes are these prompts
+    ModelPreset {
+        name: "voyage-code-3",
+        aliases: &["voyage-code"],
+        provider: EmbeddingProvider::OpenAICompatible,
+        model: "voyage-code-3",
+        base_url: Some("https://api.voyageai.com/v1"),
+        api_key_var: Some("VOYAGE_API_KEY"),
+        dimension: 1024,
+        max_input_tokens: 32_000,
+        query_prefix: "Represent the query for retrieving supporting documents: ",
+        document_prefix: "Represent the document for retrieval: ",
+        price_per_million_tokens: Some(0.18),
+    },
+    ModelPreset {
+        name: "jina-code",
+        aliases: &["jina-embeddings-v2-base-code"],
+        provider: EmbeddingProvider::OpenAICompatible,
+        model: "jina-embeddings-v2-base-code",
+        base_url: Some("https://api.jina.ai/v1"),
+        api_key_var: Some("JINA_API_KEY"),
+        dimension: 768,
+        max_input_tokens: 8192,
+        query_prefix: "",
+        document_prefix: "",
+        price_per_million_tokens: None,
+    },
+    // Served from its GGUF build by llama.cpp's server
+    ModelPreset {
+        name: "nomic-code",
+        aliases: &["nomic-embed-code"],
+        provider: EmbeddingProvider::OpenAICompatible,
+        model: "nomic-embed-code",
+        base_url: Some("http://localhost:8080/v1"),
+        api_key_var: None,
+        dimension: 3584,
+        max_input_tokens: 8192,
+        query_prefix: "Represent this query for searching relevant code: ",
+        document_prefix: "",
+        price_per_million_tokens: Some(0.0),
+    },
+];
+
+/// Preset called `name` or one of its aliases, ignoring case
+pub fn find(name: &str) -> Option<&'static ModelPreset> {
+    let name = name.trim().to_lowercase();
+    PRESETS
+        .iter()
+        .find(|preset| preset.name == name || preset.aliases.contains(&name.as_str()))
+}
+
+/// Names of all presets, for error messages
+pub fn names() -> Vec<&'static str> {
+    PRESETS.iter().map(|preset| preset.name).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_presets_by_name_and_alias() {
+        assert_eq!(find("nomic-code").unwrap().dimension, 3584);
+        assert_eq!(find("Nomic-Embed-Code").unwrap().name, "nomic-code");
+        assert_eq!(find("voyage-code").unwrap().model, "voyage-code-3");
+        assert!(find("text-embedding-3-small").is_none());
+    }
+}
@@ -0,0 +1,58 @@
+//! List prices of hosted embedding models, for indexing cost estimates
+//!
+//! Prices are USD per million input tokens as published by each provider;
+//! they change over time, so `EMBEDDING_PRICE_PER_MILLION_TOKENS` overrides
+//! this table.
+
+/// Price per million input tokens of `model` served by the provider named
+/// `provider_name`; zero for self-hosted providers, `None` when unknown
+pub fn price_per_million_tokens(provider_name: &str, model: &str) -> Option<f64> {
+    let model = model.to_lowercase();
+    match provider_name {
+        "Ollama" | "Local" | "OpenAI-compatible" => Some(0.0),
+        "OpenAI" | "Azure OpenAI" => {
+            if model.contains("text-embedding-3-small") {
+                Some(0.02)
+            } else if model.contains("text-embedding-3-large") {
+                Some(0.13)
+            } else if model.contains("text-embedding-ada-002") {
+                Some(0.10)
+            } else {
+                None
+            }
+        }
+        "Cohere" => {
+            if model.starts_with("embed-v4") {
+                Some(0.12)
+            } else if model.starts_with("embed-") {
+                Some(0.10)
+            } else {
+                None
+            }
+        }
+        "Gemini" => model.contains("gemini-embedding-001").then_some(0.15),
+        "Bedrock" => {
+            if model.starts_with("amazon.titan-embed-text-v2") {
+                Some(0.02)
+            } else if model.starts_with("amazon.titan-embed-text-v1") || model.starts_with("cohere.embed-") {
+                Some(0.10)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price_per_million_tokens() {
+        assert_eq!(price_per_million_tokens("OpenAI", "text-embedding-3-small"), Some(0.02));
+        assert_eq!(price_per_million_tokens("Bedrock", "amazon.titan-embed-text-v2:0"), Some(0.02));
+        assert_eq!(price_per_million_tokens("Ollama", "nomic-embed-text"), Some(0.0));
+        assert_eq!(price_per_million_tokens("OpenAI", "some-future-model"), None);
+    }
+}
@@ -0,0 +1,114 @@
+//! Query and document prefixes for models trained with task instructions
+//!
+//! Asymmetric retrieval models expect queries and documents to be marked
+//! differently. Hosted APIs such as Cohere and Voyage take an input type
+//! parameter; open models served through Ollama or an OpenAI-compatible server
+//! expect a text prefix instead.
+
+use super::batching::count_tokens;
+
+/// Instruction used by the BGE, mxbai and Arctic models for retrieval queries
+const RETRIEVAL_INSTRUCTION: &str = "Represent this sentence for searching relevant passages: ";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputPrompts {
+    pub query: String,
+    pub document: String,
+}
+
+impl InputPrompts {
+    fn new(query: &str, document: &str) -> Self {
+        Self { query: query.to_string(), document: document.to_string() }
+    }
+
+    /// Prefixes the model was trained with; none for symmetric models
+    pub fn for_model(model: &str) -> Self {
+        let model = model.to_lowercase();
+
+        if model.contains("nomic-embed-code") {
+            Self::new("Represent this query for searching relevant code: ", "")
+        } else if model.contains("nomic-embed") {
+            Self::new("search_query: ", "search_document: ")
+        } else if model.contains("e5-") && !model.contains("instruct") {
+            Self::new("query: ", "passage: ")
+        } else if (model.contains("bge-") && !model.contains("bge-m3"))
+            || model.contains("mxbai-embed")
+            || model.contains("snowflake-arctic-embed")
+        {
+            Self::new(RETRIEVAL_INSTRUCTION, "")
+        } else {
+            Self::default()
+        }
+    }
+
+    /// Replace the detected prefixes with configured ones, e.g. for a
+    /// fine-tuned model whose name gives no hint
+    pub fn with_overrides(mut self, query: Option<&str>, document: Option<&str>) -> Self {
+        if let Some(query) = query {
+            self.query = query.to_string();
+        }
+        if let Some(document) = document {
+            self.document = document.to_string();
+        }
+        self
+    }
+
+    pub fn query(&self, text: &str) -> String {
+        format!("{}{}", self.query, text)
+    }
+
+    pub fn documents(&self, texts: &[String]) -> Vec<String> {
+        texts.iter().map(|text| format!("{}{}", self.document, text)).collect()
+    }
+
+    /// Input limit of a model taking `max_tokens` tokens left for the text
+    /// after the longer prefix, which is added after inputs are truncated
+    pub fn input_tokens(&self, max_tokens: usize) -> usize {
+        let prefix = count_tokens(&self.query).max(count_tokens(&self.document));
+        max_tokens.saturating_sub(prefix).max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompts_per_model_family() {
+        let nomic = InputPrompts::for_model("nomic-embed-text:v1.5");
+        assert_eq!(nomic.query("parse"), "search_query: parse");
+        assert_eq!(nomic.documents(&["fn x()".to_string()]), vec!["search_document: fn x()"]);
+
+        let nomic_code = InputPrompts::for_model("nomic-ai/nomic-embed-code");
+        assert_eq!(nomic_code.query("parse"), "Represent this query for searching relevant code: parse");
+        assert_eq!(nomic_code.document, "");
+
+        let bge = InputPrompts::for_model("BAAI/bge-small-en-v1.5");
+        assert_eq!(bge.query, RETRIEVAL_INSTRUCTION);
+        assert_eq!(bge.document, "");
+
+        assert_eq!(InputPrompts::for_model("text-embedding-3-small"), InputPrompts::default());
+        assert_eq!(InputPrompts::for_model("bge-m3"), InputPrompts::default());
+    }
+
+    #[test]
+    fn test_configured_prefixes_override_detected_ones() {
+        let custom = InputPrompts::for_model("my-finetuned-embedder").with_overrides(Some("Query: "), Some("Code: "));
+        assert_eq!(custom.query("parse"), "Query: parse");
+        assert_eq!(custom.documents(&["fn x()".to_string()]), vec!["Code: fn x()"]);
+
+        // An empty override turns a detected prefix off, and an unset one keeps it
+        let nomic = InputPrompts::for_model("nomic-embed-text").with_overrides(None, Some(""));
+        assert_eq!(nomic.query, "search_query: ");
+        assert_eq!(nomic.document, "");
+    }
+
+    #[test]
+    fn test_input_limit_leaves_room_for_the_prefix() {
+        let nomic = InputPrompts::for_model("nomic-embed-text");
+        let prefix = count_tokens("search_document: ");
+        assert_eq!(nomic.input_tokens(512), 512 - prefix);
+        assert_eq!(InputPrompts::default().input_tokens(512), 512);
+        assert_eq!(nomic.input_tokens(1), 1);
+    }
+}
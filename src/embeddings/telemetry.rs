@@ -0,0 +1,229 @@
+//! Request telemetry of the embedding providers
+//!
+//! `InstrumentedEmbedding` wraps each started provider and records, per
+//! provider and model, how many requests and texts it was sent, how many
+//! failed and how long they took. Indexing adds the batches it had to send
+//! again. The counters live for the server process and back the
+//! `embedding_stats` tool, so providers can be compared on the same hardware.
+
+use crate::Result;
+use super::EmbeddingProvider;
+use async_trait::async_trait;
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Latencies kept per provider for percentiles; older ones are dropped
+const LATENCY_WINDOW: usize = 1024;
+
+#[derive(Default)]
+struct Counters {
+    requests: u64,
+    texts: u64,
+    failed: u64,
+    retried_batches: u64,
+    total_latency: Duration,
+    latencies: VecDeque<Duration>,
+}
+
+/// Snapshot of one provider's counters
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderStats {
+    pub provider: String,
+    pub model: String,
+    pub requests: u64,
+    pub texts: u64,
+    pub failed: u64,
+    pub retried_batches: u64,
+    pub mean_latency_ms: f64,
+    /// Percentiles over the latest requests
+    pub p50_latency_ms: f64,
+    pub p95_latency_ms: f64,
+    pub p99_latency_ms: f64,
+}
+
+#[derive(Default)]
+pub struct EmbeddingTelemetry {
+    providers: Mutex<BTreeMap<(String, String), Counters>>,
+}
+
+impl EmbeddingTelemetry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn update(&self, provider: &str, model: &str, apply: impl FnOnce(&mut Counters)) {
+        let mut providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        apply(providers.entry((provider.to_string(), model.to_string())).or_default());
+    }
+
+    /// Count a request of `texts` texts that took `latency`
+    pub fn record_request(&self, provider: &str, model: &str, texts: usize, latency: Duration, ok: bool) {
+        self.update(provider, model, |counters| {
+            counters.requests += 1;
+            counters.texts += texts as u64;
+            if !ok {
+                counters.failed += 1;
+            }
+            counters.total_latency += latency;
+            if counters.latencies.len() == LATENCY_WINDOW {
+                counters.latencies.pop_front();
+            }
+            counters.latencies.push_back(latency);
+        });
+    }
+
+    /// Count a batch sent again after the provider rejected it or returned
+    /// unusable embeddings
+    pub fn record_retry(&self, provider: &str, model: &str) {
+        self.update(provider, model, |counters| counters.retried_batches += 1);
+    }
+
+    pub fn snapshot(&self) -> Vec<ProviderStats> {
+        let providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        providers
+            .iter()
+            .map(|((provider, model), counters)| {
+                let mut latencies: Vec<Duration> = counters.latencies.iter().copied().collect();
+                latencies.sort();
+                ProviderStats {
+                    provider: provider.clone(),
+                    model: model.clone(),
+                    requests: counters.requests,
+                    texts: counters.texts,
+                    failed: counters.failed,
+                    retried_batches: counters.retried_batches,
+                    mean_latency_ms: if counters.requests == 0 {
+                        0.0
+                    } else {
+                        millis(counters.total_latency) / counters.requests as f64
+                    },
+                    p50_latency_ms: percentile(&latencies, 0.50),
+                    p95_latency_ms: percentile(&latencies, 0.95),
+                    p99_latency_ms: percentile(&latencies, 0.99),
+                }
+            })
+            .collect()
+    }
+
+    pub fn reset(&self) {
+        self.providers.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Nearest-rank percentile of sorted `latencies`, in milliseconds
+fn percentile(latencies: &[Duration], quantile: f64) -> f64 {
+    if latencies.is_empty() {
+        return 0.0;
+    }
+    let rank = ((quantile * latencies.len() as f64).ceil() as usize).clamp(1, latencies.len());
+    millis(latencies[rank - 1])
+}
+
+/// Provider wrapper recording every request in an `EmbeddingTelemetry`
+pub struct InstrumentedEmbedding {
+    inner: Arc<dyn EmbeddingProvider>,
+    model: String,
+    telemetry: Arc<EmbeddingTelemetry>,
+}
+
+impl InstrumentedEmbedding {
+    /// Record requests to `inner`, which serves `model`
+    pub fn new(inner: Arc<dyn EmbeddingProvider>, model: String, telemetry: Arc<EmbeddingTelemetry>) -> Self {
+        Self { inner, model, telemetry }
+    }
+
+    fn record<T>(&self, texts: usize, started: Instant, result: Result<T>) -> Result<T> {
+        self.telemetry.record_request(
+            self.inner.provider_name(),
+            &self.model,
+            texts,
+            started.elapsed(),
+            result.is_ok(),
+        );
+        result
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for InstrumentedEmbedding {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let started = Instant::now();
+        let result = self.inner.embed(text).await;
+        self.record(1, started, result)
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let started = Instant::now();
+        let result = self.inner.embed_batch(texts).await;
+        self.record(texts.len(), started, result)
+    }
+
+    async fn embed_query(&self, query: &str) -> Result<Vec<f32>> {
+        let started = Instant::now();
+        let result = self.inner.embed_query(query).await;
+        self.record(1, started, result)
+    }
+
+    async fn embed_documents(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let started = Instant::now();
+        let result = self.inner.embed_documents(texts).await;
+        self.record(texts.len(), started, result)
+    }
+
+    fn dimension(&self) -> usize {
+        self.inner.dimension()
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn max_input_tokens(&self) -> usize {
+        self.inner.max_input_tokens()
+    }
+
+    fn max_batch_tokens(&self) -> usize {
+        self.inner.max_batch_tokens()
+    }
+
+    fn max_batch_items(&self) -> usize {
+        self.inner.max_batch_items()
+    }
+
+    fn rate_limits(&self) -> Vec<(String, String)> {
+        self.inner.rate_limits()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_reports_counts_and_percentiles() {
+        let telemetry = EmbeddingTelemetry::new();
+        for ms in 1..=100 {
+            telemetry.record_request("Ollama", "nomic-embed-text", 8, Duration::from_millis(ms), ms != 100);
+        }
+        telemetry.record_retry("Ollama", "nomic-embed-text");
+        telemetry.record_request("OpenAI", "text-embedding-3-small", 1, Duration::from_millis(40), true);
+
+        let stats = telemetry.snapshot();
+        assert_eq!(stats.len(), 2);
+        let ollama = &stats[0];
+        assert_eq!(ollama.provider, "Ollama");
+        assert_eq!((ollama.requests, ollama.texts, ollama.failed, ollama.retried_batches), (100, 800, 1, 1));
+        assert_eq!(ollama.p50_latency_ms, 50.0);
+        assert_eq!(ollama.p99_latency_ms, 99.0);
+        assert_eq!(stats[1].p95_latency_ms, 40.0);
+
+        telemetry.reset();
+        assert!(telemetry.snapshot().is_empty());
+    }
+}
@@ -0,0 +1,57 @@
+//! Sanity checks on embeddings returned by providers
+//!
+//! A vector of the wrong length, with NaN/Inf components or with every
+//! component zero cannot be compared by cosine similarity. Stored in the
+//! vector index it silently skews every score, so indexing re-requests such
+//! embeddings and leaves the chunk out if the provider keeps returning them.
+
+use std::fmt;
+
+/// Why an embedding cannot be stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Defect {
+    WrongLength { expected: usize, actual: usize },
+    NonFinite,
+    AllZero,
+}
+
+impl fmt::Display for Defect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Defect::WrongLength { expected, actual } => {
+                write!(f, "{actual} dimensions instead of {expected}")
+            }
+            Defect::NonFinite => write!(f, "NaN or infinite components"),
+            Defect::AllZero => write!(f, "all components are zero"),
+        }
+    }
+}
+
+/// First defect of `embedding` for an index of `dimension` dimensions
+pub fn inspect(embedding: &[f32], dimension: usize) -> Option<Defect> {
+    if embedding.len() != dimension {
+        return Some(Defect::WrongLength { expected: dimension, actual: embedding.len() });
+    }
+    if embedding.iter().any(|value| !value.is_finite()) {
+        return Some(Defect::NonFinite);
+    }
+    if embedding.iter().all(|&value| value == 0.0) {
+        return Some(Defect::AllZero);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect() {
+        assert_eq!(inspect(&[0.1, -0.2, 0.0], 3), None);
+        assert_eq!(inspect(&[0.1, 0.2], 3), Some(Defect::WrongLength { expected: 3, actual: 2 }));
+        assert_eq!(inspect(&[0.1, f32::NAN, 0.3], 3), Some(Defect::NonFinite));
+        assert_eq!(inspect(&[f32::INFINITY, 0.2, 0.3], 3), Some(Defect::NonFinite));
+        assert_eq!(inspect(&[0.0, -0.0, 0.0], 3), Some(Defect::AllZero));
+        assert_eq!(inspect(&[], 0), Some(Defect::AllZero));
+    }
+}
@@ -1,3 +1,4 @@
+use serde::Serialize;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -23,6 +24,14 @@ pub enum Error {
     #[error("Embedding error: {0}")]
     Embedding(String),
 
+    /// The chat model reranking search results failed or gave no usable ratings
+    #[error("Rerank model error: {0}")]
+    Rerank(String),
+
+    /// The provider rejected a request for exceeding its size or token limits
+    #[error("Embedding request too large: {0}")]
+    EmbeddingTooLarge(String),
+
     #[error("Configuration error: {0}")]
     Config(String),
 
@@ -32,12 +41,28 @@ pub enum Error {
     #[error("Invalid path: {0}")]
     InvalidPath(String),
 
+    /// The path exists but this process may not read it
+    #[error("Access denied: {0}")]
+    PathDenied(String),
+
     #[error("Unsupported language: {0}")]
     UnsupportedLanguage(String),
 
     #[error("Snapshot error: {0}")]
     Snapshot(String),
 
+    /// A stored file failed verification and must be rebuilt
+    #[error("Corrupted data: {0}")]
+    Corrupted(String),
+
+    /// An existing index holds embeddings of another size than the configured model produces
+    #[error("Embedding dimension mismatch: {0}")]
+    DimensionMismatch(String),
+
+    /// An existing index was built with an embedding model the server no longer uses
+    #[error("Embedding model mismatch: {0}")]
+    ModelMismatch(String),
+
     #[error("MCP protocol error: {0}")]
     Mcp(String),
 
@@ -54,4 +79,54 @@ pub enum Error {
     Unknown(String),
 }
 
+/// Stable machine-readable code sent as `code` next to every tool error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    NotIndexed,
+    IndexingInProgress,
+    ProviderUnavailable,
+    DimensionMismatch,
+    /// The index was built with an embedding model the server no longer uses
+    ModelMismatch,
+    PathDenied,
+    InvalidPath,
+    InvalidArgument,
+    CorruptedIndex,
+    Internal,
+}
+
+impl Error {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::NotIndexed(_) => ErrorCode::NotIndexed,
+            Error::Embedding(_) | Error::EmbeddingTooLarge(_) | Error::Rerank(_) | Error::Reqwest(_) => ErrorCode::ProviderUnavailable,
+            Error::DimensionMismatch(_) => ErrorCode::DimensionMismatch,
+            Error::ModelMismatch(_) => ErrorCode::ModelMismatch,
+            Error::PathDenied(_) => ErrorCode::PathDenied,
+            Error::Io(e) if e.kind() == std::io::ErrorKind::PermissionDenied => ErrorCode::PathDenied,
+            Error::InvalidPath(_) => ErrorCode::InvalidPath,
+            Error::Config(_) | Error::UnsupportedLanguage(_) => ErrorCode::InvalidArgument,
+            Error::Corrupted(_) => ErrorCode::CorruptedIndex,
+            _ => ErrorCode::Internal,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(Error::NotIndexed("x".into()).code(), ErrorCode::NotIndexed);
+        assert_eq!(Error::EmbeddingTooLarge("x".into()).code(), ErrorCode::ProviderUnavailable);
+        let denied = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "x");
+        assert_eq!(Error::Io(denied).code(), ErrorCode::PathDenied);
+        assert_eq!(Error::Unknown("x".into()).code(), ErrorCode::Internal);
+        assert_eq!(serde_json::to_value(Error::ModelMismatch("x".into()).code()).unwrap(), "MODEL_MISMATCH");
+        assert_eq!(serde_json::to_value(ErrorCode::IndexingInProgress).unwrap(), "INDEXING_IN_PROGRESS");
+    }
+}
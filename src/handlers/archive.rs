@@ -0,0 +1,234 @@
+//! Index export and import handlers
+//!
+//! Handle the export_index and import_index MCP tools: pack a codebase's
+//! stores into one archive (see `crate::archive`), and unpack one built
+//! elsewhere, e.g. by CI, as the index of a local checkout. File paths
+//! recorded under the exporting checkout are moved to the importing one.
+
+use super::{ToolHandlers, ensure_absolute_path, validate_codebase_path};
+use super::describe::format_size;
+use crate::archive::{self, ArchiveManifest, ARCHIVE_FORMAT_VERSION};
+use crate::{Error, ErrorCode, Result};
+use serde::Deserialize;
+use std::path::PathBuf;
+use tracing::info;
+
+#[derive(Debug, Deserialize)]
+pub struct ExportIndexArgs {
+    pub path: String,
+    /// File to write the archive to
+    pub archive_path: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportIndexArgs {
+    pub path: String,
+    /// Archive written by export_index
+    pub archive_path: String,
+}
+
+/// `archive_path` when it is absolute
+fn absolute_archive_path(archive_path: &str) -> std::result::Result<PathBuf, String> {
+    let path = PathBuf::from(archive_path);
+    if path.is_absolute() {
+        Ok(path)
+    } else {
+        Err(format!("archivePath must be an absolute path, got '{archive_path}'"))
+    }
+}
+
+impl ToolHandlers {
+    /// Handle export_index tool call - returns JSON string
+    pub async fn handle_export_index(&self, args: ExportIndexArgs) -> Result<String> {
+        let ExportIndexArgs { path: codebase_path, archive_path } = args;
+
+        let absolute_path = ensure_absolute_path(&codebase_path)?;
+
+        if let Err(e) = validate_codebase_path(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!("{}. Original input: '{}'", e, codebase_path),
+                "code": e.code()
+            }).to_string());
+        }
+        let archive_path = match absolute_archive_path(&archive_path) {
+            Ok(archive_path) => archive_path,
+            Err(e) => return Ok(serde_json::json!({"error": e, "code": ErrorCode::InvalidArgument}).to_string()),
+        };
+
+        // Held for the whole export so no indexing run, vacuum or import
+        // starts writing the stores; other codebases and searches go on
+        let codebase_lock = self.codebase_lock(&absolute_path).await;
+        let _exporting = codebase_lock.lock().await;
+        let snapshot = self.snapshot_manager.lock().await;
+
+        if snapshot.is_indexing(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!(
+                    "Codebase '{}' is being indexed in the background. Export it once indexing completes.",
+                    absolute_path.display()
+                ),
+                "code": ErrorCode::IndexingInProgress
+            }).to_string());
+        }
+
+        if !snapshot.is_indexed(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!(
+                    "Codebase '{}' is not indexed. Please index it first using the index_codebase tool.",
+                    absolute_path.display()
+                ),
+                "code": ErrorCode::NotIndexed
+            }).to_string());
+        }
+
+        let settings = {
+            let metadata_store = self.get_metadata_store(&absolute_path).await?;
+            let store = metadata_store.lock().await;
+            store.flush()?;
+            store.index_settings()?
+        };
+        let manifest = ArchiveManifest {
+            format_version: ARCHIVE_FORMAT_VERSION,
+            codebase_path: absolute_path.clone(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            embedding_provider: settings.as_ref().map(|settings| settings.embedding_provider.clone()),
+            embedding_model: settings.as_ref().map(|settings| settings.embedding_model.clone()),
+            info: snapshot.info(&absolute_path),
+            files: Vec::new(),
+        };
+        drop(snapshot);
+
+        let data_dir = self.config.storage.data_dir.clone();
+        let (target, codebase) = (archive_path.clone(), absolute_path.clone());
+        let manifest = tokio::task::spawn_blocking(move || archive::export(&target, &codebase, &data_dir, manifest))
+            .await
+            .map_err(|e| Error::Io(std::io::Error::other(e)))??;
+
+        let message = format!(
+            "Exported the index of '{}' to '{}' ({} files, {}). Import it with import_index.",
+            absolute_path.display(),
+            archive_path.display(),
+            manifest.files.len(),
+            format_size(manifest.total_bytes())
+        );
+        info!("[EXPORT] {}", message);
+
+        Ok(serde_json::json!({
+            "message": message,
+            "archive_path": archive_path,
+            "files": manifest.files.len(),
+            "bytes": manifest.total_bytes()
+        }).to_string())
+    }
+
+    /// Handle import_index tool call - returns JSON string
+    pub async fn handle_import_index(&self, args: ImportIndexArgs) -> Result<String> {
+        let ImportIndexArgs { path: codebase_path, archive_path } = args;
+
+        let absolute_path = ensure_absolute_path(&codebase_path)?;
+
+        if let Err(e) = validate_codebase_path(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!("{}. Original input: '{}'", e, codebase_path),
+                "code": e.code()
+            }).to_string());
+        }
+        let archive_path = match absolute_archive_path(&archive_path) {
+            Ok(archive_path) => archive_path,
+            Err(e) => return Ok(serde_json::json!({"error": e, "code": ErrorCode::InvalidArgument}).to_string()),
+        };
+
+        let manifest = match archive::read_manifest(&archive_path) {
+            Ok(manifest) => manifest,
+            Err(e) => return Ok(serde_json::json!({"error": e.to_string(), "code": e.code()}).to_string()),
+        };
+        if let Some(error) = self.archive_model_mismatch(&manifest) {
+            return Ok(serde_json::json!({"error": error, "code": ErrorCode::ModelMismatch}).to_string());
+        }
+
+        // Waits for a vacuum of the index to finish
+        let codebase_lock = self.codebase_lock(&absolute_path).await;
+        let _vacuum_done = codebase_lock.lock().await;
+        let mut snapshot = self.snapshot_manager.lock().await;
+
+        if snapshot.is_indexing(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!(
+                    "Codebase '{}' is being indexed in the background. Import once indexing completes.",
+                    absolute_path.display()
+                ),
+                "code": ErrorCode::IndexingInProgress
+            }).to_string());
+        }
+
+        // Cached stores keep the old files open and their state in memory
+        let path_key = absolute_path.to_string_lossy().to_string();
+        self.metadata_stores.lock().await.remove(&path_key);
+        self.synchronizers.lock().await.remove(&path_key);
+        self.forget_vector_readers(&absolute_path).await;
+
+        let data_dir = self.config.storage.data_dir.clone();
+        let (source, codebase) = (archive_path.clone(), absolute_path.clone());
+        let imported = tokio::task::spawn_blocking(move || archive::import(&source, &codebase, &data_dir))
+            .await
+            .map_err(|e| Error::Io(std::io::Error::other(e)))?;
+        let manifest = match imported {
+            Ok(manifest) => manifest,
+            Err(e) => return Ok(serde_json::json!({"error": e.to_string(), "code": e.code()}).to_string()),
+        };
+
+        if manifest.codebase_path != absolute_path {
+            let metadata_store = self.get_metadata_store(&absolute_path).await?;
+            let moved = metadata_store
+                .lock()
+                .await
+                .rebase_paths(&manifest.codebase_path, &absolute_path)?;
+            info!("[IMPORT] Moved {} chunks from {} to {}", moved, manifest.codebase_path.display(), absolute_path.display());
+        }
+
+        match manifest.info.clone() {
+            Some(info) => snapshot.set_info(&absolute_path, info),
+            None => snapshot.set_failed(
+                &absolute_path,
+                "Imported an archive without index status; run index_codebase to complete it".to_string(),
+                None,
+            )?,
+        }
+        snapshot.save()?;
+        drop(snapshot);
+
+        let message = format!(
+            "Imported the index of '{}' built at '{}' on {} ({} files, {}). Run index_codebase to pick up changes made since.",
+            absolute_path.display(),
+            manifest.codebase_path.display(),
+            manifest.created_at,
+            manifest.files.len(),
+            format_size(manifest.total_bytes())
+        );
+        info!("[IMPORT] {}", message);
+
+        Ok(serde_json::json!({
+            "message": message,
+            "source_path": manifest.codebase_path,
+            "created_at": manifest.created_at,
+            "files": manifest.files.len(),
+            "bytes": manifest.total_bytes()
+        }).to_string())
+    }
+
+    /// Why the archive's vectors cannot be searched here: their model is
+    /// neither the configured provider nor one of the extra providers
+    fn archive_model_mismatch(&self, manifest: &ArchiveManifest) -> Option<String> {
+        let (provider, model) = (manifest.embedding_provider.as_deref()?, manifest.embedding_model.as_deref()?);
+        let configured = provider == self.embedding.provider_name() && model == self.embedding_model();
+        if configured || self.extra_embedding(provider, model).is_some() {
+            return None;
+        }
+        Some(format!(
+            "The archive was embedded with {} '{}', which is not configured; searches could not use it. \
+             Configure that model, or index the codebase locally.",
+            provider, model
+        ))
+    }
+}
+
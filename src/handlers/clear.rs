@@ -1,6 +1,9 @@
 
 use super::{ToolHandlers, ensure_absolute_path, validate_codebase_path};
-use crate::Result;
+use crate::{ErrorCode, Result};
+use crate::snapshot::CodebaseInfo;
+use crate::trash::Trash;
+use crate::vectordb::USearchDatabase;
 use serde::Deserialize;
 use tracing::{info, error};
 
@@ -26,10 +29,14 @@ impl ToolHandlers {
         let absolute_path = ensure_absolute_path(&codebase_path)?;
         if let Err(e) = validate_codebase_path(&absolute_path) {
             return Ok(serde_json::json!({
-                "error": format!("{}. Original input: '{}'", e, codebase_path)
+                "error": format!("{}. Original input: '{}'", e, codebase_path),
+                "code": e.code()
             }).to_string());
         }
 
+        // Waits for an export or vacuum reading the stores to finish
+        let codebase_lock = self.codebase_lock(&absolute_path).await;
+        let _cleared = codebase_lock.lock().await;
         let (is_indexed, is_indexing) = {
             let snapshot = self.snapshot_manager.lock().await;
             (
@@ -43,50 +50,73 @@ impl ToolHandlers {
                 "error": format!(
                     "Codebase '{}' is not indexed or being indexed.",
                     absolute_path.display()
-                )
+                ),
+                "code": ErrorCode::NotIndexed
             }).to_string());
         }
 
         info!("[CLEAR] Clearing codebase: {}", absolute_path.display());
 
-        match self.get_vector_db(&absolute_path) {
-            Ok(mut db) => {
-                if let Err(e) = db.delete_index(&absolute_path).await {
+        // Searches must not keep serving the removed index
+        self.forget_vector_readers(&absolute_path).await;
+        let trash_retention = self.config.storage.trash_retention();
+        let mut restorable_until = None;
+        if trash_retention.is_zero() {
+            // Clearing must not depend on the index matching the configured model
+            match USearchDatabase::remove_for_codebase(&absolute_path, &self.config.storage.data_dir) {
+                Ok(()) => {
+                    info!("[CLEAR] Successfully cleared vector index for: {}", absolute_path.display());
+                }
+                Err(e) => {
                     let error_msg = format!("Failed to clear vector index for {}: {}", absolute_path.display(), e);
                     error!("[CLEAR] {}", error_msg);
-                    return Ok(serde_json::json!({"error": error_msg}).to_string());
+                    return Ok(serde_json::json!({"error": error_msg, "code": e.code()}).to_string());
                 }
-                info!("[CLEAR] Successfully cleared vector index for: {}", absolute_path.display());
-            }
-            Err(e) => {
-                let error_msg = format!("Failed to get vector database for {}: {}", absolute_path.display(), e);
-                error!("[CLEAR] {}", error_msg);
-                return Ok(serde_json::json!({"error": error_msg}).to_string());
             }
-        }
 
-        match self.get_bm25_search(&absolute_path) {
-            Ok(mut search) => {
-                if let Err(e) = search.delete_index(&absolute_path).await {
-                    let error_msg = format!("Failed to clear BM25 index for {}: {}", absolute_path.display(), e);
+            match self.get_bm25_search(&absolute_path) {
+                Ok(mut search) => {
+                    if let Err(e) = search.delete_index(&absolute_path).await {
+                        let error_msg = format!("Failed to clear BM25 index for {}: {}", absolute_path.display(), e);
+                        error!("[CLEAR] {}", error_msg);
+                        return Ok(serde_json::json!({"error": error_msg, "code": e.code()}).to_string());
+                    }
+                    info!("[CLEAR] Successfully cleared BM25 index for: {}", absolute_path.display());
+                }
+                Err(e) => {
+                    let error_msg = format!("Failed to get BM25 search for {}: {}", absolute_path.display(), e);
                     error!("[CLEAR] {}", error_msg);
-                    return Ok(serde_json::json!({"error": error_msg}).to_string());
+                    return Ok(serde_json::json!({"error": error_msg, "code": e.code()}).to_string());
                 }
-                info!("[CLEAR] Successfully cleared BM25 index for: {}", absolute_path.display());
             }
-            Err(e) => {
-                let error_msg = format!("Failed to get BM25 search for {}: {}", absolute_path.display(), e);
-                error!("[CLEAR] {}", error_msg);
-                return Ok(serde_json::json!({"error": error_msg}).to_string());
-            }
-        }
         
-        match crate::metadata::MetadataStore::delete_for_codebase(&absolute_path, &self.config.storage.data_dir) {
-            Ok(_) => {
-                info!("[CLEAR] Successfully cleared metadata for: {}", absolute_path.display());
+            match crate::metadata::MetadataStore::delete_for_codebase(&absolute_path, &self.config.storage.data_dir) {
+                Ok(_) => {
+                    info!("[CLEAR] Successfully cleared metadata for: {}", absolute_path.display());
+                }
+                Err(e) => {
+                    tracing::warn!("[CLEAR] Failed to clear metadata (non-critical): {}", e);
+                }
             }
-            Err(e) => {
-                tracing::warn!("[CLEAR] Failed to clear metadata (non-critical): {}", e);
+        } else {
+            // The cached store keeps the metadata DB open; drop it before moving the files
+            self.metadata_stores.lock().await.remove(&absolute_path.to_string_lossy().to_string());
+            let info = self.snapshot_manager.lock().await.info(&absolute_path)
+                .filter(|info| matches!(info, CodebaseInfo::Indexed { .. }));
+
+            let trash = Trash::new(&self.config.storage.data_dir, trash_retention);
+            match trash.move_codebase(&absolute_path, info) {
+                Ok(entry) => {
+                    restorable_until = Some(entry.deleted_at.saturating_add(trash_retention.as_secs()));
+                }
+                Err(e) => {
+                    let error_msg = format!("Failed to move index of {} to the trash: {}", absolute_path.display(), e);
+                    error!("[CLEAR] {}", error_msg);
+                    return Ok(serde_json::json!({"error": error_msg, "code": e.code()}).to_string());
+                }
+            }
+            if let Err(e) = trash.purge_expired() {
+                tracing::warn!("[CLEAR] Failed to purge expired trash entries: {}", e);
             }
         }
 
@@ -108,6 +138,13 @@ impl ToolHandlers {
 
         let mut result_text = format!("Successfully cleared codebase '{}'", absolute_path.display());
 
+        let restorable_until = restorable_until
+            .and_then(|secs| chrono::DateTime::from_timestamp(secs as i64, 0))
+            .map(|time| time.to_rfc3339());
+        if let Some(until) = &restorable_until {
+            result_text.push_str(&format!("\nThe index can be brought back with restore_index until {until}"));
+        }
+
         if remaining_indexed > 0 || remaining_indexing > 0 {
             result_text.push_str(&format!(
                 "\n{remaining_indexed} other indexed codebase(s) and {remaining_indexing} indexing codebase(s) remain"
@@ -117,7 +154,8 @@ impl ToolHandlers {
         Ok(serde_json::json!({
             "message": result_text,
             "remaining_indexed": remaining_indexed,
-            "remaining_indexing": remaining_indexing
+            "remaining_indexing": remaining_indexing,
+            "restorable_until": restorable_until
         }).to_string())
     }
 }
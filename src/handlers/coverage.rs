@@ -0,0 +1,280 @@
+//! Coverage check handler
+//!
+//! Handles the coverage_check MCP tool: estimates whether a query can be
+//! answered from the index by matching its words against indexed file paths
+//! and comparing the index with the working tree, so an agent can decide
+//! between searching right away and re-indexing first.
+
+use super::{ToolHandlers, ensure_absolute_path, validate_codebase_path};
+use crate::Result;
+use crate::search::path_tokens;
+use crate::snapshot::CodebaseStatus;
+use crate::sync::FileSynchronizer;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+
+/// Query words shorter than this are too unspecific to match paths
+const MIN_WORD_LEN: usize = 3;
+
+const MAX_LISTED_DIRECTORIES: usize = 10;
+
+const MAX_LISTED_FILES: usize = 10;
+
+/// Words that appear in questions but say nothing about where code lives
+const QUERY_STOPWORDS: &[&str] = &[
+    "the", "and", "for", "how", "what", "where", "which", "does", "with", "from",
+    "that", "this", "code", "function", "method", "find", "when", "into", "are",
+];
+
+#[derive(Debug, Deserialize)]
+pub struct CoverageCheckArgs {
+    pub path: String,
+    pub query: String,
+}
+
+impl ToolHandlers {
+    /// Handle coverage_check tool call - returns JSON string
+    pub async fn handle_coverage_check(&self, args: CoverageCheckArgs) -> Result<String> {
+        let CoverageCheckArgs { path: codebase_path, query } = args;
+
+        let absolute_path = ensure_absolute_path(&codebase_path)?;
+
+        if let Err(e) = validate_codebase_path(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!("{}. Original input: '{}'", e, codebase_path),
+                "code": e.code()
+            }).to_string());
+        }
+
+        let status = self.snapshot_manager.lock().await.get_status(&absolute_path);
+        let status_line = match &status {
+            CodebaseStatus::Indexed(info) => {
+                format!("Index: {} files, {} chunks", info.indexed_files, info.total_chunks)
+            }
+            CodebaseStatus::Indexing(info) => {
+                format!("Index: indexing in progress ({:.0}%), results may be incomplete", info.indexing_percentage)
+            }
+            CodebaseStatus::IndexFailed(info) => format!("Index: last run failed ({})", info.error_message),
+            CodebaseStatus::NotFound => {
+                return Ok(serde_json::json!({
+                    "message": format!(
+                        "Codebase '{}' is not indexed. Run index_codebase before searching.",
+                        absolute_path.display()
+                    ),
+                    "recommendation": "index"
+                }).to_string());
+            }
+        };
+
+        let words = query_words(&query);
+
+        let indexed_files: Vec<String> = {
+            let metadata_store = self.get_metadata_store(&absolute_path).await?;
+            let store = metadata_store.lock().await;
+            let files: HashSet<String> = store.iter().map(|(_, metadata)| metadata.relative_path).collect();
+            files.into_iter().collect()
+        };
+        let coverage = PathCoverage::measure(&words, &indexed_files);
+
+        // Compared without recording, so the next incremental index still sees the changes
+        let pending = if FileSynchronizer::get_snapshot_path(&absolute_path, &self.config.storage.data_dir).exists() {
+            let ignore_patterns = self.stored_ignore_patterns(&absolute_path).await;
+            let additional_roots = self.stored_additional_roots(&absolute_path).await;
+            let sync = self.get_or_create_synchronizer(&absolute_path, &ignore_patterns, &additional_roots).await?;
+            let changes = sync.lock().await.pending_changes().await?;
+            Some(changes)
+        } else {
+            None
+        };
+
+        let mut message = format!("Coverage of '{}' for query \"{}\"\n{}", absolute_path.display(), query, status_line);
+
+        if words.is_empty() {
+            message.push_str("\nPaths: the query has no words specific enough to match file paths");
+        } else {
+            message.push_str(&format!(
+                "\nPaths: {} indexed files mention {} of the query words ({})",
+                coverage.matching_files,
+                words.len() - coverage.unmatched_words.len(),
+                words.join(", ")
+            ));
+            if !coverage.unmatched_words.is_empty() {
+                message.push_str(&format!(
+                    "\nNo indexed path mentions: {}",
+                    coverage.unmatched_words.join(", ")
+                ));
+            }
+            if !coverage.directories.is_empty() {
+                message.push_str("\nBest matching directories:");
+                for (directory, files) in coverage.directories.iter().take(MAX_LISTED_DIRECTORIES) {
+                    let name = if directory.is_empty() { "." } else { directory.as_str() };
+                    message.push_str(&format!("\n- {name} ({files} files)"));
+                }
+            }
+        }
+
+        let mut unindexed_matches = Vec::new();
+        let pending_count = match &pending {
+            Some(changes) => {
+                let count = changes.added.len() + changes.modified.len() + changes.removed.len();
+                if count == 0 {
+                    message.push_str("\nFreshness: the index matches the working tree");
+                } else {
+                    message.push_str(&format!(
+                        "\nFreshness: {} files changed since the last index ({} added, {} modified, {} removed)",
+                        count,
+                        changes.added.len(),
+                        changes.modified.len(),
+                        changes.removed.len()
+                    ));
+                }
+                unindexed_matches = changes
+                    .added
+                    .iter()
+                    .chain(&changes.modified)
+                    .filter(|path| matched_words(&words, path) > 0)
+                    .cloned()
+                    .collect();
+                unindexed_matches.sort();
+                if !unindexed_matches.is_empty() {
+                    message.push_str("\nChanged files matching the query that the index does not reflect yet:");
+                    for path in unindexed_matches.iter().take(MAX_LISTED_FILES) {
+                        message.push_str(&format!("\n- {path}"));
+                    }
+                }
+                Some(count)
+            }
+            None => {
+                message.push_str("\nFreshness: unknown, no change detection snapshot for this codebase");
+                None
+            }
+        };
+
+        let recommendation = match &status {
+            CodebaseStatus::Indexing(_) => "wait",
+            CodebaseStatus::IndexFailed(_) => "reindex",
+            _ if !unindexed_matches.is_empty() => "reindex",
+            _ if !words.is_empty() && coverage.matching_files == 0 && pending_count.unwrap_or(0) > 0 => "reindex",
+            _ => "search",
+        };
+        message.push_str(&format!("\nRecommendation: {}", match recommendation {
+            "wait" => "wait for indexing to finish, or search knowing results may be incomplete",
+            "reindex" => "run index_codebase (incremental) before searching",
+            _ if coverage.matching_files == 0 && !words.is_empty() => {
+                "search; no paths match, so rely on content matches"
+            }
+            _ => "search",
+        }));
+
+        Ok(serde_json::json!({
+            "message": message,
+            "recommendation": recommendation,
+            "matching_files": coverage.matching_files,
+            "pending_changes": pending_count
+        }).to_string())
+    }
+}
+
+/// Indexed files whose paths mention the query's words
+struct PathCoverage {
+    matching_files: usize,
+    unmatched_words: Vec<String>,
+    /// Directories with matching files, best first: more words matched, then more files
+    directories: Vec<(String, usize)>,
+}
+
+impl PathCoverage {
+    fn measure(words: &[String], files: &[String]) -> Self {
+        let mut matched: HashSet<&str> = HashSet::new();
+        let mut directories: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        let mut matching_files = 0;
+
+        for file in files {
+            let tokens = path_tokens(file);
+            let file_words: Vec<&str> = words
+                .iter()
+                .filter(|word| tokens.split(' ').any(|token| word_matches(word, token)))
+                .map(String::as_str)
+                .collect();
+            if file_words.is_empty() {
+                continue;
+            }
+
+            matching_files += 1;
+            matched.extend(&file_words);
+            let directory = file.rsplit_once('/').map_or("", |(directory, _)| directory);
+            let entry = directories.entry(directory.to_string()).or_default();
+            entry.0 = entry.0.max(file_words.len());
+            entry.1 += 1;
+        }
+
+        let mut directories: Vec<(String, (usize, usize))> = directories.into_iter().collect();
+        directories.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        Self {
+            matching_files,
+            unmatched_words: words.iter().filter(|w| !matched.contains(w.as_str())).cloned().collect(),
+            directories: directories.into_iter().map(|(directory, (_, files))| (directory, files)).collect(),
+        }
+    }
+}
+
+/// Distinct lowercase words of the query that could name a file or directory
+fn query_words(query: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    for word in path_tokens(query).split(' ') {
+        if word.chars().count() >= MIN_WORD_LEN
+            && !QUERY_STOPWORDS.contains(&word)
+            && !words.iter().any(|w| w == word)
+        {
+            words.push(word.to_string());
+        }
+    }
+    words
+}
+
+fn matched_words(words: &[String], path: &str) -> usize {
+    let tokens = path_tokens(path);
+    words
+        .iter()
+        .filter(|word| tokens.split(' ').any(|token| word_matches(word, token)))
+        .count()
+}
+
+/// Prefix match both ways, so `controller` and `controllers` match each other
+fn word_matches(word: &str, token: &str) -> bool {
+    token.len() >= MIN_WORD_LEN && (token.starts_with(word) || word.starts_with(token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_words() {
+        assert_eq!(
+            query_words("Where is the UserController login code?"),
+            vec!["user", "controller", "login"]
+        );
+    }
+
+    #[test]
+    fn test_path_coverage() {
+        let words = query_words("user controller payment");
+        let files: Vec<String> = [
+            "app/controllers/user.py",
+            "app/controllers/admin.py",
+            "app/models/user.py",
+            "README.md",
+        ]
+        .iter()
+        .map(|f| f.to_string())
+        .collect();
+
+        let coverage = PathCoverage::measure(&words, &files);
+        assert_eq!(coverage.matching_files, 3);
+        assert_eq!(coverage.unmatched_words, vec!["payment"]);
+        assert_eq!(coverage.directories[0], ("app/controllers".to_string(), 2));
+        assert_eq!(matched_words(&words, "src/UserController.ts"), 2);
+    }
+}
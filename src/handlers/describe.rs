@@ -0,0 +1,278 @@
+//! Describe index handler
+//!
+//! Handles the describe_index MCP tool: reports the settings a codebase was
+//! indexed with and where its data lives, for debugging missing results
+
+use super::{ToolHandlers, ensure_absolute_path, validate_codebase_path};
+use crate::{ErrorCode, Result, StoreStats};
+use crate::ast::splitter::CHUNK_ID_VERSION;
+use crate::metadata::MetadataStore;
+use crate::search::BM25Search;
+use crate::snapshot::CodebaseStatus;
+use crate::sync::FileSynchronizer;
+use serde::Deserialize;
+
+const MAX_LISTED_FAILURES: usize = 10;
+const MAX_LISTED_PACKAGES: usize = 30;
+const MAX_LISTED_PARSE_ERRORS: usize = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct DescribeIndexArgs {
+    pub path: String,
+}
+
+impl ToolHandlers {
+    /// Handle describe_index tool call - returns JSON string
+    pub async fn handle_describe_index(&self, args: DescribeIndexArgs) -> Result<String> {
+        let DescribeIndexArgs { path: codebase_path } = args;
+
+        let absolute_path = ensure_absolute_path(&codebase_path)?;
+
+        if let Err(e) = validate_codebase_path(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!("{}. Original input: '{}'", e, codebase_path),
+                "code": e.code()
+            }).to_string());
+        }
+
+        let status_line = {
+            let snapshot = self.snapshot_manager.lock().await;
+            match snapshot.get_status(&absolute_path) {
+                CodebaseStatus::Indexed(info) => format!(
+                    "indexed ({} files, {} chunks, {})",
+                    info.indexed_files, info.total_chunks, info.index_status
+                ),
+                CodebaseStatus::Indexing(info) => {
+                    format!("indexing ({:.1}% done)", info.indexing_percentage)
+                }
+                CodebaseStatus::IndexFailed(info) => format!("failed ({})", info.error_message),
+                CodebaseStatus::NotFound => {
+                    return Ok(serde_json::json!({
+                        "error": format!(
+                            "Codebase '{}' is not indexed. Please index it first using the index_codebase tool.",
+                            absolute_path.display()
+                        ),
+                        "code": ErrorCode::NotIndexed
+                    }).to_string());
+                }
+            }
+        };
+
+        let data_dir = &self.config.storage.data_dir;
+        let mut message = format!("Index for codebase '{}'\nStatus: {}", absolute_path.display(), status_line);
+
+        let (settings, stored_chunks, failed_files, chunk_id_version, input_types, language_stats, worst_parsed, packages, package_stats, profiles) = {
+            let metadata_store = self.get_metadata_store(&absolute_path).await?;
+            let store = metadata_store.lock().await;
+            let packages = store.packages()?;
+            let package_stats = store.package_stats(&packages)?;
+            (
+                store.index_settings()?,
+                store.count(),
+                store.failed_files()?,
+                store.chunk_id_version()?,
+                store.embedding_input_types()?,
+                store.language_stats()?,
+                store.worst_parsed_files(MAX_LISTED_PARSE_ERRORS)?,
+                packages,
+                package_stats,
+                store.search_profiles()?,
+            )
+        };
+
+        match &settings {
+            Some(settings) => {
+                message.push_str(&format!("\nLast full index: {}", settings.indexed_at));
+                message.push_str(&format!(
+                    "\nSplitter: {} (chunk size {}, overlap {})",
+                    settings.splitter, settings.chunk_size, settings.chunk_overlap
+                ));
+                message.push_str(&format!(
+                    "\nExtensions ({}): {}",
+                    settings.extensions.len(),
+                    settings.extensions.join(", ")
+                ));
+                if !settings.additional_roots.is_empty() {
+                    message.push_str(&format!("\nAdditional roots: {}", settings.additional_roots.join(", ")));
+                }
+                if !settings.custom_extensions.is_empty() {
+                    message.push_str(&format!("\nCustom extensions: {}", settings.custom_extensions.join(", ")));
+                }
+                message.push_str(&format!(
+                    "\nIgnore patterns: .gitignore/.ignore files{}",
+                    if settings.ignore_patterns.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" plus {}", settings.ignore_patterns.join(", "))
+                    }
+                ));
+                message.push_str(&format!(
+                    "\nEmbedding: {} '{}' (dimension {})",
+                    settings.embedding_provider, settings.embedding_model, settings.embedding_dimension
+                ));
+                message.push_str(&format!("\nVector metric: {}", settings.vector_metric.as_str()));
+                let usearch = self.config.storage.vector_backend == crate::config::VectorBackend::USearch;
+                if usearch && settings.vector_metric != self.config.storage.vector_metric {
+                    message.push_str(&format!(
+                        " (VECTOR_METRIC is now '{}'; re-index with force=true to apply it)",
+                        self.config.storage.vector_metric.as_str()
+                    ));
+                }
+
+                let builder = self.embedding.provider_for(&settings.embedding_provider, &settings.embedding_model);
+                if builder.is_some() && settings.embedding_model != self.embedding_model() {
+                    message.push_str(&format!(
+                        "\nNote: the fallback chain now embeds with '{}'; searches still query '{}', which built this index.",
+                        self.embedding_model(),
+                        settings.embedding_model
+                    ));
+                } else if self.ensure_index_model(&absolute_path).await.is_err() {
+                    message.push_str(&format!(
+                        "\nWarning: the server now uses '{}' (dimension {}); searches are refused until the codebase is re-indexed with force=true.",
+                        self.embedding_model(),
+                        self.embedding.dimension()
+                    ));
+                }
+            }
+            None => {
+                message.push_str(
+                    "\nIndex settings were not recorded (indexed before describe_index existed, or the first full index has not finished)."
+                );
+            }
+        }
+
+        if !self.config.indexing.ignore_patterns.is_empty() {
+            message.push_str(&format!(
+                "\nChange detection ignore patterns: {}",
+                self.config.indexing.ignore_patterns.join(", ")
+            ));
+        }
+
+        match BM25Search::stored_analyzer(&absolute_path, data_dir) {
+            Some(analyzer) => message.push_str(&format!(
+                "\nKeyword analyzer: {}",
+                crate::search::bm25::describe_analyzer(&analyzer)
+            )),
+            None => message.push_str("\nKeyword analyzer: no keyword index on disk"),
+        }
+
+        message.push_str(&format!("\nStored chunks: {stored_chunks}"));
+        if chunk_id_version < CHUNK_ID_VERSION {
+            message.push_str(&format!(
+                "\nChunk IDs: scheme v{chunk_id_version} (line-based); migrated to v{CHUNK_ID_VERSION} on the next incremental index"
+            ));
+        }
+        if !input_types {
+            message.push_str(
+                "\nEmbeddings: built without query/document modes; re-index with force: true to use them"
+            );
+        }
+
+        if language_stats.is_empty() {
+            message.push_str("\nChunking by language: not recorded (indexed before splitter statistics existed)");
+        } else {
+            message.push_str("\nChunking by language (AST / character fallback):");
+            for (language, stats) in &language_stats {
+                message.push_str(&format!(
+                    "\n  {}: {} / {} ({:.1}% fallback)",
+                    language,
+                    stats.ast_chunks,
+                    stats.fallback_chunks,
+                    stats.fallback_rate() * 100.0
+                ));
+                if language != "unknown" && stats.is_degraded() {
+                    message.push_str(" - the grammar may be failing on this code; search quality is degraded");
+                }
+            }
+        }
+
+        if !worst_parsed.is_empty() {
+            message.push_str("\nFiles with parse errors (share of the file the grammar could not parse):");
+            for split in &worst_parsed {
+                let Some(quality) = split.parse_quality else { continue };
+                message.push_str(&format!(
+                    "\n  {} ({}): {:.1}% in {} error node(s)",
+                    split.relative_path,
+                    split.language,
+                    quality.error_rate() * 100.0,
+                    quality.error_nodes
+                ));
+                if quality.is_degraded() {
+                    message.push_str(" - the bundled grammar may not support this syntax");
+                }
+            }
+        }
+
+        if !packages.is_empty() {
+            message.push_str(&format!("\nWorkspace packages ({}, search one with the package filter):", packages.len()));
+            for package in packages.iter().take(MAX_LISTED_PACKAGES) {
+                let stats = package_stats.get(&package.path).copied().unwrap_or_default();
+                message.push_str(&format!(
+                    "\n  {} ({}, {}): {} files, {} chunks",
+                    package.name,
+                    if package.path.is_empty() { "." } else { &package.path },
+                    package.kind,
+                    stats.files,
+                    stats.chunks
+                ));
+            }
+            if packages.len() > MAX_LISTED_PACKAGES {
+                message.push_str(&format!("\n  ... and {} more", packages.len() - MAX_LISTED_PACKAGES));
+            }
+        }
+
+        if !profiles.is_empty() {
+            message.push_str(&format!("\nSearch profiles ({}, apply one with find_code's profile argument):", profiles.len()));
+            for (name, profile) in &profiles {
+                message.push_str(&format!("\n  {}: {}", name, serde_json::to_string(profile)?));
+            }
+        }
+
+        if !failed_files.is_empty() {
+            message.push_str(&format!(
+                "\nFailed files ({}, re-index them with retry_failed_files):",
+                failed_files.len()
+            ));
+            for failure in failed_files.iter().take(MAX_LISTED_FAILURES) {
+                message.push_str(&format!("\n  {}: {}", failure.relative_path, failure.error));
+            }
+            if failed_files.len() > MAX_LISTED_FAILURES {
+                message.push_str(&format!("\n  ... and {} more", failed_files.len() - MAX_LISTED_FAILURES));
+            }
+        }
+
+        message.push_str("\n\nStorage:");
+        for (label, path) in [
+            ("Vectors", self.vector_store_path(&absolute_path)),
+            ("Keyword index", BM25Search::get_index_path_for_codebase(&absolute_path, data_dir)),
+            ("Metadata", MetadataStore::get_db_path_for_codebase(&absolute_path, data_dir)),
+            ("Change snapshot", FileSynchronizer::get_snapshot_path(&absolute_path, data_dir)),
+        ] {
+            let size = if path.exists() {
+                format_size(StoreStats::on_disk(&path).bytes)
+            } else {
+                "missing".to_string()
+            };
+            message.push_str(&format!("\n  {}: {} ({})", label, path.display(), size));
+        }
+
+        Ok(serde_json::json!({
+            "message": message
+        }).to_string())
+    }
+}
+
+pub(super) fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
@@ -0,0 +1,312 @@
+//! Self-test handler
+//!
+//! Handles the doctor MCP tool and command: checks the embedding provider,
+//! the data directory and each storage engine with a throwaway probe index,
+//! and reports every failure with a hint on how to fix it.
+
+use super::ToolHandlers;
+use crate::metadata::MetadataStore;
+use crate::search::BM25Document;
+use crate::types::{ChunkMetadata, CodeChunk};
+use crate::vectordb::{USearchDatabase, VectorDatabase, VectorDocument};
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// Snippets of the smoke test; the query is about the first one
+const PROBE_DOCUMENTS: [(&str, &str); 2] = [
+    ("doctor-probe-config", "fn parse_config(path: &Path) -> Config {\n    // Read the configuration file and parse its settings\n    toml::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()\n}"),
+    ("doctor-probe-email", "fn send_email(to: &str, body: &str) {\n    // Deliver a message to the recipient over SMTP\n    smtp::connect().send(to, body);\n}"),
+];
+const PROBE_QUERY: &str = "read and parse the configuration file";
+const PROBE_KEYWORD: &str = "parse_config";
+
+#[derive(Debug, Default, Deserialize)]
+pub struct DoctorArgs {}
+
+#[derive(Debug, Serialize)]
+struct Check {
+    name: &'static str,
+    ok: bool,
+    detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, ok: true, detail: detail.into(), hint: None }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self { name, ok: false, detail: detail.into(), hint: Some(hint.into()) }
+    }
+}
+
+impl ToolHandlers {
+    /// Handle doctor tool call - returns JSON string
+    pub async fn handle_doctor(&self, _args: DoctorArgs) -> Result<String> {
+        info!("[DOCTOR] Running self-test");
+        let data_dir = self.config.storage.data_dir.clone();
+        // Never a real codebase: the stores are keyed by a hash of this path
+        let probe_path = data_dir.join(".doctor-probe");
+
+        let mut checks = Vec::new();
+        let embeddings = self.check_embedding(&mut checks).await;
+        checks.push(check_data_dir(&data_dir));
+        checks.push(check_metadata_store(&probe_path, &data_dir));
+        checks.push(self.check_fulltext_index(&probe_path));
+        match &embeddings {
+            Some((documents, query)) => {
+                checks.push(self.check_vector_index(&probe_path, documents, query).await)
+            }
+            None => checks.push(Check::fail(
+                "vector_index",
+                "Skipped: no embeddings to store",
+                "Fix the embedding check first",
+            )),
+        }
+        cleanup_probe(&probe_path, &data_dir);
+
+        let failed: Vec<&str> = checks.iter().filter(|check| !check.ok).map(|check| check.name).collect();
+        let mut message = format!(
+            "Code Sage self-test with {} model '{}' (data dir {})",
+            self.embedding.provider_name(),
+            self.embedding_model(),
+            data_dir.display()
+        );
+        for check in &checks {
+            message.push_str(&format!(
+                "\n{} {}: {}",
+                if check.ok { "[ok]  " } else { "[FAIL]" },
+                check.name,
+                check.detail
+            ));
+            if let Some(hint) = &check.hint {
+                message.push_str(&format!("\n       Fix: {hint}"));
+            }
+        }
+        if failed.is_empty() {
+            message.push_str("\nAll checks passed.");
+        } else {
+            warn!("[DOCTOR] Failed checks: {}", failed.join(", "));
+            message.push_str(&format!("\n{} of {} checks failed.", failed.len(), checks.len()));
+        }
+
+        Ok(serde_json::json!({
+            "message": message,
+            "ok": failed.is_empty(),
+            "checks": checks
+        }).to_string())
+    }
+
+    /// Embed the probe snippets and query, checking the vectors' dimension
+    async fn check_embedding(&self, checks: &mut Vec<Check>) -> Option<(Vec<Vec<f32>>, Vec<f32>)> {
+        let expected = self.embedding.dimension();
+        let texts: Vec<String> = PROBE_DOCUMENTS.iter().map(|(_, content)| content.to_string()).collect();
+        let started = Instant::now();
+
+        let embedded = async {
+            let documents = self.embedding.embed_documents(&texts).await?;
+            let query = self.embedding.embed_query(PROBE_QUERY).await?;
+            Ok::<_, crate::Error>((documents, query))
+        };
+        let (documents, query) = match embedded.await {
+            Ok(embedded) => embedded,
+            Err(e) => {
+                checks.push(Check::fail(
+                    "embedding",
+                    format!("{} is unreachable or rejected the request: {}", self.embedding.provider_name(), e),
+                    "Check EMBEDDING_PROVIDER, EMBEDDING_MODEL, the API key and base URL, and that the server is running",
+                ));
+                return None;
+            }
+        };
+
+        if documents.len() != texts.len() {
+            checks.push(Check::fail(
+                "embedding",
+                format!("Asked for {} embeddings, got {}", texts.len(), documents.len()),
+                "The server does not follow the provider's batch API; check the base URL points at an embedding endpoint",
+            ));
+            return None;
+        }
+        if let Some(actual) = documents.iter().chain([&query]).map(Vec::len).find(|&len| len != expected) {
+            checks.push(Check::fail(
+                "embedding",
+                format!("Model returned {actual}-dimensional vectors, but {expected} are configured"),
+                "Set EMBEDDING_DIMENSIONS to the model's output size, or unset it for models that cannot shorten embeddings",
+            ));
+            return None;
+        }
+        if documents.iter().chain([&query]).any(|vector| vector.iter().all(|&v| v == 0.0) || vector.iter().any(|v| !v.is_finite())) {
+            checks.push(Check::fail(
+                "embedding",
+                "Model returned zero or non-finite vectors",
+                "Check EMBEDDING_MODEL names an embedding model rather than a chat model",
+            ));
+            return None;
+        }
+
+        checks.push(Check::pass(
+            "embedding",
+            format!("{} returned {}-dimensional vectors in {} ms", self.embedding.provider_name(), expected, started.elapsed().as_millis()),
+        ));
+        Some((documents, query))
+    }
+
+    /// Tantivy round trip: insert, reopen, count and keyword search
+    fn check_fulltext_index(&self, probe_path: &Path) -> Check {
+        let hint = "Check free disk space and that no other process holds the full-text index; delete the data dir's fulltext folder if it is corrupted";
+        let round_trip = || -> Result<Option<String>> {
+            {
+                let mut bm25 = self.get_bm25_search(probe_path)?;
+                bm25.insert(PROBE_DOCUMENTS.iter().enumerate().map(|(i, (id, content))| BM25Document {
+                    id: id.to_string(),
+                    content: content.to_string(),
+                    file_path: format!("probe_{i}.rs"),
+                    start_line: 1,
+                    end_line: 4,
+                    symbols: crate::search::symbol_tokens(content),
+                }).collect())?;
+            }
+            let bm25 = self.get_bm25_search(probe_path)?;
+            let count = bm25.count()?;
+            let top = bm25.search(PROBE_KEYWORD, PROBE_DOCUMENTS.len())?.into_iter().next().map(|result| result.id);
+            Ok(match (count, top) {
+                (count, _) if count != PROBE_DOCUMENTS.len() => Some(format!(
+                    "Reopened index holds {count} documents instead of {}", PROBE_DOCUMENTS.len()
+                )),
+                (_, Some(id)) if id == PROBE_DOCUMENTS[0].0 => None,
+                (_, top) => Some(format!("Keyword search for '{PROBE_KEYWORD}' returned {top:?}")),
+            })
+        };
+        match round_trip() {
+            Ok(None) => Check::pass("fulltext_index", "Tantivy index written, reopened and searched"),
+            Ok(Some(problem)) => Check::fail("fulltext_index", problem, hint),
+            Err(e) => Check::fail("fulltext_index", e.to_string(), hint),
+        }
+    }
+
+    /// usearch round trip plus the semantic smoke test: the query must rank
+    /// the snippet it describes first
+    async fn check_vector_index(&self, probe_path: &Path, documents: &[Vec<f32>], query: &[f32]) -> Check {
+        let hint = "Check free disk space and memory; delete the data dir's vectors folder if it is corrupted";
+        let round_trip = async {
+            {
+                let mut db = self.get_vector_db(probe_path).await?;
+                db.insert(PROBE_DOCUMENTS.iter().zip(documents).map(|((id, _), vector)| VectorDocument {
+                    id: id.to_string(),
+                    vector: vector.clone(),
+                }).collect()).await?;
+                db.save().await?;
+            }
+            let db = self.get_vector_db(probe_path).await?;
+            let count = db.count().await?;
+            let top = db.search(query, PROBE_DOCUMENTS.len()).await?.into_iter().next().map(|result| result.id);
+            Ok::<_, crate::Error>((count, top))
+        };
+        match round_trip.await {
+            Ok((count, _)) if count != PROBE_DOCUMENTS.len() => Check::fail(
+                "vector_index",
+                format!("Reopened index holds {count} vectors instead of {}", PROBE_DOCUMENTS.len()),
+                hint,
+            ),
+            Ok((_, Some(id))) if id == PROBE_DOCUMENTS[0].0 => {
+                Check::pass("vector_index", "usearch index written, reopened, and the smoke query found its snippet")
+            }
+            Ok((_, top)) => Check::fail(
+                "vector_index",
+                format!("Smoke query '{PROBE_QUERY}' ranked {top:?} first"),
+                "The model does not rank an obviously related snippet first; use a code or general-purpose embedding model",
+            ),
+            Err(e) => Check::fail("vector_index", e.to_string(), hint),
+        }
+    }
+}
+
+/// Write, read back and delete a file in the data dir
+fn check_data_dir(data_dir: &Path) -> Check {
+    let probe = data_dir.join(".doctor-write-test");
+    let round_trip = || -> std::io::Result<bool> {
+        std::fs::create_dir_all(data_dir)?;
+        std::fs::write(&probe, b"code-sage")?;
+        let read = std::fs::read(&probe)?;
+        std::fs::remove_file(&probe)?;
+        Ok(read == b"code-sage")
+    };
+    match round_trip() {
+        Ok(true) => Check::pass("data_dir", format!("{} is writable", data_dir.display())),
+        Ok(false) => Check::fail(
+            "data_dir",
+            format!("{} returned different bytes than were written", data_dir.display()),
+            "The filesystem is unreliable; point DATA_DIR at a local disk",
+        ),
+        Err(e) => Check::fail(
+            "data_dir",
+            format!("{} is not writable: {}", data_dir.display(), e),
+            "Fix the directory's permissions or point DATA_DIR at a writable directory",
+        ),
+    }
+}
+
+/// sled round trip: insert, close, reopen and read back
+fn check_metadata_store(probe_path: &Path, data_dir: &Path) -> Check {
+    let (id, content) = PROBE_DOCUMENTS[0];
+    let chunk = CodeChunk {
+        id: id.to_string(),
+        content: content.to_string(),
+        file_path: PathBuf::from("probe.rs"),
+        relative_path: "probe.rs".to_string(),
+        start_line: 1,
+        end_line: 4,
+        language: "rust".to_string(),
+        metadata: ChunkMetadata {
+            file_extension: ".rs".to_string(),
+            chunk_index: 0,
+            hash: String::new(),
+            modified_at: None,
+            file_hash: None,
+            sync_generation: None,
+        },
+    };
+    let round_trip = || -> Result<bool> {
+        MetadataStore::for_codebase(probe_path, data_dir)?.insert_batch(std::slice::from_ref(&chunk))?;
+        let stored = MetadataStore::for_codebase(probe_path, data_dir)?.get(id)?;
+        Ok(stored.is_some_and(|stored| stored.content == content))
+    };
+    match round_trip() {
+        Ok(true) => Check::pass("metadata_store", "sled store written, reopened and read back"),
+        Ok(false) => Check::fail(
+            "metadata_store",
+            "Reopened store lost the probe record",
+            "Check free disk space; sled needs a filesystem that supports file locks",
+        ),
+        Err(e) => Check::fail(
+            "metadata_store",
+            e.to_string(),
+            "Check free disk space and that no other code-sage process holds the store open",
+        ),
+    }
+}
+
+/// Remove every probe store; failures only leave a small orphan behind
+fn cleanup_probe(probe_path: &Path, data_dir: &Path) {
+    let results = [
+        USearchDatabase::remove_for_codebase(probe_path, data_dir),
+        MetadataStore::delete_for_codebase(probe_path, data_dir),
+        remove_dir(&crate::search::BM25Search::get_index_path_for_codebase(probe_path, data_dir)),
+    ];
+    for e in results.into_iter().filter_map(|result| result.err()) {
+        warn!("[DOCTOR] Failed to remove probe index: {}", e);
+    }
+}
+
+fn remove_dir(dir: &Path) -> Result<()> {
+    if dir.exists() {
+        std::fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
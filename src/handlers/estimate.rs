@@ -0,0 +1,208 @@
+//! Index estimate handler
+//!
+//! Handles the estimate_index MCP tool: chunks a codebase with the real
+//! splitter without storing anything, and estimates the tokens, embedding
+//! cost and time a full index would take with the configured provider.
+
+use super::{ToolHandlers, ensure_absolute_path, validate_codebase_path};
+use crate::ast::CodeChunker;
+use crate::embeddings::{batching, pricing};
+use crate::Result;
+use serde::Deserialize;
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// Chunks embedded to measure the provider's throughput
+const SAMPLE_CHUNKS: usize = 8;
+
+#[derive(Debug, Deserialize)]
+pub struct EstimateIndexArgs {
+    pub path: String,
+    #[serde(default)]
+    pub custom_extensions: Vec<String>,
+    #[serde(default)]
+    pub ignore_patterns: Vec<String>,
+    /// Embed a few chunks to measure throughput, at the cost of their tokens
+    #[serde(default = "default_sample")]
+    pub sample: bool,
+}
+
+fn default_sample() -> bool {
+    true
+}
+
+impl ToolHandlers {
+    /// Handle estimate_index tool call - returns JSON string
+    pub async fn handle_estimate_index(&self, args: EstimateIndexArgs) -> Result<String> {
+        let EstimateIndexArgs { path: codebase_path, custom_extensions, ignore_patterns, sample } = args;
+
+        let absolute_path = ensure_absolute_path(&codebase_path)?;
+
+        if let Err(e) = validate_codebase_path(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!("{}. Original input: '{}'", e, codebase_path),
+                "code": e.code()
+            }).to_string());
+        }
+
+        info!("[ESTIMATE] Estimating index of {}", absolute_path.display());
+
+        let additional_roots = self.stored_additional_roots(&absolute_path).await;
+        let files = self.scan_codebase(&absolute_path, &custom_extensions, &ignore_patterns, &additional_roots).await?;
+        let chunker = CodeChunker::new(self.config.indexing.chunk_size, self.config.indexing.chunk_overlap);
+        let cache_model = self.embedding_cache_model(true);
+
+        let mut skipped_files = 0;
+        let mut chunks = 0;
+        let mut tokens = 0;
+        let mut cached_chunks = 0;
+        let mut chunks_to_embed = 0;
+        let mut tokens_to_embed = 0;
+        let mut samples = Vec::new();
+        for file_path in &files {
+            let file_chunks = match self.process_file(file_path, &absolute_path, &chunker, 0).await {
+                Ok((file_chunks, Some(_))) => file_chunks,
+                Ok((_, None)) => {
+                    skipped_files += 1;
+                    continue;
+                }
+                Err(e) => {
+                    warn!("[ESTIMATE] Failed to process file {}: {}", file_path.display(), e);
+                    skipped_files += 1;
+                    continue;
+                }
+            };
+
+            let texts: Vec<String> = file_chunks.iter().map(|chunk| self.embedding_input(chunk)).collect();
+            let cached = match &self.embedding_cache {
+                Some(cache) => cache.get_many(&cache_model, &texts).unwrap_or_else(|_| vec![None; texts.len()]),
+                None => vec![None; texts.len()],
+            };
+
+            for (text, hit) in texts.into_iter().zip(cached) {
+                let text_tokens = batching::count_tokens(&text);
+                chunks += 1;
+                tokens += text_tokens;
+                if hit.is_some() {
+                    cached_chunks += 1;
+                    continue;
+                }
+                chunks_to_embed += 1;
+                tokens_to_embed += text_tokens;
+                if samples.len() < SAMPLE_CHUNKS {
+                    samples.push(text);
+                }
+            }
+        }
+
+        let provider = self.embedding.provider_name().to_string();
+        let model = self.embedding_model().to_string();
+        // A configured price is for the primary model, not for a fallback standing in for it
+        let price = self
+            .config
+            .embedding
+            .price_per_million_tokens
+            .filter(|_| model == self.config.embedding.model)
+            .or_else(|| pricing::price_per_million_tokens(&provider, &model));
+        let cost = price.map(|price| tokens_to_embed as f64 / 1_000_000.0 * price);
+
+        let throughput = if sample && !samples.is_empty() {
+            let sample_tokens: usize = samples.iter().map(|text| batching::count_tokens(text)).sum();
+            let started = Instant::now();
+            match self.embedding.embed_documents(&samples).await {
+                Ok(_) => Some(sample_tokens as f64 / started.elapsed().as_secs_f64().max(0.001)),
+                Err(e) => {
+                    warn!("[ESTIMATE] Sample embedding failed: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let seconds = self.embedding_seconds(chunks_to_embed, tokens_to_embed, throughput);
+
+        let mut message = format!(
+            "Index estimate for '{}' with {} model '{}'\nFiles: {} ({} skipped: unreadable or over 1MB)\nChunks: {} ({} tokens)",
+            absolute_path.display(),
+            provider,
+            model,
+            files.len() - skipped_files,
+            skipped_files,
+            chunks,
+            tokens
+        );
+        if cached_chunks > 0 {
+            message.push_str(&format!(
+                "\nAlready in the embedding cache: {} chunks; {} chunks ({} tokens) need embedding",
+                cached_chunks, chunks_to_embed, tokens_to_embed
+            ));
+        }
+        match (cost, price) {
+            (Some(cost), Some(price)) if price > 0.0 => {
+                message.push_str(&format!("\nEstimated embedding cost: ${cost:.2} (at ${price} per million tokens)"));
+            }
+            (Some(_), _) => message.push_str("\nEstimated embedding cost: none (self-hosted model)"),
+            _ => message.push_str(
+                "\nEstimated embedding cost: unknown price for this model; set EMBEDDING_PRICE_PER_MILLION_TOKENS",
+            ),
+        }
+        match seconds {
+            Some(seconds) => {
+                message.push_str(&format!("\nEstimated embedding time: {}", format_duration(seconds)));
+                if throughput.is_none() {
+                    message.push_str(" (from the configured rate limits; pass sample to measure the provider)");
+                }
+            }
+            None => message.push_str("\nEstimated embedding time: unknown (pass sample to measure the provider)"),
+        }
+        if self.config.indexing.low_priority {
+            message.push_str("\nINDEX_LOW_PRIORITY is on: indexing sends one batch at a time and pauses between batches");
+        }
+
+        Ok(serde_json::json!({
+            "message": message,
+            "files": files.len() - skipped_files,
+            "skipped_files": skipped_files,
+            "chunks": chunks,
+            "tokens": tokens,
+            "cached_chunks": cached_chunks,
+            "tokens_to_embed": tokens_to_embed,
+            "price_per_million_tokens": price,
+            "estimated_cost_usd": cost,
+            "measured_tokens_per_second": throughput,
+            "estimated_seconds": seconds
+        }).to_string())
+    }
+
+    /// Wall time to embed `tokens` in `chunks` chunks, from the measured
+    /// single-request throughput and the configured parallelism and rate limits
+    fn embedding_seconds(&self, chunks: usize, tokens: usize, throughput: Option<f64>) -> Option<f64> {
+        let indexing = &self.config.indexing;
+        let parallel = if indexing.low_priority {
+            1
+        } else {
+            indexing.parallel_batches.min(indexing.embedding_concurrency).max(1)
+        };
+
+        let mut seconds = throughput.map(|throughput| tokens as f64 / (throughput * parallel as f64));
+        if let Some(tokens_per_minute) = indexing.tokens_per_minute.filter(|&limit| limit > 0) {
+            let limited = tokens as f64 / tokens_per_minute as f64 * 60.0;
+            seconds = Some(seconds.map_or(limited, |seconds| seconds.max(limited)));
+        }
+        if let Some(requests_per_minute) = indexing.requests_per_minute.filter(|&limit| limit > 0) {
+            let batch_items = indexing.batch_size.unwrap_or_else(|| self.embedding.max_batch_items()).max(1);
+            let limited = chunks.div_ceil(batch_items) as f64 / requests_per_minute as f64 * 60.0;
+            seconds = Some(seconds.map_or(limited, |seconds| seconds.max(limited)));
+        }
+        seconds
+    }
+}
+
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    match seconds {
+        0..=59 => format!("{seconds}s"),
+        60..=3_599 => format!("{}m {}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {}m", seconds / 3_600, seconds % 3_600 / 60),
+    }
+}
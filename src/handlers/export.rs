@@ -0,0 +1,273 @@
+//! Export formats for search results
+//!
+//! `find_code` answers with a chat-oriented text blob by default; these
+//! renderers produce documents other tools can consume: a Markdown report,
+//! JSONL records, or a SARIF 2.1.0 log for code-review tooling.
+
+use crate::types::SearchResult;
+use std::path::Path;
+
+/// Rule id reported for every SARIF result
+const SARIF_RULE_ID: &str = "code-sage/search-match";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Markdown,
+    Jsonl,
+    Sarif,
+}
+
+impl OutputFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Sarif => "sarif",
+        }
+    }
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "jsonl" | "ndjson" => Ok(OutputFormat::Jsonl),
+            "sarif" => Ok(OutputFormat::Sarif),
+            other => Err(format!(
+                "Invalid output format '{other}'. Must be 'text', 'markdown', 'jsonl' or 'sarif'."
+            )),
+        }
+    }
+}
+
+/// Markdown report with one section per result
+pub fn render_markdown(results: &[SearchResult], query: &str, codebase_path: &Path) -> String {
+    let mut report = format!(
+        "# Code search: \"{}\"\n\nCodebase: `{}`  \nResults: {}\n",
+        query,
+        codebase_path.display(),
+        results.len()
+    );
+
+    for result in results {
+        report.push_str(&format!(
+            "\n## {}. `{}:{}-{}`\n\n- Language: {}\n- Score: {:.4}\n",
+            result.rank,
+            result.relative_path,
+            result.start_line,
+            result.end_line,
+            result.language,
+            result.score
+        ));
+        if let Some(modified) = format_modified(result.modified_at) {
+            report.push_str(&format!("- Modified: {modified}\n"));
+        }
+        if result.stale {
+            report.push_str("- Note: file changed since indexing; content may be stale\n");
+        }
+
+        // Longer fences than any backtick run in the snippet keep it intact
+        let fence = "`".repeat(longest_backtick_run(&result.content).max(2) + 1);
+        report.push_str(&format!(
+            "\n{}{}\n{}\n{}\n",
+            fence,
+            result.language,
+            result.content.trim_end(),
+            fence
+        ));
+    }
+
+    report
+}
+
+/// One JSON object per line, one line per result
+pub fn render_jsonl(results: &[SearchResult]) -> String {
+    results
+        .iter()
+        .map(|result| {
+            serde_json::json!({
+                "rank": result.rank,
+                "score": result.score,
+                "path": result.relative_path,
+                "start_line": result.start_line,
+                "end_line": result.end_line,
+                "language": result.language,
+                "modified_at": result.modified_at,
+                "stale": result.stale,
+                "content": result.content,
+            })
+            .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// SARIF 2.1.0 log with one `note`-level result per match
+pub fn render_sarif(results: &[SearchResult], query: &str, codebase_path: &Path) -> String {
+    let sarif_results: Vec<serde_json::Value> = results
+        .iter()
+        .map(|result| {
+            serde_json::json!({
+                "ruleId": SARIF_RULE_ID,
+                "level": "note",
+                "message": {
+                    "text": format!("Match #{} for query \"{}\"", result.rank, query)
+                },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": {
+                            "uri": encode_uri_path(&result.relative_path.replace('\\', "/")),
+                            "uriBaseId": "SRCROOT"
+                        },
+                        "region": {
+                            "startLine": result.start_line.max(1),
+                            "endLine": result.end_line.max(result.start_line).max(1),
+                            "snippet": { "text": result.content }
+                        }
+                    }
+                }],
+                "properties": {
+                    "rank": result.rank,
+                    "score": result.score,
+                    "language": result.language
+                }
+            })
+        })
+        .collect();
+
+    let root_uri = format!(
+        "file://{}/",
+        encode_uri_path(codebase_path.to_string_lossy().replace('\\', "/").trim_end_matches('/'))
+    );
+
+    let log = serde_json::json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": [{
+                        "id": SARIF_RULE_ID,
+                        "shortDescription": { "text": "Code matching a search query" }
+                    }]
+                }
+            },
+            "originalUriBaseIds": {
+                "SRCROOT": { "uri": root_uri }
+            },
+            "results": sarif_results
+        }]
+    });
+
+    serde_json::to_string_pretty(&log).unwrap_or_else(|_| log.to_string())
+}
+
+/// `path` with every byte but unreserved characters and `/` percent-encoded,
+/// so spaces, `#` and `?` in file names stay part of the URI path
+fn encode_uri_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~' | b'/') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+pub(crate) fn format_modified(modified_at: Option<u64>) -> Option<String> {
+    modified_at
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs as i64, 0))
+        .map(|dt| dt.format("%Y-%m-%d %H:%M UTC").to_string())
+}
+
+fn longest_backtick_run(content: &str) -> usize {
+    content
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn result(rank: usize, path: &str, content: &str) -> SearchResult {
+        SearchResult {
+            file_path: PathBuf::from("/repo").join(path),
+            relative_path: path.to_string(),
+            start_line: 3,
+            end_line: 5,
+            content: content.to_string(),
+            language: "rust".to_string(),
+            score: 0.5,
+            rank,
+            modified_at: None,
+            file_hash: None,
+            stale: false,
+        }
+    }
+
+    #[test]
+    fn test_jsonl_has_one_record_per_line() {
+        let results = vec![result(1, "src/a.rs", "fn a() {}"), result(2, "src/b.rs", "fn b() {}")];
+        let lines: Vec<serde_json::Value> = render_jsonl(&results)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["path"], "src/b.rs");
+        assert_eq!(lines[1]["start_line"], 3);
+    }
+
+    #[test]
+    fn test_sarif_locations() {
+        let results = vec![result(1, "src/a.rs", "fn a() {}")];
+        let log: serde_json::Value =
+            serde_json::from_str(&render_sarif(&results, "token refresh", Path::new("/repo"))).unwrap();
+
+        assert_eq!(log["version"], "2.1.0");
+        let run = &log["runs"][0];
+        assert_eq!(run["originalUriBaseIds"]["SRCROOT"]["uri"], "file:///repo/");
+        let location = &run["results"][0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/a.rs");
+        assert_eq!(location["region"]["startLine"], 3);
+        assert_eq!(location["region"]["endLine"], 5);
+
+        let results = vec![result(1, "docs/release notes#2.md", "# 2.0")];
+        let log: serde_json::Value =
+            serde_json::from_str(&render_sarif(&results, "release", Path::new("/my repo"))).unwrap();
+        let run = &log["runs"][0];
+        assert_eq!(run["originalUriBaseIds"]["SRCROOT"]["uri"], "file:///my%20repo/");
+        let location = &run["results"][0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "docs/release%20notes%232.md");
+    }
+
+    #[test]
+    fn test_markdown_fence_survives_backticks() {
+        let results = vec![result(1, "README.md", "```sh\ncargo build\n```")];
+        let report = render_markdown(&results, "build", Path::new("/repo"));
+
+        assert!(report.contains("## 1. `README.md:3-5`"));
+        assert!(report.contains("````rust\n```sh"));
+    }
+
+    #[test]
+    fn test_parse_output_format() {
+        assert_eq!("SARIF".parse::<OutputFormat>(), Ok(OutputFormat::Sarif));
+        assert_eq!("md".parse::<OutputFormat>(), Ok(OutputFormat::Markdown));
+        assert!("xml".parse::<OutputFormat>().is_err());
+    }
+}
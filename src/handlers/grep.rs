@@ -0,0 +1,270 @@
+//! Code grep handler
+//!
+//! Handles the grep_code MCP tool: the exact pattern lookup embedding search
+//! cannot serve. Scans every indexed file for a literal string or regex, like
+//! ripgrep over the files the index knows, and reports `file:line` matches.
+//! Files that can no longer be read are scanned in the chunk content the
+//! metadata store kept for them.
+
+use super::{ToolHandlers, ensure_absolute_path, validate_codebase_path};
+use super::search::validate_extension_filter;
+use crate::{Error, ErrorCode, Result};
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+#[derive(Debug, Deserialize)]
+pub struct GrepCodeArgs {
+    pub path: String,
+    pub pattern: String,
+    /// Read `pattern` as a regex instead of a literal string
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default = "default_case_sensitive")]
+    pub case_sensitive: bool,
+    #[serde(default)]
+    pub extension_filter: Vec<String>,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+fn default_case_sensitive() -> bool {
+    true
+}
+
+fn default_limit() -> usize {
+    100
+}
+
+/// Most matches one call returns, whatever its limit
+const MAX_LIMIT: usize = 1000;
+
+/// Longest line text returned with a match
+const MAX_LINE_CHARS: usize = 300;
+
+/// Compiled patterns beyond this size are rejected rather than built
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+struct GrepMatch {
+    relative_path: String,
+    line: usize,
+    text: String,
+}
+
+#[derive(Default)]
+struct GrepOutcome {
+    matches: Vec<GrepMatch>,
+    total_matches: usize,
+    files_matched: usize,
+    /// Relative paths of files scanned in their stored chunks
+    from_index: Vec<String>,
+}
+
+impl ToolHandlers {
+    /// Handle grep_code tool call - returns JSON string
+    pub async fn handle_grep_code(&self, args: GrepCodeArgs) -> Result<String> {
+        let GrepCodeArgs {
+            path: codebase_path,
+            pattern,
+            regex,
+            case_sensitive,
+            extension_filter,
+            limit,
+        } = args;
+
+        if pattern.is_empty() {
+            return Ok(serde_json::json!({
+                "error": "Pattern must not be empty.",
+                "code": ErrorCode::InvalidArgument
+            }).to_string());
+        }
+        let source = if regex { pattern.clone() } else { regex::escape(&pattern) };
+        let matcher = match RegexBuilder::new(&source)
+            .case_insensitive(!case_sensitive)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .build()
+        {
+            Ok(matcher) => matcher,
+            Err(e) => {
+                return Ok(serde_json::json!({
+                    "error": format!("Invalid regex '{}': {}", pattern, e),
+                    "code": ErrorCode::InvalidArgument
+                }).to_string());
+            }
+        };
+        if let Err(message) = validate_extension_filter(&extension_filter) {
+            return Ok(serde_json::json!({
+                "error": message,
+                "code": ErrorCode::InvalidArgument
+            }).to_string());
+        }
+
+        let result_limit = limit.clamp(1, MAX_LIMIT);
+
+        let absolute_path = ensure_absolute_path(&codebase_path)?;
+
+        if let Err(e) = validate_codebase_path(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!("{}. Original input: '{}'", e, codebase_path),
+                "code": e.code()
+            }).to_string());
+        }
+
+        {
+            let snapshot = self.snapshot_manager.lock().await;
+            if !snapshot.is_indexed(&absolute_path) && !snapshot.is_indexing(&absolute_path) {
+                return Ok(serde_json::json!({
+                    "error": format!(
+                        "Codebase '{}' is not indexed. Please index it first using the index_codebase tool.",
+                        absolute_path.display()
+                    ),
+                    "code": ErrorCode::NotIndexed
+                }).to_string());
+            }
+        }
+
+        info!("[GREP] Searching {} for {} '{}'", absolute_path.display(), if regex { "regex" } else { "literal" }, pattern);
+
+        let metadata_store = self.get_metadata_store(&absolute_path).await?;
+        let files: BTreeMap<String, PathBuf> = metadata_store
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, metadata)| has_extension(&metadata.file_path, &extension_filter))
+            .map(|(_, metadata)| (metadata.relative_path, metadata.file_path))
+            .collect();
+        let files_scanned = files.len();
+
+        let scan_matcher = matcher.clone();
+        let mut outcome = tokio::task::spawn_blocking(move || grep_files(&files, &scan_matcher, result_limit))
+            .await
+            .map_err(|e| Error::Io(std::io::Error::other(e)))?;
+
+        // Deleted or unreadable since indexing: the stored chunks still hold their content
+        if !outcome.from_index.is_empty() {
+            let missing: BTreeSet<&String> = outcome.from_index.iter().collect();
+            let mut stored: BTreeMap<String, BTreeMap<usize, String>> = BTreeMap::new();
+            for (_, metadata) in metadata_store.lock().await.iter() {
+                if !missing.contains(&metadata.relative_path) {
+                    continue;
+                }
+                // Overlapping chunks hold some lines twice
+                let lines = stored.entry(metadata.relative_path.clone()).or_default();
+                for (offset, line) in metadata.content.lines().enumerate() {
+                    lines.entry(metadata.start_line + offset).or_insert_with(|| line.to_string());
+                }
+            }
+            for (relative_path, lines) in stored {
+                let mut matched = false;
+                for (line, text) in lines {
+                    if matcher.is_match(&text) {
+                        matched = true;
+                        outcome.push(&relative_path, line, &text, result_limit);
+                    }
+                }
+                outcome.files_matched += usize::from(matched);
+            }
+            outcome.matches.sort_by(|a, b| a.relative_path.cmp(&b.relative_path).then(a.line.cmp(&b.line)));
+            outcome.matches.truncate(result_limit);
+        }
+
+        if outcome.total_matches == 0 {
+            return Ok(serde_json::json!({
+                "message": format!(
+                    "No matches for '{}' in {} indexed files of codebase '{}'",
+                    pattern,
+                    files_scanned,
+                    absolute_path.display()
+                ),
+                "results_count": 0
+            }).to_string());
+        }
+
+        let formatted = outcome
+            .matches
+            .iter()
+            .map(|m| format!("{}:{}: {}", m.relative_path, m.line, m.text))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut message = format!(
+            "Found {} matches for '{}' in {} of {} indexed files of codebase '{}'",
+            outcome.total_matches,
+            pattern,
+            outcome.files_matched,
+            files_scanned,
+            absolute_path.display()
+        );
+        if outcome.total_matches > outcome.matches.len() {
+            message.push_str(&format!(" (showing first {})", outcome.matches.len()));
+        }
+        if !outcome.from_index.is_empty() {
+            message.push_str(&format!(
+                "\nNote: {} files could not be read and were searched as last indexed",
+                outcome.from_index.len()
+            ));
+        }
+        message.push_str("\n\n");
+        message.push_str(&formatted);
+
+        Ok(serde_json::json!({
+            "message": message,
+            "results_count": outcome.total_matches,
+            "files_matched": outcome.files_matched
+        }).to_string())
+    }
+}
+
+impl GrepOutcome {
+    /// Count a match, keeping it while under `limit`
+    fn push(&mut self, relative_path: &str, line: usize, text: &str, limit: usize) {
+        self.total_matches += 1;
+        if self.matches.len() < limit {
+            self.matches.push(GrepMatch {
+                relative_path: relative_path.to_string(),
+                line,
+                text: truncate_line(text.trim()),
+            });
+        }
+    }
+}
+
+/// Matches of `matcher` in `files`, in path and line order; files that
+/// cannot be read are listed in `from_index` instead
+fn grep_files(files: &BTreeMap<String, PathBuf>, matcher: &Regex, limit: usize) -> GrepOutcome {
+    let mut outcome = GrepOutcome::default();
+    for (relative_path, file_path) in files {
+        let content = match std::fs::read(file_path) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(_) => {
+                outcome.from_index.push(relative_path.clone());
+                continue;
+            }
+        };
+        let mut matched = false;
+        for (index, line) in content.lines().enumerate() {
+            if matcher.is_match(line) {
+                matched = true;
+                outcome.push(relative_path, index + 1, line, limit);
+            }
+        }
+        outcome.files_matched += usize::from(matched);
+    }
+    outcome
+}
+
+fn has_extension(file_path: &Path, extension_filter: &[String]) -> bool {
+    extension_filter.is_empty()
+        || file_path
+            .extension()
+            .is_some_and(|ext| extension_filter.contains(&format!(".{}", ext.to_string_lossy())))
+}
+
+fn truncate_line(line: &str) -> String {
+    match line.char_indices().nth(MAX_LINE_CHARS) {
+        Some((end, _)) => format!("{}...", &line[..end]),
+        None => line.to_string(),
+    }
+}
@@ -0,0 +1,164 @@
+//! Index history handler
+//!
+//! Handles the index_history MCP tool: lists past indexing runs for a codebase
+
+use super::{ToolHandlers, ensure_absolute_path, validate_codebase_path};
+use crate::Result;
+use crate::metadata::IndexRun;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct IndexHistoryArgs {
+    pub path: String,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+fn default_limit() -> usize {
+    20
+}
+
+impl ToolHandlers {
+    /// Handle index_history tool call - returns JSON string
+    pub async fn handle_index_history(&self, args: IndexHistoryArgs) -> Result<String> {
+        let IndexHistoryArgs { path: codebase_path, limit } = args;
+
+        let absolute_path = ensure_absolute_path(&codebase_path)?;
+
+        if let Err(e) = validate_codebase_path(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!("{}. Original input: '{}'", e, codebase_path),
+                "code": e.code()
+            }).to_string());
+        }
+
+        if !crate::metadata::MetadataStore::exists(&absolute_path, &self.config.storage.data_dir) {
+            return Ok(serde_json::json!({
+                "message": format!("No indexing runs recorded for codebase '{}'", absolute_path.display())
+            }).to_string());
+        }
+
+        let runs = {
+            let metadata_store = self.get_metadata_store(&absolute_path).await?;
+            let store = metadata_store.lock().await;
+            store.index_history(limit.clamp(1, 200))?
+        };
+
+        if runs.is_empty() {
+            return Ok(serde_json::json!({
+                "message": format!("No indexing runs recorded for codebase '{}'", absolute_path.display())
+            }).to_string());
+        }
+
+        let mut message = format!(
+            "Last {} indexing runs for codebase '{}' (newest first):\n",
+            runs.len(),
+            absolute_path.display()
+        );
+        for run in &runs {
+            message.push('\n');
+            message.push_str(&format_run(run));
+        }
+
+        if let Some(summary) = full_run_summary(&runs) {
+            message.push_str("\n\n");
+            message.push_str(&summary);
+        }
+
+        Ok(serde_json::json!({
+            "message": message,
+            "results_count": runs.len()
+        }).to_string())
+    }
+}
+
+fn format_run(run: &IndexRun) -> String {
+    let started = chrono::DateTime::parse_from_rfc3339(&run.started_at)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|_| run.started_at.clone());
+
+    let mut line = format!(
+        "- {} | {} | {} | {} files, {} chunks | {:.1}s | {}",
+        started,
+        run.mode,
+        run.status,
+        run.files_processed,
+        run.chunks_processed,
+        run.duration_secs,
+        run.trigger
+    );
+    if let Some(error) = &run.error {
+        line.push_str(&format!("\n  Error: {error}"));
+    }
+    line
+}
+
+/// Compares the latest successful full run with the average of earlier ones
+fn full_run_summary(runs: &[IndexRun]) -> Option<String> {
+    let durations: Vec<f64> = runs
+        .iter()
+        .filter(|run| run.mode == "full" && run.status != "failed")
+        .map(|run| run.duration_secs)
+        .collect();
+
+    let (latest, earlier) = durations.split_first()?;
+    if earlier.is_empty() {
+        return None;
+    }
+
+    let average = earlier.iter().sum::<f64>() / earlier.len() as f64;
+    if average <= 0.0 {
+        return None;
+    }
+
+    Some(format!(
+        "Latest full run took {:.1}s, {:.1}x the average of the {} earlier full runs ({:.1}s)",
+        latest,
+        latest / average,
+        earlier.len(),
+        average
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(mode: &str, status: &str, duration_secs: f64) -> IndexRun {
+        IndexRun {
+            started_at: "2024-05-01T10:00:00+00:00".to_string(),
+            finished_at: "2024-05-01T10:01:00+00:00".to_string(),
+            duration_secs,
+            trigger: "analyze_code".to_string(),
+            mode: mode.to_string(),
+            files_processed: 10,
+            chunks_processed: 100,
+            status: status.to_string(),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_full_run_summary() {
+        let runs = vec![
+            run("full", "completed", 90.0),
+            run("incremental", "completed", 5.0),
+            run("full", "failed", 1.0),
+            run("full", "completed", 30.0),
+            run("full", "completed", 30.0),
+        ];
+        let summary = full_run_summary(&runs).unwrap();
+        assert!(summary.contains("3.0x"), "{summary}");
+
+        assert!(full_run_summary(&runs[..1]).is_none());
+    }
+
+    #[test]
+    fn test_format_run_includes_error() {
+        let mut failed = run("full", "failed", 2.0);
+        failed.error = Some("Embedding error: timeout".to_string());
+        let line = format_run(&failed);
+        assert!(line.starts_with("- 2024-05-01 10:00:00 | full | failed"));
+        assert!(line.contains("Error: Embedding error: timeout"));
+    }
+}
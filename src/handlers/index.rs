@@ -1,9 +1,27 @@
 
 use super::{ToolHandlers, ensure_absolute_path, validate_codebase_path};
-use crate::{Result};
+use crate::branch::checked_out_branch;
+use crate::{Error, ErrorCode, Result};
+use crate::config::{TokenizerKind, VectorMetric, VectorQuantization};
+use crate::embeddings::{batching, validate};
+use crate::retry::Backoff;
+use crate::throttle::Throttle;
+use crate::progress::EmbeddingProgressTracker;
+use crate::search::{path_tokens, BM25Document, BM25Search};
+use crate::search::normalize::normalize_text;
+use crate::sync::ignore_rules::{IgnoreRules, walk_builder};
+use crate::sync::roots::root_prefixes;
+use crate::sync::{content_hash, FileSynchronizer};
 use crate::ast::CodeChunker;
-use crate::types::{IndexStats, CodeChunk};
+use crate::ast::splitter::{CHUNK_ID_VERSION, assign_chunk_ids};
+use crate::types::{IndexStats, CodeChunk, SplitterStats};
+use crate::metadata::{FailedFile, FileSplit, IndexRun, IndexSettings, StoredMetadata};
+use crate::vectordb::{USearchDatabase, VectorDatabase};
+use crate::workspace::detect_packages;
+use futures::stream::{FuturesUnordered, StreamExt};
 use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tracing::{info, warn, error};
@@ -19,12 +37,82 @@ pub struct IndexCodebaseArgs {
     pub custom_extensions: Vec<String>,
     #[serde(default)]
     pub ignore_patterns: Vec<String>,
+    /// BM25 tokenizer ("simple" or "cjk"); defaults to BM25_TOKENIZER
+    #[serde(default)]
+    pub tokenizer: Option<String>,
+    /// Directories indexed and searched together with `path`; the roots of
+    /// the last run are kept when empty
+    #[serde(default)]
+    pub additional_roots: Vec<String>,
+    /// Provider to index with instead of the configured one, by name
+    /// (e.g. "ollama"); must be the configured one or an extra provider
+    #[serde(default)]
+    pub embedding_provider: Option<String>,
+    /// Model of the provider to index with
+    #[serde(default)]
+    pub embedding_model: Option<String>,
 }
 
 fn default_splitter() -> String {
     "ast".to_string()
 }
 
+/// Outcome of a background indexing run, for the history log
+pub(super) struct IndexRunSummary {
+    mode: &'static str,
+    files_processed: usize,
+    chunks_processed: usize,
+    status: String,
+}
+
+/// Chunks per vector insert / BM25 commit; the unit that is retried and, on
+/// persistent failure, skipped
+const STORAGE_BATCH_SIZE: usize = 50;
+
+/// Times a chunk is re-embedded after the provider returned an unusable
+/// vector for it, before it is left out of the index
+const EMBEDDING_REJECTION_RETRIES: usize = 2;
+
+/// Chunks per BM25 commit when migrating chunk IDs
+const MIGRATION_BATCH_SIZE: usize = 1000;
+
+/// Files whose chunks could not be stored during a run
+#[derive(Default)]
+struct StorageFailures {
+    /// Relative path -> first error seen for it
+    files: BTreeMap<String, String>,
+}
+
+impl StorageFailures {
+    fn record(&mut self, chunks: &[CodeChunk], error: &Error) {
+        for chunk in chunks {
+            self.files
+                .entry(chunk.relative_path.clone())
+                .or_insert_with(|| error.to_string());
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    fn to_records(&self) -> Vec<FailedFile> {
+        let failed_at = chrono::Utc::now().to_rfc3339();
+        self.files
+            .iter()
+            .map(|(relative_path, error)| FailedFile {
+                relative_path: relative_path.clone(),
+                error: error.clone(),
+                failed_at: failed_at.clone(),
+            })
+            .collect()
+    }
+}
+
 impl ToolHandlers {
     pub async fn handle_index_codebase(&self, args: IndexCodebaseArgs) -> Result<String> {
         let IndexCodebaseArgs {
@@ -33,22 +121,109 @@ impl ToolHandlers {
             splitter,
             custom_extensions,
             ignore_patterns,
+            tokenizer,
+            additional_roots,
+            embedding_provider,
+            embedding_model,
         } = args;
 
         if splitter != "ast" && splitter != "langchain" {
             return Ok(serde_json::json!({
-                "error": format!("Invalid splitter type '{}'. Must be 'ast' or 'langchain'.", splitter)
+                "error": format!("Invalid splitter type '{}'. Must be 'ast' or 'langchain'.", splitter),
+                "code": ErrorCode::InvalidArgument
             }).to_string());
         }
 
+        let requested_tokenizer = match tokenizer.as_deref().map(str::parse::<TokenizerKind>).transpose() {
+            Ok(tokenizer) => tokenizer,
+            Err(e) => {
+                return Ok(serde_json::json!({ "error": e, "code": ErrorCode::InvalidArgument }).to_string());
+            }
+        };
+
+        if let Err(e) = IgnoreRules::validate(&ignore_patterns) {
+            return Ok(serde_json::json!({ "error": e.to_string(), "code": ErrorCode::InvalidArgument }).to_string());
+        }
+
         let absolute_path = ensure_absolute_path(&codebase_path)?;
         
         if let Err(e) = validate_codebase_path(&absolute_path) {
             return Ok(serde_json::json!({
-                "error": format!("{}. Original input: '{}'", e, codebase_path)
+                "error": format!("{}. Original input: '{}'", e, codebase_path),
+                "code": e.code()
             }).to_string());
         }
 
+        // A branch namespace must not be filled with another branch's files
+        if let Some(branch) = &self.branch {
+            if let Some(checked_out) = checked_out_branch(&absolute_path).filter(|checked_out| checked_out != branch) {
+                return Ok(serde_json::json!({
+                    "error": format!(
+                        "Branch '{}' is checked out at '{}', not '{}'. Check out '{}' (or index its worktree) before indexing it.",
+                        checked_out,
+                        absolute_path.display(),
+                        branch,
+                        branch
+                    ),
+                    "code": ErrorCode::InvalidArgument
+                }).to_string());
+            }
+        }
+
+        let mut extra_roots = Vec::with_capacity(additional_roots.len());
+        for root in &additional_roots {
+            let root_path = ensure_absolute_path(root)?;
+            if let Err(e) = validate_codebase_path(&root_path) {
+                return Ok(serde_json::json!({
+                    "error": format!("{}. Additional root: '{}'", e, root),
+                    "code": e.code()
+                }).to_string());
+            }
+            extra_roots.push(root_path);
+        }
+        if let Err(e) = root_prefixes(&absolute_path, &extra_roots) {
+            return Ok(serde_json::json!({ "error": e.to_string(), "code": e.code() }).to_string());
+        }
+
+        // Codebases stay with the provider that indexed them unless another is requested
+        let (selected, switched) = match self
+            .select_embedding(&absolute_path, embedding_provider.as_deref(), embedding_model.as_deref())
+            .await
+        {
+            Ok(selection) => selection,
+            Err(e) => {
+                return Ok(serde_json::json!({ "error": e, "code": ErrorCode::InvalidArgument }).to_string());
+            }
+        };
+        if switched {
+            info!("[BACKGROUND-INDEX] Another embedding provider was requested for '{}'; rebuilding its index",
+                absolute_path.display());
+        }
+        let force = force || switched;
+
+        let handlers = selected.as_ref().unwrap_or(self);
+        handlers
+            .start_indexing(codebase_path, absolute_path, force, splitter, custom_extensions, ignore_patterns, requested_tokenizer, extra_roots)
+            .await
+    }
+
+    /// Mark the codebase as indexing and start a background run with these
+    /// handlers' provider; the arguments are already validated
+    #[allow(clippy::too_many_arguments)]
+    async fn start_indexing(
+        &self,
+        codebase_path: String,
+        absolute_path: PathBuf,
+        force: bool,
+        splitter: String,
+        custom_extensions: Vec<String>,
+        ignore_patterns: Vec<String>,
+        requested_tokenizer: Option<TokenizerKind>,
+        extra_roots: Vec<PathBuf>,
+    ) -> Result<String> {
+        // Waits for a vacuum of the index to finish
+        let codebase_lock = self.codebase_lock(&absolute_path).await;
+        let _vacuum_done = codebase_lock.lock().await;
         let mut snapshot = self.snapshot_manager.lock().await;
         
         if snapshot.is_indexing(&absolute_path) {
@@ -56,31 +231,66 @@ impl ToolHandlers {
                 "error": format!(
                     "Codebase '{}' is already being indexed in the background. Please wait for completion.",
                     absolute_path.display()
-                )
+                ),
+                "code": ErrorCode::IndexingInProgress
             }).to_string());
         }
 
+        // Vectors of two models cannot be mixed: once a fallback chain has
+        // switched providers, the next run rebuilds the index with the active one
+        let force = if !force && self.built_by_other_provider(&absolute_path).await {
+            info!("[FALLBACK-REINDEX] '{}' was indexed by another provider; rebuilding it with {} '{}'",
+                absolute_path.display(), self.embedding.provider_name(), self.embedding_model());
+            true
+        } else if !force && self.docs_model_changed(&absolute_path).await {
+            info!("[DOCS-REINDEX] '{}' was indexed with another documentation model; rebuilding it",
+                absolute_path.display());
+            true
+        } else if !force && self.embedding_options_changed_for(&absolute_path).await {
+            info!("[EMBEDDING-REINDEX] '{}' was embedded with another EMBEDDING_NORMALIZE or document prefix; rebuilding it",
+                absolute_path.display());
+            true
+        } else if !force && self.embedding_text_outdated(&absolute_path).await {
+            info!("[EMBEDDING-TEXT-REINDEX] '{}' was embedded with an older chunk text layout; rebuilding it",
+                absolute_path.display());
+            true
+        } else if !force && BM25Search::outdated_analyzer(&absolute_path, &self.config.storage.data_dir) {
+            info!("[ANALYZER-REINDEX] '{}' was indexed with an older BM25 analyzer; rebuilding it",
+                absolute_path.display());
+            true
+        } else {
+            force
+        };
+
         let should_try_incremental = !force && snapshot.is_indexed(&absolute_path);
         
+        let stored_tokenizer = BM25Search::stored_analyzer(&absolute_path, &self.config.storage.data_dir)
+            .map(|analyzer| analyzer.tokenizer);
+        
+        if let (false, Some(requested), Some(stored)) = (force, requested_tokenizer, stored_tokenizer) {
+            if requested != stored {
+                return Ok(serde_json::json!({
+                    "error": format!(
+                        "Codebase '{}' was indexed with the '{}' tokenizer. Re-index with force=true to switch to '{}'.",
+                        absolute_path.display(),
+                        stored.as_str(),
+                        requested.as_str()
+                    ),
+                    "code": ErrorCode::InvalidArgument
+                }).to_string());
+            }
+        }
+        
+        // A forced re-index keeps the codebase's tokenizer unless a new one is requested
+        let tokenizer = requested_tokenizer.or(stored_tokenizer);
+        
         if force {
             if snapshot.is_indexed(&absolute_path) {
                 info!("[FORCE-REINDEX] Removing '{}' from indexed list for re-indexing", absolute_path.display());
                 let _ = snapshot.remove_codebase(&absolute_path);
             }
             
-            let mut vector_db = self.get_vector_db(&absolute_path)?;
-            if vector_db.has_index(&absolute_path).await? {
-                info!("[FORCE-REINDEX] Clearing index for '{}'", absolute_path.display());
-                vector_db.delete_index(&absolute_path).await?;
-            }
-            
-            let mut bm25 = self.get_bm25_search(&absolute_path)?;
-            if bm25.has_index(&absolute_path).await? {
-                bm25.delete_index(&absolute_path).await?;
-            }
-            
-            use crate::sync::FileSynchronizer;
-            let _ = FileSynchronizer::delete_snapshot(&absolute_path, &self.config.storage.data_dir).await;
+            self.clear_for_rebuild(&absolute_path).await?;
         }
 
         snapshot.set_indexing(&absolute_path, 0)?;
@@ -106,33 +316,81 @@ impl ToolHandlers {
             String::new()
         };
 
+        let roots_info = if !extra_roots.is_empty() {
+            let roots: Vec<String> = extra_roots.iter().map(|root| root.display().to_string()).collect();
+            format!("\nIncluding {} additional roots: {}", roots.len(), roots.join(", "))
+        } else {
+            String::new()
+        };
+
         let handlers_clone = Arc::new(self.clone());
         let abs_path_clone = absolute_path.clone();
         let splitter_clone = splitter.clone();
         let custom_ext_clone = custom_extensions.clone();
         let ignore_pat_clone = ignore_patterns.clone();
+        let trigger = if force { "analyze_code (force)" } else { "analyze_code" };
+        let attempted_mode = if should_try_incremental { "incremental" } else { "full" };
         
         tokio::spawn(async move {
-            if let Err(e) = handlers_clone.start_background_indexing(
-                abs_path_clone,
-                force,
-                should_try_incremental,
-                splitter_clone,
-                custom_ext_clone,
-                ignore_pat_clone,
-            ).await {
-                error!("[BACKGROUND-INDEX] Indexing failed: {}", e);
+            let (mut force, mut should_try_incremental) = (force, should_try_incremental);
+            let (mut trigger, mut attempted_mode) = (trigger, attempted_mode);
+            let mut embedding_identity = handlers_clone.embedding_identity();
+            loop {
+                let started_at = chrono::Utc::now();
+                let outcome = handlers_clone.start_background_indexing(
+                    abs_path_clone.clone(),
+                    force,
+                    should_try_incremental,
+                    splitter_clone.clone(),
+                    custom_ext_clone.clone(),
+                    ignore_pat_clone.clone(),
+                    tokenizer,
+                    extra_roots.clone(),
+                ).await;
+                
+                if let Err(e) = &outcome {
+                    error!("[BACKGROUND-INDEX] Indexing failed: {}", e);
+                }
+                
+                handlers_clone.record_index_run(&abs_path_clone, trigger, attempted_mode, started_at, &outcome).await;
+                
+                // The fallback chain switched mid-run, so the index may hold
+                // vectors of both models; rebuild it with the new provider
+                let current_identity = handlers_clone.embedding_identity();
+                if current_identity == embedding_identity {
+                    break;
+                }
+                warn!("[BACKGROUND-INDEX] Embedding provider switched to {} during indexing; rebuilding '{}'",
+                    current_identity, abs_path_clone.display());
+                embedding_identity = current_identity;
+                
+                if let Err(e) = handlers_clone.clear_for_rebuild(&abs_path_clone).await {
+                    error!("[BACKGROUND-INDEX] Failed to clear '{}' for the rebuild: {}", abs_path_clone.display(), e);
+                    break;
+                }
+                {
+                    let mut snapshot = handlers_clone.snapshot_manager.lock().await;
+                    let _ = snapshot.set_indexing(&abs_path_clone, 0);
+                    let _ = snapshot.save();
+                }
+                force = true;
+                should_try_incremental = false;
+                trigger = "provider fallback";
+                attempted_mode = "full";
             }
         });
 
         Ok(serde_json::json!({
             "message": format!(
-                "Started background indexing for codebase '{}' using {} splitter.{}{}{}\n\nIndexing is running in the background. You can search the codebase while indexing is in progress, but results may be incomplete until indexing completes.",
+                "Started background indexing for codebase '{}' using {} splitter and {} '{}'.{}{}{}{}\n\nIndexing is running in the background. You can search the codebase while indexing is in progress, but results may be incomplete until indexing completes.",
                 absolute_path.display(),
                 splitter.to_uppercase(),
+                self.embedding.provider_name(),
+                self.embedding_model(),
                 path_info,
                 extension_info,
-                ignore_info
+                ignore_info,
+                roots_info
             )
         }).to_string())
     }
@@ -147,8 +405,19 @@ impl ToolHandlers {
         splitter_type: String,
         custom_extensions: Vec<String>,
         ignore_patterns: Vec<String>,
-    ) -> Result<()> {
+        tokenizer: Option<TokenizerKind>,
+        additional_roots: Vec<PathBuf>,
+    ) -> Result<IndexRunSummary> {
         info!("[BACKGROUND-INDEX] Starting background indexing for: {}", absolute_path.display());
+        self.progress.clear(&absolute_path);
+
+        // Roots are kept across runs unless new ones are given
+        let additional_roots = if additional_roots.is_empty() {
+            self.stored_additional_roots(&absolute_path).await
+        } else {
+            self.set_stored_additional_roots(&absolute_path, &additional_roots).await;
+            additional_roots
+        };
 
         let mut last_save_time = std::time::Instant::now();
 
@@ -167,26 +436,40 @@ impl ToolHandlers {
         if should_try_incremental {
             info!("[BACKGROUND-INDEX] Attempting incremental sync...");
             
-            match self.try_incremental_sync(&absolute_path).await {
+            // Change detection keeps the patterns of the last full index unless new ones are given
+            let sync_patterns = if ignore_patterns.is_empty() {
+                self.stored_ignore_patterns(&absolute_path).await
+            } else {
+                ignore_patterns.clone()
+            };
+            
+            match self.try_incremental_sync(&absolute_path, &sync_patterns, &additional_roots).await {
                 Ok(Some(changes)) if changes.added.is_empty() && changes.removed.is_empty() && changes.modified.is_empty() => {
                     info!("[BACKGROUND-INDEX] No changes detected via incremental sync. Index is up to date.");
                     
                     let mut snapshot = self.snapshot_manager.lock().await;
                     let metadata_store = self.get_metadata_store(&absolute_path).await?;
                     let total_chunks = metadata_store.lock().await.count();
-                    let files = self.scan_codebase(&absolute_path, &custom_extensions, &ignore_patterns).await?;
+                    let files = self.scan_codebase(&absolute_path, &custom_extensions, &ignore_patterns, &additional_roots).await?;
                     let indexed_files = files.len();
+                    let language_stats = self.language_stats(&absolute_path).await;
                     
                     let stats = IndexStats {
                         indexed_files,
                         total_chunks,
                         elapsed_secs: 0.0,
                         index_status: "completed".to_string(),
+                        language_stats,
                     };
                     
                     snapshot.set_indexed(&absolute_path, stats)?;
                     snapshot.save()?;
-                    return Ok(());
+                    return Ok(IndexRunSummary {
+                        mode: "up_to_date",
+                        files_processed: 0,
+                        chunks_processed: 0,
+                        status: "completed".to_string(),
+                    });
                 }
                 Ok(Some(changes)) => {
                     info!("[BACKGROUND-INDEX] Incremental sync detected {} changes ({} added, {} removed, {} modified)",
@@ -196,7 +479,9 @@ impl ToolHandlers {
                         changes.modified.len()
                     );
                     
-                    return self.process_incremental_changes(&absolute_path, changes).await;
+                    let summary = self.process_incremental_changes(&absolute_path, changes).await?;
+                    self.commit_sync_changes(&absolute_path).await?;
+                    return Ok(summary);
                 }
                 Ok(None) => {
                     info!("[BACKGROUND-INDEX] No sync snapshot found. Falling back to full indexing.");
@@ -220,12 +505,19 @@ impl ToolHandlers {
             info!("[BACKGROUND-INDEX] Using custom ignore patterns: {:?}", ignore_patterns);
         }
 
-        let files = self.scan_codebase(&absolute_path, &custom_extensions, &ignore_patterns).await?;
+        if !additional_roots.is_empty() {
+            info!("[BACKGROUND-INDEX] Including additional roots: {:?}", additional_roots);
+        }
+
+        let files = self.scan_codebase(&absolute_path, &custom_extensions, &ignore_patterns, &additional_roots).await?;
         let total_files = files.len();
         
         info!("[BACKGROUND-INDEX] Found {} files to process", total_files);
         let mut all_chunks = Vec::new();
+        let mut file_splits = Vec::new();
         let chunker = CodeChunker::new(self.config.indexing.chunk_size, self.config.indexing.chunk_overlap);
+        let mut throttle = Throttle::new(&self.config.indexing);
+        let sync_generation = self.sync_generation(&absolute_path).await;
 
         for (idx, file_path) in files.iter().enumerate() {
             let progress = ((idx as f32 / total_files as f32) * 30.0) as u8;
@@ -237,15 +529,17 @@ impl ToolHandlers {
                 info!("[BACKGROUND-INDEX] Progress: {:.1}% ({}/{})", progress, idx, total_files);
             }
 
-            match self.process_file(file_path, &absolute_path, &chunker).await {
-                Ok(mut chunks) => {
+            match self.process_file(file_path, &absolute_path, &chunker, sync_generation).await {
+                Ok((mut chunks, split)) => {
                     all_chunks.append(&mut chunks);
+                    file_splits.extend(split);
                 }
                 Err(e) => {
                     warn!("[BACKGROUND-INDEX] Failed to process file {}: {}", file_path.display(), e);
                     continue;
                 }
             }
+            throttle.pause().await;
 
             if all_chunks.len() >= 450_000 {
                 warn!("[BACKGROUND-INDEX] Chunk limit (450,000) reached. Stopping indexing.");
@@ -257,32 +551,30 @@ impl ToolHandlers {
         info!("[BACKGROUND-INDEX] Generated {} chunks from {} files", total_chunks, total_files);
 
         info!("[BACKGROUND-INDEX] Generating embeddings...");
-        let embeddings = self.generate_embeddings_batch(&all_chunks, &absolute_path).await?;
+        let embeddings = self.generate_routed_embeddings(&all_chunks, &absolute_path, true).await?;
         {
             let mut snapshot = self.snapshot_manager.lock().await;
             snapshot.set_indexing(&absolute_path, 60)?;
             snapshot.save()?;
         }
 
-        info!("[BACKGROUND-INDEX] Storing vectors...");
-        {
-            let mut vector_db = self.get_vector_db(&absolute_path)?;
-            vector_db.insert_batch(&absolute_path, &all_chunks, &embeddings).await?;
-            info!("[BACKGROUND-INDEX] Saving vector index...");
-            vector_db.save().await?;
+        info!("[BACKGROUND-INDEX] Storing vectors and building BM25 index...");
+        let mut failures = StorageFailures::default();
+        let (stored_chunks, vector_metric) = {
+            let mut vector_db = match self.get_vector_db(&absolute_path).await {
+                // The damaged index was quarantined; this run rebuilds it from scratch
+                Err(Error::Corrupted(_)) => self.get_vector_db(&absolute_path).await?,
+                result => result?,
+            };
+            vector_db.begin_bulk_load();
+            let mut bm25 = self.get_bm25_search_with_tokenizer(&absolute_path, tokenizer)?;
+            self.get_metadata_store(&absolute_path).await?.lock().await.clear_full_vectors()?;
+            let stored = self
+                .store_chunks(&absolute_path, vector_db.as_mut(), &mut bm25, &all_chunks, &embeddings, &mut failures, true)
+                .await?;
             info!("[BACKGROUND-INDEX] Vector index saved successfully");
-        }
-        {
-            let mut snapshot = self.snapshot_manager.lock().await;
-            snapshot.set_indexing(&absolute_path, 85)?;
-            snapshot.save()?;
-        }
-
-        info!("[BACKGROUND-INDEX] Building BM25 index...");
-        {
-            let mut bm25 = self.get_bm25_search(&absolute_path)?;
-            bm25.insert_batch(&absolute_path, &all_chunks).await?;
-        }
+            (stored, vector_db.metric())
+        };
         {
             let mut snapshot = self.snapshot_manager.lock().await;
             snapshot.set_indexing(&absolute_path, 95)?;
@@ -292,18 +584,67 @@ impl ToolHandlers {
         info!("[BACKGROUND-INDEX] Storing chunk metadata...");
         {
             let metadata_store = self.get_metadata_store(&absolute_path).await?;
-            metadata_store.lock().await.insert_batch(&all_chunks)?;
-            info!("[BACKGROUND-INDEX] Stored metadata for {} chunks", all_chunks.len());
+            let store = metadata_store.lock().await;
+            // Drop records of the previous index, whose chunk IDs may use an older scheme
+            store.clear()?;
+            store.insert_batch(&stored_chunks)?;
+            store.identifier_index()?.insert_batch(&stored_chunks)?;
+            store.set_chunk_id_version(CHUNK_ID_VERSION)?;
+            store.set_embedding_input_types(true)?;
+            store.set_index_settings(&IndexSettings {
+                splitter: "ast".to_string(),
+                chunk_size: self.config.indexing.chunk_size,
+                chunk_overlap: self.config.indexing.chunk_overlap,
+                extensions: self.effective_extensions(&custom_extensions),
+                custom_extensions: custom_extensions.clone(),
+                ignore_patterns: ignore_patterns.clone(),
+                additional_roots: additional_roots
+                    .iter()
+                    .map(|root| root.to_string_lossy().to_string())
+                    .collect(),
+                embedding_provider: self.embedding.provider_name().to_string(),
+                embedding_model: self.embedding_model().to_string(),
+                embedding_dimension: self.embedding.dimension(),
+                docs_embedding_provider: self.docs_identity().map(|(provider, _)| provider),
+                docs_embedding_model: self.docs_identity().map(|(_, model)| model),
+                vector_metric,
+                embedding_normalize: Some(self.config.embedding.normalize),
+                embedding_document_prefix: self.document_prefix().map(str::to_string),
+                embedding_text_version: EMBEDDING_TEXT_VERSION,
+                text_normalization: Some(self.config.search.analyzer.normalization),
+                indexed_at: chrono::Utc::now().to_rfc3339(),
+            })?;
+            store.record_failed_files(&failures.to_records())?;
+            store.record_file_splits(&file_splits)?;
+            let packages = detect_packages(&absolute_path);
+            if !packages.is_empty() {
+                info!("[BACKGROUND-INDEX] Detected {} workspace packages", packages.len());
+            }
+            store.set_packages(&packages)?;
+            info!("[BACKGROUND-INDEX] Stored metadata for {} chunks", stored_chunks.len());
+        }
+        let language_stats = self.language_stats(&absolute_path).await;
+        log_fallback_rates(&language_stats);
+        
+        if !failures.is_empty() {
+            warn!(
+                "[BACKGROUND-INDEX] {} files could not be stored; run retry_failed_files to re-index them",
+                failures.len()
+            );
         }
+        
         let stats = IndexStats {
             indexed_files: total_files,
-            total_chunks,
+            total_chunks: stored_chunks.len(),
             elapsed_secs: 0.0, // TODO: track actual time
             index_status: if all_chunks.len() >= 450_000 {
                 "limit_reached".to_string()
+            } else if !failures.is_empty() {
+                "completed_with_errors".to_string()
             } else {
                 "completed".to_string()
             },
+            language_stats,
         };
 
         {
@@ -318,68 +659,57 @@ impl ToolHandlers {
             stats.total_chunks
         );
 
-        Ok(())
+        Ok(IndexRunSummary {
+            mode: "full",
+            files_processed: stats.indexed_files,
+            chunks_processed: stats.total_chunks,
+            status: stats.index_status,
+        })
     }
 }
 
 impl ToolHandlers {
-    async fn scan_codebase(
+    pub(super) async fn scan_codebase(
         &self, 
         path: &PathBuf,
         custom_extensions: &[String],
         additional_ignore_patterns: &[String],
+        additional_roots: &[PathBuf],
     ) -> Result<Vec<PathBuf>> {
-        use ignore::WalkBuilder;
-        
         let mut files = Vec::new();
         
-        let mut extensions = self.config.indexing.supported_extensions.clone();
-        for ext in custom_extensions {
-            if !ext.starts_with('.') {
-                extensions.push(format!(".{ext}"));
-            } else {
-                extensions.push(ext.clone());
-            }
-        }
+        let extensions = self.effective_extensions(custom_extensions);
 
-        let mut builder = WalkBuilder::new(path);
-        builder
-            .follow_links(false)
-            .git_ignore(true)          // Respect .gitignore
-            .git_global(true)          // Respect global gitignore
-            .git_exclude(true)         // Respect .git/info/exclude
-            .ignore(true)              // Respect .ignore files
-            .hidden(false);            // Don't index hidden files
-        
-        if !additional_ignore_patterns.is_empty() {
-            use ignore::overrides::OverrideBuilder;
-            let mut override_builder = OverrideBuilder::new(path);
-            
-            for pattern in additional_ignore_patterns {
-                let _ = override_builder.add(&format!("!{pattern}"));
-            }
-            
-            if let Ok(overrides) = override_builder.build() {
-                builder.overrides(overrides);
-                info!("[SCAN] Applied {} custom ignore patterns", additional_ignore_patterns.len());
-            }
+        let mut patterns = self.config.indexing.ignore_patterns.clone();
+        patterns.extend_from_slice(additional_ignore_patterns);
+        if !patterns.is_empty() {
+            info!("[SCAN] Applying {} custom ignore patterns", patterns.len());
         }
-        
-        let walker = builder.build();
 
-        for entry in walker {
-            let entry = entry?;
-            
-            if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
-                continue;
-            }
+        // Additional roots are walked through their path relative to the
+        // codebase root, so their files get `../<root>/...` relative paths
+        let mut dirs = vec![path.clone()];
+        dirs.extend(root_prefixes(path, additional_roots)?.into_iter().map(|prefix| path.join(prefix)));
 
-            let file_path = entry.path();
-            
-            if let Some(ext) = file_path.extension() {
-                let ext_str = format!(".{}", ext.to_string_lossy());
-                if extensions.contains(&ext_str) {
-                    files.push(file_path.to_path_buf());
+        for dir in &dirs {
+            let rules = Arc::new(IgnoreRules::new(dir, &patterns));
+            let mut builder = walk_builder(dir);
+            builder.filter_entry(move |entry| rules.allows(entry));
+
+            for entry in builder.build() {
+                let entry = entry?;
+                
+                if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
+                    continue;
+                }
+
+                let file_path = entry.path();
+                
+                if let Some(ext) = file_path.extension() {
+                    let ext_str = format!(".{}", ext.to_string_lossy());
+                    if extensions.contains(&ext_str) {
+                        files.push(file_path.to_path_buf());
+                    }
                 }
             }
         }
@@ -395,16 +725,29 @@ impl ToolHandlers {
         Ok(files)
     }
 
-    async fn process_file(
+    fn effective_extensions(&self, custom_extensions: &[String]) -> Vec<String> {
+        let mut extensions = self.config.indexing.supported_extensions.clone();
+        for ext in custom_extensions {
+            if !ext.starts_with('.') {
+                extensions.push(format!(".{ext}"));
+            } else {
+                extensions.push(ext.clone());
+            }
+        }
+        extensions
+    }
+
+    pub(super) async fn process_file(
         &self,
         file_path: &PathBuf,
         codebase_path: &PathBuf,
         chunker: &CodeChunker,
-    ) -> Result<Vec<CodeChunk>> {
+        sync_generation: u64,
+    ) -> Result<(Vec<CodeChunk>, Option<FileSplit>)> {
         let content = tokio::fs::read_to_string(file_path).await?;
         if content.len() > 1_000_000 {
             warn!("[PROCESS-FILE] Skipping large file (>1MB): {}", file_path.display());
-            return Ok(Vec::new());
+            return Ok((Vec::new(), None));
         }
 
         let language = self.detect_language(file_path)?;
@@ -413,13 +756,29 @@ impl ToolHandlers {
             .to_string_lossy()
             .to_string();
 
-        let chunks = chunker.chunk_code(&content, &language, file_path, &relative_path)?;
+        let (mut chunks, origin, parse_quality) = chunker.chunk_code_with_origin(&content, &language, file_path, &relative_path)?;
+
+        let modified_at = file_modified_at(file_path).await;
+        let file_hash = content_hash(content.as_bytes());
+        for chunk in &mut chunks {
+            chunk.metadata.modified_at = modified_at;
+            chunk.metadata.file_hash = Some(file_hash.clone());
+            chunk.metadata.sync_generation = Some(sync_generation);
+        }
 
         if chunks.len() > 50 {
             info!("[PROCESS-FILE] Large file: {} generated {} chunks", file_path.display(), chunks.len());
         }
 
-        Ok(chunks)
+        let split = FileSplit {
+            relative_path,
+            language,
+            origin,
+            chunks: chunks.len(),
+            parse_quality,
+        };
+
+        Ok((chunks, Some(split)))
     }
 
     fn detect_language(&self, path: &Path) -> Result<String> {
@@ -434,63 +793,300 @@ impl ToolHandlers {
         Ok(language.as_str().to_string())
     }
 
+    /// Text sent to the provider for `chunk`, cut to its input limit
+    pub(super) fn embedding_input(&self, chunk: &CodeChunk) -> String {
+        let text = normalize_text(&embedding_text(chunk), &self.config.search.analyzer.normalization);
+        batching::truncate_to_tokens(&text, self.embedding.max_input_tokens()).into_owned()
+    }
+
+    /// Embeddings of `chunks` in order, documentation chunks from the
+    /// documentation model when one is configured
+    async fn generate_routed_embeddings(
+        &self,
+        chunks: &[CodeChunk],
+        absolute_path: &Path,
+        input_types: bool,
+    ) -> Result<Vec<Option<Vec<f32>>>> {
+        let Some(docs) = &self.docs_embedding else {
+            return self.generate_embeddings_batch(chunks, absolute_path, input_types).await;
+        };
+        let (docs_positions, code_positions): (Vec<usize>, Vec<usize>) =
+            (0..chunks.len()).partition(|&i| chunks[i].is_documentation());
+        if docs_positions.is_empty() {
+            return self.generate_embeddings_batch(chunks, absolute_path, input_types).await;
+        }
+
+        let select = |positions: &[usize]| -> Vec<CodeChunk> { positions.iter().map(|&i| chunks[i].clone()).collect() };
+        let code_embeddings = self.generate_embeddings_batch(&select(&code_positions), absolute_path, input_types).await?;
+        info!("[EMBEDDINGS] Embedding {} documentation chunks with {} '{}'",
+            docs_positions.len(), docs.provider.provider_name(), docs.config.model);
+        let docs_embeddings = self
+            .with_embedding(docs)
+            .generate_embeddings_batch(&select(&docs_positions), absolute_path, input_types)
+            .await?;
+
+        let mut embeddings = vec![None; chunks.len()];
+        for (positions, generated) in [(code_positions, code_embeddings), (docs_positions, docs_embeddings)] {
+            for (i, embedding) in positions.into_iter().zip(generated) {
+                embeddings[i] = embedding;
+            }
+        }
+        Ok(embeddings)
+    }
+
+    /// Embeddings of `chunks` in order; `None` for chunks the provider kept
+    /// returning unusable vectors for, which are left out of the index
     async fn generate_embeddings_batch(
         &self,
         chunks: &[CodeChunk],
         absolute_path: &Path,
-    ) -> Result<Vec<Vec<f32>>> {
-        // Use batch size of 16 (matching claude-context default behavior)
-        let batch_size = 16;
-        let mut all_embeddings = Vec::new();
-        let total_batches = chunks.len().div_ceil(batch_size);
+        input_types: bool,
+    ) -> Result<Vec<Option<Vec<f32>>>> {
+        let max_items = self
+            .config
+            .indexing
+            .batch_size
+            .unwrap_or_else(|| self.embedding.max_batch_items())
+            .max(1);
+        let mut sizer = batching::BatchSizer::new(
+            self.config.indexing.batch_tokens.unwrap_or_else(|| self.embedding.max_batch_tokens()),
+        );
+
+        let all_texts: Vec<String> = chunks.iter().map(|c| self.embedding_input(c)).collect();
+
+        // Identical chunks already embedded for any codebase are reused
+        let cache_model = self.embedding_cache_model(input_types);
+        let cached: Vec<Option<Vec<f32>>> = match &self.embedding_cache {
+            Some(cache) => cache.get_many(&cache_model, &all_texts).unwrap_or_else(|e| {
+                warn!("[EMBEDDING-CACHE] Lookup failed, embedding every chunk: {}", e);
+                vec![None; all_texts.len()]
+            }),
+            None => vec![None; all_texts.len()],
+        };
+        // Entries cached before embeddings were checked may be unusable
+        let cached: Vec<Option<Vec<f32>>> = cached
+            .into_iter()
+            .map(|hit| hit.filter(|embedding| validate::inspect(embedding, self.embedding.dimension()).is_none()))
+            .collect();
+        let missing: Vec<usize> = (0..all_texts.len()).filter(|&i| cached[i].is_none()).collect();
+        if missing.len() < all_texts.len() {
+            info!("[EMBEDDING-CACHE] Reusing {} of {} embeddings", all_texts.len() - missing.len(), all_texts.len());
+        }
+
+        let mut texts: Vec<String> = missing.iter().map(|&i| all_texts[i].clone()).collect();
+        let mut token_counts: Vec<usize> = texts.iter().map(|t| batching::count_tokens(t)).collect();
+
+        let total = texts.len();
+        let mut throttle = Throttle::new(&self.config.indexing);
+        // Low-priority jobs send one batch at a time
+        let parallel = if throttle.is_enabled() { 1 } else { self.config.indexing.parallel_batches.max(1) };
+        // Embeddings land in input order whatever order batches complete in
+        let mut slots: Vec<Option<Vec<f32>>> = vec![None; total];
+        // Times each text was re-requested after an unusable embedding
+        let mut rejections: Vec<usize> = vec![0; total];
+        let mut quarantined = 0;
+        // Ranges still to embed; rejected batches go back to the front
+        let mut pending: VecDeque<Range<usize>> = VecDeque::new();
+        if total > 0 {
+            pending.push_back(0..total);
+        }
+        let mut in_flight = FuturesUnordered::new();
+        let mut done = 0;
         let mut last_save_time = std::time::Instant::now();
+        let mut batch_number = 0;
+        let mut progress = EmbeddingProgressTracker::new(absolute_path, total, all_texts.len() - total);
 
-        for (i, chunk_batch) in chunks.chunks(batch_size).enumerate() {
-            let texts: Vec<String> = chunk_batch.iter().map(|c| c.content.clone()).collect();
-            
-            let batch_progress = (i as f32 / total_batches as f32) * 30.0;
-            let progress = (30.0 + batch_progress) as u8;
-            if last_save_time.elapsed().as_secs() >= 2 {
-                let mut snapshot = self.snapshot_manager.lock().await;
-                snapshot.set_indexing(absolute_path, progress)?;
-                snapshot.save()?;
-                last_save_time = std::time::Instant::now();
+        loop {
+            while in_flight.len() < parallel {
+                let Some(range) = pending.pop_front() else {
+                    break;
+                };
+                let batch = batching::next_batch(&token_counts[..range.end], range.start, sizer.budget(), max_items);
+                if batch.end < range.end {
+                    pending.push_front(batch.end..range.end);
+                }
+
+                batch_number += 1;
+                let batch_tokens: usize = token_counts[batch.clone()].iter().sum();
+                let batch_texts = texts[batch.clone()].to_vec();
+                info!("[EMBEDDINGS] Dispatching batch {} ({} chunks, {} tokens, {} in flight)",
+                    batch_number, batch.len(), batch_tokens, in_flight.len() + 1);
+
+                in_flight.push(async move {
+                    let _turn = self.embedding_budget.acquire(batch_tokens).await;
+                    let started = std::time::Instant::now();
+                    // Indexes from before query/document modes keep plain embeddings until rebuilt
+                    let embedded = if input_types {
+                        self.embedding.embed_documents(&batch_texts).await
+                    } else {
+                        self.embedding.embed_batch(&batch_texts).await
+                    };
+                    (batch, batch_tokens, started.elapsed(), embedded)
+                });
             }
-            
-            info!("[EMBEDDINGS] Processing batch {}/{} ({} chunks) - Progress: {}%", 
-                i + 1, 
-                total_batches,
-                texts.len(),
-                progress
-            );
 
-            let embeddings = self.embedding.embed_batch(&texts).await?;
-            all_embeddings.extend(embeddings);
+            let Some((batch, batch_tokens, elapsed, embedded)) = in_flight.next().await else {
+                break;
+            };
+
+            match embedded {
+                Ok(embeddings) => {
+                    progress.batch_completed(batch.len(), batch_tokens);
+                    if embeddings.len() != batch.len() {
+                        return Err(Error::Embedding(format!(
+                            "{} returned {} embeddings for a batch of {} chunks",
+                            self.embedding.provider_name(), embeddings.len(), batch.len()
+                        )));
+                    }
+                    if sizer.completed(batch_tokens, elapsed) {
+                        warn!("[EMBEDDINGS] Batch of {} tokens took {:.1}s, lowering budget to {} tokens",
+                            batch_tokens, elapsed.as_secs_f32(), sizer.budget());
+                    }
+                    let dimension = self.embedding.dimension();
+                    for (i, embedding) in batch.clone().zip(embeddings) {
+                        let Some(defect) = validate::inspect(&embedding, dimension) else {
+                            slots[i] = Some(embedding);
+                            done += 1;
+                            continue;
+                        };
+                        let chunk = &chunks[missing[i]];
+                        if rejections[i] < EMBEDDING_REJECTION_RETRIES {
+                            rejections[i] += 1;
+                            warn!("[EMBEDDINGS] Unusable embedding for chunk {} ({}), requesting it again",
+                                chunk.id, defect);
+                            self.telemetry.record_retry(self.embedding.provider_name(), self.embedding_model());
+                            pending.push_back(i..i + 1);
+                        } else {
+                            error!("[EMBEDDINGS] Unusable embedding for chunk {} of {} ({}), leaving it out of the index",
+                                chunk.id, chunk.relative_path, defect);
+                            quarantined += 1;
+                            done += 1;
+                        }
+                    }
+
+                    self.progress.publish(progress.event(done, false));
+                    let percentage = (30.0 + (done as f32 / total as f32) * 30.0) as u8;
+                    info!("[EMBEDDINGS] {}/{} chunks embedded - Progress: {}%", done, total, percentage);
+                    if last_save_time.elapsed().as_secs() >= 2 {
+                        let mut snapshot = self.snapshot_manager.lock().await;
+                        snapshot.set_indexing(absolute_path, percentage)?;
+                        snapshot.save()?;
+                        last_save_time = std::time::Instant::now();
+                    }
+                    throttle.pause_batch().await;
+                }
+                Err(Error::EmbeddingTooLarge(e)) if batch.len() > 1 => {
+                    sizer.rejected(batch_tokens);
+                    warn!("[EMBEDDINGS] Batch of {} chunks rejected as too large, lowering budget to {} tokens: {}",
+                        batch.len(), sizer.budget(), e);
+                    self.telemetry.record_retry(self.embedding.provider_name(), self.embedding_model());
+                    pending.push_front(batch);
+                }
+                Err(Error::EmbeddingTooLarge(e)) if token_counts[batch.start] > 1 => {
+                    // The provider's tokenizer counts more tokens than ours; keep halving the text
+                    let start = batch.start;
+                    let chunk = &chunks[missing[start]];
+                    let limit = token_counts[start] / 2;
+                    let truncated = batching::truncate_to_tokens(&texts[start], limit).into_owned();
+                    let truncated_tokens = batching::count_tokens(&truncated);
+                    // A cut that leaves the text as long would be rejected again forever
+                    if truncated_tokens >= token_counts[start] {
+                        error!("[EMBEDDINGS] Chunk {} of {} rejected as too large and cannot be truncated further, leaving it out of the index: {}",
+                            chunk.id, chunk.relative_path, e);
+                        quarantined += 1;
+                        done += 1;
+                        self.progress.publish(progress.event(done, false));
+                    } else {
+                        warn!("[EMBEDDINGS] Chunk {} rejected as too large, truncating to {} tokens: {}",
+                            chunk.id, limit, e);
+                        texts[start] = truncated;
+                        token_counts[start] = truncated_tokens;
+                        self.telemetry.record_retry(self.embedding.provider_name(), self.embedding_model());
+                        pending.push_front(batch);
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.progress.publish(progress.event(done, true));
+        if quarantined > 0 {
+            warn!("[EMBEDDINGS] {} chunks got no usable embedding; their files are recorded as failed", quarantined);
+        }
+
+        if let Some(cache) = &self.embedding_cache {
+            let (fresh_texts, fresh_embeddings): (Vec<String>, Vec<Vec<f32>>) = missing
+                .iter()
+                .zip(&slots)
+                .filter_map(|(&i, slot)| Some((all_texts[i].clone(), slot.clone()?)))
+                .unzip();
+            if let Err(e) = cache.insert_many(&cache_model, &fresh_texts, &fresh_embeddings) {
+                warn!("[EMBEDDING-CACHE] Failed to store {} embeddings: {}", fresh_embeddings.len(), e);
+            }
         }
 
-        Ok(all_embeddings)
+        let mut fresh = slots.into_iter();
+        Ok(cached
+            .into_iter()
+            .map(|hit| match hit {
+                Some(embedding) => Some(embedding),
+                None => fresh.next().flatten(),
+            })
+            .collect())
+    }
+
+    /// Identity of the embeddings this server produces, so cached vectors are
+    /// only reused for the same model in the same input mode
+    pub(super) fn embedding_cache_model(&self, input_types: bool) -> String {
+        let mut model = format!(
+            "{}|{}|{}|{}",
+            self.embedding.provider_name(),
+            self.embedding_model(),
+            self.embedding.dimension(),
+            if input_types { "document" } else { "plain" }
+        );
+        // Entries cached before normalization existed keep their key
+        if self.config.embedding.normalize {
+            model.push_str("|normalized");
+        }
+        // A configured document prefix changes the vectors
+        if let Some(prefix) = self.document_prefix().filter(|_| input_types) {
+            model.push_str(&format!("|prefix:{prefix}"));
+        }
+        model
     }
 
     async fn try_incremental_sync(
         &self,
-        codebase_path: &Path
+        codebase_path: &Path,
+        ignore_patterns: &[String],
+        additional_roots: &[PathBuf],
     ) -> Result<Option<crate::sync::FileChanges>> {
-        let sync_arc = self.get_or_create_synchronizer(codebase_path).await?;
+        // A vector index that fails verification is quarantined here, and one
+        // quarantined earlier is missing; either way only a full run restores it
+        if !self.get_vector_db(codebase_path).await?.has_index(codebase_path).await? {
+            info!("[INCREMENTAL] No vector index on disk for {}", codebase_path.display());
+            return Ok(None);
+        }
+
+        let sync_arc = self.get_or_create_synchronizer(codebase_path, ignore_patterns, additional_roots).await?;
         let mut sync = sync_arc.lock().await;
         let changes = sync.check_for_changes().await?;
         Ok(Some(changes))
     }
 
-    async fn process_incremental_changes(
+    pub(super) async fn process_incremental_changes(
         &self,
         codebase_path: &Path,
         changes: crate::sync::FileChanges,
-    ) -> Result<()> {
+    ) -> Result<IndexRunSummary> {
         let total_changes = changes.added.len() + changes.removed.len() + changes.modified.len();
         info!("[INCREMENTAL] Processing {} changes", total_changes);
 
+        self.migrate_chunk_ids(codebase_path).await?;
+
         let metadata_store = self.get_metadata_store(codebase_path).await?;
-        let mut vector_db = self.get_vector_db(codebase_path)?;
+        let mut vector_db = self.get_vector_db(codebase_path).await?;
         let mut bm25 = self.get_bm25_search(codebase_path)?;
 
         for removed_file in &changes.removed {
@@ -503,6 +1099,7 @@ impl ToolHandlers {
                     for chunk_id in &chunk_ids {
                         let _ = store.delete(chunk_id);
                     }
+                    store.identifier_index()?.delete_chunks(&chunk_ids)?;
                 }
                 vector_db.delete(&chunk_ids).await?;
                 bm25.delete(&chunk_ids)?;
@@ -510,33 +1107,33 @@ impl ToolHandlers {
             }
         }
 
-        // Delete chunks for modified files
-        for modified_file in &changes.modified {
-            info!("[INCREMENTAL] Deleting old chunks for modified file: {}", modified_file);
-            let chunk_ids = self.find_chunk_ids_for_file(codebase_path, modified_file).await?;
-            
-            if !chunk_ids.is_empty() {
-                {
-                    let store = metadata_store.lock().await;
-                    for chunk_id in &chunk_ids {
-                        let _ = store.delete(chunk_id);
-                    }
-                }
-                vector_db.delete(&chunk_ids).await?;
-                bm25.delete(&chunk_ids)?;
-                info!("[INCREMENTAL] Deleted {} old chunks for {}", chunk_ids.len(), modified_file);
-            }
-        }
+        // Chunk IDs come from content, so chunks of modified files that did not
+        // change keep their ID and their stored embedding
+        let previous_ids = self.find_chunk_ids_for_files(codebase_path, &changes.modified).await?;
 
-        info!("[INCREMENTAL] Saving vector database after deletions...");
-        vector_db.save().await?;
-        info!("[INCREMENTAL] Vector database saved successfully");
+        // These files are handled now; earlier storage failures for them no longer apply
+        {
+            let handled: Vec<String> = changes.added.iter()
+                .chain(changes.modified.iter())
+                .chain(changes.removed.iter())
+                .cloned()
+                .collect();
+            let store = metadata_store.lock().await;
+            store.clear_failed_files(&handled)?;
+            store.remove_file_splits(&handled)?;
+        }
 
+        let mut chunks_processed = 0;
+        let mut failures = StorageFailures::default();
         let files_to_index: Vec<_> = changes.added.iter()
             .chain(changes.modified.iter())
             .map(|rel_path| codebase_path.join(rel_path))
             .collect();
 
+        let mut new_chunks = Vec::new();
+        let mut file_splits = Vec::new();
+        // Hash of each re-indexed file that produced chunks, which all its stored chunks must carry
+        let mut file_hashes: HashMap<String, String> = HashMap::new();
         if !files_to_index.is_empty() {
             info!("[INCREMENTAL] Re-indexing {} files", files_to_index.len());
             
@@ -545,83 +1142,1085 @@ impl ToolHandlers {
                 self.config.indexing.chunk_overlap,
             );
             
-            let mut all_chunks = Vec::new();
+            let mut throttle = Throttle::new(&self.config.indexing);
+            let sync_generation = self.sync_generation(codebase_path).await;
             for file_path in files_to_index {
-                match self.process_file(&file_path, &codebase_path.to_path_buf(), &chunker).await {
-                    Ok(mut chunks) => {
-                        all_chunks.append(&mut chunks);
+                match self.process_file(&file_path, &codebase_path.to_path_buf(), &chunker, sync_generation).await {
+                    Ok((mut chunks, split)) => {
+                        if let Some(chunk) = chunks.first() {
+                            file_hashes.extend(chunk.metadata.file_hash.clone().map(|hash| (chunk.relative_path.clone(), hash)));
+                        }
+                        new_chunks.append(&mut chunks);
+                        file_splits.extend(split);
                     }
                     Err(e) => {
                         warn!("[INCREMENTAL] Failed to process file {}: {}", file_path.display(), e);
                     }
                 }
+                throttle.pause().await;
             }
+        }
 
-            if !all_chunks.is_empty() {
-                let embeddings = self.generate_embeddings_batch(&all_chunks, codebase_path).await?;
-                let vector_docs: Vec<_> = all_chunks.iter()
-                    .zip(embeddings.iter())
-                    .map(|(chunk, embedding)| crate::vectordb::VectorDocument {
-                        id: chunk.id.clone(),
-                        vector: embedding.clone(),
-                    })
-                    .collect();
-                vector_db.insert(vector_docs).await?;
-
-                let bm25_docs: Vec<_> = all_chunks.iter()
-                    .map(|chunk| crate::search::BM25Document {
-                        id: chunk.id.clone(),
-                        content: chunk.content.clone(),
-                        file_path: chunk.file_path.to_string_lossy().to_string(),
-                        start_line: chunk.start_line as u64,
-                        end_line: chunk.end_line as u64,
-                    })
-                    .collect();
-                bm25.insert(bm25_docs)?;
+        let stale_ids: Vec<String> = {
+            let new_ids: HashSet<&str> = new_chunks.iter().map(|chunk| chunk.id.as_str()).collect();
+            previous_ids
+                .iter()
+                .filter(|id| !new_ids.contains(id.as_str()))
+                .cloned()
+                .collect()
+        };
+        let (reused, fresh): (Vec<CodeChunk>, Vec<CodeChunk>) = new_chunks
+            .into_iter()
+            .partition(|chunk| previous_ids.contains(&chunk.id));
+
+        if !stale_ids.is_empty() {
+            {
+                let store = metadata_store.lock().await;
+                for chunk_id in &stale_ids {
+                    let _ = store.delete(chunk_id);
+                }
+                store.identifier_index()?.delete_chunks(&stale_ids)?;
+            }
+            vector_db.delete(&stale_ids).await?;
+            bm25.delete(&stale_ids)?;
+            info!("[INCREMENTAL] Deleted {} stale chunks of modified files", stale_ids.len());
+        }
+
+        info!("[INCREMENTAL] Saving vector database after deletions...");
+        vector_db.save().await?;
+        self.forget_vector_readers(codebase_path).await;
+        info!("[INCREMENTAL] Vector database saved successfully");
+
+        if !reused.is_empty() {
+            // Same content, but line numbers and modification time may have moved
+            let store = metadata_store.lock().await;
+            store.insert_batch(&reused)?;
+            store.identifier_index()?.insert_batch(&reused)?;
+            info!("[INCREMENTAL] Kept embeddings of {} unchanged chunks", reused.len());
+        }
+
+        if !fresh.is_empty() {
+            let input_types = self.embeds_with_input_types(codebase_path).await;
+            let embeddings = self.generate_routed_embeddings(&fresh, codebase_path, input_types).await?;
+            let stored_chunks = self
+                .store_chunks(codebase_path, vector_db.as_mut(), &mut bm25, &fresh, &embeddings, &mut failures, false)
+                .await?;
+
+            {
+                let store = metadata_store.lock().await;
+                store.insert_batch(&stored_chunks)?;
+                store.identifier_index()?.insert_batch(&stored_chunks)?;
+                store.record_failed_files(&failures.to_records())?;
+            }
 
-                metadata_store.lock().await.insert_batch(&all_chunks)?;
+            chunks_processed = stored_chunks.len();
+            info!("[INCREMENTAL] Successfully indexed {} new or changed chunks", stored_chunks.len());
+        }
 
-                info!("[INCREMENTAL] Successfully indexed {} chunks", all_chunks.len());
+        // Every chunk still stored for a changed file must come from its current content
+        let leftover_ids: Vec<String> = {
+            let touched: HashSet<&str> = changes.added.iter()
+                .chain(changes.modified.iter())
+                .chain(changes.removed.iter())
+                .map(String::as_str)
+                .collect();
+            let store = metadata_store.lock().await;
+            store
+                .iter()
+                .filter(|(_, metadata)| {
+                    touched.contains(metadata.relative_path.as_str())
+                        && metadata.file_hash.as_ref() != file_hashes.get(&metadata.relative_path)
+                })
+                .map(|(chunk_id, _)| chunk_id)
+                .collect()
+        };
+        if !leftover_ids.is_empty() {
+            warn!(
+                "[INCREMENTAL] {} chunks of changed files outlived the stale chunk deletion; removing them",
+                leftover_ids.len()
+            );
+            {
+                let store = metadata_store.lock().await;
+                for chunk_id in &leftover_ids {
+                    let _ = store.delete(chunk_id);
+                }
+                store.identifier_index()?.delete_chunks(&leftover_ids)?;
             }
+            vector_db.delete(&leftover_ids).await?;
+            vector_db.save().await?;
+            self.forget_vector_readers(codebase_path).await;
+            bm25.delete(&leftover_ids)?;
+        }
+        if !changes.removed.is_empty() || !stale_ids.is_empty() || !leftover_ids.is_empty() {
+            self.schedule_vacuum(codebase_path).await;
         }
 
+        {
+            let store = metadata_store.lock().await;
+            store.record_file_splits(&file_splits)?;
+            // Manifests may have changed along with the files
+            store.set_packages(&detect_packages(codebase_path))?;
+        }
+        let language_stats = self.language_stats(codebase_path).await;
+        log_fallback_rates(&language_stats);
+
         let mut snapshot = self.snapshot_manager.lock().await;
         let total_chunks = metadata_store.lock().await.count();
         
-        let files = self.scan_codebase(&codebase_path.to_path_buf(), &[], &[]).await?;
+        let additional_roots = self.stored_additional_roots(codebase_path).await;
+        let files = self.scan_codebase(&codebase_path.to_path_buf(), &[], &[], &additional_roots).await?;
         let indexed_files = files.len();
         
+        let status = if failures.is_empty() { "completed" } else { "completed_with_errors" };
         let stats = IndexStats {
             indexed_files,
             total_chunks,
             elapsed_secs: 0.0,
-            index_status: "completed".to_string(),
+            index_status: status.to_string(),
+            language_stats,
         };
         
         snapshot.set_indexed(codebase_path, stats)?;
         snapshot.save()?;
         
+        if !failures.is_empty() {
+            warn!(
+                "[INCREMENTAL] {} files could not be stored; run retry_failed_files to re-index them",
+                failures.len()
+            );
+        }
+        
         info!("[INCREMENTAL] Complete. Added: {}, Removed: {}, Modified: {}",
             changes.added.len(), changes.removed.len(), changes.modified.len());
 
-        Ok(())
+        Ok(IndexRunSummary {
+            mode: "incremental",
+            files_processed: total_changes,
+            chunks_processed,
+            status: status.to_string(),
+        })
     }
 
-    async fn find_chunk_ids_for_file(
-        &self,
-        codebase_path: &Path,
-        relative_path: &str
-    ) -> Result<Vec<String>> {
+    /// Rebuild the vector and keyword indexes from the chunks kept in the
+    /// metadata store, for when they were lost or damaged but the metadata
+    /// survived. Embeddings already in the embedding cache are reused; the
+    /// change snapshot stays, since the chunks still match the files it saw.
+    pub(super) async fn rebuild_from_metadata(&self, codebase_path: &Path) -> Result<IndexRunSummary> {
         let metadata_store = self.get_metadata_store(codebase_path).await?;
-        let store = metadata_store.lock().await;
-        
-        let mut chunk_ids = Vec::new();
-        for (chunk_id, metadata) in store.iter() {
-            if metadata.relative_path == relative_path {
-                chunk_ids.push(chunk_id);
-            }
+        let (mut chunks, input_types) = {
+            let store = metadata_store.lock().await;
+            let chunks: Vec<CodeChunk> = store.iter().map(|(id, metadata)| metadata.to_chunk(&id)).collect();
+            (chunks, store.embedding_input_types()?)
+        };
+        chunks.sort_by(|a, b| {
+            a.relative_path
+                .cmp(&b.relative_path)
+                .then(a.metadata.chunk_index.cmp(&b.metadata.chunk_index))
+        });
+        info!("[REBUILD] Rebuilding indexes of '{}' from {} stored chunks", codebase_path.display(), chunks.len());
+
+        // The keyword index keeps its tokenizer if its settings are still readable
+        let data_dir = &self.config.storage.data_dir;
+        let tokenizer = BM25Search::stored_analyzer(codebase_path, data_dir).map(|analyzer| analyzer.tokenizer);
+        self.forget_vector_readers(codebase_path).await;
+        USearchDatabase::remove_for_codebase(codebase_path, data_dir)?;
+        USearchDatabase::drop_unreferenced_mappings(codebase_path, data_dir, metadata_store.lock().await.db())?;
+        let keyword_dir = BM25Search::get_index_path_for_codebase(codebase_path, data_dir);
+        if keyword_dir.exists() {
+            std::fs::remove_dir_all(&keyword_dir)?;
+        }
+
+        let embeddings = self.generate_routed_embeddings(&chunks, codebase_path, input_types).await?;
+        {
+            let mut snapshot = self.snapshot_manager.lock().await;
+            snapshot.set_indexing(codebase_path, 60)?;
+            snapshot.save()?;
+        }
+
+        let mut failures = StorageFailures::default();
+        let stored = {
+            let mut vector_db = self.get_vector_db(codebase_path).await?;
+            vector_db.begin_bulk_load();
+            let mut bm25 = self.get_bm25_search_with_tokenizer(codebase_path, tokenizer)?;
+            metadata_store.lock().await.clear_full_vectors()?;
+            self.store_chunks(codebase_path, vector_db.as_mut(), &mut bm25, &chunks, &embeddings, &mut failures, true)
+                .await?
+        };
+
+        // Chunks that could not be stored leave the metadata too, so
+        // retry_failed_files re-indexes their files from scratch
+        let stored_ids: HashSet<&str> = stored.iter().map(|chunk| chunk.id.as_str()).collect();
+        let dropped: Vec<String> = chunks
+            .iter()
+            .filter(|chunk| !stored_ids.contains(chunk.id.as_str()))
+            .map(|chunk| chunk.id.clone())
+            .collect();
+        {
+            let store = metadata_store.lock().await;
+            for chunk_id in &dropped {
+                store.delete(chunk_id)?;
+            }
+            store.identifier_index()?.delete_chunks(&dropped)?;
+            store.record_failed_files(&failures.to_records())?;
+            // Every chunk was embedded again, with the current text
+            if let Some(mut settings) = store.index_settings()? {
+                settings.embedding_text_version = EMBEDDING_TEXT_VERSION;
+                settings.text_normalization = Some(self.config.search.analyzer.normalization);
+                store.set_index_settings(&settings)?;
+            }
+        }
+        if !failures.is_empty() {
+            warn!(
+                "[REBUILD] {} files could not be stored; run retry_failed_files to re-index them",
+                failures.len()
+            );
+        }
+
+        let indexed_files = chunks
+            .iter()
+            .map(|chunk| chunk.relative_path.as_str())
+            .collect::<HashSet<_>>()
+            .len();
+        let status = if failures.is_empty() { "completed" } else { "completed_with_errors" };
+        let stats = IndexStats {
+            indexed_files,
+            total_chunks: stored.len(),
+            elapsed_secs: 0.0,
+            index_status: status.to_string(),
+            language_stats: self.language_stats(codebase_path).await,
+        };
+        {
+            let mut snapshot = self.snapshot_manager.lock().await;
+            snapshot.set_indexed(codebase_path, stats)?;
+            snapshot.save()?;
+        }
+
+        info!("[REBUILD] Complete. Files: {}, Chunks: {}", indexed_files, stored.len());
+
+        Ok(IndexRunSummary {
+            mode: "rebuild",
+            files_processed: indexed_files,
+            chunks_processed: stored.len(),
+            status: status.to_string(),
+        })
+    }
+
+    /// Re-embed the stored chunks with the configured models and swap the
+    /// result in for the codebase's vectors
+    ///
+    /// The new vectors are written under a scratch data directory and only
+    /// replace the old ones once every chunk has one, so a failed migration
+    /// leaves the existing index as it was. The keyword index and chunk
+    /// metadata do not depend on the model and are kept.
+    pub(super) async fn migrate_embeddings(&self, codebase_path: &Path) -> Result<IndexRunSummary> {
+        let metadata_store = self.get_metadata_store(codebase_path).await?;
+        let mut chunks: Vec<CodeChunk> = metadata_store
+            .lock()
+            .await
+            .iter()
+            .map(|(id, metadata)| metadata.to_chunk(&id))
+            .collect();
+        chunks.sort_by(|a, b| {
+            a.relative_path
+                .cmp(&b.relative_path)
+                .then(a.metadata.chunk_index.cmp(&b.metadata.chunk_index))
+        });
+        info!(
+            "[MIGRATE] Re-embedding {} stored chunks of '{}' with {} '{}'",
+            chunks.len(),
+            codebase_path.display(),
+            self.embedding.provider_name(),
+            self.embedding_model()
+        );
+
+        let embeddings = self.generate_routed_embeddings(&chunks, codebase_path, true).await?;
+        let missing = embeddings.iter().filter(|embedding| embedding.is_none()).count();
+        if missing > 0 {
+            return Err(Error::Embedding(format!(
+                "{} of {} chunks got no usable embedding from {}; the existing vectors were kept",
+                missing,
+                chunks.len(),
+                self.embedding.provider_name()
+            )));
+        }
+        {
+            let mut snapshot = self.snapshot_manager.lock().await;
+            snapshot.set_indexing(codebase_path, 60)?;
+            snapshot.save()?;
+        }
+
+        let data_dir = &self.config.storage.data_dir;
+        let staging_dir = data_dir.join(format!("migrate-{}", uuid::Uuid::new_v4()));
+        let staged = self.stage_vectors(codebase_path, &staging_dir, &chunks, &embeddings).await;
+        let swapped = staged.and_then(|layout| {
+            swap_vector_dir(codebase_path, data_dir, &staging_dir)?;
+            Ok(layout)
+        });
+        if staging_dir.exists() {
+            let _ = std::fs::remove_dir_all(&staging_dir);
+        }
+        // Whichever side lost, its mapping trees are no longer named
+        let dropped = USearchDatabase::drop_unreferenced_mappings(codebase_path, data_dir, metadata_store.lock().await.db());
+        let (quantization, vector_metric) = swapped?;
+        dropped?;
+        self.forget_vector_readers(codebase_path).await;
+
+        {
+            let store = metadata_store.lock().await;
+            if let Some(mut settings) = store.index_settings()? {
+                settings.embedding_provider = self.embedding.provider_name().to_string();
+                settings.embedding_model = self.embedding_model().to_string();
+                settings.embedding_dimension = self.embedding.dimension();
+                settings.docs_embedding_provider = self.docs_identity().map(|(provider, _)| provider);
+                settings.docs_embedding_model = self.docs_identity().map(|(_, model)| model);
+                settings.vector_metric = vector_metric;
+                settings.embedding_normalize = Some(self.config.embedding.normalize);
+                settings.embedding_document_prefix = self.document_prefix().map(str::to_string);
+                settings.embedding_text_version = EMBEDDING_TEXT_VERSION;
+                settings.text_normalization = Some(self.config.search.analyzer.normalization);
+                store.set_index_settings(&settings)?;
+            }
+            store.set_embedding_input_types(true)?;
+
+            // Quantized indexes re-score with the exact vectors of the new model
+            let stored = store.clear_full_vectors().and_then(|()| {
+                if quantization == VectorQuantization::None {
+                    return Ok(());
+                }
+                let ids: Vec<String> = chunks.iter().map(|chunk| chunk.id.clone()).collect();
+                let vectors: Vec<Vec<f32>> = embeddings.into_iter().flatten().collect();
+                store.insert_full_vectors(&ids, &vectors)
+            });
+            if let Err(e) = stored {
+                warn!("[MIGRATE] Failed to store full-precision vectors: {}", e);
+            }
+        }
+
+        let indexed_files = chunks
+            .iter()
+            .map(|chunk| chunk.relative_path.as_str())
+            .collect::<HashSet<_>>()
+            .len();
+        let stats = IndexStats {
+            indexed_files,
+            total_chunks: chunks.len(),
+            elapsed_secs: 0.0,
+            index_status: "completed".to_string(),
+            language_stats: self.language_stats(codebase_path).await,
+        };
+        {
+            let mut snapshot = self.snapshot_manager.lock().await;
+            snapshot.set_indexed(codebase_path, stats)?;
+            snapshot.save()?;
+        }
+
+        info!("[MIGRATE] Complete. Files: {}, Chunks: {}", indexed_files, chunks.len());
+
+        Ok(IndexRunSummary {
+            mode: "migrate",
+            files_processed: indexed_files,
+            chunks_processed: chunks.len(),
+            status: "completed".to_string(),
+        })
+    }
+
+    /// Write `chunks` and their embeddings to a vector store of the codebase
+    /// under `staging_dir` instead of the data directory; returns the
+    /// precision the store keeps and the metric it compares by
+    async fn stage_vectors(
+        &self,
+        codebase_path: &Path,
+        staging_dir: &Path,
+        chunks: &[CodeChunk],
+        embeddings: &[Option<Vec<f32>>],
+    ) -> Result<(VectorQuantization, VectorMetric)> {
+        // The metadata stores are shared, so the staged index keeps its
+        // mappings in the live store and they stay valid after the swap
+        let mut staging = self.clone();
+        staging.config.storage.data_dir = staging_dir.to_path_buf();
+        let mut vector_db = staging.get_vector_db(codebase_path).await?;
+        vector_db.begin_bulk_load();
+
+        let total_batches = chunks.chunks(STORAGE_BATCH_SIZE).len();
+        for (i, (batch, batch_embeddings)) in chunks
+            .chunks(STORAGE_BATCH_SIZE)
+            .zip(embeddings.chunks(STORAGE_BATCH_SIZE))
+            .enumerate()
+        {
+            let vectors: Vec<Vec<f32>> = batch_embeddings.iter().flatten().cloned().collect();
+            vector_db.insert_batch(codebase_path, batch, &vectors).await?;
+
+            if (i + 1) % 20 == 0 {
+                let progress = 60 + ((i + 1) * 35 / total_batches) as u8;
+                let mut snapshot = self.snapshot_manager.lock().await;
+                snapshot.set_indexing(codebase_path, progress)?;
+                snapshot.save()?;
+            }
+        }
+        vector_db.save().await?;
+        Ok((vector_db.quantization(), vector_db.metric()))
+    }
+
+    /// Store chunks in the vector and BM25 indexes, batch by batch
+    ///
+    /// Transient write failures are retried with backoff. A batch that still
+    /// fails is rolled back and its files are added to `failures` so one bad
+    /// batch doesn't abort the whole run. Returns the chunks stored in both
+    /// indexes; only failing to save the vector index is fatal.
+    #[allow(clippy::too_many_arguments)]
+    async fn store_chunks(
+        &self,
+        codebase_path: &Path,
+        vector_db: &mut dyn VectorDatabase,
+        bm25: &mut BM25Search,
+        chunks: &[CodeChunk],
+        embeddings: &[Option<Vec<f32>>],
+        failures: &mut StorageFailures,
+        report_progress: bool,
+    ) -> Result<Vec<CodeChunk>> {
+        let policy = &self.config.indexing.retry;
+        let total_batches = chunks.chunks(STORAGE_BATCH_SIZE).len();
+        let mut stored = Vec::with_capacity(chunks.len());
+        let mut throttle = Throttle::new(&self.config.indexing);
+        let mut last_save_time = std::time::Instant::now();
+
+        for (i, (batch, batch_embeddings)) in chunks
+            .chunks(STORAGE_BATCH_SIZE)
+            .zip(embeddings.chunks(STORAGE_BATCH_SIZE))
+            .enumerate()
+        {
+            // Chunks without a usable embedding fail their file, so retry_failed_files picks it up
+            let mut chunk_batch = Vec::with_capacity(batch.len());
+            let mut embedding_batch = Vec::with_capacity(batch.len());
+            let mut rejected = Vec::new();
+            for (chunk, embedding) in batch.iter().zip(batch_embeddings) {
+                match embedding {
+                    Some(embedding) => {
+                        chunk_batch.push(chunk.clone());
+                        embedding_batch.push(embedding.clone());
+                    }
+                    None => rejected.push(chunk.clone()),
+                }
+            }
+            if !rejected.is_empty() {
+                failures.record(&rejected, &Error::Embedding(format!(
+                    "{} returned no usable embedding after {} attempts",
+                    self.embedding.provider_name(),
+                    EMBEDDING_REJECTION_RETRIES + 1
+                )));
+            }
+            if chunk_batch.is_empty() {
+                continue;
+            }
+
+            let ids: Vec<String> = chunk_batch.iter().map(|chunk| chunk.id.clone()).collect();
+
+            let mut backoff = Backoff::new(policy);
+            let vector_result = loop {
+                // Upserts replace what a failed attempt stored, so retries need no cleanup
+                match vector_db.upsert_batch(codebase_path, &chunk_batch, &embedding_batch).await {
+                    Err(e) if backoff.should_retry(&e) => backoff.wait(&e, "Vector insert").await,
+                    other => break other,
+                }
+            };
+            if let Err(e) = vector_result {
+                error!("[STORAGE] Vector insert for batch {}/{} failed: {}", i + 1, total_batches, e);
+                let _ = vector_db.delete(&ids).await;
+                failures.record(&chunk_batch, &e);
+                continue;
+            }
+
+            let mut backoff = Backoff::new(policy);
+            let mut retried = false;
+            let bm25_result = loop {
+                // A failed attempt may have committed before failing, e.g. on
+                // the reader reload; drop its documents so they aren't added twice
+                let cleared = if retried { bm25.delete(&ids) } else { Ok(()) };
+                let documents = chunk_batch.iter().map(BM25Document::from).collect();
+                match cleared.and_then(|()| bm25.insert(documents)) {
+                    Err(e) if backoff.should_retry(&e) => {
+                        backoff.wait(&e, "BM25 commit").await;
+                        retried = true;
+                    }
+                    other => break other,
+                }
+            };
+            if let Err(e) = bm25_result {
+                error!("[STORAGE] BM25 commit for batch {}/{} failed: {}", i + 1, total_batches, e);
+                let _ = bm25.delete(&ids);
+                let _ = vector_db.delete(&ids).await;
+                failures.record(&chunk_batch, &e);
+                continue;
+            }
+
+            // Quantized indexes re-score their candidates with the exact vectors;
+            // without them the quantized score is used
+            if vector_db.quantization() != VectorQuantization::None {
+                let metadata_store = self.get_metadata_store(codebase_path).await?;
+                if let Err(e) = metadata_store.lock().await.insert_full_vectors(&ids, &embedding_batch) {
+                    warn!("[STORAGE] Failed to store full-precision vectors of batch {}/{}: {}", i + 1, total_batches, e);
+                }
+            }
+
+            stored.extend(chunk_batch);
+            throttle.pause_batch().await;
+
+            if report_progress && last_save_time.elapsed().as_secs() >= 2 {
+                let progress = 60 + ((i + 1) * 35 / total_batches) as u8;
+                let mut snapshot = self.snapshot_manager.lock().await;
+                snapshot.set_indexing(codebase_path, progress)?;
+                snapshot.save()?;
+                last_save_time = std::time::Instant::now();
+            }
+        }
+
+        info!("[STORAGE] Saving vector index...");
+        let mut backoff = Backoff::new(policy);
+        let saved = loop {
+            match vector_db.save().await {
+                Err(e) if backoff.should_retry(&e) => backoff.wait(&e, "Vector index save").await,
+                other => break other,
+            }
+        };
+        saved?;
+        self.forget_vector_readers(codebase_path).await;
+
+        Ok(stored)
+    }
+
+    pub(super) async fn record_index_run(
+        &self,
+        codebase_path: &Path,
+        trigger: &str,
+        attempted_mode: &str,
+        started_at: chrono::DateTime<chrono::Utc>,
+        outcome: &Result<IndexRunSummary>,
+    ) {
+        let finished_at = chrono::Utc::now();
+        let duration_secs = (finished_at - started_at).num_milliseconds() as f64 / 1000.0;
+        
+        let run = match outcome {
+            Ok(summary) => IndexRun {
+                started_at: started_at.to_rfc3339(),
+                finished_at: finished_at.to_rfc3339(),
+                duration_secs,
+                trigger: trigger.to_string(),
+                mode: summary.mode.to_string(),
+                files_processed: summary.files_processed,
+                chunks_processed: summary.chunks_processed,
+                status: summary.status.clone(),
+                error: None,
+            },
+            Err(e) => IndexRun {
+                started_at: started_at.to_rfc3339(),
+                finished_at: finished_at.to_rfc3339(),
+                duration_secs,
+                trigger: trigger.to_string(),
+                mode: attempted_mode.to_string(),
+                files_processed: 0,
+                chunks_processed: 0,
+                status: "failed".to_string(),
+                error: Some(e.to_string()),
+            },
+        };
+        
+        let recorded = match self.get_metadata_store(codebase_path).await {
+            Ok(metadata_store) => metadata_store.lock().await.record_index_run(&run),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = recorded {
+            warn!("[BACKGROUND-INDEX] Failed to record indexing history: {}", e);
+        }
+        if let Err(e) = self.save_metadata_to_sqlite(codebase_path).await {
+            warn!("[BACKGROUND-INDEX] Failed to copy metadata into the SQLite file: {}", e);
+        }
+    }
+
+    /// Resolve indexing runs left unfinished by a previous server process
+    ///
+    /// Entries whose progress has not been updated for `stale_indexing_secs`
+    /// are taken as interrupted. A codebase whose last complete index is still
+    /// on disk (vectors, sync snapshot and settings) is restored to indexed and
+    /// synced incrementally from there; any other is marked failed, so the
+    /// "already being indexed" guard no longer blocks re-indexing it.
+    pub async fn recover_interrupted_indexing(&self) -> Result<()> {
+        let max_age = std::time::Duration::from_secs(self.config.indexing.stale_indexing_secs);
+        let stale = self.snapshot_manager.lock().await.stale_indexing_codebases(max_age);
+
+        for (codebase_path, progress) in stale {
+            let Some((stats, settings)) = self.last_complete_index(&codebase_path).await else {
+                warn!("[RECOVERY] Indexing of {} was interrupted at {}% and cannot be resumed",
+                    codebase_path.display(), progress);
+                let mut snapshot = self.snapshot_manager.lock().await;
+                snapshot.set_failed(
+                    &codebase_path,
+                    "Indexing was interrupted by a server restart. Run index_codebase with force=true to rebuild the index.".to_string(),
+                    Some(progress),
+                )?;
+                snapshot.save()?;
+                continue;
+            };
+
+            info!("[RECOVERY] Indexing of {} was interrupted at {}%; resuming from its last complete index",
+                codebase_path.display(), progress);
+            {
+                let mut snapshot = self.snapshot_manager.lock().await;
+                snapshot.set_indexed(&codebase_path, stats)?;
+                snapshot.save()?;
+            }
+
+            let response = self.handle_index_codebase(IndexCodebaseArgs {
+                path: codebase_path.to_string_lossy().to_string(),
+                force: false,
+                splitter: settings.splitter,
+                custom_extensions: settings.custom_extensions,
+                ignore_patterns: settings.ignore_patterns,
+                tokenizer: None,
+                additional_roots: Vec::new(),
+                embedding_provider: None,
+                embedding_model: None,
+            }).await?;
+            if response.contains("\"error\"") {
+                warn!("[RECOVERY] Could not resume indexing of {}: {}", codebase_path.display(), response);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove the stores a full rebuild replaces: vectors, keyword index and change snapshot
+    async fn clear_for_rebuild(&self, absolute_path: &Path) -> Result<()> {
+        self.forget_vector_readers(absolute_path).await;
+        match self.get_vector_db(absolute_path).await {
+            Ok(mut vector_db) => {
+                if vector_db.has_index(absolute_path).await? {
+                    info!("[FORCE-REINDEX] Clearing index for '{}'", absolute_path.display());
+                    vector_db.delete_index(absolute_path).await?;
+                }
+            }
+            // The damaged index was quarantined; nothing is left to clear
+            Err(Error::Corrupted(_)) => {}
+            // Built with another model; the rebuild replaces it
+            Err(Error::DimensionMismatch(_)) => {
+                info!("[FORCE-REINDEX] Removing index built with another embedding dimension for '{}'", absolute_path.display());
+                USearchDatabase::remove_for_codebase(absolute_path, &self.config.storage.data_dir)?;
+                let metadata_store = self.get_metadata_store(absolute_path).await?;
+                USearchDatabase::drop_unreferenced_mappings(
+                    absolute_path,
+                    &self.config.storage.data_dir,
+                    metadata_store.lock().await.db(),
+                )?;
+            }
+            Err(e) => return Err(e),
+        }
+        
+        let mut bm25 = self.get_bm25_search(absolute_path)?;
+        if bm25.has_index(absolute_path).await? {
+            bm25.delete_index(absolute_path).await?;
+        }
+        
+        let _ = FileSynchronizer::delete_snapshot(absolute_path, &self.config.storage.data_dir).await;
+        
+        Ok(())
+    }
+
+    /// Handlers for the provider a codebase is indexed with: the one matching
+    /// `provider`/`model` when either is given, else the extra provider that
+    /// built its index. `None` keeps these handlers; the flag is set when a
+    /// requested provider differs from the one that built the index.
+    async fn select_embedding(
+        &self,
+        codebase_path: &Path,
+        provider: Option<&str>,
+        model: Option<&str>,
+    ) -> std::result::Result<(Option<ToolHandlers>, bool), String> {
+        if provider.is_none() && model.is_none() {
+            return Ok((self.extra_handlers_for(codebase_path).await, false));
+        }
+
+        let kind = match provider {
+            Some(name) => Some(
+                crate::config::EmbeddingProvider::from_name(name)
+                    .ok_or_else(|| format!("Unknown embedding provider '{name}'"))?,
+            ),
+            None => None,
+        };
+        let matches = |config: &crate::config::EmbeddingConfig| {
+            (kind.is_none() || kind.as_ref() == Some(&config.provider))
+                && (model.is_none() || model == Some(config.model.as_str()))
+        };
+
+        let (selected, provider_name, model_name) = if matches(&self.config.embedding) {
+            (None, self.embedding.provider_name().to_string(), self.embedding_model().to_string())
+        } else if let Some(extra) = self.extra_embeddings.iter().find(|extra| matches(&extra.config)) {
+            (Some(self.with_embedding(extra)), extra.provider.provider_name().to_string(), extra.config.model.clone())
+        } else {
+            let available: Vec<String> = std::iter::once(format!(
+                "{} '{}'",
+                self.embedding.provider_name(),
+                self.config.embedding.model
+            ))
+            .chain(self.extra_embeddings.iter().map(|extra| {
+                format!("{} '{}'", extra.provider.provider_name(), extra.config.model)
+            }))
+            .collect();
+            return Err(format!(
+                "No embedding provider matches {} '{}'. Available: {}. Add it to EMBEDDING_EXTRA_PROVIDERS to index with it.",
+                provider.unwrap_or("any provider"),
+                model.unwrap_or("any model"),
+                available.join(", ")
+            ));
+        };
+
+        let settings = match self.get_metadata_store(codebase_path).await {
+            Ok(metadata_store) => metadata_store.lock().await.index_settings().ok().flatten(),
+            Err(_) => None,
+        };
+        let switched = settings.is_some_and(|settings| {
+            settings.embedding_provider != provider_name || settings.embedding_model != model_name
+        });
+        Ok((selected, switched))
+    }
+
+    /// Whether a fallback chain is embedding with another provider or model
+    /// than the one that built the codebase's index
+    async fn built_by_other_provider(&self, codebase_path: &Path) -> bool {
+        if self.embedding.active_model().is_none() {
+            return false;
+        }
+        let settings = match self.get_metadata_store(codebase_path).await {
+            Ok(metadata_store) => metadata_store.lock().await.index_settings().ok().flatten(),
+            Err(_) => None,
+        };
+        settings.is_some_and(|settings| {
+            settings.embedding_provider != self.embedding.provider_name()
+                || settings.embedding_model != self.embedding_model()
+        })
+    }
+
+    /// Whether the codebase's documentation was embedded with another model
+    /// than the configured documentation model, or shares the code index
+    /// while one is configured now (or the reverse)
+    pub(super) async fn docs_model_changed(&self, codebase_path: &Path) -> bool {
+        let settings = match self.get_metadata_store(codebase_path).await {
+            Ok(metadata_store) => metadata_store.lock().await.index_settings().ok().flatten(),
+            Err(_) => None,
+        };
+        settings.is_some_and(|settings| {
+            let built = settings.docs_embedding_provider.zip(settings.docs_embedding_model);
+            built != self.docs_identity()
+        })
+    }
+
+    /// Whether the codebase's chunks were embedded with another layout of
+    /// `embedding_text`; the vectors still answer queries, but differ from
+    /// those of new chunks
+    async fn embedding_text_outdated(&self, codebase_path: &Path) -> bool {
+        let settings = match self.get_metadata_store(codebase_path).await {
+            Ok(metadata_store) => metadata_store.lock().await.index_settings().ok().flatten(),
+            Err(_) => None,
+        };
+        settings.is_some_and(|settings| settings.embedding_text_version != EMBEDDING_TEXT_VERSION)
+    }
+
+    async fn embedding_options_changed_for(&self, codebase_path: &Path) -> bool {
+        let settings = match self.get_metadata_store(codebase_path).await {
+            Ok(metadata_store) => metadata_store.lock().await.index_settings().ok().flatten(),
+            Err(_) => None,
+        };
+        settings.is_some_and(|settings| self.embedding_options_changed(&settings))
+    }
+
+    /// Provider and model producing embeddings right now
+    fn embedding_identity(&self) -> String {
+        format!("{} '{}'", self.embedding.provider_name(), self.embedding_model())
+    }
+
+    /// Stats and settings of the last complete index, if everything an
+    /// incremental sync needs survived
+    async fn last_complete_index(&self, codebase_path: &Path) -> Option<(IndexStats, IndexSettings)> {
+        let data_dir = &self.config.storage.data_dir;
+        if !self.vector_store_path(codebase_path).exists()
+            || !crate::sync::FileSynchronizer::get_snapshot_path(codebase_path, data_dir).exists()
+        {
+            return None;
+        }
+
+        let metadata_store = self.get_metadata_store(codebase_path).await.ok()?;
+        let store = metadata_store.lock().await;
+        let settings = store.index_settings().ok()??;
+        let files: HashSet<String> = store.iter().map(|(_, metadata)| metadata.relative_path).collect();
+        let stats = IndexStats {
+            indexed_files: files.len(),
+            total_chunks: store.count(),
+            elapsed_secs: 0.0,
+            index_status: "completed".to_string(),
+            language_stats: store.language_stats().unwrap_or_default(),
+        };
+
+        Some((stats, settings))
+    }
+
+    /// Per-language splitter counts; empty if they cannot be read
+    async fn language_stats(&self, codebase_path: &Path) -> BTreeMap<String, SplitterStats> {
+        let stats = match self.get_metadata_store(codebase_path).await {
+            Ok(metadata_store) => metadata_store.lock().await.language_stats(),
+            Err(e) => Err(e),
+        };
+        stats.unwrap_or_else(|e| {
+            warn!("[BACKGROUND-INDEX] Failed to read language statistics: {}", e);
+            BTreeMap::new()
+        })
+    }
+
+    /// Custom ignore patterns recorded by the last full index
+    pub(super) async fn stored_ignore_patterns(&self, codebase_path: &Path) -> Vec<String> {
+        let settings = match self.get_metadata_store(codebase_path).await {
+            Ok(metadata_store) => metadata_store.lock().await.index_settings(),
+            Err(e) => Err(e),
+        };
+        match settings {
+            Ok(Some(settings)) => settings.ignore_patterns,
+            _ => Vec::new(),
+        }
+    }
+
+    /// Additional roots recorded for the codebase
+    pub(super) async fn stored_additional_roots(&self, codebase_path: &Path) -> Vec<PathBuf> {
+        let settings = match self.get_metadata_store(codebase_path).await {
+            Ok(metadata_store) => metadata_store.lock().await.index_settings(),
+            Err(e) => Err(e),
+        };
+        match settings {
+            Ok(Some(settings)) => settings.additional_roots.iter().map(PathBuf::from).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Record new additional roots right away, so an incremental run that adds
+    /// a root is remembered by the runs after it
+    async fn set_stored_additional_roots(&self, codebase_path: &Path, additional_roots: &[PathBuf]) {
+        let updated = match self.get_metadata_store(codebase_path).await {
+            Ok(metadata_store) => {
+                let store = metadata_store.lock().await;
+                match store.index_settings() {
+                    Ok(Some(mut settings)) => {
+                        settings.additional_roots = additional_roots
+                            .iter()
+                            .map(|root| root.to_string_lossy().to_string())
+                            .collect();
+                        store.set_index_settings(&settings)
+                    }
+                    // Written with the other settings when the full index completes
+                    Ok(None) => Ok(()),
+                    Err(e) => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = updated {
+            warn!("[BACKGROUND-INDEX] Failed to record additional roots: {}", e);
+        }
+    }
+
+    /// IDs of the stored chunks of any of `relative_paths`, in one pass over the store
+    async fn find_chunk_ids_for_files(
+        &self,
+        codebase_path: &Path,
+        relative_paths: &[String],
+    ) -> Result<HashSet<String>> {
+        if relative_paths.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        let wanted: HashSet<&str> = relative_paths.iter().map(String::as_str).collect();
+        let metadata_store = self.get_metadata_store(codebase_path).await?;
+        let store = metadata_store.lock().await;
+
+        Ok(store
+            .iter()
+            .filter(|(_, metadata)| wanted.contains(metadata.relative_path.as_str()))
+            .map(|(chunk_id, _)| chunk_id)
+            .collect())
+    }
+
+    /// Re-key chunks stored under an older chunk ID scheme
+    ///
+    /// Vectors are renamed in place and BM25 documents re-added from the stored
+    /// content, so nothing is re-embedded. Safe to re-run after an interruption.
+    async fn migrate_chunk_ids(&self, codebase_path: &Path) -> Result<()> {
+        let metadata_store = self.get_metadata_store(codebase_path).await?;
+        let store = metadata_store.lock().await;
+
+        let version = store.chunk_id_version()?;
+        if version >= CHUNK_ID_VERSION {
+            return Ok(());
+        }
+
+        // Rebuild each file's chunks in file order and derive IDs as the splitter would
+        let mut by_file: HashMap<String, Vec<(String, StoredMetadata)>> = HashMap::new();
+        for (chunk_id, metadata) in store.iter() {
+            by_file.entry(metadata.relative_path.clone()).or_default().push((chunk_id, metadata));
+        }
+
+        let mut renames = Vec::new();
+        let mut documents = Vec::new();
+        for records in by_file.values_mut() {
+            records.sort_by_key(|(_, metadata)| (metadata.start_line, metadata.chunk_index));
+            let mut chunks: Vec<CodeChunk> = records
+                .iter()
+                .map(|(chunk_id, metadata)| metadata.to_chunk(chunk_id))
+                .collect();
+            assign_chunk_ids(&mut chunks);
+
+            for ((old_id, _), chunk) in records.iter().zip(&chunks) {
+                if *old_id != chunk.id {
+                    renames.push((old_id.clone(), chunk.id.clone()));
+                    documents.push(BM25Document::from(chunk));
+                }
+            }
+        }
+
+        info!(
+            "[MIGRATE] Moving {} chunks from chunk ID scheme v{} to v{}",
+            renames.len(),
+            version,
+            CHUNK_ID_VERSION
+        );
+
+        if !renames.is_empty() {
+            let mut vector_db = self.get_vector_db(codebase_path).await?;
+            vector_db.rename(&renames).await?;
+            vector_db.save().await?;
+            self.forget_vector_readers(codebase_path).await;
+
+            let mut bm25 = self.get_bm25_search(codebase_path)?;
+            for (rename_batch, document_batch) in renames
+                .chunks(MIGRATION_BATCH_SIZE)
+                .zip(documents.chunks(MIGRATION_BATCH_SIZE))
+            {
+                // Dropping the new IDs too keeps a re-run from adding duplicates
+                let ids: Vec<String> = rename_batch
+                    .iter()
+                    .flat_map(|(old_id, new_id)| [old_id.clone(), new_id.clone()])
+                    .collect();
+                bm25.delete(&ids)?;
+                bm25.insert(document_batch.to_vec())?;
+            }
+
+            store.rename_chunks(&renames)?;
+        }
+
+        store.set_chunk_id_version(CHUNK_ID_VERSION)?;
+        info!("[MIGRATE] Chunk ID migration complete");
+        Ok(())
+    }
+
+    /// Save the change snapshot of the changes just stored
+    async fn commit_sync_changes(&self, codebase_path: &Path) -> Result<()> {
+        let path_key = codebase_path.to_string_lossy().to_string();
+        let sync = self.synchronizers.lock().await.get(&path_key).cloned();
+        match sync {
+            Some(sync) => sync.lock().await.commit_changes().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Change snapshot generation newly indexed chunks are attributed to
+    async fn sync_generation(&self, codebase_path: &Path) -> u64 {
+        let path_key = codebase_path.to_string_lossy().to_string();
+        let sync = self.synchronizers.lock().await.get(&path_key).cloned();
+        match sync {
+            Some(sync) => sync.lock().await.generation(),
+            None => FileSynchronizer::stored_generation(codebase_path, &self.config.storage.data_dir).unwrap_or(0),
+        }
+    }
+
+    async fn find_chunk_ids_for_file(
+        &self,
+        codebase_path: &Path,
+        relative_path: &str
+    ) -> Result<Vec<String>> {
+        let metadata_store = self.get_metadata_store(codebase_path).await?;
+        let store = metadata_store.lock().await;
+        
+        let mut chunk_ids = Vec::new();
+        for (chunk_id, metadata) in store.iter() {
+            if metadata.relative_path == relative_path {
+                chunk_ids.push(chunk_id);
+            }
         }
         
         Ok(chunk_ids)
     }
 }
+
+/// File modification time as unix seconds, if the filesystem reports one
+pub(crate) async fn file_modified_at(path: &Path) -> Option<u64> {
+    let modified = tokio::fs::metadata(path).await.ok()?.modified().ok()?;
+    modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .map(|duration| duration.as_secs())
+}
+
+/// Replace the codebase's vector directory with the one staged for it under
+/// `staging_dir`; the old directory is moved aside first and put back if the
+/// staged one cannot be moved in
+fn swap_vector_dir(codebase_path: &Path, data_dir: &Path, staging_dir: &Path) -> Result<()> {
+    let vector_dir = |data_dir: &Path| {
+        let index_path = USearchDatabase::get_index_path_for_codebase(codebase_path, data_dir);
+        index_path.parent().map(Path::to_path_buf).unwrap_or(index_path)
+    };
+    let (live, staged) = (vector_dir(data_dir), vector_dir(staging_dir));
+    let aside = live.with_extension("old");
+
+    if aside.exists() {
+        std::fs::remove_dir_all(&aside)?;
+    }
+    if live.exists() {
+        std::fs::rename(&live, &aside)?;
+    } else if let Some(parent) = live.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if let Err(e) = std::fs::rename(&staged, &live) {
+        if aside.exists() {
+            let _ = std::fs::rename(&aside, &live);
+        }
+        return Err(e.into());
+    }
+    if aside.exists() {
+        std::fs::remove_dir_all(&aside)?;
+    }
+    Ok(())
+}
+
+/// Warn about languages whose grammar fell back to character splitting for many chunks
+fn log_fallback_rates(language_stats: &BTreeMap<String, SplitterStats>) {
+    for (language, stats) in language_stats {
+        if language != "unknown" && stats.is_degraded() {
+            warn!(
+                "[BACKGROUND-INDEX] {:.0}% of {} chunks ({} of {}) used character fallback; the grammar may not support this code",
+                stats.fallback_rate() * 100.0,
+                language,
+                stats.fallback_chunks,
+                stats.total()
+            );
+        }
+    }
+}
+
+/// Version of `embedding_text` recorded in the index settings; raise it
+/// whenever the embedded text changes, so existing indexes are re-embedded
+pub(super) const EMBEDDING_TEXT_VERSION: u32 = 1;
+
+/// Text embedded for a chunk: its content under a header naming the file, so
+/// queries about a module's name reach code that never mentions it
+fn embedding_text(chunk: &CodeChunk) -> String {
+    format!(
+        "File: {} ({})\n\n{}",
+        chunk.relative_path,
+        path_tokens(&chunk.relative_path),
+        chunk.content
+    )
+}
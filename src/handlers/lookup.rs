@@ -0,0 +1,169 @@
+//! Identifier lookup handler
+//!
+//! Handles the lookup_identifier MCP tool: the "grep -w" workflow served from
+//! the identifier index instead of the filesystem
+
+use super::{ToolHandlers, ensure_absolute_path, validate_codebase_path};
+use crate::{ErrorCode, Result};
+use serde::Deserialize;
+use std::collections::HashSet;
+use tracing::info;
+
+#[derive(Debug, Deserialize)]
+pub struct LookupIdentifierArgs {
+    pub path: String,
+    pub name: String,
+    #[serde(default = "default_whole_word")]
+    pub whole_word: bool,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+fn default_whole_word() -> bool {
+    true
+}
+
+fn default_limit() -> usize {
+    100
+}
+
+struct IdentifierLocation {
+    name: String,
+    relative_path: String,
+    line: usize,
+    is_definition: bool,
+    line_text: String,
+}
+
+impl ToolHandlers {
+    /// Handle lookup_identifier tool call - returns JSON string
+    pub async fn handle_lookup_identifier(&self, args: LookupIdentifierArgs) -> Result<String> {
+        let LookupIdentifierArgs {
+            path: codebase_path,
+            name,
+            whole_word,
+            limit,
+        } = args;
+
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Ok(serde_json::json!({
+                "error": "Identifier name must not be empty.",
+                "code": ErrorCode::InvalidArgument
+            }).to_string());
+        }
+
+        let result_limit = limit.min(500);
+
+        let absolute_path = ensure_absolute_path(&codebase_path)?;
+
+        if let Err(e) = validate_codebase_path(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!("{}. Original input: '{}'", e, codebase_path),
+                "code": e.code()
+            }).to_string());
+        }
+
+        {
+            let snapshot = self.snapshot_manager.lock().await;
+            if !snapshot.is_indexed(&absolute_path) && !snapshot.is_indexing(&absolute_path) {
+                return Ok(serde_json::json!({
+                    "error": format!(
+                        "Codebase '{}' is not indexed. Please index it first using the index_codebase tool.",
+                        absolute_path.display()
+                    ),
+                    "code": ErrorCode::NotIndexed
+                }).to_string());
+            }
+        }
+
+        info!("[LOOKUP] Looking up '{}' in {} (whole word: {})", name, absolute_path.display(), whole_word);
+
+        let metadata_store = self.get_metadata_store(&absolute_path).await?;
+        let store = metadata_store.lock().await;
+        let occurrences = store.identifier_index()?.lookup(&name, whole_word)?;
+
+        // Overlapping chunks report the same line more than once
+        let mut seen = HashSet::new();
+        let mut locations = Vec::new();
+        for (chunk_id, occurrence) in occurrences {
+            let metadata = match store.get(&chunk_id)? {
+                Some(metadata) => metadata,
+                None => continue,
+            };
+
+            if !seen.insert((metadata.relative_path.clone(), occurrence.line, occurrence.name.clone())) {
+                continue;
+            }
+
+            let line_text = metadata.content
+                .lines()
+                .nth(occurrence.line.saturating_sub(metadata.start_line))
+                .unwrap_or("")
+                .trim()
+                .to_string();
+
+            locations.push(IdentifierLocation {
+                name: occurrence.name,
+                relative_path: metadata.relative_path,
+                line: occurrence.line,
+                is_definition: occurrence.is_definition,
+                line_text,
+            });
+        }
+        drop(store);
+
+        if locations.is_empty() {
+            return Ok(serde_json::json!({
+                "message": format!(
+                    "No occurrences of '{}' found in codebase '{}'",
+                    name,
+                    absolute_path.display()
+                )
+            }).to_string());
+        }
+
+        locations.sort_by(|a, b| {
+            b.is_definition.cmp(&a.is_definition)
+                .then_with(|| a.relative_path.cmp(&b.relative_path))
+                .then_with(|| a.line.cmp(&b.line))
+        });
+
+        let total_matches = locations.len();
+        let definitions = locations.iter().filter(|l| l.is_definition).count();
+        locations.truncate(result_limit);
+
+        let formatted = locations
+            .iter()
+            .map(|location| {
+                format!(
+                    "{}:{} [{}] {}\n   {}",
+                    location.relative_path,
+                    location.line,
+                    if location.is_definition { "definition" } else { "usage" },
+                    location.name,
+                    location.line_text
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut message = format!(
+            "Found {} occurrences of '{}' ({} definitions) in codebase '{}'",
+            total_matches,
+            name,
+            definitions,
+            absolute_path.display()
+        );
+        if total_matches > locations.len() {
+            message.push_str(&format!(" (showing first {})", locations.len()));
+        }
+        message.push_str("\n\n");
+        message.push_str(&formatted);
+
+        Ok(serde_json::json!({
+            "message": message,
+            "results_count": total_matches
+        }).to_string())
+    }
+}
@@ -0,0 +1,139 @@
+//! Migrate embeddings handler
+//!
+//! Handles the migrate_embeddings MCP tool: after the embedding provider or
+//! model changed (e.g. from 768-dimension Ollama to 1536-dimension OpenAI),
+//! re-embeds the chunk content kept in the metadata store with the new model
+//! and swaps the new vectors in, instead of clearing the index and
+//! re-reading and re-splitting every file.
+
+use super::{ToolHandlers, ensure_absolute_path, validate_codebase_path};
+use crate::{ErrorCode, Result};
+use crate::metadata::MetadataStore;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{error, info};
+
+#[derive(Debug, Deserialize)]
+pub struct MigrateEmbeddingsArgs {
+    pub path: String,
+}
+
+impl ToolHandlers {
+    /// Handle migrate_embeddings tool call - returns JSON string
+    pub async fn handle_migrate_embeddings(&self, args: MigrateEmbeddingsArgs) -> Result<String> {
+        let MigrateEmbeddingsArgs { path: codebase_path } = args;
+
+        let absolute_path = ensure_absolute_path(&codebase_path)?;
+
+        if let Err(e) = validate_codebase_path(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!("{}. Original input: '{}'", e, codebase_path),
+                "code": e.code()
+            }).to_string());
+        }
+
+        // Waits for a vacuum of the index to finish
+        let codebase_lock = self.codebase_lock(&absolute_path).await;
+        let _vacuum_done = codebase_lock.lock().await;
+        let mut snapshot = self.snapshot_manager.lock().await;
+
+        if snapshot.is_indexing(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!(
+                    "Codebase '{}' is already being indexed in the background. Please wait for completion.",
+                    absolute_path.display()
+                ),
+                "code": ErrorCode::IndexingInProgress
+            }).to_string());
+        }
+
+        let (chunk_count, settings) = if MetadataStore::exists(&absolute_path, &self.config.storage.data_dir) {
+            let metadata_store = self.get_metadata_store(&absolute_path).await?;
+            let store = metadata_store.lock().await;
+            (store.count(), store.index_settings()?)
+        } else {
+            (0, None)
+        };
+        if chunk_count == 0 {
+            return Ok(serde_json::json!({
+                "error": format!(
+                    "No stored chunks to migrate in '{}'. Index it with the index_codebase tool instead.",
+                    absolute_path.display()
+                ),
+                "code": ErrorCode::NotIndexed
+            }).to_string());
+        }
+
+        let up_to_date = settings.as_ref().is_some_and(|settings| {
+            settings.embedding_provider == self.embedding.provider_name()
+                && settings.embedding_model == self.embedding_model()
+                && settings.embedding_dimension == self.embedding.dimension()
+                && !self.embedding_options_changed(settings)
+                && settings.embedding_text_version == super::index::EMBEDDING_TEXT_VERSION
+                && settings
+                    .text_normalization
+                    .is_none_or(|normalization| normalization == self.config.search.analyzer.normalization)
+        });
+        if up_to_date && !self.docs_model_changed(&absolute_path).await {
+            return Ok(serde_json::json!({
+                "message": format!(
+                    "Codebase '{}' is already embedded with {} '{}'; nothing to migrate.",
+                    absolute_path.display(),
+                    self.embedding.provider_name(),
+                    self.embedding_model()
+                ),
+                "results_count": 0
+            }).to_string());
+        }
+
+        // The old vectors stay in place if the migration fails
+        let previous = snapshot.info(&absolute_path);
+        snapshot.set_indexing(&absolute_path, 0)?;
+        snapshot.save()?;
+        drop(snapshot);
+
+        info!(
+            "[MIGRATE] Migrating {} stored chunks of {} to {} '{}'",
+            chunk_count,
+            absolute_path.display(),
+            self.embedding.provider_name(),
+            self.embedding_model()
+        );
+
+        let handlers_clone = Arc::new(self.clone());
+        let abs_path_clone = absolute_path.clone();
+
+        tokio::spawn(async move {
+            let started_at = chrono::Utc::now();
+            let outcome = handlers_clone.migrate_embeddings(&abs_path_clone).await;
+
+            if let Err(e) = &outcome {
+                error!("[MIGRATE] Migration failed: {}", e);
+                let mut snapshot = handlers_clone.snapshot_manager.lock().await;
+                match previous {
+                    Some(info) => snapshot.set_info(&abs_path_clone, info),
+                    None => {
+                        let _ = snapshot.set_failed(&abs_path_clone, format!("Migration failed: {e}"), None);
+                    }
+                }
+                let _ = snapshot.save();
+            }
+
+            handlers_clone
+                .record_index_run(&abs_path_clone, "migrate_embeddings", "migrate", started_at, &outcome)
+                .await;
+        });
+
+        Ok(serde_json::json!({
+            "message": format!(
+                "Started re-embedding {} stored chunks of '{}' with {} '{}' in the background. \
+                 The current vectors are replaced once all chunks are embedded. Use check_status to follow progress.",
+                chunk_count,
+                absolute_path.display(),
+                self.embedding.provider_name(),
+                self.embedding_model()
+            ),
+            "results_count": chunk_count
+        }).to_string())
+    }
+}
@@ -1,23 +1,63 @@
 
 pub mod index;
 pub mod search;
+pub mod search_batch;
+pub mod search_everywhere;
 pub mod status;
 pub mod clear;
+pub mod lookup;
+pub mod grep;
+pub mod export;
+pub mod describe;
+pub mod history;
+pub mod retry_failed;
+pub mod coverage;
+pub mod restore;
+pub mod profiles;
+pub mod estimate;
+pub mod doctor;
+pub mod provider;
+pub mod stats;
+pub mod storage;
+pub mod rebuild;
+pub mod archive;
+pub mod migrate;
+pub mod vacuum;
 
 pub use index::IndexCodebaseArgs;
 pub use search::SearchCodeArgs;
+pub use search_batch::SearchCodeBatchArgs;
+pub use search_everywhere::SearchEverywhereArgs;
 pub use status::GetIndexingStatusArgs;
 pub use clear::ClearIndexArgs;
+pub use lookup::LookupIdentifierArgs;
+pub use grep::GrepCodeArgs;
+pub use describe::DescribeIndexArgs;
+pub use history::IndexHistoryArgs;
+pub use retry_failed::RetryFailedFilesArgs;
+pub use coverage::CoverageCheckArgs;
+pub use restore::RestoreIndexArgs;
+pub use profiles::SaveSearchProfileArgs;
+pub use estimate::EstimateIndexArgs;
+pub use doctor::DoctorArgs;
+pub use provider::CheckProviderArgs;
+pub use stats::EmbeddingStatsArgs;
+pub use storage::IndexStatsArgs;
+pub use rebuild::RebuildIndexArgs;
+pub use archive::{ExportIndexArgs, ImportIndexArgs};
+pub use migrate::MigrateEmbeddingsArgs;
 
 use crate::{Result, Error, Config};
+use crate::config::{EmbeddingConfig, TokenizerKind, VectorBackend};
 use crate::snapshot::SnapshotManager;
-use crate::embeddings::EmbeddingProvider;
-use crate::vectordb::{USearchDatabase, VectorDatabase};
-use crate::search::{BM25Search, HybridSearch};
+use crate::embeddings::{EmbeddingBudget, EmbeddingCache, EmbeddingProvider, EmbeddingTelemetry};
+use crate::vectordb::{ShardedUSearchDatabase, SplitVectorDatabase, USearchDatabase, VectorDatabase, VectorReader};
+use crate::search::{BM25Search, HybridSearch, LlmReranker};
 use crate::sync::FileSynchronizer;
+use crate::progress::{EmbeddingProgress, ProgressHub};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use tokio::sync::Mutex;
 
 #[derive(Clone)]
@@ -25,8 +65,51 @@ pub struct ToolHandlers {
     config: Config,
     snapshot_manager: Arc<Mutex<SnapshotManager>>,
     embedding: Arc<dyn EmbeddingProvider>,
+    /// Shared by every indexing job so concurrent codebases take turns
+    embedding_budget: Arc<EmbeddingBudget>,
+    /// Embeddings shared by all codebases; `None` when disabled or unavailable
+    embedding_cache: Option<Arc<EmbeddingCache>>,
     synchronizers: Arc<Mutex<HashMap<String, Arc<Mutex<FileSynchronizer>>>>>,
     metadata_stores: Arc<Mutex<HashMap<String, Arc<Mutex<crate::metadata::MetadataStore>>>>>,
+    /// Vector readers shared by searches; dropped whenever indexing writes the index
+    vector_readers: Arc<Mutex<VectorReaders>>,
+    /// Providers a codebase can be indexed with instead of `embedding`
+    extra_embeddings: Arc<Vec<ExtraEmbedding>>,
+    /// Embedding progress of indexing jobs
+    progress: Arc<ProgressHub>,
+    /// Request counters of every started provider
+    telemetry: Arc<EmbeddingTelemetry>,
+    /// Text model embedding documentation chunks into their own vector index
+    docs_embedding: Option<ExtraEmbedding>,
+    /// Branch namespace these handlers index and search; `None` for the
+    /// codebase's default index
+    branch: Option<String>,
+    /// Handlers of each branch namespace opened so far, sharing one snapshot
+    /// and store caches per branch
+    branch_handlers: Arc<Mutex<HashMap<String, ToolHandlers>>>,
+    /// Codebases whose indexes hold deleted vectors for the next vacuum,
+    /// also recorded in their metadata stores to survive restarts
+    vacuum_due: Arc<Mutex<HashSet<PathBuf>>>,
+    /// Per-codebase locks, see `codebase_lock`
+    codebase_locks: Arc<Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>>,
+    /// Chat model reranking find_code results, when one is configured
+    llm_reranker: Option<Arc<LlmReranker>>,
+}
+
+/// Vector readers by codebase and whether they read the documentation index
+#[derive(Default)]
+struct VectorReaders {
+    readers: HashMap<(String, bool), Arc<dyn VectorReader>>,
+    /// Times each codebase's readers were dropped, so a reader opened before
+    /// a write is not cached after it
+    generations: HashMap<String, u64>,
+}
+
+/// Provider from `EMBEDDING_EXTRA_PROVIDERS` with the settings it was started with
+#[derive(Clone)]
+pub struct ExtraEmbedding {
+    pub config: EmbeddingConfig,
+    pub provider: Arc<dyn EmbeddingProvider>,
 }
 
 impl ToolHandlers {
@@ -35,24 +118,397 @@ impl ToolHandlers {
         snapshot_manager: SnapshotManager,
         embedding: Arc<dyn EmbeddingProvider>,
     ) -> Self {
+        purge_expired_trash(&config);
+        
         Self {
+            embedding_budget: Arc::new(EmbeddingBudget::new(&config.indexing)),
+            embedding_cache: open_embedding_cache(&config),
+            llm_reranker: open_llm_reranker(&config),
             config,
             snapshot_manager: Arc::new(Mutex::new(snapshot_manager)),
             embedding,
             synchronizers: Arc::new(Mutex::new(HashMap::new())),
             metadata_stores: Arc::new(Mutex::new(HashMap::new())),
+            vector_readers: Arc::new(Mutex::new(VectorReaders::default())),
+            extra_embeddings: Arc::new(Vec::new()),
+            progress: Arc::new(ProgressHub::new()),
+            telemetry: Arc::new(EmbeddingTelemetry::new()),
+            docs_embedding: None,
+            branch: None,
+            branch_handlers: Arc::new(Mutex::new(HashMap::new())),
+            vacuum_due: Arc::new(Mutex::new(HashSet::new())),
+            codebase_locks: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+    
+    /// Embedding progress events of every indexing job, published after each batch
+    pub fn subscribe_progress(&self) -> tokio::sync::broadcast::Receiver<EmbeddingProgress> {
+        self.progress.subscribe()
+    }
+    
+    /// Providers that `analyze_code` can pick per codebase
+    pub fn with_extra_embeddings(mut self, extra_embeddings: Vec<ExtraEmbedding>) -> Self {
+        self.extra_embeddings = Arc::new(extra_embeddings);
+        self
+    }
+    
+    /// Counters the providers record their requests in, for `embedding_stats`
+    pub fn with_telemetry(mut self, telemetry: Arc<EmbeddingTelemetry>) -> Self {
+        self.telemetry = telemetry;
+        self
+    }
+    
+    /// Model that documentation chunks are embedded with instead of `embedding`
+    pub fn with_docs_embedding(mut self, docs_embedding: Option<ExtraEmbedding>) -> Self {
+        self.docs_embedding = docs_embedding;
+        self
+    }
+    
+    /// These handlers embedding with `extra`; they share stores, caches and
+    /// the embedding budget, and have no extra providers of their own
+    fn with_embedding(&self, extra: &ExtraEmbedding) -> Self {
+        let mut handlers = self.clone();
+        handlers.config.embedding = extra.config.clone();
+        handlers.embedding = Arc::clone(&extra.provider);
+        handlers.extra_embeddings = Arc::new(Vec::new());
+        handlers
+    }
+    
+    /// Handlers of the `branch` namespace, whose stores, snapshot and sync
+    /// state live under the branch's own data directory; these handlers
+    /// when `branch` is `None`. They share providers, the embedding cache
+    /// and the embedding budget.
+    pub async fn for_branch(&self, branch: Option<&str>) -> Result<Self> {
+        let Some(branch) = branch else {
+            return Ok(self.clone());
+        };
+        let branch = crate::branch::validate_branch_name(branch)?;
+        let mut branch_handlers = self.branch_handlers.lock().await;
+        if let Some(handlers) = branch_handlers.get(branch) {
+            return Ok(handlers.clone());
+        }
+
+        let mut handlers = self.clone();
+        handlers.config.storage.data_dir = crate::branch::create_branch_data_dir(&self.config.storage.data_dir, branch)?;
+        handlers.snapshot_manager = Arc::new(Mutex::new(SnapshotManager::new(
+            handlers.config.storage.data_dir.join("snapshot.json"),
+        )?));
+        handlers.synchronizers = Arc::new(Mutex::new(HashMap::new()));
+        handlers.metadata_stores = Arc::new(Mutex::new(HashMap::new()));
+        handlers.vector_readers = Arc::new(Mutex::new(VectorReaders::default()));
+        handlers.vacuum_due = Arc::new(Mutex::new(HashSet::new()));
+        handlers.codebase_locks = Arc::new(Mutex::new(HashMap::new()));
+        handlers.branch = Some(branch.to_string());
+        branch_handlers.insert(branch.to_string(), handlers.clone());
+        Ok(handlers)
+    }
+    
+    /// Recover the interrupted runs and pending vacuums of every branch
+    /// namespace on disk; run at startup so no search waits on them
+    pub async fn recover_branches(&self) {
+        for branch in crate::branch::known_branches(&self.config.storage.data_dir) {
+            let handlers = match self.for_branch(Some(&branch)).await {
+                Ok(handlers) => handlers,
+                Err(e) => {
+                    tracing::warn!("Failed to open the indexes of branch '{}': {}", branch, e);
+                    continue;
+                }
+            };
+            if let Err(e) = handlers.recover_interrupted_indexing().await {
+                tracing::warn!("Failed to recover interrupted indexing runs of branch '{}': {}", branch, e);
+            }
+            handlers.load_vacuum_due().await;
+        }
+    }
+    
+    /// The codebase's lock, held by a vacuum for its whole run and taken by
+    /// indexing runs before they start, so neither writes index files the
+    /// other is working on; searches never take it
+    async fn codebase_lock(&self, codebase_path: &Path) -> Arc<Mutex<()>> {
+        let mut locks = self.codebase_locks.lock().await;
+        Arc::clone(locks.entry(codebase_path.to_path_buf()).or_default())
+    }
+    
+    /// Extra provider with the given provider name and model
+    fn extra_embedding(&self, provider_name: &str, model: &str) -> Option<&ExtraEmbedding> {
+        self.extra_embeddings
+            .iter()
+            .find(|extra| extra.provider.provider_name() == provider_name && extra.config.model == model)
+    }
+    
+    /// Handlers embedding with the extra provider that built the codebase's
+    /// index; `None` when the configured provider built it
+    async fn extra_handlers_for(&self, codebase_path: &Path) -> Option<Self> {
+        if self.extra_embeddings.is_empty() {
+            return None;
         }
+        let settings = match self.get_metadata_store(codebase_path).await {
+            Ok(metadata_store) => metadata_store.lock().await.index_settings().ok().flatten(),
+            Err(_) => None,
+        };
+        settings
+            .and_then(|settings| self.extra_embedding(&settings.embedding_provider, &settings.embedding_model))
+            .map(|extra| self.with_embedding(extra))
     }
     
-    fn get_vector_db(&self, codebase_path: &Path) -> Result<Box<dyn VectorDatabase>> {
-        let dimension = self.embedding.dimension();
+    /// The codebase's vector index; with a documentation model, the pair of
+    /// code and documentation indexes
+    async fn get_vector_db(&self, codebase_path: &Path) -> Result<Box<dyn VectorDatabase>> {
+        let code = self.get_vector_db_with_dimension(codebase_path, self.embedding.dimension(), false).await?;
+        match &self.docs_embedding {
+            Some(docs) => Ok(Box::new(SplitVectorDatabase::new(
+                code,
+                self.get_docs_vector_db(codebase_path, docs.provider.dimension(), false).await?,
+            ))),
+            None => Ok(code),
+        }
+    }
+    
+    /// With `view`, a USearch index is memory-mapped for searching
+    async fn get_docs_vector_db(&self, codebase_path: &Path, dimension: usize, view: bool) -> Result<Box<dyn VectorDatabase>> {
+        match self.config.storage.vector_backend {
+            VectorBackend::USearch => {}
+            VectorBackend::LanceDb => return self.get_lance_db(codebase_path, dimension, true),
+            VectorBackend::Sqlite => return self.get_sqlite_db(codebase_path, dimension, true),
+        }
+        let index_path = USearchDatabase::get_docs_index_path_for_codebase(codebase_path, &self.config.storage.data_dir);
+        self.get_usearch_db(index_path, codebase_path, dimension, view, true).await
+    }
+    
+    /// Provider and model of the documentation model, if one is configured
+    fn docs_identity(&self) -> Option<(String, String)> {
+        self.docs_embedding
+            .as_ref()
+            .map(|docs| (docs.provider.provider_name().to_string(), docs.config.model.clone()))
+    }
+    
+    /// Documentation model that built the codebase's documentation index;
+    /// `None` when documentation shares the code index
+    async fn index_docs_embedding(&self, codebase_path: &Path) -> Result<Option<&ExtraEmbedding>> {
+        let settings = match self.get_metadata_store(codebase_path).await {
+            Ok(metadata_store) => metadata_store.lock().await.index_settings().ok().flatten(),
+            Err(_) => None,
+        };
+        let Some((provider, model)) = settings.and_then(|settings| {
+            Some((settings.docs_embedding_provider?, settings.docs_embedding_model?))
+        }) else {
+            return Ok(None);
+        };
+        match self.docs_embedding.as_ref().filter(|docs| {
+            docs.provider.provider_name() == provider && docs.config.model == model
+        }) {
+            Some(docs) => Ok(Some(docs)),
+            None => Err(Error::ModelMismatch(format!(
+                "Codebase '{}' embedded its documentation with {} '{}', which is not the configured EMBEDDING_DOCS_PROVIDER. \
+                 Configure it again to search the existing index, or re-index with force=true.",
+                codebase_path.display(),
+                provider,
+                model
+            ))),
+        }
+    }
+    
+    /// With `view`, a USearch index is memory-mapped for searching
+    async fn get_vector_db_with_dimension(&self, codebase_path: &Path, dimension: usize, view: bool) -> Result<Box<dyn VectorDatabase>> {
         tracing::info!("[HANDLER] Creating/loading vector DB with dimension: {}", dimension);
-        let db = USearchDatabase::for_codebase(codebase_path, dimension, &self.config.storage.data_dir)?;
-        Ok(Box::new(db))
+        match self.config.storage.vector_backend {
+            VectorBackend::USearch => {}
+            VectorBackend::LanceDb => return self.get_lance_db(codebase_path, dimension, false),
+            VectorBackend::Sqlite => return self.get_sqlite_db(codebase_path, dimension, false),
+        }
+        let index_path = USearchDatabase::get_index_path_for_codebase(codebase_path, &self.config.storage.data_dir);
+        self.get_usearch_db(index_path, codebase_path, dimension, view, true).await
+    }
+    
+    /// The usearch index at `index_path`, split into `VECTOR_SHARDS` shards;
+    /// `recover` quarantines an index that fails verification
+    async fn get_usearch_db(
+        &self,
+        index_path: PathBuf,
+        codebase_path: &Path,
+        dimension: usize,
+        view: bool,
+        recover: bool,
+    ) -> Result<Box<dyn VectorDatabase>> {
+        let storage = &self.config.storage;
+        // The ID mappings live in the codebase's metadata store
+        let mapping_db = self.get_metadata_store(codebase_path).await?.lock().await.db().clone();
+        let db = ShardedUSearchDatabase::open(
+            index_path,
+            codebase_path,
+            dimension,
+            &storage.data_dir,
+            &mapping_db,
+            storage.vector_quantization,
+            storage.vector_metric,
+            storage.hnsw,
+            view,
+            recover,
+            storage.vector_shards,
+        )?;
+        // A single index needs no fan-out
+        match db.into_single() {
+            Ok(single) => Ok(Box::new(single)),
+            Err(sharded) => Ok(Box::new(sharded)),
+        }
+    }
+    
+    /// Reader of the codebase's code or documentation vectors, opened once
+    /// and shared by searches until indexing writes the index
+    async fn get_vector_reader(&self, codebase_path: &Path, dimension: usize, docs: bool) -> Result<Arc<dyn VectorReader>> {
+        let key = (codebase_path.to_string_lossy().to_string(), docs);
+        let generation = {
+            let cache = self.vector_readers.lock().await;
+            if let Some(reader) = cache.readers.get(&key) {
+                return Ok(Arc::clone(reader));
+            }
+            cache.generations.get(&key.0).copied().unwrap_or(0)
+        };
+        
+        // Opened without holding the lock, so loading a large index does not
+        // stall searches of other codebases
+        let view = self.config.storage.vector_mmap;
+        let db = if docs {
+            self.get_docs_vector_db(codebase_path, dimension, view).await?
+        } else {
+            self.get_vector_db_with_dimension(codebase_path, dimension, view).await?
+        };
+        let reader: Arc<dyn VectorReader> = Arc::<dyn VectorDatabase>::from(db);
+        let mut cache = self.vector_readers.lock().await;
+        if cache.generations.get(&key.0).copied().unwrap_or(0) != generation {
+            // Indexing wrote the index while it was opened; serve this search
+            // from it, but let the next one open what indexing left
+            return Ok(reader);
+        }
+        Ok(Arc::clone(cache.readers.entry(key).or_insert(reader)))
+    }
+    
+    /// Drop the shared readers of the codebase, so the next search opens the
+    /// index as indexing left it
+    async fn forget_vector_readers(&self, codebase_path: &Path) {
+        let path_key = codebase_path.to_string_lossy().to_string();
+        let mut cache = self.vector_readers.lock().await;
+        cache.readers.retain(|(path, _), _| *path != path_key);
+        *cache.generations.entry(path_key).or_default() += 1;
+    }
+    
+    /// File or directory holding the codebase's vectors in the configured backend
+    fn vector_store_path(&self, codebase_path: &Path) -> PathBuf {
+        let data_dir = &self.config.storage.data_dir;
+        match self.config.storage.vector_backend {
+            VectorBackend::USearch => USearchDatabase::get_index_path_for_codebase(codebase_path, data_dir),
+            VectorBackend::LanceDb => crate::vectordb::lance_path_for_codebase(codebase_path, data_dir),
+            VectorBackend::Sqlite => crate::vectordb::sqlite_path_for_codebase(codebase_path, data_dir),
+        }
+    }
+    
+    #[cfg(feature = "lancedb")]
+    fn get_lance_db(&self, codebase_path: &Path, dimension: usize, docs: bool) -> Result<Box<dyn VectorDatabase>> {
+        use crate::vectordb::lance_db::{LanceDatabase, CODE_TABLE, DOCS_TABLE};
+        let table = if docs { DOCS_TABLE } else { CODE_TABLE };
+        Ok(Box::new(LanceDatabase::for_codebase(codebase_path, dimension, &self.config.storage.data_dir, table)))
     }
     
+    #[cfg(not(feature = "lancedb"))]
+    fn get_lance_db(&self, _codebase_path: &Path, _dimension: usize, _docs: bool) -> Result<Box<dyn VectorDatabase>> {
+        Err(Error::Config("VECTOR_BACKEND=lancedb requires building with `--features lancedb`".to_string()))
+    }
+    
+    #[cfg(feature = "sqlite")]
+    fn get_sqlite_db(&self, codebase_path: &Path, dimension: usize, docs: bool) -> Result<Box<dyn VectorDatabase>> {
+        use crate::vectordb::sqlite_db::{SqliteDatabase, CODE_TABLES, DOCS_TABLES};
+        let tables = if docs { DOCS_TABLES } else { CODE_TABLES };
+        Ok(Box::new(SqliteDatabase::for_codebase(codebase_path, dimension, &self.config.storage.data_dir, tables)?))
+    }
+    
+    #[cfg(not(feature = "sqlite"))]
+    fn get_sqlite_db(&self, _codebase_path: &Path, _dimension: usize, _docs: bool) -> Result<Box<dyn VectorDatabase>> {
+        Err(Error::Config("VECTOR_BACKEND=sqlite requires building with `--features sqlite`".to_string()))
+    }
+    
+    /// The codebase's SQLite file, when that is the configured backend and
+    /// the file exists
+    #[cfg(feature = "sqlite")]
+    fn existing_sqlite_db(&self, codebase_path: &Path) -> Result<Option<crate::vectordb::SqliteDatabase>> {
+        use crate::vectordb::sqlite_db::{SqliteDatabase, CODE_TABLES};
+        let data_dir = &self.config.storage.data_dir;
+        if self.config.storage.vector_backend != VectorBackend::Sqlite
+            || !crate::vectordb::sqlite_path_for_codebase(codebase_path, data_dir).exists()
+        {
+            return Ok(None);
+        }
+        SqliteDatabase::for_codebase(codebase_path, 0, data_dir, CODE_TABLES).map(Some)
+    }
+    
+    /// Copy the metadata store into the codebase's SQLite file, so the file
+    /// holds the index on its own
+    #[cfg(feature = "sqlite")]
+    async fn save_metadata_to_sqlite(&self, codebase_path: &Path) -> Result<()> {
+        let Some(db) = self.existing_sqlite_db(codebase_path)? else {
+            return Ok(());
+        };
+        let metadata_store = self.get_metadata_store(codebase_path).await?;
+        let store = metadata_store.lock().await;
+        db.save_metadata(&store)?;
+        db.save().await
+    }
+    
+    #[cfg(not(feature = "sqlite"))]
+    async fn save_metadata_to_sqlite(&self, _codebase_path: &Path) -> Result<()> {
+        Ok(())
+    }
+    
+    /// Fill a newly created metadata store from the copy in the codebase's
+    /// SQLite file, e.g. after the file alone was copied to this machine
+    #[cfg(feature = "sqlite")]
+    fn restore_metadata_from_sqlite(&self, codebase_path: &Path, store: &crate::metadata::MetadataStore) {
+        let restored = self
+            .existing_sqlite_db(codebase_path)
+            .and_then(|db| db.map_or(Ok(0), |db| db.restore_metadata(store)));
+        match restored {
+            Ok(0) => {}
+            Ok(count) => {
+                tracing::info!("Restored {} metadata entries of {} from its SQLite file", count, codebase_path.display());
+                if let Err(e) = self.rebuild_keyword_index(codebase_path, store) {
+                    tracing::warn!("Failed to rebuild the keyword index of {} from its restored metadata: {}", codebase_path.display(), e);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to restore the metadata of {} from its SQLite file: {}", codebase_path.display(), e),
+        }
+    }
+    
+    /// The SQLite file doesn't carry the keyword index, so a restored store
+    /// gets a fresh one built from its chunks instead of an empty one
+    #[cfg(feature = "sqlite")]
+    fn rebuild_keyword_index(&self, codebase_path: &Path, store: &crate::metadata::MetadataStore) -> Result<()> {
+        const STORAGE_BATCH_SIZE: usize = 50;
+        let keyword_dir = BM25Search::get_index_path_for_codebase(codebase_path, &self.config.storage.data_dir);
+        if keyword_dir.exists() {
+            std::fs::remove_dir_all(&keyword_dir)?;
+        }
+        let mut bm25 = self.get_bm25_search(codebase_path)?;
+        let chunks: Vec<crate::types::CodeChunk> = store.iter().map(|(id, metadata)| metadata.to_chunk(&id)).collect();
+        for chunk_batch in chunks.chunks(STORAGE_BATCH_SIZE) {
+            bm25.insert(chunk_batch.iter().map(crate::search::BM25Document::from).collect())?;
+        }
+        tracing::info!("Rebuilt the keyword index of {} from {} restored chunks", codebase_path.display(), chunks.len());
+        Ok(())
+    }
+    
+    #[cfg(not(feature = "sqlite"))]
+    fn restore_metadata_from_sqlite(&self, _codebase_path: &Path, _store: &crate::metadata::MetadataStore) {}
+    
     fn get_bm25_search(&self, codebase_path: &Path) -> Result<BM25Search> {
-        BM25Search::for_codebase(codebase_path, &self.config.storage.data_dir)
+        self.get_bm25_search_with_tokenizer(codebase_path, None)
+    }
+    
+    /// Tokenizer only applies when the index is created; existing indexes keep their own
+    fn get_bm25_search_with_tokenizer(&self, codebase_path: &Path, tokenizer: Option<TokenizerKind>) -> Result<BM25Search> {
+        let mut analyzer = self.config.search.analyzer.clone();
+        if let Some(tokenizer) = tokenizer {
+            analyzer.tokenizer = tokenizer;
+        }
+        BM25Search::for_codebase(codebase_path, &self.config.storage.data_dir, &analyzer)
     }
     
     fn get_hybrid_search(&self) -> HybridSearch {
@@ -66,28 +522,149 @@ impl ToolHandlers {
         if let Some(store) = stores.get(&path_key) {
             Ok(Arc::clone(store))
         } else {
+            let missing = !crate::metadata::MetadataStore::exists(codebase_path, &self.config.storage.data_dir);
             let store = crate::metadata::MetadataStore::for_codebase(codebase_path, &self.config.storage.data_dir)?;
+            if missing {
+                self.restore_metadata_from_sqlite(codebase_path, &store);
+            }
             let store_arc = Arc::new(Mutex::new(store));
             stores.insert(path_key, Arc::clone(&store_arc));
             Ok(store_arc)
         }
     }
 
+    /// Model of the active provider, which a fallback chain may have switched
+    fn embedding_model(&self) -> &str {
+        self.embedding.active_model().unwrap_or(&self.config.embedding.model)
+    }
+    
+    /// Configured document prefix of the active provider; fallbacks detect their own
+    fn document_prefix(&self) -> Option<&str> {
+        self.config
+            .embedding
+            .document_prefix
+            .as_deref()
+            .filter(|_| self.embedding_model() == self.config.embedding.model)
+    }
+    
+    /// Provider that built the codebase's index when it is still in the
+    /// fallback chain or among the extra providers, so queries land in the
+    /// same vector space; otherwise the active one
+    async fn index_embedding(&self, codebase_path: &Path) -> &dyn EmbeddingProvider {
+        let settings = match self.get_metadata_store(codebase_path).await {
+            Ok(metadata_store) => metadata_store.lock().await.index_settings().ok().flatten(),
+            Err(_) => None,
+        };
+        settings
+            .and_then(|settings| {
+                self.embedding
+                    .provider_for(&settings.embedding_provider, &settings.embedding_model)
+                    .or_else(|| {
+                        self.extra_embedding(&settings.embedding_provider, &settings.embedding_model)
+                            .map(|extra| extra.provider.as_ref())
+                    })
+            })
+            .unwrap_or(self.embedding.as_ref())
+    }
+    
+    /// Whether the index was embedded by the active model with another
+    /// normalization or document prefix than configured; unknown for indexes
+    /// built before they were recorded
+    fn embedding_options_changed(&self, settings: &crate::metadata::IndexSettings) -> bool {
+        let Some(normalize) = settings.embedding_normalize else {
+            return false;
+        };
+        settings.embedding_provider == self.embedding.provider_name()
+            && settings.embedding_model == self.embedding_model()
+            && (normalize != self.config.embedding.normalize
+                || settings.embedding_document_prefix.as_deref() != self.document_prefix())
+    }
+    
+    /// Fails with `Error::ModelMismatch` when no configured provider can query
+    /// the codebase's index, because it was built with another provider, model
+    /// or dimension
+    async fn ensure_index_model(&self, codebase_path: &Path) -> Result<()> {
+        let settings = match self.get_metadata_store(codebase_path).await {
+            Ok(metadata_store) => metadata_store.lock().await.index_settings().ok().flatten(),
+            Err(_) => None,
+        };
+        let Some(settings) = settings else {
+            return Ok(());
+        };
+        self.index_docs_embedding(codebase_path).await?;
+        if self.embedding_options_changed(&settings) {
+            return Err(Error::ModelMismatch(format!(
+                "Codebase '{}' was embedded with EMBEDDING_NORMALIZE={} and document prefix {:?}, but the server is configured with EMBEDDING_NORMALIZE={} and document prefix {:?}. \
+                 Run migrate_embeddings or index_codebase with force=true to re-embed the stored chunks, or restore the previous settings.",
+                codebase_path.display(),
+                settings.embedding_normalize.unwrap_or_default(),
+                settings.embedding_document_prefix.as_deref().unwrap_or(""),
+                self.config.embedding.normalize,
+                self.document_prefix().unwrap_or("")
+            )));
+        }
+        if self.embedding.provider_for(&settings.embedding_provider, &settings.embedding_model).is_some()
+            || self.extra_embedding(&settings.embedding_provider, &settings.embedding_model).is_some()
+            || (settings.embedding_provider == self.embedding.provider_name()
+                && settings.embedding_model == self.embedding_model()
+                && settings.embedding_dimension == self.embedding.dimension())
+        {
+            return Ok(());
+        }
+        Err(Error::ModelMismatch(format!(
+            "Codebase '{}' was indexed with {} '{}' (dimension {}), but the server embeds with {} '{}' (dimension {}). \
+             Re-index required with model '{}': run migrate_embeddings to re-embed the stored chunks, run index_codebase with force=true, or configure {} '{}' again to search the existing index.",
+            codebase_path.display(),
+            settings.embedding_provider,
+            settings.embedding_model,
+            settings.embedding_dimension,
+            self.embedding.provider_name(),
+            self.embedding_model(),
+            self.embedding.dimension(),
+            self.embedding_model(),
+            settings.embedding_provider,
+            settings.embedding_model
+        )))
+    }
+
+    /// Whether the codebase's embeddings were made in document mode, so queries
+    /// must use query mode; defaults to true so new indexes use the modes
+    async fn embeds_with_input_types(&self, codebase_path: &Path) -> bool {
+        match self.get_metadata_store(codebase_path).await {
+            Ok(metadata_store) => metadata_store.lock().await.embedding_input_types().unwrap_or(true),
+            Err(_) => true,
+        }
+    }
+
+    /// Synchronizer for a codebase and its additional roots, ignoring the
+    /// configured patterns plus `ignore_patterns`
     pub async fn get_or_create_synchronizer(
         &self,
-        codebase_path: &Path
+        codebase_path: &Path,
+        ignore_patterns: &[String],
+        additional_roots: &[PathBuf],
     ) -> Result<Arc<Mutex<FileSynchronizer>>> {
+        let root_prefixes = root_prefixes(codebase_path, additional_roots)?;
         let path_key = codebase_path.to_string_lossy().to_string();
         let mut syncs = self.synchronizers.lock().await;
         
+        // Same order as scanning, so a custom `!pattern` can re-include a configured one
+        let mut patterns = self.config.indexing.ignore_patterns.clone();
+        patterns.extend_from_slice(ignore_patterns);
+        
         if let Some(sync) = syncs.get(&path_key) {
+            let mut locked = sync.lock().await;
+            locked.set_ignore_patterns(patterns);
+            locked.set_root_prefixes(root_prefixes);
+            drop(locked);
             Ok(Arc::clone(sync))
         } else {
             let mut sync = FileSynchronizer::new(
                 codebase_path.to_path_buf(),
                 self.config.storage.data_dir.clone(),
-                self.config.indexing.ignore_patterns.clone(),
+                patterns,
             );
+            sync.set_root_prefixes(root_prefixes);
             sync.initialize().await?;
             let sync_arc = Arc::new(Mutex::new(sync));
             syncs.insert(path_key, Arc::clone(&sync_arc));
@@ -130,5 +707,56 @@ pub fn validate_codebase_path(path: &Path) -> Result<()> {
         )));
     }
     
+    if let Err(e) = std::fs::read_dir(path) {
+        if e.kind() == std::io::ErrorKind::PermissionDenied {
+            return Err(Error::PathDenied(format!(
+                "Directory is not readable: {}",
+                path.display()
+            )));
+        }
+    }
+    
     Ok(())
 }
+
+fn purge_expired_trash(config: &Config) {
+    let retention = config.storage.trash_retention();
+    if let Err(e) = crate::trash::Trash::new(&config.storage.data_dir, retention).purge_expired() {
+        tracing::warn!("[TRASH] Failed to purge expired entries: {}", e);
+    }
+}
+
+fn open_llm_reranker(config: &Config) -> Option<Arc<LlmReranker>> {
+    let rerank = config.search.llm_rerank.clone()?;
+    let cache_path = config.storage.data_dir.join("rerank_cache");
+    match LlmReranker::new(rerank, &config.embedding.http, &cache_path) {
+        Ok(reranker) => {
+            tracing::info!("[RERANK] Reranking the top {} results with {}", reranker.candidates(), reranker.model());
+            Some(Arc::new(reranker))
+        }
+        Err(e) => {
+            tracing::warn!("[RERANK] Disabled, failed to set up the rerank model: {}", e);
+            None
+        }
+    }
+}
+
+fn open_embedding_cache(config: &Config) -> Option<Arc<EmbeddingCache>> {
+    if !config.storage.embedding_cache_enabled {
+        return None;
+    }
+    
+    let path = config.storage.embedding_cache_dir();
+    let max_bytes = config.storage.embedding_cache_max_mb.saturating_mul(1024 * 1024);
+    match EmbeddingCache::open(&path, max_bytes) {
+        Ok(cache) => {
+            tracing::info!("[EMBEDDING-CACHE] {} cached embeddings in {}", cache.len(), path.display());
+            Some(Arc::new(cache))
+        }
+        Err(e) => {
+            // Indexing still works, only without reuse across codebases
+            tracing::warn!("[EMBEDDING-CACHE] Disabled, failed to open {}: {}", path.display(), e);
+            None
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Search profile handler
+//!
+//! Handles the save_search_profile MCP tool: stores named search tuning for a
+//! codebase that find_code applies when called with `profile`
+
+use super::{ToolHandlers, ensure_absolute_path, validate_codebase_path};
+use super::search::validate_extension_filter;
+use crate::metadata::SearchProfile;
+use crate::{ErrorCode, Result};
+use serde::Deserialize;
+use tracing::info;
+
+#[derive(Debug, Deserialize)]
+pub struct SaveSearchProfileArgs {
+    pub path: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub profile: SearchProfile,
+    /// Delete the profile instead of saving it
+    #[serde(default)]
+    pub remove: bool,
+}
+
+impl ToolHandlers {
+    /// Handle save_search_profile tool call - returns JSON string
+    pub async fn handle_save_search_profile(&self, args: SaveSearchProfileArgs) -> Result<String> {
+        let SaveSearchProfileArgs { path: codebase_path, name, profile, remove } = args;
+
+        let name = name.trim();
+        if name.is_empty() {
+            return Ok(serde_json::json!({
+                "error": "Profile name must not be empty",
+                "code": ErrorCode::InvalidArgument
+            }).to_string());
+        }
+        if let Err(message) = validate_profile(&profile) {
+            return Ok(serde_json::json!({
+                "error": message,
+                "code": ErrorCode::InvalidArgument
+            }).to_string());
+        }
+
+        let absolute_path = ensure_absolute_path(&codebase_path)?;
+
+        if let Err(e) = validate_codebase_path(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!("{}. Original input: '{}'", e, codebase_path),
+                "code": e.code()
+            }).to_string());
+        }
+
+        {
+            let snapshot = self.snapshot_manager.lock().await;
+            if !snapshot.is_indexed(&absolute_path) && !snapshot.is_indexing(&absolute_path) {
+                return Ok(serde_json::json!({
+                    "error": format!(
+                        "Codebase '{}' is not indexed. Please index it first using the index_codebase tool.",
+                        absolute_path.display()
+                    ),
+                    "code": ErrorCode::NotIndexed
+                }).to_string());
+            }
+        }
+
+        let metadata_store = self.get_metadata_store(&absolute_path).await?;
+        let store = metadata_store.lock().await;
+
+        if remove {
+            let message = if store.remove_search_profile(name)? {
+                info!("[PROFILE] Removed search profile '{}' of {}", name, absolute_path.display());
+                format!("Removed search profile '{}' from codebase '{}'", name, absolute_path.display())
+            } else {
+                format!("No search profile '{}' is saved for codebase '{}'", name, absolute_path.display())
+            };
+            return Ok(serde_json::json!({
+                "message": message,
+                "profiles": store.search_profiles()?.into_keys().collect::<Vec<_>>()
+            }).to_string());
+        }
+
+        let replaced = store.search_profile(name)?.is_some();
+        store.set_search_profile(name, &profile)?;
+        info!("[PROFILE] Saved search profile '{}' of {}", name, absolute_path.display());
+
+        Ok(serde_json::json!({
+            "message": format!(
+                "{} search profile '{}' for codebase '{}'. Use it with find_code's profile argument.",
+                if replaced { "Replaced" } else { "Saved" },
+                name,
+                absolute_path.display()
+            ),
+            "profile": profile,
+            "profiles": store.search_profiles()?.into_keys().collect::<Vec<_>>()
+        }).to_string())
+    }
+}
+
+/// Reject weights and boosts that would break ranking, and malformed extensions
+pub(super) fn validate_profile(profile: &SearchProfile) -> std::result::Result<(), String> {
+    for (name, weight) in [
+        ("vectorWeight", profile.vector_weight),
+        ("bm25Weight", profile.bm25_weight),
+        ("identifierWeight", profile.identifier_weight),
+        ("recencyBoost", profile.recency_boost),
+    ] {
+        if let Some(weight) = weight {
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(format!("{name} must be a non-negative number, got {weight}"));
+            }
+        }
+    }
+    if let Some(days) = profile.recency_half_life_days.filter(|days| !days.is_finite() || *days <= 0.0) {
+        return Err(format!("recencyHalfLifeDays must be a positive number, got {days}"));
+    }
+    if let Some((prefix, boost)) = profile.path_boosts.iter().find(|(_, boost)| !boost.is_finite() || **boost <= 0.0) {
+        return Err(format!("Path boost for '{prefix}' must be a positive number, got {boost}"));
+    }
+    validate_extension_filter(&profile.extension_filter)
+}
@@ -0,0 +1,106 @@
+//! Embedding provider health-check handler
+//!
+//! Handles the check_provider MCP tool: embeds a short query with the
+//! provider a search would use and reports its model, the dimension it
+//! returned, the round-trip latency and the rate-limit headers it sent, so
+//! agents can tell a down or throttled provider from an indexing problem.
+
+use super::{ToolHandlers, ensure_absolute_path};
+use crate::Result;
+use serde::Deserialize;
+use std::time::Instant;
+use tracing::{info, warn};
+
+const PING_QUERY: &str = "health check";
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CheckProviderArgs {
+    /// Check the provider that serves this codebase's searches instead of the
+    /// configured one
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+impl ToolHandlers {
+    /// Handle check_provider tool call - returns JSON string
+    pub async fn handle_check_provider(&self, args: CheckProviderArgs) -> Result<String> {
+        let codebase_handlers = match &args.path {
+            Some(path) => {
+                let absolute_path = ensure_absolute_path(path)?;
+                self.extra_handlers_for(&absolute_path).await
+            }
+            None => None,
+        };
+        let handlers = codebase_handlers.as_ref().unwrap_or(self);
+        let provider = handlers.embedding.provider_name().to_string();
+        let model = handlers.embedding_model().to_string();
+        let configured_dimension = handlers.embedding.dimension();
+
+        info!("[CHECK-PROVIDER] Pinging {} model '{}'", provider, model);
+        let started = Instant::now();
+        let result = handlers.embedding.embed_query(PING_QUERY).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let rate_limits: serde_json::Map<String, serde_json::Value> = handlers
+            .embedding
+            .rate_limits()
+            .into_iter()
+            .map(|(name, value)| (name, serde_json::Value::String(value)))
+            .collect();
+
+        let mut message = format!("{} model '{}'", provider, model);
+        let response = match result {
+            Ok(vector) => {
+                let dimension = vector.len();
+                let usable = dimension == configured_dimension
+                    && vector.iter().all(|v| v.is_finite())
+                    && vector.iter().any(|&v| v != 0.0);
+                message.push_str(&format!(
+                    " answered in {} ms with a {}-dimensional vector",
+                    latency_ms, dimension
+                ));
+                if dimension != configured_dimension {
+                    message.push_str(&format!(
+                        "\nWARNING: the index expects {} dimensions; check EMBEDDING_MODEL and EMBEDDING_DIMENSIONS",
+                        configured_dimension
+                    ));
+                } else if !usable {
+                    message.push_str("\nWARNING: the vector is zero or has non-finite values; check EMBEDDING_MODEL names an embedding model");
+                }
+                serde_json::json!({
+                    "ok": usable,
+                    "dimension": dimension,
+                })
+            }
+            Err(e) => {
+                warn!("[CHECK-PROVIDER] {} failed: {}", provider, e);
+                message.push_str(&format!(" failed after {} ms: {}", latency_ms, e));
+                serde_json::json!({
+                    "ok": false,
+                    "error": e.to_string(),
+                    "code": e.code(),
+                })
+            }
+        };
+        if rate_limits.is_empty() {
+            message.push_str("\nRate limits: not reported by this provider");
+        } else {
+            message.push_str("\nRate limits:");
+            for (name, value) in &rate_limits {
+                message.push_str(&format!("\n  {}: {}", name, value.as_str().unwrap_or_default()));
+            }
+        }
+
+        let mut report = serde_json::json!({
+            "message": message,
+            "provider": provider,
+            "model": model,
+            "configured_dimension": configured_dimension,
+            "latency_ms": latency_ms,
+            "rate_limits": rate_limits,
+        });
+        if let (Some(report), serde_json::Value::Object(outcome)) = (report.as_object_mut(), response) {
+            report.extend(outcome);
+        }
+        Ok(report.to_string())
+    }
+}
@@ -0,0 +1,135 @@
+//! Rebuild index handler
+//!
+//! Handles the rebuild_index MCP tool: regenerates the vector and keyword
+//! indexes of a codebase from the chunk content kept in its metadata store,
+//! for when `index.usearch` or the keyword index was deleted or damaged.
+//! Embeddings come from the embedding cache where it has them, so a rebuild
+//! usually costs far fewer provider calls than re-indexing the files.
+
+use super::{ToolHandlers, ensure_absolute_path, validate_codebase_path};
+use crate::{ErrorCode, Result};
+use crate::metadata::MetadataStore;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{error, info};
+
+#[derive(Debug, Deserialize)]
+pub struct RebuildIndexArgs {
+    pub path: String,
+}
+
+impl ToolHandlers {
+    /// Handle rebuild_index tool call - returns JSON string
+    pub async fn handle_rebuild_index(&self, args: RebuildIndexArgs) -> Result<String> {
+        let RebuildIndexArgs { path: codebase_path } = args;
+
+        let absolute_path = ensure_absolute_path(&codebase_path)?;
+
+        if let Err(e) = validate_codebase_path(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!("{}. Original input: '{}'", e, codebase_path),
+                "code": e.code()
+            }).to_string());
+        }
+
+        // Waits for a vacuum of the index to finish
+        let codebase_lock = self.codebase_lock(&absolute_path).await;
+        let _vacuum_done = codebase_lock.lock().await;
+        let mut snapshot = self.snapshot_manager.lock().await;
+
+        if snapshot.is_indexing(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!(
+                    "Codebase '{}' is already being indexed in the background. Please wait for completion.",
+                    absolute_path.display()
+                ),
+                "code": ErrorCode::IndexingInProgress
+            }).to_string());
+        }
+
+        let (chunk_count, settings) = if MetadataStore::exists(&absolute_path, &self.config.storage.data_dir) {
+            let metadata_store = self.get_metadata_store(&absolute_path).await?;
+            let store = metadata_store.lock().await;
+            (store.count(), store.index_settings()?)
+        } else {
+            (0, None)
+        };
+        if chunk_count == 0 {
+            return Ok(serde_json::json!({
+                "error": format!(
+                    "No stored chunks to rebuild '{}' from. Index it with the index_codebase tool instead.",
+                    absolute_path.display()
+                ),
+                "code": ErrorCode::NotIndexed
+            }).to_string());
+        }
+
+        // The rebuilt vectors must come from the models the settings name
+        let handlers = self.extra_handlers_for(&absolute_path).await.unwrap_or_else(|| self.clone());
+        if let Some(settings) = &settings {
+            if settings.embedding_provider != handlers.embedding.provider_name()
+                || settings.embedding_model != handlers.embedding_model()
+            {
+                return Ok(serde_json::json!({
+                    "error": format!(
+                        "Codebase '{}' was indexed with {} '{}', which is not configured. \
+                         Configure it again to rebuild, or re-index with force=true.",
+                        absolute_path.display(),
+                        settings.embedding_provider,
+                        settings.embedding_model
+                    ),
+                    "code": ErrorCode::ModelMismatch
+                }).to_string());
+            }
+        }
+        if handlers.docs_model_changed(&absolute_path).await {
+            return Ok(serde_json::json!({
+                "error": format!(
+                    "Codebase '{}' was indexed with another documentation model than EMBEDDING_DOCS_PROVIDER. \
+                     Configure it again to rebuild, or re-index with force=true.",
+                    absolute_path.display()
+                ),
+                "code": ErrorCode::ModelMismatch
+            }).to_string());
+        }
+
+        snapshot.set_indexing(&absolute_path, 0)?;
+        snapshot.save()?;
+        drop(snapshot);
+
+        info!("[REBUILD] Rebuilding indexes of {} from {} stored chunks", absolute_path.display(), chunk_count);
+
+        let handlers_clone = Arc::new(handlers);
+        let abs_path_clone = absolute_path.clone();
+
+        tokio::spawn(async move {
+            let started_at = chrono::Utc::now();
+            let outcome = handlers_clone.rebuild_from_metadata(&abs_path_clone).await;
+
+            if let Err(e) = &outcome {
+                error!("[REBUILD] Rebuild failed: {}", e);
+                // The old stores are gone, so the codebase is not searchable until a run succeeds
+                let mut snapshot = handlers_clone.snapshot_manager.lock().await;
+                let _ = snapshot.set_failed(
+                    &abs_path_clone,
+                    format!("Rebuild failed: {e}. Run rebuild_index again, or index_codebase with force=true."),
+                    None,
+                );
+                let _ = snapshot.save();
+            }
+
+            handlers_clone
+                .record_index_run(&abs_path_clone, "rebuild_index", "rebuild", started_at, &outcome)
+                .await;
+        });
+
+        Ok(serde_json::json!({
+            "message": format!(
+                "Started rebuilding the indexes of '{}' from {} stored chunks in the background. Use check_status to follow progress.",
+                absolute_path.display(),
+                chunk_count
+            ),
+            "results_count": chunk_count
+        }).to_string())
+    }
+}
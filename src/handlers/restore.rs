@@ -0,0 +1,95 @@
+//! Bring back an index removed by `delete_index`
+//!
+//! Deleted indexes stay in the trash for `storage.trash_retention_hours`;
+//! restoring moves the newest one of the codebase back in place.
+
+use super::{ToolHandlers, ensure_absolute_path, validate_codebase_path};
+use crate::{ErrorCode, Result};
+use crate::trash::Trash;
+use serde::Deserialize;
+use tracing::info;
+
+#[derive(Debug, Deserialize)]
+pub struct RestoreIndexArgs {
+    pub path: String,
+}
+
+impl ToolHandlers {
+    pub async fn handle_restore_index(&self, args: RestoreIndexArgs) -> Result<String> {
+        let RestoreIndexArgs { path: codebase_path } = args;
+
+        let absolute_path = ensure_absolute_path(&codebase_path)?;
+
+        if let Err(e) = validate_codebase_path(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!("{}. Original input: '{}'", e, codebase_path),
+                "code": e.code()
+            }).to_string());
+        }
+
+        let mut snapshot = self.snapshot_manager.lock().await;
+
+        if snapshot.is_indexing(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!(
+                    "Codebase '{}' is being indexed in the background. Wait for completion and delete that index before restoring.",
+                    absolute_path.display()
+                ),
+                "code": ErrorCode::IndexingInProgress
+            }).to_string());
+        }
+
+        if snapshot.is_indexed(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!(
+                    "Codebase '{}' already has an index. Delete it first to restore the previous one.",
+                    absolute_path.display()
+                ),
+                "code": ErrorCode::InvalidArgument
+            }).to_string());
+        }
+
+        let trash = Trash::new(&self.config.storage.data_dir, self.config.storage.trash_retention());
+        let Some(entry) = trash.latest_for(&absolute_path)? else {
+            return Ok(serde_json::json!({
+                "error": format!(
+                    "No deleted index of '{}' is in the trash; it was never deleted or its retention expired.",
+                    absolute_path.display()
+                ),
+                "code": ErrorCode::NotIndexed
+            }).to_string());
+        };
+
+        // A failed attempt may have left partial stores and an open metadata DB behind
+        self.metadata_stores.lock().await.remove(&absolute_path.to_string_lossy().to_string());
+        self.forget_vector_readers(&absolute_path).await;
+        trash.restore(&entry)?;
+
+        let message = match entry.info.clone() {
+            Some(info) => {
+                snapshot.set_info(&absolute_path, info);
+                format!("Restored the index of '{}'", absolute_path.display())
+            }
+            None => {
+                snapshot.set_failed(
+                    &absolute_path,
+                    "Restored an index that was deleted while indexing; run index_codebase to complete it".to_string(),
+                    None,
+                )?;
+                format!(
+                    "Restored the partial index of '{}'; run index_codebase to complete it",
+                    absolute_path.display()
+                )
+            }
+        };
+        snapshot.save()?;
+        drop(snapshot);
+
+        info!("[RESTORE] {}", message);
+
+        Ok(serde_json::json!({
+            "message": message,
+            "deleted_at": chrono::DateTime::from_timestamp(entry.deleted_at as i64, 0).map(|time| time.to_rfc3339())
+        }).to_string())
+    }
+}
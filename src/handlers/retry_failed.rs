@@ -0,0 +1,112 @@
+//! Retry failed files handler
+//!
+//! Handles the retry_failed_files MCP tool: re-indexes files whose chunks
+//! could not be stored during an earlier run
+
+use super::{ToolHandlers, ensure_absolute_path, validate_codebase_path};
+use crate::{ErrorCode, Result};
+use crate::sync::FileChanges;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{error, info};
+
+#[derive(Debug, Deserialize)]
+pub struct RetryFailedFilesArgs {
+    pub path: String,
+}
+
+impl ToolHandlers {
+    /// Handle retry_failed_files tool call - returns JSON string
+    pub async fn handle_retry_failed_files(&self, args: RetryFailedFilesArgs) -> Result<String> {
+        let RetryFailedFilesArgs { path: codebase_path } = args;
+
+        let absolute_path = ensure_absolute_path(&codebase_path)?;
+
+        if let Err(e) = validate_codebase_path(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!("{}. Original input: '{}'", e, codebase_path),
+                "code": e.code()
+            }).to_string());
+        }
+
+        let mut snapshot = self.snapshot_manager.lock().await;
+
+        if snapshot.is_indexing(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!(
+                    "Codebase '{}' is already being indexed in the background. Please wait for completion.",
+                    absolute_path.display()
+                ),
+                "code": ErrorCode::IndexingInProgress
+            }).to_string());
+        }
+
+        if !snapshot.is_indexed(&absolute_path) {
+            return Ok(serde_json::json!({
+                "error": format!(
+                    "Codebase '{}' is not indexed. Please index it first using the index_codebase tool.",
+                    absolute_path.display()
+                ),
+                "code": ErrorCode::NotIndexed
+            }).to_string());
+        }
+
+        let failed_files = {
+            let metadata_store = self.get_metadata_store(&absolute_path).await?;
+            let store = metadata_store.lock().await;
+            store.failed_files()?
+        };
+
+        if failed_files.is_empty() {
+            return Ok(serde_json::json!({
+                "message": format!("No failed files recorded for codebase '{}'", absolute_path.display()),
+                "results_count": 0
+            }).to_string());
+        }
+
+        // Files deleted since the failure only need their leftover chunks removed
+        let (modified, removed): (Vec<String>, Vec<String>) = failed_files
+            .into_iter()
+            .map(|failure| failure.relative_path)
+            .partition(|relative_path| absolute_path.join(relative_path).is_file());
+        let retried = modified.len() + removed.len();
+
+        snapshot.set_indexing(&absolute_path, 0)?;
+        snapshot.save()?;
+        drop(snapshot);
+
+        info!("[RETRY-FAILED] Re-indexing {} failed files for {}", retried, absolute_path.display());
+
+        // Re-embed with the provider that built the index
+        let handlers = self.extra_handlers_for(&absolute_path).await.unwrap_or_else(|| self.clone());
+        let handlers_clone = Arc::new(handlers);
+        let abs_path_clone = absolute_path.clone();
+        let changes = FileChanges {
+            added: Vec::new(),
+            removed,
+            modified,
+        };
+
+        tokio::spawn(async move {
+            let started_at = chrono::Utc::now();
+            let outcome = handlers_clone.process_incremental_changes(&abs_path_clone, changes).await;
+
+            if let Err(e) = &outcome {
+                error!("[RETRY-FAILED] Re-indexing failed: {}", e);
+            }
+
+            handlers_clone
+                .record_index_run(&abs_path_clone, "retry_failed_files", "incremental", started_at, &outcome)
+                .await;
+        });
+
+        Ok(serde_json::json!({
+            "message": format!(
+                "Started re-indexing {} failed files for codebase '{}' in the background. Use check_status to follow progress.",
+                retried,
+                absolute_path.display()
+            ),
+            "results_count": retried
+        }).to_string())
+    }
+}
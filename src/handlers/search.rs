@@ -3,11 +3,25 @@
 //! Handles the search_code MCP tool following claude-context logic
 
 use super::{ToolHandlers, ensure_absolute_path, validate_codebase_path};
-use crate::Result;
-use crate::types::SearchResult;
+use super::export::{self, OutputFormat};
+use super::profiles::validate_profile;
+use crate::{Error, ErrorCode, Result};
+use crate::types::{Language, SearchResult};
+use crate::search::normalize::normalize_text;
+use crate::search::query::{contains_phrase, exact_names, identifier_expansions, is_code_like, quoted_phrases};
+use crate::sync::content_hash;
+use crate::search::{BM25Search, HybridSearch, IdentifierIndex, PathGlobs};
+use crate::metadata::{MetadataStore, SearchProfile};
+use crate::config::{NormalizationConfig, VectorMetric, VectorQuantization};
+use crate::embeddings::EmbeddingProvider;
+use crate::vectordb::{self, VectorReader};
+use crate::workspace::{self, Package};
 use serde::Deserialize;
-use std::path::Path;
-use tracing::info;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
 
 #[derive(Debug, Deserialize)]
 pub struct SearchCodeArgs {
@@ -17,6 +31,113 @@ pub struct SearchCodeArgs {
     pub limit: usize,
     #[serde(default)]
     pub extension_filter: Vec<String>,
+    /// Only return chunks in this language, e.g. "rust" or "python"
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Only return chunks from files under this directory of the codebase
+    #[serde(default)]
+    pub subdirectory: Option<String>,
+    /// Only return chunks from files matching one of these globs; a leading `!` excludes
+    #[serde(default)]
+    pub include_globs: Vec<String>,
+    /// Never return chunks from files matching one of these globs
+    #[serde(default)]
+    pub exclude_globs: Vec<String>,
+    /// Only return chunks from files modified at or after this time
+    #[serde(default)]
+    pub modified_after: Option<String>,
+    /// Only return chunks from files modified at or before this time
+    #[serde(default)]
+    pub modified_before: Option<String>,
+    /// "text" (default), "markdown", "jsonl" or "sarif"
+    #[serde(default)]
+    pub output_format: Option<String>,
+    /// Only return chunks from this workspace package, by name or directory
+    #[serde(default)]
+    pub package: Option<String>,
+    /// Candidates fetched from each retriever; defaults to `search.retrieval_depth`
+    #[serde(default)]
+    pub retrieval_depth: Option<usize>,
+    /// Longest snippet per result; defaults to `search.max_snippet_chars`
+    #[serde(default)]
+    pub max_snippet_chars: Option<usize>,
+    /// Total snippet characters in the response; defaults to `search.response_char_budget`
+    #[serde(default)]
+    pub response_char_budget: Option<usize>,
+    /// Saved search profile to apply; explicit arguments override its values
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Lowest relevance a result may have; defaults to `search.min_score`
+    #[serde(default)]
+    pub min_score: Option<f32>,
+    /// Weight of the vector ranking in fusion, over the profile's and `search.vector_weight`
+    #[serde(default)]
+    pub vector_weight: Option<f32>,
+    /// Weight of the BM25 ranking in fusion, over the profile's and `search.bm25_weight`
+    #[serde(default)]
+    pub bm25_weight: Option<f32>,
+    /// Extra score share of recently modified files, over the profile's and `search.recency_boost`
+    #[serde(default)]
+    pub recency_boost: Option<f32>,
+    /// Days after which the recency boost halves, over the profile's and `search.recency_half_life_days`
+    #[serde(default)]
+    pub recency_half_life_days: Option<f32>,
+    /// Lines of the file shown before and after each result's chunk
+    #[serde(default)]
+    pub context_lines: usize,
+    /// Rerank the top fused candidates with the configured chat model;
+    /// defaults to on when one is configured
+    #[serde(default)]
+    pub rerank: Option<bool>,
+}
+
+/// Stores a codebase's searches read, gathered once per tool call; the
+/// vector readers are shared with other calls
+pub(super) struct SearchStores {
+    vector_db: Arc<dyn VectorReader>,
+    bm25: BM25Search,
+    metadata_store: Arc<Mutex<MetadataStore>>,
+    /// Documentation index, when a separate model embedded the codebase's documentation
+    docs: Option<DocsStore>,
+}
+
+/// Documentation index and the model that embeds queries for it
+pub(super) struct DocsStore {
+    vector_db: Arc<dyn VectorReader>,
+    embedding: Arc<dyn EmbeddingProvider>,
+}
+
+/// Workspace package a search is limited to
+#[derive(Debug, Clone)]
+pub(super) struct PackageScope {
+    package: Package,
+    /// All packages of the codebase, so files of nested packages are excluded
+    packages: Vec<Package>,
+}
+
+impl PackageScope {
+    fn contains(&self, relative_path: &str) -> bool {
+        workspace::package_for(&self.packages, relative_path)
+            .is_some_and(|package| package.path == self.package.path)
+    }
+}
+
+/// Inclusive modification-time window, in unix seconds
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(super) struct ModifiedRange {
+    after: Option<u64>,
+    before: Option<u64>,
+}
+
+impl ModifiedRange {
+    fn is_empty(&self) -> bool {
+        self.after.is_none() && self.before.is_none()
+    }
+
+    fn contains(&self, modified_at: u64) -> bool {
+        self.after.is_none_or(|after| modified_at >= after)
+            && self.before.is_none_or(|before| modified_at <= before)
+    }
 }
 
 fn default_limit() -> usize {
@@ -24,23 +145,82 @@ fn default_limit() -> usize {
 }
 
 impl ToolHandlers {
-    /// Handle search_code tool call - returns JSON string
-    pub async fn handle_search_code(&self, args: SearchCodeArgs) -> Result<String> {
+    /// Handle search_code tool call - returns content blocks
+    ///
+    /// The first block is the JSON summary; text results follow as one block each so
+    /// clients can page through them, while export formats stay a single document.
+    pub async fn handle_search_code(&self, args: SearchCodeArgs) -> Result<Vec<String>> {
         let SearchCodeArgs {
             path: codebase_path,
             query,
             limit,
             extension_filter,
+            language,
+            subdirectory,
+            include_globs,
+            exclude_globs,
+            modified_after,
+            modified_before,
+            output_format,
+            package,
+            retrieval_depth,
+            max_snippet_chars,
+            response_char_budget,
+            profile,
+            min_score,
+            vector_weight,
+            bm25_weight,
+            recency_boost,
+            recency_half_life_days,
+            context_lines,
+            rerank,
         } = args;
 
-        let result_limit = limit.min(50); // Cap at 50 like claude-context
+        if let Some(min_score) = min_score.filter(|score| !(0.0..=1.0).contains(score)) {
+            return Ok(vec![serde_json::json!({
+                "error": format!("Invalid minScore: {}. Use a score between 0 and 1.", min_score),
+                "code": ErrorCode::InvalidArgument
+            }).to_string()]);
+        }
+
+        // Ranking arguments, applied over the profile's
+        let overrides = SearchProfile {
+            vector_weight,
+            bm25_weight,
+            recency_boost,
+            recency_half_life_days,
+            ..SearchProfile::default()
+        };
+        if let Err(e) = validate_profile(&overrides) {
+            return Ok(vec![serde_json::json!({ "error": e, "code": ErrorCode::InvalidArgument }).to_string()]);
+        }
+
+        let reranker = match (rerank, &self.llm_reranker) {
+            (Some(false), _) => None,
+            (_, Some(reranker)) => Some(Arc::clone(reranker)),
+            (Some(true), None) => {
+                return Ok(vec![serde_json::json!({
+                    "error": "Reranking is not configured on this server; set RERANK_LLM_MODEL to enable it.",
+                    "code": ErrorCode::InvalidArgument
+                }).to_string()]);
+            }
+            (None, None) => None,
+        };
+
+        let output_format = match output_format.as_deref().map(str::parse::<OutputFormat>).transpose() {
+            Ok(format) => format.unwrap_or_default(),
+            Err(e) => {
+                return Ok(vec![serde_json::json!({ "error": e, "code": ErrorCode::InvalidArgument }).to_string()]);
+            }
+        };
 
         let absolute_path = ensure_absolute_path(&codebase_path)?;
 
         if let Err(e) = validate_codebase_path(&absolute_path) {
-            return Ok(serde_json::json!({
-                "error": format!("{}. Original input: '{}'", e, codebase_path)
-            }).to_string());
+            return Ok(vec![serde_json::json!({
+                "error": format!("{}. Original input: '{}'", e, codebase_path),
+                "code": e.code()
+            }).to_string()]);
         }
 
         let snapshot = self.snapshot_manager.lock().await;
@@ -49,12 +229,13 @@ impl ToolHandlers {
         let is_indexing = snapshot.is_indexing(&absolute_path);
 
         if !is_indexed && !is_indexing {
-            return Ok(serde_json::json!({
+            return Ok(vec![serde_json::json!({
                 "error": format!(
                     "Codebase '{}' is not indexed. Please index it first using the index_codebase tool.",
                     absolute_path.display()
-                )
-            }).to_string());
+                ),
+                "code": ErrorCode::NotIndexed
+            }).to_string()]);
         }
 
         let indexing_status_message = if is_indexing {
@@ -65,41 +246,269 @@ impl ToolHandlers {
 
         drop(snapshot);
 
+        let profile = match profile {
+            Some(name) => {
+                let store = self.get_metadata_store(&absolute_path).await?;
+                let store = store.lock().await;
+                match store.search_profile(&name)? {
+                    Some(profile) => {
+                        info!("[SEARCH] Using search profile '{}'", name);
+                        Some(profile)
+                    }
+                    None => {
+                        let names: Vec<String> = store.search_profiles()?.into_keys().collect();
+                        let known = if names.is_empty() {
+                            "no search profiles are saved for this codebase; create one with save_search_profile".to_string()
+                        } else {
+                            format!("known profiles: {}", names.join(", "))
+                        };
+                        return Ok(vec![serde_json::json!({
+                            "error": format!("Unknown search profile '{}'; {}", name, known),
+                            "code": ErrorCode::InvalidArgument
+                        }).to_string()]);
+                    }
+                }
+            }
+            None => None,
+        };
+        let profile = if overrides != SearchProfile::default() {
+            let mut profile = profile.unwrap_or_default();
+            profile.vector_weight = overrides.vector_weight.or(profile.vector_weight);
+            profile.bm25_weight = overrides.bm25_weight.or(profile.bm25_weight);
+            profile.recency_boost = overrides.recency_boost.or(profile.recency_boost);
+            profile.recency_half_life_days = overrides.recency_half_life_days.or(profile.recency_half_life_days);
+            Some(profile)
+        } else {
+            profile
+        };
+
+        // Explicit arguments take precedence over the profile
+        let (extension_filter, package, retrieval_depth) = match &profile {
+            Some(profile) => (
+                if extension_filter.is_empty() { profile.extension_filter.clone() } else { extension_filter },
+                package.or_else(|| profile.package.clone()),
+                retrieval_depth.or(profile.retrieval_depth),
+            ),
+            None => (extension_filter, package, retrieval_depth),
+        };
+
+        let result_limit = limit.min(self.config.search.max_results);
+        // Fusion needs at least as many candidates as results
+        let depth_ceiling = self.config.search.max_retrieval_depth.max(self.config.search.retrieval_depth);
+        let depth = retrieval_depth
+            .unwrap_or(self.config.search.retrieval_depth)
+            .min(depth_ceiling)
+            .max(result_limit);
+
         info!("[SEARCH] Searching in codebase: {}", absolute_path.display());
         info!("[SEARCH] Query: \"{}\"", query);
+        if is_code_like(&query) {
+            info!("[SEARCH] Code-like query: matching its words, BM25 weight {}", self.config.search.code_query_bm25_weight);
+        }
+        let phrases = quoted_phrases(&query);
+        if !phrases.is_empty() {
+            info!("[SEARCH] Exact phrases required: {:?}", phrases);
+        }
+        let expansions = identifier_expansions(&query);
+        if !expansions.is_empty() {
+            info!("[SEARCH] Keyword search also matches identifier spellings: {:?}", expansions);
+        }
         info!("[SEARCH] Indexing status: {}", if is_indexing { "In Progress" } else { "Completed" });
 
         info!("[SEARCH] Using embedding provider: {} for search", self.embedding.provider_name());
         info!("[SEARCH] Generating embeddings for query using {}...", self.embedding.provider_name());
 
-        if !extension_filter.is_empty() {
-            for ext in &extension_filter {
-                if !ext.starts_with('.') || ext.len() <= 1 || ext.contains(' ') {
-                    return Ok(serde_json::json!({
-                        "error": format!(
-                            "Invalid file extension in extensionFilter: '{}'. Use proper extensions like '.ts', '.py'.",
-                            ext
-                        )
-                    }).to_string());
+        if let Err(message) = validate_extension_filter(&extension_filter) {
+            return Ok(vec![serde_json::json!({
+                "error": message,
+                "code": ErrorCode::InvalidArgument
+            }).to_string()]);
+        }
+
+        // A language is searched as the extensions of its files, which the
+        // vector and keyword indexes both filter on
+        let extension_filter = match language.as_deref().map(str::trim) {
+            Some(name) => {
+                let language = name.parse::<Language>().unwrap_or(Language::Unknown);
+                if language == Language::Unknown {
+                    return Ok(vec![serde_json::json!({
+                        "error": format!("Unknown language '{}'; known languages: {}", name, known_languages().join(", ")),
+                        "code": ErrorCode::InvalidArgument
+                    }).to_string()]);
+                }
+                let language_extensions = language.extensions();
+                if extension_filter.is_empty() {
+                    language_extensions
+                } else {
+                    let common: Vec<String> = extension_filter
+                        .into_iter()
+                        .filter(|ext| language_extensions.contains(ext))
+                        .collect();
+                    if common.is_empty() {
+                        return Ok(vec![serde_json::json!({
+                            "error": format!(
+                                "None of the extensions in extensionFilter are {} files ({})",
+                                language.as_str(),
+                                language_extensions.join(", ")
+                            ),
+                            "code": ErrorCode::InvalidArgument
+                        }).to_string()]);
+                    }
+                    common
+                }
+            }
+            None => extension_filter,
+        };
+
+        let mut path_globs = match PathGlobs::new(&include_globs, &exclude_globs) {
+            Ok(path_globs) => path_globs,
+            Err(e) => return Ok(vec![serde_json::json!({ "error": e.to_string(), "code": e.code() }).to_string()]),
+        };
+        if let Some(subdirectory) = &subdirectory {
+            match relative_subdirectory(&absolute_path, subdirectory) {
+                Ok(Some(directory)) => {
+                    info!("[SEARCH] Scoped to directory {}/", directory);
+                    path_globs = path_globs.within(&directory);
                 }
+                Ok(None) => {}
+                Err(e) => return Ok(vec![serde_json::json!({ "error": e, "code": ErrorCode::InvalidArgument }).to_string()]),
             }
         }
 
-        let query_embedding = self.embedding.embed(&query).await?;
+        let now = chrono::Utc::now().timestamp().max(0) as u64;
+        let mut modified_range = ModifiedRange::default();
+        for (name, value, bound) in [
+            ("modifiedAfter", &modified_after, &mut modified_range.after),
+            ("modifiedBefore", &modified_before, &mut modified_range.before),
+        ] {
+            if let Some(value) = value {
+                match parse_time_bound(value, now) {
+                    Some(timestamp) => *bound = Some(timestamp),
+                    None => {
+                        return Ok(vec![serde_json::json!({
+                            "error": format!(
+                                "Invalid {}: '{}'. Use a date (2024-05-01), an RFC 3339 timestamp, or a relative age like '14d', '2w' or '12h'.",
+                                name,
+                                value
+                            ),
+                            "code": ErrorCode::InvalidArgument
+                        }).to_string()]);
+                    }
+                }
+            }
+        }
 
-        let search_results = self.hybrid_search_with_filter(
+        let package_scope = match package {
+            Some(name) => {
+                let packages = self.get_metadata_store(&absolute_path).await?.lock().await.packages()?;
+                match workspace::find_package(&packages, &name) {
+                    Some(package) => Some(PackageScope { package: package.clone(), packages }),
+                    None => {
+                        let known = if packages.is_empty() {
+                            "no workspace packages were detected in this codebase".to_string()
+                        } else {
+                            format!(
+                                "known packages: {}",
+                                packages.iter().map(|p| p.name.as_str()).collect::<Vec<_>>().join(", ")
+                            )
+                        };
+                        return Ok(vec![serde_json::json!({
+                            "error": format!("Unknown package '{}'; {}", name, known),
+                            "code": ErrorCode::InvalidArgument
+                        }).to_string()]);
+                    }
+                }
+            }
+            None => None,
+        };
+        if let Some(scope) = &package_scope {
+            info!("[SEARCH] Scoped to package {} ({})", scope.package.name, scope.package.path);
+        }
+
+        // The keyword indexes don't need the provider, so an outage degrades
+        // the search to them instead of failing it
+        let query_embedding = match self.embed_search_query(&absolute_path, &query).await {
+            Ok(embedding) => Some(embedding),
+            Err(e @ Error::ModelMismatch(_)) => {
+                return Ok(vec![serde_json::json!({ "error": e.to_string(), "code": e.code() }).to_string()]);
+            }
+            Err(e) if e.code() == ErrorCode::ProviderUnavailable => {
+                warn!("[SEARCH] Embedding the query failed, searching keywords only: {}", e);
+                None
+            }
+            Err(e) => return Err(e),
+        };
+        let degraded_warning = query_embedding.is_none().then(|| {
+            format!(
+                "Embedding provider {} is unavailable; results come from keyword (BM25) and identifier matching only, without semantic ranking",
+                self.embedding.provider_name()
+            )
+        });
+
+        // The model picks the results from more candidates than are returned
+        let fused_limit = match &reranker {
+            Some(reranker) => result_limit.max(reranker.candidates()),
+            None => result_limit,
+        };
+        let stores = self.open_search_stores(&absolute_path).await?;
+        let mut search_results = self.hybrid_search_with_filter(
+            &stores,
             &absolute_path,
             &query,
-            query_embedding.as_slice(),
-            result_limit,
+            query_embedding.as_deref(),
+            fused_limit,
+            depth,
             &extension_filter,
+            modified_range,
+            package_scope.as_ref(),
+            &path_globs,
+            profile.as_ref(),
+            min_score.unwrap_or(self.config.search.min_score),
         ).await?;
 
+        if let Some(reranker) = &reranker {
+            // The fused order is still a ranking
+            if let Err(e) = reranker.rerank(&query, &mut search_results).await {
+                warn!("[SEARCH] Reranking failed, keeping the fused order: {}", e);
+            }
+            search_results.truncate(result_limit);
+        }
+
+        if context_lines > 0 {
+            expand_context(&mut search_results, context_lines.min(MAX_CONTEXT_LINES)).await;
+            // Widened results of one file can now touch
+            if self.config.search.stitch_adjacent {
+                search_results = stitch_adjacent(search_results);
+            }
+        }
+
         info!("[SEARCH] Search completed! Found {} results using {} embeddings",
             search_results.len(),
-            self.embedding.provider_name()
+            if degraded_warning.is_some() { "no" } else { self.embedding.provider_name() }
         );
 
+        let document = match output_format {
+            OutputFormat::Text => None,
+            OutputFormat::Markdown => Some(export::render_markdown(&search_results, &query, &absolute_path)),
+            OutputFormat::Jsonl => Some(export::render_jsonl(&search_results)),
+            OutputFormat::Sarif => Some(export::render_sarif(&search_results, &query, &absolute_path)),
+        };
+        if let Some(document) = document {
+            return Ok(vec![serde_json::json!({
+                "message": document,
+                "format": output_format.as_str(),
+                "results_count": search_results.len(),
+                "indexing_in_progress": is_indexing,
+                "degraded": degraded_warning.is_some(),
+                "warning": degraded_warning
+            }).to_string()]);
+        }
+
+        let degraded_note = degraded_warning
+            .as_ref()
+            .map(|warning| format!("\n\n**Degraded search**: {}.", warning))
+            .unwrap_or_default();
+
         if search_results.is_empty() {
             let mut no_results_message = format!(
                 "No results found for query: \"{}\" in codebase '{}'",
@@ -112,13 +521,20 @@ impl ToolHandlers {
                     "\n\nNote: This codebase is still being indexed. Try searching again after indexing completes, or the query may not match any indexed content."
                 );
             }
+            no_results_message.push_str(&degraded_note);
 
-            return Ok(serde_json::json!({
-                "message": no_results_message
-            }).to_string());
+            return Ok(vec![serde_json::json!({
+                "message": no_results_message,
+                "degraded": degraded_warning.is_some()
+            }).to_string()]);
         }
 
-        let formatted_results = self.format_search_results(&search_results, &absolute_path);
+        let result_blocks = self.format_search_results(
+            &search_results,
+            &absolute_path,
+            response_char_budget.unwrap_or(self.config.search.response_char_budget),
+            max_snippet_chars.unwrap_or(self.config.search.max_snippet_chars),
+        );
 
         let mut result_message = format!(
             "Found {} results for query: \"{}\" in codebase '{}'{}",
@@ -128,90 +544,351 @@ impl ToolHandlers {
             indexing_status_message
         );
 
-        result_message.push_str("\n\n");
-        result_message.push_str(&formatted_results);
-
         if is_indexing {
             result_message.push_str(
                 "\n\n**Tip**: This codebase is still being indexed. More results may become available as indexing progresses."
             );
         }
+        result_message.push_str(&degraded_note);
 
-        Ok(serde_json::json!({
+        let mut blocks = Vec::with_capacity(result_blocks.len() + 1);
+        blocks.push(serde_json::json!({
             "message": result_message,
-            "results_count": search_results.len()
-        }).to_string())
+            "results_count": search_results.len(),
+            "degraded": degraded_warning.is_some()
+        }).to_string());
+        blocks.extend(result_blocks);
+
+        Ok(blocks)
     }
 }
 
 impl ToolHandlers {
-    /// Perform hybrid search with optional extension filter
-    async fn hybrid_search_with_filter(
+    /// Hybrid search returning structured results, without the tool's prose formatting
+    pub async fn search_results(&self, codebase_path: &Path, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        let query_embedding = self.embed_search_query(codebase_path, query).await?;
+        let stores = self.open_search_stores(codebase_path).await?;
+
+        self.hybrid_search_with_filter(
+            &stores,
+            codebase_path,
+            query,
+            Some(query_embedding.as_slice()),
+            limit,
+            self.config.search.retrieval_depth.max(limit),
+            &[],
+            ModifiedRange::default(),
+            None,
+            &PathGlobs::default(),
+            None,
+            self.config.search.min_score,
+        ).await
+    }
+
+    /// Open the stores searches of a codebase read, once for any number of queries
+    pub(super) async fn open_search_stores(&self, codebase_path: &Path) -> Result<SearchStores> {
+        let dimension = self.index_embedding(codebase_path).await.dimension();
+        let docs = match self.index_docs_embedding(codebase_path).await? {
+            Some(docs) => Some(DocsStore {
+                vector_db: self.get_vector_reader(codebase_path, docs.provider.dimension(), true).await?,
+                embedding: Arc::clone(&docs.provider),
+            }),
+            None => None,
+        };
+        Ok(SearchStores {
+            vector_db: self.get_vector_reader(codebase_path, dimension, false).await?,
+            bm25: self.get_bm25_search(codebase_path)?,
+            metadata_store: self.get_metadata_store(codebase_path).await?,
+            docs,
+        })
+    }
+
+    /// Embed a query the same way the codebase's chunks were embedded
+    pub(super) async fn embed_search_query(&self, codebase_path: &Path, query: &str) -> Result<Vec<f32>> {
+        self.ensure_index_model(codebase_path).await?;
+        let embedding = self.index_embedding(codebase_path).await;
+        self.embed_query_with(embedding, codebase_path, query).await
+    }
+
+    /// Embed a query with `embedding` the way the codebase's chunks were embedded
+    async fn embed_query_with(&self, embedding: &dyn EmbeddingProvider, codebase_path: &Path, query: &str) -> Result<Vec<f32>> {
+        let embedding_query = normalize_text(query, &self.index_normalization(codebase_path).await);
+        if self.embeds_with_input_types(codebase_path).await {
+            embedding.embed_query(&embedding_query).await
+        } else {
+            embedding.embed(&embedding_query).await
+        }
+    }
+
+    /// Unicode normalization the codebase's chunks were embedded with; the
+    /// configured one for indexes built before it was recorded
+    async fn index_normalization(&self, codebase_path: &Path) -> NormalizationConfig {
+        let settings = match self.get_metadata_store(codebase_path).await {
+            Ok(metadata_store) => metadata_store.lock().await.index_settings().ok().flatten(),
+            Err(_) => None,
+        };
+        settings
+            .and_then(|settings| settings.text_normalization)
+            .unwrap_or(self.config.search.analyzer.normalization)
+    }
+
+    /// Perform hybrid search with optional extension filter, fusing `depth`
+    /// candidates from each retriever with the weights and boosts of `profile`;
+    /// without a query embedding only the keyword retrievers run. Results
+    /// score their fused share of a chunk ranked first by every leg, which
+    /// boosts can push past 1; those whose `raw_relevance` is under
+    /// `min_score` are dropped.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) async fn hybrid_search_with_filter(
         &self,
+        stores: &SearchStores,
         codebase_path: &Path,
         query_text: &str,
-        query_embedding: &[f32],
+        query_embedding: Option<&[f32]>,
         limit: usize,
+        depth: usize,
         extension_filter: &[String],
+        modified_range: ModifiedRange,
+        package_scope: Option<&PackageScope>,
+        path_globs: &PathGlobs,
+        profile: Option<&SearchProfile>,
+        min_score: f32,
     ) -> Result<Vec<SearchResult>> {
-        let vector_results = {
-            let vector_db = self.get_vector_db(codebase_path)?;
-            vector_db.search_codebase(codebase_path, query_embedding, 50).await?
-        };
-
-        let bm25_results = {
-            let bm25 = self.get_bm25_search(codebase_path)?;
-            bm25.search_codebase(codebase_path, query_text, 50).await?
-        };
-
-        let hybrid_search = self.get_hybrid_search();
-        let combined_results = hybrid_search.rerank(vector_results, bm25_results);
-
-        let metadata_store = self.get_metadata_store(codebase_path).await?;
-        let metadata_store_guard = metadata_store.lock().await;
-        
-        let mut results = Vec::new();
-        for (rank, (chunk_id, score)) in combined_results.iter().enumerate() {
-            if let Some(metadata) = metadata_store_guard.get(chunk_id)? {
-                let result = SearchResult {
-                    file_path: metadata.file_path.clone(),
-                    relative_path: metadata.relative_path.clone(),
-                    start_line: metadata.start_line,
-                    end_line: metadata.end_line,
-                    content: metadata.content.clone(),
-                    language: metadata.language.clone(),
-                    score: *score,
-                    rank: rank + 1,
-                };
-                results.push(result);
-            }
-        }
-        
-        if !extension_filter.is_empty() {
-            results.retain(|result| {
-                if let Some(ext) = std::path::Path::new(&result.file_path).extension() {
-                    let ext_str = format!(".{}", ext.to_string_lossy());
-                    extension_filter.contains(&ext_str)
-                } else {
-                    false
+        // Quoted phrases are literal strings every result must contain
+        let phrases = quoted_phrases(query_text);
+        let filtered = !extension_filter.is_empty()
+            || !modified_range.is_empty()
+            || package_scope.is_some()
+            || !path_globs.is_empty()
+            || !phrases.is_empty();
+        let max_depth = self.config.search.max_retrieval_depth.max(depth);
+        // The keyword index filters on its raw paths, extensions included
+        let keyword_globs = path_globs.clone().with_extensions(extension_filter);
+
+        // Pasted code is matched on its exact tokens more reliably than on its embedding
+        let code_like = is_code_like(query_text);
+        let exact_match_boost = self.config.search.exact_match_boost;
+        let names = if exact_match_boost > 0.0 { exact_names(query_text) } else { Vec::new() };
+        let SearchStores { vector_db, bm25, metadata_store, docs } = stores;
+        let docs_query = match docs.as_ref().filter(|_| query_embedding.is_some()) {
+            Some(docs) => match self.embed_query_with(docs.embedding.as_ref(), codebase_path, query_text).await {
+                Ok(embedding) => Some((docs, embedding)),
+                Err(e) if e.code() == ErrorCode::ProviderUnavailable => {
+                    warn!("[SEARCH] Embedding the query for documentation failed, skipping the documentation index: {}", e);
+                    None
+                }
+                Err(e) => return Err(e),
+            },
+            None => None,
+        };
+
+        let rrf_k = profile.and_then(|profile| profile.rrf_k).unwrap_or(self.config.search.rrf_k);
+        let mut hybrid_search = HybridSearch::new(rrf_k).with_vector_weight(self.config.search.vector_weight);
+        let mut bm25_weight = self.config.search.bm25_weight;
+        if let Some(profile) = profile {
+            if let Some(weight) = profile.vector_weight {
+                hybrid_search = hybrid_search.with_vector_weight(weight);
+            }
+            if let Some(weight) = profile.identifier_weight {
+                hybrid_search = hybrid_search.with_identifier_weight(weight);
+            }
+            bm25_weight = profile.bm25_weight.unwrap_or(bm25_weight);
+        }
+        if code_like {
+            bm25_weight *= self.config.search.code_query_bm25_weight;
+        }
+        let hybrid_search = hybrid_search.with_bm25_weight(bm25_weight);
+        let recency_boost = profile
+            .and_then(|profile| profile.recency_boost)
+            .unwrap_or(self.config.search.recency_boost);
+        let recency_half_life_days = profile
+            .and_then(|profile| profile.recency_half_life_days)
+            .unwrap_or(self.config.search.recency_half_life_days);
+        // Scores are shares of what a chunk ranked first by every leg would get
+        let best_score = hybrid_search.best_score(query_embedding.is_some()).max(f32::MIN_POSITIVE);
+
+        // Filters run after fusion, so a narrow filter can discard most of the
+        // candidates; fetch deeper until enough survive
+        let mut depth = depth;
+        loop {
+            let vector_results = match query_embedding {
+                Some(query_embedding) => {
+                    self.vector_candidates(
+                        vector_db.as_ref(),
+                        metadata_store,
+                        query_embedding,
+                        depth,
+                        extension_filter,
+                        path_globs,
+                    )
+                    .await?
+                }
+                None => Vec::new(),
+            };
+            let docs_vector_results = match &docs_query {
+                Some((docs, docs_embedding)) => {
+                    self.vector_candidates(
+                        docs.vector_db.as_ref(),
+                        metadata_store,
+                        docs_embedding,
+                        depth,
+                        extension_filter,
+                        path_globs,
+                    )
+                    .await?
+                }
+                None => Vec::new(),
+            };
+            let bm25_results = bm25.search_codebase(codebase_path, query_text, depth, &keyword_globs).await?;
+
+            let metadata_store_guard = metadata_store.lock().await;
+            let identifier_index = metadata_store_guard.identifier_index()?;
+            let identifier_results = identifier_index.search(query_text, depth)?;
+
+            // Every retriever returned less than asked: searching deeper finds nothing new
+            let exhausted = vector_results.len() < depth
+                && docs_vector_results.len() < depth
+                && bm25_results.len() < depth
+                && identifier_results.len() < depth;
+
+            let relevance = raw_relevance(&[vector_results.as_slice(), docs_vector_results.as_slice()], &bm25_results, &identifier_results);
+            let combined_results = hybrid_search.rerank_with_docs(
+                vector_results,
+                docs_vector_results,
+                bm25_results,
+                identifier_results,
+            );
+
+            let mut results = Vec::new();
+            let mut boosted = false;
+            for (rank, (chunk_id, score)) in combined_results.iter().enumerate() {
+                // Fused scores only reflect ranks, so the threshold applies to how well the chunk matched
+                if min_score > 0.0 && relevance.get(chunk_id).is_none_or(|&relevance| relevance < min_score) {
+                    continue;
                 }
-            });
+                let mut score = score / best_score;
+                if let Some(metadata) = metadata_store_guard.get(chunk_id)? {
+                    if !phrases.iter().all(|phrase| contains_phrase(&metadata.content, phrase)) {
+                        continue;
+                    }
+                    // Names the query spells out rank their definitions and uses above looser matches
+                    let multiplier = exact_match_multiplier(&identifier_index, chunk_id, &metadata.content, &names, exact_match_boost)?;
+                    boosted |= multiplier > 1.0;
+                    score *= multiplier;
+                    let result = SearchResult {
+                        file_path: metadata.file_path.clone(),
+                        relative_path: metadata.relative_path.clone(),
+                        start_line: metadata.start_line,
+                        end_line: metadata.end_line,
+                        content: metadata.content.clone(),
+                        language: metadata.language.clone(),
+                        score,
+                        rank: rank + 1,
+                        modified_at: metadata.modified_at,
+                        file_hash: metadata.file_hash.clone(),
+                        stale: false,
+                    };
+                    results.push(result);
+                }
+            }
+            drop(metadata_store_guard);
+            if boosted {
+                sort_by_score(&mut results);
+            }
+
+            if let Some(profile) = profile.filter(|profile| !profile.path_boosts.is_empty()) {
+                results = apply_path_boosts(results, profile);
+            }
+            if recency_boost > 0.0 {
+                results = apply_recency_boost(results, recency_boost, recency_half_life_days).await;
+            }
+
+            let mut results = apply_filters(results, extension_filter, modified_range, package_scope, path_globs).await;
+
+            if !filtered || results.len() >= limit || exhausted || depth >= max_depth {
+                results.truncate(limit);
+                if self.config.search.stitch_adjacent {
+                    results = stitch_adjacent(results);
+                }
+                mark_stale(&mut results).await;
+                return Ok(results);
+            }
+
+            let next_depth = (depth * DEPTH_GROWTH).min(max_depth);
+            info!(
+                "[SEARCH] Filters kept {} of {} requested results at depth {}; retrying at depth {}",
+                results.len(),
+                limit,
+                depth,
+                next_depth
+            );
+            depth = next_depth;
+        }
+    }
+
+    /// Best `depth` matches of `vector_db` among files with the filter's
+    /// extensions and paths (where the store can filter), re-scored by the
+    /// full vectors when the index is quantized
+    async fn vector_candidates(
+        &self,
+        vector_db: &dyn VectorReader,
+        metadata_store: &Mutex<MetadataStore>,
+        query_embedding: &[f32],
+        depth: usize,
+        extension_filter: &[String],
+        path_globs: &PathGlobs,
+    ) -> Result<Vec<vectordb::SearchResult>> {
+        if vector_db.quantization() == VectorQuantization::None {
+            return vector_db.search_filtered(query_embedding, depth, extension_filter, path_globs).await;
         }
+        let candidates = vector_db
+            .search_filtered(
+                query_embedding,
+                depth * self.config.search.rerank_oversample,
+                extension_filter,
+                path_globs,
+            )
+            .await?;
+        let ids: Vec<String> = candidates.iter().map(|candidate| candidate.id.clone()).collect();
+        let full_vectors = metadata_store.lock().await.full_vectors(&ids)?;
+        Ok(rescore_candidates(candidates, &full_vectors, query_embedding, depth, vector_db.metric()))
+    }
 
-        results.truncate(limit);
-        Ok(results)
+    /// One formatted block per result, with snippets trimmed to fit the response budget
+    pub(super) fn format_search_results(
+        &self,
+        results: &[SearchResult],
+        codebase_path: &Path,
+        response_char_budget: usize,
+        max_snippet_chars: usize,
+    ) -> Vec<String> {
+        let codebase_paths = vec![codebase_path; results.len()];
+        self.format_tagged_search_results(results, &codebase_paths, response_char_budget, max_snippet_chars)
     }
 
-    fn format_search_results(&self, results: &[SearchResult], codebase_path: &Path) -> String {
-        let codebase_name = codebase_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown");
+    /// Like `format_search_results`, for results of the codebase at the same
+    /// position of `codebase_paths`
+    pub(super) fn format_tagged_search_results(
+        &self,
+        results: &[SearchResult],
+        codebase_paths: &[&Path],
+        response_char_budget: usize,
+        max_snippet_chars: usize,
+    ) -> Vec<String> {
+        let lengths: Vec<usize> = results.iter().map(|result| result.content.len()).collect();
+        let snippet_budget = response_char_budget.saturating_sub(results.len() * RESULT_HEADER_ALLOWANCE);
+        let budgets = allocate_snippet_budgets(&lengths, snippet_budget, max_snippet_chars);
 
         results
             .iter()
+            .zip(budgets)
+            .zip(codebase_paths)
             .enumerate()
-            .map(|(index, result)| {
+            .map(|(index, ((result, budget), codebase_path))| {
+                let codebase_name = codebase_path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("unknown");
                 let location = format!(
                     "{}:{}-{}",
                     result.relative_path,
@@ -219,29 +896,601 @@ impl ToolHandlers {
                     result.end_line
                 );
 
-                let context = self.truncate_content(&result.content, 5000);
+                let context = self.truncate_content(&result.content, budget);
+
+                let mut modified = export::format_modified(result.modified_at)
+                    .map(|modified| format!("\n   Modified: {modified}"))
+                    .unwrap_or_default();
+                if result.stale {
+                    modified.push_str("\n   Note: file changed since indexing; content may be stale");
+                }
 
                 format!(
-                    "{}. Code snippet ({}) [{}]\n   Location: {}\n   Rank: {}\n   Context: \n```{}\n{}\n```\n",
+                    "{}. Code snippet ({}) [{}]\n   Location: {}{}\n   Rank: {}\n   Context: \n```{}\n{}\n```\n",
                     index + 1,
                     result.language,
                     codebase_name,
                     location,
+                    modified,
                     index + 1,
                     result.language,
                     context
                 )
             })
-            .collect::<Vec<_>>()
-            .join("\n")
+            .collect()
     }
 
     fn truncate_content(&self, content: &str, max_length: usize) -> String {
         if content.len() <= max_length {
             content.to_string()
         } else {
-            let truncated = &content[..max_length];
-            format!("{truncated}...\n[Content truncated]")
+            let mut end = max_length;
+            while !content.is_char_boundary(end) {
+                end -= 1;
+            }
+            // Prefer cutting at a line break when one is reasonably close
+            if let Some(newline) = content[..end].rfind('\n') {
+                if newline >= end / 2 {
+                    end = newline;
+                }
+            }
+            let truncated = &content[..end];
+            format!(
+                "{truncated}...\n[Content truncated: {} of {} chars shown]",
+                truncated.chars().count(),
+                content.chars().count()
+            )
+        }
+    }
+}
+
+/// Reject entries of `extension_filter` that are not extensions like ".ts"
+pub(super) fn validate_extension_filter(extension_filter: &[String]) -> std::result::Result<(), String> {
+    match extension_filter.iter().find(|ext| !ext.starts_with('.') || ext.len() <= 1 || ext.contains(' ')) {
+        Some(ext) => Err(format!(
+            "Invalid file extension in extensionFilter: '{ext}'. Use proper extensions like '.ts', '.py'."
+        )),
+        None => Ok(()),
+    }
+}
+
+/// `subdirectory` of the codebase, absolute or relative, as a relative path
+/// without leading or trailing slashes; `None` for the codebase root
+fn relative_subdirectory(codebase_path: &Path, subdirectory: &str) -> std::result::Result<Option<String>, String> {
+    let path = Path::new(subdirectory.trim());
+    let relative = if path.is_absolute() {
+        path.strip_prefix(codebase_path)
+            .map_err(|_| format!("subdirectory '{}' is outside the codebase '{}'", subdirectory, codebase_path.display()))?
+    } else {
+        path
+    };
+    let mut parts = Vec::new();
+    for component in relative.components() {
+        match component {
+            std::path::Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            std::path::Component::CurDir => {}
+            _ => return Err(format!("subdirectory '{subdirectory}' must be a directory inside the codebase, without '..'")),
+        }
+    }
+    if parts.is_empty() {
+        return Ok(None);
+    }
+    let directory = parts.join("/");
+    if !codebase_path.join(&directory).is_dir() {
+        return Err(format!("subdirectory '{}' does not exist in codebase '{}'", directory, codebase_path.display()));
+    }
+    Ok(Some(directory))
+}
+
+/// Names of the languages files are indexed as, as the `language` filter accepts them
+fn known_languages() -> Vec<&'static str> {
+    let mut names: Vec<&'static str> = Language::supported_extensions()
+        .iter()
+        .map(|ext| Language::from_extension(ext))
+        .filter(|language| *language != Language::Unknown)
+        .map(|language| language.as_str())
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+/// Factor by which retrieval deepens when filters leave too few results
+const DEPTH_GROWTH: usize = 4;
+
+/// Results that pass the extension, package, path glob and modification-time filters
+async fn apply_filters(
+    mut results: Vec<SearchResult>,
+    extension_filter: &[String],
+    modified_range: ModifiedRange,
+    package_scope: Option<&PackageScope>,
+    path_globs: &PathGlobs,
+) -> Vec<SearchResult> {
+    if !extension_filter.is_empty() {
+        results.retain(|result| {
+            if let Some(ext) = std::path::Path::new(&result.file_path).extension() {
+                let ext_str = format!(".{}", ext.to_string_lossy());
+                extension_filter.contains(&ext_str)
+            } else {
+                false
+            }
+        });
+    }
+
+    if let Some(scope) = package_scope {
+        results.retain(|result| scope.contains(&result.relative_path));
+    }
+
+    if !path_globs.is_empty() {
+        results.retain(|result| path_globs.matches(&result.relative_path));
+    }
+
+    if !modified_range.is_empty() {
+        let mut in_range = Vec::with_capacity(results.len());
+        for mut result in results {
+            // Chunks indexed before mtimes were recorded fall back to the file on disk
+            if result.modified_at.is_none() {
+                result.modified_at = super::index::file_modified_at(&result.file_path).await;
+            }
+            if result.modified_at.is_some_and(|modified_at| modified_range.contains(modified_at)) {
+                in_range.push(result);
+            }
+        }
+        results = in_range;
+    }
+
+    results
+}
+
+/// Re-score candidates of a quantized index by the index's `metric` with
+/// their full-precision vectors, on the scale of its scores, and keep the
+/// best `depth`; a candidate without one keeps its quantized score
+fn rescore_candidates(
+    mut candidates: Vec<vectordb::SearchResult>,
+    full_vectors: &[Option<Vec<f32>>],
+    query: &[f32],
+    depth: usize,
+    metric: VectorMetric,
+) -> Vec<vectordb::SearchResult> {
+    let query_norm = query.iter().map(|v| v * v).sum::<f32>().sqrt();
+    for (candidate, vector) in candidates.iter_mut().zip(full_vectors) {
+        let Some(vector) = vector.as_ref().filter(|vector| vector.len() == query.len()) else {
+            continue;
+        };
+        let dot: f32 = vector.iter().zip(query).map(|(a, b)| a * b).sum();
+        match metric {
+            VectorMetric::Cosine => {
+                let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+                if norm > 0.0 && query_norm > 0.0 {
+                    candidate.score = dot / (norm * query_norm);
+                }
+            }
+            VectorMetric::Dot => candidate.score = dot,
+            VectorMetric::L2 => {
+                let distance: f32 = vector.iter().zip(query).map(|(a, b)| (a - b) * (a - b)).sum();
+                candidate.score = 1.0 - distance / 2.0;
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    candidates.truncate(depth);
+    candidates
+}
+
+/// `1 + boost` when the chunk defines one of `names` as written, `1 + boost / 2`
+/// when it uses one whose use counts, 1 otherwise
+fn exact_match_multiplier(
+    index: &IdentifierIndex,
+    chunk_id: &str,
+    content: &str,
+    names: &[(String, bool)],
+    boost: f32,
+) -> Result<f32> {
+    let mut multiplier = 1.0;
+    for (name, use_counts) in names {
+        match index.exact_match(chunk_id, name, content)? {
+            Some(true) => return Ok(1.0 + boost),
+            Some(false) if *use_counts => multiplier = 1.0 + boost / 2.0,
+            _ => {}
+        }
+    }
+    Ok(multiplier)
+}
+
+/// How well each candidate matched, whatever its rank: its cosine
+/// similarity to the query, or, for keyword matches, its BM25 or identifier
+/// score as a share of that leg's best, whichever is highest
+fn raw_relevance(
+    vector_legs: &[&[vectordb::SearchResult]],
+    bm25_results: &[crate::search::BM25Result],
+    identifier_results: &[crate::search::IdentifierResult],
+) -> HashMap<String, f32> {
+    let mut relevance: HashMap<String, f32> = HashMap::new();
+    let mut raise = |id: &str, score: f32| {
+        let entry = relevance.entry(id.to_string()).or_insert(f32::MIN);
+        *entry = entry.max(score);
+    };
+    for results in vector_legs {
+        for result in results.iter() {
+            raise(&result.id, result.score);
+        }
+    }
+    let best_bm25 = bm25_results.iter().map(|result| result.score).fold(0.0, f32::max);
+    for result in bm25_results.iter().filter(|_| best_bm25 > 0.0) {
+        raise(&result.id, result.score / best_bm25);
+    }
+    let best_identifier = identifier_results.iter().map(|result| result.score).fold(0.0, f32::max);
+    for result in identifier_results.iter().filter(|_| best_identifier > 0.0) {
+        raise(&result.id, result.score / best_identifier);
+    }
+    relevance
+}
+
+/// Order results by score, best first, and number their ranks
+fn sort_by_score(results: &mut [SearchResult]) {
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    for (index, result) in results.iter_mut().enumerate() {
+        result.rank = index + 1;
+    }
+}
+
+/// Scale scores by the profile's path boosts and re-rank
+fn apply_path_boosts(mut results: Vec<SearchResult>, profile: &SearchProfile) -> Vec<SearchResult> {
+    for result in &mut results {
+        result.score *= profile.path_boost(&result.relative_path);
+    }
+    sort_by_score(&mut results);
+    results
+}
+
+/// Scale scores by how recently each result's file was modified and re-rank
+async fn apply_recency_boost(mut results: Vec<SearchResult>, boost: f32, half_life_days: f32) -> Vec<SearchResult> {
+    let now = chrono::Utc::now().timestamp().max(0) as u64;
+    for result in &mut results {
+        // Chunks indexed before mtimes were recorded fall back to the file on disk
+        if result.modified_at.is_none() {
+            result.modified_at = super::index::file_modified_at(&result.file_path).await;
+        }
+        if let Some(modified_at) = result.modified_at {
+            result.score *= recency_multiplier(modified_at, now, boost, half_life_days);
+        }
+    }
+    sort_by_score(&mut results);
+    results
+}
+
+/// `1 + boost` for a file modified at `now`, decaying toward 1 by half every
+/// `half_life_days`; files dated in the future count as modified now
+fn recency_multiplier(modified_at: u64, now: u64, boost: f32, half_life_days: f32) -> f32 {
+    let age_days = now.saturating_sub(modified_at) as f32 / 86_400.0;
+    1.0 + boost * 0.5f32.powf(age_days / half_life_days.max(f32::MIN_POSITIVE))
+}
+
+/// Flag results whose file changed since their chunk was indexed
+///
+/// An unchanged modification time is taken as unchanged content; otherwise
+/// the file is hashed and compared with the hash recorded at indexing time.
+/// Chunks indexed before hashes were recorded are never flagged.
+async fn mark_stale(results: &mut [SearchResult]) {
+    let mut checked: HashMap<PathBuf, bool> = HashMap::new();
+    for result in results.iter_mut() {
+        let Some(indexed_hash) = &result.file_hash else { continue };
+        if let Some(&stale) = checked.get(&result.file_path) {
+            result.stale = stale;
+            continue;
+        }
+
+        let stale = if result.modified_at.is_some()
+            && super::index::file_modified_at(&result.file_path).await == result.modified_at
+        {
+            false
+        } else {
+            match tokio::fs::read(&result.file_path).await {
+                Ok(content) => content_hash(&content) != *indexed_hash,
+                // Deleted or unreadable since indexing
+                Err(_) => true,
+            }
+        };
+        checked.insert(result.file_path.clone(), stale);
+        result.stale = stale;
+    }
+}
+
+/// Most context lines a result is widened by on each side
+const MAX_CONTEXT_LINES: usize = 100;
+
+/// Widen each result by `lines` lines of its file on each side, read from
+/// disk. Results whose file changed since indexing keep their stored
+/// content, since their line numbers may no longer point at it.
+async fn expand_context(results: &mut [SearchResult], lines: usize) {
+    let mut files: HashMap<PathBuf, Option<Vec<String>>> = HashMap::new();
+    for result in results.iter_mut() {
+        if result.stale {
+            continue;
+        }
+        if !files.contains_key(&result.file_path) {
+            let content = tokio::fs::read_to_string(&result.file_path).await.ok();
+            let file_lines = content.map(|content| content.lines().map(str::to_string).collect());
+            files.insert(result.file_path.clone(), file_lines);
+        }
+        let Some(Some(file_lines)) = files.get(&result.file_path) else { continue };
+        if let Some((start_line, end_line, content)) = widened(file_lines, result.start_line, result.end_line, lines) {
+            result.start_line = start_line;
+            result.end_line = end_line;
+            result.content = content;
+        }
+    }
+}
+
+/// Lines `start_line..=end_line` (1-based) of `file_lines` with `lines` more
+/// on each side, and their range; `None` when the file is shorter than the range
+fn widened(file_lines: &[String], start_line: usize, end_line: usize, lines: usize) -> Option<(usize, usize, String)> {
+    if start_line == 0 || start_line > end_line || end_line > file_lines.len() {
+        return None;
+    }
+    let start = start_line.saturating_sub(lines).max(1);
+    let end = (end_line + lines).min(file_lines.len());
+    Some((start, end, file_lines[start - 1..end].join("\n")))
+}
+
+/// Merge results from the same file whose line ranges touch or overlap into
+/// one snippet, kept at the position of its best-ranked part
+fn stitch_adjacent(results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let mut stitched: Vec<SearchResult> = Vec::with_capacity(results.len());
+
+    for result in results {
+        let Some(index) = stitched.iter().position(|kept| can_stitch(kept, &result)) else {
+            stitched.push(result);
+            continue;
+        };
+        stitched[index] = stitch_pair(&stitched[index], &result);
+
+        // The grown range may now reach a later result of the same file
+        while let Some(other) = (index + 1..stitched.len()).find(|&j| can_stitch(&stitched[index], &stitched[j])) {
+            let later = stitched.remove(other);
+            stitched[index] = stitch_pair(&stitched[index], &later);
+        }
+    }
+
+    stitched
+}
+
+fn can_stitch(a: &SearchResult, b: &SearchResult) -> bool {
+    // Content lines must map one-to-one onto the line range to splice them
+    let whole_lines = |r: &SearchResult| r.content.lines().count() == r.end_line + 1 - r.start_line;
+
+    a.file_path == b.file_path
+        && b.start_line <= a.end_line + 1
+        && a.start_line <= b.end_line + 1
+        && whole_lines(a)
+        && whole_lines(b)
+}
+
+fn stitch_pair(a: &SearchResult, b: &SearchResult) -> SearchResult {
+    let (first, second) = if a.start_line <= b.start_line { (a, b) } else { (b, a) };
+
+    let content = if second.end_line <= first.end_line {
+        first.content.clone()
+    } else {
+        let already_shown = first.end_line + 1 - second.start_line;
+        let tail: Vec<&str> = second.content.lines().skip(already_shown).collect();
+        format!("{}\n{}", first.content.trim_end_matches('\n'), tail.join("\n"))
+    };
+
+    SearchResult {
+        start_line: first.start_line,
+        end_line: first.end_line.max(second.end_line),
+        content,
+        score: a.score.max(b.score),
+        rank: a.rank.min(b.rank),
+        modified_at: a.modified_at.or(b.modified_at),
+        ..a.clone()
+    }
+}
+
+/// Room reserved per result for the location/rank header and code fences
+const RESULT_HEADER_ALLOWANCE: usize = 200;
+
+/// Split `budget` across snippets: short snippets keep their full length and
+/// what they don't use is shared among the longer ones.
+fn allocate_snippet_budgets(lengths: &[usize], budget: usize, max_per_result: usize) -> Vec<usize> {
+    let mut budgets = vec![0; lengths.len()];
+    let mut order: Vec<usize> = (0..lengths.len()).collect();
+    order.sort_by_key(|&i| lengths[i]);
+
+    let mut remaining = budget;
+    for (position, &i) in order.iter().enumerate() {
+        let share = remaining / (order.len() - position);
+        budgets[i] = lengths[i].min(share).min(max_per_result);
+        remaining -= budgets[i];
+    }
+
+    budgets
+}
+
+/// Parse a `modifiedAfter`/`modifiedBefore` value into unix seconds
+///
+/// Accepts RFC 3339 timestamps, plain dates (midnight UTC) and relative ages
+/// such as "12h", "14d" or "2w" (measured back from `now`).
+fn parse_time_bound(value: &str, now: u64) -> Option<u64> {
+    let value = value.trim();
+
+    if let Ok(timestamp) = chrono::DateTime::parse_from_rfc3339(value) {
+        return u64::try_from(timestamp.timestamp()).ok();
+    }
+
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let midnight = date.and_hms_opt(0, 0, 0)?.and_utc();
+        return u64::try_from(midnight.timestamp()).ok();
+    }
+
+    let unit_start = value.find(|c: char| !c.is_ascii_digit())?;
+    let amount: u64 = value[..unit_start].parse().ok()?;
+    let seconds_per_unit = match &value[unit_start..] {
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        _ => return None,
+    };
+    Some(now.saturating_sub(amount.saturating_mul(seconds_per_unit)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rescore_candidates_uses_full_vectors() {
+        let candidate = |id: &str, score: f32| vectordb::SearchResult { id: id.to_string(), score };
+        // The quantized index ranked "rough" first; the exact vectors say otherwise
+        let candidates = vec![candidate("rough", 0.9), candidate("exact", 0.8), candidate("missing", 0.5)];
+        let full_vectors = vec![Some(vec![0.0, 1.0]), Some(vec![1.0, 0.1]), None];
+
+        let rescored = rescore_candidates(candidates, &full_vectors, &[1.0, 0.0], 2, VectorMetric::Cosine);
+        assert_eq!(rescored.len(), 2);
+        assert_eq!(rescored[0].id, "exact");
+        assert_eq!(rescored[1].id, "missing");
+        assert_eq!(rescored[1].score, 0.5);
+    }
+
+    #[test]
+    fn test_raw_relevance_takes_the_best_signal() {
+        let vector = |id: &str, score: f32| vectordb::SearchResult { id: id.to_string(), score };
+        let bm25 = |id: &str, score: f32| crate::search::BM25Result { id: id.to_string(), score };
+        let vectors = vec![vector("close", 0.8), vector("far", 0.1), vector("keyword", 0.05)];
+        let keywords = vec![bm25("keyword", 12.0), bm25("weak", 3.0)];
+
+        let relevance = raw_relevance(&[vectors.as_slice()], &keywords, &[]);
+        assert_eq!(relevance["close"], 0.8);
+        assert_eq!(relevance["far"], 0.1);
+        // Ranked last by the vector leg, but the best keyword match
+        assert_eq!(relevance["keyword"], 1.0);
+        assert_eq!(relevance["weak"], 0.25);
+    }
+
+    #[test]
+    fn test_parse_time_bound() {
+        let now = 1_700_000_000;
+        assert_eq!(parse_time_bound("2023-11-14", now), Some(1_699_920_000));
+        assert_eq!(parse_time_bound("2023-11-14T22:13:20Z", now), Some(1_700_000_000));
+        assert_eq!(parse_time_bound("14d", now), Some(now - 14 * 86_400));
+        assert_eq!(parse_time_bound("2w", now), Some(now - 14 * 86_400));
+        assert_eq!(parse_time_bound("12h", now), Some(now - 12 * 3_600));
+        assert_eq!(parse_time_bound("last sprint", now), None);
+        assert_eq!(parse_time_bound("10", now), None);
+    }
+
+    #[test]
+    fn test_allocate_snippet_budgets() {
+        // Short snippets are kept whole and the rest is shared by the long ones
+        assert_eq!(allocate_snippet_budgets(&[100, 10_000, 10_000], 6_100, 5_000), vec![100, 3_000, 3_000]);
+        // The per-result cap still applies when the budget is generous
+        assert_eq!(allocate_snippet_budgets(&[8_000, 200], 100_000, 5_000), vec![5_000, 200]);
+        assert_eq!(allocate_snippet_budgets(&[500, 500], 0, 5_000), vec![0, 0]);
+    }
+
+    #[test]
+    fn test_widened() {
+        let file_lines: Vec<String> = (1..=10).map(|line| format!("line {line}")).collect();
+        assert_eq!(widened(&file_lines, 4, 5, 2), Some((2, 7, "line 2\nline 3\nline 4\nline 5\nline 6\nline 7".to_string())));
+        // Clamped to the file
+        assert_eq!(widened(&file_lines, 2, 9, 5).map(|(start, end, _)| (start, end)), Some((1, 10)));
+        // The file lost lines since indexing
+        assert_eq!(widened(&file_lines, 9, 12, 1), None);
+    }
+
+    #[test]
+    fn test_stitch_adjacent() {
+        let result = |path: &str, start_line: usize, lines: &[&str], rank: usize| SearchResult {
+            file_path: std::path::PathBuf::from(path),
+            relative_path: path.to_string(),
+            start_line,
+            end_line: start_line + lines.len() - 1,
+            content: lines.join("\n"),
+            language: "rust".to_string(),
+            score: 1.0 / rank as f32,
+            rank,
+            modified_at: None,
+            file_hash: None,
+            stale: false,
+        };
+
+        let stitched = stitch_adjacent(vec![
+            result("a.rs", 10, &["c", "d", "e"], 1),
+            result("b.rs", 1, &["x"], 2),
+            result("a.rs", 1, &["a", "b"], 3),
+            // Overlaps the merged range by one line
+            result("a.rs", 12, &["e", "f"], 4),
+        ]);
+
+        assert_eq!(stitched.len(), 3);
+        assert_eq!((stitched[0].start_line, stitched[0].end_line), (10, 13));
+        assert_eq!(stitched[0].content, "c\nd\ne\nf");
+        assert_eq!(stitched[0].rank, 1);
+        // Lines 1-2 and 10-13 do not touch
+        assert_eq!(stitched[2].content, "a\nb");
+    }
+
+    #[test]
+    fn test_recency_multiplier() {
+        let now = 1_700_000_000;
+        let day = 86_400;
+        assert!((recency_multiplier(now, now, 1.0, 30.0) - 2.0).abs() < 1e-6);
+        assert!((recency_multiplier(now - 30 * day, now, 1.0, 30.0) - 1.5).abs() < 1e-6);
+        assert!((recency_multiplier(now - 60 * day, now, 0.5, 30.0) - 1.125).abs() < 1e-6);
+        assert!((recency_multiplier(now + day, now, 1.0, 30.0) - 2.0).abs() < 1e-6);
+        assert!(recency_multiplier(now - 3650 * day, now, 1.0, 30.0) < 1.0001);
+        assert_eq!(recency_multiplier(now - day, now, 0.0, 30.0), 1.0);
+    }
+
+    #[test]
+    fn test_modified_range() {
+        let range = ModifiedRange { after: Some(100), before: Some(200) };
+        assert!(range.contains(100));
+        assert!(range.contains(200));
+        assert!(!range.contains(99));
+        assert!(!range.contains(201));
+        assert!(ModifiedRange::default().is_empty());
+    }
+
+    #[test]
+    fn test_relative_subdirectory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("services/auth")).unwrap();
+        let codebase = dir.path();
+
+        for input in ["services/auth", "./services/auth/", " services//auth "] {
+            assert_eq!(relative_subdirectory(codebase, input).unwrap().as_deref(), Some("services/auth"), "{input}");
+        }
+        let absolute = codebase.join("services/auth");
+        assert_eq!(relative_subdirectory(codebase, &absolute.to_string_lossy()).unwrap().as_deref(), Some("services/auth"));
+        assert_eq!(relative_subdirectory(codebase, "./").unwrap(), None);
+
+        assert!(relative_subdirectory(codebase, "services/billing").is_err());
+        assert!(relative_subdirectory(codebase, "../elsewhere").is_err());
+        assert!(relative_subdirectory(codebase, "/elsewhere").is_err());
+    }
+
+    #[test]
+    fn test_validate_extension_filter() {
+        assert!(validate_extension_filter(&[]).is_ok());
+        assert!(validate_extension_filter(&[".ts".to_string(), ".py".to_string()]).is_ok());
+        for invalid in ["ts", ".", ".t s"] {
+            let error = validate_extension_filter(&[".rs".to_string(), invalid.to_string()]).unwrap_err();
+            assert!(error.contains(&format!("'{invalid}'")), "{error}");
+        }
+    }
+
+    #[test]
+    fn test_languages_resolve_to_their_extensions() {
+        let languages = known_languages();
+        assert!(languages.contains(&"rust") && languages.contains(&"typescript"));
+        assert!(!languages.contains(&"unknown"));
+        for name in languages {
+            let language: Language = name.parse().unwrap();
+            assert_eq!(language.as_str(), name);
+            assert!(!language.extensions().is_empty(), "{name}");
         }
+        assert_eq!(Language::Python.extensions(), [".py", ".pyi"]);
     }
 }
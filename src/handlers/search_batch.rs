@@ -0,0 +1,174 @@
+//! Batch search handler
+//!
+//! Handles the find_code_batch MCP tool: runs several queries against one
+//! codebase, opening its vector, keyword and metadata stores once, and
+//! returns the results grouped by query.
+
+use super::search::{ModifiedRange, validate_extension_filter};
+use super::{ToolHandlers, ensure_absolute_path, validate_codebase_path};
+use crate::{ErrorCode, Result};
+use crate::search::PathGlobs;
+use serde::Deserialize;
+use tracing::info;
+
+/// Most queries one call may run
+const MAX_BATCH_QUERIES: usize = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct SearchCodeBatchArgs {
+    pub path: String,
+    pub queries: Vec<String>,
+    /// Results per query
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    #[serde(default)]
+    pub extension_filter: Vec<String>,
+    /// Longest snippet per result; defaults to `search.max_snippet_chars`
+    #[serde(default)]
+    pub max_snippet_chars: Option<usize>,
+    /// Total snippet characters across all queries; defaults to `search.response_char_budget`
+    #[serde(default)]
+    pub response_char_budget: Option<usize>,
+}
+
+fn default_limit() -> usize {
+    5
+}
+
+impl ToolHandlers {
+    /// Handle find_code_batch tool call - returns content blocks
+    ///
+    /// The first block is the JSON summary; each query's results follow as one block.
+    pub async fn handle_search_code_batch(&self, args: SearchCodeBatchArgs) -> Result<Vec<String>> {
+        let SearchCodeBatchArgs {
+            path: codebase_path,
+            queries,
+            limit,
+            extension_filter,
+            max_snippet_chars,
+            response_char_budget,
+        } = args;
+
+        let queries: Vec<String> = queries
+            .into_iter()
+            .map(|query| query.trim().to_string())
+            .filter(|query| !query.is_empty())
+            .collect();
+        if queries.is_empty() || queries.len() > MAX_BATCH_QUERIES {
+            return Ok(vec![serde_json::json!({
+                "error": format!("Pass between 1 and {} non-empty queries, got {}", MAX_BATCH_QUERIES, queries.len()),
+                "code": ErrorCode::InvalidArgument
+            }).to_string()]);
+        }
+        if let Err(message) = validate_extension_filter(&extension_filter) {
+            return Ok(vec![serde_json::json!({
+                "error": message,
+                "code": ErrorCode::InvalidArgument
+            }).to_string()]);
+        }
+
+        let absolute_path = ensure_absolute_path(&codebase_path)?;
+
+        if let Err(e) = validate_codebase_path(&absolute_path) {
+            return Ok(vec![serde_json::json!({
+                "error": format!("{}. Original input: '{}'", e, codebase_path),
+                "code": e.code()
+            }).to_string()]);
+        }
+
+        let is_indexing = {
+            let snapshot = self.snapshot_manager.lock().await;
+            let is_indexing = snapshot.is_indexing(&absolute_path);
+            if !snapshot.is_indexed(&absolute_path) && !is_indexing {
+                return Ok(vec![serde_json::json!({
+                    "error": format!(
+                        "Codebase '{}' is not indexed. Please index it first using the index_codebase tool.",
+                        absolute_path.display()
+                    ),
+                    "code": ErrorCode::NotIndexed
+                }).to_string()]);
+            }
+            is_indexing
+        };
+
+        if let Err(e) = self.ensure_index_model(&absolute_path).await {
+            return Ok(vec![serde_json::json!({ "error": e.to_string(), "code": e.code() }).to_string()]);
+        }
+
+        info!("[SEARCH-BATCH] Running {} queries in codebase: {}", queries.len(), absolute_path.display());
+
+        let embeddings = futures::future::try_join_all(
+            queries.iter().map(|query| self.embed_search_query(&absolute_path, query)),
+        )
+        .await?;
+
+        let result_limit = limit.min(self.config.search.max_results);
+        let depth = self.config.search.retrieval_depth.max(result_limit);
+        let stores = self.open_search_stores(&absolute_path).await?;
+
+        // Every query gets an equal share of the response
+        let budget = response_char_budget.unwrap_or(self.config.search.response_char_budget) / queries.len();
+        let max_snippet_chars = max_snippet_chars.unwrap_or(self.config.search.max_snippet_chars);
+
+        let mut total = 0;
+        let mut summaries = Vec::with_capacity(queries.len());
+        let mut groups = Vec::with_capacity(queries.len());
+        for (index, (query, embedding)) in queries.iter().zip(&embeddings).enumerate() {
+            let results = self.hybrid_search_with_filter(
+                &stores,
+                &absolute_path,
+                query,
+                Some(embedding.as_slice()),
+                result_limit,
+                depth,
+                &extension_filter,
+                ModifiedRange::default(),
+                None,
+                &PathGlobs::default(),
+                None,
+                self.config.search.min_score,
+            ).await?;
+
+            let mut group = format!("## Query {}: \"{}\"\n", index + 1, query);
+            if results.is_empty() {
+                group.push_str("\nNo results found.\n");
+            } else {
+                for block in self.format_search_results(&results, &absolute_path, budget, max_snippet_chars) {
+                    group.push('\n');
+                    group.push_str(&block);
+                }
+            }
+
+            total += results.len();
+            summaries.push(serde_json::json!({
+                "query": query,
+                "results_count": results.len()
+            }));
+            groups.push(group);
+        }
+
+        info!("[SEARCH-BATCH] Completed {} queries with {} results", queries.len(), total);
+
+        let mut message = format!(
+            "Ran {} queries in codebase '{}': {} results",
+            queries.len(),
+            absolute_path.display(),
+            total
+        );
+        if is_indexing {
+            message.push_str(
+                "\n\n**Tip**: This codebase is still being indexed. More results may become available as indexing progresses."
+            );
+        }
+
+        let mut blocks = Vec::with_capacity(groups.len() + 1);
+        blocks.push(serde_json::json!({
+            "message": message,
+            "results_count": total,
+            "queries": summaries
+        }).to_string());
+        blocks.extend(groups);
+
+        Ok(blocks)
+    }
+}
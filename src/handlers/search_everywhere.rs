@@ -0,0 +1,218 @@
+//! Cross-codebase search handler
+//!
+//! Handles the find_code_everywhere MCP tool: runs one query against every
+//! indexed codebase, for developers working across many small related
+//! repositories, and merges the per-codebase rankings with reciprocal rank
+//! fusion, so each codebase's best hits surface whatever its size or score
+//! scale. Each hit is tagged with its codebase.
+
+use super::search::{ModifiedRange, validate_extension_filter};
+use super::ToolHandlers;
+use crate::{Error, ErrorCode, Result};
+use crate::types::SearchResult;
+use crate::search::PathGlobs;
+use serde::Deserialize;
+use std::path::Path;
+use tracing::{info, warn};
+
+#[derive(Debug, Deserialize)]
+pub struct SearchEverywhereArgs {
+    pub query: String,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    #[serde(default)]
+    pub extension_filter: Vec<String>,
+    /// Longest snippet per result; defaults to `search.max_snippet_chars`
+    #[serde(default)]
+    pub max_snippet_chars: Option<usize>,
+    /// Total snippet characters in the response; defaults to `search.response_char_budget`
+    #[serde(default)]
+    pub response_char_budget: Option<usize>,
+}
+
+fn default_limit() -> usize {
+    10
+}
+
+/// How the search of one codebase went
+enum CodebaseOutcome {
+    Results(Vec<SearchResult>),
+    /// Searched by keywords only, since the query could not be embedded
+    Degraded(Vec<SearchResult>),
+    Skipped(Error),
+}
+
+impl ToolHandlers {
+    /// Handle find_code_everywhere tool call - returns content blocks
+    ///
+    /// The first block is the JSON summary; results follow as one block each.
+    pub async fn handle_search_everywhere(&self, args: SearchEverywhereArgs) -> Result<Vec<String>> {
+        let SearchEverywhereArgs {
+            query,
+            limit,
+            extension_filter,
+            max_snippet_chars,
+            response_char_budget,
+        } = args;
+
+        let query = query.trim().to_string();
+        if query.is_empty() {
+            return Ok(vec![serde_json::json!({
+                "error": "Pass a non-empty query",
+                "code": ErrorCode::InvalidArgument
+            }).to_string()]);
+        }
+        if let Err(message) = validate_extension_filter(&extension_filter) {
+            return Ok(vec![serde_json::json!({
+                "error": message,
+                "code": ErrorCode::InvalidArgument
+            }).to_string()]);
+        }
+
+        let mut codebases = self.snapshot_manager.lock().await.get_indexed_codebases();
+        codebases.sort();
+        if codebases.is_empty() {
+            return Ok(vec![serde_json::json!({
+                "error": "No codebase is indexed yet. Please index one first using the index_codebase tool.",
+                "code": ErrorCode::NotIndexed
+            }).to_string()]);
+        }
+
+        info!("[SEARCH-EVERYWHERE] Searching {} codebases for \"{}\"", codebases.len(), query);
+
+        let result_limit = limit.min(self.config.search.max_results);
+        let outcomes = futures::future::join_all(
+            codebases
+                .iter()
+                .map(|codebase_path| self.search_one_codebase(codebase_path, &query, result_limit, &extension_filter)),
+        )
+        .await;
+
+        let mut lists: Vec<Vec<(f32, SearchResult)>> = Vec::with_capacity(codebases.len());
+        let mut summaries = Vec::with_capacity(codebases.len());
+        let mut degraded = false;
+        for (codebase_path, outcome) in codebases.iter().zip(outcomes) {
+            let (results, error) = match outcome {
+                CodebaseOutcome::Results(results) => (results, None),
+                CodebaseOutcome::Degraded(results) => {
+                    degraded = true;
+                    (results, None)
+                }
+                CodebaseOutcome::Skipped(e) => {
+                    warn!("[SEARCH-EVERYWHERE] Skipped {}: {}", codebase_path.display(), e);
+                    (Vec::new(), Some(e))
+                }
+            };
+            summaries.push(serde_json::json!({
+                "path": codebase_path,
+                "results_count": results.len(),
+                "error": error.as_ref().map(|e| e.to_string()),
+                "code": error.as_ref().map(|e| e.code())
+            }));
+            lists.push(results.into_iter().map(|result| (result.score, result)).collect());
+        }
+
+        let mut merged: Vec<(&Path, SearchResult)> = self
+            .get_hybrid_search()
+            .interleave(lists)
+            .into_iter()
+            .map(|(list, mut result, score)| {
+                result.score = score;
+                (codebases[list].as_path(), result)
+            })
+            .take(result_limit)
+            .collect();
+        for (rank, (_, result)) in merged.iter_mut().enumerate() {
+            result.rank = rank + 1;
+        }
+
+        info!("[SEARCH-EVERYWHERE] Found {} results across {} codebases", merged.len(), codebases.len());
+
+        let degraded_note = if degraded {
+            format!(
+                "\n\n**Degraded search**: Embedding provider {} is unavailable; some codebases were searched by keywords and identifiers only.",
+                self.embedding.provider_name()
+            )
+        } else {
+            String::new()
+        };
+
+        if merged.is_empty() {
+            return Ok(vec![serde_json::json!({
+                "message": format!(
+                    "No results found for query: \"{}\" in {} indexed codebases{}",
+                    query,
+                    codebases.len(),
+                    degraded_note
+                ),
+                "results_count": 0,
+                "codebases": summaries,
+                "degraded": degraded
+            }).to_string()]);
+        }
+
+        let (paths, results): (Vec<&Path>, Vec<SearchResult>) = merged.into_iter().unzip();
+        let result_blocks = self.format_tagged_search_results(
+            &results,
+            &paths,
+            response_char_budget.unwrap_or(self.config.search.response_char_budget),
+            max_snippet_chars.unwrap_or(self.config.search.max_snippet_chars),
+        );
+
+        let mut blocks = Vec::with_capacity(result_blocks.len() + 1);
+        blocks.push(serde_json::json!({
+            "message": format!(
+                "Found {} results for query: \"{}\" across {} indexed codebases{}",
+                results.len(),
+                query,
+                codebases.len(),
+                degraded_note
+            ),
+            "results_count": results.len(),
+            "codebases": summaries,
+            "degraded": degraded
+        }).to_string());
+        blocks.extend(result_blocks);
+
+        Ok(blocks)
+    }
+
+    /// Hybrid search of one codebase, as find_code runs it without a profile
+    async fn search_one_codebase(
+        &self,
+        codebase_path: &Path,
+        query: &str,
+        limit: usize,
+        extension_filter: &[String],
+    ) -> CodebaseOutcome {
+        let (query_embedding, degraded) = match self.embed_search_query(codebase_path, query).await {
+            Ok(embedding) => (Some(embedding), false),
+            // The keyword indexes don't need the provider
+            Err(e) if e.code() == ErrorCode::ProviderUnavailable => (None, true),
+            Err(e) => return CodebaseOutcome::Skipped(e),
+        };
+        let stores = match self.open_search_stores(codebase_path).await {
+            Ok(stores) => stores,
+            Err(e) => return CodebaseOutcome::Skipped(e),
+        };
+        let results = self.hybrid_search_with_filter(
+            &stores,
+            codebase_path,
+            query,
+            query_embedding.as_deref(),
+            limit,
+            self.config.search.retrieval_depth.max(limit),
+            extension_filter,
+            ModifiedRange::default(),
+            None,
+            &PathGlobs::default(),
+            None,
+            self.config.search.min_score,
+        ).await;
+        match results {
+            Ok(results) if degraded => CodebaseOutcome::Degraded(results),
+            Ok(results) => CodebaseOutcome::Results(results),
+            Err(e) => CodebaseOutcome::Skipped(e),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Embedding stats handler
+//!
+//! Handles the embedding_stats MCP tool: reports the request counters each
+//! embedding provider collected since the server started (or the last
+//! reset), so providers can be compared on the same hardware.
+
+use super::ToolHandlers;
+use crate::Result;
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct EmbeddingStatsArgs {
+    /// Clear the counters after reporting them
+    #[serde(default)]
+    pub reset: bool,
+}
+
+impl ToolHandlers {
+    /// Handle embedding_stats tool call - returns JSON string
+    pub async fn handle_embedding_stats(&self, args: EmbeddingStatsArgs) -> Result<String> {
+        let providers = self.telemetry.snapshot();
+        if args.reset {
+            self.telemetry.reset();
+        }
+
+        let mut message = if providers.is_empty() {
+            "No embedding requests recorded yet.".to_string()
+        } else {
+            format!("Embedding requests by provider (active: {} '{}'):", self.embedding.provider_name(), self.embedding_model())
+        };
+        for stats in &providers {
+            message.push_str(&format!(
+                "\n{} '{}': {} requests ({} texts), {} failed, {} batches retried; latency mean {:.0} ms, p50 {:.0} ms, p95 {:.0} ms, p99 {:.0} ms",
+                stats.provider,
+                stats.model,
+                stats.requests,
+                stats.texts,
+                stats.failed,
+                stats.retried_batches,
+                stats.mean_latency_ms,
+                stats.p50_latency_ms,
+                stats.p95_latency_ms,
+                stats.p99_latency_ms
+            ));
+        }
+        if args.reset {
+            message.push_str("\nCounters reset.");
+        }
+
+        Ok(serde_json::json!({
+            "message": message,
+            "providers": providers
+        }).to_string())
+    }
+}
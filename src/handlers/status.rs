@@ -22,12 +22,14 @@ impl ToolHandlers {
         // Validate path exists
         if let Err(e) = validate_codebase_path(&absolute_path) {
             return Ok(serde_json::json!({
-                "error": format!("{}. Original input: '{}'", e, codebase_path)
+                "error": format!("{}. Original input: '{}'", e, codebase_path),
+                "code": e.code()
             }).to_string());
         }
 
         let snapshot = self.snapshot_manager.lock().await;
         let status = snapshot.get_status(&absolute_path);
+        let mut embedding_progress = None;
 
         let status_message = match status {
             crate::snapshot::CodebaseStatus::Indexed(info) => {
@@ -50,6 +52,22 @@ impl ToolHandlers {
                         .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
                         .unwrap_or_else(|| "unknown".to_string())
                 ));
+                if let Some(notice) = crate::search::BM25Search::pending_analyzer_change(
+                    &absolute_path,
+                    &self.config.storage.data_dir,
+                    &self.config.search.analyzer,
+                ) {
+                    msg.push_str(&format!("\nNote: {notice}"));
+                }
+                if crate::metadata::MetadataStore::exists(&absolute_path, &self.config.storage.data_dir) {
+                    let metadata_store = self.get_metadata_store(&absolute_path).await?;
+                    let failed = metadata_store.lock().await.failed_files()?.len();
+                    if failed > 0 {
+                        msg.push_str(&format!(
+                            "\nWarning: {failed} files could not be stored after retries. Use retry_failed_files to re-index them."
+                        ));
+                    }
+                }
                 msg
             }
 
@@ -66,6 +84,19 @@ impl ToolHandlers {
                 } else if progress_percentage < 100.0 {
                     msg.push_str(" (Processing files and generating embeddings...)");
                 }
+                if let Some(progress) = self.progress.latest(&absolute_path).filter(|progress| !progress.finished) {
+                    msg.push_str(&format!(
+                        "\nEmbeddings: {}/{} chunks ({} from cache), {} batches done, ~{} remaining, {:.0} tokens/s ({:.1} chunks/s)",
+                        progress.chunks_embedded,
+                        progress.chunks_total,
+                        progress.chunks_cached,
+                        progress.batches_done,
+                        progress.batches_remaining,
+                        progress.tokens_per_second,
+                        progress.chunks_per_second
+                    ));
+                    embedding_progress = Some(progress);
+                }
 
                 msg.push_str(&format!(
                     "\nLast updated: {}",
@@ -119,8 +150,15 @@ impl ToolHandlers {
             String::new()
         };
 
-        Ok(serde_json::json!({
+        let mut response = serde_json::json!({
             "message": status_message + &path_info
-        }).to_string())
+        });
+        if let Some(progress) = embedding_progress {
+            response["embedding_progress"] = serde_json::json!(progress);
+        }
+        if let Some(branch) = &self.branch {
+            response["branch"] = serde_json::json!(branch);
+        }
+        Ok(response.to_string())
     }
 }
@@ -0,0 +1,169 @@
+//! Index stats handler
+//!
+//! Handles the index_stats MCP tool: reports the files, bytes and entries of
+//! the vector, keyword and metadata stores of a codebase, or of every known
+//! codebase largest first, so users can see which index fills their disk
+
+use super::{ToolHandlers, ensure_absolute_path, validate_codebase_path};
+use super::describe::format_size;
+use crate::config::VectorBackend;
+use crate::search::BM25Search;
+use crate::vectordb::USearchDatabase;
+use crate::{Result, StoreStats};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct IndexStatsArgs {
+    /// Codebase to report on; every known codebase when absent
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CodebaseStorage {
+    path: String,
+    vectors: StoreStats,
+    keyword_index: StoreStats,
+    metadata: StoreStats,
+    total_bytes: u64,
+    /// Why the stores could not be measured; the other codebases are still listed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl ToolHandlers {
+    /// Handle index_stats tool call - returns JSON string
+    pub async fn handle_index_stats(&self, args: IndexStatsArgs) -> Result<String> {
+        let codebases = match &args.path {
+            Some(codebase_path) => {
+                let absolute_path = ensure_absolute_path(codebase_path)?;
+                if let Err(e) = validate_codebase_path(&absolute_path) {
+                    return Ok(serde_json::json!({
+                        "error": format!("{}. Original input: '{}'", e, codebase_path),
+                        "code": e.code()
+                    }).to_string());
+                }
+                vec![absolute_path]
+            }
+            None => self.snapshot_manager.lock().await.get_codebases(),
+        };
+
+        let mut codebase_storage = Vec::with_capacity(codebases.len());
+        for codebase_path in &codebases {
+            let storage = self.codebase_storage(codebase_path).await.unwrap_or_else(|e| CodebaseStorage {
+                path: codebase_path.display().to_string(),
+                vectors: StoreStats::default(),
+                keyword_index: StoreStats::default(),
+                metadata: StoreStats::default(),
+                total_bytes: 0,
+                error: Some(e.to_string()),
+            });
+            codebase_storage.push(storage);
+        }
+        codebase_storage.sort_by(|a, b| b.total_bytes.cmp(&a.total_bytes));
+
+        let total_bytes: u64 = codebase_storage.iter().map(|storage| storage.total_bytes).sum();
+        let mut message = if codebase_storage.is_empty() {
+            "No indexed codebases.".to_string()
+        } else {
+            format!(
+                "Index storage of {} codebase(s), {} in total, largest first:",
+                codebase_storage.len(),
+                format_size(total_bytes)
+            )
+        };
+        for storage in &codebase_storage {
+            if let Some(error) = &storage.error {
+                message.push_str(&format!("\n\n{}: stores could not be read: {}", storage.path, error));
+                continue;
+            }
+            message.push_str(&format!("\n\n{}: {}", storage.path, format_size(storage.total_bytes)));
+            let dimension = storage
+                .vectors
+                .dimension
+                .map(|dimension| format!(", dimension {dimension}"))
+                .unwrap_or_default();
+            message.push_str(&format!(
+                "\n  Vectors: {} vectors{}, {} files, {}",
+                storage.vectors.entries,
+                dimension,
+                storage.vectors.files,
+                format_size(storage.vectors.bytes)
+            ));
+            for (label, stats, entries) in [
+                ("Keyword index", &storage.keyword_index, "documents"),
+                ("Metadata", &storage.metadata, "chunks"),
+            ] {
+                message.push_str(&format!(
+                    "\n  {}: {} {}, {} files, {}",
+                    label,
+                    stats.entries,
+                    entries,
+                    stats.files,
+                    format_size(stats.bytes)
+                ));
+            }
+        }
+
+        let mut response = serde_json::json!({
+            "message": message,
+            "totalBytes": total_bytes,
+            "codebases": codebase_storage
+        });
+        if let Some(branch) = &self.branch {
+            response["branch"] = serde_json::json!(branch);
+        }
+        Ok(response.to_string())
+    }
+
+    /// Stores are opened read-only where possible and accept any dimension,
+    /// so a codebase indexed with another model is still measured; a damaged
+    /// vector index is reported, not quarantined
+    async fn codebase_storage(&self, codebase_path: &Path) -> Result<CodebaseStorage> {
+        let data_dir = &self.config.storage.data_dir;
+
+        let (metadata, has_docs) = if crate::metadata::MetadataStore::exists(codebase_path, data_dir) {
+            let metadata_store = self.get_metadata_store(codebase_path).await?;
+            let store = metadata_store.lock().await;
+            let has_docs = store
+                .index_settings()?
+                .is_some_and(|settings| settings.docs_embedding_model.is_some());
+            (store.stats(), has_docs)
+        } else {
+            (StoreStats::default(), false)
+        };
+
+        let mut vectors = StoreStats::default();
+        if self.vector_store_path(codebase_path).exists() {
+            match self.config.storage.vector_backend {
+                VectorBackend::USearch => {
+                    let index_path = USearchDatabase::get_index_path_for_codebase(codebase_path, data_dir);
+                    vectors = self.get_usearch_db(index_path, codebase_path, 0, true, false).await?.stats().await?;
+                    let docs_path = USearchDatabase::get_docs_index_path_for_codebase(codebase_path, data_dir);
+                    if docs_path.exists() {
+                        vectors.add(&self.get_usearch_db(docs_path, codebase_path, 0, true, false).await?.stats().await?);
+                    }
+                }
+                VectorBackend::LanceDb | VectorBackend::Sqlite => {
+                    vectors = self.get_vector_db_with_dimension(codebase_path, 0, true).await?.stats().await?;
+                    if has_docs {
+                        vectors.add(&self.get_docs_vector_db(codebase_path, 0, true).await?.stats().await?);
+                    }
+                }
+            }
+        }
+
+        let keyword_index = BM25Search::stats_for_codebase(codebase_path, data_dir)?;
+
+        Ok(CodebaseStorage {
+            path: codebase_path.display().to_string(),
+            total_bytes: vectors.bytes + keyword_index.bytes + metadata.bytes,
+            vectors,
+            keyword_index,
+            metadata,
+            error: None,
+        })
+    }
+}
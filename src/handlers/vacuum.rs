@@ -0,0 +1,129 @@
+//! Background vacuum of vector indexes
+//!
+//! Deletes from a USearch index only unmap the vectors, so incremental runs
+//! that delete the chunks of thousands of files do not wait on one graph
+//! removal per vector. Every `VECTOR_VACUUM_INTERVAL_SECS`, a background
+//! task removes the deleted vectors of the codebases whose runs deleted
+//! chunks, between indexing runs, and rewrites their index files. Each
+//! metadata store records whether its index is due, so a restart keeps
+//! the schedule.
+
+use super::ToolHandlers;
+use crate::Result;
+use crate::config::VectorBackend;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+impl ToolHandlers {
+    /// Have the next vacuum remove the vectors deleted from the codebase's index
+    pub(super) async fn schedule_vacuum(&self, codebase_path: &Path) {
+        // The other backends delete rows outright
+        if self.config.storage.vector_backend == VectorBackend::USearch {
+            self.set_vacuum_due(codebase_path, true).await;
+            self.vacuum_due.lock().await.insert(codebase_path.to_path_buf());
+        }
+    }
+
+    /// Schedule the indexed codebases whose stores recorded a pending vacuum
+    pub(super) async fn load_vacuum_due(&self) {
+        if self.config.storage.vector_backend != VectorBackend::USearch {
+            return;
+        }
+        let indexed = self.snapshot_manager.lock().await.get_indexed_codebases();
+        for codebase_path in indexed {
+            if !crate::metadata::MetadataStore::exists(&codebase_path, &self.config.storage.data_dir) {
+                continue;
+            }
+            let due = match self.get_metadata_store(&codebase_path).await {
+                Ok(metadata_store) => metadata_store.lock().await.vacuum_due().unwrap_or(false),
+                Err(_) => false,
+            };
+            if due {
+                self.vacuum_due.lock().await.insert(codebase_path);
+            }
+        }
+    }
+
+    async fn set_vacuum_due(&self, codebase_path: &Path, due: bool) {
+        let result = match self.get_metadata_store(codebase_path).await {
+            Ok(metadata_store) => metadata_store.lock().await.set_vacuum_due(due),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            warn!("[VACUUM] Failed to record the vacuum schedule of {}: {}", codebase_path.display(), e);
+        }
+    }
+
+    /// Vacuum the scheduled indexes of these handlers and of every branch
+    /// namespace every `VECTOR_VACUUM_INTERVAL_SECS`; nothing when it is 0
+    pub fn spawn_vacuum_task(&self) {
+        let interval_secs = self.config.storage.vacuum_interval_secs;
+        if interval_secs == 0 {
+            return;
+        }
+        let handlers = self.clone();
+        tokio::spawn(async move {
+            handlers.load_vacuum_due().await;
+            let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+            // The first tick completes at once
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let branches: Vec<ToolHandlers> = handlers.branch_handlers.lock().await.values().cloned().collect();
+                for handlers in std::iter::once(&handlers).chain(&branches) {
+                    handlers.vacuum_scheduled().await;
+                }
+            }
+        });
+    }
+
+    /// Vacuum the scheduled indexes; those being indexed wait for the next round
+    pub async fn vacuum_scheduled(&self) {
+        let due: Vec<PathBuf> = self.vacuum_due.lock().await.drain().collect();
+        for codebase_path in due {
+            // Held while vacuuming so no indexing run starts writing the
+            // index; the snapshot is released, so searches go on
+            let codebase_lock = self.codebase_lock(&codebase_path).await;
+            let _vacuuming = codebase_lock.lock().await;
+            {
+                let snapshot = self.snapshot_manager.lock().await;
+                if snapshot.is_indexing(&codebase_path) {
+                    self.vacuum_due.lock().await.insert(codebase_path);
+                    continue;
+                }
+                if !snapshot.is_indexed(&codebase_path) {
+                    continue;
+                }
+            }
+
+            match self.vacuum_index(&codebase_path).await {
+                Ok(removed) => {
+                    self.set_vacuum_due(&codebase_path, false).await;
+                    if removed > 0 {
+                        info!(
+                            "[VACUUM] Removed {} deleted vectors from the index of {}",
+                            removed,
+                            codebase_path.display()
+                        );
+                    }
+                }
+                Err(e) => warn!("[VACUUM] Failed to vacuum the index of {}: {}", codebase_path.display(), e),
+            }
+        }
+    }
+
+    /// Remove the deleted vectors of the codebase's code and documentation
+    /// indexes, returning how many were removed
+    async fn vacuum_index(&self, codebase_path: &Path) -> Result<usize> {
+        // Any dimension opens the index, since no vector is compared
+        let mut removed = self.get_vector_db_with_dimension(codebase_path, 0, false).await?.vacuum().await?;
+        if self.docs_embedding.is_some() {
+            removed += self.get_docs_vector_db(codebase_path, 0, false).await?.vacuum().await?;
+        }
+        if removed > 0 {
+            self.forget_vector_readers(codebase_path).await;
+        }
+        Ok(removed)
+    }
+}
@@ -0,0 +1,151 @@
+//! Checksummed JSON state files
+//!
+//! Files that must stay consistent with other stores (vector ID mappings,
+//! change detection snapshots) are written as an envelope holding a format
+//! version and a SHA-256 of the payload, via a temporary file and a rename.
+//! A file that fails verification is moved aside rather than deleted, so it
+//! can still be inspected, and the caller rebuilds the state it held.
+
+use crate::{Error, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize)]
+struct EnvelopeRef<'a> {
+    format_version: u32,
+    checksum: String,
+    data: &'a serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    format_version: u32,
+    checksum: String,
+    data: serde_json::Value,
+}
+
+/// Write `data` to `path` with its format version and checksum
+pub fn write_checked<T: Serialize>(path: &Path, format_version: u32, data: &T) -> Result<()> {
+    let data = serde_json::to_value(data)?;
+    let checksum = checksum(&data)?;
+    let json = serde_json::to_vec(&EnvelopeRef { format_version, checksum, data: &data })?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // A crash mid-write leaves the temporary file behind, never a truncated state file
+    let temp_path = sibling(path, ".tmp");
+    let mut file = std::fs::File::create(&temp_path)?;
+    file.write_all(&json)?;
+    file.sync_all()?;
+    std::fs::rename(&temp_path, path)?;
+
+    Ok(())
+}
+
+/// Verified contents of a file written by `write_checked`, `None` if it does
+/// not exist. Files from before checksums were added are accepted unverified.
+///
+/// Returns `Error::Corrupted` for unreadable JSON, a checksum mismatch or
+/// contents that do not match `T`.
+pub fn read_checked<T: DeserializeOwned>(path: &Path, format_version: u32) -> Result<Option<T>> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::Io(e)),
+    };
+
+    let value: serde_json::Value = serde_json::from_slice(&bytes)
+        .map_err(|e| corrupted(path, format!("not valid JSON ({e})")))?;
+
+    let is_envelope = ["format_version", "checksum", "data"]
+        .iter()
+        .all(|key| value.get(key).is_some());
+
+    let data = if is_envelope {
+        let envelope: Envelope = serde_json::from_value(value)
+            .map_err(|e| corrupted(path, format!("malformed header ({e})")))?;
+        if envelope.format_version > format_version {
+            return Err(Error::Config(format!(
+                "{} was written in format version {}, newer than the supported version {}; upgrade code-sage",
+                path.display(),
+                envelope.format_version,
+                format_version
+            )));
+        }
+        if checksum(&envelope.data)? != envelope.checksum {
+            return Err(corrupted(path, "checksum mismatch".to_string()));
+        }
+        envelope.data
+    } else {
+        value
+    };
+
+    serde_json::from_value(data)
+        .map(Some)
+        .map_err(|e| corrupted(path, format!("unexpected contents ({e})")))
+}
+
+/// Move a damaged file aside as `<name>.corrupt-<timestamp>`, returning the new path
+pub fn quarantine(path: &Path) -> Result<PathBuf> {
+    let target = sibling(path, &format!(".corrupt-{}", chrono::Utc::now().format("%Y%m%dT%H%M%S")));
+    std::fs::rename(path, &target)?;
+    Ok(target)
+}
+
+fn checksum(data: &serde_json::Value) -> Result<String> {
+    Ok(format!("{:x}", Sha256::digest(serde_json::to_vec(data)?)))
+}
+
+fn corrupted(path: &Path, reason: String) -> Error {
+    Error::Corrupted(format!("{}: {}", path.display(), reason))
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_checked_roundtrip_and_damage() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let data: HashMap<String, u64> = [("a".to_string(), 1), ("b".to_string(), 2)].into();
+
+        assert!(read_checked::<HashMap<String, u64>>(&path, 1).unwrap().is_none());
+
+        write_checked(&path, 1, &data).unwrap();
+        assert_eq!(read_checked::<HashMap<String, u64>>(&path, 1).unwrap(), Some(data.clone()));
+        assert!(matches!(read_checked::<HashMap<String, u64>>(&path, 0), Err(Error::Config(_))));
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, content.replace("\"a\":1", "\"a\":7")).unwrap();
+        assert!(matches!(read_checked::<HashMap<String, u64>>(&path, 1), Err(Error::Corrupted(_))));
+
+        std::fs::write(&path, &content[..content.len() / 2]).unwrap();
+        assert!(matches!(read_checked::<HashMap<String, u64>>(&path, 1), Err(Error::Corrupted(_))));
+
+        let moved = quarantine(&path).unwrap();
+        assert!(!path.exists() && moved.exists());
+    }
+
+    #[test]
+    fn test_accepts_files_without_envelope() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("legacy.json");
+        std::fs::write(&path, r#"{"a": 1}"#).unwrap();
+
+        let data: HashMap<String, u64> = read_checked(&path, 1).unwrap().unwrap();
+        assert_eq!(data["a"], 1);
+    }
+}
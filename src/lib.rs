@@ -6,11 +6,21 @@ pub mod snapshot;
 pub mod sync;
 pub mod vectordb;
 pub mod metadata;
+pub mod retry;
+pub mod throttle;
+pub mod progress;
+pub mod integrity;
+pub mod trash;
+pub mod archive;
+pub mod workspace;
+pub mod branch;
+#[cfg(feature = "tui")]
+pub mod tui;
 
 pub mod error;
 pub mod types;
 pub mod config;
 
-pub use error::{Error, Result};
+pub use error::{Error, ErrorCode, Result};
 pub use types::*;
 pub use config::Config;
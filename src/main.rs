@@ -18,6 +18,21 @@ struct IndexCodebaseParams {
     #[schemars(description = "Force re-indexing even if already indexed")]
     #[serde(default)]
     force: bool,
+    #[schemars(description = "BM25 tokenizer: 'simple' (default) or 'cjk' for code with Chinese, Japanese or Korean comments. Changing it requires force=true")]
+    #[serde(default)]
+    tokenizer: Option<String>,
+    #[schemars(description = "Absolute paths of other directories (e.g. a shared library checked out next to this one) to index and search as part of this codebase. Omit to keep the roots of the previous run")]
+    #[serde(default)]
+    additional_roots: Vec<String>,
+    #[schemars(description = "Embedding provider to index this codebase with instead of the server's default (e.g. 'ollama', 'openai'); must be the default or listed in EMBEDDING_EXTRA_PROVIDERS. Switching providers rebuilds the index; later runs keep the chosen one")]
+    #[serde(default)]
+    embedding_provider: Option<String>,
+    #[schemars(description = "Embedding model to index this codebase with, picking among the default and extra providers")]
+    #[serde(default)]
+    embedding_model: Option<String>,
+    #[schemars(description = "Git branch to index the codebase as, kept apart from the default index and other branches (e.g. 'main' and 'feature/login'); must be the branch checked out at path. Omit for the default index")]
+    #[serde(default)]
+    branch: Option<String>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -25,22 +40,132 @@ struct IndexCodebaseParams {
 struct SearchCodeParams {
     #[schemars(description = "Absolute path to the indexed codebase directory")]
     path: String,
-    #[schemars(description = "Natural language search query")]
+    #[schemars(description = "Natural language search query; put a literal string in double quotes (\"buffer pool manager\") to only get results containing it")]
     query: String,
-    #[schemars(description = "Maximum number of results to return")]
+    #[schemars(description = "Maximum number of results to return (capped by SEARCH_MAX_RESULTS, default 50)")]
     #[serde(default = "default_limit")]
     limit: usize,
+    #[schemars(description = "Only return code in this language, e.g. 'rust', 'python', 'typescript', 'go' or 'markdown'")]
+    #[serde(default)]
+    language: Option<String>,
+    #[schemars(description = "Only return code under this directory of the codebase, e.g. 'services/auth' in a monorepo; relative to the codebase or absolute")]
+    #[serde(default)]
+    subdirectory: Option<String>,
+    #[schemars(description = "Only return code from files whose path relative to the codebase matches one of these globs, e.g. ['src/**', '**/*.{ts,tsx}']; a glob starting with '!' excludes instead, e.g. '!**/tests/**'")]
+    #[serde(default)]
+    include_globs: Vec<String>,
+    #[schemars(description = "Never return code from files whose relative path matches one of these globs, e.g. ['**/tests/**', '*.generated.ts']")]
+    #[serde(default)]
+    exclude_globs: Vec<String>,
+    #[schemars(description = "Only return code from files modified at or after this time: a date (2024-05-01), an RFC 3339 timestamp, or a relative age like '14d', '2w', '12h'")]
+    #[serde(default)]
+    modified_after: Option<String>,
+    #[schemars(description = "Only return code from files modified at or before this time (same formats as modifiedAfter)")]
+    #[serde(default)]
+    modified_before: Option<String>,
+    #[schemars(description = "Result format: 'text' (default), 'markdown' for a report, 'jsonl' for one JSON record per line, or 'sarif' for code-review tooling")]
+    #[serde(default)]
+    output_format: Option<String>,
+    #[schemars(description = "Only return code from this workspace package (Cargo, npm/yarn/pnpm or Go workspace member), given by package name or directory such as 'packages/auth'")]
+    #[serde(default)]
+    package: Option<String>,
+    #[schemars(description = "Candidates fetched from each retriever before ranking (default from SEARCH_RETRIEVAL_DEPTH, 50); filtered searches deepen automatically when too few results pass")]
+    #[serde(default)]
+    retrieval_depth: Option<usize>,
+    #[schemars(description = "Longest code snippet shown per result, in characters (default from MAX_SNIPPET_CHARS, 5000)")]
+    #[serde(default)]
+    max_snippet_chars: Option<usize>,
+    #[schemars(description = "Total characters of code snippets in the response (default from RESPONSE_CHAR_BUDGET, 40000); raise it for large-context models")]
+    #[serde(default)]
+    response_char_budget: Option<usize>,
+    #[schemars(description = "Name of a search profile saved with save_search_profile; its weights, filters and path boosts apply unless overridden by the arguments above")]
+    #[serde(default)]
+    profile: Option<String>,
+    #[schemars(description = "Drop results whose relevance, the cosine similarity to the query or the keyword score as a share of the best keyword match, is under this, from 0 to 1 (default from MIN_SCORE, 0.2); 0 keeps every candidate")]
+    #[serde(default)]
+    min_score: Option<f32>,
+    #[schemars(description = "Weight of semantic (vector) matches in the ranking, over the profile's and SEARCH_VECTOR_WEIGHT (default 1.0); raise it for conceptual questions")]
+    #[serde(default)]
+    vector_weight: Option<f32>,
+    #[schemars(description = "Weight of keyword (BM25) matches in the ranking, over the profile's and SEARCH_BM25_WEIGHT (default 1.0); raise it for queries of exact names, error messages or config keys")]
+    #[serde(default)]
+    bm25_weight: Option<f32>,
+    #[schemars(description = "Favor recently modified files: a file modified just now scores (1 + recencyBoost) times as much, decaying by half every recencyHalfLifeDays; e.g. 1.0 to find new code. Overrides the profile's and SEARCH_RECENCY_BOOST (default 0, off)")]
+    #[serde(default)]
+    recency_boost: Option<f32>,
+    #[schemars(description = "Days after which the recency boost halves (default from SEARCH_RECENCY_HALF_LIFE_DAYS, 30)")]
+    #[serde(default)]
+    recency_half_life_days: Option<f32>,
+    #[schemars(description = "Also show this many lines of the file before and after each result (at most 100), e.g. to see the enclosing signature and imports without reading the file")]
+    #[serde(default)]
+    context_lines: Option<usize>,
+    #[schemars(description = "Rerank the top candidates with the chat model set by RERANK_LLM_MODEL before returning the best ones; on by default when configured, false skips it to save time and cost")]
+    #[serde(default)]
+    rerank: Option<bool>,
+    #[schemars(description = "Search the index of this git branch, built with analyze_code and the same branch, instead of the default index")]
+    #[serde(default)]
+    branch: Option<String>,
 }
 
 fn default_limit() -> usize {
     10
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct SearchCodeBatchParams {
+    #[schemars(description = "Absolute path to the indexed codebase directory")]
+    path: String,
+    #[schemars(description = "Natural language search queries, at most 10; results are grouped per query")]
+    queries: Vec<String>,
+    #[schemars(description = "Maximum number of results per query (default 5, capped by SEARCH_MAX_RESULTS)")]
+    #[serde(default = "default_batch_limit")]
+    limit: usize,
+    #[schemars(description = "Only return code from files with these extensions, such as '.ts' or '.py'")]
+    #[serde(default)]
+    extension_filter: Vec<String>,
+    #[schemars(description = "Longest code snippet shown per result, in characters (default from MAX_SNIPPET_CHARS, 5000)")]
+    #[serde(default)]
+    max_snippet_chars: Option<usize>,
+    #[schemars(description = "Total characters of code snippets in the response, shared evenly by the queries (default from RESPONSE_CHAR_BUDGET, 40000)")]
+    #[serde(default)]
+    response_char_budget: Option<usize>,
+}
+
+fn default_batch_limit() -> usize {
+    5
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct SearchEverywhereParams {
+    #[schemars(description = "Natural language search query, run against every indexed codebase")]
+    query: String,
+    #[schemars(description = "Maximum number of results across all codebases (capped by SEARCH_MAX_RESULTS, default 10)")]
+    #[serde(default = "default_limit")]
+    limit: usize,
+    #[schemars(description = "Only return code from files with these extensions, such as '.ts' or '.py'")]
+    #[serde(default)]
+    extension_filter: Vec<String>,
+    #[schemars(description = "Longest code snippet shown per result, in characters (default from MAX_SNIPPET_CHARS, 5000)")]
+    #[serde(default)]
+    max_snippet_chars: Option<usize>,
+    #[schemars(description = "Total characters of code snippets in the response (default from RESPONSE_CHAR_BUDGET, 40000)")]
+    #[serde(default)]
+    response_char_budget: Option<usize>,
+    #[schemars(description = "Search the indexes of this git branch instead of the default indexes")]
+    #[serde(default)]
+    branch: Option<String>,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct ClearIndexParams {
     #[schemars(description = "Absolute path to the codebase directory to clear")]
     path: String,
+    #[schemars(description = "Clear the index of this git branch instead of the default index")]
+    #[serde(default)]
+    branch: Option<String>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -48,83 +173,766 @@ struct ClearIndexParams {
 struct GetIndexingStatusParams {
     #[schemars(description = "Absolute path to the codebase directory")]
     path: String,
+    #[schemars(description = "Report on the index of this git branch instead of the default index")]
+    #[serde(default)]
+    branch: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct LookupIdentifierParams {
+    #[schemars(description = "Absolute path to the indexed codebase directory")]
+    path: String,
+    #[schemars(description = "Identifier name to look up (case-insensitive)")]
+    name: String,
+    #[schemars(description = "Match whole identifiers only; set to false to also match identifiers containing the name")]
+    #[serde(default = "default_whole_word")]
+    whole_word: bool,
+    #[schemars(description = "Maximum number of locations to return")]
+    #[serde(default = "default_lookup_limit")]
+    limit: usize,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct GrepCodeParams {
+    #[schemars(description = "Absolute path to the indexed codebase directory")]
+    path: String,
+    #[schemars(description = "Literal string to find, or a regex when regex is true")]
+    pattern: String,
+    #[schemars(description = "Read the pattern as a regular expression (Rust regex syntax) instead of a literal string")]
+    #[serde(default)]
+    regex: bool,
+    #[schemars(description = "Match case exactly (default true)")]
+    #[serde(default = "default_case_sensitive")]
+    case_sensitive: bool,
+    #[schemars(description = "Only search files with these extensions, e.g. ['.rs', '.toml']")]
+    #[serde(default)]
+    extension_filter: Vec<String>,
+    #[schemars(description = "Maximum number of matching lines to return (default 100, at most 1000)")]
+    #[serde(default = "default_grep_limit")]
+    limit: usize,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct DescribeIndexParams {
+    #[schemars(description = "Absolute path to the codebase directory")]
+    path: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct IndexHistoryParams {
+    #[schemars(description = "Absolute path to the codebase directory")]
+    path: String,
+    #[schemars(description = "Maximum number of runs to return, newest first")]
+    #[serde(default = "default_history_limit")]
+    limit: usize,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct RetryFailedFilesParams {
+    #[schemars(description = "Absolute path to the codebase directory")]
+    path: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct CoverageCheckParams {
+    #[schemars(description = "Absolute path to the codebase directory")]
+    path: String,
+    #[schemars(description = "The query you are about to search for")]
+    query: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct RestoreIndexParams {
+    #[schemars(description = "Absolute path to the codebase directory whose deleted index to restore")]
+    path: String,
+    #[schemars(description = "Restore the deleted index of this git branch instead of the default index")]
+    #[serde(default)]
+    branch: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct SaveSearchProfileParams {
+    #[schemars(description = "Absolute path to the indexed codebase directory")]
+    path: String,
+    #[schemars(description = "Profile name, e.g. 'frontend' or 'infra'; saving an existing name replaces it")]
+    name: String,
+    #[schemars(description = "RRF constant k; lower values favor the top results of each retriever (default from SEARCH_RRF_K)")]
+    #[serde(default)]
+    rrf_k: Option<usize>,
+    #[schemars(description = "Weight of semantic (vector) matches in the ranking (default 1.0)")]
+    #[serde(default)]
+    vector_weight: Option<f32>,
+    #[schemars(description = "Weight of keyword (BM25) matches in the ranking (default 1.0)")]
+    #[serde(default)]
+    bm25_weight: Option<f32>,
+    #[schemars(description = "Weight of identifier-name matches in the ranking (default 1.0)")]
+    #[serde(default)]
+    identifier_weight: Option<f32>,
+    #[schemars(description = "Candidates fetched from each retriever before ranking")]
+    #[serde(default)]
+    retrieval_depth: Option<usize>,
+    #[schemars(description = "Only return code from files with these extensions, such as '.ts' or '.py'")]
+    #[serde(default)]
+    extension_filter: Vec<String>,
+    #[schemars(description = "Only return code from this workspace package, by name or directory")]
+    #[serde(default)]
+    package: Option<String>,
+    #[schemars(description = "Score multipliers keyed by path prefix, e.g. {\"src/\": 1.5, \"vendor/\": 0.3}; the longest matching prefix applies")]
+    #[serde(default)]
+    path_boosts: std::collections::BTreeMap<String, f32>,
+    #[schemars(description = "Favor recently modified files: a file modified just now scores (1 + recencyBoost) times as much, decaying by half every recencyHalfLifeDays (default from SEARCH_RECENCY_BOOST, 0)")]
+    #[serde(default)]
+    recency_boost: Option<f32>,
+    #[schemars(description = "Days after which the recency boost halves (default from SEARCH_RECENCY_HALF_LIFE_DAYS, 30)")]
+    #[serde(default)]
+    recency_half_life_days: Option<f32>,
+    #[schemars(description = "Delete the named profile instead of saving it")]
+    #[serde(default)]
+    remove: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct EstimateIndexParams {
+    #[schemars(description = "Absolute path to the codebase directory to estimate")]
+    path: String,
+    #[schemars(description = "Additional file extensions to include, as analyze_code would")]
+    #[serde(default)]
+    custom_extensions: Vec<String>,
+    #[schemars(description = "Additional ignore patterns, as analyze_code would")]
+    #[serde(default)]
+    ignore_patterns: Vec<String>,
+    #[schemars(description = "Embed a few chunks to measure the provider's speed (default true); costs only their tokens")]
+    #[serde(default = "default_estimate_sample")]
+    sample: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct CheckProviderParams {
+    #[schemars(description = "Absolute path to an indexed codebase, to check the provider its searches use (default: the configured provider)")]
+    #[serde(default)]
+    path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct EmbeddingStatsParams {
+    #[schemars(description = "Clear the counters after reporting them (default false)")]
+    #[serde(default)]
+    reset: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct RebuildIndexParams {
+    #[schemars(description = "Absolute path to the codebase directory")]
+    path: String,
+    #[schemars(description = "Rebuild the index of this git branch instead of the default index")]
+    #[serde(default)]
+    branch: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct ExportIndexParams {
+    #[schemars(description = "Absolute path to the indexed codebase directory")]
+    path: String,
+    #[schemars(description = "Absolute path of the archive file to write")]
+    archive_path: String,
+    #[schemars(description = "Export the index of this git branch instead of the default index")]
+    #[serde(default)]
+    branch: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct ImportIndexParams {
+    #[schemars(description = "Absolute path to the local checkout the index belongs to")]
+    path: String,
+    #[schemars(description = "Absolute path to an archive written by export_index")]
+    archive_path: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct MigrateEmbeddingsParams {
+    #[schemars(description = "Absolute path to the codebase directory")]
+    path: String,
+    #[schemars(description = "Re-embed the index of this git branch instead of the default index")]
+    #[serde(default)]
+    branch: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct IndexStatsParams {
+    #[schemars(description = "Absolute path to an indexed codebase (default: every indexed codebase, largest first)")]
+    #[serde(default)]
+    path: Option<String>,
+    #[schemars(description = "Report on the indexes of this git branch instead of the default indexes")]
+    #[serde(default)]
+    branch: Option<String>,
+}
+
+fn default_estimate_sample() -> bool {
+    true
+}
+
+fn default_history_limit() -> usize {
+    20
+}
+
+fn default_whole_word() -> bool {
+    true
+}
+
+fn default_case_sensitive() -> bool {
+    true
+}
+
+fn default_grep_limit() -> usize {
+    100
+}
+
+fn default_lookup_limit() -> usize {
+    100
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let command = std::env::args().nth(1);
+
+    let config = code_sage::Config::from_env()?;
+
+    match command.as_deref() {
+        None | Some("serve") => run_server(config).await,
+        Some("tui") => run_tui(config).await,
+        Some("doctor") => run_doctor(config).await,
+        Some(other) => Err(format!("Unknown command '{other}'. Usage: code-sage [serve|tui|doctor]").into()),
+    }
+}
+
+async fn run_server(config: code_sage::Config) -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt()
         .with_writer(std::io::stderr)
         .with_ansi(false)
         .init();
 
     tracing::info!("Starting Code Sage MCP Server");
-
-    let config = code_sage::Config::from_env()?;
     tracing::info!("Configuration loaded");
 
     let snapshot_path = config.storage.data_dir.join("snapshot.json");
     let snapshot = code_sage::snapshot::SnapshotManager::new(snapshot_path)?;
     tracing::info!("Snapshot manager loaded");
 
+    let telemetry = Arc::new(code_sage::embeddings::EmbeddingTelemetry::new());
+    let embedding = init_embedding(&config, &telemetry).await?;
+    tracing::info!("Embedding provider initialized: {}", embedding.provider_name());
+
+    let extra_embeddings = init_extra_embeddings(&config, &telemetry).await;
+    let docs_embedding = init_docs_embedding(&config, &telemetry).await;
+
+    let handlers = code_sage::handlers::ToolHandlers::new(
+        config.clone(),
+        snapshot,
+        embedding,
+    )
+    .with_extra_embeddings(extra_embeddings)
+    .with_docs_embedding(docs_embedding)
+    .with_telemetry(telemetry);
+    tracing::info!("Tool handlers initialized");
+
+    let process_lock = lock_data_dir(&config.storage.data_dir)?;
+    if process_lock.is_some() {
+        recover(&handlers).await;
+    }
+    handlers.spawn_vacuum_task();
+
+    let server = EmbeddingsContextServer::new(Arc::new(handlers));
+
+    tracing::info!("Server initialized, starting stdio transport");
+    
+    let service = server.serve(stdio()).await?;
+    service.waiting().await?;
+
+    Ok(())
+}
+
+#[cfg(feature = "tui")]
+async fn run_tui(config: code_sage::Config) -> Result<(), Box<dyn std::error::Error>> {
+    // The terminal is taken over by the UI, so logs go to a file
+    std::fs::create_dir_all(&config.storage.data_dir)?;
+    let log_file = std::fs::File::create(config.storage.data_dir.join("tui.log"))?;
+    tracing_subscriber::fmt()
+        .with_writer(std::sync::Mutex::new(log_file))
+        .with_ansi(false)
+        .init();
+
+    // A running server holds the sled stores open, and sled would only fail
+    // once a pane opens them
+    let Some(_process_lock) = lock_data_dir(&config.storage.data_dir)? else {
+        return Err(format!(
+            "Another code-sage process, such as a running server, holds {} and its index stores. \
+             Stop it before starting the terminal UI, or point DATA_DIR at another directory.",
+            config.storage.data_dir.join("process.lock").display()
+        )
+        .into());
+    };
+    let snapshot_path = config.storage.data_dir.join("snapshot.json");
+    let snapshot = code_sage::snapshot::SnapshotManager::new(snapshot_path.clone())?;
+    let telemetry = Arc::new(code_sage::embeddings::EmbeddingTelemetry::new());
+    let embedding = init_embedding(&config, &telemetry).await?;
+    let extra_embeddings = init_extra_embeddings(&config, &telemetry).await;
+    let docs_embedding = init_docs_embedding(&config, &telemetry).await;
+
+    let handlers = code_sage::handlers::ToolHandlers::new(config, snapshot, embedding)
+        .with_extra_embeddings(extra_embeddings)
+        .with_docs_embedding(docs_embedding)
+        .with_telemetry(telemetry);
+    recover(&handlers).await;
+    handlers.spawn_vacuum_task();
+    code_sage::tui::run(handlers, snapshot_path).await?;
+
+    Ok(())
+}
+
+/// Take the data directory's process lock, held until the returned file
+/// is dropped; `None` when another code-sage process, e.g. a server next to
+/// the terminal UI, holds it
+fn lock_data_dir(data_dir: &std::path::Path) -> std::io::Result<Option<std::fs::File>> {
+    std::fs::create_dir_all(data_dir)?;
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(data_dir.join("process.lock"))?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(std::fs::TryLockError::WouldBlock) => {
+            tracing::warn!(
+                "Another code-sage process uses {}; its interrupted indexing runs are left to it",
+                data_dir.display()
+            );
+            Ok(None)
+        }
+        Err(std::fs::TryLockError::Error(e)) => Err(e),
+    }
+}
+
+/// Resume or fail the indexing runs a stopped process left marked as
+/// running; only the process holding the data directory's lock does, so a
+/// live process's runs are not taken for interrupted ones
+async fn recover(handlers: &code_sage::handlers::ToolHandlers) {
+    if let Err(e) = handlers.recover_interrupted_indexing().await {
+        tracing::warn!("Failed to recover interrupted indexing runs: {}", e);
+    }
+    handlers.recover_branches().await;
+}
+
+#[cfg(not(feature = "tui"))]
+async fn run_tui(_config: code_sage::Config) -> Result<(), Box<dyn std::error::Error>> {
+    Err("code-sage was built without the terminal UI. Rebuild with `cargo build --release --features tui`.".into())
+}
+
+/// Print the self-test report and exit non-zero when a check fails
+async fn run_doctor(config: code_sage::Config) -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_ansi(false)
+        .with_max_level(tracing::Level::WARN)
+        .init();
+
+    // A provider that fails to start is the first thing the report would flag
+    let telemetry = Arc::new(code_sage::embeddings::EmbeddingTelemetry::new());
+    let embedding = match init_embedding(&config, &telemetry).await {
+        Ok(embedding) => embedding,
+        Err(e) => {
+            println!("[FAIL] embedding: provider {:?} with model '{}' failed to start: {}", config.embedding.provider, config.embedding.model, e);
+            println!("       Fix: Check EMBEDDING_PROVIDER, EMBEDDING_MODEL, the API key and base URL, and that the server is running");
+            std::process::exit(1);
+        }
+    };
+
+    let snapshot_path = config.storage.data_dir.join("snapshot.json");
+    let snapshot = code_sage::snapshot::SnapshotManager::new(snapshot_path)?;
+    let handlers = code_sage::handlers::ToolHandlers::new(config, snapshot, embedding);
+
+    let report: serde_json::Value = serde_json::from_str(&handlers.handle_doctor(Default::default()).await?)?;
+    println!("{}", report["message"].as_str().unwrap_or_default());
+    if !report["ok"].as_bool().unwrap_or(false) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// The configured provider, chained with the fallbacks that start when any are configured
+async fn init_embedding(
+    config: &code_sage::Config,
+    telemetry: &Arc<code_sage::embeddings::EmbeddingTelemetry>,
+) -> Result<Arc<dyn code_sage::embeddings::EmbeddingProvider>, Box<dyn std::error::Error>> {
+    if config.embedding.fallbacks.is_empty() {
+        return init_provider(config, telemetry).await;
+    }
+    
+    let mut entries = Vec::new();
+    let mut first_error = None;
+    for embedding in std::iter::once(&config.embedding).chain(&config.embedding.fallbacks) {
+        let mut provider_config = config.clone();
+        provider_config.embedding = embedding.clone();
+        match init_provider(&provider_config, telemetry).await {
+            Ok(provider) => entries.push(code_sage::embeddings::FallbackEntry {
+                provider,
+                model: embedding.model.clone(),
+            }),
+            Err(e) => {
+                tracing::warn!("Embedding provider {:?} with model '{}' is unavailable: {}",
+                    embedding.provider, embedding.model, e);
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    
+    if let Some(e) = first_error.filter(|_| entries.is_empty()) {
+        return Err(e);
+    }
+    
+    let chain: Vec<String> = entries.iter()
+        .map(|entry| format!("{} '{}'", entry.provider.provider_name(), entry.model))
+        .collect();
+    tracing::info!("Embedding fallback chain: {}", chain.join(" -> "));
+    
+    Ok(Arc::new(code_sage::embeddings::FallbackEmbedding::new(entries)))
+}
+
+/// Provider of `EMBEDDING_DOCS_PROVIDER`; when it fails to start,
+/// documentation is embedded with the main provider
+async fn init_docs_embedding(
+    config: &code_sage::Config,
+    telemetry: &Arc<code_sage::embeddings::EmbeddingTelemetry>,
+) -> Option<code_sage::handlers::ExtraEmbedding> {
+    let embedding = config.embedding.docs_provider.as_deref()?;
+    let mut provider_config = config.clone();
+    provider_config.embedding = embedding.clone();
+    match init_provider(&provider_config, telemetry).await {
+        Ok(provider) => {
+            tracing::info!("Documentation embedding provider: {} '{}'", provider.provider_name(), embedding.model);
+            Some(code_sage::handlers::ExtraEmbedding {
+                config: embedding.clone(),
+                provider,
+            })
+        }
+        Err(e) => {
+            tracing::warn!("Documentation embedding provider {:?} with model '{}' is unavailable, embedding docs with the main provider: {}",
+                embedding.provider, embedding.model, e);
+            None
+        }
+    }
+}
+
+/// Providers of `EMBEDDING_EXTRA_PROVIDERS`; ones that fail to start are
+/// skipped, so only codebases asking for them are affected
+async fn init_extra_embeddings(
+    config: &code_sage::Config,
+    telemetry: &Arc<code_sage::embeddings::EmbeddingTelemetry>,
+) -> Vec<code_sage::handlers::ExtraEmbedding> {
+    let mut extras = Vec::new();
+    for embedding in &config.embedding.extra_providers {
+        let mut provider_config = config.clone();
+        provider_config.embedding = embedding.clone();
+        match init_provider(&provider_config, telemetry).await {
+            Ok(provider) => {
+                tracing::info!("Extra embedding provider available: {} '{}'", provider.provider_name(), embedding.model);
+                extras.push(code_sage::handlers::ExtraEmbedding {
+                    config: embedding.clone(),
+                    provider,
+                });
+            }
+            Err(e) => {
+                tracing::warn!("Extra embedding provider {:?} with model '{}' is unavailable: {}",
+                    embedding.provider, embedding.model, e);
+            }
+        }
+    }
+    extras
+}
+
+async fn init_provider(
+    config: &code_sage::Config,
+    telemetry: &Arc<code_sage::embeddings::EmbeddingTelemetry>,
+) -> Result<Arc<dyn code_sage::embeddings::EmbeddingProvider>, Box<dyn std::error::Error>> {
+    let http_client = code_sage::embeddings::http::build_client(&config.embedding.http)?;
     let embedding: Arc<dyn code_sage::embeddings::EmbeddingProvider> = match config.embedding.provider {
         code_sage::config::EmbeddingProvider::OpenAI => {
+            let keys = code_sage::embeddings::ApiKeys::from_config(&config.embedding).await?;
+            let api_key = keys.current()
+                .ok_or_else(|| code_sage::Error::Config("Missing OPENAI_API_KEY".to_string()))?;
+            let mut openai = code_sage::embeddings::OpenAIEmbedding::new(
+                api_key,
+                Some(config.embedding.model.clone()),
+                config.embedding.base_url.clone(),
+            )
+            .with_dimensions(config.embedding.dimensions)
+            .with_prefixes(config.embedding.query_prefix.as_deref(), config.embedding.document_prefix.as_deref())
+            .with_client(http_client)
+            .with_api_keys(keys);
+            
+            if let Err(e) = openai.detect_dimension().await {
+                tracing::warn!("Failed to detect dimension: {}. Model may not be available.", e);
+                return Err(code_sage::Error::Config(
+                    format!("Failed to initialize OpenAI with model '{}': {}",
+                        config.embedding.model, e)
+                ).into());
+            }
+            
+            tracing::info!("OpenAI initialized with model '{}' (dimension: {})",
+                config.embedding.model, openai.dimension());
+            
+            Arc::new(openai)
+        }
+        code_sage::config::EmbeddingProvider::Ollama => {
+            let mut ollama = code_sage::embeddings::OllamaEmbedding::new(
+                Some(config.embedding.base_url.clone().unwrap_or_else(|| "http://localhost:11434".to_string())),
+                Some(config.embedding.model.clone()),
+            )
+            .with_keep_alive(config.embedding.ollama_keep_alive.clone())
+            .with_auto_pull(config.embedding.ollama_auto_pull)
+            .with_prefixes(config.embedding.query_prefix.as_deref(), config.embedding.document_prefix.as_deref())
+            .with_client(http_client);
+            
+            if let Err(e) = ollama.initialize().await {
+                tracing::warn!("Failed to initialize Ollama: {}. Model may not be available.", e);
+                let hint = if config.embedding.ollama_auto_pull {
+                    "Please ensure Ollama is running and the model name is correct."
+                } else {
+                    "Please ensure Ollama is running and the model is pulled, or set OLLAMA_AUTO_PULL=true."
+                };
+                return Err(code_sage::Error::Config(
+                    format!("Failed to initialize Ollama with model '{}': {}. {}",
+                        config.embedding.model, e, hint)
+                ).into());
+            }
+            
+            tracing::info!("Ollama initialized with model '{}'", config.embedding.model);
+            
+            Arc::new(ollama)
+        }
+        code_sage::config::EmbeddingProvider::Cohere => {
+            let api_key = config.embedding.api_key.clone()
+                .ok_or_else(|| code_sage::Error::Config("Missing COHERE_API_KEY".to_string()))?;
+            let mut cohere = code_sage::embeddings::CohereEmbedding::new(
+                api_key,
+                Some(config.embedding.model.clone()),
+                config.embedding.base_url.clone(),
+            ).with_client(http_client);
+            
+            if let Err(e) = cohere.detect_dimension().await {
+                tracing::warn!("Failed to detect dimension: {}. Model may not be available.", e);
+                return Err(code_sage::Error::Config(
+                    format!("Failed to initialize Cohere with model '{}'. Please ensure the API is accessible.",
+                        config.embedding.model)
+                ).into());
+            }
+            
+            tracing::info!("Cohere initialized with model '{}' (dimension: {})",
+                config.embedding.model, cohere.dimension());
+            
+            Arc::new(cohere)
+        }
+        code_sage::config::EmbeddingProvider::AzureOpenAI => {
+            let keys = code_sage::embeddings::ApiKeys::from_config(&config.embedding).await?;
+            let api_key = keys.current()
+                .ok_or_else(|| code_sage::Error::Config("Missing AZURE_OPENAI_API_KEY".to_string()))?;
+            let endpoint = config.embedding.base_url.clone()
+                .ok_or_else(|| code_sage::Error::Config("Missing AZURE_OPENAI_ENDPOINT".to_string()))?;
+            let deployment = config.embedding.azure_deployment.clone()
+                .unwrap_or_else(|| config.embedding.model.clone());
+            let mut azure = code_sage::embeddings::OpenAIEmbedding::azure(
+                api_key,
+                endpoint,
+                deployment.clone(),
+                config.embedding.azure_api_version.clone().unwrap_or_else(|| "2024-02-01".to_string()),
+                config.embedding.model.clone(),
+            )
+            .with_dimensions(config.embedding.dimensions)
+            .with_prefixes(config.embedding.query_prefix.as_deref(), config.embedding.document_prefix.as_deref())
+            .with_client(http_client)
+            .with_api_keys(keys);
+            
+            if let Err(e) = azure.detect_dimension().await {
+                tracing::warn!("Failed to detect dimension: {}. Deployment may not be available.", e);
+                return Err(code_sage::Error::Config(
+                    format!("Failed to initialize Azure OpenAI with deployment '{}'. Please ensure the endpoint and deployment are correct: {}",
+                        deployment, e)
+                ).into());
+            }
+            
+            tracing::info!("Azure OpenAI initialized with deployment '{}' (dimension: {})",
+                deployment, azure.dimension());
+            
+            Arc::new(azure)
+        }
+        code_sage::config::EmbeddingProvider::OpenAICompatible => {
+            let base_url = config.embedding.base_url.clone()
+                .unwrap_or_else(|| "http://localhost:1234/v1".to_string());
+            let mut compatible = code_sage::embeddings::OpenAIEmbedding::compatible(
+                config.embedding.api_key.clone(),
+                config.embedding.model.clone(),
+                base_url.clone(),
+                config.embedding.max_input_tokens.unwrap_or(512),
+            )
+            .with_dimensions(config.embedding.dimensions)
+            .with_prefixes(config.embedding.query_prefix.as_deref(), config.embedding.document_prefix.as_deref())
+            .with_client(http_client)
+            .with_api_keys(code_sage::embeddings::ApiKeys::from_config(&config.embedding).await?);
+            
+            if let Err(e) = compatible.detect_dimension().await {
+                tracing::warn!("Failed to detect dimension: {}. Server may not be running.", e);
+                return Err(code_sage::Error::Config(
+                    format!("Failed to initialize OpenAI-compatible server at {} with model '{}': {}",
+                        base_url, config.embedding.model, e)
+                ).into());
+            }
+            
+            tracing::info!("OpenAI-compatible server at {} initialized with model '{}' (dimension: {}, max tokens: {})",
+                base_url, config.embedding.model, compatible.dimension(), compatible.max_input_tokens());
+            
+            Arc::new(compatible)
+        }
+        code_sage::config::EmbeddingProvider::Gemini => {
             let api_key = config.embedding.api_key.clone()
-                .ok_or_else(|| code_sage::Error::Config("Missing OPENAI_API_KEY".to_string()))?;
-            let mut openai = code_sage::embeddings::OpenAIEmbedding::new(
+                .ok_or_else(|| code_sage::Error::Config("Missing GEMINI_API_KEY".to_string()))?;
+            let mut gemini = code_sage::embeddings::GeminiEmbedding::new(
                 api_key,
                 Some(config.embedding.model.clone()),
                 config.embedding.base_url.clone(),
-            );
+            ).with_client(http_client);
             
-            if let Err(e) = openai.detect_dimension().await {
+            if let Err(e) = gemini.detect_dimension().await {
                 tracing::warn!("Failed to detect dimension: {}. Model may not be available.", e);
                 return Err(code_sage::Error::Config(
-                    format!("Failed to initialize OpenAI with model '{}'. Please ensure the API is accessible.",
+                    format!("Failed to initialize Gemini with model '{}'. Please ensure the API is accessible.",
                         config.embedding.model)
                 ).into());
             }
             
-            tracing::info!("OpenAI initialized with model '{}' (dimension: {})",
-                config.embedding.model, openai.dimension());
+            tracing::info!("Gemini initialized with model '{}' (dimension: {})",
+                config.embedding.model, gemini.dimension());
             
-            Arc::new(openai)
+            Arc::new(gemini)
         }
-        code_sage::config::EmbeddingProvider::Ollama => {
-            let mut ollama = code_sage::embeddings::OllamaEmbedding::new(
-                Some(config.embedding.base_url.clone().unwrap_or_else(|| "http://localhost:11434".to_string())),
+        code_sage::config::EmbeddingProvider::Bedrock => {
+            let credentials = code_sage::embeddings::AwsCredentials::from_env()?;
+            let region = config.embedding.aws_region.clone()
+                .ok_or_else(|| code_sage::Error::Config("Missing AWS_REGION".to_string()))?;
+            let mut bedrock = code_sage::embeddings::BedrockEmbedding::new(
+                credentials,
+                region.clone(),
                 Some(config.embedding.model.clone()),
-            );
+                config.embedding.base_url.clone(),
+            ).with_client(http_client);
             
-            if let Err(e) = ollama.initialize().await {
-                tracing::warn!("Failed to initialize Ollama: {}. Model may not be available.", e);
+            if let Err(e) = bedrock.detect_dimension().await {
+                tracing::warn!("Failed to detect dimension: {}. Model may not be available.", e);
                 return Err(code_sage::Error::Config(
-                    format!("Failed to initialize Ollama with model '{}'. Please ensure Ollama is running and the model is pulled.",
-                        config.embedding.model)
+                    format!("Failed to initialize Bedrock with model '{}' in {}. Please ensure model access is enabled and the credentials allow bedrock:InvokeModel.",
+                        config.embedding.model, region)
                 ).into());
             }
             
-            tracing::info!("Ollama initialized with model '{}'", config.embedding.model);
+            tracing::info!("Bedrock initialized with model '{}' in {} (dimension: {})",
+                config.embedding.model, region, bedrock.dimension());
             
-            Arc::new(ollama)
+            Arc::new(bedrock)
+        }
+        #[cfg(feature = "local")]
+        code_sage::config::EmbeddingProvider::Local => {
+            let cache_dir = config.embedding.model_cache_dir.clone()
+                .unwrap_or_else(|| config.storage.data_dir.join("models"));
+            let local = code_sage::embeddings::LocalEmbedding::new(
+                Some(config.embedding.model.clone()),
+                cache_dir,
+            ).await.map_err(|e| {
+                tracing::warn!("Failed to load local model: {}", e);
+                code_sage::Error::Config(
+                    format!("Failed to initialize local embeddings with model '{}': {}", config.embedding.model, e)
+                )
+            })?
+            .with_prefixes(config.embedding.query_prefix.as_deref(), config.embedding.document_prefix.as_deref());
+            
+            tracing::info!("Local embeddings initialized with model '{}' (dimension: {})",
+                local.model_code(), local.dimension());
+            
+            Arc::new(local)
+        }
+        #[cfg(not(feature = "local"))]
+        code_sage::config::EmbeddingProvider::Local => {
+            return Err(code_sage::Error::Config(
+                "EMBEDDING_PROVIDER=local requires building with `--features local`".to_string()
+            ).into());
+        }
+        #[cfg(feature = "mock")]
+        code_sage::config::EmbeddingProvider::Mock => {
+            let mock = code_sage::embeddings::MockEmbedding::new(config.embedding.dimensions);
+            tracing::warn!("Mock embeddings enabled (dimension: {}); search results are keyword-based, not semantic",
+                mock.dimension());
+            Arc::new(mock)
+        }
+        #[cfg(not(feature = "mock"))]
+        code_sage::config::EmbeddingProvider::Mock => {
+            return Err(code_sage::Error::Config(
+                "EMBEDDING_PROVIDER=mock requires building with `--features mock`".to_string()
+            ).into());
         }
     };
-    tracing::info!("Embedding provider initialized: {}", embedding.provider_name());
 
-    let handlers = code_sage::handlers::ToolHandlers::new(
-        config.clone(),
-        snapshot,
-        embedding,
-    );
-    tracing::info!("Tool handlers initialized");
+    if let Some(preset) = config.embedding.preset.as_deref().and_then(code_sage::embeddings::presets::find) {
+        if embedding.dimension() != preset.dimension {
+            tracing::warn!("Preset '{}' expects {}-dimensional embeddings, but the server returned {}; check the server hosts '{}'",
+                preset.name, preset.dimension, embedding.dimension(), preset.model);
+        }
+    }
 
-    let server = EmbeddingsContextServer::new(Arc::new(handlers));
+    let embedding: Arc<dyn code_sage::embeddings::EmbeddingProvider> = if config.embedding.normalize {
+        tracing::info!("Embeddings of '{}' are L2-normalized", config.embedding.model);
+        Arc::new(code_sage::embeddings::NormalizedEmbedding::new(embedding))
+    } else {
+        embedding
+    };
 
-    tracing::info!("Server initialized, starting stdio transport");
-    
-    let service = server.serve(stdio()).await?;
-    service.waiting().await?;
+    Ok(Arc::new(code_sage::embeddings::InstrumentedEmbedding::new(
+        embedding,
+        config.embedding.model.clone(),
+        Arc::clone(telemetry),
+    )))
+}
 
-    Ok(())
+/// Handlers report expected failures as JSON with an `error` field; those are
+/// flagged as failed calls so clients can branch on `isError` and `code`
+fn tool_result(blocks: Vec<String>) -> CallToolResult {
+    let failed = blocks.first()
+        .and_then(|block| serde_json::from_str::<serde_json::Value>(block).ok())
+        .is_some_and(|value| value.get("error").is_some());
+    let content = blocks.into_iter().map(Content::text).collect();
+    if failed {
+        CallToolResult::error(content)
+    } else {
+        CallToolResult::success(content)
+    }
+}
+
+fn tool_failure(context: &str, e: &code_sage::Error) -> CallToolResult {
+    CallToolResult::error(vec![Content::text(
+        serde_json::json!({"error": format!("{}: {}", context, e), "code": e.code()}).to_string()
+    )])
 }
 
 struct EmbeddingsContextServer {
@@ -158,13 +966,19 @@ impl EmbeddingsContextServer {
             splitter: "ast".to_string(),
             custom_extensions: vec![],
             ignore_patterns: vec![],
+            tokenizer: params.tokenizer,
+            additional_roots: params.additional_roots,
+            embedding_provider: params.embedding_provider,
+            embedding_model: params.embedding_model,
         };
 
-        match self.handlers.handle_index_codebase(args).await {
-            Ok(json_response) => Ok(CallToolResult::success(vec![Content::text(json_response)])),
-            Err(e) => Ok(CallToolResult::success(vec![Content::text(
-                serde_json::json!({"error": format!("Indexing failed: {}", e)}).to_string()
-            )])),
+        let handlers = match self.handlers.for_branch(params.branch.as_deref()).await {
+            Ok(handlers) => handlers,
+            Err(e) => return Ok(tool_failure("Indexing failed", &e)),
+        };
+        match handlers.handle_index_codebase(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Indexing failed", &e)),
         }
     }
 
@@ -182,13 +996,85 @@ impl EmbeddingsContextServer {
             query: params.query,
             limit: params.limit,
             extension_filter: vec![],
+            language: params.language,
+            subdirectory: params.subdirectory,
+            include_globs: params.include_globs,
+            exclude_globs: params.exclude_globs,
+            modified_after: params.modified_after,
+            modified_before: params.modified_before,
+            output_format: params.output_format,
+            package: params.package,
+            retrieval_depth: params.retrieval_depth,
+            max_snippet_chars: params.max_snippet_chars,
+            response_char_budget: params.response_char_budget,
+            profile: params.profile,
+            min_score: params.min_score,
+            vector_weight: params.vector_weight,
+            bm25_weight: params.bm25_weight,
+            recency_boost: params.recency_boost,
+            recency_half_life_days: params.recency_half_life_days,
+            context_lines: params.context_lines.unwrap_or(0),
+            rerank: params.rerank,
+        };
+        
+        let handlers = match self.handlers.for_branch(params.branch.as_deref()).await {
+            Ok(handlers) => handlers,
+            Err(e) => return Ok(tool_failure("Search failed", &e)),
+        };
+        match handlers.handle_search_code(args).await {
+            Ok(blocks) => Ok(tool_result(blocks)),
+            Err(e) => Ok(tool_failure("Search failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "find_code_batch",
+        description = "Run several find_code queries against one codebase in a single call. Cheaper than separate calls when planning multiple retrievals; results are grouped per query."
+    )]
+    async fn search_code_batch(
+        &self,
+        params: rmcp::handler::server::wrapper::Parameters<SearchCodeBatchParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let params = params.0;
+        let args = code_sage::handlers::SearchCodeBatchArgs {
+            path: params.path,
+            queries: params.queries,
+            limit: params.limit,
+            extension_filter: params.extension_filter,
+            max_snippet_chars: params.max_snippet_chars,
+            response_char_budget: params.response_char_budget,
         };
         
-        match self.handlers.handle_search_code(args).await {
-            Ok(json_response) => Ok(CallToolResult::success(vec![Content::text(json_response)])),
-            Err(e) => Ok(CallToolResult::success(vec![Content::text(
-                serde_json::json!({"error": format!("Search failed: {}", e)}).to_string()
-            )])),
+        match self.handlers.handle_search_code_batch(args).await {
+            Ok(blocks) => Ok(tool_result(blocks)),
+            Err(e) => Ok(tool_failure("Batch search failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "find_code_everywhere",
+        description = "Find code across every indexed codebase at once, for work spanning many related repositories. Results of all codebases are merged into one ranking and each is tagged with its codebase."
+    )]
+    async fn search_everywhere(
+        &self,
+        params: rmcp::handler::server::wrapper::Parameters<SearchEverywhereParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let params = params.0;
+        let args = code_sage::handlers::SearchEverywhereArgs {
+            query: params.query,
+            limit: params.limit,
+            extension_filter: params.extension_filter,
+            max_snippet_chars: params.max_snippet_chars,
+            response_char_budget: params.response_char_budget,
+        };
+
+        let handlers = match self.handlers.for_branch(params.branch.as_deref()).await {
+            Ok(handlers) => handlers,
+            Err(e) => return Ok(tool_failure("Search failed", &e)),
+        };
+        match handlers.handle_search_everywhere(args).await {
+            Ok(blocks) => Ok(tool_result(blocks)),
+            Err(e) => Ok(tool_failure("Search failed", &e)),
         }
     }
 
@@ -205,11 +1091,13 @@ impl EmbeddingsContextServer {
             path: params.path,
         };
         
-        match self.handlers.handle_clear_index(args).await {
-            Ok(json_response) => Ok(CallToolResult::success(vec![Content::text(json_response)])),
-            Err(e) => Ok(CallToolResult::success(vec![Content::text(
-                serde_json::json!({"error": format!("Clear failed: {}", e)}).to_string()
-            )])),
+        let handlers = match self.handlers.for_branch(params.branch.as_deref()).await {
+            Ok(handlers) => handlers,
+            Err(e) => return Ok(tool_failure("Clear failed", &e)),
+        };
+        match handlers.handle_clear_index(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Clear failed", &e)),
         }
     }
 
@@ -226,11 +1114,364 @@ impl EmbeddingsContextServer {
             path: params.path,
         };
         
-        match self.handlers.handle_get_indexing_status(args).await {
-            Ok(json_response) => Ok(CallToolResult::success(vec![Content::text(json_response)])),
-            Err(e) => Ok(CallToolResult::success(vec![Content::text(
-                serde_json::json!({"error": format!("Status check failed: {}", e)}).to_string()
-            )])),
+        let handlers = match self.handlers.for_branch(params.branch.as_deref()).await {
+            Ok(handlers) => handlers,
+            Err(e) => return Ok(tool_failure("Status check failed", &e)),
+        };
+        match handlers.handle_get_indexing_status(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Status check failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "lookup_identifier",
+        description = "Find every definition and usage of an identifier by exact name (case-insensitive), like 'grep -w' but served from the index. Returns file:line locations with definitions listed first."
+    )]
+    async fn lookup_identifier(
+        &self,
+        params: rmcp::handler::server::wrapper::Parameters<LookupIdentifierParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let params = params.0;
+        let args = code_sage::handlers::LookupIdentifierArgs {
+            path: params.path,
+            name: params.name,
+            whole_word: params.whole_word,
+            limit: params.limit,
+        };
+        
+        match self.handlers.handle_lookup_identifier(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Lookup failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "grep_code",
+        description = "Find an exact string or regex in every indexed file, like ripgrep over the files the index knows. Use it when you know the literal text or pattern; embedding search cannot guarantee exact matches. Returns file:line matches in path order."
+    )]
+    async fn grep_code(
+        &self,
+        params: rmcp::handler::server::wrapper::Parameters<GrepCodeParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let params = params.0;
+        let args = code_sage::handlers::GrepCodeArgs {
+            path: params.path,
+            pattern: params.pattern,
+            regex: params.regex,
+            case_sensitive: params.case_sensitive,
+            extension_filter: params.extension_filter,
+            limit: params.limit,
+        };
+        
+        match self.handlers.handle_grep_code(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Grep failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "describe_index",
+        description = "Show exactly how a codebase was indexed: splitter, chunk size and overlap, file extensions, ignore patterns, embedding model and dimension, chunk counts and storage locations. Use it to debug why a file or result is missing."
+    )]
+    async fn describe_index(
+        &self,
+        params: rmcp::handler::server::wrapper::Parameters<DescribeIndexParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let params = params.0;
+        let args = code_sage::handlers::DescribeIndexArgs {
+            path: params.path,
+        };
+        
+        match self.handlers.handle_describe_index(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Describe failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "index_history",
+        description = "List past indexing runs for a codebase: start time, duration, trigger, full or incremental, files and chunks processed, and errors. Use it to investigate slow or failing indexing."
+    )]
+    async fn index_history(
+        &self,
+        params: rmcp::handler::server::wrapper::Parameters<IndexHistoryParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let params = params.0;
+        let args = code_sage::handlers::IndexHistoryArgs {
+            path: params.path,
+            limit: params.limit,
+        };
+        
+        match self.handlers.handle_index_history(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("History lookup failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "retry_failed_files",
+        description = "Re-index files whose chunks could not be stored during an earlier indexing run (listed by check_status and describe_index). Runs in the background."
+    )]
+    async fn retry_failed_files(
+        &self,
+        params: rmcp::handler::server::wrapper::Parameters<RetryFailedFilesParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let params = params.0;
+        let args = code_sage::handlers::RetryFailedFilesArgs {
+            path: params.path,
+        };
+        
+        match self.handlers.handle_retry_failed_files(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Retry failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "coverage_check",
+        description = "Estimate whether a query can be answered from the index before searching: reports which query words match indexed file paths and directories, and whether files changed since the last index. Returns a recommendation: search, reindex or wait."
+    )]
+    async fn coverage_check(
+        &self,
+        params: rmcp::handler::server::wrapper::Parameters<CoverageCheckParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let params = params.0;
+        let args = code_sage::handlers::CoverageCheckArgs {
+            path: params.path,
+            query: params.query,
+        };
+        
+        match self.handlers.handle_coverage_check(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Coverage check failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "restore_index",
+        description = "Restore an index removed by delete_index. Deleted indexes are kept for a retention period (7 days by default), so an accidental delete does not require re-indexing."
+    )]
+    async fn restore_index(
+        &self,
+        params: rmcp::handler::server::wrapper::Parameters<RestoreIndexParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let params = params.0;
+        let args = code_sage::handlers::RestoreIndexArgs {
+            path: params.path,
+        };
+        
+        let handlers = match self.handlers.for_branch(params.branch.as_deref()).await {
+            Ok(handlers) => handlers,
+            Err(e) => return Ok(tool_failure("Restore failed", &e)),
+        };
+        match handlers.handle_restore_index(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Restore failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "save_search_profile",
+        description = "Save named search tuning for a codebase (ranking weights, RRF k, filters, path boosts) so find_code can apply it by name through its profile argument. Profiles survive re-indexing; describe_index lists them."
+    )]
+    async fn save_search_profile(
+        &self,
+        params: rmcp::handler::server::wrapper::Parameters<SaveSearchProfileParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let params = params.0;
+        let args = code_sage::handlers::SaveSearchProfileArgs {
+            path: params.path,
+            name: params.name,
+            profile: code_sage::metadata::SearchProfile {
+                rrf_k: params.rrf_k,
+                vector_weight: params.vector_weight,
+                bm25_weight: params.bm25_weight,
+                identifier_weight: params.identifier_weight,
+                retrieval_depth: params.retrieval_depth,
+                extension_filter: params.extension_filter,
+                package: params.package,
+                path_boosts: params.path_boosts,
+                recency_boost: params.recency_boost,
+                recency_half_life_days: params.recency_half_life_days,
+            },
+            remove: params.remove,
+        };
+        
+        match self.handlers.handle_save_search_profile(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Saving search profile failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "estimate_index",
+        description = "Estimate what indexing a codebase would take before starting it: counts files, chunks and tokens with the real chunker and reports the expected embedding cost and time for the configured provider. Nothing is stored."
+    )]
+    async fn estimate_index(
+        &self,
+        params: rmcp::handler::server::wrapper::Parameters<EstimateIndexParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let params = params.0;
+        let args = code_sage::handlers::EstimateIndexArgs {
+            path: params.path,
+            custom_extensions: params.custom_extensions,
+            ignore_patterns: params.ignore_patterns,
+            sample: params.sample,
+        };
+        
+        match self.handlers.handle_estimate_index(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Estimate failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "doctor",
+        description = "Self-test the server: checks the embedding provider is reachable and returns vectors of the configured dimension, the data directory is writable, and the vector, full-text and metadata stores survive a write, reopen and search round trip. Each failed check comes with a suggested fix."
+    )]
+    async fn doctor(&self) -> Result<CallToolResult, rmcp::ErrorData> {
+        match self.handlers.handle_doctor(code_sage::handlers::DoctorArgs::default()).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Self-test failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "check_provider",
+        description = "Ping the embedding provider and report its model, the dimension it returns, the round-trip latency and the remaining rate limits it advertises. Use it to diagnose failed searches or stalled indexing without reading server logs."
+    )]
+    async fn check_provider(
+        &self,
+        params: rmcp::handler::server::wrapper::Parameters<CheckProviderParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let args = code_sage::handlers::CheckProviderArgs { path: params.0.path };
+        
+        match self.handlers.handle_check_provider(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Provider check failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "embedding_stats",
+        description = "Report embedding request telemetry per provider and model since the server started: request and text counts, failed requests, batches sent again, and mean/p50/p95/p99 latency. Use it to compare providers (e.g. Ollama vs OpenAI) on this machine."
+    )]
+    async fn embedding_stats(
+        &self,
+        params: rmcp::handler::server::wrapper::Parameters<EmbeddingStatsParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let args = code_sage::handlers::EmbeddingStatsArgs { reset: params.0.reset };
+        
+        match self.handlers.handle_embedding_stats(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Embedding stats failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "index_stats",
+        description = "Report the disk footprint of indexes: files, bytes and entries of the vector store (with vector count and dimension), the keyword index and the metadata store, for one codebase or every indexed codebase ranked by size. Use it to find which index fills the disk."
+    )]
+    async fn index_stats(
+        &self,
+        params: rmcp::handler::server::wrapper::Parameters<IndexStatsParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let params = params.0;
+        let args = code_sage::handlers::IndexStatsArgs { path: params.path };
+        
+        let handlers = match self.handlers.for_branch(params.branch.as_deref()).await {
+            Ok(handlers) => handlers,
+            Err(e) => return Ok(tool_failure("Index stats failed", &e)),
+        };
+        match handlers.handle_index_stats(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Index stats failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "rebuild_index",
+        description = "Rebuild the vector and keyword indexes of a codebase from the chunk content in its metadata store, when they were deleted or damaged. Reuses cached embeddings instead of re-indexing the files. Runs in the background."
+    )]
+    async fn rebuild_index(
+        &self,
+        params: rmcp::handler::server::wrapper::Parameters<RebuildIndexParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let params = params.0;
+        let args = code_sage::handlers::RebuildIndexArgs { path: params.path };
+        
+        let handlers = match self.handlers.for_branch(params.branch.as_deref()).await {
+            Ok(handlers) => handlers,
+            Err(e) => return Ok(tool_failure("Rebuild failed", &e)),
+        };
+        match handlers.handle_rebuild_index(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Rebuild failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "export_index",
+        description = "Export the vector, keyword and metadata stores and the sync snapshot of an indexed codebase into one versioned archive file, e.g. to build the index once in CI and share it."
+    )]
+    async fn export_index(
+        &self,
+        params: rmcp::handler::server::wrapper::Parameters<ExportIndexParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let params = params.0;
+        let args = code_sage::handlers::ExportIndexArgs {
+            path: params.path,
+            archive_path: params.archive_path,
+        };
+        
+        let handlers = match self.handlers.for_branch(params.branch.as_deref()).await {
+            Ok(handlers) => handlers,
+            Err(e) => return Ok(tool_failure("Export failed", &e)),
+        };
+        match handlers.handle_export_index(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Export failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "import_index",
+        description = "Import an archive written by export_index as the index of a local checkout, replacing its current index. File paths are moved from the exporting checkout to this one. Run index_codebase afterwards to pick up later changes."
+    )]
+    async fn import_index(
+        &self,
+        params: rmcp::handler::server::wrapper::Parameters<ImportIndexParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let args = code_sage::handlers::ImportIndexArgs {
+            path: params.0.path,
+            archive_path: params.0.archive_path,
+        };
+        
+        match self.handlers.handle_import_index(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Import failed", &e)),
+        }
+    }
+
+    #[tool(
+        name = "migrate_embeddings",
+        description = "After changing the embedding provider or model, re-embed the chunk content stored for a codebase with the configured model and swap the new vectors in, without re-reading the files. The current vectors stay in place until all chunks are embedded. Runs in the background."
+    )]
+    async fn migrate_embeddings(
+        &self,
+        params: rmcp::handler::server::wrapper::Parameters<MigrateEmbeddingsParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let params = params.0;
+        let args = code_sage::handlers::MigrateEmbeddingsArgs { path: params.path };
+        
+        let handlers = match self.handlers.for_branch(params.branch.as_deref()).await {
+            Ok(handlers) => handlers,
+            Err(e) => return Ok(tool_failure("Migration failed", &e)),
+        };
+        match handlers.handle_migrate_embeddings(args).await {
+            Ok(json_response) => Ok(tool_result(vec![json_response])),
+            Err(e) => Ok(tool_failure("Migration failed", &e)),
         }
     }
 }
@@ -3,14 +3,18 @@
 //! Stores chunk metadata per codebase for fast lookup during search
 
 use crate::{Result, Error};
-use crate::types::CodeChunk;
+use crate::types::{ChunkOrigin, CodeChunk, ParseQuality, SplitterStats, StoreStats};
+use crate::search::IdentifierIndex;
+use crate::workspace::{self, Package};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use sha2::{Sha256, Digest};
 
 /// Metadata store using Sled
 pub struct MetadataStore {
     db: sled::Db,
+    path: PathBuf,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +28,295 @@ pub struct StoredMetadata {
     pub file_extension: String,
     pub chunk_index: usize,
     pub hash: String,
+    /// File modification time (unix seconds); `None` for chunks indexed before it was recorded
+    pub modified_at: Option<u64>,
+    /// SHA-256 of the whole file when the chunk was indexed, as the change snapshot records it
+    pub file_hash: Option<String>,
+    /// Change snapshot generation the chunk was indexed at
+    pub sync_generation: Option<u64>,
+}
+
+/// Record layout written before file provenance existed
+#[derive(Deserialize)]
+struct PreProvenanceStoredMetadata {
+    content: String,
+    file_path: PathBuf,
+    relative_path: String,
+    start_line: usize,
+    end_line: usize,
+    language: String,
+    file_extension: String,
+    chunk_index: usize,
+    hash: String,
+    modified_at: Option<u64>,
+}
+
+impl From<PreProvenanceStoredMetadata> for StoredMetadata {
+    fn from(old: PreProvenanceStoredMetadata) -> Self {
+        Self {
+            content: old.content,
+            file_path: old.file_path,
+            relative_path: old.relative_path,
+            start_line: old.start_line,
+            end_line: old.end_line,
+            language: old.language,
+            file_extension: old.file_extension,
+            chunk_index: old.chunk_index,
+            hash: old.hash,
+            modified_at: old.modified_at,
+            file_hash: None,
+            sync_generation: None,
+        }
+    }
+}
+
+/// Record layout written before `modified_at` existed
+#[derive(Deserialize)]
+struct LegacyStoredMetadata {
+    content: String,
+    file_path: PathBuf,
+    relative_path: String,
+    start_line: usize,
+    end_line: usize,
+    language: String,
+    file_extension: String,
+    chunk_index: usize,
+    hash: String,
+}
+
+impl From<LegacyStoredMetadata> for StoredMetadata {
+    fn from(legacy: LegacyStoredMetadata) -> Self {
+        Self {
+            content: legacy.content,
+            file_path: legacy.file_path,
+            relative_path: legacy.relative_path,
+            start_line: legacy.start_line,
+            end_line: legacy.end_line,
+            language: legacy.language,
+            file_extension: legacy.file_extension,
+            chunk_index: legacy.chunk_index,
+            hash: legacy.hash,
+            modified_at: None,
+            file_hash: None,
+            sync_generation: None,
+        }
+    }
+}
+
+impl StoredMetadata {
+    /// Decode a stored record, accepting the layouts written before file
+    /// provenance and before `modified_at` existed
+    ///
+    /// Each layout extends the previous one, so a record in an older layout
+    /// runs out of bytes when decoded as a newer one.
+    fn decode(bytes: &[u8]) -> std::result::Result<Self, bincode::error::DecodeError> {
+        let config = bincode::config::standard();
+        match bincode::serde::decode_from_slice::<StoredMetadata, _>(bytes, config) {
+            Ok((metadata, _len)) => Ok(metadata),
+            Err(e) => {
+                if let Ok((old, _len)) = bincode::serde::decode_from_slice::<PreProvenanceStoredMetadata, _>(bytes, config) {
+                    return Ok(old.into());
+                }
+                let (legacy, _len) = bincode::serde::decode_from_slice::<LegacyStoredMetadata, _>(bytes, config)
+                    .map_err(|_| e)?;
+                Ok(legacy.into())
+            }
+        }
+    }
+}
+
+impl StoredMetadata {
+    /// Rebuild the chunk this record was stored for
+    pub fn to_chunk(&self, id: &str) -> CodeChunk {
+        CodeChunk {
+            id: id.to_string(),
+            content: self.content.clone(),
+            file_path: self.file_path.clone(),
+            relative_path: self.relative_path.clone(),
+            start_line: self.start_line,
+            end_line: self.end_line,
+            language: self.language.clone(),
+            metadata: crate::types::ChunkMetadata {
+                file_extension: self.file_extension.clone(),
+                chunk_index: self.chunk_index,
+                hash: self.hash.clone(),
+                modified_at: self.modified_at,
+                file_hash: self.file_hash.clone(),
+                sync_generation: self.sync_generation,
+            },
+        }
+    }
+}
+
+/// Settings a full index run used, kept for `describe_index`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexSettings {
+    pub splitter: String,
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+    /// Effective extension list (defaults plus custom)
+    pub extensions: Vec<String>,
+    pub custom_extensions: Vec<String>,
+    pub ignore_patterns: Vec<String>,
+    /// Directories indexed together with the codebase root
+    #[serde(default)]
+    pub additional_roots: Vec<String>,
+    pub embedding_provider: String,
+    pub embedding_model: String,
+    pub embedding_dimension: usize,
+    /// Provider and model of the separate documentation index, if any
+    #[serde(default)]
+    pub docs_embedding_provider: Option<String>,
+    #[serde(default)]
+    pub docs_embedding_model: Option<String>,
+    /// Distance the vector index compares vectors by; cosine for indexes
+    /// built before it was configurable
+    #[serde(default)]
+    pub vector_metric: crate::config::VectorMetric,
+    /// Whether the vectors were normalized (`EMBEDDING_NORMALIZE`); `None`
+    /// in indexes built before it was recorded, along with the prefix
+    #[serde(default)]
+    pub embedding_normalize: Option<bool>,
+    /// `EMBEDDING_DOCUMENT_PREFIX` (or a preset's) put before the chunks
+    #[serde(default)]
+    pub embedding_document_prefix: Option<String>,
+    /// Layout of the text embedded for each chunk; 0 in indexes built before
+    /// it was recorded
+    #[serde(default)]
+    pub embedding_text_version: u32,
+    /// Unicode normalization of the embedded text, which queries get too;
+    /// `None` in indexes built before it was recorded
+    #[serde(default)]
+    pub text_normalization: Option<crate::config::NormalizationConfig>,
+    /// RFC 3339 timestamp of the run
+    pub indexed_at: String,
+}
+
+/// Sled tree holding `IndexSettings` under `INDEX_SETTINGS_KEY`
+const SETTINGS_TREE: &str = "index_settings";
+const INDEX_SETTINGS_KEY: &[u8] = b"current";
+
+/// Key in `SETTINGS_TREE` holding the chunk ID scheme version of the stored chunks
+const CHUNK_ID_VERSION_KEY: &[u8] = b"chunk_id_version";
+
+/// Key in `SETTINGS_TREE` recording whether chunks were embedded in document
+/// mode (`EmbeddingProvider::embed_documents`)
+const INPUT_TYPES_KEY: &[u8] = b"embedding_input_types";
+
+/// Key in `SETTINGS_TREE` holding the workspace packages detected at the codebase root
+const PACKAGES_KEY: &[u8] = b"workspace_packages";
+
+/// Key in `SETTINGS_TREE` recording that the vector index holds deleted
+/// vectors for the next vacuum
+const VACUUM_DUE_KEY: &[u8] = b"vacuum_due";
+
+/// Sled tree of full-precision embeddings (little-endian f32) keyed by chunk
+/// ID, kept for quantized vector indexes to re-score their candidates. `clear`
+/// leaves it alone: a full run stores vectors before it replaces the records.
+const FULL_VECTORS_TREE: &str = "full_vectors";
+
+/// Sled tree of `IndexRun` records keyed by a big-endian sequence number
+const HISTORY_TREE: &str = "index_history";
+
+/// Oldest runs beyond this many are dropped
+const MAX_HISTORY_ENTRIES: usize = 500;
+
+/// Sled tree of `FailedFile` records keyed by relative path
+const FAILED_FILES_TREE: &str = "failed_files";
+
+/// A file whose chunks could not be stored, kept for `retry_failed_files`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedFile {
+    pub relative_path: String,
+    pub error: String,
+    /// RFC 3339 timestamp of the failure
+    pub failed_at: String,
+}
+
+/// Sled tree of `FileSplit` records keyed by relative path
+const FILE_SPLITS_TREE: &str = "file_splits";
+
+/// How the chunks of one indexed file were produced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSplit {
+    pub relative_path: String,
+    pub language: String,
+    pub origin: ChunkOrigin,
+    pub chunks: usize,
+    /// `None` when no grammar parsed the file, or for splits recorded before parse errors were
+    #[serde(default)]
+    pub parse_quality: Option<ParseQuality>,
+}
+
+/// Indexed files and chunks of one workspace package
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PackageStats {
+    pub files: usize,
+    pub chunks: usize,
+}
+
+/// One indexing run, as recorded in the history log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexRun {
+    /// RFC 3339 timestamps
+    pub started_at: String,
+    pub finished_at: String,
+    pub duration_secs: f64,
+    /// What started the run, e.g. "analyze_code" or "analyze_code (force)"
+    pub trigger: String,
+    /// "full", "incremental" or "up_to_date"
+    pub mode: String,
+    pub files_processed: usize,
+    pub chunks_processed: usize,
+    /// "completed", "completed_with_errors", "limit_reached" or "failed"
+    pub status: String,
+    pub error: Option<String>,
+}
+
+/// Sled tree of `SearchProfile` records keyed by name; unlike the other
+/// trees it survives re-indexing
+const SEARCH_PROFILES_TREE: &str = "search_profiles";
+
+/// Named search tuning saved for a codebase and applied by `find_code`
+///
+/// Every field is optional; unset fields fall back to the server config, and
+/// arguments passed to `find_code` override the profile.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rrf_k: Option<usize>,
+    /// Multipliers of each retriever's RRF scores
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector_weight: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bm25_weight: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identifier_weight: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retrieval_depth: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension_filter: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    /// Score multipliers keyed by relative path prefix; the longest matching prefix wins
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub path_boosts: BTreeMap<String, f32>,
+    /// Extra score share of recently modified files; see `search.recency_boost`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recency_boost: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recency_half_life_days: Option<f32>,
+}
+
+impl SearchProfile {
+    /// Score multiplier for a result at `relative_path`
+    pub fn path_boost(&self, relative_path: &str) -> f32 {
+        self.path_boosts
+            .iter()
+            .filter(|(prefix, _)| relative_path.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(1.0, |(_, boost)| *boost)
+    }
 }
 
 impl From<&CodeChunk> for StoredMetadata {
@@ -38,12 +331,15 @@ impl From<&CodeChunk> for StoredMetadata {
             file_extension: chunk.metadata.file_extension.clone(),
             chunk_index: chunk.metadata.chunk_index,
             hash: chunk.metadata.hash.clone(),
+            modified_at: chunk.metadata.modified_at,
+            file_hash: chunk.metadata.file_hash.clone(),
+            sync_generation: chunk.metadata.sync_generation,
         }
     }
 }
 
 impl MetadataStore {
-    fn get_db_path_for_codebase(codebase_path: &Path, data_dir: &Path) -> PathBuf {
+    pub(crate) fn get_db_path_for_codebase(codebase_path: &Path, data_dir: &Path) -> PathBuf {
         let mut hasher = Sha256::new();
         hasher.update(codebase_path.to_string_lossy().as_bytes());
         let hash = format!("{:x}", hasher.finalize());
@@ -66,7 +362,13 @@ impl MetadataStore {
                 format!("Failed to open Sled DB: {e}")
             )))?;
         
-        Ok(Self { db })
+        Ok(Self { db, path: db_path })
+    }
+    
+    /// Database of the store, whose trees also hold the codebase's usearch
+    /// vector mappings
+    pub(crate) fn db(&self) -> &sled::Db {
+        &self.db
     }
     
     /// Store metadata for a chunk
@@ -115,7 +417,7 @@ impl MetadataStore {
         
         match value {
             Some(bytes) => {
-                let (metadata, _len) = bincode::serde::decode_from_slice(&bytes, bincode::config::standard())
+                let metadata = StoredMetadata::decode(&bytes)
                     .map_err(|e| Error::Io(std::io::Error::other(
                         format!("Failed to deserialize metadata: {e}")
                     )))?;
@@ -142,6 +444,10 @@ impl MetadataStore {
             .map_err(|e| Error::Io(std::io::Error::other(
                 format!("Failed to delete metadata: {e}")
             )))?;
+        self.full_vectors_tree()?.remove(chunk_id.as_bytes())
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to delete full vector: {e}")
+            )))?;
         Ok(())
     }
     
@@ -151,6 +457,506 @@ impl MetadataStore {
             .map_err(|e| Error::Io(std::io::Error::other(
                 format!("Failed to clear metadata: {e}")
             )))?;
+        self.identifier_index()?.clear()?;
+        self.settings_tree()?.clear()
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to clear index settings: {e}")
+            )))?;
+        self.clear_all_failed_files()?;
+        self.file_splits_tree()?.clear()
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to clear file splits: {e}")
+            )))?;
+        Ok(())
+    }
+    
+    /// Record the settings of a full index run
+    pub fn set_index_settings(&self, settings: &IndexSettings) -> Result<()> {
+        // JSON rather than bincode so fields can be added without breaking old records
+        let value = serde_json::to_vec(settings)?;
+        self.settings_tree()?.insert(INDEX_SETTINGS_KEY, value)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to store index settings: {e}")
+            )))?;
+        Ok(())
+    }
+    
+    /// Settings of the last full index run, if one was recorded
+    pub fn index_settings(&self) -> Result<Option<IndexSettings>> {
+        let value = self.settings_tree()?.get(INDEX_SETTINGS_KEY)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to read index settings: {e}")
+            )))?;
+        
+        match value {
+            Some(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+    
+    /// Chunk ID scheme the stored chunks use; stores that predate versioning are version 1
+    pub fn chunk_id_version(&self) -> Result<u32> {
+        let value = self.settings_tree()?.get(CHUNK_ID_VERSION_KEY)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to read chunk ID version: {e}")
+            )))?;
+        
+        match value {
+            Some(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            None => Ok(1),
+        }
+    }
+    
+    pub fn set_chunk_id_version(&self, version: u32) -> Result<()> {
+        self.settings_tree()?.insert(CHUNK_ID_VERSION_KEY, serde_json::to_vec(&version)?)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to store chunk ID version: {e}")
+            )))?;
+        Ok(())
+    }
+    
+    /// Whether stored embeddings distinguish queries from documents; stores
+    /// with chunks from before the distinction existed do not
+    pub fn embedding_input_types(&self) -> Result<bool> {
+        let value = self.settings_tree()?.get(INPUT_TYPES_KEY)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to read embedding input types: {e}")
+            )))?;
+        
+        match value {
+            Some(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            None => Ok(self.count() == 0),
+        }
+    }
+    
+    pub fn set_embedding_input_types(&self, enabled: bool) -> Result<()> {
+        self.settings_tree()?.insert(INPUT_TYPES_KEY, serde_json::to_vec(&enabled)?)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to store embedding input types: {e}")
+            )))?;
+        Ok(())
+    }
+    
+    /// Whether the vector index holds deleted vectors for the next vacuum
+    pub fn vacuum_due(&self) -> Result<bool> {
+        let value = self.settings_tree()?.get(VACUUM_DUE_KEY)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to read the vacuum flag: {e}")
+            )))?;
+        Ok(value.is_some())
+    }
+    
+    pub fn set_vacuum_due(&self, due: bool) -> Result<()> {
+        let tree = self.settings_tree()?;
+        let result = if due { tree.insert(VACUUM_DUE_KEY, &[1u8][..]) } else { tree.remove(VACUUM_DUE_KEY) };
+        result.map_err(|e| Error::Io(std::io::Error::other(
+            format!("Failed to store the vacuum flag: {e}")
+        )))?;
+        Ok(())
+    }
+    
+    /// Workspace packages found when the codebase was last indexed
+    pub fn packages(&self) -> Result<Vec<Package>> {
+        let value = self.settings_tree()?.get(PACKAGES_KEY)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to read workspace packages: {e}")
+            )))?;
+        
+        match value {
+            Some(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            None => Ok(Vec::new()),
+        }
+    }
+    
+    pub fn set_packages(&self, packages: &[Package]) -> Result<()> {
+        self.settings_tree()?.insert(PACKAGES_KEY, serde_json::to_vec(packages)?)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to store workspace packages: {e}")
+            )))?;
+        Ok(())
+    }
+    
+    /// Move stored chunks to new IDs, including their identifier index entries
+    pub fn rename_chunks(&self, renames: &[(String, String)]) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        let mut renamed_chunks = Vec::new();
+        
+        for (old_id, new_id) in renames {
+            if let Some(metadata) = self.get(old_id)? {
+                let value = bincode::serde::encode_to_vec(&metadata, bincode::config::standard())
+                    .map_err(|e| Error::Io(std::io::Error::other(
+                        format!("Failed to serialize metadata: {e}")
+                    )))?;
+                batch.remove(old_id.as_bytes());
+                batch.insert(new_id.as_bytes(), value);
+                renamed_chunks.push(metadata.to_chunk(new_id));
+            }
+        }
+        
+        self.db.apply_batch(batch)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to apply batch: {e}")
+            )))?;
+        
+        let full_vectors = self.full_vectors_tree()?;
+        let mut vector_batch = sled::Batch::default();
+        for (old_id, new_id) in renames {
+            let vector = full_vectors.get(old_id.as_bytes())
+                .map_err(|e| Error::Io(std::io::Error::other(
+                    format!("Failed to read full vector: {e}")
+                )))?;
+            if let Some(vector) = vector {
+                vector_batch.remove(old_id.as_bytes());
+                vector_batch.insert(new_id.as_bytes(), vector);
+            }
+        }
+        full_vectors.apply_batch(vector_batch)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to apply batch: {e}")
+            )))?;
+        
+        let identifiers = self.identifier_index()?;
+        let old_ids: Vec<String> = renames.iter().map(|(old_id, _)| old_id.clone()).collect();
+        identifiers.delete_chunks(&old_ids)?;
+        identifiers.insert_batch(&renamed_chunks)?;
+        
+        Ok(())
+    }
+    
+    /// Saved search profiles, sorted by name
+    pub fn search_profiles(&self) -> Result<BTreeMap<String, SearchProfile>> {
+        let mut profiles = BTreeMap::new();
+        for entry in self.search_profiles_tree()?.iter() {
+            let (key, value) = entry
+                .map_err(|e| Error::Io(std::io::Error::other(
+                    format!("Failed to read search profiles: {e}")
+                )))?;
+            profiles.insert(String::from_utf8_lossy(&key).into_owned(), serde_json::from_slice(&value)?);
+        }
+        Ok(profiles)
+    }
+    
+    pub fn search_profile(&self, name: &str) -> Result<Option<SearchProfile>> {
+        let value = self.search_profiles_tree()?.get(name.as_bytes())
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to read search profile: {e}")
+            )))?;
+        
+        match value {
+            Some(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+    
+    /// Save a search profile, replacing any profile of the same name
+    pub fn set_search_profile(&self, name: &str, profile: &SearchProfile) -> Result<()> {
+        self.search_profiles_tree()?.insert(name.as_bytes(), serde_json::to_vec(profile)?)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to store search profile: {e}")
+            )))?;
+        Ok(())
+    }
+    
+    /// Delete a search profile; returns whether it existed
+    pub fn remove_search_profile(&self, name: &str) -> Result<bool> {
+        let removed = self.search_profiles_tree()?.remove(name.as_bytes())
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to remove search profile: {e}")
+            )))?;
+        Ok(removed.is_some())
+    }
+    
+    /// Store the full-precision embeddings of chunks in a quantized index
+    pub fn insert_full_vectors(&self, chunk_ids: &[String], vectors: &[Vec<f32>]) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        for (chunk_id, vector) in chunk_ids.iter().zip(vectors) {
+            let bytes: Vec<u8> = vector.iter().flat_map(|value| value.to_le_bytes()).collect();
+            batch.insert(chunk_id.as_bytes(), bytes);
+        }
+        self.full_vectors_tree()?.apply_batch(batch)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to store full vectors: {e}")
+            )))?;
+        Ok(())
+    }
+    
+    /// Full-precision embeddings of `chunk_ids`, in order; `None` where none is stored
+    pub fn full_vectors(&self, chunk_ids: &[String]) -> Result<Vec<Option<Vec<f32>>>> {
+        let tree = self.full_vectors_tree()?;
+        chunk_ids
+            .iter()
+            .map(|chunk_id| {
+                let bytes = tree.get(chunk_id.as_bytes())
+                    .map_err(|e| Error::Io(std::io::Error::other(
+                        format!("Failed to read full vector: {e}")
+                    )))?;
+                Ok(bytes.map(|bytes| {
+                    bytes
+                        .chunks_exact(4)
+                        .map(|value| f32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+                        .collect()
+                }))
+            })
+            .collect()
+    }
+    
+    /// Drop every full-precision embedding, before a full index stores new ones
+    pub fn clear_full_vectors(&self) -> Result<()> {
+        self.full_vectors_tree()?.clear()
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to clear full vectors: {e}")
+            )))?;
+        Ok(())
+    }
+    
+    fn full_vectors_tree(&self) -> Result<sled::Tree> {
+        self.db.open_tree(FULL_VECTORS_TREE)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to open full vectors tree: {e}")
+            )))
+    }
+    
+    fn search_profiles_tree(&self) -> Result<sled::Tree> {
+        self.db.open_tree(SEARCH_PROFILES_TREE)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to open search profiles tree: {e}")
+            )))
+    }
+    
+    fn settings_tree(&self) -> Result<sled::Tree> {
+        self.db.open_tree(SETTINGS_TREE)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to open index settings tree: {e}")
+            )))
+    }
+    
+    /// Append a run to the indexing history, dropping the oldest beyond `MAX_HISTORY_ENTRIES`
+    pub fn record_index_run(&self, run: &IndexRun) -> Result<()> {
+        let tree = self.history_tree()?;
+        let sequence = self.db.generate_id()
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to generate history id: {e}")
+            )))?;
+        
+        tree.insert(sequence.to_be_bytes(), serde_json::to_vec(run)?)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to record index run: {e}")
+            )))?;
+        
+        while tree.len() > MAX_HISTORY_ENTRIES {
+            let popped = tree.pop_min()
+                .map_err(|e| Error::Io(std::io::Error::other(
+                    format!("Failed to prune index history: {e}")
+                )))?;
+            if popped.is_none() {
+                break;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Most recent indexing runs, newest first
+    pub fn index_history(&self, limit: usize) -> Result<Vec<IndexRun>> {
+        let mut runs = Vec::new();
+        for entry in self.history_tree()?.iter().rev().take(limit) {
+            let (_key, value) = entry
+                .map_err(|e| Error::Io(std::io::Error::other(
+                    format!("Failed to read index history: {e}")
+                )))?;
+            runs.push(serde_json::from_slice(&value)?);
+        }
+        Ok(runs)
+    }
+    
+    fn history_tree(&self) -> Result<sled::Tree> {
+        self.db.open_tree(HISTORY_TREE)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to open index history tree: {e}")
+            )))
+    }
+    
+    /// Remember files whose chunks could not be stored, replacing earlier records for them
+    pub fn record_failed_files(&self, failures: &[FailedFile]) -> Result<()> {
+        let tree = self.failed_files_tree()?;
+        for failure in failures {
+            tree.insert(failure.relative_path.as_bytes(), serde_json::to_vec(failure)?)
+                .map_err(|e| Error::Io(std::io::Error::other(
+                    format!("Failed to record failed file: {e}")
+                )))?;
+        }
+        Ok(())
+    }
+    
+    /// Files recorded as failed, sorted by path
+    pub fn failed_files(&self) -> Result<Vec<FailedFile>> {
+        let mut failures = Vec::new();
+        for entry in self.failed_files_tree()?.iter() {
+            let (_key, value) = entry
+                .map_err(|e| Error::Io(std::io::Error::other(
+                    format!("Failed to read failed files: {e}")
+                )))?;
+            failures.push(serde_json::from_slice(&value)?);
+        }
+        Ok(failures)
+    }
+    
+    /// Forget failures for files that are being re-indexed
+    pub fn clear_failed_files(&self, relative_paths: &[String]) -> Result<()> {
+        let tree = self.failed_files_tree()?;
+        for relative_path in relative_paths {
+            tree.remove(relative_path.as_bytes())
+                .map_err(|e| Error::Io(std::io::Error::other(
+                    format!("Failed to clear failed file: {e}")
+                )))?;
+        }
+        Ok(())
+    }
+    
+    /// Forget all failures, e.g. before a full re-index
+    pub fn clear_all_failed_files(&self) -> Result<()> {
+        self.failed_files_tree()?.clear()
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to clear failed files: {e}")
+            )))
+    }
+    
+    fn failed_files_tree(&self) -> Result<sled::Tree> {
+        self.db.open_tree(FAILED_FILES_TREE)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to open failed files tree: {e}")
+            )))
+    }
+    
+    /// Record how files were split, replacing earlier records for them
+    pub fn record_file_splits(&self, splits: &[FileSplit]) -> Result<()> {
+        let tree = self.file_splits_tree()?;
+        for split in splits {
+            tree.insert(split.relative_path.as_bytes(), serde_json::to_vec(split)?)
+                .map_err(|e| Error::Io(std::io::Error::other(
+                    format!("Failed to record file split: {e}")
+                )))?;
+        }
+        Ok(())
+    }
+    
+    /// Forget split records of removed files
+    pub fn remove_file_splits(&self, relative_paths: &[String]) -> Result<()> {
+        let tree = self.file_splits_tree()?;
+        for relative_path in relative_paths {
+            tree.remove(relative_path.as_bytes())
+                .map_err(|e| Error::Io(std::io::Error::other(
+                    format!("Failed to remove file split: {e}")
+                )))?;
+        }
+        Ok(())
+    }
+    
+    /// Chunk counts per language by splitting strategy; empty for indexes
+    /// built before splits were recorded
+    pub fn language_stats(&self) -> Result<BTreeMap<String, SplitterStats>> {
+        let mut stats: BTreeMap<String, SplitterStats> = BTreeMap::new();
+        for split in self.file_splits()? {
+            stats.entry(split.language).or_default().add(split.origin, split.chunks);
+        }
+        Ok(stats)
+    }
+    
+    /// Files with the largest share inside parse errors, worst first; files
+    /// without errors are left out
+    pub fn worst_parsed_files(&self, limit: usize) -> Result<Vec<FileSplit>> {
+        let mut splits: Vec<FileSplit> = self.file_splits()?
+            .into_iter()
+            .filter(|split| split.parse_quality.is_some_and(|quality| quality.error_nodes > 0))
+            .collect();
+        splits.sort_by(|a, b| {
+            let rate = |split: &FileSplit| split.parse_quality.map_or(0.0, |quality| quality.error_rate());
+            rate(b).total_cmp(&rate(a)).then_with(|| a.relative_path.cmp(&b.relative_path))
+        });
+        splits.truncate(limit);
+        Ok(splits)
+    }
+    
+    /// File and chunk counts per workspace package, keyed by package path;
+    /// files outside every package are not counted
+    pub fn package_stats(&self, packages: &[Package]) -> Result<BTreeMap<String, PackageStats>> {
+        let mut stats: BTreeMap<String, PackageStats> = BTreeMap::new();
+        for split in self.file_splits()? {
+            if let Some(package) = workspace::package_for(packages, &split.relative_path) {
+                let entry = stats.entry(package.path.clone()).or_default();
+                entry.files += 1;
+                entry.chunks += split.chunks;
+            }
+        }
+        Ok(stats)
+    }
+    
+    fn file_splits(&self) -> Result<Vec<FileSplit>> {
+        let mut splits = Vec::new();
+        for entry in self.file_splits_tree()?.iter() {
+            let (_key, value) = entry
+                .map_err(|e| Error::Io(std::io::Error::other(
+                    format!("Failed to read file splits: {e}")
+                )))?;
+            splits.push(serde_json::from_slice(&value)?);
+        }
+        Ok(splits)
+    }
+    
+    fn file_splits_tree(&self) -> Result<sled::Tree> {
+        self.db.open_tree(FILE_SPLITS_TREE)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to open file splits tree: {e}")
+            )))
+    }
+    
+    /// Identifier n-gram index stored in the same Sled DB
+    pub fn identifier_index(&self) -> Result<IdentifierIndex> {
+        IdentifierIndex::open(&self.db)
+    }
+    
+    /// Point the stored file paths and additional roots below `from` at
+    /// `to`, for an index built at another path; returns the chunks moved
+    pub fn rebase_paths(&self, from: &Path, to: &Path) -> Result<usize> {
+        let mut batch = sled::Batch::default();
+        let mut moved = 0;
+        
+        for (chunk_id, mut metadata) in self.iter() {
+            let Ok(relative) = metadata.file_path.strip_prefix(from) else {
+                continue;
+            };
+            metadata.file_path = to.join(relative);
+            let value = bincode::serde::encode_to_vec(&metadata, bincode::config::standard())
+                .map_err(|e| Error::Io(std::io::Error::other(
+                    format!("Failed to serialize metadata: {e}")
+                )))?;
+            batch.insert(chunk_id.as_bytes(), value);
+            moved += 1;
+        }
+        
+        self.db.apply_batch(batch)
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to apply batch: {e}")
+            )))?;
+        
+        if let Some(mut settings) = self.index_settings()? {
+            for root in &mut settings.additional_roots {
+                if let Ok(relative) = Path::new(root.as_str()).strip_prefix(from) {
+                    *root = to.join(relative).to_string_lossy().to_string();
+                }
+            }
+            self.set_index_settings(&settings)?;
+        }
+        
+        Ok(moved)
+    }
+    
+    /// Write everything stored so far to disk, e.g. before copying the database
+    pub fn flush(&self) -> Result<()> {
+        self.db.flush()
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to flush metadata: {e}")
+            )))?;
         Ok(())
     }
     
@@ -159,13 +965,21 @@ impl MetadataStore {
         self.db.len()
     }
     
+    /// Files and bytes of the database directory and the chunks it describes
+    pub fn stats(&self) -> StoreStats {
+        StoreStats {
+            entries: self.count(),
+            ..StoreStats::on_disk(&self.path)
+        }
+    }
+    
     /// Iterate over all stored metadata
     pub fn iter(&self) -> impl Iterator<Item = (String, StoredMetadata)> + '_ {
         self.db.iter().filter_map(|result| {
             match result {
                 Ok((key, value)) => {
                     let chunk_id = String::from_utf8(key.to_vec()).ok()?;
-                    let (metadata, _len) = bincode::serde::decode_from_slice(&value, bincode::config::standard()).ok()?;
+                    let metadata = StoredMetadata::decode(&value).ok()?;
                     Some((chunk_id, metadata))
                 }
                 Err(_) => None,
@@ -173,6 +987,37 @@ impl MetadataStore {
         })
     }
     
+    /// Call `f` with the tree name, key and value of every entry of every
+    /// tree, for copying the store elsewhere
+    pub fn for_each_entry(&self, mut f: impl FnMut(&[u8], &[u8], &[u8]) -> Result<()>) -> Result<()> {
+        let mut tree_names = self.db.tree_names();
+        tree_names.sort();
+        for name in tree_names {
+            let tree = self.db.open_tree(&name)
+                .map_err(|e| Error::Io(std::io::Error::other(
+                    format!("Failed to open metadata tree: {e}")
+                )))?;
+            for entry in tree.iter() {
+                let (key, value) = entry
+                    .map_err(|e| Error::Io(std::io::Error::other(
+                        format!("Failed to read metadata: {e}")
+                    )))?;
+                f(&name, &key, &value)?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Store an entry copied with `for_each_entry`
+    pub fn insert_entry(&self, tree: &[u8], key: &[u8], value: &[u8]) -> Result<()> {
+        self.db.open_tree(tree)
+            .and_then(|tree| tree.insert(key, value))
+            .map_err(|e| Error::Io(std::io::Error::other(
+                format!("Failed to store metadata: {e}")
+            )))?;
+        Ok(())
+    }
+    
     /// Check if metadata exists for a codebase
     pub fn exists(codebase_path: &Path, data_dir: &Path) -> bool {
         let db_path = Self::get_db_path_for_codebase(codebase_path, data_dir);
@@ -215,6 +1060,9 @@ mod tests {
             file_extension: ".rs".to_string(),
             chunk_index: 0,
             hash: "abc123".to_string(),
+            modified_at: Some(1_700_000_000),
+            file_hash: Some("f00d".to_string()),
+            sync_generation: Some(3),
         };
         
         store.insert("chunk_1", &metadata).unwrap();
@@ -223,6 +1071,9 @@ mod tests {
         let retrieved = store.get("chunk_1").unwrap().unwrap();
         assert_eq!(retrieved.relative_path, "file.rs");
         assert_eq!(retrieved.start_line, 10);
+        assert_eq!(retrieved.modified_at, Some(1_700_000_000));
+        assert_eq!(retrieved.file_hash.as_deref(), Some("f00d"));
+        assert_eq!(retrieved.sync_generation, Some(3));
         
         store.delete("chunk_1").unwrap();
         assert_eq!(store.count(), 0);
@@ -250,6 +1101,9 @@ mod tests {
                     file_extension: ".rs".to_string(),
                     chunk_index: i,
                     hash: format!("hash{i}"),
+                    modified_at: None,
+                    file_hash: None,
+                    sync_generation: None,
                 },
             }
         }).collect();
@@ -266,4 +1120,251 @@ mod tests {
         store.clear().unwrap();
         assert_eq!(store.count(), 0);
     }
+    
+    #[test]
+    fn test_rebase_paths_moves_only_chunks_below_the_old_root() {
+        let dir = tempdir().unwrap();
+        let store = MetadataStore::for_codebase(&dir.path().join("codebase"), dir.path()).unwrap();
+        
+        let chunks: Vec<CodeChunk> = ["/ci/repo/src/lib.rs", "/elsewhere/shared.rs"].iter().enumerate().map(|(i, path)| {
+            CodeChunk {
+                id: format!("chunk_{i}"),
+                content: String::new(),
+                file_path: PathBuf::from(path),
+                relative_path: format!("file{i}.rs"),
+                start_line: 1,
+                end_line: 1,
+                language: "rust".to_string(),
+                metadata: ChunkMetadata {
+                    file_extension: ".rs".to_string(),
+                    chunk_index: 0,
+                    hash: String::new(),
+                    modified_at: None,
+                    file_hash: None,
+                    sync_generation: None,
+                },
+            }
+        }).collect();
+        store.insert_batch(&chunks).unwrap();
+        
+        assert_eq!(store.rebase_paths(Path::new("/ci/repo"), Path::new("/home/dev/repo")).unwrap(), 1);
+        assert_eq!(store.get("chunk_0").unwrap().unwrap().file_path, PathBuf::from("/home/dev/repo/src/lib.rs"));
+        assert_eq!(store.get("chunk_1").unwrap().unwrap().file_path, PathBuf::from("/elsewhere/shared.rs"));
+    }
+    
+    #[test]
+    fn test_legacy_records_decode_without_mtime() {
+        let dir = tempdir().unwrap();
+        let store = MetadataStore::for_codebase(&dir.path().join("test_codebase"), dir.path()).unwrap();
+        
+        // Same field order as StoredMetadata before `modified_at` was added
+        let legacy = (
+            "fn old() {}".to_string(),
+            PathBuf::from("/test/old.rs"),
+            "old.rs".to_string(),
+            1usize,
+            3usize,
+            "rust".to_string(),
+            ".rs".to_string(),
+            0usize,
+            "hash".to_string(),
+        );
+        let bytes = bincode::serde::encode_to_vec(&legacy, bincode::config::standard()).unwrap();
+        store.db.insert("legacy", bytes).unwrap();
+        
+        let retrieved = store.get("legacy").unwrap().unwrap();
+        assert_eq!(retrieved.relative_path, "old.rs");
+        assert_eq!(retrieved.modified_at, None);
+    }
+
+    #[test]
+    fn test_records_decode_without_provenance() {
+        let dir = tempdir().unwrap();
+        let store = MetadataStore::for_codebase(&dir.path().join("test_codebase"), dir.path()).unwrap();
+
+        // Same field order as StoredMetadata before `file_hash` was added
+        let old = (
+            "fn old() {}".to_string(),
+            PathBuf::from("/test/old.rs"),
+            "old.rs".to_string(),
+            1usize,
+            3usize,
+            "rust".to_string(),
+            ".rs".to_string(),
+            0usize,
+            "hash".to_string(),
+            Some(1_700_000_000u64),
+        );
+        let bytes = bincode::serde::encode_to_vec(&old, bincode::config::standard()).unwrap();
+        store.db.insert("old", bytes).unwrap();
+
+        let retrieved = store.get("old").unwrap().unwrap();
+        assert_eq!(retrieved.modified_at, Some(1_700_000_000));
+        assert_eq!(retrieved.file_hash, None);
+        assert_eq!(retrieved.sync_generation, None);
+    }
+
+    #[test]
+    fn test_index_history_newest_first() {
+        let dir = tempdir().unwrap();
+        let store = MetadataStore::for_codebase(&dir.path().join("test_codebase"), dir.path()).unwrap();
+        
+        for (i, mode) in ["full", "incremental", "up_to_date"].iter().enumerate() {
+            store.record_index_run(&IndexRun {
+                started_at: format!("2024-01-0{}T00:00:00+00:00", i + 1),
+                finished_at: format!("2024-01-0{}T00:01:00+00:00", i + 1),
+                duration_secs: 60.0,
+                trigger: "analyze_code".to_string(),
+                mode: mode.to_string(),
+                files_processed: i,
+                chunks_processed: i * 10,
+                status: "completed".to_string(),
+                error: None,
+            }).unwrap();
+        }
+        
+        let history = store.index_history(2).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].mode, "up_to_date");
+        assert_eq!(history[1].mode, "incremental");
+        
+        // History lives outside the chunk tree
+        assert_eq!(store.count(), 0);
+    }
+    
+    #[test]
+    fn test_search_profiles_survive_clear() {
+        let dir = tempdir().unwrap();
+        let store = MetadataStore::for_codebase(&dir.path().join("test_codebase"), dir.path()).unwrap();
+        
+        let profile = SearchProfile {
+            bm25_weight: Some(2.0),
+            extension_filter: vec![".ts".to_string()],
+            path_boosts: BTreeMap::from([("src/".to_string(), 1.5), ("src/legacy/".to_string(), 0.5)]),
+            ..Default::default()
+        };
+        store.set_search_profile("frontend", &profile).unwrap();
+        store.clear().unwrap();
+        
+        assert_eq!(store.search_profile("frontend").unwrap(), Some(profile.clone()));
+        assert_eq!(store.search_profiles().unwrap().len(), 1);
+        assert_eq!(profile.path_boost("src/legacy/app.ts"), 0.5);
+        assert_eq!(profile.path_boost("src/app.ts"), 1.5);
+        assert_eq!(profile.path_boost("docs/app.md"), 1.0);
+        
+        assert!(store.remove_search_profile("frontend").unwrap());
+        assert!(!store.remove_search_profile("frontend").unwrap());
+        assert_eq!(store.count(), 0);
+    }
+    
+    #[test]
+    fn test_full_vectors_follow_renames_and_deletes() {
+        let dir = tempdir().unwrap();
+        let store = MetadataStore::for_codebase(&dir.path().join("test_codebase"), dir.path()).unwrap();
+        
+        let ids = vec!["a".to_string(), "b".to_string()];
+        store.insert_full_vectors(&ids, &[vec![0.5, -1.25], vec![2.0, 0.0]]).unwrap();
+        store.rename_chunks(&[("a".to_string(), "c".to_string())]).unwrap();
+        store.delete("b").unwrap();
+        
+        let vectors = store.full_vectors(&["a".to_string(), "b".to_string(), "c".to_string()]).unwrap();
+        assert_eq!(vectors, vec![None, None, Some(vec![0.5, -1.25])]);
+        
+        store.clear_full_vectors().unwrap();
+        assert_eq!(store.full_vectors(&["c".to_string()]).unwrap(), vec![None]);
+    }
+    
+    #[test]
+    fn test_failed_files_roundtrip() {
+        let dir = tempdir().unwrap();
+        let store = MetadataStore::for_codebase(&dir.path().join("test_codebase"), dir.path()).unwrap();
+        
+        let failure = |path: &str| FailedFile {
+            relative_path: path.to_string(),
+            error: "Vector database error: disk full".to_string(),
+            failed_at: "2024-01-01T00:00:00+00:00".to_string(),
+        };
+        store.record_failed_files(&[failure("src/b.rs"), failure("src/a.rs")]).unwrap();
+        
+        let paths: Vec<_> = store.failed_files().unwrap().into_iter().map(|f| f.relative_path).collect();
+        assert_eq!(paths, vec!["src/a.rs", "src/b.rs"]);
+        
+        store.clear_failed_files(&["src/a.rs".to_string()]).unwrap();
+        assert_eq!(store.failed_files().unwrap().len(), 1);
+        assert_eq!(store.count(), 0);
+    }
+    
+    #[test]
+    fn test_vacuum_flag_survives_reopening() {
+        let dir = tempdir().unwrap();
+        let codebase = dir.path().join("test_codebase");
+        {
+            let store = MetadataStore::for_codebase(&codebase, dir.path()).unwrap();
+            assert!(!store.vacuum_due().unwrap());
+            store.set_vacuum_due(true).unwrap();
+        }
+        let store = MetadataStore::for_codebase(&codebase, dir.path()).unwrap();
+        assert!(store.vacuum_due().unwrap());
+        store.set_vacuum_due(false).unwrap();
+        assert!(!store.vacuum_due().unwrap());
+    }
+    
+    #[test]
+    fn test_language_stats_from_file_splits() {
+        let dir = tempdir().unwrap();
+        let store = MetadataStore::for_codebase(&dir.path().join("test_codebase"), dir.path()).unwrap();
+        
+        let split = |path: &str, language: &str, origin, chunks| FileSplit {
+            relative_path: path.to_string(),
+            language: language.to_string(),
+            origin,
+            chunks,
+            parse_quality: None,
+        };
+        store.record_file_splits(&[
+            split("src/a.ts", "typescript", ChunkOrigin::Ast, 6),
+            split("src/b.ts", "typescript", ChunkOrigin::Fallback, 2),
+            split("src/c.rs", "rust", ChunkOrigin::Ast, 3),
+        ]).unwrap();
+        
+        // Re-indexing a file replaces its record
+        store.record_file_splits(&[split("src/b.ts", "typescript", ChunkOrigin::Ast, 4)]).unwrap();
+        store.remove_file_splits(&["src/c.rs".to_string()]).unwrap();
+        
+        let stats = store.language_stats().unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats["typescript"], SplitterStats { ast_chunks: 10, fallback_chunks: 0 });
+    }
+    
+    #[test]
+    fn test_rename_chunks() {
+        let dir = tempdir().unwrap();
+        let store = MetadataStore::for_codebase(&dir.path().join("test_codebase"), dir.path()).unwrap();
+        assert_eq!(store.chunk_id_version().unwrap(), 1);
+        
+        let chunk = StoredMetadata {
+            content: "fn parse_config() {}".to_string(),
+            file_path: PathBuf::from("/repo/src/config.rs"),
+            relative_path: "src/config.rs".to_string(),
+            start_line: 3,
+            end_line: 3,
+            language: "rust".to_string(),
+            file_extension: "rs".to_string(),
+            chunk_index: 0,
+            hash: "abc".to_string(),
+            modified_at: None,
+            file_hash: None,
+            sync_generation: None,
+        }.to_chunk("old");
+        store.insert_batch(std::slice::from_ref(&chunk)).unwrap();
+        store.identifier_index().unwrap().insert_batch(std::slice::from_ref(&chunk)).unwrap();
+        
+        store.rename_chunks(&[("old".to_string(), "new".to_string())]).unwrap();
+        store.set_chunk_id_version(2).unwrap();
+        
+        assert!(store.get("old").unwrap().is_none());
+        assert_eq!(store.get("new").unwrap().unwrap().start_line, 3);
+        assert_eq!(store.count(), 1);
+        assert_eq!(store.chunk_id_version().unwrap(), 2);
+    }
 }
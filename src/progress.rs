@@ -0,0 +1,191 @@
+//! Structured progress of embedding generation
+//!
+//! Indexing jobs publish an `EmbeddingProgress` after every embedding batch.
+//! The latest event of each codebase backs `check_status`, and subscribers
+//! (such as MCP progress notifications) receive every event as it happens.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+
+/// Events a slow subscriber can fall behind by before it misses some
+const CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbeddingProgress {
+    pub codebase: PathBuf,
+    /// Chunks of this run that need an embedding, cached ones included
+    pub chunks_total: usize,
+    /// Chunks done so far, cached ones included
+    pub chunks_embedded: usize,
+    /// Chunks whose embedding came from the embedding cache
+    pub chunks_cached: usize,
+    pub batches_done: usize,
+    /// Estimated from the average size of the batches sent so far
+    pub batches_remaining: usize,
+    pub tokens_per_second: f64,
+    pub chunks_per_second: f64,
+    /// Set on the last event of the embedding phase
+    pub finished: bool,
+    /// Unix time of the event
+    pub updated_at: u64,
+}
+
+pub struct ProgressHub {
+    sender: broadcast::Sender<EmbeddingProgress>,
+    latest: Mutex<HashMap<PathBuf, EmbeddingProgress>>,
+}
+
+impl ProgressHub {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            sender,
+            latest: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Receive every event published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<EmbeddingProgress> {
+        self.sender.subscribe()
+    }
+
+    pub fn publish(&self, event: EmbeddingProgress) {
+        self.latest
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(event.codebase.clone(), event.clone());
+        // Having no subscribers is not an error
+        let _ = self.sender.send(event);
+    }
+
+    /// Latest event of `codebase`, if its current or last run embedded anything
+    pub fn latest(&self, codebase: &Path) -> Option<EmbeddingProgress> {
+        self.latest.lock().unwrap_or_else(|e| e.into_inner()).get(codebase).cloned()
+    }
+
+    /// Forget `codebase`'s events, e.g. when a new indexing run starts
+    pub fn clear(&self, codebase: &Path) {
+        self.latest.lock().unwrap_or_else(|e| e.into_inner()).remove(codebase);
+    }
+}
+
+impl Default for ProgressHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds the events of one embedding phase from the batches it completes
+pub struct EmbeddingProgressTracker {
+    codebase: PathBuf,
+    to_embed: usize,
+    cached: usize,
+    started: Instant,
+    batches_done: usize,
+    chunks_sent: usize,
+    tokens_sent: usize,
+}
+
+impl EmbeddingProgressTracker {
+    /// Phase of `codebase` embedding `to_embed` chunks after `cached` came from the cache
+    pub fn new(codebase: &Path, to_embed: usize, cached: usize) -> Self {
+        Self {
+            codebase: codebase.to_path_buf(),
+            to_embed,
+            cached,
+            started: Instant::now(),
+            batches_done: 0,
+            chunks_sent: 0,
+            tokens_sent: 0,
+        }
+    }
+
+    pub fn batch_completed(&mut self, chunks: usize, tokens: usize) {
+        self.batches_done += 1;
+        self.chunks_sent += chunks;
+        self.tokens_sent += tokens;
+    }
+
+    /// Event after `done` of the chunks to embed have an embedding or were given up on
+    pub fn event(&self, done: usize, finished: bool) -> EmbeddingProgress {
+        let seconds = self.started.elapsed().as_secs_f64().max(0.001);
+        let remaining = self.to_embed.saturating_sub(done);
+        let batches_remaining = if finished || remaining == 0 {
+            0
+        } else if self.batches_done == 0 {
+            1
+        } else {
+            let average_batch = (self.chunks_sent / self.batches_done).max(1);
+            remaining.div_ceil(average_batch)
+        };
+        EmbeddingProgress {
+            codebase: self.codebase.clone(),
+            chunks_total: self.cached + self.to_embed,
+            chunks_embedded: self.cached + done,
+            chunks_cached: self.cached,
+            batches_done: self.batches_done,
+            batches_remaining,
+            tokens_per_second: self.tokens_sent as f64 / seconds,
+            chunks_per_second: self.chunks_sent as f64 / seconds,
+            finished,
+            updated_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(codebase: &str, chunks_embedded: usize) -> EmbeddingProgress {
+        EmbeddingProgress {
+            codebase: PathBuf::from(codebase),
+            chunks_total: 100,
+            chunks_embedded,
+            chunks_cached: 0,
+            batches_done: 1,
+            batches_remaining: 1,
+            tokens_per_second: 1000.0,
+            chunks_per_second: 10.0,
+            finished: false,
+            updated_at: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_and_latest_see_published_events() {
+        let hub = ProgressHub::new();
+        let mut receiver = hub.subscribe();
+
+        hub.publish(event("/repo", 10));
+        hub.publish(event("/repo", 50));
+        hub.publish(event("/other", 5));
+
+        assert_eq!(receiver.recv().await.unwrap().chunks_embedded, 10);
+        assert_eq!(hub.latest(Path::new("/repo")).unwrap().chunks_embedded, 50);
+
+        hub.clear(Path::new("/repo"));
+        assert!(hub.latest(Path::new("/repo")).is_none());
+        assert_eq!(hub.latest(Path::new("/other")).unwrap().chunks_embedded, 5);
+    }
+
+    #[test]
+    fn test_tracker_estimates_remaining_batches() {
+        let mut tracker = EmbeddingProgressTracker::new(Path::new("/repo"), 100, 20);
+        tracker.batch_completed(10, 2_000);
+        tracker.batch_completed(30, 6_000);
+
+        let progress = tracker.event(40, false);
+        assert_eq!(progress.chunks_total, 120);
+        assert_eq!(progress.chunks_embedded, 60);
+        // 60 chunks left at 20 chunks per batch
+        assert_eq!(progress.batches_remaining, 3);
+        assert!(progress.tokens_per_second > 0.0);
+
+        assert_eq!(tracker.event(100, true).batches_remaining, 0);
+    }
+}
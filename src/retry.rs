@@ -0,0 +1,112 @@
+//! Retry with exponential backoff for storage writes
+//!
+//! Indexing a large codebase can take hours; a single flaky vector-DB write
+//! or Tantivy commit should not throw that work away. Callers drive the loop
+//! themselves so they can clean up partial writes between attempts:
+//!
+//! ```ignore
+//! let mut backoff = Backoff::new(&config.indexing.retry);
+//! let result = loop {
+//!     match vector_db.insert(docs.clone()).await {
+//!         Err(e) if backoff.should_retry(&e) => backoff.wait(&e, "vector insert").await,
+//!         other => break other,
+//!     }
+//! };
+//! ```
+
+use crate::Error;
+use crate::config::RetryConfig;
+use std::time::Duration;
+use tracing::warn;
+
+pub struct Backoff<'a> {
+    policy: &'a RetryConfig,
+    attempt: u32,
+}
+
+impl<'a> Backoff<'a> {
+    pub fn new(policy: &'a RetryConfig) -> Self {
+        Self { policy, attempt: 1 }
+    }
+
+    /// Whether `error` is worth another attempt under the policy
+    pub fn should_retry(&self, error: &Error) -> bool {
+        is_transient(error) && self.attempt < self.policy.max_attempts
+    }
+
+    /// Sleep before the next attempt
+    pub async fn wait(&mut self, error: &Error, operation: &str) {
+        let delay = self.delay();
+        warn!(
+            "[RETRY] {} failed (attempt {}/{}): {}. Retrying in {}ms",
+            operation,
+            self.attempt,
+            self.policy.max_attempts,
+            error,
+            delay.as_millis()
+        );
+        self.attempt += 1;
+        tokio::time::sleep(delay).await;
+    }
+
+    fn delay(&self) -> Duration {
+        let factor = 1u64 << (self.attempt - 1).min(16);
+        let millis = self.policy.initial_backoff_ms.saturating_mul(factor);
+        Duration::from_millis(millis.min(self.policy.max_backoff_ms))
+    }
+}
+
+/// Errors caused by the request itself fail the same way on every attempt
+fn is_transient(error: &Error) -> bool {
+    !matches!(
+        error,
+        Error::Config(_)
+            | Error::InvalidPath(_)
+            | Error::PathDenied(_)
+            | Error::NotIndexed(_)
+            | Error::UnsupportedLanguage(_)
+            | Error::EmbeddingTooLarge(_)
+            | Error::Corrupted(_)
+            | Error::DimensionMismatch(_)
+            | Error::ModelMismatch(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        let policy = RetryConfig {
+            max_attempts: 10,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 500,
+        };
+        let mut backoff = Backoff::new(&policy);
+
+        let mut delays = Vec::new();
+        for _ in 0..4 {
+            delays.push(backoff.delay().as_millis());
+            backoff.attempt += 1;
+        }
+        assert_eq!(delays, vec![100, 200, 400, 500]);
+    }
+
+    #[test]
+    fn test_should_retry_respects_attempts_and_error_kind() {
+        let policy = RetryConfig {
+            max_attempts: 2,
+            initial_backoff_ms: 0,
+            max_backoff_ms: 0,
+        };
+        let mut backoff = Backoff::new(&policy);
+        let transient = Error::VectorDb("disk busy".to_string());
+
+        assert!(backoff.should_retry(&transient));
+        assert!(!backoff.should_retry(&Error::Config("bad dimension".to_string())));
+
+        backoff.attempt += 1;
+        assert!(!backoff.should_retry(&transient));
+    }
+}
@@ -1,47 +1,123 @@
 //! BM25 full-text search using Tantivy
 
-use crate::{Error, Result};
-use super::{BM25Document, BM25Result};
+use crate::{Error, Result, StoreStats};
+use crate::config::{ANALYZER_VERSION, AnalyzerConfig, NormalizationConfig, TokenizerKind};
+use super::{path_tokens, BM25Document, BM25Result, PathGlobs};
+use super::cjk::CjkBigramTokenizer;
+use super::normalize::NormalizingTokenizer;
+use super::query::{escape_query, identifier_expansions, is_code_like, phrase_query, quoted_phrases, sanitize_query};
 use std::path::{Path, PathBuf};
 use tantivy::{
-    Index, IndexWriter, IndexReader,
+    Index, IndexWriter, IndexReader, Term,
     schema::*,
-    query::QueryParser,
+    query::{BooleanQuery, BoostQuery, ConstScoreQuery, Occur, Query, QueryParser, RegexQuery, TermQuery},
     collector::TopDocs,
+    tokenizer::{LowerCaser, RemoveLongFilter, SimpleTokenizer, StopWordFilter, Stemmer, TextAnalyzer, TokenStream, TokenizerManager},
     TantivyDocument,
 };
 
+/// Tokenizer registered for the content field
+const CONTENT_TOKENIZER: &str = "code_sage";
+
+/// Analyzer settings the index was built with, stored next to the Tantivy files
+const ANALYZER_SETTINGS_FILE: &str = "analyzer.json";
+
+/// Weight of path words relative to content words
+const PATH_FIELD_BOOST: f32 = 2.0;
+
+/// Weight of defined names relative to content words, so the definition of
+/// a searched name ranks above the chunks that only call or mention it
+const SYMBOL_FIELD_BOOST: f32 = 3.0;
+
+/// Weight of the identifier spellings a query is expanded with relative to
+/// its own words, as they are guesses
+const EXPANSION_BOOST: f32 = 0.5;
+
 pub struct BM25Search {
     index: Index,
     reader: IndexReader,
     writer: IndexWriter,
     id_field: Field,
     content_field: Field,
+    /// Words of the relative path; absent from indexes built before it was added
+    path_field: Option<Field>,
+    /// Names the chunk defines; absent from indexes built before it was added
+    symbol_field: Option<Field>,
     file_path_field: Field,
     start_line_field: Field,
     end_line_field: Field,
     data_dir: PathBuf, // Needed for computing delete paths
+    analyzer_notice: Option<String>,
+    /// Tokenizers of prose queries, whose content analyzer drops the stopwords
+    prose_tokenizers: TokenizerManager,
 }
 
 impl BM25Search {
-    pub fn new(index_dir: &Path, data_dir: PathBuf) -> Result<Self> {
+    pub fn new(index_dir: &Path, data_dir: PathBuf, analyzer: &AnalyzerConfig) -> Result<Self> {
         // Create schema
         let mut schema_builder = Schema::builder();
         
+        let content_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(CONTENT_TOKENIZER)
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        );
+        
         let id_field = schema_builder.add_text_field("id", STRING | STORED);
-        let content_field = schema_builder.add_text_field("content", TEXT);
+        let content_field = schema_builder.add_text_field("content", content_options.clone());
         let file_path_field = schema_builder.add_text_field("file_path", STRING | STORED);
         let start_line_field = schema_builder.add_u64_field("start_line", STORED);
         let end_line_field = schema_builder.add_u64_field("end_line", STORED);
+        schema_builder.add_text_field("path", content_options.clone());
+        schema_builder.add_text_field("symbols", content_options);
         
         let schema = schema_builder.build();
         
         // Create or open index
         std::fs::create_dir_all(index_dir)?;
         
-        let index = Index::create_in_dir(index_dir, schema.clone())
-            .or_else(|_| Index::open_in_dir(index_dir))
-            .map_err(|e| Error::FullText(format!("Failed to create/open index: {e}")))?;
+        let settings_path = index_dir.join(ANALYZER_SETTINGS_FILE);
+        
+        let (index, effective_analyzer) = match Index::create_in_dir(index_dir, schema.clone()) {
+            Ok(index) => {
+                let analyzer = AnalyzerConfig { version: ANALYZER_VERSION, ..analyzer.clone() };
+                std::fs::write(&settings_path, serde_json::to_string_pretty(&analyzer)?)?;
+                (index, analyzer)
+            }
+            Err(_) => {
+                let index = Index::open_in_dir(index_dir)
+                    .map_err(|e| Error::FullText(format!("Failed to create/open index: {e}")))?;
+                (index, Self::load_analyzer_settings(&settings_path))
+            }
+        };
+        
+        // Query and index tokens must agree, so keep the analyzer the index was built with.
+        // Every word is indexed: prose queries drop the stopwords, code-like
+        // ones keep them, so `todo_list()` matches as written. Indexes of
+        // version 0 dropped them from the index too, until they are rebuilt
+        let indexed = if effective_analyzer.version >= 1 {
+            AnalyzerConfig { stopwords: Vec::new(), ..effective_analyzer.clone() }
+        } else {
+            effective_analyzer.clone()
+        };
+        index.tokenizers().register(CONTENT_TOKENIZER, build_analyzer(&indexed)?);
+        let prose_tokenizers = TokenizerManager::default();
+        prose_tokenizers.register(CONTENT_TOKENIZER, build_analyzer(&effective_analyzer)?);
+        
+        // Fields appended to the schema later are looked up in the one the index was created with
+        let path_field = index.schema().get_field("path").ok();
+        if path_field.is_none() {
+            tracing::info!("[BM25] Index predates path matching; re-index with force=true to enable it");
+        }
+        let symbol_field = index.schema().get_field("symbols").ok();
+        if symbol_field.is_none() {
+            tracing::info!("[BM25] Index predates symbol name boosting; re-index with force=true to enable it");
+        }
+        
+        let analyzer_notice = analyzer_change_notice(&effective_analyzer, analyzer);
+        if let Some(notice) = &analyzer_notice {
+            tracing::warn!("[BM25] {}", notice);
+        }
         
         let reader = index.reader()
             .map_err(|e| Error::FullText(format!("Failed to create reader: {e}")))?;
@@ -55,19 +131,82 @@ impl BM25Search {
             writer,
             id_field,
             content_field,
+            path_field,
+            symbol_field,
             file_path_field,
             start_line_field,
             end_line_field,
             data_dir,
+            analyzer_notice,
+            prose_tokenizers,
         })
     }
     
+    fn load_analyzer_settings(settings_path: &Path) -> AnalyzerConfig {
+        // Indexes created before analyzer settings were persisted used the default analyzer,
+        // without Unicode normalization
+        std::fs::read_to_string(settings_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_else(|| AnalyzerConfig {
+                version: 0,
+                normalization: NormalizationConfig::disabled(),
+                ..AnalyzerConfig::default()
+            })
+    }
+    
+    /// Re-index guidance when the configured analyzer differs from the one the index was built with
+    pub fn analyzer_notice(&self) -> Option<&str> {
+        self.analyzer_notice.as_deref()
+    }
+    
+    /// Same as `analyzer_notice`, without opening the index (and its writer lock)
+    pub fn pending_analyzer_change(codebase_path: &Path, data_dir: &Path, analyzer: &AnalyzerConfig) -> Option<String> {
+        let stored = Self::stored_analyzer(codebase_path, data_dir)?;
+        analyzer_change_notice(&stored, analyzer)
+    }
+    
+    /// Whether the codebase's index was built with an older version of the
+    /// analysis, so the next index run rebuilds it
+    pub fn outdated_analyzer(codebase_path: &Path, data_dir: &Path) -> bool {
+        Self::stored_analyzer(codebase_path, data_dir).is_some_and(|analyzer| analyzer.version < ANALYZER_VERSION)
+    }
+    
+    /// Analyzer settings an existing index for the codebase was built with
+    pub fn stored_analyzer(codebase_path: &Path, data_dir: &Path) -> Option<AnalyzerConfig> {
+        let index_dir = Self::get_index_path_for_codebase(codebase_path, data_dir);
+        if !index_dir.exists() {
+            return None;
+        }
+        
+        Some(Self::load_analyzer_settings(&index_dir.join(ANALYZER_SETTINGS_FILE)))
+    }
+    
     pub fn insert(&mut self, documents: Vec<BM25Document>) -> Result<()> {
+        if let Err(e) = self.add_and_commit(documents) {
+            // Drop the staged documents so a retried insert doesn't index them twice
+            let _ = self.writer.rollback();
+            return Err(e);
+        }
+        
+        self.reader.reload()
+            .map_err(|e| Error::FullText(format!("Failed to reload: {e}")))?;
+        
+        Ok(())
+    }
+    
+    fn add_and_commit(&mut self, documents: Vec<BM25Document>) -> Result<()> {
         for doc in documents {
             let mut tantivy_doc = TantivyDocument::default();
             
             tantivy_doc.add_text(self.id_field, &doc.id);
             tantivy_doc.add_text(self.content_field, &doc.content);
+            if let Some(path_field) = self.path_field {
+                tantivy_doc.add_text(path_field, path_tokens(&doc.file_path));
+            }
+            if let Some(symbol_field) = self.symbol_field {
+                tantivy_doc.add_text(symbol_field, &doc.symbols);
+            }
             tantivy_doc.add_text(self.file_path_field, &doc.file_path);
             tantivy_doc.add_u64(self.start_line_field, doc.start_line);
             tantivy_doc.add_u64(self.end_line_field, doc.end_line);
@@ -79,18 +218,72 @@ impl BM25Search {
         self.writer.commit()
             .map_err(|e| Error::FullText(format!("Failed to commit: {e}")))?;
         
-        self.reader.reload()
-            .map_err(|e| Error::FullText(format!("Failed to reload: {e}")))?;
-        
         Ok(())
     }
     
     pub fn search(&self, query_text: &str, top_k: usize) -> Result<Vec<BM25Result>> {
+        self.search_paths(query_text, top_k, &PathGlobs::default())
+    }
+    
+    /// Best `top_k` matches among the files `path_globs` keeps
+    pub fn search_paths(&self, query_text: &str, top_k: usize, path_globs: &PathGlobs) -> Result<Vec<BM25Result>> {
         let searcher = self.reader.searcher();
         
-        let query_parser = QueryParser::for_index(&self.index, vec![self.content_field]);
-        let query = query_parser.parse_query(query_text)
-            .map_err(|e| Error::FullText(format!("Failed to parse query: {e}")))?;
+        let mut fields = vec![(self.content_field, 1.0)];
+        fields.extend(self.path_field.map(|field| (field, PATH_FIELD_BOOST)));
+        fields.extend(self.symbol_field.map(|field| (field, SYMBOL_FIELD_BOOST)));
+        let code_like = is_code_like(query_text);
+        let tokenizers = if code_like { self.index.tokenizers() } else { &self.prose_tokenizers };
+        let mut query_parser = QueryParser::new(
+            self.index.schema(),
+            fields.iter().map(|&(field, _)| field).collect(),
+            tokenizers.clone(),
+        );
+        for &(field, boost) in &fields {
+            query_parser.set_field_boost(field, boost);
+        }
+        // Quoted phrases must match as phrases; otherwise code is searched for
+        // its words, not read as query syntax, and prose keeps the syntax it
+        // can mean unless it still does not parse
+        let parsed = if let Some(phrases) = phrase_query(query_text) {
+            match query_parser.parse_query(&phrases) {
+                Ok(query) => Some(query),
+                Err(e) => {
+                    tracing::debug!("[BM25] Phrase query does not parse ({}), searching its words", e);
+                    None
+                }
+            }
+        } else if code_like {
+            None
+        } else {
+            let schema = self.index.schema();
+            let field_names: Vec<&str> = fields.iter().map(|&(field, _)| schema.get_field_name(field)).collect();
+            match query_parser.parse_query(&sanitize_query(query_text, &field_names)) {
+                Ok(query) => Some(query),
+                Err(e) => {
+                    tracing::debug!("[BM25] Query does not parse ({}), searching its words", e);
+                    None
+                }
+            }
+        };
+        let query = match parsed {
+            Some(query) => query,
+            None => {
+                let escaped = escape_query(query_text);
+                if escaped.is_empty() {
+                    return Ok(Vec::new());
+                }
+                match query_parser.parse_query(&escaped) {
+                    Ok(query) => query,
+                    Err(e) => {
+                        tracing::debug!("[BM25] Escaped query does not parse ({}), matching any of its words", e);
+                        self.any_word_query(tokenizers, &escaped, &fields)
+                    }
+                }
+            }
+        };
+        let query = self.with_expansions(query, query_text, &fields);
+        let query = self.restrict_to_paths(query, path_globs)?;
         
         let top_docs = searcher.search(&query, &TopDocs::with_limit(top_k))
             .map_err(|e| Error::FullText(format!("Search failed: {e}")))?;
@@ -116,6 +309,64 @@ impl BM25Search {
         Ok(results)
     }
     
+    /// `query` also matching the identifier spellings of `query_text`, which
+    /// add matches and score but are never required
+    fn with_expansions(&self, query: Box<dyn Query>, query_text: &str, fields: &[(Field, f32)]) -> Box<dyn Query> {
+        let expansions = identifier_expansions(query_text);
+        if expansions.is_empty() {
+            return query;
+        }
+        tracing::debug!("[BM25] Expanded with identifier spellings {:?}", expansions);
+        let expanded = Box::new(BoostQuery::new(
+            self.any_word_query(self.index.tokenizers(), &expansions.join(" "), fields),
+            EXPANSION_BOOST,
+        ));
+        // Quoted phrases stay required
+        let occur = if quoted_phrases(query_text).is_empty() { Occur::Should } else { Occur::Must };
+        Box::new(BooleanQuery::new(vec![(occur, query), (Occur::Should, expanded)]))
+    }
+    
+    /// `query` limited to documents whose raw path the globs keep, so
+    /// retrieval itself is narrowed; the path clauses add nothing to scores
+    fn restrict_to_paths(&self, query: Box<dyn Query>, path_globs: &PathGlobs) -> Result<Box<dyn Query>> {
+        if path_globs.is_empty() {
+            return Ok(query);
+        }
+        let path_query = |pattern: &str| -> Result<Box<dyn Query>> {
+            let regex = RegexQuery::from_pattern(pattern, self.file_path_field)
+                .map_err(|e| Error::FullText(format!("Invalid path filter: {e}")))?;
+            Ok(Box::new(ConstScoreQuery::new(Box::new(regex), 0.0)))
+        };
+        let mut clauses = vec![(Occur::Must, query)];
+        for pattern in path_globs.required_patterns() {
+            clauses.push((Occur::Must, path_query(pattern)?));
+        }
+        if let Some(pattern) = path_globs.exclude_pattern() {
+            clauses.push((Occur::MustNot, path_query(pattern)?));
+        }
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+    
+    /// Query matching documents with any word of `text`, as the content
+    /// analyzer of `tokenizers` splits it, in any of `fields`, weighted by
+    /// their boosts; built without the parser, so it cannot fail
+    fn any_word_query(&self, tokenizers: &TokenizerManager, text: &str, fields: &[(Field, f32)]) -> Box<dyn Query> {
+        let mut words = Vec::new();
+        if let Some(mut analyzer) = tokenizers.get(CONTENT_TOKENIZER) {
+            analyzer.token_stream(text).process(&mut |token| words.push(token.text.clone()));
+        }
+        let clauses: Vec<(Occur, Box<dyn Query>)> = words
+            .iter()
+            .flat_map(|word| {
+                fields.iter().map(move |&(field, boost)| {
+                    let term = TermQuery::new(Term::from_field_text(field, word), IndexRecordOption::WithFreqs);
+                    (Occur::Should, Box::new(BoostQuery::new(Box::new(term), boost)) as Box<dyn Query>)
+                })
+            })
+            .collect();
+        Box::new(BooleanQuery::new(clauses))
+    }
+    
     pub fn delete(&mut self, ids: &[String]) -> Result<()> {
         for id in ids {
             let term = tantivy::Term::from_field_text(self.id_field, id);
@@ -171,16 +422,7 @@ impl BM25Search {
         const STORAGE_BATCH_SIZE: usize = 50;
         
         for (i, chunk_batch) in chunks.chunks(STORAGE_BATCH_SIZE).enumerate() {
-            let documents: Vec<BM25Document> = chunk_batch
-                .iter()
-                .map(|chunk| BM25Document {
-                    id: chunk.id.clone(),
-                    content: chunk.content.clone(),
-                    file_path: chunk.relative_path.clone(),
-                    start_line: chunk.start_line as u64,
-                    end_line: chunk.end_line as u64,
-                })
-                .collect();
+            let documents: Vec<BM25Document> = chunk_batch.iter().map(BM25Document::from).collect();
             
             tracing::info!("[BM25] Inserting batch {} ({} documents)", i + 1, documents.len());
             self.insert(documents)?;
@@ -194,11 +436,12 @@ impl BM25Search {
         _codebase_path: &Path,
         query_text: &str,
         top_k: usize,
+        path_globs: &PathGlobs,
     ) -> Result<Vec<BM25Result>> {
-        self.search(query_text, top_k)
+        self.search_paths(query_text, top_k, path_globs)
     }
     
-    fn get_index_path_for_codebase(codebase_path: &Path, data_dir: &Path) -> PathBuf {
+    pub(crate) fn get_index_path_for_codebase(codebase_path: &Path, data_dir: &Path) -> PathBuf {
         use sha2::{Sha256, Digest};
         
         let mut hasher = Sha256::new();
@@ -210,8 +453,396 @@ impl BM25Search {
             .join(&hash[..16])
     }
     
-    pub fn for_codebase(codebase_path: &Path, data_dir: &Path) -> Result<Self> {
+    /// Files and bytes of the codebase's index directory and the documents it
+    /// holds; opened without a writer, so it works while the codebase is
+    /// being indexed
+    pub fn stats_for_codebase(codebase_path: &Path, data_dir: &Path) -> Result<StoreStats> {
         let index_dir = Self::get_index_path_for_codebase(codebase_path, data_dir);
-        Self::new(&index_dir, data_dir.to_path_buf())
+        if !index_dir.exists() {
+            return Ok(StoreStats::default());
+        }
+        let index = Index::open_in_dir(&index_dir)
+            .map_err(|e| Error::FullText(format!("Failed to open index: {e}")))?;
+        let reader = index.reader()
+            .map_err(|e| Error::FullText(format!("Failed to create reader: {e}")))?;
+        Ok(StoreStats {
+            entries: reader.searcher().num_docs() as usize,
+            ..StoreStats::on_disk(&index_dir)
+        })
+    }
+    
+    pub fn for_codebase(codebase_path: &Path, data_dir: &Path, analyzer: &AnalyzerConfig) -> Result<Self> {
+        let index_dir = Self::get_index_path_for_codebase(codebase_path, data_dir);
+        Self::new(&index_dir, data_dir.to_path_buf(), analyzer)
+    }
+}
+
+fn build_analyzer(config: &AnalyzerConfig) -> Result<TextAnalyzer> {
+    // Normalization runs before tokenizing: a combining accent would otherwise split a word
+    let normalization = config.normalization;
+    let builder = match config.tokenizer {
+        TokenizerKind::Simple => {
+            TextAnalyzer::builder(NormalizingTokenizer::new(SimpleTokenizer::default(), normalization)).dynamic()
+        }
+        TokenizerKind::Cjk => {
+            TextAnalyzer::builder(NormalizingTokenizer::new(CjkBigramTokenizer, normalization)).dynamic()
+        }
+    };
+    
+    let mut builder = builder
+        .filter_dynamic(RemoveLongFilter::limit(40))
+        .filter_dynamic(LowerCaser);
+    
+    if !config.stopwords.is_empty() {
+        let stopwords: Vec<String> = config.stopwords.iter().map(|w| w.to_lowercase()).collect();
+        builder = builder.filter_dynamic(StopWordFilter::remove(stopwords));
+    }
+    
+    if config.stemming {
+        builder = builder.filter_dynamic(Stemmer::new(stemmer_language(&config.stemmer_language)?));
+    }
+    
+    Ok(builder.build())
+}
+
+/// Stemmer for `BM25_STEMMER_LANGUAGE`, checked when the configuration is read
+pub(crate) fn stemmer_language(name: &str) -> Result<tantivy::tokenizer::Language> {
+    use tantivy::tokenizer::Language;
+    
+    match name.to_lowercase().as_str() {
+        "arabic" => Ok(Language::Arabic),
+        "danish" => Ok(Language::Danish),
+        "dutch" => Ok(Language::Dutch),
+        "english" => Ok(Language::English),
+        "finnish" => Ok(Language::Finnish),
+        "french" => Ok(Language::French),
+        "german" => Ok(Language::German),
+        "greek" => Ok(Language::Greek),
+        "hungarian" => Ok(Language::Hungarian),
+        "italian" => Ok(Language::Italian),
+        "norwegian" => Ok(Language::Norwegian),
+        "portuguese" => Ok(Language::Portuguese),
+        "romanian" => Ok(Language::Romanian),
+        "russian" => Ok(Language::Russian),
+        "spanish" => Ok(Language::Spanish),
+        "swedish" => Ok(Language::Swedish),
+        "tamil" => Ok(Language::Tamil),
+        "turkish" => Ok(Language::Turkish),
+        other => Err(Error::Config(format!("Unsupported stemmer language: {other}"))),
+    }
+}
+
+fn analyzer_change_notice(built_with: &AnalyzerConfig, configured: &AnalyzerConfig) -> Option<String> {
+    if built_with.version < ANALYZER_VERSION {
+        return Some(format!(
+            "BM25 index was built with analyzer version {} (current {}); the next index run rebuilds it.",
+            built_with.version, ANALYZER_VERSION
+        ));
+    }
+    // The tokenizer is chosen per codebase, so only the global filters are compared
+    if built_with.stemming == configured.stemming
+        && built_with.stemmer_language == configured.stemmer_language
+        && built_with.stopwords == configured.stopwords
+        && built_with.normalization == configured.normalization
+    {
+        return None;
+    }
+    
+    Some(format!(
+        "BM25 analyzer settings changed (index built with {}, configured {}). Re-index with force=true to apply them.",
+        describe_analyzer(built_with),
+        describe_analyzer(configured)
+    ))
+}
+
+pub(crate) fn describe_analyzer(config: &AnalyzerConfig) -> String {
+    let stemming = if config.stemming {
+        format!("{} stemming", config.stemmer_language)
+    } else {
+        "no stemming".to_string()
+    };
+    let normalization = &config.normalization;
+    let mut folding = Vec::new();
+    if normalization.nfc {
+        folding.push("NFC");
+    }
+    if normalization.fold_width {
+        folding.push("width");
+    }
+    if normalization.fold_diacritics {
+        folding.push("diacritics");
+    }
+    let folding = if folding.is_empty() {
+        "no normalization".to_string()
+    } else {
+        format!("{} normalization", folding.join("+"))
+    };
+    
+    format!(
+        "{} tokenizer, {}, {} stopwords, {}",
+        config.tokenizer.as_str(),
+        stemming,
+        config.stopwords.len(),
+        folding
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    
+    fn document(id: &str, content: &str) -> BM25Document {
+        BM25Document {
+            id: id.to_string(),
+            content: content.to_string(),
+            file_path: format!("{id}.rs"),
+            start_line: 1,
+            end_line: 1,
+            symbols: crate::search::symbol_tokens(content),
+        }
+    }
+    
+    #[test]
+    fn test_stemming_and_stopwords() {
+        let dir = tempdir().unwrap();
+        let analyzer = AnalyzerConfig {
+            version: ANALYZER_VERSION,
+            tokenizer: TokenizerKind::Simple,
+            stemming: true,
+            stemmer_language: "english".to_string(),
+            stopwords: vec!["todo".to_string()],
+            normalization: NormalizationConfig::default(),
+        };
+        
+        let mut bm25 = BM25Search::new(&dir.path().join("index"), dir.path().to_path_buf(), &analyzer).unwrap();
+        bm25.insert(vec![
+            document("doc1", "retries failed connections"),
+            document("doc2", "TODO cleanup"),
+        ]).unwrap();
+        
+        let results = bm25.search("connection retry", 10).unwrap();
+        assert_eq!(results[0].id, "doc1");
+        assert!(bm25.search("todo", 10).unwrap().is_empty());
+        
+        // Code keeps its stopwords
+        bm25.insert(vec![document("doc3", "fn todo_list() {}"), document("doc4", "fn list() {}")]).unwrap();
+        assert_eq!(bm25.search("todo_list()", 10).unwrap()[0].id, "doc3");
+    }
+    
+    #[test]
+    fn test_code_queries_parse() {
+        let dir = tempdir().unwrap();
+        let mut bm25 = BM25Search::new(&dir.path().join("index"), dir.path().to_path_buf(), &AnalyzerConfig::default()).unwrap();
+        bm25.insert(vec![
+            document("doc1", "fn load_config(path: &Path) -> Result<Config> {}"),
+            document("doc2", "fn render(template: &str) {}"),
+        ]).unwrap();
+        
+        for query in ["load_config(path: &Path) -> Result<Config>", "config: ("] {
+            let results = bm25.search(query, 10).unwrap();
+            assert_eq!(results[0].id, "doc1", "query {query}");
+        }
+    }
+    
+    #[test]
+    fn test_prose_with_stray_syntax_searches() {
+        let dir = tempdir().unwrap();
+        let mut bm25 = BM25Search::new(&dir.path().join("index"), dir.path().to_path_buf(), &AnalyzerConfig::default()).unwrap();
+        bm25.insert(vec![
+            document("doc1", "// Retry the request with exponential backoff on timeout errors"),
+            document("doc2", "fn render(template: &str) {}"),
+        ]).unwrap();
+        
+        for query in [
+            "error: timeout",
+            "\"retry with backoff",
+            "retry (backoff",
+            "retry/backoff",
+            "timeout AND",
+            "OR retry NOT",
+            "content:timeout",
+        ] {
+            let results = bm25.search(query, 10).unwrap();
+            assert_eq!(results.first().map(|result| result.id.as_str()), Some("doc1"), "query {query}");
+        }
+        
+        let fields = [(bm25.content_field, 1.0)];
+        let query = bm25.any_word_query("Backoff timeouts", &fields);
+        let searcher = bm25.reader.searcher();
+        assert_eq!(searcher.search(&query, &TopDocs::with_limit(10)).unwrap().len(), 1);
+    }
+    
+    #[test]
+    fn test_quoted_phrases_must_match_in_order() {
+        let dir = tempdir().unwrap();
+        let mut bm25 = BM25Search::new(&dir.path().join("index"), dir.path().to_path_buf(), &AnalyzerConfig::default()).unwrap();
+        bm25.insert(vec![
+            document("doc1", "// The buffer pool manager evicts cold pages"),
+            document("doc2", "// The manager of the pool keeps a buffer of pages"),
+        ]).unwrap();
+        
+        let ids = |query: &str| -> Vec<String> {
+            bm25.search(query, 10).unwrap().into_iter().map(|result| result.id).collect()
+        };
+        assert_eq!(ids("\"buffer pool manager\""), ["doc1"]);
+        assert_eq!(ids("\"buffer pool manager\" pages"), ["doc1"]);
+        assert!(ids("\"pool buffer\"").is_empty());
+        assert_eq!(ids("buffer pool manager").len(), 2);
+    }
+    
+    #[test]
+    fn test_path_globs_narrow_retrieval() {
+        let dir = tempdir().unwrap();
+        let mut bm25 = BM25Search::new(&dir.path().join("index"), dir.path().to_path_buf(), &AnalyzerConfig::default()).unwrap();
+        bm25.insert(["src/cache.rs", "src/tests/cache.rs", "benches/cache.rs"].iter().map(|path| BM25Document {
+            file_path: path.to_string(),
+            ..document(path, "fn evict_cache_entry() {}")
+        }).collect()).unwrap();
+        
+        let globs = PathGlobs::new(&["src/**".to_string()], &["**/tests/**".to_string()]).unwrap();
+        let results = bm25.search_paths("evict cache entry", 10, &globs).unwrap();
+        assert_eq!(results.iter().map(|result| result.id.as_str()).collect::<Vec<_>>(), ["src/cache.rs"]);
+        assert_eq!(bm25.search("evict cache entry", 10).unwrap().len(), 3);
+    }
+    
+    #[test]
+    fn test_path_words_match() {
+        let dir = tempdir().unwrap();
+        let mut bm25 = BM25Search::new(&dir.path().join("index"), dir.path().to_path_buf(), &AnalyzerConfig::default()).unwrap();
+        bm25.insert(vec![
+            BM25Document {
+                file_path: "controllers/UserController.py".to_string(),
+                ..document("doc1", "def show(request): pass")
+            },
+            document("doc2", "def render(template): pass"),
+        ]).unwrap();
+        
+        let results = bm25.search("user controller", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "doc1");
+    }
+    
+    #[test]
+    fn test_prose_matches_identifier_spellings() {
+        let dir = tempdir().unwrap();
+        let mut bm25 = BM25Search::new(&dir.path().join("index"), dir.path().to_path_buf(), &AnalyzerConfig::default()).unwrap();
+        bm25.insert(vec![
+            document("camel", "let pool = HttpClient::builder().build();"),
+            document("snake", "self.retry_policy.apply(request)"),
+            document("other", "fn render(template: &str) {}"),
+        ]).unwrap();
+        
+        let ids = |query: &str| -> Vec<String> {
+            bm25.search(query, 10).unwrap().into_iter().map(|result| result.id).collect()
+        };
+        assert_eq!(ids("http client"), ["camel"]);
+        assert_eq!(ids("RetryPolicy"), ["snake"]);
+        // Quoted phrases stay required
+        assert_eq!(ids("\"render\" http client"), ["other"]);
+    }
+    
+    #[test]
+    fn test_definition_ranks_above_call_sites() {
+        let dir = tempdir().unwrap();
+        let mut bm25 = BM25Search::new(&dir.path().join("index"), dir.path().to_path_buf(), &AnalyzerConfig::default()).unwrap();
+        let mut documents: Vec<BM25Document> = (0..40)
+            .map(|i| document(&format!("call{i}"), "let config = parse_config(&path)?;\nvalidate(&config);"))
+            .collect();
+        documents.push(document(
+            "definition",
+            "/// Read the settings file\nfn parse_config(path: &Path) -> Result<Config> {\n    let text = std::fs::read_to_string(path)?;\n    toml::from_str(&text)\n}",
+        ));
+        bm25.insert(documents).unwrap();
+        
+        for query in ["parse_config", "ParseConfig", "parse config"] {
+            let results = bm25.search(query, 5).unwrap();
+            assert_eq!(results[0].id, "definition", "query {query}");
+        }
+    }
+    
+    #[test]
+    fn test_stats_read_while_writer_is_open() {
+        let dir = tempdir().unwrap();
+        let codebase_path = dir.path().join("codebase");
+        assert_eq!(BM25Search::stats_for_codebase(&codebase_path, dir.path()).unwrap(), StoreStats::default());
+        
+        let mut bm25 = BM25Search::for_codebase(&codebase_path, dir.path(), &AnalyzerConfig::default()).unwrap();
+        bm25.insert(vec![document("doc1", "fn main() {}"), document("doc2", "fn helper() {}")]).unwrap();
+        
+        let stats = BM25Search::stats_for_codebase(&codebase_path, dir.path()).unwrap();
+        assert_eq!(stats.entries, 2);
+        assert!(stats.files > 0 && stats.bytes > 0);
+    }
+    
+    #[test]
+    fn test_analyzer_change_is_reported() {
+        let dir = tempdir().unwrap();
+        let index_dir = dir.path().join("index");
+        
+        {
+            let bm25 = BM25Search::new(&index_dir, dir.path().to_path_buf(), &AnalyzerConfig::default()).unwrap();
+            assert!(bm25.analyzer_notice().is_none());
+        }
+        
+        let changed = AnalyzerConfig {
+            stemming: true,
+            ..AnalyzerConfig::default()
+        };
+        let bm25 = BM25Search::new(&index_dir, dir.path().to_path_buf(), &changed).unwrap();
+        assert!(bm25.analyzer_notice().is_some());
+    }
+    
+    #[test]
+    fn test_index_of_older_analyzer_is_outdated() {
+        let dir = tempdir().unwrap();
+        let codebase_path = dir.path().join("codebase");
+        drop(BM25Search::for_codebase(&codebase_path, dir.path(), &AnalyzerConfig::default()).unwrap());
+        assert!(!BM25Search::outdated_analyzer(&codebase_path, dir.path()));
+        
+        // Settings written before the version was recorded
+        let settings_path = BM25Search::get_index_path_for_codebase(&codebase_path, dir.path()).join(ANALYZER_SETTINGS_FILE);
+        let mut settings: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&settings_path).unwrap()).unwrap();
+        settings.as_object_mut().unwrap().remove("version");
+        std::fs::write(&settings_path, settings.to_string()).unwrap();
+        
+        assert!(BM25Search::outdated_analyzer(&codebase_path, dir.path()));
+        let bm25 = BM25Search::for_codebase(&codebase_path, dir.path(), &AnalyzerConfig::default()).unwrap();
+        assert!(bm25.analyzer_notice().is_some_and(|notice| notice.contains("rebuilds")));
+    }
+    
+    #[test]
+    fn test_cjk_tokenizer_matches_comment_words() {
+        let dir = tempdir().unwrap();
+        let analyzer = AnalyzerConfig {
+            tokenizer: TokenizerKind::Cjk,
+            ..AnalyzerConfig::default()
+        };
+        
+        let mut bm25 = BM25Search::new(&dir.path().join("index"), dir.path().to_path_buf(), &analyzer).unwrap();
+        bm25.insert(vec![
+            document("doc1", "// 读取配置文件\nfn load() {}"),
+            document("doc2", "// 写入日志\nfn log() {}"),
+        ]).unwrap();
+        
+        let results = bm25.search("配置文件", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "doc1");
+    }
+    
+    #[test]
+    fn test_unicode_variants_match() {
+        let dir = tempdir().unwrap();
+        let mut bm25 = BM25Search::new(&dir.path().join("index"), dir.path().to_path_buf(), &AnalyzerConfig::default()).unwrap();
+        bm25.insert(vec![
+            document("doc1", "fn cafe\u{301}_service() {}"),
+            document("doc2", "fn tea_service() {}"),
+        ]).unwrap();
+        
+        for query in ["café", "ｃａｆé"] {
+            let results = bm25.search(query, 10).unwrap();
+            assert_eq!(results.len(), 1, "query {query}");
+            assert_eq!(results[0].id, "doc1");
+        }
     }
 }
@@ -0,0 +1,149 @@
+//! CJK-aware tokenizer for the BM25 content field
+//!
+//! Chinese, Japanese and Korean text has no whitespace between words, so the
+//! default tokenizer turns a whole comment line into one unsearchable token.
+//! Runs of CJK characters are emitted as overlapping bigrams (the approach
+//! used by Lucene's CJKBigramFilter); everything else is split on
+//! non-alphanumeric characters like `SimpleTokenizer`.
+
+use tantivy::tokenizer::{Token, TokenStream, Tokenizer};
+
+#[derive(Clone, Default)]
+pub struct CjkBigramTokenizer;
+
+pub struct CjkTokenStream {
+    tokens: Vec<Token>,
+    index: usize,
+}
+
+impl Tokenizer for CjkBigramTokenizer {
+    type TokenStream<'a> = CjkTokenStream;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        CjkTokenStream {
+            tokens: tokenize(text),
+            index: 0,
+        }
+    }
+}
+
+impl TokenStream for CjkTokenStream {
+    fn advance(&mut self) -> bool {
+        if self.index < self.tokens.len() {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.tokens[self.index.saturating_sub(1)]
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.tokens[self.index.saturating_sub(1)]
+    }
+}
+
+/// Han ideographs, kana and hangul
+pub fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF     // Hiragana, Katakana
+        | 0x31F0..=0x31FF   // Katakana phonetic extensions
+        | 0x3400..=0x4DBF   // CJK extension A
+        | 0x4E00..=0x9FFF   // CJK unified ideographs
+        | 0xF900..=0xFAFF   // CJK compatibility ideographs
+        | 0xFF66..=0xFF9F   // Halfwidth katakana
+        | 0x1100..=0x11FF   // Hangul jamo
+        | 0x3130..=0x318F   // Hangul compatibility jamo
+        | 0xAC00..=0xD7AF   // Hangul syllables
+        | 0x20000..=0x2A6DF // CJK extension B
+    )
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word_start: Option<usize> = None;
+    let mut cjk_run: Vec<(usize, char)> = Vec::new();
+
+    for (offset, c) in text.char_indices() {
+        if is_cjk(c) {
+            flush_word(text, &mut word_start, offset, &mut tokens);
+            cjk_run.push((offset, c));
+        } else if c.is_alphanumeric() {
+            flush_cjk(&mut cjk_run, &mut tokens);
+            word_start.get_or_insert(offset);
+        } else {
+            flush_word(text, &mut word_start, offset, &mut tokens);
+            flush_cjk(&mut cjk_run, &mut tokens);
+        }
+    }
+
+    flush_word(text, &mut word_start, text.len(), &mut tokens);
+    flush_cjk(&mut cjk_run, &mut tokens);
+
+    tokens
+}
+
+fn flush_word(text: &str, word_start: &mut Option<usize>, end: usize, tokens: &mut Vec<Token>) {
+    if let Some(start) = word_start.take() {
+        push_token(tokens, text[start..end].to_string(), start, end);
+    }
+}
+
+fn flush_cjk(run: &mut Vec<(usize, char)>, tokens: &mut Vec<Token>) {
+    match run.len() {
+        0 => {}
+        1 => {
+            let (offset, c) = run[0];
+            push_token(tokens, c.to_string(), offset, offset + c.len_utf8());
+        }
+        _ => {
+            for pair in run.windows(2) {
+                let (from, first) = pair[0];
+                let (second_offset, second) = pair[1];
+                push_token(
+                    tokens,
+                    format!("{first}{second}"),
+                    from,
+                    second_offset + second.len_utf8(),
+                );
+            }
+        }
+    }
+    run.clear();
+}
+
+fn push_token(tokens: &mut Vec<Token>, text: String, offset_from: usize, offset_to: usize) {
+    let position = tokens.len();
+    tokens.push(Token {
+        offset_from,
+        offset_to,
+        position,
+        text,
+        position_length: 1,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(input: &str) -> Vec<String> {
+        tokenize(input).into_iter().map(|t| t.text).collect()
+    }
+
+    #[test]
+    fn test_cjk_bigrams() {
+        assert_eq!(texts("读取配置"), vec!["读取", "取配", "配置"]);
+        assert_eq!(texts("// 读取API配置"), vec!["读取", "API", "配置"]);
+        assert_eq!(texts("键"), vec!["键"]);
+    }
+
+    #[test]
+    fn test_latin_text_matches_simple_tokenizer() {
+        assert_eq!(texts("fn load_config(path)"), vec!["fn", "load", "config", "path"]);
+        assert_eq!(texts("設定 é"), vec!["設定", "é"]);
+    }
+}
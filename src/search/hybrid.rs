@@ -1,32 +1,80 @@
 
-use super::{BM25Result};
+use super::{BM25Result, IdentifierResult};
 use crate::vectordb::SearchResult as VectorResult;
 use std::collections::HashMap;
 
 #[derive(Clone)]
 pub struct HybridSearch {
     rrf_k: usize,
+    /// Multipliers of each leg's RRF scores
+    vector_weight: f32,
+    bm25_weight: f32,
+    identifier_weight: f32,
 }
 
 impl HybridSearch {
     pub fn new(rrf_k: usize) -> Self {
-        Self { rrf_k }
+        Self { rrf_k, vector_weight: 1.0, bm25_weight: 1.0, identifier_weight: 1.0 }
+    }
+    
+    pub fn with_vector_weight(mut self, weight: f32) -> Self {
+        self.vector_weight = weight;
+        self
+    }
+    
+    pub fn with_identifier_weight(mut self, weight: f32) -> Self {
+        self.identifier_weight = weight;
+        self
+    }
+    
+    pub fn with_bm25_weight(mut self, weight: f32) -> Self {
+        self.bm25_weight = weight;
+        self
     }
     
     pub fn rerank(
         &self,
         vector_results: Vec<VectorResult>,
         bm25_results: Vec<BM25Result>,
+    ) -> Vec<(String, f32)> {
+        self.rerank_with_identifiers(vector_results, bm25_results, Vec::new())
+    }
+    
+    /// RRF over the vector, BM25 and identifier n-gram legs
+    pub fn rerank_with_identifiers(
+        &self,
+        vector_results: Vec<VectorResult>,
+        bm25_results: Vec<BM25Result>,
+        identifier_results: Vec<IdentifierResult>,
+    ) -> Vec<(String, f32)> {
+        self.rerank_with_docs(vector_results, Vec::new(), bm25_results, identifier_results)
+    }
+    
+    /// RRF over the code and documentation vector legs, BM25 and identifier
+    /// n-grams; both vector legs carry the vector weight
+    pub fn rerank_with_docs(
+        &self,
+        vector_results: Vec<VectorResult>,
+        docs_vector_results: Vec<VectorResult>,
+        bm25_results: Vec<BM25Result>,
+        identifier_results: Vec<IdentifierResult>,
     ) -> Vec<(String, f32)> {
         let mut scores: HashMap<String, f32> = HashMap::new();
         
-        for (rank, result) in vector_results.iter().enumerate() {
-            let rrf_score = 1.0 / (self.rrf_k + rank + 1) as f32;
-            *scores.entry(result.id.clone()).or_insert(0.0) += rrf_score;
+        for results in [&vector_results, &docs_vector_results] {
+            for (rank, result) in results.iter().enumerate() {
+                let rrf_score = self.vector_weight / (self.rrf_k + rank + 1) as f32;
+                *scores.entry(result.id.clone()).or_insert(0.0) += rrf_score;
+            }
         }
         
         for (rank, result) in bm25_results.iter().enumerate() {
-            let rrf_score = 1.0 / (self.rrf_k + rank + 1) as f32;
+            let rrf_score = self.bm25_weight / (self.rrf_k + rank + 1) as f32;
+            *scores.entry(result.id.clone()).or_insert(0.0) += rrf_score;
+        }
+        
+        for (rank, result) in identifier_results.iter().enumerate() {
+            let rrf_score = self.identifier_weight / (self.rrf_k + rank + 1) as f32;
             *scores.entry(result.id.clone()).or_insert(0.0) += rrf_score;
         }
         
@@ -36,6 +84,37 @@ impl HybridSearch {
         results
     }
     
+    /// Fused score of a chunk ranked first by every leg that ran: a vector
+    /// leg when `vectors` (a chunk is in the code or the documentation
+    /// index, never both), BM25 and identifiers. Dividing by it puts fused
+    /// scores on the same scale whatever `rrf_k` and the weights are, 1 for
+    /// that chunk; it only reflects ranks, not how well anything matched.
+    pub fn best_score(&self, vectors: bool) -> f32 {
+        let vector_weight = if vectors { self.vector_weight } else { 0.0 };
+        (vector_weight + self.bm25_weight + self.identifier_weight) / (self.rrf_k + 1) as f32
+    }
+    
+    /// RRF over ranked lists whose items never coincide, such as the results
+    /// of different codebases; items tied on rank keep the order of their
+    /// own scores. Returns each item with the index of its list and its RRF score.
+    pub fn interleave<T>(&self, lists: Vec<Vec<(f32, T)>>) -> Vec<(usize, T, f32)> {
+        let mut fused: Vec<(usize, T, f32, f32)> = lists
+            .into_iter()
+            .enumerate()
+            .flat_map(|(list, items)| {
+                items.into_iter().enumerate().map(move |(rank, (score, item))| {
+                    (list, item, 1.0 / (self.rrf_k + rank + 1) as f32, score)
+                })
+            })
+            .collect();
+        fused.sort_by(|a, b| {
+            b.2.partial_cmp(&a.2)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(b.3.partial_cmp(&a.3).unwrap_or(std::cmp::Ordering::Equal))
+        });
+        fused.into_iter().map(|(list, item, rrf_score, _)| (list, item, rrf_score)).collect()
+    }
+    
     pub fn search(
         &self,
         vector_results: Vec<VectorResult>,
@@ -92,4 +171,104 @@ mod tests {
         assert_eq!(results[0].0, "doc1");
         assert!((results[0].1 - 2.0/101.0).abs() < 0.001);
     }
+    
+    #[test]
+    fn test_identifier_leg_promotes_partial_name_match() {
+        let hybrid = HybridSearch::new(100);
+        
+        let vector_results = vec![
+            VectorResult { id: "doc1".to_string(), score: 0.9 },
+            VectorResult { id: "doc2".to_string(), score: 0.8 },
+        ];
+        
+        let identifier_results = vec![
+            IdentifierResult { id: "doc2".to_string(), score: 0.9 },
+        ];
+        
+        let results = hybrid.rerank_with_identifiers(vector_results, Vec::new(), identifier_results);
+        
+        assert_eq!(results[0].0, "doc2");
+    }
+    
+    #[test]
+    fn test_docs_leg_ranks_alongside_code_leg() {
+        let hybrid = HybridSearch::new(100);
+        
+        let vector_results = vec![
+            VectorResult { id: "code1".to_string(), score: 0.9 },
+        ];
+        let docs_vector_results = vec![
+            VectorResult { id: "readme".to_string(), score: 0.6 },
+        ];
+        let bm25_results = vec![
+            BM25Result { id: "readme".to_string(), score: 5.0 },
+        ];
+        
+        let results = hybrid.rerank_with_docs(vector_results, docs_vector_results, bm25_results, Vec::new());
+        
+        assert_eq!(results[0].0, "readme");
+        assert_eq!(results[1].0, "code1");
+    }
+    
+    #[test]
+    fn test_interleave_alternates_lists_by_rank() {
+        let hybrid = HybridSearch::new(100);
+        
+        let small_repo = vec![(0.02, "a1"), (0.01, "a2")];
+        let large_repo = vec![(0.03, "b1"), (0.025, "b2"), (0.02, "b3")];
+        
+        let results = hybrid.interleave(vec![small_repo, large_repo]);
+        
+        let order: Vec<(usize, &str)> = results.iter().map(|(list, item, _)| (*list, *item)).collect();
+        assert_eq!(order, vec![(1, "b1"), (0, "a1"), (1, "b2"), (0, "a2"), (1, "b3")]);
+        assert!((results[0].2 - 1.0 / 101.0).abs() < 1e-6);
+    }
+    
+    #[test]
+    fn test_bm25_weight_favors_keyword_matches() {
+        let vector_results = vec![
+            VectorResult { id: "doc1".to_string(), score: 0.9 },
+            VectorResult { id: "doc2".to_string(), score: 0.8 },
+        ];
+        let bm25_results = vec![
+            BM25Result { id: "doc2".to_string(), score: 10.0 },
+            BM25Result { id: "doc1".to_string(), score: 9.0 },
+        ];
+        
+        let results = HybridSearch::new(0).with_bm25_weight(2.0).rerank(vector_results, bm25_results);
+        
+        assert_eq!(results[0].0, "doc2");
+    }
+    
+    #[test]
+    fn test_vector_weight_favors_semantic_matches() {
+        let vector_results = vec![
+            VectorResult { id: "doc1".to_string(), score: 0.9 },
+            VectorResult { id: "doc2".to_string(), score: 0.8 },
+        ];
+        let bm25_results = vec![
+            BM25Result { id: "doc2".to_string(), score: 10.0 },
+            BM25Result { id: "doc1".to_string(), score: 9.0 },
+        ];
+        
+        let results = HybridSearch::new(0).with_vector_weight(2.0).rerank(vector_results, bm25_results);
+        
+        assert_eq!(results[0].0, "doc1");
+    }
+    
+    #[test]
+    fn test_best_score_calibrates_fused_scores() {
+        let hybrid = HybridSearch::new(60).with_bm25_weight(2.0);
+        let vector_results = vec![VectorResult { id: "doc1".to_string(), score: 0.9 }];
+        let bm25_results = vec![BM25Result { id: "doc1".to_string(), score: 10.0 }];
+        let identifier_results = vec![IdentifierResult { id: "doc1".to_string(), score: 1.0 }];
+        
+        let results = hybrid.rerank_with_identifiers(vector_results.clone(), bm25_results, identifier_results);
+        assert!((results[0].1 / hybrid.best_score(true) - 1.0).abs() < 1e-6);
+        
+        // Found by the vector leg alone: its share of the weights
+        let results = hybrid.rerank(vector_results, Vec::new());
+        assert!((results[0].1 / hybrid.best_score(true) - 0.25).abs() < 1e-6);
+        assert!((hybrid.best_score(false) - 3.0 / 61.0).abs() < 1e-6);
+    }
 }
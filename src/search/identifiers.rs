@@ -0,0 +1,572 @@
+//! Identifier n-gram index for partial-name matching
+//!
+//! Identifiers are extracted from chunk content and their trigrams are indexed
+//! in sled trees next to the chunk metadata, so partial names such as
+//! "synchro" or "usearch_db" still reach `FileSynchronizer` / `USearchDatabase`
+//! when both BM25 tokenization and embeddings miss them.
+
+use crate::{Error, Result};
+use crate::types::CodeChunk;
+use super::IdentifierResult;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+const OCCURRENCES_TREE: &str = "identifiers";
+const TRIGRAMS_TREE: &str = "identifier_trigrams";
+const CHUNK_IDENTIFIERS_TREE: &str = "chunk_identifiers";
+
+/// Identifiers shorter than this are not indexed
+const MIN_IDENTIFIER_LEN: usize = 3;
+
+/// Query tokens need at least two trigrams to avoid matching everything
+const MIN_QUERY_TOKEN_LEN: usize = 4;
+
+/// Minimum fraction of query trigrams an identifier must contain
+const MIN_TRIGRAM_OVERLAP: f32 = 0.75;
+
+const MAX_MATCHED_IDENTIFIERS: usize = 50;
+
+/// Keywords and noise words that would only bloat the index
+const IGNORED_IDENTIFIERS: &[&str] = &[
+    "and", "break", "case", "catch", "class", "const", "continue", "def", "else",
+    "elif", "enum", "false", "for", "from", "func", "function", "if", "impl",
+    "import", "let", "mut", "nil", "none", "not", "null", "pub", "return",
+    "self", "static", "struct", "this", "true", "try", "use", "var", "void",
+    "while", "with",
+];
+
+/// Keywords that introduce a definition of the identifier that follows them
+const DEFINITION_KEYWORDS: &[&str] = &[
+    "class", "const", "def", "enum", "fn", "func", "function", "interface",
+    "let", "macro_rules!", "mod", "module", "protocol", "static", "struct",
+    "trait", "type", "union", "val", "var",
+];
+
+/// Definition keywords that name a function, type or module rather than a
+/// local binding
+const SYMBOL_KEYWORDS: &[&str] = &[
+    "class", "def", "enum", "fn", "func", "function", "interface",
+    "macro_rules!", "mod", "module", "protocol", "struct", "trait", "type",
+    "union",
+];
+
+/// A single occurrence of an identifier inside a chunk
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IdentifierOccurrence {
+    /// Identifier as written in the source
+    pub name: String,
+    /// Absolute line number in the file
+    pub line: usize,
+    /// Whether the occurrence looks like a definition (`fn name`, `class Name`, ...)
+    pub is_definition: bool,
+}
+
+/// Trigram index over identifiers, backed by trees of the metadata sled DB
+pub struct IdentifierIndex {
+    /// `{identifier_lowercase}\0{chunk_id}` -> occurrences in that chunk
+    occurrences: sled::Tree,
+    /// `{trigram}\0{identifier_lowercase}` -> empty
+    trigrams: sled::Tree,
+    /// `{chunk_id}` -> identifiers (lowercase) found in that chunk
+    chunk_identifiers: sled::Tree,
+}
+
+impl IdentifierIndex {
+    pub fn open(db: &sled::Db) -> Result<Self> {
+        Ok(Self {
+            occurrences: open_tree(db, OCCURRENCES_TREE)?,
+            trigrams: open_tree(db, TRIGRAMS_TREE)?,
+            chunk_identifiers: open_tree(db, CHUNK_IDENTIFIERS_TREE)?,
+        })
+    }
+
+    /// Index identifiers for a batch of chunks, replacing any previous entries
+    pub fn insert_batch(&self, chunks: &[CodeChunk]) -> Result<()> {
+        let chunk_ids: Vec<String> = chunks.iter().map(|c| c.id.clone()).collect();
+        self.delete_chunks(&chunk_ids)?;
+
+        let mut occurrence_batch = sled::Batch::default();
+        let mut trigram_batch = sled::Batch::default();
+        let mut chunk_batch = sled::Batch::default();
+
+        for chunk in chunks {
+            let mut grouped: HashMap<String, Vec<IdentifierOccurrence>> = HashMap::new();
+            for occurrence in extract_identifiers(&chunk.content, chunk.start_line) {
+                grouped
+                    .entry(occurrence.name.to_lowercase())
+                    .or_default()
+                    .push(occurrence);
+            }
+
+            if grouped.is_empty() {
+                continue;
+            }
+
+            for (key, occurrences) in &grouped {
+                occurrence_batch.insert(occurrence_key(key, &chunk.id), encode(occurrences)?);
+                for trigram in trigrams(&normalize_identifier(key)) {
+                    trigram_batch.insert(trigram_key(&trigram, key), Vec::<u8>::new());
+                }
+            }
+
+            let keys: Vec<&String> = grouped.keys().collect();
+            chunk_batch.insert(chunk.id.as_bytes(), encode(&keys)?);
+        }
+
+        self.occurrences.apply_batch(occurrence_batch).map_err(storage_error)?;
+        self.trigrams.apply_batch(trigram_batch).map_err(storage_error)?;
+        self.chunk_identifiers.apply_batch(chunk_batch).map_err(storage_error)?;
+
+        Ok(())
+    }
+
+    /// Remove all identifier entries for the given chunks
+    pub fn delete_chunks(&self, chunk_ids: &[String]) -> Result<()> {
+        let mut touched = HashSet::new();
+
+        for chunk_id in chunk_ids {
+            if let Some(bytes) = self.chunk_identifiers.remove(chunk_id.as_bytes()).map_err(storage_error)? {
+                let keys: Vec<String> = decode(&bytes)?;
+                for key in keys {
+                    self.occurrences.remove(occurrence_key(&key, chunk_id)).map_err(storage_error)?;
+                    touched.insert(key);
+                }
+            }
+        }
+
+        // Drop trigram entries of identifiers that no longer occur anywhere
+        for key in touched {
+            if self.occurrences.scan_prefix(identifier_prefix(&key)).next().is_none() {
+                for trigram in trigrams(&normalize_identifier(&key)) {
+                    self.trigrams.remove(trigram_key(&trigram, &key)).map_err(storage_error)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Find chunks containing identifiers that partially match the query tokens
+    pub fn search(&self, query: &str, top_k: usize) -> Result<Vec<IdentifierResult>> {
+        let mut identifier_scores: HashMap<String, f32> = HashMap::new();
+
+        for token in query_tokens(query) {
+            let normalized = normalize_identifier(&token);
+            let query_trigrams = trigrams(&normalized);
+            if query_trigrams.is_empty() {
+                continue;
+            }
+
+            let mut hits: HashMap<String, usize> = HashMap::new();
+            for trigram in &query_trigrams {
+                for entry in self.trigrams.scan_prefix(trigram_prefix(trigram)) {
+                    let (key, _) = entry.map_err(storage_error)?;
+                    if let Some(identifier) = identifier_from_trigram_key(&key) {
+                        *hits.entry(identifier).or_insert(0) += 1;
+                    }
+                }
+            }
+
+            let token_len = normalized.chars().count();
+            for (identifier, shared) in hits {
+                let overlap = shared as f32 / query_trigrams.len() as f32;
+                if overlap < MIN_TRIGRAM_OVERLAP {
+                    continue;
+                }
+
+                // Prefer identifiers whose length is close to the query token
+                let identifier_len = normalize_identifier(&identifier).chars().count().max(1);
+                let closeness = token_len.min(identifier_len) as f32 / identifier_len as f32;
+                *identifier_scores.entry(identifier).or_insert(0.0) += overlap * (0.5 + 0.5 * closeness);
+            }
+        }
+
+        let mut ranked_identifiers: Vec<(String, f32)> = identifier_scores.into_iter().collect();
+        ranked_identifiers.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        ranked_identifiers.truncate(MAX_MATCHED_IDENTIFIERS);
+
+        let mut chunk_scores: HashMap<String, f32> = HashMap::new();
+        for (identifier, score) in ranked_identifiers {
+            for chunk_id in self.chunks_for_identifier(&identifier)? {
+                let entry = chunk_scores.entry(chunk_id).or_insert(0.0);
+                *entry = entry.max(score);
+            }
+        }
+
+        let mut results: Vec<IdentifierResult> = chunk_scores
+            .into_iter()
+            .map(|(id, score)| IdentifierResult { id, score })
+            .collect();
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        results.truncate(top_k);
+
+        Ok(results)
+    }
+
+    /// Case-insensitive lookup of an identifier, returning `(chunk_id, occurrence)` pairs
+    ///
+    /// With `whole_word` set only identifiers equal to `name` match, otherwise
+    /// every identifier containing `name` does.
+    pub fn lookup(&self, name: &str, whole_word: bool) -> Result<Vec<(String, IdentifierOccurrence)>> {
+        let needle = name.to_lowercase();
+        let identifiers = if whole_word {
+            vec![needle]
+        } else {
+            self.identifiers_containing(&needle)?
+        };
+
+        let mut results = Vec::new();
+        for identifier in identifiers {
+            for entry in self.occurrences.scan_prefix(identifier_prefix(&identifier)) {
+                let (key, value) = entry.map_err(storage_error)?;
+                if let Some((_, chunk_id)) = split_key(&key) {
+                    let occurrences: Vec<IdentifierOccurrence> = decode(&value)?;
+                    results.extend(occurrences.into_iter().map(|o| (chunk_id.clone(), o)));
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Whether the chunk holds `name` exactly as written: `Some(true)` when it
+    /// defines a function, type or module of that name, `Some(false)` when it
+    /// only uses it or binds a local to it, `None` without it. `content` is the
+    /// chunk's text, which tells symbol definitions from local bindings.
+    pub fn exact_match(&self, chunk_id: &str, name: &str, content: &str) -> Result<Option<bool>> {
+        let Some(value) = self.occurrences.get(occurrence_key(&name.to_lowercase(), chunk_id)).map_err(storage_error)? else {
+            return Ok(None);
+        };
+        let occurrences: Vec<IdentifierOccurrence> = decode(&value)?;
+        let mut exact = occurrences.iter().filter(|occurrence| occurrence.name == name).peekable();
+        if exact.peek().is_none() {
+            return Ok(None);
+        }
+        let defines = exact.any(|occurrence| occurrence.is_definition)
+            && defined_symbols(content).iter().any(|symbol| symbol == name);
+        Ok(Some(defines))
+    }
+
+    /// Number of distinct identifiers currently indexed
+    pub fn count(&self) -> usize {
+        let mut previous: Option<String> = None;
+        let mut count = 0;
+        for (key, _) in self.occurrences.iter().flatten() {
+            let identifier = split_key(&key).map(|(identifier, _)| identifier);
+            if identifier.is_some() && identifier != previous {
+                count += 1;
+                previous = identifier;
+            }
+        }
+        count
+    }
+
+    pub fn clear(&self) -> Result<()> {
+        self.occurrences.clear().map_err(storage_error)?;
+        self.trigrams.clear().map_err(storage_error)?;
+        self.chunk_identifiers.clear().map_err(storage_error)?;
+        Ok(())
+    }
+
+    fn identifiers_containing(&self, needle: &str) -> Result<Vec<String>> {
+        let needle_trigrams = trigrams(&normalize_identifier(needle));
+
+        // An identifier containing the needle necessarily contains all of its trigrams
+        let mut candidates: Option<HashSet<String>> = None;
+        for trigram in &needle_trigrams {
+            let with_trigram = self.identifiers_with_trigram(trigram)?;
+            candidates = Some(match candidates {
+                Some(mut current) => {
+                    current.retain(|c| with_trigram.contains(c));
+                    current
+                }
+                None => with_trigram,
+            });
+        }
+
+        let candidates = match candidates {
+            Some(candidates) => candidates,
+            None => self.all_identifiers()?,
+        };
+
+        Ok(candidates.into_iter().filter(|c| c.contains(needle)).collect())
+    }
+
+    fn identifiers_with_trigram(&self, trigram: &str) -> Result<HashSet<String>> {
+        let mut identifiers = HashSet::new();
+        for entry in self.trigrams.scan_prefix(trigram_prefix(trigram)) {
+            let (key, _) = entry.map_err(storage_error)?;
+            if let Some(identifier) = identifier_from_trigram_key(&key) {
+                identifiers.insert(identifier);
+            }
+        }
+        Ok(identifiers)
+    }
+
+    fn all_identifiers(&self) -> Result<HashSet<String>> {
+        let mut identifiers = HashSet::new();
+        for entry in self.occurrences.iter() {
+            let (key, _) = entry.map_err(storage_error)?;
+            if let Some((identifier, _)) = split_key(&key) {
+                identifiers.insert(identifier);
+            }
+        }
+        Ok(identifiers)
+    }
+
+    fn chunks_for_identifier(&self, identifier: &str) -> Result<Vec<String>> {
+        let mut chunk_ids = Vec::new();
+        for entry in self.occurrences.scan_prefix(identifier_prefix(identifier)) {
+            let (key, _) = entry.map_err(storage_error)?;
+            if let Some((_, chunk_id)) = split_key(&key) {
+                chunk_ids.push(chunk_id);
+            }
+        }
+        Ok(chunk_ids)
+    }
+}
+
+/// Extract identifier occurrences from chunk content
+///
+/// `start_line` is the file line of the first content line, so returned
+/// occurrences carry absolute line numbers.
+pub fn extract_identifiers(content: &str, start_line: usize) -> Vec<IdentifierOccurrence> {
+    let mut seen = HashSet::new();
+    let mut occurrences = Vec::new();
+
+    for (offset, line) in content.lines().enumerate() {
+        for found in identifier_regex().find_iter(line) {
+            let name = found.as_str();
+            if !is_indexable(name) {
+                continue;
+            }
+
+            let line_number = start_line + offset;
+            if seen.insert((name.to_string(), line_number)) {
+                occurrences.push(IdentifierOccurrence {
+                    name: name.to_string(),
+                    line: line_number,
+                    is_definition: is_definition_context(&line[..found.start()]),
+                });
+            }
+        }
+    }
+
+    occurrences
+}
+
+/// Names of the functions, types and modules `content` defines, in order
+/// of appearance, without repeats
+pub fn defined_symbols(content: &str) -> Vec<String> {
+    let mut symbols: Vec<String> = Vec::new();
+    for line in content.lines() {
+        for found in identifier_regex().find_iter(line) {
+            let name = found.as_str();
+            let defined = preceding_keyword(&line[..found.start()]).is_some_and(|word| SYMBOL_KEYWORDS.contains(&word));
+            if defined && is_indexable(name) && !symbols.iter().any(|symbol| symbol == name) {
+                symbols.push(name.to_string());
+            }
+        }
+    }
+    symbols
+}
+
+/// Lowercase and drop separators so `usearch_db` and `USearchDb` compare equal
+pub fn normalize_identifier(name: &str) -> String {
+    name.chars()
+        .filter(|c| *c != '_')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn is_indexable(name: &str) -> bool {
+    name.len() >= MIN_IDENTIFIER_LEN
+        && name.chars().any(|c| c.is_ascii_alphabetic())
+        && !IGNORED_IDENTIFIERS.contains(&name.to_lowercase().as_str())
+}
+
+/// Check whether the text preceding an identifier ends with a definition keyword
+fn is_definition_context(preceding: &str) -> bool {
+    preceding_keyword(preceding).is_some_and(|word| DEFINITION_KEYWORDS.contains(&word))
+}
+
+/// Last word of the text preceding an identifier
+fn preceding_keyword(preceding: &str) -> Option<&str> {
+    preceding
+        .trim_end()
+        .rsplit(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '!'))
+        .next()
+}
+
+fn identifier_regex() -> &'static regex::Regex {
+    static IDENTIFIER_RE: OnceLock<regex::Regex> = OnceLock::new();
+    IDENTIFIER_RE.get_or_init(|| {
+        regex::Regex::new(r"[A-Za-z_][A-Za-z0-9_]*").expect("valid identifier regex")
+    })
+}
+
+fn query_tokens(query: &str) -> Vec<String> {
+    query
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|token| normalize_identifier(token).chars().count() >= MIN_QUERY_TOKEN_LEN)
+        .filter(|token| !IGNORED_IDENTIFIERS.contains(&token.to_lowercase().as_str()))
+        .map(String::from)
+        .collect()
+}
+
+fn trigrams(text: &str) -> HashSet<String> {
+    let chars: Vec<char> = text.chars().collect();
+    chars.windows(3).map(|w| w.iter().collect()).collect()
+}
+
+fn occurrence_key(identifier: &str, chunk_id: &str) -> Vec<u8> {
+    format!("{identifier}\0{chunk_id}").into_bytes()
+}
+
+fn identifier_prefix(identifier: &str) -> Vec<u8> {
+    format!("{identifier}\0").into_bytes()
+}
+
+fn trigram_key(trigram: &str, identifier: &str) -> Vec<u8> {
+    format!("{trigram}\0{identifier}").into_bytes()
+}
+
+fn trigram_prefix(trigram: &str) -> Vec<u8> {
+    format!("{trigram}\0").into_bytes()
+}
+
+fn split_key(key: &[u8]) -> Option<(String, String)> {
+    let key = std::str::from_utf8(key).ok()?;
+    let (left, right) = key.split_once('\0')?;
+    Some((left.to_string(), right.to_string()))
+}
+
+fn identifier_from_trigram_key(key: &[u8]) -> Option<String> {
+    split_key(key).map(|(_, identifier)| identifier)
+}
+
+fn open_tree(db: &sled::Db, name: &str) -> Result<sled::Tree> {
+    db.open_tree(name).map_err(storage_error)
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    bincode::serde::encode_to_vec(value, bincode::config::standard())
+        .map_err(|e| Error::Io(std::io::Error::other(
+            format!("Failed to serialize identifiers: {e}")
+        )))
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+        .map(|(value, _len)| value)
+        .map_err(|e| Error::Io(std::io::Error::other(
+            format!("Failed to deserialize identifiers: {e}")
+        )))
+}
+
+fn storage_error(e: sled::Error) -> Error {
+    Error::Io(std::io::Error::other(format!("Identifier index error: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChunkMetadata;
+    use std::path::PathBuf;
+
+    fn chunk(id: &str, content: &str, start_line: usize) -> CodeChunk {
+        CodeChunk {
+            id: id.to_string(),
+            content: content.to_string(),
+            file_path: PathBuf::from(format!("/test/{id}.rs")),
+            relative_path: format!("{id}.rs"),
+            start_line,
+            end_line: start_line + content.lines().count(),
+            language: "rust".to_string(),
+            metadata: ChunkMetadata {
+                file_extension: ".rs".to_string(),
+                chunk_index: 0,
+                hash: id.to_string(),
+                modified_at: None,
+                file_hash: None,
+                sync_generation: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_extract_identifiers() {
+        let occurrences = extract_identifiers("pub struct FileSynchronizer {\n    root_dir: PathBuf,\n}", 10);
+
+        assert!(occurrences.contains(&IdentifierOccurrence { name: "FileSynchronizer".to_string(), line: 10, is_definition: true }));
+        assert!(occurrences.contains(&IdentifierOccurrence { name: "root_dir".to_string(), line: 11, is_definition: false }));
+        assert!(!occurrences.iter().any(|o| o.name == "pub" || o.name == "struct"));
+    }
+
+    #[test]
+    fn test_defined_symbols_skip_local_bindings() {
+        let content = "pub struct Config {}\nimpl Config {\n    pub fn parse_config() -> Config {\n        let parsed = Config {};\n        parsed\n    }\n}\nclass Loader:\n    def load(self): pass";
+        assert_eq!(defined_symbols(content), vec!["Config", "parse_config", "Loader", "load"]);
+    }
+
+    #[test]
+    fn test_lookup_whole_word_and_substring() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let index = IdentifierIndex::open(&db).unwrap();
+
+        index.insert_batch(&[
+            chunk("def", "fn parse_config() {}", 1),
+            chunk("call", "let cfg = PARSE_CONFIG();\nparse_config_file();", 5),
+        ]).unwrap();
+
+        let exact = index.lookup("Parse_Config", true).unwrap();
+        assert_eq!(exact.len(), 2);
+        assert!(exact.iter().any(|(id, o)| id == "def" && o.is_definition));
+        assert!(exact.iter().any(|(id, o)| id == "call" && o.line == 5 && !o.is_definition));
+
+        let partial = index.lookup("parse_config", false).unwrap();
+        assert_eq!(partial.len(), 3);
+        assert!(partial.iter().any(|(_, o)| o.name == "parse_config_file" && o.line == 6));
+    }
+
+    #[test]
+    fn test_exact_match_is_case_sensitive() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let index = IdentifierIndex::open(&db).unwrap();
+
+        let definition = "pub struct RateLimiter {}";
+        let usage = "let limiter = RateLimiter::new();";
+        index.insert_batch(&[chunk("def", definition, 1), chunk("use", usage, 1)]).unwrap();
+
+        assert_eq!(index.exact_match("def", "RateLimiter", definition).unwrap(), Some(true));
+        assert_eq!(index.exact_match("use", "RateLimiter", usage).unwrap(), Some(false));
+        assert_eq!(index.exact_match("use", "ratelimiter", usage).unwrap(), None);
+        assert_eq!(index.exact_match("def", "limiter", definition).unwrap(), None);
+        // A local binding is not a definition of the name
+        assert_eq!(index.exact_match("use", "limiter", usage).unwrap(), Some(false));
+    }
+
+    #[test]
+    fn test_partial_name_matching() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let index = IdentifierIndex::open(&db).unwrap();
+
+        index.insert_batch(&[
+            chunk("sync", "pub struct FileSynchronizer {}", 1),
+            chunk("vectordb", "impl USearchDatabase {}", 1),
+            chunk("other", "fn unrelated_helper() {}", 1),
+        ]).unwrap();
+
+        let results = index.search("synchro", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "sync");
+
+        let results = index.search("where is usearch_db", 10).unwrap();
+        assert_eq!(results[0].id, "vectordb");
+
+        index.delete_chunks(&["sync".to_string()]).unwrap();
+        assert!(index.search("synchro", 10).unwrap().is_empty());
+        assert_eq!(index.count(), 2);
+    }
+}
@@ -0,0 +1,287 @@
+//! Reranking search results with a chat model
+//!
+//! Fusion ranks candidates by where the retrievers placed them, which cannot
+//! tell a chunk that answers the query from one that merely shares its words.
+//! When configured, the top fused candidates are sent in one request to a
+//! cheap model behind an OpenAI-compatible `/chat/completions` endpoint, which
+//! rates each from 0 to 10 against the query, and are reordered by those
+//! ratings. Ratings are stored in a sled database under
+//! `<data_dir>/rerank_cache`, keyed by hashes of the query and the chunk
+//! content, so repeated searches only pay for chunks that changed.
+
+use crate::config::{HttpConfig, LlmRerankConfig};
+use crate::embeddings::http::build_client;
+use crate::types::SearchResult;
+use crate::{Error, Result};
+use reqwest::Client;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::{info, warn};
+
+/// Longest snippet of a candidate sent to the model
+const MAX_SNIPPET_CHARS: usize = 2000;
+
+/// Stored ratings beyond this many evict the least recently used ones
+const MAX_CACHED_RATINGS: usize = 200_000;
+
+/// Eviction stops once this fraction of `MAX_CACHED_RATINGS` is left
+const EVICTION_TARGET: f64 = 0.9;
+
+const INSTRUCTIONS: &str = "You rate how well code snippets answer a code search query. \
+Reply with only a JSON array of integers, one per snippet in the order given, \
+from 0 (unrelated to the query) to 10 (exactly what the query is looking for).";
+
+pub struct LlmReranker {
+    client: Client,
+    config: LlmRerankConfig,
+    /// Ratings of earlier searches; `None` when the cache failed to open
+    cache: Option<sled::Db>,
+    /// Ratings in the cache
+    cached: AtomicUsize,
+}
+
+impl LlmReranker {
+    /// Reranker of `config`, caching ratings at `cache_path`
+    pub fn new(config: LlmRerankConfig, http: &HttpConfig, cache_path: &Path) -> Result<Self> {
+        let client = build_client(http)?;
+        let cache = match sled::open(cache_path) {
+            Ok(cache) => Some(cache),
+            Err(e) => {
+                // Reranking still works, only paying for every rating
+                warn!("[RERANK] Rating cache disabled, failed to open {}: {}", cache_path.display(), e);
+                None
+            }
+        };
+        let cached = AtomicUsize::new(cache.as_ref().map_or(0, |cache| cache.len()));
+        Ok(Self { client, config, cache, cached })
+    }
+
+    pub fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    /// Top fused candidates the model scores
+    pub fn candidates(&self) -> usize {
+        self.config.candidates
+    }
+
+    /// Reorder the first `candidates` of `results` by the model's rating
+    /// against `query`, best first; equally rated ones and the candidates
+    /// beyond keep their fused order. On failure `results` are left as they are.
+    pub async fn rerank(&self, query: &str, results: &mut Vec<SearchResult>) -> Result<()> {
+        let count = results.len().min(self.config.candidates);
+        if count < 2 {
+            return Ok(());
+        }
+        let keys: Vec<[u8; 64]> = results[..count]
+            .iter()
+            .map(|result| rating_key(&self.config.model, query, &result.content))
+            .collect();
+        let mut ratings: Vec<Option<f32>> = keys.iter().map(|key| self.cached_rating(key)).collect();
+
+        let missing: Vec<usize> = (0..count).filter(|&i| ratings[i].is_none()).collect();
+        if !missing.is_empty() {
+            let snippets: Vec<&SearchResult> = missing.iter().map(|&i| &results[i]).collect();
+            let rated = self.rate(query, &snippets).await?;
+            for (&i, rating) in missing.iter().zip(rated) {
+                ratings[i] = Some(rating);
+            }
+            self.store_ratings(missing.iter().map(|&i| (&keys[i], ratings[i].unwrap_or_default())));
+        }
+        info!(
+            "[RERANK] Rated {} candidates with {} ({} cached)",
+            count,
+            self.config.model,
+            count - missing.len()
+        );
+
+        let mut order: Vec<usize> = (0..count).collect();
+        // Stable, so equal ratings keep the fused order
+        order.sort_by(|&a, &b| {
+            let rating = |i: usize| ratings[i].unwrap_or_default();
+            rating(b).total_cmp(&rating(a))
+        });
+        let mut candidates: Vec<Option<SearchResult>> = results.drain(..count).map(Some).collect();
+        let mut reranked: Vec<SearchResult> = order.into_iter().filter_map(|i| candidates[i].take()).collect();
+        reranked.append(results);
+        for (rank, result) in reranked.iter_mut().enumerate() {
+            result.rank = rank + 1;
+        }
+        *results = reranked;
+        Ok(())
+    }
+
+    /// Ratings of `snippets` against `query`, in one request
+    async fn rate(&self, query: &str, snippets: &[&SearchResult]) -> Result<Vec<f32>> {
+        let mut prompt = format!("Query: {query}\n");
+        for (i, snippet) in snippets.iter().enumerate() {
+            prompt.push_str(&format!(
+                "\nSnippet {} ({}:{}-{}):\n```\n{}\n```\n",
+                i + 1,
+                snippet.relative_path,
+                snippet.start_line,
+                snippet.end_line,
+                truncate_snippet(&snippet.content)
+            ));
+        }
+        let request = serde_json::json!({
+            "model": self.config.model,
+            "temperature": 0,
+            "messages": [
+                { "role": "system", "content": INSTRUCTIONS },
+                { "role": "user", "content": prompt },
+            ],
+        });
+
+        let url = format!("{}/chat/completions", self.config.base_url.trim_end_matches('/'));
+        let mut builder = self.client.post(url).json(&request);
+        if let Some(api_key) = &self.config.api_key {
+            builder = builder.bearer_auth(api_key);
+        }
+        let response = builder.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(Error::Rerank(format!("API error ({status}): {error_text}")));
+        }
+
+        let body: serde_json::Value = response.json().await?;
+        let reply = body["choices"][0]["message"]["content"].as_str().unwrap_or_default();
+        parse_ratings(reply, snippets.len()).ok_or_else(|| {
+            let mut excerpt = reply.to_string();
+            excerpt.truncate(200);
+            Error::Rerank(format!(
+                "No rating for each of {} snippets: {}",
+                snippets.len(),
+                excerpt
+            ))
+        })
+    }
+
+    fn cached_rating(&self, key: &[u8; 64]) -> Option<f32> {
+        let cache = self.cache.as_ref()?;
+        let value = cache.get(key).ok()??;
+        let rating = f32::from_le_bytes(value.get(8..12)?.try_into().ok()?);
+        // Refresh the access time that eviction orders by
+        let _ = cache.insert(key, encode_entry(now_secs(), rating));
+        Some(rating)
+    }
+
+    fn store_ratings<'a>(&self, ratings: impl IntoIterator<Item = (&'a [u8; 64], f32)>) {
+        let Some(cache) = &self.cache else {
+            return;
+        };
+        let now = now_secs();
+        for (key, rating) in ratings {
+            match cache.insert(key, encode_entry(now, rating)) {
+                Ok(None) => {
+                    self.cached.fetch_add(1, Ordering::Relaxed);
+                }
+                Ok(Some(_)) => {}
+                Err(e) => {
+                    warn!("[RERANK] Failed to cache a rating: {}", e);
+                    return;
+                }
+            }
+        }
+        if self.cached.load(Ordering::Relaxed) > MAX_CACHED_RATINGS {
+            self.cached.store(evict(cache), Ordering::Relaxed);
+        }
+    }
+}
+
+/// Remove the least recently used ratings until `EVICTION_TARGET` of the
+/// limit is left; returns how many are left
+fn evict(cache: &sled::Db) -> usize {
+    let mut entries: Vec<(u64, sled::IVec)> = cache
+        .iter()
+        .filter_map(|entry| entry.ok())
+        .map(|(key, value)| {
+            let used_at = value.get(..8).map_or(0, |b| u64::from_le_bytes(b.try_into().unwrap_or_default()));
+            (used_at, key)
+        })
+        .collect();
+    entries.sort_by_key(|(used_at, _)| *used_at);
+    let target = (MAX_CACHED_RATINGS as f64 * EVICTION_TARGET) as usize;
+    let stored = entries.len();
+    let excess = stored.saturating_sub(target);
+    for (_, key) in entries.into_iter().take(excess) {
+        let _ = cache.remove(key);
+    }
+    info!("[RERANK] Evicted {} least recently used ratings", excess);
+    stored - excess
+}
+
+/// Last access time in seconds, then the rating as a little-endian `f32`
+fn encode_entry(used_at: u64, rating: f32) -> Vec<u8> {
+    let mut bytes = used_at.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&rating.to_le_bytes());
+    bytes
+}
+
+/// Hash of the model and query, then hash of the chunk content
+fn rating_key(model: &str, query: &str, content: &str) -> [u8; 64] {
+    let mut key = [0; 64];
+    let mut query_hasher = Sha256::new();
+    query_hasher.update(model.as_bytes());
+    query_hasher.update([0]);
+    query_hasher.update(query.trim().as_bytes());
+    key[..32].copy_from_slice(&query_hasher.finalize());
+    key[32..].copy_from_slice(&Sha256::digest(content.as_bytes()));
+    key
+}
+
+/// Ratings in 0..=1 from the model's reply, `None` unless it holds a JSON
+/// array of exactly `expected` numbers; models often wrap it in prose or a
+/// code fence
+fn parse_ratings(reply: &str, expected: usize) -> Option<Vec<f32>> {
+    let start = reply.find('[')?;
+    let end = reply.rfind(']')?;
+    let values: Vec<serde_json::Value> = serde_json::from_str(reply.get(start..=end)?).ok()?;
+    if values.len() != expected {
+        return None;
+    }
+    values
+        .iter()
+        .map(|value| value.as_f64().map(|rating| (rating as f32).clamp(0.0, 10.0) / 10.0))
+        .collect()
+}
+
+fn truncate_snippet(content: &str) -> &str {
+    match content.char_indices().nth(MAX_SNIPPET_CHARS) {
+        Some((end, _)) => &content[..end],
+        None => content,
+    }
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ratings_are_parsed_from_the_reply() {
+        assert_eq!(parse_ratings("[10, 0, 5]", 3), Some(vec![1.0, 0.0, 0.5]));
+        assert_eq!(parse_ratings("Ratings:\n```json\n[7, 12]\n```", 2), Some(vec![0.7, 1.0]));
+        assert_eq!(parse_ratings("[3, 4]", 3), None);
+        assert_eq!(parse_ratings("[3, \"high\"]", 2), None);
+        assert_eq!(parse_ratings("no idea", 1), None);
+    }
+
+    #[test]
+    fn test_rating_keys_separate_queries_and_chunks() {
+        let key = rating_key("gpt-4o-mini", "parse config", "fn parse() {}");
+        assert_eq!(key, rating_key("gpt-4o-mini", " parse config ", "fn parse() {}"));
+        assert_ne!(key, rating_key("gpt-4o-mini", "load config", "fn parse() {}"));
+        assert_ne!(key, rating_key("gpt-4o-mini", "parse config", "fn load() {}"));
+        assert_ne!(key, rating_key("other-model", "parse config", "fn parse() {}"));
+        assert_eq!(key[32..], rating_key("gpt-4o-mini", "load config", "fn parse() {}")[32..]);
+    }
+}
@@ -1,9 +1,18 @@
 
 pub mod bm25;
+pub mod cjk;
+pub mod normalize;
 pub mod hybrid;
+pub mod identifiers;
+pub mod llm_rerank;
+pub mod path_glob;
+pub mod query;
 
 pub use bm25::BM25Search;
 pub use hybrid::HybridSearch;
+pub use identifiers::IdentifierIndex;
+pub use llm_rerank::LlmReranker;
+pub use path_glob::PathGlobs;
 
 #[derive(Debug, Clone)]
 pub struct BM25Document {
@@ -12,6 +21,56 @@ pub struct BM25Document {
     pub file_path: String,
     pub start_line: u64,
     pub end_line: u64,
+    /// Names the chunk defines, as written and split into words; see `symbol_tokens`
+    pub symbols: String,
+}
+
+impl From<&crate::types::CodeChunk> for BM25Document {
+    fn from(chunk: &crate::types::CodeChunk) -> Self {
+        Self {
+            id: chunk.id.clone(),
+            content: chunk.content.clone(),
+            file_path: chunk.relative_path.clone(),
+            start_line: chunk.start_line as u64,
+            end_line: chunk.end_line as u64,
+            symbols: symbol_tokens(&chunk.content),
+        }
+    }
+}
+
+/// Names of the functions, classes, structs and other types and modules
+/// `content` defines: as written, joined without separators, and split into words,
+/// so `parse_config`, `ParseConfig` and "parse config" all reach a chunk
+/// defining `parse_config`
+pub fn symbol_tokens(content: &str) -> String {
+    let names = identifiers::defined_symbols(content);
+    let joined: Vec<String> = names.iter().map(|name| identifiers::normalize_identifier(name)).collect();
+    let words = path_tokens(&names.join(" "));
+    format!("{} {} {}", names.join(" "), joined.join(" "), words)
+}
+
+/// Lowercase words of a relative path, split on separators and camelCase,
+/// so `src/UserController.ts` reads as `src user controller ts`
+pub fn path_tokens(relative_path: &str) -> String {
+    let mut words = Vec::new();
+
+    for part in relative_path.split(|c: char| !c.is_alphanumeric()).filter(|p| !p.is_empty()) {
+        let chars: Vec<char> = part.chars().collect();
+        let mut start = 0;
+        for i in 1..chars.len() {
+            // `userId` splits before `I`, `HTTPServer` before `S`
+            let boundary = chars[i].is_uppercase()
+                && (chars[i - 1].is_lowercase()
+                    || (chars[i - 1].is_uppercase() && chars.get(i + 1).is_some_and(|c| c.is_lowercase())));
+            if boundary {
+                words.push(chars[start..i].iter().collect::<String>().to_lowercase());
+                start = i;
+            }
+        }
+        words.push(chars[start..].iter().collect::<String>().to_lowercase());
+    }
+
+    words.join(" ")
 }
 
 #[derive(Debug, Clone)]
@@ -19,3 +78,9 @@ pub struct BM25Result {
     pub id: String,
     pub score: f32,
 }
+
+#[derive(Debug, Clone)]
+pub struct IdentifierResult {
+    pub id: String,
+    pub score: f32,
+}
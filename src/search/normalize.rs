@@ -0,0 +1,112 @@
+//! Unicode normalization for indexed content and queries
+//!
+//! Visually identical text can have different code points: "café" typed as a
+//! precomposed "é" or as "e" + U+0301, fullwidth "ｃａｆｅ" from an IME, etc.
+//! The same normalization runs on both sides (Tantivy analyzer and embedding
+//! input), so these variants end up as the same tokens.
+
+use crate::config::NormalizationConfig;
+use std::borrow::Cow;
+use tantivy::tokenizer::Tokenizer;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+/// Normalize `text` according to `config`, borrowing when nothing changes
+pub fn normalize_text<'a>(text: &'a str, config: &NormalizationConfig) -> Cow<'a, str> {
+    if text.is_ascii() || !(config.nfc || config.fold_width || config.fold_diacritics) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut normalized: Cow<'a, str> = Cow::Borrowed(text);
+
+    if config.fold_width && normalized.chars().any(is_wide) {
+        normalized = Cow::Owned(normalized.chars().map(fold_width).collect());
+    }
+
+    if config.fold_diacritics {
+        let folded: String = normalized.nfd().filter(|c| !is_diacritic(*c)).nfc().collect();
+        normalized = Cow::Owned(folded);
+    } else if config.nfc && !is_nfc(&normalized) {
+        normalized = Cow::Owned(normalized.nfc().collect());
+    }
+
+    normalized
+}
+
+fn is_wide(c: char) -> bool {
+    matches!(c, '\u{FF01}'..='\u{FF5E}' | '\u{3000}')
+}
+
+fn fold_width(c: char) -> char {
+    match c {
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        '\u{3000}' => ' ',
+        _ => c,
+    }
+}
+
+/// Combining diacritical marks only; kana voicing marks are meaningful and kept
+fn is_diacritic(c: char) -> bool {
+    matches!(c, '\u{0300}'..='\u{036F}')
+}
+
+/// Runs `normalize_text` before handing the text to the wrapped tokenizer
+#[derive(Clone)]
+pub struct NormalizingTokenizer<T> {
+    inner: T,
+    config: NormalizationConfig,
+    buffer: String,
+}
+
+impl<T> NormalizingTokenizer<T> {
+    pub fn new(inner: T, config: NormalizationConfig) -> Self {
+        Self {
+            inner,
+            config,
+            buffer: String::new(),
+        }
+    }
+}
+
+impl<T: Tokenizer> Tokenizer for NormalizingTokenizer<T> {
+    type TokenStream<'a> = T::TokenStream<'a>;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        match normalize_text(text, &self.config) {
+            Cow::Borrowed(text) => self.inner.token_stream(text),
+            Cow::Owned(normalized) => {
+                self.buffer = normalized;
+                self.inner.token_stream(&self.buffer)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nfc_and_width_folding() {
+        let config = NormalizationConfig::default();
+        assert_eq!(normalize_text("cafe\u{301}_service", &config), "café_service");
+        assert_eq!(normalize_text("ｃａｆé_ｓｅｒｖｉｃｅ", &config), "café_service");
+        assert_eq!(normalize_text("配置\u{3000}文件", &config), "配置 文件");
+        assert!(matches!(normalize_text("plain_ascii", &config), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_diacritic_folding_keeps_kana() {
+        let config = NormalizationConfig {
+            fold_diacritics: true,
+            ..NormalizationConfig::default()
+        };
+        assert_eq!(normalize_text("café_naïve", &config), "cafe_naive");
+        assert_eq!(normalize_text("データ", &config), "データ");
+    }
+
+    #[test]
+    fn test_disabled_is_identity() {
+        let config = NormalizationConfig::disabled();
+        assert_eq!(normalize_text("cafe\u{301}", &config), "cafe\u{301}");
+    }
+}
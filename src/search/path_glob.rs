@@ -0,0 +1,231 @@
+//! Include and exclude globs over relative paths
+//!
+//! `src/**` keeps the files under `src`, `!**/tests/**` drops those in any
+//! `tests` directory. `*` and `?` stay within one path component, `**` spans
+//! any number of them, `{ts,tsx}` picks alternatives, and a glob without a
+//! `/` matches the file name at any depth, as in `.gitignore`. Each list
+//! compiles to one regex, which filters search results and, run by Tantivy
+//! over the raw path field, the keyword search itself. An extension filter
+//! and a subdirectory narrow the globs the same way, so the keyword search
+//! honors them too.
+
+use crate::{Error, Result};
+use regex::Regex;
+
+/// Globs a search is limited to; empty lists keep every path
+#[derive(Debug, Clone, Default)]
+pub struct PathGlobs {
+    /// Patterns every kept path matches: the include globs, the extensions
+    /// and the subdirectory
+    required: Vec<(String, Regex)>,
+    exclude: Option<(String, Regex)>,
+}
+
+impl PathGlobs {
+    /// Globs of `include` (where a leading `!` excludes instead) and `exclude`
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        let mut includes = Vec::new();
+        let mut excludes = Vec::new();
+        for glob in include.iter().map(|glob| glob.trim()) {
+            match glob.strip_prefix('!') {
+                Some(negated) => excludes.push(negated),
+                None => includes.push(glob),
+            }
+        }
+        excludes.extend(exclude.iter().map(|glob| glob.trim().trim_start_matches('!')));
+        Ok(Self {
+            required: alternation(&includes)?.into_iter().collect(),
+            exclude: alternation(&excludes)?,
+        })
+    }
+
+    /// These globs, also requiring one of `extensions` (e.g. ".rs") unless
+    /// the list is empty
+    pub fn with_extensions(self, extensions: &[String]) -> Self {
+        if extensions.is_empty() {
+            return self;
+        }
+        let escaped: Vec<String> = extensions.iter().map(|ext| regex::escape(ext)).collect();
+        self.requiring(format!(".*(?:{})", escaped.join("|")))
+    }
+
+    /// These globs, also requiring paths under `directory`, a relative path
+    /// without leading or trailing slashes
+    pub fn within(self, directory: &str) -> Self {
+        self.requiring(format!("{}/.*", regex::escape(directory)))
+    }
+
+    fn requiring(mut self, pattern: String) -> Self {
+        let regex = Regex::new(&format!("^{pattern}$")).expect("escaped literals form a valid regex");
+        self.required.push((pattern, regex));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.required.is_empty() && self.exclude.is_none()
+    }
+
+    /// Whether the file at `relative_path` passes the globs
+    pub fn matches(&self, relative_path: &str) -> bool {
+        self.required.iter().all(|(_, regex)| regex.is_match(relative_path))
+            && !self.exclude.as_ref().is_some_and(|(_, regex)| regex.is_match(relative_path))
+    }
+
+    /// Unanchored regexes every kept path matches, for Tantivy's
+    /// `RegexQuery`, which matches whole terms
+    pub fn required_patterns(&self) -> impl Iterator<Item = &str> {
+        self.required.iter().map(|(pattern, _)| pattern.as_str())
+    }
+
+    /// Unanchored regex of the paths to drop
+    pub fn exclude_pattern(&self) -> Option<&str> {
+        self.exclude.as_ref().map(|(pattern, _)| pattern.as_str())
+    }
+}
+
+/// Regex matching any of `globs`, bare and anchored; `None` without globs
+fn alternation(globs: &[&str]) -> Result<Option<(String, Regex)>> {
+    if globs.is_empty() {
+        return Ok(None);
+    }
+    let parts = globs.iter().map(|glob| glob_to_regex(glob)).collect::<Result<Vec<_>>>()?;
+    let pattern = format!("(?:{})", parts.join("|"));
+    let regex = Regex::new(&format!("^{pattern}$"))
+        .map_err(|e| Error::Config(format!("Invalid path globs {}: {e}", globs.join(", "))))?;
+    Ok(Some((pattern, regex)))
+}
+
+fn glob_to_regex(glob: &str) -> Result<String> {
+    let invalid = |reason: &str| Error::Config(format!("Invalid path glob '{glob}': {reason}"));
+    let trimmed = glob.trim_start_matches("./").trim_start_matches('/');
+    if trimmed.is_empty() {
+        return Err(invalid("it is empty"));
+    }
+    // A directory stands for everything under it
+    let expanded = if trimmed.ends_with('/') { format!("{trimmed}**") } else { trimmed.to_string() };
+
+    let chars: Vec<char> = expanded.chars().collect();
+    let mut regex = String::new();
+    if !expanded.contains('/') {
+        regex.push_str("(?:.*/)?");
+    }
+    let mut braces = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let starts_component = i == 0 || chars[i - 1] == '/';
+                i += 2;
+                if starts_component && chars.get(i) == Some(&'/') {
+                    regex.push_str("(?:.*/)?");
+                    i += 1;
+                } else {
+                    regex.push_str(".*");
+                }
+                continue;
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                let mut end = i + 1;
+                let negated = matches!(chars.get(end), Some('!' | '^'));
+                if negated {
+                    end += 1;
+                }
+                // A `]` right after the opening bracket is part of the class
+                if chars.get(end) == Some(&']') {
+                    end += 1;
+                }
+                while end < chars.len() && chars[end] != ']' {
+                    end += 1;
+                }
+                if end >= chars.len() {
+                    return Err(invalid("unclosed '['"));
+                }
+                let start = i + 1 + usize::from(negated);
+                regex.push_str(if negated { "[^/" } else { "[" });
+                for &c in &chars[start..end] {
+                    if matches!(c, '\\' | '[' | '&' | '~' | '^') {
+                        regex.push('\\');
+                    }
+                    regex.push(c);
+                }
+                regex.push(']');
+                i = end;
+            }
+            '{' => {
+                braces += 1;
+                regex.push_str("(?:");
+            }
+            ',' if braces > 0 => regex.push('|'),
+            '}' if braces > 0 => {
+                braces -= 1;
+                regex.push(')');
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    if braces > 0 {
+        return Err(invalid("unclosed '{'"));
+    }
+    Ok(regex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn globs(include: &[&str], exclude: &[&str]) -> PathGlobs {
+        let owned = |globs: &[&str]| globs.iter().map(|glob| glob.to_string()).collect::<Vec<_>>();
+        PathGlobs::new(&owned(include), &owned(exclude)).unwrap()
+    }
+
+    #[test]
+    fn test_globs_match_relative_paths() {
+        let src = globs(&["src/**", "!**/tests/**"], &[]);
+        assert!(src.matches("src/main.rs"));
+        assert!(src.matches("src/search/bm25.rs"));
+        assert!(!src.matches("src/search/tests/fixtures.rs"));
+        assert!(!src.matches("benches/search.rs"));
+
+        let typescript = globs(&["**/*.{ts,tsx}"], &["*.test.ts"]);
+        assert!(typescript.matches("index.ts"));
+        assert!(typescript.matches("web/components/Button.tsx"));
+        assert!(!typescript.matches("web/components/Button.test.ts"));
+        assert!(!typescript.matches("web/main.js"));
+
+        let components = globs(&["src/*/mod.rs", "docs/"], &[]);
+        assert!(components.matches("src/search/mod.rs"));
+        assert!(!components.matches("src/search/nested/mod.rs"));
+        assert!(components.matches("docs/guide/setup.md"));
+
+        let classes = globs(&["v[0-9].rs", "[!_]*.py"], &[]);
+        assert!(classes.matches("api/v2.rs"));
+        assert!(classes.matches("tools/run.py"));
+        assert!(!classes.matches("tools/_private.py"));
+
+        let python_tests = globs(&["tests/**"], &[]).with_extensions(&[".py".to_string(), ".pyi".to_string()]);
+        assert!(python_tests.matches("tests/test_api.py"));
+        assert!(python_tests.matches("tests/stubs.pyi"));
+        assert!(!python_tests.matches("tests/fixtures.json"));
+        assert!(!python_tests.matches("src/api.py"));
+
+        let auth = globs(&["**/*.go"], &[]).within("services/auth");
+        assert!(auth.matches("services/auth/handler.go"));
+        assert!(auth.matches("services/auth/internal/token.go"));
+        assert!(!auth.matches("services/authz/handler.go"));
+        assert!(!auth.matches("services/auth/README.md"));
+
+        assert!(globs(&[], &[]).is_empty());
+        assert!(!globs(&[], &[]).with_extensions(&[".rs".to_string()]).is_empty());
+        assert!(globs(&[], &[]).matches("anything.rs"));
+    }
+
+    #[test]
+    fn test_invalid_globs_are_rejected() {
+        for invalid in ["src/[abc", "*.{ts,tsx", "", "./"] {
+            assert!(PathGlobs::new(&[invalid.to_string()], &[]).is_err(), "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,375 @@
+//! Preprocessing of search queries before Tantivy parses them
+//!
+//! Tantivy's query parser reads `:`, `(`, `{`, `-`, `!` and friends as query
+//! syntax, so a query like `fn load() -> Result<Config>` fails to parse or
+//! means something else. Code-like queries have their syntax neutralized and
+//! lean more on keyword matching, since their exact tokens matter more than
+//! what an embedding makes of them. Prose keeps the syntax it can mean, with
+//! stray punctuation such as an unmatched quote taken out first.
+//!
+//! Quoted phrases (`"buffer pool manager"`) are literal strings the user is
+//! hunting for, in code and prose queries alike: they must match as phrases,
+//! and results are checked to contain them.
+//!
+//! Prose names code differently than code does: "http client" is one token,
+//! `httpclient`, in `HttpClient` or `httpClient`. Queries are expanded with
+//! such spellings before the keyword search, as optional terms.
+
+use super::identifiers::normalize_identifier;
+use super::path_tokens;
+use std::collections::HashSet;
+
+/// Operators that only appear in code
+const CODE_OPERATORS: &[&str] = &["::", "->", "=>", "==", "!=", "&&", "||", "+=", "</", "/>"];
+
+/// Characters that are rare in prose but common in code
+const CODE_PUNCTUATION: &[char] = &['{', '}', ';', '<', '>', '=', '[', ']'];
+
+/// Share of words that must look like identifiers for a query without code
+/// punctuation to count as code
+const IDENTIFIER_DENSITY: f32 = 0.5;
+
+/// Whether a query is mostly code: it contains code operators or punctuation,
+/// or most of its words are snake_case, camelCase or `call()`-shaped
+pub fn is_code_like(query: &str) -> bool {
+    if CODE_OPERATORS.iter().any(|op| query.contains(op)) {
+        return true;
+    }
+    if query.chars().filter(|c| CODE_PUNCTUATION.contains(c)).count() >= 2 {
+        return true;
+    }
+
+    let words: Vec<&str> = query.split_whitespace().collect();
+    if words.is_empty() {
+        return false;
+    }
+    let identifiers = words.iter().filter(|word| looks_like_identifier(word)).count();
+    identifiers as f32 / words.len() as f32 >= IDENTIFIER_DENSITY
+}
+
+fn looks_like_identifier(word: &str) -> bool {
+    let word = word.trim_matches(|c: char| matches!(c, ',' | '?' | '"' | '\'' | '`'));
+    let name = word.trim_end_matches(';').trim_end_matches("()");
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '$')) {
+        return false;
+    }
+
+    let called = word.ends_with("()") || word.ends_with("();");
+    let snake_case = name.trim_matches('_').contains('_');
+    let member_access = name.split('.').filter(|part| !part.is_empty()).count() > 1
+        && !name.ends_with('.');
+    let chars: Vec<char> = name.chars().collect();
+    let camel_case = chars.windows(2).any(|pair| pair[0].is_lowercase() && pair[1].is_uppercase());
+
+    called || snake_case || member_access || camel_case
+}
+
+/// Query text with Tantivy syntax neutralized
+///
+/// Punctuation becomes a space, which the BM25 tokenizers split on anyway, and
+/// the boolean operators are lowercased into plain words.
+pub fn escape_query(query: &str) -> String {
+    let plain: String = query
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' || c.is_whitespace() { c } else { ' ' })
+        .collect();
+
+    plain
+        .split_whitespace()
+        .map(|word| match word {
+            "AND" | "OR" | "NOT" | "IN" => word.to_lowercase(),
+            _ => word.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Operators of the query syntax, which need a term on each side (or, for
+/// `NOT`, after them)
+const OPERATORS: &[&str] = &["AND", "OR", "NOT"];
+
+/// Prose query without the syntax it cannot mean: an unmatched quote,
+/// parentheses that do not balance, slashes (regex delimiters to Tantivy),
+/// `word:` prefixes that name none of `fields`, and operators with nothing
+/// to join, which become plain words
+pub fn sanitize_query(query: &str, fields: &[&str]) -> String {
+    let mut text = query.to_string();
+    if text.matches('"').count() % 2 == 1 {
+        if let Some(last) = text.rfind('"') {
+            text.replace_range(last..last + 1, " ");
+        }
+    }
+
+    let mut depth = 0i32;
+    let balanced = text.chars().all(|c| {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        depth >= 0
+    }) && depth == 0;
+
+    let mut sanitized = String::with_capacity(text.len());
+    for (index, c) in text.char_indices() {
+        let keep = match c {
+            '(' | ')' => balanced,
+            '/' => false,
+            ':' => {
+                let field = text[..index]
+                    .rsplit(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .next()
+                    .unwrap_or("");
+                fields.contains(&field)
+            }
+            _ => true,
+        };
+        sanitized.push(if keep { c } else { ' ' });
+    }
+
+    let words: Vec<&str> = sanitized.split_whitespace().collect();
+    let is_operator = |word: Option<&&str>| word.is_some_and(|word| OPERATORS.contains(word));
+    words
+        .iter()
+        .enumerate()
+        .map(|(index, &word)| {
+            if !OPERATORS.contains(&word) {
+                return word.to_string();
+            }
+            let previous = index.checked_sub(1).and_then(|previous| words.get(previous));
+            let next = words.get(index + 1);
+            let joins = next.is_some()
+                && !(is_operator(next) && next != Some(&"NOT"))
+                && (word == "NOT" || (previous.is_some() && !is_operator(previous)));
+            if joins { word.to_string() } else { word.to_lowercase() }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Most spellings one query is expanded with
+const MAX_EXPANSIONS: usize = 12;
+
+/// Prose words that are not part of a name, so adjacent words joined across
+/// them are not tried
+const CONNECTIVES: &[&str] = &[
+    "a", "an", "the", "is", "are", "was", "be", "it", "this", "that", "of", "to", "in", "on", "at", "for",
+    "by", "from", "with", "and", "or", "not", "how", "what", "where", "when", "why", "which", "who", "does",
+    "do", "can", "get", "i",
+];
+
+/// Spellings code would give the query's words, lowercased as the keyword
+/// index holds them: adjacent prose words joined (`http client` →
+/// `httpclient`, as in `HttpClient`), camelCase words split (`HttpClient` →
+/// `http`, `client`, as in `http_client`) and snake_case words joined
+/// (`retry_policy` → `retrypolicy`, as in `RetryPolicy`). Quoted phrases are
+/// literal and not expanded, and queries with required terms (`AND`, `NOT`,
+/// `+word`, `-word`) get none, since expansions can only add matches.
+pub fn identifier_expansions(query: &str) -> Vec<String> {
+    let text = unquoted(query);
+    let raw: Vec<&str> = text.split_whitespace().collect();
+    if raw.iter().any(|word| OPERATORS.contains(word) || word.starts_with(['+', '-'])) {
+        return Vec::new();
+    }
+    let words: Vec<&str> = raw
+        .iter()
+        .map(|word| word.trim_matches(|c: char| !(c.is_alphanumeric() || c == '_')))
+        .filter(|word| !word.is_empty())
+        .collect();
+    let own: HashSet<String> = words.iter().map(|word| word.to_lowercase()).collect();
+    let mut expansions: Vec<String> = Vec::new();
+    let mut add = |term: String| {
+        if term.chars().count() >= 2 && !own.contains(&term) && !expansions.contains(&term) {
+            expansions.push(term);
+        }
+    };
+
+    for word in words.iter().filter(|word| word.chars().all(|c| c.is_alphanumeric() || c == '_')) {
+        if word.trim_matches('_').contains('_') {
+            // The tokenizer splits snake_case on its own
+            add(normalize_identifier(word));
+            continue;
+        }
+        let parts = path_tokens(word);
+        if parts.split_whitespace().count() > 1 {
+            parts.split_whitespace().for_each(|part| add(part.to_string()));
+        }
+    }
+    // Code names are not written as prose, so only prose is joined
+    if !is_code_like(query) {
+        let joinable = |word: &&str| {
+            word.chars().all(char::is_alphanumeric) && !CONNECTIVES.contains(&word.to_lowercase().as_str())
+        };
+        for size in 2..=3 {
+            for window in words.windows(size).filter(|window| window.iter().all(joinable)) {
+                add(window.concat().to_lowercase());
+            }
+        }
+    }
+    expansions.truncate(MAX_EXPANSIONS);
+    expansions
+}
+
+/// Names in `query` worth matching as written against the identifiers of
+/// chunks, each with whether a mere use of it counts or only a definition:
+/// snake_case and camelCase names and names with digits rarely come from
+/// prose, and neither does any name of a code query. Plain lowercase words
+/// of a prose query are taken as prose.
+pub fn exact_names(query: &str) -> Vec<(String, bool)> {
+    let code_like = is_code_like(query);
+    let mut names: Vec<(String, bool)> = Vec::new();
+    for token in query.split(|c: char| !(c.is_alphanumeric() || c == '_')) {
+        let starts_like_name = token.starts_with(|c: char| c.is_alphabetic() || c == '_');
+        if token.chars().count() < 3 || !starts_like_name || names.iter().any(|(name, _)| name == token) {
+            continue;
+        }
+        let chars: Vec<char> = token.chars().collect();
+        if !code_like && chars.iter().all(|c| c.is_lowercase()) {
+            continue;
+        }
+        let compound = token.trim_matches('_').contains('_')
+            || chars.windows(2).any(|pair| pair[0].is_lowercase() && pair[1].is_uppercase())
+            || chars.iter().any(char::is_ascii_digit);
+        names.push((token.to_string(), code_like || compound));
+    }
+    names
+}
+
+/// `query` without its quoted parts
+fn unquoted(query: &str) -> String {
+    let mut rest = query.to_string();
+    for part in quoted_parts(query) {
+        rest = rest.replacen(&format!("\"{part}\""), " ", 1);
+    }
+    rest
+}
+
+/// Phrases between matched double quotes, with whitespace runs collapsed;
+/// an unmatched last quote starts none
+pub fn quoted_phrases(query: &str) -> Vec<String> {
+    quoted_parts(query)
+        .into_iter()
+        .map(|phrase| phrase.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|phrase| !phrase.is_empty())
+        .collect()
+}
+
+/// Text between matched double quotes, as written
+fn quoted_parts(query: &str) -> Vec<&str> {
+    let parts: Vec<&str> = query.split('"').collect();
+    // Odd parts are quoted, except a last one left open by an unmatched quote
+    let quoted = if parts.len() % 2 == 0 { parts.len() - 2 } else { parts.len() };
+    parts[..quoted].iter().skip(1).step_by(2).copied().collect()
+}
+
+/// Query requiring each quoted phrase as a phrase, with the rest of the
+/// words optional; `None` when the query quotes nothing
+pub fn phrase_query(query: &str) -> Option<String> {
+    let phrases = quoted_phrases(query);
+    if phrases.is_empty() {
+        return None;
+    }
+    let rest = unquoted(query);
+    let mut clauses: Vec<String> = phrases
+        .iter()
+        .map(|phrase| escape_query(phrase))
+        .filter(|phrase| !phrase.is_empty())
+        .map(|phrase| format!("+\"{phrase}\""))
+        .collect();
+    clauses.push(escape_query(&rest));
+    Some(clauses.join(" ").trim().to_string())
+}
+
+/// Whether `content` contains `phrase`, ignoring case and how whitespace is
+/// laid out; punctuation, such as the whole of `->`, must match as written
+pub fn contains_phrase(content: &str, phrase: &str) -> bool {
+    let collapse = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    collapse(content).contains(&collapse(phrase))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_code_like() {
+        for query in [
+            "fn load() -> Result<Config>",
+            "std::fs::read_to_string",
+            "if (user == null) { return; }",
+            "parse_config load_settings",
+            "getUserById",
+            "self.embedding.dimension()",
+        ] {
+            assert!(is_code_like(query), "{query}");
+        }
+
+        for query in [
+            "how are embeddings cached",
+            "where is the config file parsed?",
+            "what does parse_config do when the file is missing",
+            "",
+        ] {
+            assert!(!is_code_like(query), "{query}");
+        }
+    }
+
+    #[test]
+    fn test_escape_query() {
+        assert_eq!(escape_query("fn load() -> Result<Config, Error>"), "fn load Result Config Error");
+        assert_eq!(escape_query("path:src/main.rs AND -test"), "path src main rs and test");
+        assert_eq!(escape_query("{}"), "");
+    }
+
+    #[test]
+    fn test_sanitize_query() {
+        let fields = ["content", "path"];
+        assert_eq!(sanitize_query("error: \"unterminated string", &fields), "error unterminated string");
+        assert_eq!(sanitize_query("\"buffer pool\" manager", &fields), "\"buffer pool\" manager");
+        assert_eq!(sanitize_query("retry (backoff", &fields), "retry backoff");
+        assert_eq!(sanitize_query("(retry OR backoff) path:src", &fields), "(retry OR backoff) path:src");
+        assert_eq!(sanitize_query("read and/or write", &fields), "read and or write");
+        assert_eq!(sanitize_query("AND cache OR", &fields), "and cache or");
+        assert_eq!(sanitize_query("cache AND NOT disk", &fields), "cache AND NOT disk");
+        assert_eq!(sanitize_query("cache AND OR disk", &fields), "cache and or disk");
+    }
+
+    #[test]
+    fn test_quoted_phrases() {
+        assert_eq!(quoted_phrases("\"buffer  pool\nmanager\" eviction"), ["buffer pool manager"]);
+        assert_eq!(quoted_phrases("\"a b\" or \"c d\" \"open"), ["a b", "c d"]);
+        assert!(quoted_phrases("no quotes \"\" here").is_empty());
+
+        assert_eq!(phrase_query("\"buffer pool manager\" eviction"), Some("+\"buffer pool manager\" eviction".to_string()));
+        assert_eq!(phrase_query("\"Vec::new()\" AND \"x\""), Some("+\"Vec new\" +\"x\" and".to_string()));
+        assert_eq!(phrase_query("plain words"), None);
+
+        assert!(contains_phrase("// The Buffer pool\n    manager evicts pages", "buffer pool manager"));
+        assert!(!contains_phrase("buffer manager pool", "buffer pool manager"));
+        assert!(!contains_phrase("// buffer-pool manager", "buffer pool manager"));
+        assert!(contains_phrase("fn load() -> Config", "->"));
+        assert!(!contains_phrase("fn load() { config }", "->"));
+    }
+
+    #[test]
+    fn test_exact_names() {
+        assert_eq!(exact_names("where is the new RateLimiter used"), [("RateLimiter".to_string(), true)]);
+        assert!(exact_names("where is the config loaded").is_empty());
+        assert_eq!(exact_names("where is Config loaded"), [("Config".to_string(), false)]);
+        assert_eq!(exact_names("retry_policy v2"), [("retry_policy".to_string(), true)]);
+        assert_eq!(
+            exact_names("Config::load()"),
+            [("Config".to_string(), true), ("load".to_string(), true)]
+        );
+    }
+
+    #[test]
+    fn test_identifier_expansions() {
+        assert_eq!(identifier_expansions("http client retry"), ["httpclient", "clientretry", "httpclientretry"]);
+        assert_eq!(identifier_expansions("where does HttpClient retry?"), ["http", "client", "httpclientretry"]);
+        assert_eq!(identifier_expansions("retry_policy"), ["retrypolicy"]);
+        assert_eq!(identifier_expansions("\"http client\" config loader"), ["configloader"]);
+        assert!(identifier_expansions("cache AND disk").is_empty());
+        assert!(identifier_expansions("how is it cached").is_empty());
+    }
+}
@@ -1,8 +1,8 @@
 
-use crate::{Result, IndexingStatus, IndexStats};
+use crate::{Result, IndexingStatus, IndexStats, SplitterStats};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use chrono::Utc;
 
 /// Codebase snapshot (v2 format)
@@ -29,6 +29,8 @@ pub enum CodebaseInfo {
         total_chunks: usize,
         #[serde(rename = "indexStatus")]
         index_status: String,
+        #[serde(rename = "languageStats", default, skip_serializing_if = "BTreeMap::is_empty")]
+        language_stats: BTreeMap<String, SplitterStats>,
         #[serde(rename = "lastUpdated")]
         last_updated: String,
     },
@@ -63,6 +65,7 @@ pub struct IndexedStatusInfo {
     pub indexed_files: usize,
     pub total_chunks: usize,
     pub index_status: String,
+    pub language_stats: BTreeMap<String, SplitterStats>,
     pub last_updated: u64,
 }
 
@@ -151,6 +154,7 @@ impl SnapshotManager {
             indexed_files: stats.indexed_files,
             total_chunks: stats.total_chunks,
             index_status: stats.index_status,
+            language_stats: stats.language_stats,
             last_updated: Utc::now().to_rfc3339(),
         };
         self.codebases.insert(key, info);
@@ -184,6 +188,15 @@ impl SnapshotManager {
         matches!(self.codebases.get(&key), Some(CodebaseInfo::Indexed { .. }))
     }
     
+    /// Stored entry of a codebase, e.g. to keep it with a deleted index
+    pub fn info(&self, path: &Path) -> Option<CodebaseInfo> {
+        self.codebases.get(&path.to_string_lossy().to_string()).cloned()
+    }
+    
+    pub fn set_info(&mut self, path: &Path, info: CodebaseInfo) {
+        self.codebases.insert(path.to_string_lossy().to_string(), info);
+    }
+    
     pub fn remove_codebase(&mut self, path: &Path) -> Result<()> {
         self.remove(path)
     }
@@ -214,6 +227,33 @@ impl SnapshotManager {
             .collect()
     }
     
+    /// Every known codebase regardless of status, sorted by path
+    pub fn get_codebases(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self.codebases.keys().map(PathBuf::from).collect();
+        paths.sort();
+        paths
+    }
+    
+    /// Codebases in the indexing state whose progress was last updated more
+    /// than `max_age` ago, with that progress; after a restart these belong
+    /// to a run that no longer exists
+    pub fn stale_indexing_codebases(&self, max_age: std::time::Duration) -> Vec<(PathBuf, u8)> {
+        let now = Utc::now().timestamp().max(0) as u64;
+        let mut stale: Vec<(PathBuf, u8)> = self
+            .codebases
+            .iter()
+            .filter_map(|(path, info)| match info {
+                CodebaseInfo::Indexing { indexing_percentage, last_updated } => {
+                    let age = now.saturating_sub(parse_timestamp(last_updated));
+                    (age >= max_age.as_secs()).then(|| (PathBuf::from(path), *indexing_percentage))
+                }
+                _ => None,
+            })
+            .collect();
+        stale.sort();
+        stale
+    }
+    
     pub fn get_indexing_progress(&self, path: &Path) -> u8 {
         let key = path.to_string_lossy().to_string();
         if let Some(CodebaseInfo::Indexing { indexing_percentage, .. }) = self.codebases.get(&key) {
@@ -231,12 +271,14 @@ impl SnapshotManager {
                 indexed_files,
                 total_chunks,
                 index_status,
+                language_stats,
                 last_updated,
             }) => {
                 CodebaseStatus::Indexed(IndexedStatusInfo {
                     indexed_files: *indexed_files,
                     total_chunks: *total_chunks,
                     index_status: index_status.clone(),
+                    language_stats: language_stats.clone(),
                     last_updated: parse_timestamp(last_updated),
                 })
             }
@@ -311,6 +353,7 @@ mod tests {
             total_chunks: 500,
             elapsed_secs: 10.5,
             index_status: "completed".to_string(),
+            language_stats: BTreeMap::new(),
         };
         manager.set_indexed(&test_path, stats).unwrap();
         manager.save().unwrap();
@@ -325,4 +368,23 @@ mod tests {
         assert!(json.contains("\"indexedFiles\"") && json.contains("100"));
         assert!(json.contains("\"totalChunks\"") && json.contains("500"));
     }
+    
+    #[test]
+    fn test_stale_indexing_codebases() {
+        let dir = tempdir().unwrap();
+        let mut manager = SnapshotManager::new(dir.path().join("snapshot.json")).unwrap();
+        let (crashed, running) = (dir.path().join("crashed"), dir.path().join("running"));
+        
+        manager.set_indexing(&running, 40).unwrap();
+        manager.codebases.insert(
+            crashed.to_string_lossy().to_string(),
+            CodebaseInfo::Indexing {
+                indexing_percentage: 70,
+                last_updated: (Utc::now() - chrono::Duration::minutes(10)).to_rfc3339(),
+            },
+        );
+        
+        let stale = manager.stale_indexing_codebases(std::time::Duration::from_secs(120));
+        assert_eq!(stale, vec![(crashed, 70)]);
+    }
 }
@@ -0,0 +1,137 @@
+//! Gitignore-style matching for user-supplied ignore patterns
+//!
+//! Patterns from `IGNORE_PATTERNS` and the `ignore_patterns` tool argument are
+//! read like the lines of a `.gitignore` at the codebase root:
+//!
+//! - `!pattern` re-includes something an earlier pattern excluded
+//! - a leading or inner `/` anchors the pattern to the root (`/build`, `src/gen/`)
+//! - a trailing `/` matches directories only
+//! - as in git, nothing below an excluded directory can be re-included
+//!
+//! Scanning and change detection walk the tree with the same builder, so both
+//! also honor nested `.gitignore` / `.ignore` files.
+
+use crate::{Error, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{DirEntry, WalkBuilder};
+use std::path::Path;
+use tracing::warn;
+
+pub struct IgnoreRules {
+    matcher: Gitignore,
+}
+
+impl IgnoreRules {
+    /// Build rules for `root`; invalid patterns are skipped with a warning
+    pub fn new(root: &Path, patterns: &[String]) -> Self {
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in patterns {
+            if let Err(e) = builder.add_line(None, pattern) {
+                warn!("[IGNORE] Skipping invalid ignore pattern '{}': {}", pattern, e);
+            }
+        }
+
+        let matcher = builder.build().unwrap_or_else(|e| {
+            warn!("[IGNORE] Failed to build ignore rules: {}", e);
+            Gitignore::empty()
+        });
+
+        Self { matcher }
+    }
+
+    /// Reject patterns that are not valid gitignore globs
+    pub fn validate(patterns: &[String]) -> Result<()> {
+        let mut builder = GitignoreBuilder::new("");
+        for pattern in patterns {
+            builder
+                .add_line(None, pattern)
+                .map_err(|e| Error::Config(format!("Invalid ignore pattern '{pattern}': {e}")))?;
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matcher.is_empty()
+    }
+
+    /// Whether `path` (relative to the root, or absolute below it) is excluded
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let parent_ignored = path
+            .ancestors()
+            .skip(1)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .any(|dir| self.matcher.matched(dir, true).is_ignore());
+
+        parent_ignored || self.matcher.matched(path, is_dir).is_ignore()
+    }
+
+    /// Walk filter; parents were already checked when the walker descended into them
+    pub fn allows(&self, entry: &DirEntry) -> bool {
+        if entry.depth() == 0 {
+            return true;
+        }
+        let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+        !self.matcher.matched(entry.path(), is_dir).is_ignore()
+    }
+}
+
+/// Walker over `root` honoring .gitignore, global gitignore, .git/info/exclude
+/// and .ignore files; callers add their own `filter_entry` for `IgnoreRules`
+pub fn walk_builder(root: &Path) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .follow_links(false)
+        .git_ignore(true)
+        .git_global(true)
+        .git_exclude(true)
+        .ignore(true)
+        // Hidden files are indexed; callers decide whether to skip them
+        .hidden(false);
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(patterns: &[&str]) -> IgnoreRules {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        IgnoreRules::new(Path::new("/repo"), &patterns)
+    }
+
+    #[test]
+    fn test_globs() {
+        let rules = rules(&["*.js", "*.min.css", "node_modules"]);
+
+        assert!(rules.is_ignored(Path::new("test.js"), false));
+        assert!(rules.is_ignored(Path::new("web/site.min.css"), false));
+        assert!(!rules.is_ignored(Path::new("test.ts"), false));
+        assert!(rules.is_ignored(Path::new("node_modules"), true));
+        assert!(rules.is_ignored(Path::new("src/node_modules/pkg/index.ts"), false));
+    }
+
+    #[test]
+    fn test_negation_and_anchoring() {
+        let rules = rules(&["*.log", "!keep.log", "/build", "docs/", "gen/", "!gen/keep.rs"]);
+
+        assert!(rules.is_ignored(Path::new("logs/app.log"), false));
+        assert!(!rules.is_ignored(Path::new("logs/keep.log"), false));
+
+        // Anchored to the root
+        assert!(rules.is_ignored(Path::new("build/out.rs"), false));
+        assert!(!rules.is_ignored(Path::new("src/build/mod.rs"), false));
+
+        // Directory-only pattern
+        assert!(rules.is_ignored(Path::new("src/docs/intro.md"), false));
+        assert!(!rules.is_ignored(Path::new("docs"), false));
+
+        // Nothing below an excluded directory can be re-included
+        assert!(rules.is_ignored(Path::new("gen/keep.rs"), false));
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_glob() {
+        assert!(IgnoreRules::validate(&["src/**/*.rs".to_string()]).is_ok());
+        assert!(IgnoreRules::validate(&["src/[".to_string()]).is_err());
+    }
+}
@@ -1,6 +1,9 @@
 
+pub mod ignore_rules;
 pub mod merkle;
+pub mod roots;
 pub mod synchronizer;
 
+pub use ignore_rules::IgnoreRules;
 pub use merkle::MerkleDAG;
-pub use synchronizer::{FileSynchronizer, FileChanges};
+pub use synchronizer::{FileSynchronizer, FileChanges, content_hash};
@@ -0,0 +1,152 @@
+//! Additional source roots indexed together with a codebase
+//!
+//! A codebase can span directories that are checked out side by side
+//! (`backend/` next to `shared-lib/`). The first path stays the index key;
+//! files of the other roots are addressed relative to it, so
+//! `/work/shared-lib/src/lib.rs` becomes `../shared-lib/src/lib.rs` in a
+//! codebase rooted at `/work/backend`. Joining such a relative path onto the
+//! codebase root yields a readable path again, which keeps scanning, change
+//! detection and incremental updates unaware of the extra roots.
+//!
+//! The prefixes are joined onto the codebase root as it was given, which may
+//! lead through a symlink, while `..` after a symlink leads to the parent of
+//! its target. A prefix therefore has to reach the same directory whether
+//! `..` is resolved through the links or lexically; when the plain relative
+//! path does not, it climbs to the filesystem root first.
+
+use crate::{Error, Result};
+use std::path::{Component, Path, PathBuf};
+
+/// Path of each additional root relative to `codebase_root`; roots are
+/// compared by their canonical paths
+pub fn root_prefixes(codebase_root: &Path, additional_roots: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let base = canonical(codebase_root)?;
+    let mut seen = vec![base.clone()];
+    let mut prefixes = Vec::with_capacity(additional_roots.len());
+
+    for root in additional_roots {
+        let dir = canonical(root)?;
+        if let Some(existing) = seen.iter().find(|other| dir.starts_with(other) || other.starts_with(&dir)) {
+            return Err(Error::InvalidPath(format!(
+                "Additional root '{}' overlaps with '{}'; roots must be separate directories",
+                root.display(),
+                existing.display()
+            )));
+        }
+        let prefix = relative_path(&lexical(codebase_root), &lexical(root));
+        let resolves = codebase_root.join(&prefix).canonicalize().is_ok_and(|resolved| resolved == dir);
+        prefixes.push(if resolves { prefix } else { climb_to(codebase_root, &base, &dir) });
+        seen.push(dir);
+    }
+
+    Ok(prefixes)
+}
+
+fn canonical(path: &Path) -> Result<PathBuf> {
+    path.canonicalize()
+        .map_err(|e| Error::InvalidPath(format!("Cannot resolve '{}': {}", path.display(), e)))
+}
+
+/// `path` with `.` dropped and `..` applied to the component before it
+fn lexical(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// Path leading from `root`, whose canonical path is `base`, to the
+/// canonical directory `dir` by way of the filesystem root: as many `..` as
+/// the deeper of `root` and `base` has components end there whichever of
+/// the two they are resolved against
+fn climb_to(root: &Path, base: &Path, dir: &Path) -> PathBuf {
+    let depth = |path: &Path| {
+        lexical(path)
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_)))
+            .count()
+    };
+    let mut path = PathBuf::new();
+    for _ in 0..depth(root).max(depth(base)) {
+        path.push("..");
+    }
+    for component in dir.components().filter(|component| matches!(component, Component::Normal(_))) {
+        path.push(component.as_os_str());
+    }
+    path
+}
+
+/// Path leading from directory `from` to `to`, both absolute
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from: Vec<Component> = from.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut path = PathBuf::new();
+    for _ in common..from.len() {
+        path.push("..");
+    }
+    for component in &to[common..] {
+        path.push(component.as_os_str());
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path(Path::new("/work/backend"), Path::new("/work/shared-lib")),
+            PathBuf::from("../shared-lib")
+        );
+        assert_eq!(
+            relative_path(Path::new("/work/apps/api"), Path::new("/libs/common")),
+            PathBuf::from("../../../libs/common")
+        );
+    }
+
+    #[test]
+    fn test_root_prefixes_rejects_overlaps() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = dir.path().join("backend");
+        let shared = dir.path().join("shared-lib");
+        std::fs::create_dir_all(backend.join("src")).unwrap();
+        std::fs::create_dir_all(&shared).unwrap();
+
+        let prefixes = root_prefixes(&backend, &[shared.clone()]).unwrap();
+        assert_eq!(prefixes, vec![PathBuf::from("../shared-lib")]);
+
+        assert!(root_prefixes(&backend, &[backend.join("src")]).is_err());
+        assert!(root_prefixes(&backend, &[dir.path().to_path_buf()]).is_err());
+        assert!(root_prefixes(&backend, &[shared.clone(), shared]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_prefix_of_symlinked_checkout_resolves_both_ways() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("disk/checkouts/backend");
+        let shared = dir.path().join("work/shared-lib");
+        std::fs::create_dir_all(&target).unwrap();
+        std::fs::create_dir_all(&shared).unwrap();
+        // `work/backend` is a link into another tree, next to a real `work/shared-lib`
+        let backend = dir.path().join("work/backend");
+        std::os::unix::fs::symlink(&target, &backend).unwrap();
+
+        let prefixes = root_prefixes(&backend, &[shared.clone()]).unwrap();
+        let joined = backend.join(&prefixes[0]);
+        let shared = shared.canonicalize().unwrap();
+        assert_eq!(joined.canonicalize().unwrap(), shared);
+        assert_eq!(lexical(&joined), shared);
+    }
+}
@@ -1,15 +1,16 @@
 
+use super::ignore_rules::{IgnoreRules, walk_builder};
 use super::merkle::MerkleDAG;
 use crate::error::{Error, Result};
+use crate::integrity;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::fs;
 use tracing::{info, warn};
 
-type FileHashesFuture<'a> = std::pin::Pin<Box<dyn std::future::Future<Output = Result<HashMap<String, String>>> + Send + 'a>>;
-
 #[derive(Debug, Clone)]
 pub struct FileChanges {
     pub added: Vec<String>,
@@ -17,34 +18,59 @@ pub struct FileChanges {
     pub modified: Vec<String>,
 }
 
+/// Format version of the snapshot files
+const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
 #[derive(Debug, Serialize, Deserialize)]
 struct SnapshotData {
     file_hashes: HashMap<String, String>,
     merkle_dag: MerkleDAG,
+    /// Bumped each time changes are detected; 0 for snapshots written before it existed
+    #[serde(default)]
+    generation: u64,
+}
+
+/// Hash of a file's content as recorded in the snapshot
+pub fn content_hash(content: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content);
+    format!("{:x}", hasher.finalize())
 }
 
 pub struct FileSynchronizer {
     file_hashes: HashMap<String, String>,
     merkle_dag: MerkleDAG,
+    generation: u64,
     root_dir: PathBuf,
     snapshot_path: PathBuf,
     ignore_patterns: Vec<String>,
+    ignore_rules: Arc<IgnoreRules>,
+    /// Additional roots, relative to `root_dir` (see `roots`)
+    root_prefixes: Vec<PathBuf>,
+    /// State found by the last `check_for_changes`, saved by `commit_changes`
+    /// once the stores hold those changes
+    pending: Option<SnapshotData>,
 }
 
 impl FileSynchronizer {
     pub fn new(root_dir: PathBuf, data_dir: PathBuf, ignore_patterns: Vec<String>) -> Self {
         let snapshot_path = Self::get_snapshot_path(&root_dir, &data_dir);
+        let ignore_rules = Arc::new(IgnoreRules::new(&root_dir, &ignore_patterns));
         
         Self {
             file_hashes: HashMap::new(),
             merkle_dag: MerkleDAG::new(),
+            generation: 0,
             root_dir,
             snapshot_path,
             ignore_patterns,
+            ignore_rules,
+            root_prefixes: Vec::new(),
+            pending: None,
         }
     }
 
-    fn get_snapshot_path(codebase_path: &Path, data_dir: &Path) -> PathBuf {
+    pub(crate) fn get_snapshot_path(codebase_path: &Path, data_dir: &Path) -> PathBuf {
         let merkle_dir = data_dir.join("merkle");
         
         let normalized_path = codebase_path.canonicalize()
@@ -65,150 +91,83 @@ impl FileSynchronizer {
         }
         
         let content = fs::read(file_path).await?;
-        let mut hasher = Sha256::new();
-        hasher.update(&content);
-        Ok(format!("{:x}", hasher.finalize()))
-    }
-
-    fn generate_file_hashes<'a>(&'a self, dir: &'a Path) -> FileHashesFuture<'a> {
-        Box::pin(async move {
-            let mut file_hashes = HashMap::new();
-            
-            let mut entries = match fs::read_dir(dir).await {
-                Ok(entries) => entries,
-                Err(e) => {
-                    warn!("[Synchronizer] Cannot read directory {}: {}", dir.display(), e);
-                    return Ok(file_hashes);
-                }
-            };
-
-            while let Some(entry) = entries.next_entry().await? {
-                let full_path = entry.path();
-                let relative_path = full_path.strip_prefix(&self.root_dir)
-                    .unwrap_or(&full_path)
-                    .to_string_lossy()
-                    .to_string();
-
-                // Check if this path should be ignored
-                let metadata = match fs::metadata(&full_path).await {
-                    Ok(m) => m,
-                    Err(e) => {
-                        warn!("[Synchronizer] Cannot stat {}: {}", full_path.display(), e);
-                        continue;
-                    }
-                };
-
-                if self.should_ignore(&relative_path, metadata.is_dir()) {
-                    continue;
-                }
-
-                if metadata.is_dir() {
-                    let sub_hashes = self.generate_file_hashes(&full_path).await?;
-                    file_hashes.extend(sub_hashes);
-                } else if metadata.is_file() {
-                    match Self::hash_file(&full_path).await {
-                        Ok(hash) => {
-                            file_hashes.insert(relative_path, hash);
-                        }
-                        Err(e) => {
-                            warn!("[Synchronizer] Cannot hash file {}: {}", full_path.display(), e);
-                            continue;
-                        }
-                    }
-                }
-            }
-
-            Ok(file_hashes)
-        })
+        Ok(content_hash(&content))
     }
 
-    fn should_ignore(&self, relative_path: &str, is_directory: bool) -> bool {
-        let path_parts: Vec<&str> = relative_path.split(std::path::MAIN_SEPARATOR).collect();
-        if path_parts.iter().any(|part| part.starts_with('.')) {
-            return true;
-        }
+    async fn generate_file_hashes(&self) -> Result<HashMap<String, String>> {
+        let mut file_hashes = HashMap::new();
 
-        if self.ignore_patterns.is_empty() {
-            return false;
+        self.hash_dir(&self.root_dir, Arc::clone(&self.ignore_rules), &mut file_hashes).await;
+        for prefix in &self.root_prefixes {
+            // Patterns are anchored at each root, like a .gitignore at its top
+            let dir = self.root_dir.join(prefix);
+            let rules = Arc::new(IgnoreRules::new(&dir, &self.ignore_patterns));
+            self.hash_dir(&dir, rules, &mut file_hashes).await;
         }
 
-        let normalized_path = relative_path.replace('\\', "/");
-        let normalized_path = normalized_path.trim_matches('/');
+        Ok(file_hashes)
+    }
 
-        if normalized_path.is_empty() {
-            return false;
-        }
+    /// Hash the files below `dir`, keyed by their path relative to `root_dir`
+    async fn hash_dir(&self, dir: &Path, rules: Arc<IgnoreRules>, file_hashes: &mut HashMap<String, String>) {
+        let mut builder = walk_builder(dir);
+        builder.filter_entry(move |entry| {
+            let hidden = entry.depth() > 0 && entry.file_name().to_string_lossy().starts_with('.');
+            !hidden && rules.allows(entry)
+        });
+
+        for entry in builder.build() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warn!("[Synchronizer] Cannot read entry: {}", e);
+                    continue;
+                }
+            };
 
-        for pattern in &self.ignore_patterns {
-            if self.match_pattern(normalized_path, pattern, is_directory) {
-                return true;
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
             }
-        }
 
-        let normalized_path_parts: Vec<&str> = normalized_path.split('/').collect();
-        for i in 0..normalized_path_parts.len() {
-            let partial_path = normalized_path_parts[..=i].join("/");
-            for pattern in &self.ignore_patterns {
-                if pattern.ends_with('/') {
-                    let dir_pattern = &pattern[..pattern.len() - 1];
-                    if self.simple_glob_match(&partial_path, dir_pattern)
-                        || self.simple_glob_match(normalized_path_parts[i], dir_pattern)
-                    {
-                        return true;
-                    }
-                }
-                else if pattern.contains('/') {
-                    if self.simple_glob_match(&partial_path, pattern) {
-                        return true;
-                    }
+            let full_path = entry.path();
+            let relative_path = full_path.strip_prefix(&self.root_dir)
+                .unwrap_or(full_path)
+                .to_string_lossy()
+                .to_string();
+
+            match Self::hash_file(full_path).await {
+                Ok(hash) => {
+                    file_hashes.insert(relative_path, hash);
                 }
-                else if self.simple_glob_match(normalized_path_parts[i], pattern) {
-                    return true;
+                Err(e) => {
+                    warn!("[Synchronizer] Cannot hash file {}: {}", full_path.display(), e);
                 }
             }
         }
-
-        false
     }
 
-    fn match_pattern(&self, file_path: &str, pattern: &str, is_directory: bool) -> bool {
-        let clean_path = file_path.trim_matches('/');
-        let clean_pattern = pattern.trim_matches('/');
-
-        if clean_path.is_empty() || clean_pattern.is_empty() {
-            return false;
-        }
-
-        if pattern.ends_with('/') {
-            if !is_directory {
-                return false;
-            }
-            let dir_pattern = &clean_pattern[..clean_pattern.len() - 1];
-
-            return self.simple_glob_match(clean_path, dir_pattern)
-                || clean_path.split('/').any(|part| self.simple_glob_match(part, dir_pattern));
-        }
-
-        if clean_pattern.contains('/') {
-            return self.simple_glob_match(clean_path, clean_pattern);
-        }
+    /// Whether change detection skips `relative_path`: hidden entries, custom
+    /// ignore patterns, or anything below a directory they exclude
+    pub fn should_ignore(&self, relative_path: &str, is_directory: bool) -> bool {
+        let hidden = relative_path
+            .split(['/', '\\'])
+            .any(|part| part.starts_with('.'));
 
-        let file_name = clean_path.split('/').next_back().unwrap_or(clean_path);
-        self.simple_glob_match(file_name, clean_pattern)
+        hidden || self.ignore_rules.is_ignored(Path::new(relative_path), is_directory)
     }
 
-    fn simple_glob_match(&self, text: &str, pattern: &str) -> bool {
-        if text.is_empty() || pattern.is_empty() {
-            return false;
+    /// Replace the custom ignore patterns, e.g. when a re-index passes new ones
+    pub fn set_ignore_patterns(&mut self, ignore_patterns: Vec<String>) {
+        if ignore_patterns != self.ignore_patterns {
+            self.ignore_rules = Arc::new(IgnoreRules::new(&self.root_dir, &ignore_patterns));
+            self.ignore_patterns = ignore_patterns;
         }
+    }
 
-        let regex_pattern = regex::escape(pattern).replace("\\*", ".*");
-        let regex = match regex::Regex::new(&format!("^{regex_pattern}$")) {
-            Ok(r) => r,
-            Err(_) => return false,
-        };
-
-        regex.is_match(text)
+    /// Replace the additional roots; files of added roots show up as added
+    /// on the next check, files of dropped ones as removed
+    pub fn set_root_prefixes(&mut self, root_prefixes: Vec<PathBuf>) {
+        self.root_prefixes = root_prefixes;
     }
 
     fn build_merkle_dag(file_hashes: &HashMap<String, String>) -> MerkleDAG {
@@ -241,11 +200,15 @@ impl FileSynchronizer {
         Ok(())
     }
 
+    /// Changes since the last committed snapshot. The new state is only kept
+    /// in memory until `commit_changes`, so a run interrupted before its
+    /// changes are stored finds them again.
     pub async fn check_for_changes(&mut self) -> Result<FileChanges> {
         info!("[Synchronizer] Checking for file changes...");
+        self.pending = None;
 
         // Generate new file hashes
-        let new_file_hashes = self.generate_file_hashes(&self.root_dir).await?;
+        let new_file_hashes = self.generate_file_hashes().await?;
         let new_merkle_dag = Self::build_merkle_dag(&new_file_hashes);
 
         let dag_changes = MerkleDAG::compare(&self.merkle_dag, &new_merkle_dag);
@@ -256,9 +219,11 @@ impl FileSynchronizer {
         {
             let file_changes = self.compare_states(&self.file_hashes, &new_file_hashes);
 
-            self.file_hashes = new_file_hashes;
-            self.merkle_dag = new_merkle_dag;
-            self.save_snapshot().await?;
+            self.pending = Some(SnapshotData {
+                file_hashes: new_file_hashes,
+                merkle_dag: new_merkle_dag,
+                generation: self.generation + 1,
+            });
 
             info!(
                 "[Synchronizer] Found changes: {} added, {} removed, {} modified",
@@ -277,6 +242,25 @@ impl FileSynchronizer {
         })
     }
 
+    /// Record the state found by the last `check_for_changes` as the snapshot
+    /// later checks compare against
+    pub async fn commit_changes(&mut self) -> Result<()> {
+        let Some(pending) = self.pending.take() else {
+            return Ok(());
+        };
+        self.file_hashes = pending.file_hashes;
+        self.merkle_dag = pending.merkle_dag;
+        self.generation = pending.generation;
+        self.save_snapshot().await
+    }
+
+    /// Changes since the last snapshot, without recording them, so the next
+    /// `check_for_changes` still reports them
+    pub async fn pending_changes(&self) -> Result<FileChanges> {
+        let new_file_hashes = self.generate_file_hashes().await?;
+        Ok(self.compare_states(&self.file_hashes, &new_file_hashes))
+    }
+
     fn compare_states(
         &self,
         old_hashes: &HashMap<String, String>,
@@ -311,38 +295,56 @@ impl FileSynchronizer {
         self.file_hashes.get(file_path)
     }
 
-    async fn save_snapshot(&self) -> Result<()> {
-        if let Some(parent) = self.snapshot_path.parent() {
-            fs::create_dir_all(parent).await?;
-        }
+    /// Number of times changes were detected since the snapshot was created,
+    /// counting the ones awaiting `commit_changes`
+    pub fn generation(&self) -> u64 {
+        self.pending.as_ref().map_or(self.generation, |pending| pending.generation)
+    }
 
+    /// Generation recorded in a codebase's snapshot, without hashing its files
+    pub fn stored_generation(codebase_path: &Path, data_dir: &Path) -> Option<u64> {
+        let snapshot_path = Self::get_snapshot_path(codebase_path, data_dir);
+        integrity::read_checked::<SnapshotData>(&snapshot_path, SNAPSHOT_FORMAT_VERSION)
+            .ok()
+            .flatten()
+            .map(|snapshot| snapshot.generation)
+    }
+
+    async fn save_snapshot(&self) -> Result<()> {
         let snapshot = SnapshotData {
             file_hashes: self.file_hashes.clone(),
             merkle_dag: self.merkle_dag.clone(),
+            generation: self.generation,
         };
 
-        let json = serde_json::to_string_pretty(&snapshot)?;
-        fs::write(&self.snapshot_path, json).await?;
+        integrity::write_checked(&self.snapshot_path, SNAPSHOT_FORMAT_VERSION, &snapshot)?;
         info!("[Synchronizer] Saved snapshot to {}", self.snapshot_path.display());
         Ok(())
     }
 
     async fn load_snapshot(&mut self) -> Result<()> {
-        match fs::read_to_string(&self.snapshot_path).await {
-            Ok(content) => {
-                let snapshot: SnapshotData = serde_json::from_str(&content)?;
+        match integrity::read_checked::<SnapshotData>(&self.snapshot_path, SNAPSHOT_FORMAT_VERSION) {
+            Ok(Some(snapshot)) => {
                 self.file_hashes = snapshot.file_hashes;
                 self.merkle_dag = snapshot.merkle_dag;
+                self.generation = snapshot.generation;
                 info!("[Synchronizer] Loaded snapshot from {}", self.snapshot_path.display());
                 Ok(())
             }
-            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                self.file_hashes = self.generate_file_hashes(&self.root_dir).await?;
+            Ok(None) => {
+                self.file_hashes = self.generate_file_hashes().await?;
                 self.merkle_dag = Self::build_merkle_dag(&self.file_hashes);
                 self.save_snapshot().await?;
                 Ok(())
             }
-            Err(e) => Err(Error::Io(e)),
+            Err(Error::Corrupted(reason)) => {
+                // Without the previous hashes, changes since the last run cannot be
+                // told apart; callers fall back to a full index
+                let moved = integrity::quarantine(&self.snapshot_path)?;
+                warn!("[Synchronizer] Snapshot failed verification ({}); quarantined as {}", reason, moved.display());
+                Err(Error::Corrupted(reason))
+            }
+            Err(e) => Err(e),
         }
     }
 
@@ -377,17 +379,6 @@ impl FileSynchronizer {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_simple_glob_match() {
-        let data_dir = PathBuf::from("/tmp/data");
-        let sync = FileSynchronizer::new(PathBuf::from("/tmp"), data_dir, vec![]);
-        
-        assert!(sync.simple_glob_match("test.js", "*.js"));
-        assert!(sync.simple_glob_match("test.min.js", "*.min.js"));
-        assert!(!sync.simple_glob_match("test.js", "*.ts"));
-        assert!(sync.simple_glob_match("node_modules", "node_modules"));
-    }
-
     #[test]
     fn test_should_ignore() {
         let data_dir = PathBuf::from("/tmp/data");
@@ -407,4 +398,42 @@ mod tests {
         assert!(sync.should_ignore("test.log", false));
         assert!(!sync.should_ignore("src/index.js", false));
     }
+
+    #[test]
+    fn test_should_ignore_gitignore_semantics() {
+        let data_dir = PathBuf::from("/tmp/data");
+        let sync = FileSynchronizer::new(
+            PathBuf::from("/tmp"),
+            data_dir,
+            vec!["*.log".to_string(), "!audit.log".to_string(), "/dist".to_string()],
+        );
+
+        assert!(sync.should_ignore("logs/app.log", false));
+        assert!(!sync.should_ignore("logs/audit.log", false));
+        assert!(sync.should_ignore("dist/bundle.js", false));
+        assert!(!sync.should_ignore("web/dist/bundle.js", false));
+        assert!(sync.should_ignore(".github/workflows/ci.yml", false));
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_found_again_until_committed() {
+        let codebase = tempfile::tempdir().unwrap();
+        let data_dir = tempfile::tempdir().unwrap();
+        std::fs::write(codebase.path().join("lib.rs"), "fn a() {}").unwrap();
+        let mut sync = FileSynchronizer::new(codebase.path().to_path_buf(), data_dir.path().to_path_buf(), Vec::new());
+        sync.initialize().await.unwrap();
+
+        std::fs::write(codebase.path().join("main.rs"), "fn main() {}").unwrap();
+        assert_eq!(sync.check_for_changes().await.unwrap().added, ["main.rs"]);
+        assert_eq!(sync.generation(), 1);
+
+        // A restart before the stores held the change finds it again
+        let mut restarted = FileSynchronizer::new(codebase.path().to_path_buf(), data_dir.path().to_path_buf(), Vec::new());
+        restarted.initialize().await.unwrap();
+        assert_eq!(restarted.check_for_changes().await.unwrap().added, ["main.rs"]);
+
+        restarted.commit_changes().await.unwrap();
+        assert!(restarted.check_for_changes().await.unwrap().added.is_empty());
+        assert_eq!(restarted.generation(), 1);
+    }
 }
@@ -0,0 +1,90 @@
+//! Duty-cycle throttling for low-priority indexing
+//!
+//! An indexing job calls `pause` after each unit of work (a file, an
+//! embedding or storage batch). Once a work slice has run for `SLICE`, the
+//! job sleeps long enough that its work takes up `cpu_share` of wall time,
+//! plus `batch_pause` after batches, so the editor and the rest of the machine
+//! stay responsive while a large repository is indexed.
+
+use crate::config::IndexingConfig;
+use std::time::{Duration, Instant};
+
+/// Work done before the job yields; short enough to keep the machine responsive
+const SLICE: Duration = Duration::from_millis(50);
+
+pub struct Throttle {
+    /// `None` when indexing runs at full speed
+    cpu_share: Option<f64>,
+    batch_pause: Duration,
+    slice_start: Instant,
+}
+
+impl Throttle {
+    pub fn new(config: &IndexingConfig) -> Self {
+        Self {
+            cpu_share: config.low_priority.then(|| config.cpu_share.clamp(0.05, 1.0)),
+            batch_pause: Duration::from_millis(config.batch_pause_ms),
+            slice_start: Instant::now(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.cpu_share.is_some()
+    }
+
+    /// Sleep after a file once the current work slice is used up
+    pub async fn pause(&mut self) {
+        if let Some(delay) = self.rest() {
+            tokio::time::sleep(delay).await;
+            self.slice_start = Instant::now();
+        }
+    }
+
+    /// Sleep after an embedding or storage batch, spreading IO bursts out
+    pub async fn pause_batch(&mut self) {
+        if !self.is_enabled() {
+            return;
+        }
+        let delay = self.rest().unwrap_or_default() + self.batch_pause;
+        tokio::time::sleep(delay).await;
+        self.slice_start = Instant::now();
+    }
+
+    /// Sleep owed for the work since the slice started, once it reached `SLICE`
+    fn rest(&self) -> Option<Duration> {
+        let share = self.cpu_share?;
+        let worked = self.slice_start.elapsed();
+        (worked >= SLICE).then(|| worked.mul_f64((1.0 - share) / share))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(low_priority: bool, cpu_share: f64) -> IndexingConfig {
+        let mut config = crate::Config::default().indexing;
+        config.low_priority = low_priority;
+        config.cpu_share = cpu_share;
+        config
+    }
+
+    #[test]
+    fn test_rest_matches_cpu_share() {
+        let mut throttle = Throttle::new(&config(true, 0.25));
+        assert!(throttle.rest().is_none());
+
+        throttle.slice_start = Instant::now() - Duration::from_millis(100);
+        let rest = throttle.rest().unwrap();
+        // 100ms of work at a 25% share owes three times as much rest
+        assert!(rest >= Duration::from_millis(300) && rest < Duration::from_millis(400), "{rest:?}");
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        let mut throttle = Throttle::new(&config(false, 0.25));
+        throttle.slice_start = Instant::now() - Duration::from_secs(1);
+        assert!(!throttle.is_enabled());
+        assert!(throttle.rest().is_none());
+    }
+}
@@ -0,0 +1,198 @@
+//! Soft-deleted codebase indexes
+//!
+//! `delete_index` moves a codebase's vector, full-text and metadata
+//! directories into `data_dir/trash/<entry>/` next to a manifest recording the
+//! codebase and its snapshot entry, so `restore_index` can move them back.
+//! Entries older than the retention period are purged for good.
+
+use crate::snapshot::CodebaseInfo;
+use crate::metadata::MetadataStore;
+use crate::search::BM25Search;
+use crate::vectordb::USearchDatabase;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// A cleared codebase waiting in the trash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    #[serde(skip)]
+    pub id: String,
+    pub codebase_path: PathBuf,
+    /// Unix seconds
+    pub deleted_at: u64,
+    /// Snapshot entry at deletion; `None` when the codebase was still being indexed
+    pub info: Option<CodebaseInfo>,
+}
+
+pub struct Trash {
+    root: PathBuf,
+    data_dir: PathBuf,
+    retention: Duration,
+}
+
+impl Trash {
+    pub fn new(data_dir: &Path, retention: Duration) -> Self {
+        Self {
+            root: data_dir.join("trash"),
+            data_dir: data_dir.to_path_buf(),
+            retention,
+        }
+    }
+
+    /// Per-codebase store directories, named as they appear inside an entry
+    fn stores(&self, codebase_path: &Path) -> Vec<(&'static str, PathBuf)> {
+        let vectors = USearchDatabase::get_index_path_for_codebase(codebase_path, &self.data_dir);
+        let mut stores = vec![
+            ("fulltext", BM25Search::get_index_path_for_codebase(codebase_path, &self.data_dir)),
+            ("metadata", MetadataStore::get_db_path_for_codebase(codebase_path, &self.data_dir)),
+        ];
+        if let Some(vectors_dir) = vectors.parent() {
+            stores.push(("vectors", vectors_dir.to_path_buf()));
+        }
+        stores
+    }
+
+    /// Move the codebase's stores into a new trash entry
+    pub fn move_codebase(&self, codebase_path: &Path, info: Option<CodebaseInfo>) -> Result<TrashEntry> {
+        let now = chrono::Utc::now();
+        let mut hasher = Sha256::new();
+        hasher.update(codebase_path.to_string_lossy().as_bytes());
+        let hash = format!("{:x}", hasher.finalize());
+
+        let entry = TrashEntry {
+            id: format!("{}-{}", &hash[..16], now.timestamp_millis()),
+            codebase_path: codebase_path.to_path_buf(),
+            deleted_at: now.timestamp().max(0) as u64,
+            info,
+        };
+        let entry_dir = self.root.join(&entry.id);
+        std::fs::create_dir_all(&entry_dir)?;
+        std::fs::write(entry_dir.join(MANIFEST_FILE), serde_json::to_vec_pretty(&entry)?)?;
+
+        for (name, dir) in self.stores(codebase_path) {
+            if dir.exists() {
+                std::fs::rename(&dir, entry_dir.join(name))?;
+            }
+        }
+
+        info!("[TRASH] Moved index of {} to {}", codebase_path.display(), entry_dir.display());
+        Ok(entry)
+    }
+
+    /// All entries, newest first; entries with an unreadable manifest are skipped
+    pub fn entries(&self) -> Result<Vec<TrashEntry>> {
+        if !self.root.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+        for dir in std::fs::read_dir(&self.root)? {
+            let dir = dir?;
+            let manifest = dir.path().join(MANIFEST_FILE);
+            let parsed = std::fs::read(&manifest)
+                .map_err(crate::Error::from)
+                .and_then(|bytes| serde_json::from_slice::<TrashEntry>(&bytes).map_err(Into::into));
+            match parsed {
+                Ok(mut entry) => {
+                    entry.id = dir.file_name().to_string_lossy().to_string();
+                    entries.push(entry);
+                }
+                Err(e) => warn!("[TRASH] Skipping {}: {}", dir.path().display(), e),
+            }
+        }
+
+        entries.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at).then_with(|| b.id.cmp(&a.id)));
+        Ok(entries)
+    }
+
+    /// Most recently deleted entry of a codebase
+    pub fn latest_for(&self, codebase_path: &Path) -> Result<Option<TrashEntry>> {
+        Ok(self.entries()?.into_iter().find(|entry| entry.codebase_path == codebase_path))
+    }
+
+    /// Move an entry's stores back in place and drop the entry
+    ///
+    /// Stores already present for the codebase are replaced; callers only
+    /// restore codebases that have no usable index.
+    pub fn restore(&self, entry: &TrashEntry) -> Result<()> {
+        let entry_dir = self.root.join(&entry.id);
+
+        for (name, dir) in self.stores(&entry.codebase_path) {
+            let stored = entry_dir.join(name);
+            if !stored.exists() {
+                continue;
+            }
+            if dir.exists() {
+                std::fs::remove_dir_all(&dir)?;
+            }
+            if let Some(parent) = dir.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::rename(&stored, &dir)?;
+        }
+
+        std::fs::remove_dir_all(&entry_dir)?;
+        info!("[TRASH] Restored index of {} from {}", entry.codebase_path.display(), entry.id);
+        Ok(())
+    }
+
+    /// Delete entries past the retention period, returning how many were removed
+    pub fn purge_expired(&self) -> Result<usize> {
+        let now = chrono::Utc::now().timestamp().max(0) as u64;
+        let mut purged = 0;
+
+        for entry in self.entries()? {
+            if now.saturating_sub(entry.deleted_at) < self.retention.as_secs() {
+                continue;
+            }
+            std::fs::remove_dir_all(self.root.join(&entry.id))?;
+            info!("[TRASH] Purged expired index of {}", entry.codebase_path.display());
+            purged += 1;
+        }
+
+        Ok(purged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_move_and_restore() {
+        let dir = tempdir().unwrap();
+        let data_dir = dir.path().join("data");
+        let codebase = dir.path().join("project");
+        let trash = Trash::new(&data_dir, Duration::from_secs(3600));
+
+        let metadata_dir = MetadataStore::get_db_path_for_codebase(&codebase, &data_dir);
+        std::fs::create_dir_all(&metadata_dir).unwrap();
+        std::fs::write(metadata_dir.join("db"), b"chunks").unwrap();
+
+        trash.move_codebase(&codebase, None).unwrap();
+        assert!(!metadata_dir.exists());
+
+        let entry = trash.latest_for(&codebase).unwrap().unwrap();
+        trash.restore(&entry).unwrap();
+        assert_eq!(std::fs::read(metadata_dir.join("db")).unwrap(), b"chunks");
+        assert!(trash.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_purge_expired() {
+        let dir = tempdir().unwrap();
+        let codebase = dir.path().join("project");
+
+        Trash::new(dir.path(), Duration::from_secs(3600)).move_codebase(&codebase, None).unwrap();
+        assert_eq!(Trash::new(dir.path(), Duration::from_secs(3600)).purge_expired().unwrap(), 0);
+        assert_eq!(Trash::new(dir.path(), Duration::ZERO).purge_expired().unwrap(), 1);
+        assert!(Trash::new(dir.path(), Duration::ZERO).entries().unwrap().is_empty());
+    }
+}
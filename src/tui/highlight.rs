@@ -0,0 +1,146 @@
+//! Lightweight syntax highlighting for result previews
+//!
+//! A single-line lexer that colors comments, strings, numbers, keywords and
+//! type-like identifiers. It does not track state across lines (block
+//! comments, multi-line strings), which is good enough for previews.
+
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "case", "catch", "class", "const", "continue", "def", "default",
+    "defer", "do", "elif", "else", "enum", "except", "export", "extends", "false", "finally", "fn",
+    "for", "from", "func", "function", "go", "if", "impl", "implements", "import", "in", "interface",
+    "is", "lambda", "let", "loop", "match", "mod", "module", "mut", "new", "nil", "None", "not",
+    "null", "or", "and", "package", "private", "protected", "pub", "public", "raise", "return",
+    "self", "Self", "static", "struct", "super", "switch", "this", "throw", "trait", "True", "False",
+    "true", "try", "type", "use", "val", "var", "void", "where", "while", "with", "yield",
+];
+
+fn line_comment_prefix(language: &str) -> &'static str {
+    match language {
+        "python" | "ruby" | "elixir" | "yaml" | "toml" | "shell" | "bash" | "r" => "#",
+        "sql" | "lua" | "haskell" => "--",
+        _ => "//",
+    }
+}
+
+/// Highlight one line of `language` source
+pub fn highlight_line(line: &str, language: &str) -> Line<'static> {
+    let comment_prefix = line_comment_prefix(language);
+    // Rust lifetimes ('a) would otherwise open a char literal
+    let single_quote_strings = language != "rust";
+
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    let mut i = 0;
+
+    let flush = |plain: &mut String, spans: &mut Vec<Span<'static>>| {
+        if !plain.is_empty() {
+            spans.push(Span::raw(std::mem::take(plain)));
+        }
+    };
+
+    while i < chars.len() {
+        let (offset, c) = chars[i];
+
+        if line[offset..].starts_with(comment_prefix) {
+            flush(&mut plain, &mut spans);
+            spans.push(Span::styled(
+                line[offset..].to_string(),
+                Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+            ));
+            return Line::from(spans);
+        }
+
+        if c == '"' || (c == '\'' && single_quote_strings) {
+            flush(&mut plain, &mut spans);
+            let mut end = i + 1;
+            while end < chars.len() && chars[end].1 != c {
+                if chars[end].1 == '\\' {
+                    end += 1;
+                }
+                end += 1;
+            }
+            let end = end.min(chars.len() - 1);
+            let end_offset = chars[end].0 + chars[end].1.len_utf8();
+            spans.push(Span::styled(line[offset..end_offset].to_string(), Style::default().fg(Color::Green)));
+            i = end + 1;
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let mut end = i;
+            while end < chars.len() && (chars[end].1.is_alphanumeric() || chars[end].1 == '_') {
+                end += 1;
+            }
+            let end_offset = chars.get(end).map(|(o, _)| *o).unwrap_or(line.len());
+            let word = &line[offset..end_offset];
+
+            let style = if KEYWORDS.contains(&word) {
+                Some(Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD))
+            } else if word.chars().next().is_some_and(char::is_uppercase) {
+                Some(Style::default().fg(Color::Yellow))
+            } else {
+                None
+            };
+
+            match style {
+                Some(style) => {
+                    flush(&mut plain, &mut spans);
+                    spans.push(Span::styled(word.to_string(), style));
+                }
+                None => plain.push_str(word),
+            }
+            i = end;
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            flush(&mut plain, &mut spans);
+            let mut end = i;
+            while end < chars.len() && (chars[end].1.is_ascii_alphanumeric() || chars[end].1 == '.' || chars[end].1 == '_') {
+                end += 1;
+            }
+            let end_offset = chars.get(end).map(|(o, _)| *o).unwrap_or(line.len());
+            spans.push(Span::styled(line[offset..end_offset].to_string(), Style::default().fg(Color::Cyan)));
+            i = end;
+            continue;
+        }
+
+        plain.push(c);
+        i += 1;
+    }
+
+    flush(&mut plain, &mut spans);
+    Line::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styled_words(line: &Line) -> Vec<(String, Option<Color>)> {
+        line.spans.iter().map(|span| (span.content.to_string(), span.style.fg)).collect()
+    }
+
+    #[test]
+    fn test_highlight_rust_line() {
+        let line = highlight_line("let s: String = \"a // b\"; // note", "rust");
+        let spans = styled_words(&line);
+
+        assert_eq!(spans[0], ("let".to_string(), Some(Color::Magenta)));
+        assert!(spans.contains(&("String".to_string(), Some(Color::Yellow))));
+        assert!(spans.contains(&("\"a // b\"".to_string(), Some(Color::Green))));
+        assert_eq!(spans.last().unwrap(), &("// note".to_string(), Some(Color::DarkGray)));
+    }
+
+    #[test]
+    fn test_python_comment_prefix() {
+        let line = highlight_line("x = 1  # count", "python");
+        let text: String = line.spans.iter().map(|span| span.content.to_string()).collect();
+        assert_eq!(text, "x = 1  # count");
+        assert_eq!(line.spans.last().unwrap().style.fg, Some(Color::DarkGray));
+    }
+}
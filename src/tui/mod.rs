@@ -0,0 +1,357 @@
+//! Interactive terminal UI (`code-sage tui`)
+//!
+//! Lists the codebases known to the snapshot, shows live indexing progress
+//! and offers a search pane with highlighted previews, using the same
+//! handlers as the MCP tools. Built only with the `tui` feature.
+
+mod highlight;
+
+use crate::handlers::ToolHandlers;
+use crate::snapshot::{CodebaseStatus, SnapshotManager};
+use crate::types::SearchResult;
+use crate::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Gauge, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How often the snapshot file is re-read for progress updates
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+const SEARCH_LIMIT: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Codebases,
+    Query,
+    Results,
+}
+
+struct App {
+    handlers: ToolHandlers,
+    snapshot_path: PathBuf,
+    codebases: Vec<(PathBuf, CodebaseStatus)>,
+    codebase_state: ListState,
+    query: String,
+    results: Vec<SearchResult>,
+    result_state: ListState,
+    preview_scroll: u16,
+    focus: Focus,
+    status_line: String,
+    last_refresh: Instant,
+}
+
+/// Run the terminal UI until the user quits
+pub async fn run(handlers: ToolHandlers, snapshot_path: PathBuf) -> Result<()> {
+    let mut app = App {
+        handlers,
+        snapshot_path,
+        codebases: Vec::new(),
+        codebase_state: ListState::default(),
+        query: String::new(),
+        results: Vec::new(),
+        result_state: ListState::default(),
+        preview_scroll: 0,
+        focus: Focus::Codebases,
+        status_line: "Tab: switch pane  Enter: search  ↑/↓: move  PgUp/PgDn: scroll preview  q: quit".to_string(),
+        last_refresh: Instant::now(),
+    };
+    app.refresh_codebases();
+
+    let mut terminal = ratatui::init();
+    let outcome = app.event_loop(&mut terminal).await;
+    ratatui::restore();
+    outcome
+}
+
+impl App {
+    async fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.render(frame))?;
+
+            if event::poll(Duration::from_millis(200))? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press && !self.handle_key(key, terminal).await? {
+                        return Ok(());
+                    }
+                }
+            }
+
+            if self.last_refresh.elapsed() >= REFRESH_INTERVAL {
+                self.refresh_codebases();
+            }
+        }
+    }
+
+    /// Returns `false` when the UI should exit
+    async fn handle_key(&mut self, key: KeyEvent, terminal: &mut DefaultTerminal) -> Result<bool> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Ok(false);
+        }
+
+        match key.code {
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Codebases => Focus::Query,
+                    Focus::Query => Focus::Results,
+                    Focus::Results => Focus::Codebases,
+                };
+                return Ok(true);
+            }
+            KeyCode::PageDown => {
+                self.preview_scroll = self.preview_scroll.saturating_add(10);
+                return Ok(true);
+            }
+            KeyCode::PageUp => {
+                self.preview_scroll = self.preview_scroll.saturating_sub(10);
+                return Ok(true);
+            }
+            _ => {}
+        }
+
+        match self.focus {
+            Focus::Query => match key.code {
+                KeyCode::Enter => {
+                    self.status_line = format!("Searching for \"{}\"...", self.query);
+                    terminal.draw(|frame| self.render(frame))?;
+                    self.run_search().await;
+                }
+                KeyCode::Esc => self.focus = Focus::Codebases,
+                KeyCode::Backspace => {
+                    self.query.pop();
+                }
+                KeyCode::Char(c) => self.query.push(c),
+                _ => {}
+            },
+            Focus::Codebases => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+                KeyCode::Up => self.codebase_state.select_previous(),
+                KeyCode::Down => self.codebase_state.select_next(),
+                KeyCode::Enter | KeyCode::Char('/') => self.focus = Focus::Query,
+                _ => {}
+            },
+            Focus::Results => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+                KeyCode::Up => {
+                    self.result_state.select_previous();
+                    self.preview_scroll = 0;
+                }
+                KeyCode::Down => {
+                    self.result_state.select_next();
+                    self.preview_scroll = 0;
+                }
+                KeyCode::Char('/') => self.focus = Focus::Query,
+                _ => {}
+            },
+        }
+
+        Ok(true)
+    }
+
+    fn refresh_codebases(&mut self) {
+        self.last_refresh = Instant::now();
+
+        let snapshot = match SnapshotManager::new(self.snapshot_path.clone()) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                self.status_line = format!("Failed to read snapshot: {e}");
+                return;
+            }
+        };
+
+        let selected = self.selected_codebase();
+        self.codebases = snapshot
+            .get_codebases()
+            .into_iter()
+            .map(|path| {
+                let status = snapshot.get_status(&path);
+                (path, status)
+            })
+            .collect();
+
+        let index = selected
+            .and_then(|selected| self.codebases.iter().position(|(path, _)| *path == selected))
+            .or(if self.codebases.is_empty() { None } else { Some(0) });
+        self.codebase_state.select(index);
+    }
+
+    fn selected_codebase(&self) -> Option<PathBuf> {
+        self.codebase_state
+            .selected()
+            .and_then(|index| self.codebases.get(index))
+            .map(|(path, _)| path.clone())
+    }
+
+    async fn run_search(&mut self) {
+        let Some(codebase) = self.selected_codebase() else {
+            self.status_line = "Select a codebase first".to_string();
+            return;
+        };
+
+        let query = self.query.trim().to_string();
+        if query.is_empty() {
+            return;
+        }
+
+        match self.handlers.search_results(&codebase, &query, SEARCH_LIMIT).await {
+            Ok(results) => {
+                self.status_line = format!("{} results for \"{}\" in {}", results.len(), query, codebase.display());
+                self.results = results;
+                self.result_state.select(if self.results.is_empty() { None } else { Some(0) });
+                self.preview_scroll = 0;
+                if !self.results.is_empty() {
+                    self.focus = Focus::Results;
+                }
+            }
+            Err(e) => {
+                self.status_line = format!("Search failed: {e}");
+            }
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let [main, footer] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] = Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)]).areas(main);
+        let [query_area, results_area, preview_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Percentage(35),
+            Constraint::Min(0),
+        ])
+        .areas(right);
+
+        self.render_codebases(frame, left);
+        self.render_query(frame, query_area);
+        self.render_results(frame, results_area);
+        self.render_preview(frame, preview_area);
+
+        frame.render_widget(
+            Paragraph::new(self.status_line.as_str()).style(Style::default().fg(Color::Gray)),
+            footer,
+        );
+    }
+
+    fn pane(&self, title: &str, focus: Focus) -> Block<'static> {
+        let border = if self.focus == focus {
+            Style::default().fg(Color::Cyan)
+        } else {
+            Style::default()
+        };
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(border)
+            .title(title.to_string())
+    }
+
+    fn render_codebases(&mut self, frame: &mut Frame, area: Rect) {
+        let block = self.pane(" Codebases ", Focus::Codebases);
+
+        // An indexing codebase gets a progress gauge under the list
+        let indexing = self.codebases.iter().find_map(|(path, status)| match status {
+            CodebaseStatus::Indexing(info) => Some((path.clone(), info.indexing_percentage)),
+            _ => None,
+        });
+
+        let list_area = if let Some((path, percentage)) = &indexing {
+            let [list_area, gauge_area] = Layout::vertical([Constraint::Min(0), Constraint::Length(3)]).areas(area);
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            frame.render_widget(
+                Gauge::default()
+                    .block(Block::default().borders(Borders::ALL).title(format!(" Indexing {name} ")))
+                    .gauge_style(Style::default().fg(Color::Cyan))
+                    .percent(percentage.clamp(0.0, 100.0) as u16),
+                gauge_area,
+            );
+            list_area
+        } else {
+            area
+        };
+
+        let items: Vec<ListItem> = self
+            .codebases
+            .iter()
+            .map(|(path, status)| {
+                let (label, color) = match status {
+                    CodebaseStatus::Indexed(info) => (format!("{} files, {} chunks", info.indexed_files, info.total_chunks), Color::Green),
+                    CodebaseStatus::Indexing(info) => (format!("indexing {:.0}%", info.indexing_percentage), Color::Yellow),
+                    CodebaseStatus::IndexFailed(_) => ("failed".to_string(), Color::Red),
+                    CodebaseStatus::NotFound => ("unknown".to_string(), Color::Gray),
+                };
+                ListItem::new(vec![
+                    Line::from(path.display().to_string()),
+                    Line::from(Span::styled(format!("  {label}"), Style::default().fg(color))),
+                ])
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut self.codebase_state);
+    }
+
+    fn render_query(&self, frame: &mut Frame, area: Rect) {
+        let cursor = if self.focus == Focus::Query { "▏" } else { "" };
+        frame.render_widget(
+            Paragraph::new(format!("{}{}", self.query, cursor)).block(self.pane(" Search ", Focus::Query)),
+            area,
+        );
+    }
+
+    fn render_results(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .results
+            .iter()
+            .map(|result| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:>2}. ", result.rank), Style::default().fg(Color::DarkGray)),
+                    Span::raw(format!("{}:{}-{}", result.relative_path, result.start_line, result.end_line)),
+                    Span::styled(format!("  {:.3}", result.score), Style::default().fg(Color::DarkGray)),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(self.pane(" Results ", Focus::Results))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.result_state);
+    }
+
+    fn render_preview(&self, frame: &mut Frame, area: Rect) {
+        let selected = self.result_state.selected().and_then(|index| self.results.get(index));
+
+        let (title, lines) = match selected {
+            Some(result) => {
+                let lines: Vec<Line> = result
+                    .content
+                    .lines()
+                    .enumerate()
+                    .map(|(offset, line)| {
+                        let mut highlighted = highlight::highlight_line(line, &result.language);
+                        highlighted.spans.insert(
+                            0,
+                            Span::styled(
+                                format!("{:>5} ", result.start_line + offset),
+                                Style::default().fg(Color::DarkGray),
+                            ),
+                        );
+                        highlighted
+                    })
+                    .collect();
+                (format!(" {} ({}) ", result.relative_path, result.language), lines)
+            }
+            None => (" Preview ".to_string(), Vec::new()),
+        };
+
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .wrap(Wrap { trim: false })
+                .scroll((self.preview_scroll, 0)),
+            area,
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 /// A code chunk with metadata
@@ -14,12 +15,33 @@ pub struct CodeChunk {
     pub metadata: ChunkMetadata,
 }
 
+/// Extensions of prose documentation, embedded with the documentation model
+/// when one is configured
+pub const DOCUMENTATION_EXTENSIONS: &[&str] = &["md", "markdown", "rst", "txt", "adoc"];
+
+impl CodeChunk {
+    /// Whether the chunk is prose documentation rather than source code
+    pub fn is_documentation(&self) -> bool {
+        let extension = self.metadata.file_extension.to_lowercase();
+        DOCUMENTATION_EXTENSIONS.contains(&extension.as_str())
+    }
+}
+
 /// Metadata for a code chunk
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChunkMetadata {
     pub file_extension: String,
     pub chunk_index: usize,
     pub hash: String,
+    /// File modification time (unix seconds) when the chunk was indexed
+    #[serde(default)]
+    pub modified_at: Option<u64>,
+    /// SHA-256 of the whole file when the chunk was indexed, matching the change snapshot
+    #[serde(default)]
+    pub file_hash: Option<String>,
+    /// Change snapshot generation the chunk was indexed at
+    #[serde(default)]
+    pub sync_generation: Option<u64>,
 }
 
 /// Search result from hybrid search
@@ -33,6 +55,13 @@ pub struct SearchResult {
     pub language: String,
     pub score: f32,
     pub rank: usize,
+    pub modified_at: Option<u64>,
+    /// SHA-256 of the file when the chunk was indexed
+    #[serde(default)]
+    pub file_hash: Option<String>,
+    /// The file changed since it was indexed, so the content may be stale
+    #[serde(default)]
+    pub stale: bool,
 }
 
 /// Indexing statistics
@@ -42,6 +71,85 @@ pub struct IndexStats {
     pub total_chunks: usize,
     pub elapsed_secs: f64,
     pub index_status: String,
+    /// Chunks per language by splitting strategy
+    #[serde(default)]
+    pub language_stats: BTreeMap<String, SplitterStats>,
+}
+
+/// How a file was split into chunks
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkOrigin {
+    /// Split along syntax nodes by the tree-sitter grammar
+    Ast,
+    /// Character-based splitting, used when the grammar is missing or parsing failed
+    Fallback,
+}
+
+/// Fallback rate from which a language with a grammar is reported as degraded
+pub const FALLBACK_WARNING_RATE: f64 = 0.2;
+
+/// Chunk counts of one language by splitting strategy
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SplitterStats {
+    pub ast_chunks: usize,
+    pub fallback_chunks: usize,
+}
+
+impl SplitterStats {
+    pub fn add(&mut self, origin: ChunkOrigin, chunks: usize) {
+        match origin {
+            ChunkOrigin::Ast => self.ast_chunks += chunks,
+            ChunkOrigin::Fallback => self.fallback_chunks += chunks,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.ast_chunks + self.fallback_chunks
+    }
+
+    /// Share of chunks from character fallback, between 0 and 1
+    pub fn fallback_rate(&self) -> f64 {
+        if self.total() == 0 {
+            0.0
+        } else {
+            self.fallback_chunks as f64 / self.total() as f64
+        }
+    }
+
+    /// Whether enough chunks fell back to character splitting that the
+    /// language's grammar is probably failing
+    pub fn is_degraded(&self) -> bool {
+        self.fallback_rate() >= FALLBACK_WARNING_RATE
+    }
+}
+
+/// Share of a file's bytes inside ERROR nodes from which it is reported as badly parsed
+pub const PARSE_ERROR_WARNING_RATE: f64 = 0.1;
+
+/// How much of a file the tree-sitter grammar could not parse
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ParseQuality {
+    /// Bytes covered by ERROR nodes
+    pub error_bytes: usize,
+    pub total_bytes: usize,
+    /// ERROR nodes plus nodes the parser inserted as MISSING
+    pub error_nodes: usize,
+}
+
+impl ParseQuality {
+    /// Share of the file inside ERROR nodes, between 0 and 1
+    pub fn error_rate(&self) -> f64 {
+        if self.total_bytes == 0 {
+            0.0
+        } else {
+            self.error_bytes as f64 / self.total_bytes as f64
+        }
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.error_rate() >= PARSE_ERROR_WARNING_RATE
+    }
 }
 
 /// Codebase indexing status
@@ -105,6 +213,14 @@ impl Language {
         .collect()
     }
 
+    /// Supported extensions of files in this language, e.g. `[".py", ".pyi"]`
+    pub fn extensions(&self) -> Vec<String> {
+        Self::supported_extensions()
+            .into_iter()
+            .filter(|ext| Self::from_extension(ext) == *self)
+            .collect()
+    }
+
     pub fn from_extension(ext: &str) -> Self {
         match ext {
             ".rs" => Language::Rust,
@@ -201,3 +317,39 @@ impl std::str::FromStr for Language {
         }
     }
 }
+
+/// Footprint of one of a codebase's stores: vectors, keyword index or metadata
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StoreStats {
+    pub files: usize,
+    pub bytes: u64,
+    /// Vectors, keyword documents or metadata records held
+    pub entries: usize,
+    /// Length of the stored vectors; `None` for stores without vectors
+    pub dimension: Option<usize>,
+}
+
+impl StoreStats {
+    /// Files and bytes under `path`, a file or a directory; nothing when missing
+    pub fn on_disk(path: &std::path::Path) -> Self {
+        let mut stats = Self::default();
+        for metadata in walkdir::WalkDir::new(path)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.metadata().ok())
+            .filter(|metadata| metadata.is_file())
+        {
+            stats.files += 1;
+            stats.bytes += metadata.len();
+        }
+        stats
+    }
+
+    /// Sum of `self` and `other`, e.g. over the shards of one store
+    pub fn add(&mut self, other: &StoreStats) {
+        self.files += other.files;
+        self.bytes += other.bytes;
+        self.entries += other.entries;
+        self.dimension = self.dimension.or(other.dimension);
+    }
+}
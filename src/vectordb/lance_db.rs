@@ -0,0 +1,558 @@
+//! Vector database backed by LanceDB
+//!
+//! Each codebase gets a Lance dataset next to where its usearch index would
+//! live. Rows carry the chunk's path, language, extension and lines alongside
+//! its vector, so searches can filter inside the store (e.g. by extension or
+//! path) instead of discarding candidates after fusion. Search is exact (a
+//! full scan), so no index has to be rebuilt as chunks change.
+//!
+//! Lance only takes the place of the usearch index; it does not replace the
+//! sled metadata store or the tantivy keyword index, which indexing still
+//! writes as it does with usearch. Chunk content stays in the metadata store
+//! alone: tables written with a `content` column lose it when next opened.
+
+use super::{SearchResult, VectorDatabase, VectorDocument, VectorReader};
+use crate::types::{CodeChunk, StoreStats};
+use crate::search::PathGlobs;
+use crate::{Error, Result};
+use arrow_array::types::Float32Type;
+use arrow_array::{
+    Array, FixedSizeListArray, Float32Array, RecordBatch, RecordBatchIterator, StringArray, UInt32Array,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use lancedb::query::{ExecutableQuery, QueryBase, Select};
+use lancedb::{DistanceType, Table};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use tracing::info;
+
+/// Table of source chunks
+pub const CODE_TABLE: &str = "chunks";
+/// Table of documentation chunks embedded with a separate model
+pub const DOCS_TABLE: &str = "docs";
+
+/// IDs per delete or lookup predicate
+const PREDICATE_BATCH: usize = 500;
+
+/// One stored chunk; the chunk columns are empty for vectors inserted
+/// without their chunk
+struct Row {
+    id: String,
+    vector: Vec<f32>,
+    relative_path: Option<String>,
+    language: Option<String>,
+    file_extension: Option<String>,
+    start_line: Option<u32>,
+    end_line: Option<u32>,
+}
+
+impl Row {
+    fn from_chunk(chunk: &CodeChunk, vector: &[f32]) -> Self {
+        Self {
+            id: chunk.id.clone(),
+            vector: vector.to_vec(),
+            relative_path: Some(chunk.relative_path.clone()),
+            language: Some(chunk.language.clone()),
+            file_extension: Some(chunk.metadata.file_extension.trim_start_matches('.').to_lowercase()),
+            start_line: Some(chunk.start_line as u32),
+            end_line: Some(chunk.end_line as u32),
+        }
+    }
+}
+
+pub struct LanceDatabase {
+    /// Directory of the Lance datasets
+    uri: PathBuf,
+    table_name: &'static str,
+    dimension: usize,
+    codebase_path: PathBuf,
+    /// Opened on first use, since connecting is asynchronous
+    table: OnceCell<Table>,
+}
+
+impl LanceDatabase {
+    /// The codebase's `table_name` table, created with `dimension` on first
+    /// write; a table of another dimension is reported by the first operation
+    /// that opens it
+    pub fn for_codebase(codebase_path: &Path, dimension: usize, data_dir: &Path, table_name: &'static str) -> Self {
+        Self {
+            uri: super::lance_path_for_codebase(codebase_path, data_dir),
+            table_name,
+            dimension,
+            codebase_path: codebase_path.to_path_buf(),
+            table: OnceCell::new(),
+        }
+    }
+
+    fn table_path(&self) -> PathBuf {
+        self.uri.join(format!("{}.lance", self.table_name))
+    }
+
+    fn schema(&self) -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Utf8, false),
+            Field::new(
+                "vector",
+                DataType::FixedSizeList(Arc::new(Field::new("item", DataType::Float32, true)), self.dimension as i32),
+                false,
+            ),
+            Field::new("relative_path", DataType::Utf8, true),
+            Field::new("language", DataType::Utf8, true),
+            Field::new("file_extension", DataType::Utf8, true),
+            Field::new("start_line", DataType::UInt32, true),
+            Field::new("end_line", DataType::UInt32, true),
+        ]))
+    }
+
+    async fn table(&self) -> Result<&Table> {
+        self.table.get_or_try_init(|| self.open_table()).await
+    }
+
+    async fn open_table(&self) -> Result<Table> {
+        std::fs::create_dir_all(&self.uri)?;
+        let uri = self.uri.to_string_lossy();
+        let db = lancedb::connect(&uri).execute().await.map_err(lance_error)?;
+        let names = db.table_names().execute().await.map_err(lance_error)?;
+
+        if names.iter().any(|name| name == self.table_name) {
+            let table = db.open_table(self.table_name).execute().await.map_err(lance_error)?;
+            let schema = table.schema().await.map_err(lance_error)?;
+            // Content was copied here before; the metadata store holds it
+            if schema.field_with_name("content").is_ok() {
+                table.drop_columns(&["content"]).await.map_err(lance_error)?;
+            }
+            let stored = vector_dimension(&schema);
+            if self.dimension == 0 || stored == Some(self.dimension) {
+                return Ok(table);
+            }
+            let rows = table.count_rows(None).await.map_err(lance_error)?;
+            // Nothing stored yet, so nothing is lost by resizing
+            if rows > 0 {
+                return Err(Error::DimensionMismatch(format!(
+                    "the LanceDB table of {} holds {} vectors of dimension {}, but the configured embedding model produces dimension {}. \
+                     Re-index with force=true to rebuild it with the current model, or set EMBEDDING_PROVIDER/EMBEDDING_MODEL back to the model that built it",
+                    self.codebase_path.display(),
+                    rows,
+                    stored.unwrap_or_default(),
+                    self.dimension
+                )));
+            }
+            db.drop_table(self.table_name).await.map_err(lance_error)?;
+        }
+
+        info!("[VECTOR-DB] Creating LanceDB table '{}' in {}", self.table_name, self.uri.display());
+        db.create_empty_table(self.table_name, self.schema())
+            .execute()
+            .await
+            .map_err(lance_error)
+    }
+
+    async fn add_rows(&self, rows: &[Row]) -> Result<()> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let ids: Vec<String> = rows.iter().map(|row| row.id.clone()).collect();
+        self.delete_ids(&ids).await?;
+
+        let schema = self.schema();
+        let batch = self.batch_from_rows(rows)?;
+        let reader = RecordBatchIterator::new(vec![Ok(batch)], schema);
+        self.table()
+            .await?
+            .add(Box::new(reader))
+            .execute()
+            .await
+            .map_err(lance_error)?;
+        Ok(())
+    }
+
+    async fn delete_ids(&self, ids: &[String]) -> Result<()> {
+        let table = self.table().await?;
+        for ids in ids.chunks(PREDICATE_BATCH) {
+            table.delete(&id_predicate(ids)).await.map_err(lance_error)?;
+        }
+        Ok(())
+    }
+
+    /// Stored rows with the given IDs
+    async fn rows_with_ids(&self, ids: &[String]) -> Result<Vec<Row>> {
+        let table = self.table().await?;
+        let mut rows = Vec::new();
+        for ids in ids.chunks(PREDICATE_BATCH) {
+            let batches: Vec<RecordBatch> = table
+                .query()
+                .only_if(id_predicate(ids))
+                .execute()
+                .await
+                .map_err(lance_error)?
+                .try_collect()
+                .await
+                .map_err(lance_error)?;
+            for batch in &batches {
+                rows.extend(rows_from_batch(batch)?);
+            }
+        }
+        Ok(rows)
+    }
+
+    fn batch_from_rows(&self, rows: &[Row]) -> Result<RecordBatch> {
+        if let Some(row) = rows.iter().find(|row| row.vector.len() != self.dimension) {
+            return Err(Error::VectorDb(format!(
+                "Vector dimension mismatch: expected {}, got {}",
+                self.dimension,
+                row.vector.len()
+            )));
+        }
+        let strings = |field: fn(&Row) -> Option<&str>| Arc::new(StringArray::from_iter(rows.iter().map(field)));
+        let lines = |field: fn(&Row) -> Option<u32>| Arc::new(UInt32Array::from_iter(rows.iter().map(field)));
+        let vectors = FixedSizeListArray::from_iter_primitive::<Float32Type, _, _>(
+            rows.iter().map(|row| Some(row.vector.iter().copied().map(Some))),
+            self.dimension as i32,
+        );
+
+        RecordBatch::try_new(
+            self.schema(),
+            vec![
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|row| row.id.as_str()))),
+                Arc::new(vectors),
+                strings(|row| row.relative_path.as_deref()),
+                strings(|row| row.language.as_deref()),
+                strings(|row| row.file_extension.as_deref()),
+                lines(|row| row.start_line),
+                lines(|row| row.end_line),
+            ],
+        )
+        .map_err(|e| Error::VectorDb(format!("Failed to build LanceDB batch: {e}")))
+    }
+
+    async fn nearest(&self, query_vector: &[f32], top_k: usize, filter: Option<String>) -> Result<Vec<SearchResult>> {
+        if query_vector.len() != self.dimension {
+            return Err(Error::VectorDb(format!(
+                "Query vector dimension mismatch: expected {}, got {}",
+                self.dimension,
+                query_vector.len()
+            )));
+        }
+        let mut query = self
+            .table()
+            .await?
+            .query()
+            .nearest_to(query_vector)
+            .map_err(lance_error)?
+            .distance_type(DistanceType::Cosine)
+            .select(Select::columns(&["id"]))
+            .limit(top_k);
+        if let Some(filter) = filter {
+            query = query.only_if(filter);
+        }
+        let batches: Vec<RecordBatch> = query
+            .execute()
+            .await
+            .map_err(lance_error)?
+            .try_collect()
+            .await
+            .map_err(lance_error)?;
+
+        let mut results = Vec::new();
+        for batch in &batches {
+            let ids = string_column(batch, "id")?;
+            let distances = batch
+                .column_by_name("_distance")
+                .and_then(|column| column.as_any().downcast_ref::<Float32Array>())
+                .ok_or_else(|| Error::VectorDb("LanceDB result has no distances".to_string()))?;
+            for i in 0..batch.num_rows() {
+                results.push(SearchResult {
+                    id: ids.value(i).to_string(),
+                    score: 1.0 - distances.value(i),
+                });
+            }
+        }
+        Ok(results)
+    }
+}
+
+#[async_trait]
+impl VectorReader for LanceDatabase {
+    async fn search(&self, query_vector: &[f32], top_k: usize) -> Result<Vec<SearchResult>> {
+        self.nearest(query_vector, top_k, None).await
+    }
+
+    async fn search_with_extensions(
+        &self,
+        query_vector: &[f32],
+        top_k: usize,
+        extensions: &[String],
+    ) -> Result<Vec<SearchResult>> {
+        self.search_filtered(query_vector, top_k, extensions, &PathGlobs::default()).await
+    }
+
+    async fn search_filtered(
+        &self,
+        query_vector: &[f32],
+        top_k: usize,
+        extensions: &[String],
+        path_globs: &PathGlobs,
+    ) -> Result<Vec<SearchResult>> {
+        let mut predicates = Vec::new();
+        if !extensions.is_empty() {
+            let extensions: Vec<String> = extensions
+                .iter()
+                .map(|extension| extension.trim_start_matches('.').to_lowercase())
+                .collect();
+            predicates.push(format!("file_extension IN ({})", quoted_list(&extensions)));
+        }
+        let path_predicate = |pattern: &str| format!("regexp_match(relative_path, {})", quoted_list(&[format!("^(?:{pattern})$")]));
+        predicates.extend(path_globs.required_patterns().map(path_predicate));
+        predicates.extend(path_globs.exclude_pattern().map(|pattern| format!("NOT {}", path_predicate(pattern))));
+        let filter = (!predicates.is_empty()).then(|| predicates.join(" AND "));
+        self.nearest(query_vector, top_k, filter).await
+    }
+
+    async fn has_index(&self, _codebase_path: &Path) -> Result<bool> {
+        Ok(self.table_path().exists())
+    }
+
+    async fn count(&self) -> Result<usize> {
+        if !self.table_path().exists() {
+            return Ok(0);
+        }
+        self.table().await?.count_rows(None).await.map_err(lance_error)
+    }
+
+    /// The dimension is read from the table, since it may have been opened
+    /// accepting any
+    async fn stats(&self) -> Result<StoreStats> {
+        if !self.table_path().exists() {
+            return Ok(StoreStats::default());
+        }
+        let table = self.table().await?;
+        Ok(StoreStats {
+            entries: table.count_rows(None).await.map_err(lance_error)?,
+            dimension: vector_dimension(&table.schema().await.map_err(lance_error)?),
+            ..StoreStats::on_disk(&self.table_path())
+        })
+    }
+}
+
+#[async_trait]
+impl VectorDatabase for LanceDatabase {
+    async fn insert(&mut self, documents: Vec<VectorDocument>) -> Result<()> {
+        let rows: Vec<Row> = documents
+            .into_iter()
+            .map(|document| Row {
+                id: document.id,
+                vector: document.vector,
+                relative_path: None,
+                language: None,
+                file_extension: None,
+                start_line: None,
+                end_line: None,
+            })
+            .collect();
+        self.add_rows(&rows).await
+    }
+
+    /// Store the chunks' paths, languages and lines with their vectors
+    async fn insert_batch(
+        &mut self,
+        _codebase_path: &Path,
+        chunks: &[CodeChunk],
+        embeddings: &[Vec<f32>],
+    ) -> Result<()> {
+        let rows: Vec<Row> = chunks
+            .iter()
+            .zip(embeddings)
+            .map(|(chunk, embedding)| Row::from_chunk(chunk, embedding))
+            .collect();
+        info!("[VECTOR-DB] Writing {} chunks to LanceDB table '{}'", rows.len(), self.table_name);
+        self.add_rows(&rows).await
+    }
+
+    /// Writing rows already removes the rows stored under their IDs
+    async fn upsert_batch(
+        &mut self,
+        codebase_path: &Path,
+        chunks: &[CodeChunk],
+        embeddings: &[Vec<f32>],
+    ) -> Result<()> {
+        self.insert_batch(codebase_path, chunks, embeddings).await
+    }
+
+    async fn delete(&mut self, ids: &[String]) -> Result<()> {
+        self.delete_ids(ids).await
+    }
+
+    async fn rename(&mut self, renames: &[(String, String)]) -> Result<()> {
+        if renames.is_empty() {
+            return Ok(());
+        }
+        let old_ids: Vec<String> = renames.iter().map(|(old, _)| old.clone()).collect();
+        let new_ids: Vec<String> = renames.iter().map(|(_, new)| new.clone()).collect();
+        let taken: HashSet<String> = self.rows_with_ids(&new_ids).await?.into_iter().map(|row| row.id).collect();
+
+        let mut moved = Vec::new();
+        for mut row in self.rows_with_ids(&old_ids).await? {
+            let new_id = renames.iter().find(|(old, _)| *old == row.id).map(|(_, new)| new.clone());
+            if let Some(new_id) = new_id.filter(|new_id| !taken.contains(new_id)) {
+                row.id = new_id;
+                moved.push(row);
+            }
+        }
+        self.delete_ids(&old_ids).await?;
+        self.add_rows(&moved).await
+    }
+
+    async fn delete_index(&mut self, _codebase_path: &Path) -> Result<()> {
+        let path = self.table_path();
+        if path.exists() {
+            std::fs::remove_dir_all(&path)?;
+        }
+        self.table = OnceCell::new();
+        Ok(())
+    }
+
+    /// Every write is committed as it happens
+    async fn save(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn load(&mut self) -> Result<()> {
+        self.table().await.map(|_| ())
+    }
+}
+
+fn lance_error(e: lancedb::Error) -> Error {
+    Error::VectorDb(format!("LanceDB: {e}"))
+}
+
+/// Dimension of the `vector` column of a table's schema
+fn vector_dimension(schema: &Schema) -> Option<usize> {
+    match schema.field_with_name("vector").ok()?.data_type() {
+        DataType::FixedSizeList(_, size) => Some(*size as usize),
+        _ => None,
+    }
+}
+
+/// SQL string literals of `values`, separated by commas
+fn quoted_list(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|value| format!("'{}'", value.replace('\'', "''")))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn id_predicate(ids: &[String]) -> String {
+    format!("id IN ({})", quoted_list(ids))
+}
+
+fn string_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a StringArray> {
+    batch
+        .column_by_name(name)
+        .and_then(|column| column.as_any().downcast_ref::<StringArray>())
+        .ok_or_else(|| Error::VectorDb(format!("LanceDB result has no '{name}' column")))
+}
+
+fn rows_from_batch(batch: &RecordBatch) -> Result<Vec<Row>> {
+    let ids = string_column(batch, "id")?;
+    let vectors = batch
+        .column_by_name("vector")
+        .and_then(|column| column.as_any().downcast_ref::<FixedSizeListArray>())
+        .ok_or_else(|| Error::VectorDb("LanceDB result has no vectors".to_string()))?;
+    let text = |name: &str, i: usize| -> Result<Option<String>> {
+        let column = string_column(batch, name)?;
+        Ok((!column.is_null(i)).then(|| column.value(i).to_string()))
+    };
+    let line = |name: &str, i: usize| -> Option<u32> {
+        let column = batch.column_by_name(name)?.as_any().downcast_ref::<UInt32Array>()?;
+        (!column.is_null(i)).then(|| column.value(i))
+    };
+
+    let mut rows = Vec::with_capacity(batch.num_rows());
+    for i in 0..batch.num_rows() {
+        let vector = vectors.value(i);
+        let vector = vector
+            .as_any()
+            .downcast_ref::<Float32Array>()
+            .ok_or_else(|| Error::VectorDb("LanceDB vector is not f32".to_string()))?;
+        rows.push(Row {
+            id: ids.value(i).to_string(),
+            vector: vector.values().to_vec(),
+            relative_path: text("relative_path", i)?,
+            language: text("language", i)?,
+            file_extension: text("file_extension", i)?,
+            start_line: line("start_line", i),
+            end_line: line("end_line", i),
+        });
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ChunkMetadata;
+    use tempfile::TempDir;
+
+    fn chunk(id: &str, extension: &str) -> CodeChunk {
+        CodeChunk {
+            id: id.to_string(),
+            content: format!("content of {id}"),
+            file_path: PathBuf::from(format!("/repo/{id}.{extension}")),
+            relative_path: format!("{id}.{extension}"),
+            start_line: 1,
+            end_line: 3,
+            language: extension.to_string(),
+            metadata: ChunkMetadata {
+                file_extension: extension.to_string(),
+                chunk_index: 0,
+                hash: String::new(),
+                modified_at: None,
+                file_hash: None,
+                sync_generation: None,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_insert_filter_rename_and_delete() {
+        let temp = TempDir::new().unwrap();
+        let codebase = Path::new("/repo");
+        let mut db = LanceDatabase::for_codebase(codebase, 3, temp.path(), CODE_TABLE);
+        assert!(!db.has_index(codebase).await.unwrap());
+
+        let chunks = vec![chunk("a", "rs"), chunk("b", "py"), chunk("c", "rs")];
+        let embeddings = vec![vec![1.0, 0.0, 0.0], vec![0.9, 0.1, 0.0], vec![0.0, 1.0, 0.0]];
+        db.insert_batch(codebase, &chunks, &embeddings).await.unwrap();
+        assert_eq!(db.count().await.unwrap(), 3);
+
+        let results = db.search(&[1.0, 0.0, 0.0], 2).await.unwrap();
+        assert_eq!(results[0].id, "a");
+        assert!(results[0].score > 0.99);
+
+        let python = db.search_with_extensions(&[1.0, 0.0, 0.0], 3, &[".py".to_string()]).await.unwrap();
+        assert_eq!(python.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), vec!["b"]);
+        let globs = PathGlobs::new(&[], &["c.*".to_string()]).unwrap();
+        let rust = db.search_filtered(&[1.0, 0.0, 0.0], 3, &["rs".to_string()], &globs).await.unwrap();
+        assert_eq!(rust.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), vec!["a"]);
+
+        db.rename(&[("a".to_string(), "a2".to_string())]).await.unwrap();
+        let results = db.search(&[1.0, 0.0, 0.0], 1).await.unwrap();
+        assert_eq!(results[0].id, "a2");
+
+        db.delete(&["b".to_string()]).await.unwrap();
+        assert_eq!(db.count().await.unwrap(), 2);
+
+        // A reopened table keeps its rows, and another dimension is refused
+        assert_eq!(LanceDatabase::for_codebase(codebase, 3, temp.path(), CODE_TABLE).count().await.unwrap(), 2);
+        let mut resized = LanceDatabase::for_codebase(codebase, 4, temp.path(), CODE_TABLE);
+        assert!(matches!(resized.load().await, Err(Error::DimensionMismatch(_))));
+
+        db.delete_index(codebase).await.unwrap();
+        assert!(!db.has_index(codebase).await.unwrap());
+    }
+}
@@ -1,6 +1,16 @@
-//! Vector database module using USearch
+//! Vector database module using USearch, or LanceDB or SQLite with the
+//! `lancedb` and `sqlite` features
 
 pub mod usearch_db;
+mod usearch_flat;
+mod usearch_log;
+mod usearch_mappings;
+pub mod split;
+pub mod sharded;
+#[cfg(feature = "lancedb")]
+pub mod lance_db;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_db;
 
 use crate::Result;
 use async_trait::async_trait;
@@ -18,19 +28,87 @@ pub struct SearchResult {
     pub score: f32,
 }
 
+/// Read half of a vector store. Searches only need `&self`, so one reader
+/// per codebase can be shared by concurrent searches while indexing writes
+/// through its own `VectorDatabase`
 #[async_trait]
-pub trait VectorDatabase: Send + Sync {
-    /// Insert vectors into the database
-    async fn insert(&mut self, documents: Vec<VectorDocument>) -> Result<()>;
-    
+pub trait VectorReader: Send + Sync {
     /// Search for similar vectors
     async fn search(&self, query_vector: &[f32], top_k: usize) -> Result<Vec<SearchResult>>;
     
+    /// Check if index exists for a codebase
+    async fn has_index(&self, codebase_path: &Path) -> Result<bool>;
+    
+    async fn search_codebase(
+        &self,
+        _codebase_path: &Path,
+        query_vector: &[f32],
+        top_k: usize,
+    ) -> Result<Vec<SearchResult>> {
+        self.search(query_vector, top_k).await
+    }
+    
+    /// Search among chunks whose file has one of `extensions` (e.g. ".rs");
+    /// stores that keep no chunk metadata search all vectors and leave the
+    /// filtering to the caller
+    async fn search_with_extensions(
+        &self,
+        query_vector: &[f32],
+        top_k: usize,
+        _extensions: &[String],
+    ) -> Result<Vec<SearchResult>> {
+        self.search(query_vector, top_k).await
+    }
+    
+    /// Like `search_with_extensions`, also among chunks whose relative path
+    /// `path_globs` keeps; stores that cannot filter by path search as
+    /// `search_with_extensions` and leave that filtering to the caller
+    async fn search_filtered(
+        &self,
+        query_vector: &[f32],
+        top_k: usize,
+        extensions: &[String],
+        _path_globs: &crate::search::PathGlobs,
+    ) -> Result<Vec<SearchResult>> {
+        self.search_with_extensions(query_vector, top_k, extensions).await
+    }
+    
+    /// Get total number of vectors
+    async fn count(&self) -> Result<usize>;
+    
+    /// Files and bytes on disk, vectors and dimension of the index
+    async fn stats(&self) -> Result<crate::types::StoreStats>;
+    
+    /// Precision of the stored vectors; quantized indexes need their
+    /// candidates re-scored with full-precision vectors
+    fn quantization(&self) -> crate::config::VectorQuantization {
+        crate::config::VectorQuantization::None
+    }
+    
+    /// Distance the stored vectors are compared by
+    fn metric(&self) -> crate::config::VectorMetric {
+        crate::config::VectorMetric::Cosine
+    }
+}
+
+/// Write half of a vector store
+#[async_trait]
+pub trait VectorDatabase: VectorReader {
+    /// Insert vectors into the database
+    async fn insert(&mut self, documents: Vec<VectorDocument>) -> Result<()>;
+    
     /// Delete vectors by IDs
     async fn delete(&mut self, ids: &[String]) -> Result<()>;
     
-    /// Check if index exists for a codebase
-    async fn has_index(&self, codebase_path: &Path) -> Result<bool>;
+    /// Move vectors to new IDs without re-inserting them; a vector whose new
+    /// ID is already taken is dropped
+    async fn rename(&mut self, renames: &[(String, String)]) -> Result<()>;
+    
+    /// Reclaim the space of deleted vectors that `delete` only marked as
+    /// deleted, returning how many were removed
+    async fn vacuum(&mut self) -> Result<usize> {
+        Ok(0)
+    }
     
     /// Delete entire index for a codebase
     async fn delete_index(&mut self, codebase_path: &Path) -> Result<()>;
@@ -64,17 +142,24 @@ pub trait VectorDatabase: Send + Sync {
         Ok(())
     }
     
-    async fn search_codebase(
-        &self,
-        _codebase_path: &Path,
-        query_vector: &[f32],
-        top_k: usize,
-    ) -> Result<Vec<SearchResult>> {
-        self.search(query_vector, top_k).await
+    /// Store vectors, replacing those already stored under the same chunk
+    /// IDs, so a retried or re-indexed batch never leaves two vectors for
+    /// one chunk
+    async fn upsert_batch(
+        &mut self,
+        codebase_path: &Path,
+        chunks: &[crate::types::CodeChunk],
+        embeddings: &[Vec<f32>],
+    ) -> Result<()> {
+        let ids: Vec<String> = chunks.iter().map(|chunk| chunk.id.clone()).collect();
+        self.delete(&ids).await?;
+        self.insert_batch(codebase_path, chunks, embeddings).await
     }
     
-    /// Get total number of vectors
-    async fn count(&self) -> Result<usize>;
+    /// Take the changes until the next `save` as a build of the index from
+    /// scratch, which a crash restarts anyway, so stores may write them once
+    /// at the save instead of making each one durable
+    fn begin_bulk_load(&mut self) {}
     
     /// Save index to disk
     async fn save(&self) -> Result<()>;
@@ -84,3 +169,20 @@ pub trait VectorDatabase: Send + Sync {
 }
 
 pub use usearch_db::USearchDatabase;
+pub use sharded::ShardedUSearchDatabase;
+
+/// Directory of the codebase's LanceDB tables; it shares the directory of
+/// the usearch index, so removing or trashing that directory covers both
+pub fn lance_path_for_codebase(codebase_path: &Path, data_dir: &Path) -> std::path::PathBuf {
+    USearchDatabase::get_index_path_for_codebase(codebase_path, data_dir).with_file_name("lance")
+}
+
+/// The codebase's SQLite vector file, in the directory of the usearch index
+pub fn sqlite_path_for_codebase(codebase_path: &Path, data_dir: &Path) -> std::path::PathBuf {
+    USearchDatabase::get_index_path_for_codebase(codebase_path, data_dir).with_file_name("index.sqlite")
+}
+pub use split::SplitVectorDatabase;
+#[cfg(feature = "lancedb")]
+pub use lance_db::LanceDatabase;
+#[cfg(feature = "sqlite")]
+pub use sqlite_db::SqliteDatabase;
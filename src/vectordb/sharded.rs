@@ -0,0 +1,335 @@
+//! Codebase vector index split across several usearch indexes
+//!
+//! One usearch index per repository becomes unwieldy past a million chunks:
+//! building, saving and loading it is all-or-nothing. `VECTOR_SHARDS` spreads
+//! new vectors over that many shard files by a hash of their chunk ID, and
+//! searches query every shard and merge the hits by score. Shard 0 is the
+//! usual `index.usearch`, so an unsharded index is a one-shard index and
+//! existence checks on that file keep working. Every shard found on disk is
+//! opened whatever `VECTOR_SHARDS` says, and lookups by ID go to the shard
+//! holding the ID, so changing the setting never strands vectors.
+
+use super::{SearchResult, USearchDatabase, VectorDatabase, VectorDocument, VectorReader};
+use crate::config::{HnswConfig, VectorMetric, VectorQuantization};
+use crate::search::PathGlobs;
+use crate::types::{CodeChunk, StoreStats};
+use crate::Result;
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub struct ShardedUSearchDatabase {
+    shards: Vec<USearchDatabase>,
+}
+
+impl ShardedUSearchDatabase {
+    pub fn new(shards: Vec<USearchDatabase>) -> Self {
+        Self { shards }
+    }
+
+    /// Open the shards of the index at `index_path`: at least `shard_count`,
+    /// and all that exist on disk; their mappings live in `mapping_db`.
+    /// `recover` quarantines shards that fail verification
+    #[allow(clippy::too_many_arguments)]
+    pub fn open(
+        index_path: PathBuf,
+        codebase_path: &Path,
+        dimension: usize,
+        data_dir: &Path,
+        mapping_db: &sled::Db,
+        quantization: VectorQuantization,
+        metric: VectorMetric,
+        hnsw: HnswConfig,
+        view: bool,
+        recover: bool,
+        shard_count: usize,
+    ) -> Result<Self> {
+        // Without a dimension missing shards cannot be created, so only the
+        // ones on disk are opened
+        let mut count = if dimension == 0 { 1 } else { shard_count.max(1) };
+        while USearchDatabase::shard_path(&index_path, count).exists() {
+            count += 1;
+        }
+
+        let shards = (0..count)
+            .map(|shard| {
+                USearchDatabase::open_for_codebase(
+                    USearchDatabase::shard_path(&index_path, shard),
+                    codebase_path,
+                    dimension,
+                    data_dir,
+                    mapping_db,
+                    quantization,
+                    metric,
+                    hnsw,
+                    view,
+                    recover,
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(shards))
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// The only shard, or `self` back when there are several
+    pub fn into_single(mut self) -> std::result::Result<USearchDatabase, Self> {
+        if self.shards.len() == 1 {
+            return Ok(self.shards.remove(0));
+        }
+        Err(self)
+    }
+
+    /// Shard holding `id`, or the one its hash assigns a new ID to
+    fn shard_for(&self, id: &str) -> usize {
+        if let Some(shard) = self.shards.iter().position(|shard| shard.contains(id)) {
+            return shard;
+        }
+        let hash = Sha256::digest(id.as_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&hash[..8]);
+        (u64::from_le_bytes(bytes) % self.shards.len() as u64) as usize
+    }
+
+    /// Best `top_k` of the shards' hits
+    fn merge(mut results: Vec<SearchResult>, top_k: usize) -> Vec<SearchResult> {
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        results.truncate(top_k);
+        results
+    }
+}
+
+#[async_trait]
+impl VectorReader for ShardedUSearchDatabase {
+    async fn search(&self, query_vector: &[f32], top_k: usize) -> Result<Vec<SearchResult>> {
+        let mut results = Vec::new();
+        for shard in &self.shards {
+            results.extend(shard.search(query_vector, top_k).await?);
+        }
+        Ok(Self::merge(results, top_k))
+    }
+
+    async fn search_with_extensions(
+        &self,
+        query_vector: &[f32],
+        top_k: usize,
+        extensions: &[String],
+    ) -> Result<Vec<SearchResult>> {
+        let mut results = Vec::new();
+        for shard in &self.shards {
+            results.extend(shard.search_with_extensions(query_vector, top_k, extensions).await?);
+        }
+        Ok(Self::merge(results, top_k))
+    }
+
+    async fn search_filtered(
+        &self,
+        query_vector: &[f32],
+        top_k: usize,
+        extensions: &[String],
+        path_globs: &PathGlobs,
+    ) -> Result<Vec<SearchResult>> {
+        let mut results = Vec::new();
+        for shard in &self.shards {
+            results.extend(shard.search_filtered(query_vector, top_k, extensions, path_globs).await?);
+        }
+        Ok(Self::merge(results, top_k))
+    }
+
+    async fn has_index(&self, codebase_path: &Path) -> Result<bool> {
+        self.shards[0].has_index(codebase_path).await
+    }
+
+    async fn count(&self) -> Result<usize> {
+        let mut count = 0;
+        for shard in &self.shards {
+            count += shard.count().await?;
+        }
+        Ok(count)
+    }
+
+    async fn stats(&self) -> Result<StoreStats> {
+        let mut stats = StoreStats::default();
+        for shard in &self.shards {
+            stats.add(&shard.stats().await?);
+        }
+        Ok(stats)
+    }
+
+    fn quantization(&self) -> VectorQuantization {
+        self.shards[0].quantization()
+    }
+
+    fn metric(&self) -> VectorMetric {
+        self.shards[0].metric()
+    }
+}
+
+#[async_trait]
+impl VectorDatabase for ShardedUSearchDatabase {
+    async fn insert(&mut self, documents: Vec<VectorDocument>) -> Result<()> {
+        let mut by_shard: HashMap<usize, Vec<VectorDocument>> = HashMap::new();
+        for document in documents {
+            by_shard.entry(self.shard_for(&document.id)).or_default().push(document);
+        }
+        for (shard, documents) in by_shard {
+            self.shards[shard].insert(documents).await?;
+        }
+        Ok(())
+    }
+
+    async fn insert_batch(
+        &mut self,
+        codebase_path: &Path,
+        chunks: &[CodeChunk],
+        embeddings: &[Vec<f32>],
+    ) -> Result<()> {
+        let mut by_shard: HashMap<usize, (Vec<CodeChunk>, Vec<Vec<f32>>)> = HashMap::new();
+        for (chunk, embedding) in chunks.iter().zip(embeddings) {
+            let (chunks, embeddings) = by_shard.entry(self.shard_for(&chunk.id)).or_default();
+            chunks.push(chunk.clone());
+            embeddings.push(embedding.clone());
+        }
+        for (shard, (chunks, embeddings)) in by_shard {
+            self.shards[shard].insert_batch(codebase_path, &chunks, &embeddings).await?;
+        }
+        Ok(())
+    }
+
+    /// An existing ID routes to the shard holding it, which replaces its vector
+    async fn upsert_batch(
+        &mut self,
+        codebase_path: &Path,
+        chunks: &[CodeChunk],
+        embeddings: &[Vec<f32>],
+    ) -> Result<()> {
+        self.insert_batch(codebase_path, chunks, embeddings).await
+    }
+
+    async fn delete(&mut self, ids: &[String]) -> Result<()> {
+        for shard in &mut self.shards {
+            shard.delete(ids).await?;
+        }
+        Ok(())
+    }
+
+    /// A vector stays in its shard under the new ID; the new ID then routes
+    /// there through the lookup, not its hash
+    async fn rename(&mut self, renames: &[(String, String)]) -> Result<()> {
+        for (old_id, new_id) in renames {
+            if old_id == new_id {
+                continue;
+            }
+            if self.shards.iter().any(|shard| shard.contains(new_id)) {
+                self.delete(std::slice::from_ref(old_id)).await?;
+                continue;
+            }
+            if let Some(shard) = self.shards.iter_mut().find(|shard| shard.contains(old_id)) {
+                shard.rename(&[(old_id.clone(), new_id.clone())]).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn vacuum(&mut self) -> Result<usize> {
+        let mut removed = 0;
+        for shard in &mut self.shards {
+            removed += shard.vacuum().await?;
+        }
+        Ok(removed)
+    }
+
+    /// Shards share the index directory, which the first shard removes
+    async fn delete_index(&mut self, codebase_path: &Path) -> Result<()> {
+        for shard in &mut self.shards {
+            shard.delete_index(codebase_path).await?;
+        }
+        Ok(())
+    }
+
+    async fn save(&self) -> Result<()> {
+        for shard in &self.shards {
+            shard.save().await?;
+        }
+        Ok(())
+    }
+
+    fn begin_bulk_load(&mut self) {
+        for shard in &mut self.shards {
+            shard.begin_bulk_load();
+        }
+    }
+
+    async fn load(&mut self) -> Result<()> {
+        for shard in &mut self.shards {
+            shard.load().await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn open(codebase_path: &Path, data_dir: &Path, mapping_db: &sled::Db, shard_count: usize) -> ShardedUSearchDatabase {
+        ShardedUSearchDatabase::open(
+            USearchDatabase::get_index_path_for_codebase(codebase_path, data_dir),
+            codebase_path,
+            4,
+            data_dir,
+            mapping_db,
+            VectorQuantization::None,
+            VectorMetric::Cosine,
+            HnswConfig::default(),
+            false,
+            true,
+            shard_count,
+        )
+        .unwrap()
+    }
+
+    fn vector(i: usize) -> Vec<f32> {
+        let mut vector = vec![0.1; 4];
+        vector[i % 4] = 1.0 + i as f32;
+        vector
+    }
+
+    #[tokio::test]
+    async fn test_sharded_index_spreads_and_merges_vectors() {
+        let dir = tempdir().unwrap();
+        let codebase_path = dir.path().join("codebase");
+        let data_dir = dir.path();
+        let mapping_db = sled::open(data_dir.join("metadata")).unwrap();
+
+        let mut db = open(&codebase_path, data_dir, &mapping_db, 4);
+        db.insert((0..40).map(|i| VectorDocument { id: format!("chunk{i}"), vector: vector(i) }).collect())
+            .await
+            .unwrap();
+        let used = db
+            .shards
+            .iter()
+            .filter(|shard| (0..40).any(|i| shard.contains(&format!("chunk{i}"))))
+            .count();
+        assert!(used > 1);
+        db.save().await.unwrap();
+
+        // Fewer shards configured later still finds every shard on disk
+        let mut db = open(&codebase_path, data_dir, &mapping_db, 1);
+        assert_eq!(db.shard_count(), 4);
+        assert_eq!(db.count().await.unwrap(), 40);
+        let results = db.search(&[1.0, 0.0, 0.0, 0.0], 5).await.unwrap();
+        assert_eq!(results.len(), 5);
+        assert!(results.windows(2).all(|pair| pair[0].score >= pair[1].score));
+
+        db.rename(&[("chunk0".to_string(), "renamed".to_string())]).await.unwrap();
+        db.delete(&["chunk1".to_string()]).await.unwrap();
+        assert!(!db.shards.iter().any(|shard| shard.contains("chunk1") || shard.contains("chunk0")));
+        let results = db.search(&vector(0), 1).await.unwrap();
+        assert_eq!(results[0].id, "renamed");
+    }
+}
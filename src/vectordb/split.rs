@@ -0,0 +1,159 @@
+//! Vector index pair of a codebase with a separate documentation model
+//!
+//! Source chunks and documentation chunks (Markdown, reStructuredText, plain
+//! text) are embedded by different models, so their vectors live in two
+//! usearch indexes. This wrapper routes inserts by chunk and applies deletes,
+//! renames and saves to both, so indexing code can treat the pair as one
+//! database; searches query each index with its own model's query embedding.
+
+use super::{SearchResult, VectorDatabase, VectorDocument, VectorReader};
+use crate::config::{VectorMetric, VectorQuantization};
+use crate::search::PathGlobs;
+use crate::types::{CodeChunk, StoreStats};
+use crate::Result;
+use async_trait::async_trait;
+use std::path::Path;
+
+pub struct SplitVectorDatabase {
+    code: Box<dyn VectorDatabase>,
+    docs: Box<dyn VectorDatabase>,
+}
+
+impl SplitVectorDatabase {
+    pub fn new(code: Box<dyn VectorDatabase>, docs: Box<dyn VectorDatabase>) -> Self {
+        Self { code, docs }
+    }
+}
+
+#[async_trait]
+impl VectorReader for SplitVectorDatabase {
+    async fn search(&self, query_vector: &[f32], top_k: usize) -> Result<Vec<SearchResult>> {
+        self.code.search(query_vector, top_k).await
+    }
+
+    async fn search_with_extensions(
+        &self,
+        query_vector: &[f32],
+        top_k: usize,
+        extensions: &[String],
+    ) -> Result<Vec<SearchResult>> {
+        self.code.search_with_extensions(query_vector, top_k, extensions).await
+    }
+
+    async fn search_filtered(
+        &self,
+        query_vector: &[f32],
+        top_k: usize,
+        extensions: &[String],
+        path_globs: &PathGlobs,
+    ) -> Result<Vec<SearchResult>> {
+        self.code.search_filtered(query_vector, top_k, extensions, path_globs).await
+    }
+
+    async fn has_index(&self, codebase_path: &Path) -> Result<bool> {
+        self.code.has_index(codebase_path).await
+    }
+
+    async fn count(&self) -> Result<usize> {
+        Ok(self.code.count().await? + self.docs.count().await?)
+    }
+
+    /// The dimension reported is the code index's
+    async fn stats(&self) -> Result<StoreStats> {
+        let mut stats = self.code.stats().await?;
+        stats.add(&self.docs.stats().await?);
+        Ok(stats)
+    }
+
+    fn quantization(&self) -> VectorQuantization {
+        self.code.quantization()
+    }
+
+    fn metric(&self) -> VectorMetric {
+        self.code.metric()
+    }
+}
+
+#[async_trait]
+impl VectorDatabase for SplitVectorDatabase {
+    /// Vectors without a chunk to classify go to the code index
+    async fn insert(&mut self, documents: Vec<VectorDocument>) -> Result<()> {
+        self.code.insert(documents).await
+    }
+
+    async fn delete(&mut self, ids: &[String]) -> Result<()> {
+        self.code.delete(ids).await?;
+        self.docs.delete(ids).await
+    }
+
+    async fn rename(&mut self, renames: &[(String, String)]) -> Result<()> {
+        self.code.rename(renames).await?;
+        self.docs.rename(renames).await
+    }
+
+    async fn delete_index(&mut self, codebase_path: &Path) -> Result<()> {
+        self.code.delete_index(codebase_path).await?;
+        self.docs.delete_index(codebase_path).await
+    }
+
+    async fn insert_batch(
+        &mut self,
+        codebase_path: &Path,
+        chunks: &[CodeChunk],
+        embeddings: &[Vec<f32>],
+    ) -> Result<()> {
+        let (docs, code): (Vec<(&CodeChunk, &Vec<f32>)>, Vec<(&CodeChunk, &Vec<f32>)>) =
+            chunks.iter().zip(embeddings).partition(|(chunk, _)| chunk.is_documentation());
+        for (db, pairs) in [(&mut self.code, code), (&mut self.docs, docs)] {
+            if pairs.is_empty() {
+                continue;
+            }
+            let (chunks, embeddings): (Vec<CodeChunk>, Vec<Vec<f32>>) =
+                pairs.into_iter().map(|(chunk, embedding)| (chunk.clone(), embedding.clone())).unzip();
+            db.insert_batch(codebase_path, &chunks, &embeddings).await?;
+        }
+        Ok(())
+    }
+
+    /// A chunk is replaced in its own index and removed from the other one,
+    /// in case it was stored there before
+    async fn upsert_batch(
+        &mut self,
+        codebase_path: &Path,
+        chunks: &[CodeChunk],
+        embeddings: &[Vec<f32>],
+    ) -> Result<()> {
+        let (docs, code): (Vec<(&CodeChunk, &Vec<f32>)>, Vec<(&CodeChunk, &Vec<f32>)>) =
+            chunks.iter().zip(embeddings).partition(|(chunk, _)| chunk.is_documentation());
+        for (db, other, pairs) in [(&mut self.code, &mut self.docs, code), (&mut self.docs, &mut self.code, docs)] {
+            if pairs.is_empty() {
+                continue;
+            }
+            let (chunks, embeddings): (Vec<CodeChunk>, Vec<Vec<f32>>) =
+                pairs.into_iter().map(|(chunk, embedding)| (chunk.clone(), embedding.clone())).unzip();
+            let ids: Vec<String> = chunks.iter().map(|chunk| chunk.id.clone()).collect();
+            other.delete(&ids).await?;
+            db.upsert_batch(codebase_path, &chunks, &embeddings).await?;
+        }
+        Ok(())
+    }
+
+    async fn vacuum(&mut self) -> Result<usize> {
+        Ok(self.code.vacuum().await? + self.docs.vacuum().await?)
+    }
+
+    async fn save(&self) -> Result<()> {
+        self.code.save().await?;
+        self.docs.save().await
+    }
+
+    fn begin_bulk_load(&mut self) {
+        self.code.begin_bulk_load();
+        self.docs.begin_bulk_load();
+    }
+
+    async fn load(&mut self) -> Result<()> {
+        self.code.load().await?;
+        self.docs.load().await
+    }
+}
@@ -0,0 +1,588 @@
+//! Vector database in a single SQLite file, searched with sqlite-vec
+//!
+//! A codebase's vectors and the chunks they belong to (path, language,
+//! extension, lines, content) live in one `index.sqlite` file: a regular
+//! `chunks` table and a `vec0` virtual table of vectors sharing its rowids.
+//! Documentation chunks embedded with a separate model get their own pair of
+//! tables (`docs_chunks`, `docs_vectors`) in the same file. At the end of
+//! each indexing run the codebase's metadata store (chunk records, settings,
+//! identifiers, history) is copied into a `metadata` table, and a missing
+//! metadata store is restored from it, so the file holds the index on its
+//! own apart from the keyword index.
+
+use super::{SearchResult, VectorDatabase, VectorDocument, VectorReader};
+use crate::metadata::MetadataStore;
+use crate::types::{CodeChunk, StoreStats};
+use crate::{Error, Result};
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, Once};
+use tracing::info;
+
+/// Table prefix of source chunks
+pub const CODE_TABLES: &str = "";
+/// Table prefix of documentation chunks embedded with a separate model
+pub const DOCS_TABLES: &str = "docs_";
+
+pub struct SqliteDatabase {
+    connection: Mutex<Connection>,
+    path: PathBuf,
+    /// Prefix of this database's `chunks`, `vectors` and `settings` tables
+    prefix: &'static str,
+    dimension: usize,
+}
+
+impl SqliteDatabase {
+    /// Open the codebase's tables with `prefix`, creating them with
+    /// `dimension` if needed
+    ///
+    /// Returns `Error::DimensionMismatch` when stored vectors have another
+    /// dimension than `dimension` (0 accepts any), like the usearch index.
+    pub fn for_codebase(codebase_path: &Path, dimension: usize, data_dir: &Path, prefix: &'static str) -> Result<Self> {
+        let path = super::sqlite_path_for_codebase(codebase_path, data_dir);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        register_sqlite_vec();
+        let connection = Connection::open(&path).map_err(sqlite_error)?;
+        connection
+            .execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")
+            .map_err(sqlite_error)?;
+        connection
+            .execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS {prefix}settings (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+                 CREATE TABLE IF NOT EXISTS {prefix}chunks (
+                     rowid INTEGER PRIMARY KEY,
+                     id TEXT NOT NULL UNIQUE,
+                     relative_path TEXT,
+                     language TEXT,
+                     file_extension TEXT,
+                     start_line INTEGER,
+                     end_line INTEGER,
+                     content TEXT
+                 );
+                 CREATE INDEX IF NOT EXISTS {prefix}chunks_extension ON {prefix}chunks (file_extension);
+                 CREATE TABLE IF NOT EXISTS metadata (
+                     tree BLOB NOT NULL,
+                     key BLOB NOT NULL,
+                     value BLOB NOT NULL,
+                     hash BLOB NOT NULL,
+                     PRIMARY KEY (tree, key)
+                 ) WITHOUT ROWID;"
+            ))
+            .map_err(sqlite_error)?;
+
+        let mut db = Self {
+            connection: Mutex::new(connection),
+            path,
+            prefix,
+            dimension,
+        };
+        match db.stored_dimension()? {
+            Some(stored) if dimension == 0 || stored == dimension => db.dimension = stored,
+            Some(stored) => {
+                let count = db.count_sync()?;
+                if count > 0 {
+                    return Err(Error::DimensionMismatch(format!(
+                        "the SQLite index of {} holds {} vectors of dimension {}, but the configured embedding model produces dimension {}. \
+                         Re-index with force=true to rebuild it with the current model, or set EMBEDDING_PROVIDER/EMBEDDING_MODEL back to the model that built it",
+                        codebase_path.display(),
+                        count,
+                        stored,
+                        dimension
+                    )));
+                }
+                // Nothing stored yet, so nothing is lost by resizing
+                db.create_vectors_table()?;
+            }
+            None => db.create_vectors_table()?,
+        }
+        Ok(db)
+    }
+
+    /// Make the `metadata` table a copy of `store`, writing only the entries
+    /// that changed since the last copy
+    pub fn save_metadata(&self, store: &MetadataStore) -> Result<()> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(sqlite_error)?;
+        let mut written = 0;
+        let removed = {
+            let mut stored: HashMap<(Vec<u8>, Vec<u8>), Vec<u8>> = HashMap::new();
+            let mut statement = transaction.prepare("SELECT tree, key, hash FROM metadata").map_err(sqlite_error)?;
+            let mut rows = statement.query([]).map_err(sqlite_error)?;
+            while let Some(row) = rows.next().map_err(sqlite_error)? {
+                stored.insert(
+                    (row.get(0).map_err(sqlite_error)?, row.get(1).map_err(sqlite_error)?),
+                    row.get(2).map_err(sqlite_error)?,
+                );
+            }
+
+            let mut upsert = transaction
+                .prepare("INSERT OR REPLACE INTO metadata (tree, key, value, hash) VALUES (?1, ?2, ?3, ?4)")
+                .map_err(sqlite_error)?;
+            store.for_each_entry(|tree, key, value| {
+                let hash = Sha256::digest(value).to_vec();
+                if stored.remove(&(tree.to_vec(), key.to_vec())).as_ref() != Some(&hash) {
+                    upsert.execute(params![tree, key, value, hash]).map_err(sqlite_error)?;
+                    written += 1;
+                }
+                Ok(())
+            })?;
+
+            let mut delete = transaction
+                .prepare("DELETE FROM metadata WHERE tree = ?1 AND key = ?2")
+                .map_err(sqlite_error)?;
+            for (tree, key) in stored.keys() {
+                delete.execute(params![tree, key]).map_err(sqlite_error)?;
+            }
+            stored.len()
+        };
+        transaction.commit().map_err(sqlite_error)?;
+        info!("[VECTOR-DB] Copied metadata to {} ({} entries written, {} removed)", self.path.display(), written, removed);
+        Ok(())
+    }
+
+    /// Fill `store` from the `metadata` table; returns the entries restored
+    pub fn restore_metadata(&self, store: &MetadataStore) -> Result<usize> {
+        let connection = self.connection();
+        let mut statement = connection.prepare("SELECT tree, key, value FROM metadata").map_err(sqlite_error)?;
+        let mut rows = statement.query([]).map_err(sqlite_error)?;
+        let mut restored = 0;
+        while let Some(row) = rows.next().map_err(sqlite_error)? {
+            let tree: Vec<u8> = row.get(0).map_err(sqlite_error)?;
+            let key: Vec<u8> = row.get(1).map_err(sqlite_error)?;
+            let value: Vec<u8> = row.get(2).map_err(sqlite_error)?;
+            store.insert_entry(&tree, &key, &value)?;
+            restored += 1;
+        }
+        store.flush()?;
+        Ok(restored)
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn stored_dimension(&self) -> Result<Option<usize>> {
+        let value: Option<String> = self
+            .connection()
+            .query_row(
+                &format!("SELECT value FROM {}settings WHERE key = 'dimension'", self.prefix),
+                [],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        Ok(value.and_then(|value| value.parse().ok()))
+    }
+
+    /// (Re)create the vector table at the configured dimension
+    fn create_vectors_table(&self) -> Result<()> {
+        let prefix = self.prefix;
+        self.connection()
+            .execute_batch(&format!(
+                "DROP TABLE IF EXISTS {prefix}vectors;
+                 CREATE VIRTUAL TABLE {prefix}vectors USING vec0(embedding float[{}] distance_metric=cosine);
+                 INSERT OR REPLACE INTO {prefix}settings (key, value) VALUES ('dimension', '{}');",
+                self.dimension, self.dimension
+            ))
+            .map_err(sqlite_error)
+    }
+
+    fn count_sync(&self) -> Result<usize> {
+        let count: i64 = self
+            .connection()
+            .query_row(&format!("SELECT COUNT(*) FROM {}chunks", self.prefix), [], |row| row.get(0))
+            .map_err(sqlite_error)?;
+        Ok(count as usize)
+    }
+
+    /// Store `vector` for the chunk `id`, with the chunk's columns when given
+    fn upsert(&self, connection: &Connection, id: &str, vector: &[f32], chunk: Option<&CodeChunk>) -> Result<()> {
+        if vector.len() != self.dimension {
+            return Err(Error::VectorDb(format!(
+                "Vector dimension mismatch: expected {}, got {}",
+                self.dimension,
+                vector.len()
+            )));
+        }
+        let prefix = self.prefix;
+        let rowid: i64 = connection
+            .query_row(
+                &format!(
+                    "INSERT INTO {prefix}chunks (id, relative_path, language, file_extension, start_line, end_line, content)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                     ON CONFLICT (id) DO UPDATE SET relative_path = excluded.relative_path, language = excluded.language,
+                         file_extension = excluded.file_extension, start_line = excluded.start_line,
+                         end_line = excluded.end_line, content = excluded.content
+                     RETURNING rowid"
+                ),
+                params![
+                    id,
+                    chunk.map(|chunk| chunk.relative_path.as_str()),
+                    chunk.map(|chunk| chunk.language.as_str()),
+                    chunk.map(|chunk| chunk.metadata.file_extension.trim_start_matches('.').to_lowercase()),
+                    chunk.map(|chunk| chunk.start_line as i64),
+                    chunk.map(|chunk| chunk.end_line as i64),
+                    chunk.map(|chunk| chunk.content.as_str()),
+                ],
+                |row| row.get(0),
+            )
+            .map_err(sqlite_error)?;
+        connection
+            .execute(&format!("DELETE FROM {prefix}vectors WHERE rowid = ?1"), params![rowid])
+            .map_err(sqlite_error)?;
+        connection
+            .execute(
+                &format!("INSERT INTO {prefix}vectors (rowid, embedding) VALUES (?1, ?2)"),
+                params![rowid, vector_blob(vector)],
+            )
+            .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    fn write<'a>(&self, rows: impl Iterator<Item = (&'a str, &'a [f32], Option<&'a CodeChunk>)>) -> Result<()> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(sqlite_error)?;
+        for (id, vector, chunk) in rows {
+            self.upsert(&transaction, id, vector, chunk)?;
+        }
+        transaction.commit().map_err(sqlite_error)
+    }
+
+    /// `top_k` nearest chunks, among those with `extension` when given
+    fn nearest(&self, query_vector: &[f32], top_k: usize, extension: Option<&str>) -> Result<Vec<SearchResult>> {
+        if query_vector.len() != self.dimension {
+            return Err(Error::VectorDb(format!(
+                "Query vector dimension mismatch: expected {}, got {}",
+                self.dimension,
+                query_vector.len()
+            )));
+        }
+        let prefix = self.prefix;
+        let connection = self.connection();
+        let map = |row: &rusqlite::Row<'_>| -> rusqlite::Result<SearchResult> {
+            let distance: f64 = row.get(1)?;
+            Ok(SearchResult { id: row.get(0)?, score: 1.0 - distance as f32 })
+        };
+        let results = match extension {
+            // vec0 applies the k limit before joins, so restrict its rowids instead
+            Some(extension) => connection
+                .prepare(&format!(
+                    "WITH knn AS (
+                         SELECT rowid, distance FROM {prefix}vectors
+                         WHERE embedding MATCH ?1 AND k = ?2
+                           AND rowid IN (SELECT rowid FROM {prefix}chunks WHERE file_extension = ?3)
+                     )
+                     SELECT c.id, knn.distance FROM knn JOIN {prefix}chunks c ON c.rowid = knn.rowid
+                     ORDER BY knn.distance"
+                ))
+                .and_then(|mut statement| {
+                    statement
+                        .query_map(params![vector_blob(query_vector), top_k as i64, extension], map)?
+                        .collect::<rusqlite::Result<Vec<_>>>()
+                }),
+            None => connection
+                .prepare(&format!(
+                    "WITH knn AS (
+                         SELECT rowid, distance FROM {prefix}vectors WHERE embedding MATCH ?1 AND k = ?2
+                     )
+                     SELECT c.id, knn.distance FROM knn JOIN {prefix}chunks c ON c.rowid = knn.rowid
+                     ORDER BY knn.distance"
+                ))
+                .and_then(|mut statement| {
+                    statement
+                        .query_map(params![vector_blob(query_vector), top_k as i64], map)?
+                        .collect::<rusqlite::Result<Vec<_>>>()
+                }),
+        };
+        results.map_err(sqlite_error)
+    }
+}
+
+#[async_trait]
+impl VectorReader for SqliteDatabase {
+    async fn search(&self, query_vector: &[f32], top_k: usize) -> Result<Vec<SearchResult>> {
+        self.nearest(query_vector, top_k, None)
+    }
+
+    async fn search_with_extensions(
+        &self,
+        query_vector: &[f32],
+        top_k: usize,
+        extensions: &[String],
+    ) -> Result<Vec<SearchResult>> {
+        if extensions.is_empty() {
+            return self.nearest(query_vector, top_k, None);
+        }
+        let mut results = Vec::new();
+        for extension in extensions {
+            let extension = extension.trim_start_matches('.').to_lowercase();
+            results.extend(self.nearest(query_vector, top_k, Some(&extension))?);
+        }
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        results.truncate(top_k);
+        Ok(results)
+    }
+
+    /// The file is shared and outlives `delete_index`, so an index exists
+    /// once a run saved these tables, even with no chunks, or while they
+    /// hold chunks from before that was recorded
+    async fn has_index(&self, _codebase_path: &Path) -> Result<bool> {
+        if !self.path.exists() {
+            return Ok(false);
+        }
+        let saved = self
+            .connection()
+            .query_row(&format!("SELECT 1 FROM {}settings WHERE key = 'saved'", self.prefix), [], |_| Ok(()))
+            .optional()
+            .map_err(sqlite_error)?
+            .is_some();
+        Ok(saved || self.count_sync()? > 0)
+    }
+
+    async fn count(&self) -> Result<usize> {
+        self.count_sync()
+    }
+
+    /// Documentation tables live in the code tables' file, whose size is
+    /// reported with the code tables
+    async fn stats(&self) -> Result<StoreStats> {
+        let mut stats = StoreStats::default();
+        if self.prefix.is_empty() {
+            stats.add(&StoreStats::on_disk(&self.path));
+            stats.add(&StoreStats::on_disk(&PathBuf::from(format!("{}-wal", self.path.display()))));
+        }
+        stats.entries = self.count_sync()?;
+        stats.dimension = Some(self.dimension);
+        Ok(stats)
+    }
+}
+
+#[async_trait]
+impl VectorDatabase for SqliteDatabase {
+    async fn insert(&mut self, documents: Vec<VectorDocument>) -> Result<()> {
+        self.write(documents.iter().map(|document| (document.id.as_str(), document.vector.as_slice(), None)))
+    }
+
+    /// Store the chunks' metadata and content with their vectors
+    async fn insert_batch(
+        &mut self,
+        _codebase_path: &Path,
+        chunks: &[CodeChunk],
+        embeddings: &[Vec<f32>],
+    ) -> Result<()> {
+        info!("[VECTOR-DB] Writing {} chunks to {}", chunks.len(), self.path.display());
+        self.write(
+            chunks
+                .iter()
+                .zip(embeddings)
+                .map(|(chunk, embedding)| (chunk.id.as_str(), embedding.as_slice(), Some(chunk))),
+        )
+    }
+
+    /// Rows are written with upserts already
+    async fn upsert_batch(
+        &mut self,
+        codebase_path: &Path,
+        chunks: &[CodeChunk],
+        embeddings: &[Vec<f32>],
+    ) -> Result<()> {
+        self.insert_batch(codebase_path, chunks, embeddings).await
+    }
+
+    async fn delete(&mut self, ids: &[String]) -> Result<()> {
+        let prefix = self.prefix;
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(sqlite_error)?;
+        for id in ids {
+            let rowid: Option<i64> = transaction
+                .query_row(&format!("DELETE FROM {prefix}chunks WHERE id = ?1 RETURNING rowid"), params![id], |row| row.get(0))
+                .optional()
+                .map_err(sqlite_error)?;
+            if let Some(rowid) = rowid {
+                transaction
+                    .execute(&format!("DELETE FROM {prefix}vectors WHERE rowid = ?1"), params![rowid])
+                    .map_err(sqlite_error)?;
+            }
+        }
+        transaction.commit().map_err(sqlite_error)
+    }
+
+    /// Vectors keep their rowid, so only the chunk's ID changes
+    async fn rename(&mut self, renames: &[(String, String)]) -> Result<()> {
+        let prefix = self.prefix;
+        let taken: Vec<String> = {
+            let connection = self.connection();
+            let mut statement = connection
+                .prepare(&format!("SELECT 1 FROM {prefix}chunks WHERE id = ?1"))
+                .map_err(sqlite_error)?;
+            renames
+                .iter()
+                .filter(|(_, new)| statement.exists(params![new]).unwrap_or(false))
+                .map(|(old, _)| old.clone())
+                .collect()
+        };
+        self.delete(&taken).await?;
+
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(sqlite_error)?;
+        for (old, new) in renames.iter().filter(|(old, _)| !taken.contains(old)) {
+            transaction
+                .execute(&format!("UPDATE {prefix}chunks SET id = ?2 WHERE id = ?1"), params![old, new])
+                .map_err(sqlite_error)?;
+        }
+        transaction.commit().map_err(sqlite_error)
+    }
+
+    /// Empties this database's tables, and for the code tables the copy of
+    /// the metadata store; the other model's tables in the same file are kept
+    async fn delete_index(&mut self, _codebase_path: &Path) -> Result<()> {
+        let prefix = self.prefix;
+        let metadata = if prefix == CODE_TABLES { "DELETE FROM metadata;" } else { "" };
+        self.connection()
+            .execute_batch(&format!("DELETE FROM {prefix}chunks; DELETE FROM {prefix}settings WHERE key = 'saved'; {metadata}"))
+            .map_err(sqlite_error)?;
+        self.create_vectors_table()
+    }
+
+    /// Every write is committed as it happens; fold the write-ahead log back
+    /// into the file so it can be copied on its own
+    async fn save(&self) -> Result<()> {
+        self.connection()
+            .execute_batch(&format!(
+                "INSERT OR REPLACE INTO {}settings (key, value) VALUES ('saved', '1');
+                 PRAGMA wal_checkpoint(TRUNCATE);",
+                self.prefix
+            ))
+            .map_err(sqlite_error)
+    }
+
+    async fn load(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Load sqlite-vec into every connection opened from now on
+fn register_sqlite_vec() {
+    static REGISTER: Once = Once::new();
+    REGISTER.call_once(|| {
+        // SAFETY: sqlite3_vec_init has the signature SQLite expects of an
+        // extension entry point, as documented by sqlite-vec
+        unsafe {
+            rusqlite::ffi::sqlite3_auto_extension(Some(std::mem::transmute::<*const (), unsafe extern "C" fn(
+                *mut rusqlite::ffi::sqlite3,
+                *mut *mut std::os::raw::c_char,
+                *const rusqlite::ffi::sqlite3_api_routines,
+            ) -> std::os::raw::c_int>(
+                sqlite_vec::sqlite3_vec_init as *const (),
+            )));
+        }
+    });
+}
+
+fn sqlite_error(e: rusqlite::Error) -> Error {
+    Error::VectorDb(format!("SQLite: {e}"))
+}
+
+/// Little-endian f32s, the binary vector format sqlite-vec reads
+fn vector_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|value| value.to_le_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ChunkMetadata;
+    use tempfile::TempDir;
+
+    fn chunk(id: &str, extension: &str) -> CodeChunk {
+        CodeChunk {
+            id: id.to_string(),
+            content: format!("content of {id}"),
+            file_path: PathBuf::from(format!("/repo/{id}.{extension}")),
+            relative_path: format!("{id}.{extension}"),
+            start_line: 1,
+            end_line: 3,
+            language: extension.to_string(),
+            metadata: ChunkMetadata {
+                file_extension: extension.to_string(),
+                chunk_index: 0,
+                hash: String::new(),
+                modified_at: None,
+                file_hash: None,
+                sync_generation: None,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_insert_filter_rename_and_delete() {
+        let temp = TempDir::new().unwrap();
+        let codebase = Path::new("/repo");
+        let mut db = SqliteDatabase::for_codebase(codebase, 3, temp.path(), CODE_TABLES).unwrap();
+
+        let chunks = vec![chunk("a", "rs"), chunk("b", "py"), chunk("c", "rs")];
+        let embeddings = vec![vec![1.0, 0.0, 0.0], vec![0.9, 0.1, 0.0], vec![0.0, 1.0, 0.0]];
+        db.insert_batch(codebase, &chunks, &embeddings).await.unwrap();
+        assert_eq!(db.count().await.unwrap(), 3);
+
+        let results = db.search(&[1.0, 0.0, 0.0], 2).await.unwrap();
+        assert_eq!(results[0].id, "a");
+        assert!(results[0].score > 0.99);
+
+        let python = db.search_with_extensions(&[1.0, 0.0, 0.0], 3, &[".py".to_string()]).await.unwrap();
+        assert_eq!(python.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), vec!["b"]);
+
+        db.rename(&[("a".to_string(), "a2".to_string())]).await.unwrap();
+        assert_eq!(db.search(&[1.0, 0.0, 0.0], 1).await.unwrap()[0].id, "a2");
+
+        db.delete(&["b".to_string()]).await.unwrap();
+        assert_eq!(db.count().await.unwrap(), 2);
+
+        // Documentation tables share the file without touching these rows
+        let docs = SqliteDatabase::for_codebase(codebase, 2, temp.path(), DOCS_TABLES).unwrap();
+        assert_eq!(docs.count().await.unwrap(), 0);
+
+        drop(db);
+        assert!(matches!(
+            SqliteDatabase::for_codebase(codebase, 4, temp.path(), CODE_TABLES),
+            Err(Error::DimensionMismatch(_))
+        ));
+        let mut db = SqliteDatabase::for_codebase(codebase, 3, temp.path(), CODE_TABLES).unwrap();
+        assert_eq!(db.count().await.unwrap(), 2);
+        assert!(db.has_index(codebase).await.unwrap());
+        db.delete_index(codebase).await.unwrap();
+        assert_eq!(db.count().await.unwrap(), 0);
+        assert!(!db.has_index(codebase).await.unwrap());
+
+        // A saved run counts as an index even when it found nothing to store
+        db.save().await.unwrap();
+        assert!(db.has_index(codebase).await.unwrap());
+        db.delete_index(codebase).await.unwrap();
+        assert!(!db.has_index(codebase).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_metadata_is_copied_into_the_file() {
+        let temp = TempDir::new().unwrap();
+        let codebase = Path::new("/repo");
+        let db = SqliteDatabase::for_codebase(codebase, 3, temp.path(), CODE_TABLES).unwrap();
+
+        let store = MetadataStore::for_codebase(codebase, &temp.path().join("source")).unwrap();
+        store.insert_batch(&[chunk("a", "rs"), chunk("b", "py")]).unwrap();
+        store.set_chunk_id_version(2).unwrap();
+        db.save_metadata(&store).unwrap();
+        store.delete("b").unwrap();
+        db.save_metadata(&store).unwrap();
+
+        let restored = MetadataStore::for_codebase(codebase, &temp.path().join("copy")).unwrap();
+        assert!(db.restore_metadata(&restored).unwrap() > 0);
+        assert_eq!(restored.count(), 1);
+        assert_eq!(restored.get("a").unwrap().unwrap().content, "content of a");
+        assert_eq!(restored.chunk_id_version().unwrap(), 2);
+    }
+}
@@ -1,62 +1,289 @@
 
 use crate::{Error, Result};
-use super::{VectorDatabase, VectorDocument, SearchResult};
+use crate::config::{HnswConfig, VectorMetric, VectorQuantization};
+use crate::integrity;
+use crate::search::PathGlobs;
+use super::{VectorDatabase, VectorDocument, VectorReader, SearchResult};
+use super::usearch_flat::FlatIndex;
+use super::usearch_log::{self, LogEntry};
+use super::usearch_mappings::{self, MappingStore, StoredMappings};
+use crate::types::{CodeChunk, StoreStats};
 use async_trait::async_trait;
+use serde::Deserialize;
 use std::path::{Path, PathBuf};
-use std::collections::HashMap;
-use usearch::Index;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use usearch::{Index, ffi::Matches};
 use usearch::ScalarKind;
 use usearch::MetricKind;
 use sha2::{Sha256, Digest};
+use tracing::warn;
+
+/// Format version of `mappings.json`, which held the mappings of indexes
+/// saved before the mapping store
+const MAPPINGS_FORMAT_VERSION: u32 = 1;
+
+/// Saves leave changes in the log until it holds this share of the index's
+/// vectors (1 in 10), then rewrite the index
+const LOG_COMPACTION_DIVISOR: usize = 10;
+
+/// Changes a log may hold whatever the index size; small indexes are cheap
+/// to rewrite anyway
+const LOG_MIN_ENTRIES: usize = 256;
+
+#[derive(Deserialize)]
+struct LegacyMappings {
+    id_map: HashMap<String, u64>,
+    next_id: u64,
+    /// Vectors in the index file when the mappings were saved; absent in older files
+    #[serde(default)]
+    vector_count: Option<usize>,
+    /// Precision the index stores; absent in files written before quantization
+    #[serde(default)]
+    quantization: VectorQuantization,
+    /// File extension of each vector's chunk; absent in files written before
+    /// filtered search, whose vectors then pass every extension filter
+    #[serde(default)]
+    extensions: HashMap<u64, String>,
+}
+
+/// Where the saved mappings of an index were read from
+enum MappingSource {
+    Store(MappingStore),
+    /// `mappings.json`, with the vectors the index held when it was written
+    /// if the file records them
+    Legacy { vector_count: Option<usize> },
+}
+
+/// Vectors of an index: a flat array scanned by every search while the
+/// index is small enough to search exactly, an HNSW graph once past that
+enum Vectors {
+    Flat(FlatIndex),
+    Graph(Index),
+}
+
+impl Vectors {
+    /// An empty store; flat unless `hnsw` never searches exactly
+    fn new(dimension: usize, quantization: VectorQuantization, metric: VectorMetric, hnsw: HnswConfig) -> Result<Self> {
+        if hnsw.exact_search_max_chunks == 0 {
+            return new_graph(dimension, quantization, metric, hnsw).map(Self::Graph);
+        }
+        Ok(Self::Flat(FlatIndex::new(dimension, quantization, metric)))
+    }
+    
+    fn dimensions(&self) -> usize {
+        match self {
+            Self::Flat(flat) => flat.dimensions(),
+            Self::Graph(index) => index.dimensions(),
+        }
+    }
+    
+    fn size(&self) -> usize {
+        match self {
+            Self::Flat(flat) => flat.size(),
+            Self::Graph(index) => index.size(),
+        }
+    }
+    
+    fn contains(&self, key: u64) -> bool {
+        match self {
+            Self::Flat(flat) => flat.contains(key),
+            Self::Graph(index) => index.contains(key),
+        }
+    }
+    
+    fn reserve(&mut self, capacity: usize) -> Result<()> {
+        match self {
+            Self::Flat(flat) => flat.reserve(capacity),
+            Self::Graph(index) => index
+                .reserve(capacity)
+                .map_err(|e| Error::VectorDb(format!("Failed to reserve capacity: {e:?}")))?,
+        }
+        Ok(())
+    }
+    
+    /// Store `vector` under `key`, replacing the one it held
+    fn add(&mut self, key: u64, vector: &[f32]) -> Result<()> {
+        match self {
+            Self::Flat(flat) => flat.add(key, vector),
+            Self::Graph(index) => add_to_graph(index, key, vector),
+        }
+    }
+    
+    fn remove(&mut self, key: u64) -> Result<()> {
+        match self {
+            Self::Flat(flat) => flat.remove(key),
+            Self::Graph(index) => {
+                index
+                    .remove(key)
+                    .map_err(|e| Error::VectorDb(format!("Failed to remove vector: {e:?}")))?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// The `count` nearest vectors, by a scan when `exact`; a flat store is
+    /// always scanned
+    fn search(&self, query: &[f32], count: usize, exact: bool) -> Result<Matches> {
+        match self {
+            Self::Flat(flat) => Ok(flat.search(query, count, |_| true)),
+            Self::Graph(index) => search_graph(index, query, count, exact),
+        }
+    }
+    
+    /// The `count` nearest vectors `filter` accepts
+    fn filtered_search(&self, query: &[f32], count: usize, filter: impl Fn(u64) -> bool) -> Result<Matches> {
+        match self {
+            Self::Flat(flat) => Ok(flat.search(query, count, filter)),
+            Self::Graph(index) => index
+                .filtered_search(query, count, filter)
+                .map_err(|e| Error::VectorDb(format!("Search failed: {e:?}"))),
+        }
+    }
+    
+    fn save(&self, path: &Path) -> Result<()> {
+        match self {
+            Self::Flat(flat) => flat.save(path),
+            Self::Graph(index) => index
+                .save(&path.to_string_lossy())
+                .map_err(|e| Error::VectorDb(format!("Failed to save index: {e:?}"))),
+        }
+    }
+}
 
 pub struct USearchDatabase {
-    index: Index,
+    index: Vectors,
     path: PathBuf,
     dimension: usize,
+    quantization: VectorQuantization,
+    /// Distance the index compares vectors by; Hamming for binary indexes
+    metric: VectorMetric,
     data_dir: PathBuf,
     id_map: HashMap<String, u64>,
     reverse_id_map: HashMap<u64, String>,
+    /// Lowercase extension, without the dot, of the chunk behind each vector
+    extensions: HashMap<u64, String>,
+    /// Relative path of the file of the chunk behind each vector
+    paths: HashMap<u64, String>,
     next_id: u64,
+    /// Keys of deleted vectors still in the graph: unmapped, so searches skip
+    /// them, until `vacuum` removes them
+    tombstones: HashSet<u64>,
+    /// The index is memory-mapped read-only from `path` rather than loaded
+    viewed: bool,
+    /// Vectors the replayed log added to a memory-mapped index, searched
+    /// next to the mapped file until the first write loads it
+    side: Option<Index>,
+    /// Keys of the mapped file whose vectors the side index replaced
+    shadowed: HashSet<u64>,
+    /// Graph settings; the exact search threshold also decides when a flat
+    /// store builds its graph
+    hnsw: HnswConfig,
+    /// Metadata store of the codebase, which holds the mapping trees
+    mapping_db: sled::Db,
+    /// Tree of the chunk ID behind each key; none before the first save
+    mapping_store: Mutex<Option<MappingStore>>,
+    /// Keys whose mappings changed since the store was last saved
+    changed_keys: Mutex<HashSet<u64>>,
+    /// The next save writes every mapping to a new tree: they were read
+    /// from `mappings.json`, or the index is new
+    rewrite_mappings: AtomicBool,
+    /// Entries in the log on top of the saved index file
+    logged: AtomicUsize,
+    /// The saved index file plus the log reproduce this index; false for a
+    /// new index, after a log replay stopped early, or after a change failed
+    /// half-way, so that the next change or save rewrites the index first
+    base_saved: AtomicBool,
+    /// Changes until the next save build the index from scratch, so they
+    /// skip the log and the save writes the index file instead
+    bulk_load: AtomicBool,
 }
 
 impl USearchDatabase {
-    pub fn new(path: PathBuf, dimension: usize, data_dir: PathBuf) -> Result<Self> {
-        let index = Index::new(&usearch::IndexOptions {
-            dimensions: dimension,
-            metric: MetricKind::Cos,
-            quantization: ScalarKind::F32,
-            connectivity: 16,
-            expansion_add: 128,
-            expansion_search: 64,
-            multi: false,
-        }).map_err(|e| Error::VectorDb(format!("Failed to create index: {e:?}")))?;
+    /// Create an empty index whose mappings go to a tree of `mapping_db`,
+    /// the codebase's metadata store
+    pub fn new(path: PathBuf, dimension: usize, data_dir: PathBuf, mapping_db: &sled::Db) -> Result<Self> {
+        Self::new_quantized(path, dimension, data_dir, mapping_db, VectorQuantization::None, VectorMetric::Cosine, HnswConfig::default())
+    }
+    
+    /// Create an empty index storing vectors at `quantization` precision,
+    /// compared by `metric`, with the graph settings of `hnsw`
+    pub fn new_quantized(
+        path: PathBuf,
+        dimension: usize,
+        data_dir: PathBuf,
+        mapping_db: &sled::Db,
+        quantization: VectorQuantization,
+        metric: VectorMetric,
+        hnsw: HnswConfig,
+    ) -> Result<Self> {
+        let index = Vectors::new(dimension, quantization, metric, hnsw)?;
         
         Ok(Self {
             index,
             path,
             dimension,
+            quantization,
+            metric,
             data_dir,
             id_map: HashMap::new(),
             reverse_id_map: HashMap::new(),
+            extensions: HashMap::new(),
+            paths: HashMap::new(),
             next_id: 0,
+            tombstones: HashSet::new(),
+            viewed: false,
+            side: None,
+            shadowed: HashSet::new(),
+            hnsw,
+            mapping_db: mapping_db.clone(),
+            mapping_store: Mutex::new(None),
+            changed_keys: Mutex::new(HashSet::new()),
+            rewrite_mappings: AtomicBool::new(true),
+            logged: AtomicUsize::new(0),
+            base_saved: AtomicBool::new(false),
+            bulk_load: AtomicBool::new(false),
         })
     }
     
-    pub fn from_file(path: PathBuf, data_dir: PathBuf) -> Result<Self> {
-        let index = Index::new(&usearch::IndexOptions {
-            dimensions: 1536,
-            metric: MetricKind::Cos,
-            quantization: ScalarKind::F32,
-            connectivity: 16,
-            expansion_add: 128,
-            expansion_search: 64,
-            multi: false,
-        }).map_err(|e| Error::VectorDb(format!("Failed to create index: {e:?}")))?;
+    /// Open a saved index; its dimension is the one stored in the file, and
+    /// `dimension` only sizes a missing file
+    pub fn from_file(path: PathBuf, dimension: usize, data_dir: PathBuf, mapping_db: &sled::Db) -> Result<Self> {
+        Self::open_file(path, dimension, data_dir, mapping_db, false)
+    }
+    
+    /// Like `from_file`, memory-mapping the index instead of reading it into
+    /// RAM; the first write loads it fully
+    pub fn view_file(path: PathBuf, dimension: usize, data_dir: PathBuf, mapping_db: &sled::Db) -> Result<Self> {
+        Self::open_file(path, dimension, data_dir, mapping_db, true)
+    }
+    
+    fn open_file(path: PathBuf, dimension: usize, data_dir: PathBuf, mapping_db: &sled::Db, view: bool) -> Result<Self> {
+        // The precision and metric decide how the file is read
+        let mappings = Self::read_mappings(&path, mapping_db)?;
+        let quantization = mappings.as_ref().map(|(mappings, _)| mappings.quantization).unwrap_or_default();
+        let metric = mappings.as_ref().map(|(mappings, _)| mappings.metric).unwrap_or_default();
         
-        if path.exists() {
-            index.load(&path.to_string_lossy())
-                .map_err(|e| Error::VectorDb(format!("Failed to load index: {e:?}")))?;
-        }
+        let hnsw = HnswConfig::default();
+        // A flat store is small, so it is read rather than mapped
+        let mut viewed = false;
+        let index = if !path.exists() {
+            Vectors::new(dimension, quantization, metric, hnsw)?
+        } else if FlatIndex::is_flat_file(&path)? {
+            Vectors::Flat(FlatIndex::load(&path, quantization, metric)?)
+        } else {
+            let index = new_graph(dimension, quantization, metric, hnsw)?;
+            if view {
+                index.view(&path.to_string_lossy())
+                    .map_err(|e| Error::Corrupted(format!("{}: failed to map index ({e:?})", path.display())))?;
+                viewed = true;
+            } else {
+                index.load(&path.to_string_lossy())
+                    .map_err(|e| Error::Corrupted(format!("{}: failed to load index ({e:?})", path.display())))?;
+            }
+            Vectors::Graph(index)
+        };
         
         let dimension = index.dimensions();
         
@@ -64,40 +291,474 @@ impl USearchDatabase {
             index,
             path: path.clone(),
             dimension,
+            quantization,
+            metric,
             data_dir,
             id_map: HashMap::new(),
             reverse_id_map: HashMap::new(),
+            extensions: HashMap::new(),
+            paths: HashMap::new(),
             next_id: 0,
+            tombstones: HashSet::new(),
+            viewed,
+            side: None,
+            shadowed: HashSet::new(),
+            hnsw,
+            mapping_db: mapping_db.clone(),
+            mapping_store: Mutex::new(None),
+            changed_keys: Mutex::new(HashSet::new()),
+            rewrite_mappings: AtomicBool::new(true),
+            logged: AtomicUsize::new(0),
+            base_saved: AtomicBool::new(path.exists()),
+            bulk_load: AtomicBool::new(false),
         };
         
-        db.load_mappings_sync()?;
+        db.apply_mappings(mappings)?;
+        db.replay_log()?;
+        db.check_mappings()?;
         
         Ok(db)
     }
     
-    fn load_mappings_sync(&mut self) -> Result<()> {
-        let mappings_path = self.path.with_extension("mappings.json");
-        if mappings_path.exists() {
-            let mappings_str = std::fs::read_to_string(mappings_path)?;
-            let mappings: serde_json::Value = serde_json::from_str(&mappings_str)?;
-            
-            if let Some(id_map_obj) = mappings.get("id_map").and_then(|v| v.as_object()) {
-                for (key, value) in id_map_obj {
-                    if let Some(id) = value.as_u64() {
-                        self.id_map.insert(key.clone(), id);
-                        self.reverse_id_map.insert(id, key.clone());
+    /// Use the expansion factors and exact search threshold of `hnsw` from
+    /// now on; an existing graph keeps the connectivity it was built with,
+    /// and a flat store holding more vectors than the threshold builds its
+    /// graph with `hnsw`
+    pub fn set_search_settings(&mut self, hnsw: HnswConfig) -> Result<()> {
+        let connectivity = match &self.index {
+            Vectors::Graph(index) => {
+                index.change_expansion_add(hnsw.expansion_add);
+                index.change_expansion_search(hnsw.expansion_search);
+                index.connectivity()
+            }
+            Vectors::Flat(_) => hnsw.connectivity,
+        };
+        self.hnsw = HnswConfig { connectivity, ..hnsw };
+        if self.size() > hnsw.exact_search_max_chunks {
+            self.build_graph()?;
+        }
+        Ok(())
+    }
+    
+    /// Whether searches scan every vector: on small indexes the scan is
+    /// cheap and, unlike the graph walk, never misses a closer vector. Such
+    /// indexes keep their vectors in a flat store, which has no graph to walk.
+    fn searches_exactly(&self) -> bool {
+        matches!(self.index, Vectors::Flat(_)) || self.size() <= self.hnsw.exact_search_max_chunks
+    }
+    
+    /// Move the vectors of a flat store into an HNSW graph, once the index
+    /// grew past the exact search threshold; deleted ones move with them
+    /// until `vacuum`
+    fn build_graph(&mut self) -> Result<()> {
+        let Vectors::Flat(flat) = &self.index else {
+            return Ok(());
+        };
+        let index = new_graph(self.dimension, self.quantization, self.metric, self.hnsw)?;
+        index
+            .reserve(flat.size())
+            .map_err(|e| Error::VectorDb(format!("Failed to reserve capacity: {e:?}")))?;
+        for (key, vector) in flat.iter() {
+            add_to_graph(&index, key, vector)?;
+        }
+        self.index = Vectors::Graph(index);
+        // Written by the next change or save, so reopening does not build it again
+        *self.base_saved.get_mut() = false;
+        Ok(())
+    }
+    
+    /// Vectors held, deleted ones included, counting a key the side index
+    /// replaced once
+    fn size(&self) -> usize {
+        let side = self.side.as_ref().map_or(0, |side| side.size());
+        self.index.size() + side - self.shadowed.len()
+    }
+    
+    /// Whether a vector is stored under `id`
+    pub(crate) fn contains(&self, id: &str) -> bool {
+        self.id_map.contains_key(id)
+    }
+    
+    /// Whether the index is served memory-mapped
+    pub fn is_viewed(&self) -> bool {
+        self.viewed
+    }
+    
+    /// Read a memory-mapped index into RAM so it can be modified, moving
+    /// the vectors of the side index into it
+    fn ensure_loaded(&mut self) -> Result<()> {
+        // Only graphs are mapped
+        let Vectors::Graph(index) = &self.index else {
+            return Ok(());
+        };
+        if !self.viewed {
+            return Ok(());
+        }
+        index
+            .load(&self.path.to_string_lossy())
+            .map_err(|e| Error::Corrupted(format!("{}: failed to load index ({e:?})", self.path.display())))?;
+        self.viewed = false;
+        if self.side.take().is_some() {
+            self.shadowed.clear();
+            // The mappings already hold these changes; only their vectors are redone
+            let (entries, _complete) = usearch_log::read(&self.path)?;
+            let replayed = *self.logged.get_mut();
+            let capacity = self.index.size() + replayed;
+            self.index.reserve(capacity)?;
+            for entry in entries.iter().take(replayed) {
+                self.apply_vector(entry)?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Apply the changes logged since the index file was saved; a
+    /// memory-mapped index gets the added vectors in a side index, so
+    /// it is not read into RAM for them
+    fn replay_log(&mut self) -> Result<()> {
+        // A log without its index file is left over from a removed index
+        if !self.path.exists() {
+            return Ok(());
+        }
+        let log_path = usearch_log::log_path(&self.path);
+        let (entries, complete) = usearch_log::read(&self.path)?;
+        if !entries.is_empty() {
+            let additions = entries.iter().filter(|entry| matches!(entry, LogEntry::Add { .. })).count();
+            if self.viewed {
+                let side = new_graph(self.dimension, self.quantization, self.metric, HnswConfig::default())?;
+                side.reserve(additions)
+                    .map_err(|e| Error::VectorDb(format!("Failed to reserve capacity: {e:?}")))?;
+                self.side = Some(side);
+            } else {
+                let capacity = self.index.size() + additions;
+                self.index.reserve(capacity)?;
+            }
+            for entry in &entries {
+                self.apply(entry).map_err(|e| {
+                    Error::Corrupted(format!("{}: failed to replay change log ({e})", log_path.display()))
+                })?;
+            }
+        }
+        *self.logged.get_mut() = entries.len();
+        if !complete {
+            warn!(
+                "[VECTOR-DB] {}: stopped at an unreadable entry after {} changes; the next save rewrites the index",
+                log_path.display(),
+                entries.len()
+            );
+            *self.base_saved.get_mut() = false;
+        }
+        Ok(())
+    }
+    
+    /// Make the vector side of one change: in the side index while the
+    /// file is memory-mapped
+    fn apply_vector(&mut self, entry: &LogEntry) -> Result<()> {
+        match entry {
+            LogEntry::Add { key, vector, .. } => {
+                if vector.len() != self.dimension {
+                    return Err(Error::VectorDb(format!(
+                        "Vector dimension mismatch: expected {}, got {}",
+                        self.dimension,
+                        vector.len()
+                    )));
+                }
+                // Adding a key again replaces its vector, deleted or not
+                self.tombstones.remove(key);
+                match &self.side {
+                    Some(side) => {
+                        if self.index.contains(*key) {
+                            self.shadowed.insert(*key);
+                        }
+                        add_to_graph(side, *key, vector)?;
                     }
+                    None => self.index.add(*key, vector)?,
                 }
             }
-            
-            if let Some(next_id) = mappings.get("next_id").and_then(|v| v.as_u64()) {
-                self.next_id = next_id;
+            // Removing a vector from the graph is slow, so it is only
+            // unmapped and left to `vacuum`
+            LogEntry::Remove { key } => {
+                if self.index.contains(*key) || self.side.as_ref().is_some_and(|side| side.contains(*key)) {
+                    self.tombstones.insert(*key);
+                }
             }
+            LogEntry::Rename { .. } => {}
+        }
+        Ok(())
+    }
+    
+    /// Make one change to the index and its mappings
+    fn apply(&mut self, entry: &LogEntry) -> Result<()> {
+        let (LogEntry::Add { key, .. } | LogEntry::Remove { key } | LogEntry::Rename { key, .. }) = entry;
+        self.changed_keys.get_mut().unwrap_or_else(|e| e.into_inner()).insert(*key);
+        self.apply_vector(entry)?;
+        match entry {
+            LogEntry::Add { key, id, extension, path, .. } => {
+                self.id_map.insert(id.clone(), *key);
+                self.reverse_id_map.insert(*key, id.clone());
+                match extension {
+                    Some(extension) => self.extensions.insert(*key, extension.clone()),
+                    None => self.extensions.remove(key),
+                };
+                match path {
+                    Some(path) => self.paths.insert(*key, path.clone()),
+                    None => self.paths.remove(key),
+                };
+                self.next_id = self.next_id.max(key + 1);
+            }
+            LogEntry::Remove { key } => {
+                if let Some(id) = self.reverse_id_map.remove(key) {
+                    self.id_map.remove(&id);
+                }
+                self.extensions.remove(key);
+                self.paths.remove(key);
+            }
+            // Only the string mapping changes; the vector keeps its internal key
+            LogEntry::Rename { key, id } => {
+                if let Some(old_id) = self.reverse_id_map.insert(*key, id.clone()) {
+                    self.id_map.remove(&old_id);
+                }
+                self.id_map.insert(id.clone(), *key);
+            }
+        }
+        Ok(())
+    }
+    
+    /// Make `entries` and append them to the log before returning, so they
+    /// survive a crash before the next save
+    fn apply_and_log(&mut self, entries: Vec<LogEntry>) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        for entry in &entries {
+            if let Err(e) = self.apply(entry) {
+                // The index now holds changes the log lacks
+                *self.base_saved.get_mut() = false;
+                return Err(e);
+            }
+        }
+        // A build interrupted by a crash starts over, so its vectors are only
+        // written once, by the save that ends it
+        if *self.bulk_load.get_mut() {
+            *self.base_saved.get_mut() = false;
+            return Ok(());
+        }
+        // The log only applies on top of a saved base, so write one instead
+        if !*self.base_saved.get_mut() || !self.path.exists() {
+            return self.write_base();
+        }
+        if let Err(e) = usearch_log::append(&usearch_log::log_path(&self.path), &entries) {
+            *self.base_saved.get_mut() = false;
+            return Err(e);
+        }
+        *self.logged.get_mut() += entries.len();
+        Ok(())
+    }
+    
+    /// Rewrite the index file and its mappings from memory and empty the log
+    fn write_base(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        
+        // Written aside and renamed over the old file, so searches that mapped
+        // it keep reading the old contents instead of a file being rewritten
+        let temp_path = self.path.with_extension("usearch.tmp");
+        self.index.save(&temp_path)?;
+        std::fs::rename(&temp_path, &self.path)?;
+        
+        // Saved after the index file: until then, the mappings saved with the
+        // previous file plus the log still describe the new one
+        self.save_mappings()?;
+        let legacy_path = self.path.with_extension("mappings.json");
+        if legacy_path.exists() {
+            std::fs::remove_file(&legacy_path)?;
+        }
+        
+        // The rewritten index holds every logged change
+        usearch_log::remove(&self.path)?;
+        self.logged.store(0, Ordering::SeqCst);
+        self.base_saved.store(true, Ordering::SeqCst);
+        
+        Ok(())
+    }
+    
+    /// Save the mappings changed since the last save to the mapping store
+    fn save_mappings(&self) -> Result<()> {
+        let mut store = self.mapping_store.lock().unwrap_or_else(|e| e.into_inner());
+        let mut changed_keys = self.changed_keys.lock().unwrap_or_else(|e| e.into_inner());
+        // Every mapping goes to a new tree, which the index switches to once it is written
+        let keys: Vec<u64> = if self.rewrite_mappings.load(Ordering::SeqCst) || store.is_none() {
+            *store = Some(MappingStore::create(&self.mapping_db)?);
+            self.reverse_id_map.keys().chain(&self.tombstones).copied().collect()
+        } else {
+            changed_keys.iter().copied().collect()
+        };
+        let Some(store) = store.as_ref() else {
+            return Ok(());
+        };
+        store.save(
+            &self.path,
+            keys,
+            &self.reverse_id_map,
+            &self.extensions,
+            &self.paths,
+            &self.tombstones,
+            self.next_id,
+            self.quantization,
+            self.metric,
+        )?;
+        changed_keys.clear();
+        self.rewrite_mappings.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+    
+    /// Mappings saved for the index at `index_path`: from its tree of
+    /// `mapping_db`, or from `mappings.json` for an index saved before the store
+    fn read_mappings(index_path: &Path, mapping_db: &sled::Db) -> Result<Option<(StoredMappings, MappingSource)>> {
+        if let Some(store) = MappingStore::open(mapping_db, index_path)? {
+            return Ok(store.load()?.map(|mappings| (mappings, MappingSource::Store(store))));
         }
         
+        let legacy_path = index_path.with_extension("mappings.json");
+        let Some(legacy) = integrity::read_checked::<LegacyMappings>(&legacy_path, MAPPINGS_FORMAT_VERSION)? else {
+            return Ok(None);
+        };
+        let mappings = StoredMappings {
+            ids: legacy.id_map.into_iter().map(|(id, key)| (key, id)).collect(),
+            extensions: legacy.extensions,
+            paths: HashMap::new(),
+            next_id: legacy.next_id,
+            tombstones: HashSet::new(),
+            quantization: legacy.quantization,
+            metric: VectorMetric::Cosine,
+        };
+        Ok(Some((mappings, MappingSource::Legacy { vector_count: legacy.vector_count })))
+    }
+    
+    fn apply_mappings(&mut self, mappings: Option<(StoredMappings, MappingSource)>) -> Result<()> {
+        let Some((mappings, source)) = mappings else {
+            return Ok(());
+        };
+        
+        match source {
+            MappingSource::Store(store) => {
+                *self.rewrite_mappings.get_mut() = false;
+                *self.mapping_store.get_mut().unwrap_or_else(|e| e.into_inner()) = Some(store);
+            }
+            // The JSON file was saved after the index; a crash in between
+            // leaves vectors that no mapping resolves
+            MappingSource::Legacy { vector_count } => {
+                if let Some(vector_count) = vector_count.filter(|&count| count != self.index.size()) {
+                    return Err(Error::Corrupted(format!(
+                        "{}: mappings were saved for {} vectors but the index holds {}",
+                        self.path.with_extension("mappings.json").display(),
+                        vector_count,
+                        self.index.size()
+                    )));
+                }
+                *self.rewrite_mappings.get_mut() = true;
+            }
+        }
+        
+        for (key, id) in mappings.ids {
+            self.id_map.insert(id.clone(), key);
+            self.reverse_id_map.insert(key, id);
+        }
+        self.extensions = mappings.extensions;
+        self.paths = mappings.paths;
+        // Vacuumed ones are gone from an index file saved just before a crash
+        self.tombstones = mappings.tombstones.into_iter().filter(|&key| self.index.contains(key)).collect();
+        self.next_id = mappings.next_id;
+        
         Ok(())
     }
     
+    /// Verify that, after the log replay, every vector has exactly one chunk
+    /// ID in the mapping store or is a tombstone
+    fn check_mappings(&mut self) -> Result<()> {
+        let mapped = self.reverse_id_map.len() + self.tombstones.len();
+        if self.mapping_store.get_mut().unwrap_or_else(|e| e.into_inner()).is_none() || mapped == self.size() {
+            return Ok(());
+        }
+        Err(Error::Corrupted(format!(
+            "{}: mappings hold {} chunk IDs and {} deleted vectors but the index holds {} vectors",
+            usearch_mappings::mappings_path(&self.path).display(),
+            self.reverse_id_map.len(),
+            self.tombstones.len(),
+            self.size()
+        )))
+    }
+    
+    /// Move the index and its mappings aside after they failed verification
+    fn quarantine_files(index_path: &Path, mapping_db: &sled::Db) -> Result<()> {
+        // Nothing names its mapping tree once the file pointing to it is moved
+        if let Some(store) = MappingStore::open(mapping_db, index_path).ok().flatten() {
+            store.remove()?;
+        }
+        for path in [
+            index_path.to_path_buf(),
+            usearch_mappings::mappings_path(index_path),
+            index_path.with_extension("mappings.json"),
+            usearch_log::log_path(index_path),
+            usearch_log::legacy_log_path(index_path),
+        ] {
+            if path.exists() {
+                let moved = integrity::quarantine(&path)?;
+                warn!("[VECTOR-DB] Quarantined {} as {}", path.display(), moved.display());
+            }
+        }
+        Ok(())
+    }
+    
+    /// Keys and distances of `results` from the mapped file merged with
+    /// those of the side index, nearest first; keys the side index replaced
+    /// are taken from it
+    fn with_side_results(&self, results: Matches, side_results: Option<Matches>) -> (Vec<u64>, Vec<f32>) {
+        let Some(side_results) = side_results else {
+            return (results.keys, results.distances);
+        };
+        let mut merged: Vec<(u64, f32)> = results
+            .keys
+            .into_iter()
+            .zip(results.distances)
+            .filter(|(key, _)| !self.shadowed.contains(key))
+            .chain(side_results.keys.into_iter().zip(side_results.distances))
+            .collect();
+        merged.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        merged.into_iter().unzip()
+    }
+    
+    fn to_search_results(&self, keys: &[u64], distances: &[f32]) -> Vec<SearchResult> {
+        let mut search_results = Vec::new();
+        
+        for (internal_id, distance) in keys.iter().zip(distances.iter()) {
+            if let Some(string_id) = self.reverse_id_map.get(internal_id) {
+                let score = match (self.quantization, self.metric) {
+                    // Share of differing signs, mapped onto the cosine range
+                    (VectorQuantization::Binary, _) => 1.0 - 2.0 * distance / self.dimension as f32,
+                    // Half the squared distance of unit vectors is 1 - cosine
+                    (_, VectorMetric::L2) => 1.0 - distance / 2.0,
+                    // usearch reports 1 - cosine and 1 - inner product
+                    _ => 1.0 - distance,
+                };
+                
+                search_results.push(SearchResult {
+                    id: string_id.clone(),
+                    score,
+                });
+            }
+        }
+        
+        search_results
+    }
+    
+    /// Changes the log, and deleted vectors the graph, may hold before the
+    /// index is rewritten
+    fn compaction_threshold(&self) -> usize {
+        (self.index.size() / LOG_COMPACTION_DIVISOR).max(LOG_MIN_ENTRIES)
+    }
+    
     fn get_or_create_internal_id(&mut self, string_id: &str) -> u64 {
         if let Some(&id) = self.id_map.get(string_id) {
             id
@@ -109,37 +770,37 @@ impl USearchDatabase {
             id
         }
     }
-}
-
-#[async_trait]
-impl VectorDatabase for USearchDatabase {
-    async fn insert(&mut self, documents: Vec<VectorDocument>) -> Result<()> {
-        let current_size = self.index.size();
-        let needed_capacity = current_size + documents.len();
-        
-        self.index
-            .reserve(needed_capacity)
-            .map_err(|e| Error::VectorDb(format!("Failed to reserve capacity: {e:?}")))?;
-        
+    
+    /// Store `vectors`, each with its ID and the extension and relative path
+    /// of its chunk's file
+    fn add_vectors(&mut self, vectors: Vec<(String, Vec<f32>, Option<(String, String)>)>) -> Result<()> {
+        self.ensure_loaded()?;
+        let needed_capacity = self.index.size() + vectors.len();
+        if needed_capacity > self.hnsw.exact_search_max_chunks {
+            self.build_graph()?;
+        }
+        self.index.reserve(needed_capacity)?;
         
-        for doc in documents.iter() {
-            if doc.vector.len() != self.dimension {
+        let mut entries = Vec::with_capacity(vectors.len());
+        for (id, vector, file) in vectors {
+            if vector.len() != self.dimension {
                 return Err(Error::VectorDb(format!(
                     "Vector dimension mismatch: expected {}, got {}",
                     self.dimension,
-                    doc.vector.len()
+                    vector.len()
                 )));
             }
             
-            let internal_id = self.get_or_create_internal_id(&doc.id);
-            
-            self.index
-                .add(internal_id, &doc.vector)
-                .map_err(|e| Error::VectorDb(format!("Failed to add vector: {e:?}")))?;
-                
+            let key = self.get_or_create_internal_id(&id);
+            let (extension, path) = file.unzip();
+            entries.push(LogEntry::Add { key, id, extension, path, vector });
         }
-        Ok(())
+        self.apply_and_log(entries)
     }
+}
+
+#[async_trait]
+impl VectorReader for USearchDatabase {
     
     async fn search(&self, query_vector: &[f32], top_k: usize) -> Result<Vec<SearchResult>> {
         if query_vector.len() != self.dimension {
@@ -150,109 +811,439 @@ impl VectorDatabase for USearchDatabase {
             )));
         }
         
-        let results = self.index
-            .search(query_vector, top_k)
-            .map_err(|e| Error::VectorDb(format!("Search failed: {e:?}")))?;
+        // Deleted and replaced vectors may take some of the places, so as many more are asked for
+        let count = top_k + self.tombstones.len() + self.shadowed.len();
+        let exact = self.searches_exactly();
+        let results = self.index.search(query_vector, count, exact)?;
+        let side_results = self.side.as_ref().map(|side| search_graph(side, query_vector, count, exact)).transpose()?;
         
-        let mut search_results = Vec::new();
+        let (keys, distances) = self.with_side_results(results, side_results);
+        let mut search_results = self.to_search_results(&keys, &distances);
+        search_results.truncate(top_k);
+        Ok(search_results)
+    }
+    
+    async fn search_with_extensions(
+        &self,
+        query_vector: &[f32],
+        top_k: usize,
+        extensions: &[String],
+    ) -> Result<Vec<SearchResult>> {
+        self.search_filtered(query_vector, top_k, extensions, &PathGlobs::default()).await
+    }
+    
+    async fn search_filtered(
+        &self,
+        query_vector: &[f32],
+        top_k: usize,
+        extensions: &[String],
+        path_globs: &PathGlobs,
+    ) -> Result<Vec<SearchResult>> {
+        if extensions.is_empty() && path_globs.is_empty() {
+            return self.search(query_vector, top_k).await;
+        }
+        let extensions: Vec<String> = extensions
+            .iter()
+            .map(|extension| extension.trim_start_matches('.').to_lowercase())
+            .collect();
         
-        for match_result in results.keys.iter().zip(results.distances.iter()) {
-            let (internal_id, distance) = match_result;
-            
-            if let Some(string_id) = self.reverse_id_map.get(internal_id) {
-                let score = 1.0 - distance;
-                
-                search_results.push(SearchResult {
-                    id: string_id.clone(),
-                    score,
-                });
-            }
+        // Vectors saved before their file was recorded pass every filter
+        let matches = |key: u64| {
+            !self.tombstones.contains(&key)
+                && (extensions.is_empty()
+                    || self.extensions.get(&key).is_none_or(|extension| extensions.contains(extension)))
+                && (path_globs.is_empty() || self.paths.get(&key).is_none_or(|path| path_globs.matches(path)))
+        };
+        
+        // The scan ranks every vector, so the best matching ones are kept
+        if self.searches_exactly() {
+            let results = self.index.search(query_vector, self.index.size(), true)?;
+            let side_results = self
+                .side
+                .as_ref()
+                .map(|side| search_graph(side, query_vector, side.size(), true))
+                .transpose()?;
+            let (keys, distances) = self.with_side_results(results, side_results);
+            let (keys, distances): (Vec<u64>, Vec<f32>) = keys
+                .into_iter()
+                .zip(distances)
+                .filter(|(key, _)| matches(*key))
+                .take(top_k)
+                .unzip();
+            return Ok(self.to_search_results(&keys, &distances));
         }
         
+        // The graph walk skips other files, so top_k counts matching chunks only
+        let results = self.index.filtered_search(query_vector, top_k, |key| matches(key) && !self.shadowed.contains(&key))?;
+        let side_results = match &self.side {
+            Some(side) => Some(
+                side.filtered_search(query_vector, top_k, matches)
+                    .map_err(|e| Error::VectorDb(format!("Search failed: {e:?}")))?,
+            ),
+            None => None,
+        };
+        
+        let (keys, distances) = self.with_side_results(results, side_results);
+        let mut search_results = self.to_search_results(&keys, &distances);
+        search_results.truncate(top_k);
         Ok(search_results)
     }
     
+    async fn count(&self) -> Result<usize> {
+        Ok(self.size() - self.tombstones.len())
+    }
+    
+    async fn stats(&self) -> Result<StoreStats> {
+        let mut stats = StoreStats::on_disk(&self.path);
+        stats.add(&StoreStats::on_disk(&usearch_mappings::mappings_path(&self.path)));
+        stats.add(&StoreStats::on_disk(&self.path.with_extension("mappings.json")));
+        stats.add(&StoreStats::on_disk(&usearch_log::log_path(&self.path)));
+        stats.add(&StoreStats::on_disk(&usearch_log::legacy_log_path(&self.path)));
+        stats.entries = self.size() - self.tombstones.len();
+        stats.dimension = Some(self.dimension);
+        Ok(stats)
+    }
+    
+    fn quantization(&self) -> VectorQuantization {
+        self.quantization
+    }
+    
+    fn metric(&self) -> VectorMetric {
+        self.metric
+    }
+    
+    async fn has_index(&self, codebase_path: &Path) -> Result<bool> {
+        let index_path = Self::get_index_path_for_codebase(codebase_path, &self.data_dir);
+        Ok(index_path.exists())
+    }
+}
+
+#[async_trait]
+impl VectorDatabase for USearchDatabase {
+    async fn insert(&mut self, documents: Vec<VectorDocument>) -> Result<()> {
+        self.add_vectors(documents.into_iter().map(|doc| (doc.id, doc.vector, None)).collect())
+    }
+    
+    async fn insert_batch(
+        &mut self,
+        _codebase_path: &Path,
+        chunks: &[CodeChunk],
+        embeddings: &[Vec<f32>],
+    ) -> Result<()> {
+        tracing::info!("[VECTOR-DB] Inserting {} vectors", chunks.len());
+        self.add_vectors(
+            chunks
+                .iter()
+                .zip(embeddings)
+                .map(|(chunk, embedding)| {
+                    let extension = chunk.metadata.file_extension.trim_start_matches('.').to_lowercase();
+                    (chunk.id.clone(), embedding.clone(), Some((extension, chunk.relative_path.clone())))
+                })
+                .collect(),
+        )
+    }
+    
+    /// An existing ID keeps its key, and adding the key again replaces its vector
+    async fn upsert_batch(
+        &mut self,
+        codebase_path: &Path,
+        chunks: &[CodeChunk],
+        embeddings: &[Vec<f32>],
+    ) -> Result<()> {
+        self.insert_batch(codebase_path, chunks, embeddings).await
+    }
+    
     async fn delete(&mut self, ids: &[String]) -> Result<()> {
-        for id in ids {
-            if let Some(&internal_id) = self.id_map.get(id) {
-                self.index
-                    .remove(internal_id)
-                    .map_err(|e| Error::VectorDb(format!("Failed to remove vector: {e:?}")))?;
-                
-                self.id_map.remove(id);
-                self.reverse_id_map.remove(&internal_id);
-            }
-        }
+        self.ensure_loaded()?;
+        let entries = ids
+            .iter()
+            .filter_map(|id| self.id_map.get(id))
+            .map(|&key| LogEntry::Remove { key })
+            .collect();
+        self.apply_and_log(entries)?;
         
+        // Deleted vectors still cost memory and search time, so they are
+        // bounded like the log when no vacuum comes around
+        if self.tombstones.len() > self.compaction_threshold() {
+            self.vacuum().await?;
+        }
         Ok(())
     }
     
-    async fn count(&self) -> Result<usize> {
-        Ok(self.index.size())
+    async fn rename(&mut self, renames: &[(String, String)]) -> Result<()> {
+        self.ensure_loaded()?;
+        for (old_id, new_id) in renames {
+            if old_id == new_id {
+                continue;
+            }
+            
+            if self.id_map.contains_key(new_id) {
+                self.delete(std::slice::from_ref(old_id)).await?;
+                continue;
+            }
+            
+            if let Some(&key) = self.id_map.get(old_id) {
+                self.apply_and_log(vec![LogEntry::Rename { key, id: new_id.clone() }])?;
+            }
+        }
+        
+        Ok(())
     }
     
     async fn save(&self) -> Result<()> {
-        if let Some(parent) = self.path.parent() {
-            std::fs::create_dir_all(parent)?;
+        // Nothing changed since the file was mapped
+        if self.viewed {
+            return Ok(());
         }
         
-        self.index
-            .save(&self.path.to_string_lossy())
-            .map_err(|e| Error::VectorDb(format!("Failed to save index: {e:?}")))?;
-        
-        let mappings_path = self.path.with_extension("mappings.json");
-        let mappings = serde_json::json!({
-            "id_map": self.id_map,
-            "next_id": self.next_id,
-        });
-        
-        std::fs::write(mappings_path, serde_json::to_string_pretty(&mappings)?)?;
+        // Changes reach the log as they are made; saving only folds a log
+        // that outgrew its share of the index back into the index file, or
+        // moves mappings read from `mappings.json` to the store
+        if self.base_saved.load(Ordering::SeqCst)
+            && self.path.exists()
+            && self.logged.load(Ordering::SeqCst) <= self.compaction_threshold()
+            && !self.rewrite_mappings.load(Ordering::SeqCst)
+        {
+            return Ok(());
+        }
         
+        self.write_base()?;
+        self.bulk_load.store(false, Ordering::SeqCst);
         Ok(())
     }
     
+    fn begin_bulk_load(&mut self) {
+        *self.bulk_load.get_mut() = true;
+    }
+    
+    /// Remove the deleted vectors from the graph and rewrite the index
+    async fn vacuum(&mut self) -> Result<usize> {
+        if self.tombstones.is_empty() {
+            return Ok(0);
+        }
+        self.ensure_loaded()?;
+        let keys: Vec<u64> = self.tombstones.iter().copied().collect();
+        for &key in &keys {
+            if let Err(e) = self.index.remove(key) {
+                // The index now lacks vectors its saved base holds
+                *self.base_saved.get_mut() = false;
+                return Err(e);
+            }
+            self.tombstones.remove(&key);
+            self.changed_keys.get_mut().unwrap_or_else(|e| e.into_inner()).insert(key);
+        }
+        self.write_base()?;
+        Ok(keys.len())
+    }
+    
     async fn load(&mut self) -> Result<()> {
         if !self.path.exists() {
             return Err(Error::VectorDb("Index file does not exist".to_string()));
         }
         
-        self.index
-            .load(&self.path.to_string_lossy())
-            .map_err(|e| Error::VectorDb(format!("Failed to load index: {e:?}")))?;
+        self.index = if FlatIndex::is_flat_file(&self.path)? {
+            Vectors::Flat(FlatIndex::load(&self.path, self.quantization, self.metric)?)
+        } else {
+            let index = new_graph(self.dimension, self.quantization, self.metric, self.hnsw)?;
+            index
+                .load(&self.path.to_string_lossy())
+                .map_err(|e| Error::Corrupted(format!("{}: failed to load index ({e:?})", self.path.display())))?;
+            Vectors::Graph(index)
+        };
+        self.viewed = false;
+        self.side = None;
+        self.shadowed.clear();
         
         self.id_map.clear();
         self.reverse_id_map.clear();
+        self.extensions.clear();
+        self.paths.clear();
+        self.tombstones.clear();
         self.next_id = 0;
+        *self.mapping_store.get_mut().unwrap_or_else(|e| e.into_inner()) = None;
+        self.changed_keys.get_mut().unwrap_or_else(|e| e.into_inner()).clear();
+        *self.rewrite_mappings.get_mut() = true;
+        *self.base_saved.get_mut() = true;
+        
+        let mappings = Self::read_mappings(&self.path, &self.mapping_db)?;
+        self.apply_mappings(mappings)?;
+        self.replay_log()?;
+        self.check_mappings()
+    }
+    
+    async fn delete_index(&mut self, codebase_path: &Path) -> Result<()> {
+        *self.mapping_store.get_mut().unwrap_or_else(|e| e.into_inner()) = None;
+        let index_path = Self::get_index_path_for_codebase(codebase_path, &self.data_dir);
+        if let Some(index_dir) = index_path.parent() {
+            usearch_mappings::drop_named(&self.mapping_db, index_dir)?;
+        }
+        Self::remove_for_codebase(codebase_path, &self.data_dir)?;
+        
+        self.index = Vectors::new(self.dimension, self.quantization, self.metric, self.hnsw)?;
+        self.viewed = false;
+        self.side = None;
+        self.shadowed.clear();
+        self.id_map.clear();
+        self.reverse_id_map.clear();
+        self.extensions.clear();
+        self.paths.clear();
+        self.tombstones.clear();
+        self.next_id = 0;
+        self.changed_keys.get_mut().unwrap_or_else(|e| e.into_inner()).clear();
+        *self.rewrite_mappings.get_mut() = true;
+        *self.logged.get_mut() = 0;
+        *self.base_saved.get_mut() = false;
+        
+        Ok(())
+    }
+}
+
+impl USearchDatabase {
+    pub(crate) fn get_index_path_for_codebase(codebase_path: &Path, data_dir: &Path) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.update(codebase_path.to_string_lossy().as_bytes());
+        let hash = format!("{:x}", hasher.finalize());
+        
+        data_dir
+            .join("vectors")
+            .join(&hash[..16])
+            .join("index.usearch")
+    }
+    
+    /// Index of the codebase's documentation chunks when they are embedded
+    /// with a separate model; it shares the code index's directory
+    pub(crate) fn get_docs_index_path_for_codebase(codebase_path: &Path, data_dir: &Path) -> PathBuf {
+        Self::get_index_path_for_codebase(codebase_path, data_dir).with_file_name("docs.usearch")
+    }
+    
+    /// Open the codebase's index, or create an empty one of `dimension`
+    ///
+    /// Returns `Error::DimensionMismatch` when the stored vectors have another
+    /// dimension than `dimension` (0 accepts any): they cannot be compared with
+    /// embeddings from the configured model.
+    pub fn for_codebase(codebase_path: &Path, dimension: usize, data_dir: &Path, mapping_db: &sled::Db) -> Result<Self> {
+        Self::for_codebase_quantized(
+            codebase_path,
+            dimension,
+            data_dir,
+            mapping_db,
+            VectorQuantization::None,
+            VectorMetric::Cosine,
+            HnswConfig::default(),
+        )
+    }
+    
+    /// Like `for_codebase`, creating a missing index at `quantization`
+    /// precision compared by `metric`; an existing index keeps the precision,
+    /// metric and connectivity it was built with, but searches with the
+    /// expansion factors of `hnsw`
+    pub fn for_codebase_quantized(
+        codebase_path: &Path,
+        dimension: usize,
+        data_dir: &Path,
+        mapping_db: &sled::Db,
+        quantization: VectorQuantization,
+        metric: VectorMetric,
+        hnsw: HnswConfig,
+    ) -> Result<Self> {
+        let index_path = Self::get_index_path_for_codebase(codebase_path, data_dir);
+        Self::open_for_codebase(index_path, codebase_path, dimension, data_dir, mapping_db, quantization, metric, hnsw, false, true)
+    }
+    
+    /// Like `for_codebase_quantized`, memory-mapping an existing index for
+    /// searches instead of reading it into RAM
+    pub fn view_for_codebase(
+        codebase_path: &Path,
+        dimension: usize,
+        data_dir: &Path,
+        mapping_db: &sled::Db,
+        quantization: VectorQuantization,
+        metric: VectorMetric,
+        hnsw: HnswConfig,
+    ) -> Result<Self> {
+        let index_path = Self::get_index_path_for_codebase(codebase_path, data_dir);
+        Self::open_for_codebase(index_path, codebase_path, dimension, data_dir, mapping_db, quantization, metric, hnsw, true, true)
+    }
+    
+    /// Like `for_codebase_quantized`, for the index of documentation chunks
+    pub fn for_codebase_docs(
+        codebase_path: &Path,
+        dimension: usize,
+        data_dir: &Path,
+        mapping_db: &sled::Db,
+        quantization: VectorQuantization,
+        metric: VectorMetric,
+        hnsw: HnswConfig,
+    ) -> Result<Self> {
+        let index_path = Self::get_docs_index_path_for_codebase(codebase_path, data_dir);
+        Self::open_for_codebase(index_path, codebase_path, dimension, data_dir, mapping_db, quantization, metric, hnsw, false, true)
+    }
+    
+    /// File of shard `shard` of the index at `index_path`; shard 0 is the
+    /// index file itself, so an unsharded index is a one-shard index
+    pub(crate) fn shard_path(index_path: &Path, shard: usize) -> PathBuf {
+        if shard == 0 {
+            return index_path.to_path_buf();
+        }
+        index_path.with_extension(format!("shard-{shard}.usearch"))
+    }
+    
+    /// Open the index at `index_path`, or create an empty one; the checks of
+    /// `for_codebase` apply. With `recover`, an index that fails verification
+    /// is quarantined; without, it is left in place for read-only reports
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn open_for_codebase(
+        index_path: PathBuf,
+        codebase_path: &Path,
+        dimension: usize,
+        data_dir: &Path,
+        mapping_db: &sled::Db,
+        quantization: VectorQuantization,
+        metric: VectorMetric,
+        hnsw: HnswConfig,
+        view: bool,
+        recover: bool,
+    ) -> Result<Self> {
+        if !index_path.exists() {
+            return Self::new_quantized(index_path, dimension, data_dir.to_path_buf(), mapping_db, quantization, metric, hnsw);
+        }
         
-        let mappings_path = self.path.with_extension("mappings.json");
-        if mappings_path.exists() {
-            let mappings_str = std::fs::read_to_string(mappings_path)?;
-            let mappings: serde_json::Value = serde_json::from_str(&mappings_str)?;
-            
-            if let Some(id_map_obj) = mappings.get("id_map").and_then(|v| v.as_object()) {
-                for (key, value) in id_map_obj {
-                    if let Some(id) = value.as_u64() {
-                        self.id_map.insert(key.clone(), id);
-                        self.reverse_id_map.insert(id, key.clone());
-                    }
-                }
-            }
-            
-            if let Some(next_id) = mappings.get("next_id").and_then(|v| v.as_u64()) {
-                self.next_id = next_id;
+        let mut db = match Self::open_file(index_path.clone(), dimension, data_dir.to_path_buf(), mapping_db, view) {
+            Err(Error::Corrupted(reason)) if recover => {
+                warn!("[VECTOR-DB] Vector index failed verification: {}", reason);
+                Self::quarantine_files(&index_path, mapping_db)?;
+                return Err(Error::Corrupted(format!(
+                    "{reason}. The vector index was quarantined; the next index_codebase run rebuilds it"
+                )));
             }
+            loaded => loaded?,
+        };
+        
+        if dimension == 0 || db.dimension == dimension {
+            db.set_search_settings(hnsw)?;
+            return Ok(db);
         }
         
-        Ok(())
-    }
-    
-    async fn has_index(&self, codebase_path: &Path) -> Result<bool> {
-        let index_path = Self::get_index_path_for_codebase(codebase_path, &self.data_dir);
-        Ok(index_path.exists())
+        // Nothing stored yet, so nothing is lost by resizing
+        if db.index.size() == 0 {
+            return Self::new_quantized(index_path, dimension, data_dir.to_path_buf(), mapping_db, quantization, metric, hnsw);
+        }
+        
+        Err(Error::DimensionMismatch(format!(
+            "the vector index of {} holds {} vectors of dimension {}, but the configured embedding model produces dimension {}. \
+             Re-index with force=true to rebuild it with the current model, or set EMBEDDING_PROVIDER/EMBEDDING_MODEL back to the model that built it",
+            codebase_path.display(),
+            db.index.size(),
+            db.dimension,
+            dimension
+        )))
     }
     
-    async fn delete_index(&mut self, codebase_path: &Path) -> Result<()> {
-        let index_path = Self::get_index_path_for_codebase(codebase_path, &self.data_dir);
+    /// Delete the codebase's index files without opening them, e.g. when
+    /// their dimension no longer matches the configured model. Their mapping
+    /// trees stay in the metadata store until `drop_unreferenced_mappings`.
+    pub fn remove_for_codebase(codebase_path: &Path, data_dir: &Path) -> Result<()> {
+        let index_path = Self::get_index_path_for_codebase(codebase_path, data_dir);
         
         if let Some(index_dir) = index_path.parent() {
             if index_dir.exists() {
@@ -260,49 +1251,104 @@ impl VectorDatabase for USearchDatabase {
             }
         }
         
-        self.id_map.clear();
-        self.reverse_id_map.clear();
-        self.next_id = 0;
-        
         Ok(())
     }
-}
-
-impl USearchDatabase {
-    fn get_index_path_for_codebase(codebase_path: &Path, data_dir: &Path) -> PathBuf {
-        let mut hasher = Sha256::new();
-        hasher.update(codebase_path.to_string_lossy().as_bytes());
-        let hash = format!("{:x}", hasher.finalize());
-        
-        data_dir
-            .join("vectors")
-            .join(&hash[..16])
-            .join("index.usearch")
-    }
     
-    pub fn for_codebase(codebase_path: &Path, dimension: usize, data_dir: &Path) -> Result<Self> {
+    /// Drop the mapping trees of `mapping_db` that none of the codebase's
+    /// index files name, once the files were removed or replaced; nothing
+    /// may be writing the codebase's indexes meanwhile
+    pub fn drop_unreferenced_mappings(codebase_path: &Path, data_dir: &Path, mapping_db: &sled::Db) -> Result<()> {
         let index_path = Self::get_index_path_for_codebase(codebase_path, data_dir);
-        
-        if index_path.exists() {
-            Self::from_file(index_path, data_dir.to_path_buf())
-        } else {
-            Self::new(index_path, dimension, data_dir.to_path_buf())
+        match index_path.parent() {
+            Some(index_dir) => usearch_mappings::drop_unreferenced(mapping_db, index_dir),
+            None => Ok(()),
         }
     }
 }
 
+/// Empty HNSW graph laid out by `index_options`
+fn new_graph(dimension: usize, quantization: VectorQuantization, metric: VectorMetric, hnsw: HnswConfig) -> Result<Index> {
+    Index::new(&index_options(dimension, quantization, metric, hnsw))
+        .map_err(|e| Error::VectorDb(format!("Failed to create index: {e:?}")))
+}
+
+/// Store `vector` under `key` in the graph, replacing the one it held
+fn add_to_graph(index: &Index, key: u64, vector: &[f32]) -> Result<()> {
+    if index.contains(key) {
+        index
+            .remove(key)
+            .map_err(|e| Error::VectorDb(format!("Failed to remove vector: {e:?}")))?;
+    }
+    index
+        .add(key, vector)
+        .map_err(|e| Error::VectorDb(format!("Failed to add vector: {e:?}")))
+}
+
+/// The `count` nearest vectors of the graph, by a scan when `exact`
+fn search_graph(index: &Index, query: &[f32], count: usize, exact: bool) -> Result<Matches> {
+    let results = if exact { index.exact_search(query, count) } else { index.search(query, count) };
+    results.map_err(|e| Error::VectorDb(format!("Search failed: {e:?}")))
+}
+
+/// Index layout for `quantization` and `metric`: binary vectors are
+/// compared by Hamming distance, the others by `metric`
+fn index_options(
+    dimension: usize,
+    quantization: VectorQuantization,
+    metric: VectorMetric,
+    hnsw: HnswConfig,
+) -> usearch::IndexOptions {
+    let metric_kind = match metric {
+        VectorMetric::Cosine => MetricKind::Cos,
+        VectorMetric::Dot => MetricKind::IP,
+        VectorMetric::L2 => MetricKind::L2sq,
+    };
+    let (metric, scalar) = match quantization {
+        VectorQuantization::None => (metric_kind, ScalarKind::F32),
+        VectorQuantization::Int8 => (metric_kind, ScalarKind::I8),
+        VectorQuantization::Binary => (MetricKind::Hamming, ScalarKind::B1),
+    };
+    usearch::IndexOptions {
+        dimensions: dimension,
+        metric,
+        quantization: scalar,
+        connectivity: hnsw.connectivity,
+        expansion_add: hnsw.expansion_add,
+        expansion_search: hnsw.expansion_search,
+        multi: false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::tempdir;
     
+    /// Metadata store holding the mapping trees of a test's indexes
+    fn mapping_db(data_dir: &Path) -> sled::Db {
+        sled::open(data_dir.join("metadata")).unwrap()
+    }
+    
+    /// Settings that build a graph from the first vector on
+    fn graph_only() -> HnswConfig {
+        HnswConfig { exact_search_max_chunks: 0, ..HnswConfig::default() }
+    }
+    
+    fn graph(db: &USearchDatabase) -> &Index {
+        match &db.index {
+            Vectors::Graph(index) => index,
+            Vectors::Flat(_) => panic!("the index holds a flat store"),
+        }
+    }
+    
     #[tokio::test]
     async fn test_insert_and_search() {
         let dir = tempdir().unwrap();
         let path = dir.path().join("test.usearch");
         let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
         
-        let mut db = USearchDatabase::new(path, 128, data_dir).unwrap();
+        let mut db = USearchDatabase::new(path, 128, data_dir, &mapping_db).unwrap();
         
         let docs = vec![
             VectorDocument {
@@ -329,13 +1375,309 @@ mod tests {
         assert!(results[0].score > results[1].score);
     }
     
+    #[tokio::test]
+    async fn test_quantized_index_keeps_precision_on_reopen() {
+        let dir = tempdir().unwrap();
+        let codebase_path = dir.path().join("codebase");
+        let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
+        
+        let mut near = vec![-1.0; 128];
+        near[..64].fill(1.0);
+        for quantization in [VectorQuantization::Int8, VectorQuantization::Binary] {
+            {
+                let mut db = USearchDatabase::for_codebase_quantized(&codebase_path, 128, &data_dir, &mapping_db, quantization, VectorMetric::Cosine, HnswConfig::default()).unwrap();
+                db.insert(vec![
+                    VectorDocument { id: "near".to_string(), vector: near.clone() },
+                    VectorDocument { id: "far".to_string(), vector: vec![-1.0; 128] },
+                ]).await.unwrap();
+                db.save().await.unwrap();
+            }
+            
+            // Reopened without asking for a precision, the index keeps its own
+            let db = USearchDatabase::for_codebase(&codebase_path, 128, &data_dir, &mapping_db).unwrap();
+            assert_eq!(db.quantization(), quantization);
+            let results = db.search(&vec![1.0; 128], 2).await.unwrap();
+            assert_eq!(results[0].id, "near");
+            assert!(results[0].score > results[1].score);
+            
+            USearchDatabase::remove_for_codebase(&codebase_path, &data_dir).unwrap();
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_dot_metric_index_keeps_metric_on_reopen() {
+        let dir = tempdir().unwrap();
+        let codebase_path = dir.path().join("codebase");
+        let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
+        
+        // Same direction as the query, so cosine cannot tell them apart
+        {
+            let mut db = USearchDatabase::for_codebase_quantized(&codebase_path, 128, &data_dir, &mapping_db, VectorQuantization::None, VectorMetric::Dot, HnswConfig::default()).unwrap();
+            db.insert(vec![
+                VectorDocument { id: "short".to_string(), vector: vec![0.01; 128] },
+                VectorDocument { id: "long".to_string(), vector: vec![0.05; 128] },
+            ]).await.unwrap();
+            db.save().await.unwrap();
+        }
+        
+        let db = USearchDatabase::for_codebase(&codebase_path, 128, &data_dir, &mapping_db).unwrap();
+        assert_eq!(db.metric(), VectorMetric::Dot);
+        let results = db.search(&vec![0.1; 128], 2).await.unwrap();
+        assert_eq!(results[0].id, "long");
+        assert!((results[0].score - 0.64).abs() < 1e-3);
+    }
+    
+    #[tokio::test]
+    async fn test_hnsw_settings_apply_to_new_and_reopened_index() {
+        let dir = tempdir().unwrap();
+        let codebase_path = dir.path().join("codebase");
+        let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
+        
+        let hnsw = HnswConfig { connectivity: 32, expansion_add: 200, expansion_search: 100, ..graph_only() };
+        let mut db = USearchDatabase::for_codebase_quantized(&codebase_path, 128, &data_dir, &mapping_db, VectorQuantization::None, VectorMetric::Cosine, hnsw).unwrap();
+        assert_eq!(graph(&db).connectivity(), 32);
+        db.insert(vec![VectorDocument { id: "doc".to_string(), vector: vec![1.0; 128] }]).await.unwrap();
+        db.save().await.unwrap();
+        
+        // The graph keeps its connectivity, the expansion factors follow the config
+        let hnsw = HnswConfig { connectivity: 8, expansion_add: 64, expansion_search: 256, ..HnswConfig::default() };
+        let db = USearchDatabase::for_codebase_quantized(&codebase_path, 128, &data_dir, &mapping_db, VectorQuantization::None, VectorMetric::Cosine, hnsw).unwrap();
+        assert_eq!(graph(&db).connectivity(), 32);
+        assert_eq!(graph(&db).expansion_search(), 256);
+        assert_eq!(db.search(&vec![1.0; 128], 1).await.unwrap()[0].id, "doc");
+    }
+    
+    #[tokio::test]
+    async fn test_flat_store_builds_its_graph_past_the_threshold() {
+        let dir = tempdir().unwrap();
+        let codebase_path = dir.path().join("codebase");
+        let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
+        let index_path = USearchDatabase::get_index_path_for_codebase(&codebase_path, &data_dir);
+        let document = |i: usize| {
+            let mut vector = vec![0.1; 16];
+            vector[i % 16] = 1.0 + i as f32;
+            VectorDocument { id: format!("doc{i}"), vector }
+        };
+        
+        let hnsw = HnswConfig { connectivity: 8, exact_search_max_chunks: 10, ..HnswConfig::default() };
+        let mut db = USearchDatabase::for_codebase_quantized(&codebase_path, 16, &data_dir, &mapping_db, VectorQuantization::None, VectorMetric::Cosine, hnsw).unwrap();
+        db.insert((0..10).map(document).collect()).await.unwrap();
+        db.delete(&["doc0".to_string()]).await.unwrap();
+        db.save().await.unwrap();
+        assert!(matches!(db.index, Vectors::Flat(_)));
+        assert!(FlatIndex::is_flat_file(&index_path).unwrap());
+        
+        // Reopened, the flat store is read back and still searched
+        let mut db = USearchDatabase::for_codebase_quantized(&codebase_path, 16, &data_dir, &mapping_db, VectorQuantization::None, VectorMetric::Cosine, hnsw).unwrap();
+        assert!(matches!(db.index, Vectors::Flat(_)));
+        assert_eq!(db.count().await.unwrap(), 9);
+        assert_eq!(db.search(&document(3).vector, 1).await.unwrap()[0].id, "doc3");
+        
+        // The vector that crosses the threshold moves every vector into a graph
+        db.insert(vec![document(10)]).await.unwrap();
+        assert_eq!(graph(&db).connectivity(), 8);
+        assert_eq!(db.vacuum().await.unwrap(), 1);
+        drop(db);
+        
+        let db = USearchDatabase::for_codebase_quantized(&codebase_path, 16, &data_dir, &mapping_db, VectorQuantization::None, VectorMetric::Cosine, hnsw).unwrap();
+        assert!(!FlatIndex::is_flat_file(&index_path).unwrap());
+        assert_eq!(graph(&db).size(), 10);
+        assert_eq!(db.search(&document(10).vector, 1).await.unwrap()[0].id, "doc10");
+        assert!(!db.contains("doc0"));
+    }
+    
+    fn chunk(id: &str, extension: &str) -> CodeChunk {
+        CodeChunk {
+            id: id.to_string(),
+            content: String::new(),
+            file_path: PathBuf::from(format!("/repo/{id}.{extension}")),
+            relative_path: format!("{id}.{extension}"),
+            start_line: 1,
+            end_line: 1,
+            language: extension.to_string(),
+            metadata: crate::types::ChunkMetadata {
+                file_extension: extension.to_string(),
+                chunk_index: 0,
+                hash: String::new(),
+                modified_at: None,
+                file_hash: None,
+                sync_generation: None,
+            },
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_extension_filter_applies_during_search() {
+        let dir = tempdir().unwrap();
+        let codebase_path = dir.path().join("codebase");
+        let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
+        
+        // Every Rust chunk is closer to the query than the only Python one
+        let mut chunks = Vec::new();
+        let mut embeddings = Vec::new();
+        for i in 0..20 {
+            chunks.push(chunk(&format!("rs{i}"), "rs"));
+            let mut vector = vec![1.0; 128];
+            vector[i] = 0.5;
+            embeddings.push(vector);
+        }
+        chunks.push(chunk("py", "py"));
+        embeddings.push(vec![-1.0; 128]);
+        
+        {
+            let mut db = USearchDatabase::for_codebase(&codebase_path, 128, &data_dir, &mapping_db).unwrap();
+            db.insert_batch(&codebase_path, &chunks, &embeddings).await.unwrap();
+            db.save().await.unwrap();
+        }
+        
+        let db = USearchDatabase::for_codebase(&codebase_path, 128, &data_dir, &mapping_db).unwrap();
+        let python = db.search_with_extensions(&vec![1.0; 128], 5, &[".py".to_string()]).await.unwrap();
+        assert_eq!(python.len(), 1);
+        assert_eq!(python[0].id, "py");
+        
+        let rust = db.search_with_extensions(&vec![1.0; 128], 5, &["RS".to_string()]).await.unwrap();
+        assert_eq!(rust.len(), 5);
+        assert!(rust.iter().all(|result| result.id.starts_with("rs")));
+        
+        // Paths narrow the walk the same way, with or without extensions
+        let globs = PathGlobs::new(&["rs1*".to_string()], &["rs1.rs".to_string()]).unwrap();
+        let mut scoped = db.search_filtered(&vec![1.0; 128], 20, &[], &globs).await.unwrap();
+        scoped.sort_by(|a, b| a.id.cmp(&b.id));
+        let expected: Vec<String> = (10..20).map(|i| format!("rs{i}")).collect();
+        assert_eq!(scoped.iter().map(|result| result.id.clone()).collect::<Vec<_>>(), expected);
+        assert!(db.search_filtered(&vec![1.0; 128], 5, &["py".to_string()], &globs).await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_small_index_is_searched_exactly() {
+        let dir = tempdir().unwrap();
+        let codebase_path = dir.path().join("codebase");
+        let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
+        
+        let mut chunks = Vec::new();
+        let mut embeddings = Vec::new();
+        for i in 0..300 {
+            let extension = if i % 3 == 0 { "py" } else { "rs" };
+            chunks.push(chunk(&format!("c{i}"), extension));
+            embeddings.push((0..32).map(|j| ((i * 7 + j * 13) % 17) as f32 - 8.0).collect::<Vec<f32>>());
+        }
+        let query: Vec<f32> = (0..32).map(|j| (j % 5) as f32 - 2.0).collect();
+        
+        // Ranking by cosine similarity over every vector is what the scan must return
+        let cosine = |vector: &[f32]| {
+            let dot: f32 = vector.iter().zip(&query).map(|(a, b)| a * b).sum();
+            let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+            dot / (norm(vector) * norm(&query))
+        };
+        let mut expected: Vec<(usize, f32)> = embeddings.iter().map(|vector| cosine(vector)).enumerate().collect();
+        expected.sort_by(|a, b| b.1.total_cmp(&a.1));
+        
+        let hnsw = HnswConfig { exact_search_max_chunks: 1000, ..HnswConfig::default() };
+        let mut db = USearchDatabase::for_codebase_quantized(&codebase_path, 32, &data_dir, &mapping_db, VectorQuantization::None, VectorMetric::Cosine, hnsw).unwrap();
+        db.insert_batch(&codebase_path, &chunks, &embeddings).await.unwrap();
+        assert!(db.searches_exactly());
+        // No graph is built for an index this small
+        assert!(matches!(db.index, Vectors::Flat(_)));
+        
+        let results = db.search(&query, 10).await.unwrap();
+        assert_eq!(results.len(), 10);
+        for (result, (_, score)) in results.iter().zip(&expected) {
+            assert!((result.score - score).abs() < 1e-4, "{} scored {}, expected {}", result.id, result.score, score);
+        }
+        
+        let python = db.search_with_extensions(&query, 10, &["py".to_string()]).await.unwrap();
+        let expected_python: Vec<f32> = expected.iter().filter(|(i, _)| i % 3 == 0).map(|(_, score)| *score).take(10).collect();
+        assert_eq!(python.len(), 10);
+        assert!(python.iter().all(|result| chunks.iter().any(|chunk| chunk.id == result.id && chunk.metadata.file_extension == "py")));
+        for (result, score) in python.iter().zip(&expected_python) {
+            assert!((result.score - score).abs() < 1e-4);
+        }
+        
+        // Past the threshold the graph is built and searches walk it
+        db.set_search_settings(HnswConfig { exact_search_max_chunks: 100, ..HnswConfig::default() }).unwrap();
+        assert!(!db.searches_exactly());
+        assert_eq!(graph(&db).size(), 300);
+        assert_eq!(db.search(&query, 1).await.unwrap()[0].id, results[0].id);
+    }
+    
+    #[tokio::test]
+    async fn test_viewed_index_searches_and_loads_on_write() {
+        let dir = tempdir().unwrap();
+        let codebase_path = dir.path().join("codebase");
+        let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
+        
+        // Flat stores are read into RAM, so only a graph is mapped
+        let mut db = USearchDatabase::for_codebase_quantized(&codebase_path, 128, &data_dir, &mapping_db, VectorQuantization::None, VectorMetric::Cosine, graph_only()).unwrap();
+        db.insert(vec![VectorDocument { id: "a".to_string(), vector: vec![1.0; 128] }]).await.unwrap();
+        db.save().await.unwrap();
+        
+        let mut db = USearchDatabase::view_for_codebase(&codebase_path, 128, &data_dir, &mapping_db, VectorQuantization::None, VectorMetric::Cosine, HnswConfig::default()).unwrap();
+        assert!(db.is_viewed());
+        assert_eq!(db.search(&vec![1.0; 128], 1).await.unwrap()[0].id, "a");
+        
+        db.insert(vec![VectorDocument { id: "b".to_string(), vector: vec![-1.0; 128] }]).await.unwrap();
+        assert!(!db.is_viewed());
+        db.save().await.unwrap();
+        
+        let db = USearchDatabase::for_codebase(&codebase_path, 128, &data_dir, &mapping_db).unwrap();
+        assert_eq!(db.count().await.unwrap(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_viewed_index_keeps_logged_changes_in_a_side_index() {
+        let dir = tempdir().unwrap();
+        let codebase_path = dir.path().join("codebase");
+        let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
+        let unit = |axis: usize| {
+            let mut vector = vec![0.0; 8];
+            vector[axis] = 1.0;
+            vector
+        };
+        let document = |id: &str, axis: usize| VectorDocument { id: id.to_string(), vector: unit(axis) };
+        
+        let mut db = USearchDatabase::for_codebase_quantized(&codebase_path, 8, &data_dir, &mapping_db, VectorQuantization::None, VectorMetric::Cosine, graph_only()).unwrap();
+        db.insert(vec![document("a", 0), document("b", 1), document("c", 2)]).await.unwrap();
+        db.save().await.unwrap();
+        // Small changes stay in the log
+        db.insert(vec![document("d", 3), document("a", 4)]).await.unwrap();
+        db.delete(&["b".to_string()]).await.unwrap();
+        db.save().await.unwrap();
+        assert!(usearch_log::log_path(&USearchDatabase::get_index_path_for_codebase(&codebase_path, &data_dir)).exists());
+        
+        let mut db = USearchDatabase::view_for_codebase(&codebase_path, 8, &data_dir, &mapping_db, VectorQuantization::None, VectorMetric::Cosine, HnswConfig::default()).unwrap();
+        assert!(db.is_viewed());
+        assert_eq!(db.count().await.unwrap(), 3);
+        assert_eq!(db.search(&unit(3), 1).await.unwrap()[0].id, "d");
+        // "a" was replaced, so only its new vector is found
+        assert_eq!(db.search(&unit(4), 1).await.unwrap()[0].id, "a");
+        let ids = |results: Vec<SearchResult>| results.into_iter().map(|result| result.id).collect::<Vec<_>>();
+        let near_old_a = ids(db.search(&unit(0), 3).await.unwrap());
+        assert_eq!(near_old_a.len(), 3);
+        assert!(!near_old_a.contains(&"b".to_string()));
+        
+        db.insert(vec![document("e", 5)]).await.unwrap();
+        assert!(!db.is_viewed());
+        assert_eq!(db.count().await.unwrap(), 4);
+        assert_eq!(db.search(&unit(4), 1).await.unwrap()[0].id, "a");
+        assert_eq!(db.search(&unit(3), 1).await.unwrap()[0].id, "d");
+    }
+    
     #[tokio::test]
     async fn test_has_and_delete_index() {
         let dir = tempdir().unwrap();
         let codebase_path = dir.path().join("codebase");
         let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
         
-        let mut db = USearchDatabase::for_codebase(&codebase_path, 128, &data_dir).unwrap();
+        let mut db = USearchDatabase::for_codebase(&codebase_path, 128, &data_dir, &mapping_db).unwrap();
         
         assert!(!db.has_index(&codebase_path).await.unwrap());
         let docs = vec![VectorDocument {
@@ -348,6 +1690,8 @@ mod tests {
         assert!(db.has_index(&codebase_path).await.unwrap());
         db.delete_index(&codebase_path).await.unwrap();
         assert!(!db.has_index(&codebase_path).await.unwrap());
+        // The mappings go with the files
+        assert!(!mapping_db.tree_names().iter().any(|name| name.starts_with(b"usearch_mappings/")));
     }
     
     #[tokio::test]
@@ -355,9 +1699,10 @@ mod tests {
         let dir = tempdir().unwrap();
         let path = dir.path().join("persistent.usearch");
         let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
         
         {
-            let mut db = USearchDatabase::new(path.clone(), 128, data_dir.clone()).unwrap();
+            let mut db = USearchDatabase::new(path.clone(), 128, data_dir.clone(), &mapping_db).unwrap();
             let docs = vec![VectorDocument {
                 id: "persistent_doc".to_string(),
                 vector: vec![1.0; 128],
@@ -367,7 +1712,7 @@ mod tests {
         }
         
         {
-            let mut db = USearchDatabase::from_file(path, data_dir).unwrap();
+            let mut db = USearchDatabase::from_file(path, 128, data_dir, &mapping_db).unwrap();
             db.load().await.unwrap();
             
             assert_eq!(db.count().await.unwrap(), 1);
@@ -377,4 +1722,288 @@ mod tests {
             assert_eq!(results[0].id, "persistent_doc");
         }
     }
+    
+    #[tokio::test]
+    async fn test_small_changes_are_logged_until_compaction() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("logged.usearch");
+        let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
+        let log_path = usearch_log::log_path(&path);
+        let vector = |i: usize| {
+            let mut vector = vec![0.1; 128];
+            vector[i % 128] = 1.0 + i as f32;
+            vector
+        };
+        
+        let mut db = USearchDatabase::new(path.clone(), 128, data_dir.clone(), &mapping_db).unwrap();
+        db.insert((0..10).map(|i| VectorDocument { id: format!("doc{i}"), vector: vector(i) }).collect()).await.unwrap();
+        db.save().await.unwrap();
+        assert!(!log_path.exists());
+        let index_bytes = std::fs::read(&path).unwrap();
+        
+        // Later saves append to the log and leave the index file alone
+        db.insert(vec![VectorDocument { id: "doc10".to_string(), vector: vector(10) }]).await.unwrap();
+        db.delete(&["doc0".to_string()]).await.unwrap();
+        db.rename(&[("doc1".to_string(), "renamed".to_string())]).await.unwrap();
+        db.save().await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), index_bytes);
+        assert_eq!(usearch_log::read(&path).unwrap().0.len(), 3);
+        
+        let mut db = USearchDatabase::from_file(path.clone(), 128, data_dir.clone(), &mapping_db).unwrap();
+        assert_eq!(db.count().await.unwrap(), 10);
+        assert!(!db.contains("doc0"));
+        assert_eq!(db.search(&vector(1), 1).await.unwrap()[0].id, "renamed");
+        assert_eq!(db.search(&vector(10), 1).await.unwrap()[0].id, "doc10");
+        
+        // Past the threshold the next save rewrites the index and empties the log
+        db.insert((11..11 + LOG_MIN_ENTRIES).map(|i| VectorDocument { id: format!("doc{i}"), vector: vector(i) }).collect()).await.unwrap();
+        db.save().await.unwrap();
+        assert!(!log_path.exists());
+        let db = USearchDatabase::from_file(path, 128, data_dir, &mapping_db).unwrap();
+        assert_eq!(db.count().await.unwrap(), 10 + LOG_MIN_ENTRIES);
+    }
+    
+    #[tokio::test]
+    async fn test_bulk_load_skips_the_log() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("bulk.usearch");
+        let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
+        let log_path = usearch_log::log_path(&path);
+        let vector = |i: usize| {
+            let mut vector = vec![0.1; 128];
+            vector[i] = 1.0;
+            vector
+        };
+        
+        let mut db = USearchDatabase::new(path.clone(), 128, data_dir.clone(), &mapping_db).unwrap();
+        db.begin_bulk_load();
+        for i in 0..3 {
+            db.insert(vec![VectorDocument { id: format!("doc{i}"), vector: vector(i) }]).await.unwrap();
+        }
+        assert!(!path.exists());
+        assert!(!log_path.exists());
+        db.save().await.unwrap();
+        assert_eq!(USearchDatabase::from_file(path.clone(), 128, data_dir.clone(), &mapping_db).unwrap().count().await.unwrap(), 3);
+        
+        // Changes after the build are logged again
+        db.insert(vec![VectorDocument { id: "doc3".to_string(), vector: vector(3) }]).await.unwrap();
+        assert_eq!(usearch_log::read(&path).unwrap().0.len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_changes_survive_without_save() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("unsaved.usearch");
+        let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
+        let vector = |i: usize| {
+            let mut vector = vec![0.1; 128];
+            vector[i] = 1.0;
+            vector
+        };
+        
+        // A process that dies before saving still leaves every change on disk
+        let mut db = USearchDatabase::new(path.clone(), 128, data_dir.clone(), &mapping_db).unwrap();
+        db.insert((0..3).map(|i| VectorDocument { id: format!("doc{i}"), vector: vector(i) }).collect()).await.unwrap();
+        db.insert(vec![VectorDocument { id: "doc3".to_string(), vector: vector(3) }]).await.unwrap();
+        db.delete(&["doc0".to_string()]).await.unwrap();
+        drop(db);
+        
+        let db = USearchDatabase::from_file(path, 128, data_dir, &mapping_db).unwrap();
+        assert_eq!(db.count().await.unwrap(), 3);
+        assert!(!db.contains("doc0"));
+        assert_eq!(db.search(&vector(3), 1).await.unwrap()[0].id, "doc3");
+    }
+    
+    #[tokio::test]
+    async fn test_upsert_replaces_stored_vector() {
+        let dir = tempdir().unwrap();
+        let codebase_path = dir.path().join("codebase");
+        let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
+        let vector = |i: usize| {
+            let mut vector = vec![0.1; 128];
+            vector[i] = 1.0;
+            vector
+        };
+        let chunks = [chunk("a", "rs"), chunk("b", "rs")];
+        
+        let mut db = USearchDatabase::for_codebase(&codebase_path, 128, &data_dir, &mapping_db).unwrap();
+        db.upsert_batch(&codebase_path, &chunks, &[vector(0), vector(1)]).await.unwrap();
+        // Storing a chunk again, e.g. on a retry, keeps one vector for it
+        db.upsert_batch(&codebase_path, &chunks[..1], &[vector(2)]).await.unwrap();
+        db.save().await.unwrap();
+        drop(db);
+        
+        let db = USearchDatabase::for_codebase(&codebase_path, 128, &data_dir, &mapping_db).unwrap();
+        assert_eq!(db.count().await.unwrap(), 2);
+        assert_eq!(db.search(&vector(2), 1).await.unwrap()[0].id, "a");
+        assert_eq!(db.search(&vector(1), 1).await.unwrap()[0].id, "b");
+    }
+    
+    #[tokio::test]
+    async fn test_deleted_vectors_stay_hidden_until_vacuumed() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("vacuum.usearch");
+        let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
+        let vector = |i: usize| {
+            let mut vector = vec![0.1; 128];
+            vector[i] = 1.0;
+            vector
+        };
+        
+        let mut db = USearchDatabase::new(path.clone(), 128, data_dir.clone(), &mapping_db).unwrap();
+        db.insert((0..4).map(|i| VectorDocument { id: format!("doc{i}"), vector: vector(i) }).collect()).await.unwrap();
+        db.delete(&["doc0".to_string(), "doc1".to_string()]).await.unwrap();
+        
+        // Deleted vectors stay in the graph, but no search returns them
+        assert_eq!(db.index.size(), 4);
+        assert_eq!(db.count().await.unwrap(), 2);
+        let ids: Vec<String> = db.search(&vector(0), 2).await.unwrap().into_iter().map(|result| result.id).collect();
+        assert_eq!(ids.len(), 2);
+        assert!(!ids.contains(&"doc0".to_string()) && !ids.contains(&"doc1".to_string()));
+        
+        // Compaction keeps them, and a reopened index still hides them
+        db.write_base().unwrap();
+        drop(db);
+        let mut db = USearchDatabase::from_file(path.clone(), 128, data_dir.clone(), &mapping_db).unwrap();
+        assert_eq!(db.index.size(), 4);
+        assert_eq!(db.count().await.unwrap(), 2);
+        
+        // Adding a deleted ID again stores it under a new key
+        db.insert(vec![VectorDocument { id: "doc1".to_string(), vector: vector(1) }]).await.unwrap();
+        assert_eq!(db.search(&vector(1), 1).await.unwrap()[0].id, "doc1");
+        
+        assert_eq!(db.vacuum().await.unwrap(), 2);
+        assert_eq!(db.vacuum().await.unwrap(), 0);
+        drop(db);
+        let db = USearchDatabase::from_file(path, 128, data_dir, &mapping_db).unwrap();
+        assert_eq!(db.index.size(), 3);
+        assert_eq!(db.count().await.unwrap(), 3);
+        assert!(!db.contains("doc0"));
+    }
+    
+    #[tokio::test]
+    async fn test_mappings_out_of_step_are_quarantined() {
+        let dir = tempdir().unwrap();
+        let codebase_path = dir.path().join("codebase");
+        let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
+        
+        let mut db = USearchDatabase::for_codebase(&codebase_path, 128, &data_dir, &mapping_db).unwrap();
+        db.insert(vec![VectorDocument {
+            id: "doc".to_string(),
+            vector: vec![1.0; 128],
+        }]).await.unwrap();
+        db.save().await.unwrap();
+        
+        // Mappings for a vector the index does not hold
+        let mappings_path = usearch_mappings::mappings_path(&db.path);
+        let ids = HashMap::from([(0, "doc".to_string()), (1, "ghost".to_string())]);
+        MappingStore::open(&mapping_db, &db.path)
+            .unwrap()
+            .unwrap()
+            .save(&db.path, [1], &ids, &HashMap::new(), &HashMap::new(), &HashSet::new(), 2, VectorQuantization::None, VectorMetric::Cosine)
+            .unwrap();
+        
+        assert!(matches!(
+            USearchDatabase::for_codebase(&codebase_path, 128, &data_dir, &mapping_db),
+            Err(Error::Corrupted(_))
+        ));
+        assert!(!db.path.exists() && !mappings_path.exists());
+        assert!(!mapping_db.tree_names().iter().any(|name| name.starts_with(b"usearch_mappings/")));
+        
+        // Quarantined files make room for an empty index to rebuild into
+        let db = USearchDatabase::for_codebase(&codebase_path, 128, &data_dir, &mapping_db).unwrap();
+        assert_eq!(db.count().await.unwrap(), 0);
+    }
+    
+    #[tokio::test]
+    async fn test_json_mappings_move_to_the_store_on_save() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("legacy.usearch");
+        let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
+        
+        let mut db = USearchDatabase::new(path.clone(), 128, data_dir.clone(), &mapping_db).unwrap();
+        db.insert(vec![VectorDocument { id: "doc".to_string(), vector: vec![1.0; 128] }]).await.unwrap();
+        db.save().await.unwrap();
+        drop(db);
+        
+        // An index saved before the store, with its mappings in JSON
+        std::fs::remove_file(usearch_mappings::mappings_path(&path)).unwrap();
+        let legacy_path = path.with_extension("mappings.json");
+        let legacy = serde_json::json!({
+            "id_map": {"doc": 0},
+            "next_id": 1,
+            "vector_count": 1,
+            "quantization": "none",
+            "extensions": {"0": "rs"},
+        });
+        integrity::write_checked(&legacy_path, MAPPINGS_FORMAT_VERSION, &legacy).unwrap();
+        
+        let mut db = USearchDatabase::from_file(path.clone(), 128, data_dir.clone(), &mapping_db).unwrap();
+        assert_eq!(db.search(&vec![1.0; 128], 1).await.unwrap()[0].id, "doc");
+        db.insert(vec![VectorDocument { id: "other".to_string(), vector: vec![-1.0; 128] }]).await.unwrap();
+        db.save().await.unwrap();
+        assert!(!legacy_path.exists());
+        drop(db);
+        
+        let db = USearchDatabase::from_file(path, 128, data_dir, &mapping_db).unwrap();
+        assert_eq!(db.count().await.unwrap(), 2);
+        assert!(db.contains("doc") && db.contains("other"));
+        assert_eq!(db.extensions.get(&0).map(String::as_str), Some("rs"));
+    }
+    
+    #[tokio::test]
+    async fn test_dimension_change_is_reported() {
+        let dir = tempdir().unwrap();
+        let codebase_path = dir.path().join("codebase");
+        let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
+        
+        let mut db = USearchDatabase::for_codebase(&codebase_path, 128, &data_dir, &mapping_db).unwrap();
+        db.save().await.unwrap();
+        
+        // An empty index is simply recreated at the new size
+        let mut db = USearchDatabase::for_codebase(&codebase_path, 64, &data_dir, &mapping_db).unwrap();
+        assert_eq!(db.dimension, 64);
+        db.insert(vec![VectorDocument {
+            id: "doc".to_string(),
+            vector: vec![1.0; 64],
+        }]).await.unwrap();
+        db.save().await.unwrap();
+        
+        assert!(matches!(
+            USearchDatabase::for_codebase(&codebase_path, 128, &data_dir, &mapping_db),
+            Err(Error::DimensionMismatch(_))
+        ));
+        
+        USearchDatabase::remove_for_codebase(&codebase_path, &data_dir).unwrap();
+        let db = USearchDatabase::for_codebase(&codebase_path, 128, &data_dir, &mapping_db).unwrap();
+        assert_eq!(db.dimension, 128);
+    }
+    
+    #[tokio::test]
+    async fn test_rename_keeps_vector() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("rename.usearch");
+        let data_dir = dir.path().to_path_buf();
+        let mapping_db = mapping_db(&data_dir);
+        
+        let mut db = USearchDatabase::new(path, 128, data_dir, &mapping_db).unwrap();
+        db.insert(vec![VectorDocument {
+            id: "old".to_string(),
+            vector: vec![1.0; 128],
+        }]).await.unwrap();
+        
+        db.rename(&[("old".to_string(), "new".to_string())]).await.unwrap();
+        
+        let results = db.search(&[1.0; 128], 1).await.unwrap();
+        assert_eq!(results[0].id, "new");
+        assert_eq!(db.count().await.unwrap(), 1);
+    }
 }
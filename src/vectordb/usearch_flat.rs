@@ -0,0 +1,251 @@
+//! Flat vector store of small usearch indexes
+//!
+//! Indexes of at most `VECTOR_EXACT_SEARCH_MAX_CHUNKS` vectors are searched
+//! by comparing the query with every vector, so an HNSW graph would only
+//! cost them build time and memory. Their vectors are kept in one array
+//! instead, searched by a scan, and moved into a usearch index, which links
+//! them into a graph, once the index grows past the limit.
+//!
+//! The store is saved at the index path, as a magic header followed by the
+//! bincode encoding of its keys and vectors, so opening an index tells the
+//! two layouts apart by their first bytes. Vectors are kept at full
+//! precision; the index's quantization applies once the graph is built,
+//! and binary indexes compare the signs of the stored values.
+
+use crate::config::{VectorMetric, VectorQuantization};
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use usearch::ffi::Matches;
+
+/// First bytes of a saved flat store; usearch index files start otherwise
+const MAGIC: &[u8; 8] = b"CSFLAT01";
+
+#[derive(Serialize, Deserialize)]
+struct SavedVectors {
+    dimension: usize,
+    keys: Vec<u64>,
+    /// Vectors of `keys` in order, `dimension` values each
+    vectors: Vec<f32>,
+}
+
+pub(crate) struct FlatIndex {
+    dimension: usize,
+    quantization: VectorQuantization,
+    metric: VectorMetric,
+    keys: Vec<u64>,
+    vectors: Vec<f32>,
+    /// Position of each key in `keys`
+    positions: HashMap<u64, usize>,
+}
+
+impl FlatIndex {
+    pub(crate) fn new(dimension: usize, quantization: VectorQuantization, metric: VectorMetric) -> Self {
+        Self {
+            dimension,
+            quantization,
+            metric,
+            keys: Vec::new(),
+            vectors: Vec::new(),
+            positions: HashMap::new(),
+        }
+    }
+
+    /// Whether the file at `path` holds a flat store rather than a usearch index
+    pub(crate) fn is_flat_file(path: &Path) -> Result<bool> {
+        let mut header = [0u8; MAGIC.len()];
+        let mut file = std::fs::File::open(path)?;
+        match file.read_exact(&mut header) {
+            Ok(()) => Ok(&header == MAGIC),
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Read the store saved at `path`
+    pub(crate) fn load(path: &Path, quantization: VectorQuantization, metric: VectorMetric) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let corrupted = |reason: &str| Error::Corrupted(format!("{}: {reason}", path.display()));
+        let encoded = bytes.strip_prefix(MAGIC.as_slice()).ok_or_else(|| corrupted("not a flat vector store"))?;
+        let (saved, _len): (SavedVectors, usize) = bincode::serde::decode_from_slice(encoded, bincode::config::standard())
+            .map_err(|e| corrupted(&format!("failed to read vectors ({e})")))?;
+        if saved.vectors.len() != saved.keys.len() * saved.dimension {
+            return Err(corrupted(&format!(
+                "{} values do not make {} vectors of dimension {}",
+                saved.vectors.len(),
+                saved.keys.len(),
+                saved.dimension
+            )));
+        }
+        let positions = saved.keys.iter().enumerate().map(|(position, &key)| (key, position)).collect();
+        Ok(Self {
+            dimension: saved.dimension,
+            quantization,
+            metric,
+            keys: saved.keys,
+            vectors: saved.vectors,
+            positions,
+        })
+    }
+
+    /// Write the store to `path`
+    pub(crate) fn save(&self, path: &Path) -> Result<()> {
+        let saved = SavedVectors {
+            dimension: self.dimension,
+            keys: self.keys.clone(),
+            vectors: self.vectors.clone(),
+        };
+        let encoded = bincode::serde::encode_to_vec(&saved, bincode::config::standard())
+            .map_err(|e| Error::VectorDb(format!("Failed to serialize vectors: {e}")))?;
+        let mut bytes = Vec::with_capacity(MAGIC.len() + encoded.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&encoded);
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    pub(crate) fn dimensions(&self) -> usize {
+        self.dimension
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub(crate) fn contains(&self, key: u64) -> bool {
+        self.positions.contains_key(&key)
+    }
+
+    pub(crate) fn reserve(&mut self, capacity: usize) {
+        let additional = capacity.saturating_sub(self.keys.len());
+        self.keys.reserve(additional);
+        self.vectors.reserve(additional * self.dimension);
+    }
+
+    /// Store `vector` under `key`, replacing the one it held
+    pub(crate) fn add(&mut self, key: u64, vector: &[f32]) -> Result<()> {
+        if vector.len() != self.dimension {
+            return Err(Error::VectorDb(format!(
+                "Vector dimension mismatch: expected {}, got {}",
+                self.dimension,
+                vector.len()
+            )));
+        }
+        match self.positions.get(&key) {
+            Some(&position) => self.vector_mut(position).copy_from_slice(vector),
+            None => {
+                self.positions.insert(key, self.keys.len());
+                self.keys.push(key);
+                self.vectors.extend_from_slice(vector);
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop the vector of `key`, moving the last vector into its place
+    pub(crate) fn remove(&mut self, key: u64) {
+        let Some(position) = self.positions.remove(&key) else {
+            return;
+        };
+        let last = self.keys.len() - 1;
+        if position != last {
+            let moved = self.keys[last];
+            self.keys[position] = moved;
+            self.positions.insert(moved, position);
+            self.vectors.copy_within(last * self.dimension..(last + 1) * self.dimension, position * self.dimension);
+        }
+        self.keys.truncate(last);
+        self.vectors.truncate(last * self.dimension);
+    }
+
+    /// Keys and vectors in the order they are stored
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (u64, &[f32])> {
+        self.keys.iter().copied().zip(self.vectors.chunks_exact(self.dimension.max(1)))
+    }
+
+    /// The `count` vectors nearest to `query` among those `filter` accepts,
+    /// nearest first, with distances as usearch reports them
+    pub(crate) fn search(&self, query: &[f32], count: usize, filter: impl Fn(u64) -> bool) -> Matches {
+        let mut ranked: Vec<(u64, f32)> = self
+            .iter()
+            .filter(|(key, _)| filter(*key))
+            .map(|(key, vector)| (key, self.distance(query, vector)))
+            .collect();
+        ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+        ranked.truncate(count);
+        let (keys, distances) = ranked.into_iter().unzip();
+        Matches { keys, distances }
+    }
+
+    fn vector_mut(&mut self, position: usize) -> &mut [f32] {
+        &mut self.vectors[position * self.dimension..(position + 1) * self.dimension]
+    }
+
+    /// Distance of `a` to `b` under the index's metric: the number of
+    /// differing signs for binary indexes, else 1 - cosine, 1 - inner
+    /// product or the squared Euclidean distance
+    fn distance(&self, a: &[f32], b: &[f32]) -> f32 {
+        let dot = || a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+        match (self.quantization, self.metric) {
+            (VectorQuantization::Binary, _) => a.iter().zip(b).filter(|(x, y)| (**x > 0.0) != (**y > 0.0)).count() as f32,
+            (_, VectorMetric::Cosine) => {
+                let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+                let norms = norm(a) * norm(b);
+                if norms == 0.0 {
+                    1.0
+                } else {
+                    1.0 - dot() / norms
+                }
+            }
+            (_, VectorMetric::Dot) => 1.0 - dot(),
+            (_, VectorMetric::L2) => a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_removed_vector_makes_room_for_the_last() {
+        let mut flat = FlatIndex::new(2, VectorQuantization::None, VectorMetric::L2);
+        flat.add(1, &[0.0, 0.0]).unwrap();
+        flat.add(2, &[1.0, 0.0]).unwrap();
+        flat.add(3, &[3.0, 0.0]).unwrap();
+        flat.remove(1);
+        assert!(!flat.contains(1) && flat.size() == 2);
+
+        let nearest = flat.search(&[3.0, 0.0], 5, |_| true);
+        assert_eq!(nearest.keys, vec![3, 2]);
+        assert_eq!(nearest.distances, vec![0.0, 4.0]);
+        assert_eq!(flat.search(&[3.0, 0.0], 5, |key| key != 3).keys, vec![2]);
+
+        // Adding a stored key again replaces its vector
+        flat.add(2, &[3.0, 1.0]).unwrap();
+        assert_eq!(flat.size(), 2);
+        assert_eq!(flat.search(&[3.0, 1.0], 1, |_| true).keys, vec![2]);
+    }
+
+    #[test]
+    fn test_saved_store_is_told_apart_and_read_back() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("index.usearch");
+        let mut flat = FlatIndex::new(3, VectorQuantization::None, VectorMetric::Cosine);
+        flat.add(7, &[1.0, 0.0, 0.0]).unwrap();
+        flat.add(9, &[0.0, 1.0, 0.0]).unwrap();
+        flat.save(&path).unwrap();
+        assert!(FlatIndex::is_flat_file(&path).unwrap());
+
+        let read = FlatIndex::load(&path, VectorQuantization::None, VectorMetric::Cosine).unwrap();
+        assert_eq!(read.dimensions(), 3);
+        assert_eq!(read.iter().map(|(key, _)| key).collect::<Vec<_>>(), vec![7, 9]);
+        assert_eq!(read.search(&[0.0, 2.0, 0.0], 1, |_| true).keys, vec![9]);
+
+        std::fs::write(&path, b"usearch").unwrap();
+        assert!(!FlatIndex::is_flat_file(&path).unwrap());
+    }
+}
@@ -0,0 +1,199 @@
+//! Append-only log of changes to a usearch index
+//!
+//! Writing a multi-GB index file and its mappings after every small
+//! incremental sync dominates indexing time on large repositories, and
+//! vectors held only in memory until a save are lost in a crash while the
+//! keyword index and metadata store already committed their chunks. Every
+//! insert, delete and rename therefore appends its changes to a log next to
+//! the index before returning, and saves only rewrite the index (and empty
+//! the log) once the log grows past a share of the index. Opening an index
+//! replays its log on top of the saved file. Full builds skip the log, since
+//! a crash restarts them anyway, and write the index file once at their
+//! final save.
+//!
+//! Each entry is written as its length (little-endian u32) followed by its
+//! bincode encoding, so vectors are stored as raw f32s rather than decimal
+//! text. Logs written as JSON lines before that are still read.
+
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// One change to the index, in the order it was made
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum LogEntry {
+    Add {
+        key: u64,
+        id: String,
+        #[serde(default)]
+        extension: Option<String>,
+        /// Relative path of the chunk's file; absent in logs written before
+        /// path filtered search
+        #[serde(default)]
+        path: Option<String>,
+        vector: Vec<f32>,
+    },
+    Remove {
+        key: u64,
+    },
+    Rename {
+        key: u64,
+        id: String,
+    },
+}
+
+/// Log of the index at `index_path`
+pub(crate) fn log_path(index_path: &Path) -> PathBuf {
+    index_path.with_extension("log")
+}
+
+/// JSON-lines log of the index at `index_path`, written before the binary log
+pub(crate) fn legacy_log_path(index_path: &Path) -> PathBuf {
+    index_path.with_extension("log.jsonl")
+}
+
+/// Append `entries` and flush them to disk
+pub(crate) fn append(path: &Path, entries: &[LogEntry]) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    let mut buffer = Vec::new();
+    for entry in entries {
+        let encoded = bincode::serde::encode_to_vec(entry, bincode::config::standard())
+            .map_err(|e| Error::Io(std::io::Error::other(format!("Failed to serialize log entry: {e}"))))?;
+        buffer.extend_from_slice(&(encoded.len() as u32).to_le_bytes());
+        buffer.extend_from_slice(&encoded);
+    }
+    file.write_all(&buffer)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Entries of the logs of the index at `index_path`, a JSON-lines log
+/// first, none when neither exists. The second value is false when reading
+/// stopped at an unreadable entry, e.g. one a crash cut short; the entries
+/// before it are still returned.
+pub(crate) fn read(index_path: &Path) -> Result<(Vec<LogEntry>, bool)> {
+    let (mut entries, complete) = read_legacy(&legacy_log_path(index_path))?;
+    if !complete {
+        return Ok((entries, false));
+    }
+    let mut reader = match std::fs::File::open(log_path(index_path)) {
+        Ok(file) => std::io::BufReader::new(file),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((entries, true)),
+        Err(e) => return Err(e.into()),
+    };
+    // A clean end falls between entries
+    while !reader.fill_buf()?.is_empty() {
+        let mut length = [0u8; 4];
+        if reader.read_exact(&mut length).is_err() {
+            return Ok((entries, false));
+        }
+        let length = u32::from_le_bytes(length) as u64;
+        // Read through `take`, so a damaged length allocates no more than the file holds
+        let mut encoded = Vec::new();
+        (&mut reader).take(length).read_to_end(&mut encoded)?;
+        if (encoded.len() as u64) < length {
+            return Ok((entries, false));
+        }
+        match bincode::serde::decode_from_slice(&encoded, bincode::config::standard()) {
+            Ok((entry, _len)) => entries.push(entry),
+            Err(_) => return Ok((entries, false)),
+        }
+    }
+    Ok((entries, true))
+}
+
+fn read_legacy(path: &Path) -> Result<(Vec<LogEntry>, bool)> {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((Vec::new(), true)),
+        Err(e) => return Err(e.into()),
+    };
+    let mut entries = Vec::new();
+    for line in std::io::BufReader::new(file).lines() {
+        let Ok(line) = line else {
+            return Ok((entries, false));
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(_) => return Ok((entries, false)),
+        }
+    }
+    Ok((entries, true))
+}
+
+/// Delete the logs of the index at `index_path`
+pub(crate) fn remove(index_path: &Path) -> Result<()> {
+    for path in [log_path(index_path), legacy_log_path(index_path)] {
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_truncated_last_entry_keeps_earlier_entries() {
+        let dir = tempdir().unwrap();
+        let index_path = dir.path().join("index.usearch");
+        let path = log_path(&index_path);
+        assert_eq!(read(&index_path).unwrap(), (Vec::new(), true));
+
+        let entries = vec![
+            LogEntry::Add { key: 0, id: "a".to_string(), extension: Some("rs".to_string()), path: Some("src/a.rs".to_string()), vector: vec![1.0, 0.5] },
+            LogEntry::Rename { key: 0, id: "b".to_string() },
+        ];
+        append(&path, &entries).unwrap();
+        append(&path, &[LogEntry::Remove { key: 0 }]).unwrap();
+        let (read_back, complete) = read(&index_path).unwrap();
+        assert!(complete);
+        assert_eq!(read_back.len(), 3);
+        assert_eq!(read_back[..2], entries[..]);
+
+        // Vectors take four bytes per value
+        let size = std::fs::metadata(&path).unwrap().len();
+        append(&path, &[LogEntry::Add { key: 1, id: "c".to_string(), extension: None, path: None, vector: vec![0.25; 256] }]).unwrap();
+        assert!(std::fs::metadata(&path).unwrap().len() - size < 1100);
+
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&100u32.to_le_bytes()).unwrap();
+        file.write_all(b"cut short").unwrap();
+        let (read_back, complete) = read(&index_path).unwrap();
+        assert!(!complete);
+        assert_eq!(read_back.len(), 4);
+    }
+
+    #[test]
+    fn test_json_lines_log_is_read_first() {
+        let dir = tempdir().unwrap();
+        let index_path = dir.path().join("index.usearch");
+        std::fs::write(
+            legacy_log_path(&index_path),
+            "{\"add\":{\"key\":0,\"id\":\"a\",\"extension\":\"rs\",\"vector\":[1.0,0.5]}}\n",
+        )
+        .unwrap();
+        append(&log_path(&index_path), &[LogEntry::Remove { key: 0 }]).unwrap();
+
+        let (entries, complete) = read(&index_path).unwrap();
+        assert!(complete);
+        assert_eq!(
+            entries,
+            vec![
+                LogEntry::Add { key: 0, id: "a".to_string(), extension: Some("rs".to_string()), path: None, vector: vec![1.0, 0.5] },
+                LogEntry::Remove { key: 0 },
+            ]
+        );
+
+        remove(&index_path).unwrap();
+        assert!(!log_path(&index_path).exists() && !legacy_log_path(&index_path).exists());
+    }
+}
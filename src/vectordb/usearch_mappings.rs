@@ -0,0 +1,313 @@
+//! Chunk IDs of usearch vectors, kept in the codebase's metadata store
+//!
+//! usearch addresses vectors by `u64` keys. The chunk ID, file extension and
+//! file path behind each key used to be rewritten to a `mappings.json` file next to
+//! the index on every save, which took long on large indexes and could be
+//! left half-written or out of step with the index by a crash. They now live
+//! in a tree of the codebase's metadata sled database, updated by each save
+//! with one atomic batch holding only the keys changed since the previous
+//! save. Indexes saved with a JSON file are moved to sled by their next save.
+//!
+//! Next to the index, `index.mappings` only names its tree. A save that
+//! replaces every mapping fills a new tree and then points the file at it,
+//! so the mappings switch at once, and a staged index moved into place
+//! brings its tree along.
+
+use crate::config::{VectorMetric, VectorQuantization};
+use crate::integrity;
+use crate::{Error, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Prefix of the names of mapping trees in the metadata store
+const TREE_PREFIX: &str = "usearch_mappings/";
+
+/// Format version of the file naming an index's tree
+const POINTER_FORMAT_VERSION: u32 = 1;
+
+/// Prefix of the entries mapping a big-endian key to its chunk ID
+const ID_PREFIX: u8 = b'k';
+
+/// Prefix of the entries mapping a big-endian key to its file extension
+const EXTENSION_PREFIX: u8 = b'e';
+
+/// Prefix of the entries mapping a big-endian key to its file's relative path
+const PATH_PREFIX: u8 = b'p';
+
+/// Prefix of the empty entries of big-endian keys of deleted vectors the
+/// index file still holds
+const TOMBSTONE_PREFIX: u8 = b't';
+
+const NEXT_ID_KEY: &[u8] = b"next_id";
+const QUANTIZATION_KEY: &[u8] = b"quantization";
+const METRIC_KEY: &[u8] = b"metric";
+
+/// Mappings of an index as saved
+#[derive(Debug, Default)]
+pub(crate) struct StoredMappings {
+    pub ids: HashMap<u64, String>,
+    pub extensions: HashMap<u64, String>,
+    /// Empty for stores saved before path filtered search
+    pub paths: HashMap<u64, String>,
+    /// Keys of deleted vectors not yet vacuumed from the index
+    pub tombstones: HashSet<u64>,
+    pub next_id: u64,
+    pub quantization: VectorQuantization,
+    /// Cosine for stores saved before the metric was configurable
+    pub metric: VectorMetric,
+}
+
+/// File naming the mapping tree of the index at `index_path`
+pub(crate) fn mappings_path(index_path: &Path) -> PathBuf {
+    index_path.with_extension("mappings")
+}
+
+pub(crate) struct MappingStore {
+    db: sled::Db,
+    tree: sled::Tree,
+    name: String,
+}
+
+impl MappingStore {
+    /// Tree named next to the index at `index_path`, `None` before its first save
+    pub fn open(db: &sled::Db, index_path: &Path) -> Result<Option<Self>> {
+        let Some(name) = integrity::read_checked::<String>(&mappings_path(index_path), POINTER_FORMAT_VERSION)? else {
+            return Ok(None);
+        };
+        if !name.starts_with(TREE_PREFIX) || !db.tree_names().iter().any(|tree| tree == name.as_bytes()) {
+            return Err(Error::Corrupted(format!(
+                "{}: the mapping tree {} is missing from the metadata store",
+                mappings_path(index_path).display(),
+                name
+            )));
+        }
+        Self::with_name(db, name).map(Some)
+    }
+
+    /// A new, empty tree, which `save` points the index at
+    pub fn create(db: &sled::Db) -> Result<Self> {
+        Self::with_name(db, format!("{TREE_PREFIX}{}", uuid::Uuid::new_v4()))
+    }
+
+    fn with_name(db: &sled::Db, name: String) -> Result<Self> {
+        let tree = db.open_tree(&name).map_err(storage_error)?;
+        Ok(Self { db: db.clone(), tree, name })
+    }
+
+    /// Drop the tree, e.g. of an index moved aside
+    pub fn remove(self) -> Result<()> {
+        self.db.drop_tree(self.name.as_bytes()).map_err(storage_error)?;
+        Ok(())
+    }
+
+    /// Saved mappings, `None` if nothing was saved yet
+    pub fn load(&self) -> Result<Option<StoredMappings>> {
+        let Some(next_id) = self.tree.get(NEXT_ID_KEY).map_err(storage_error)? else {
+            return Ok(None);
+        };
+        let mut mappings = StoredMappings {
+            next_id: decode_key(&next_id)?,
+            ..StoredMappings::default()
+        };
+        if let Some(quantization) = self.tree.get(QUANTIZATION_KEY).map_err(storage_error)? {
+            mappings.quantization = serde_json::from_slice(&quantization)?;
+        }
+        if let Some(metric) = self.tree.get(METRIC_KEY).map_err(storage_error)? {
+            mappings.metric = serde_json::from_slice(&metric)?;
+        }
+        for (prefix, target) in [
+            (ID_PREFIX, &mut mappings.ids),
+            (EXTENSION_PREFIX, &mut mappings.extensions),
+            (PATH_PREFIX, &mut mappings.paths),
+        ] {
+            for entry in self.tree.scan_prefix([prefix]) {
+                let (key, value) = entry.map_err(storage_error)?;
+                let value = String::from_utf8(value.to_vec())
+                    .map_err(|e| Error::Corrupted(format!("invalid mapping value: {e}")))?;
+                target.insert(decode_key(&key[1..])?, value);
+            }
+        }
+        for entry in self.tree.scan_prefix([TOMBSTONE_PREFIX]) {
+            let (key, _) = entry.map_err(storage_error)?;
+            mappings.tombstones.insert(decode_key(&key[1..])?);
+        }
+        Ok(Some(mappings))
+    }
+
+    /// Save the mappings and tombstones of `changed` keys, removing those of
+    /// keys without either, in one atomic batch, then point the index at
+    /// `index_path` to this tree if it named another, which is dropped
+    #[allow(clippy::too_many_arguments)]
+    pub fn save(
+        &self,
+        index_path: &Path,
+        changed: impl IntoIterator<Item = u64>,
+        ids: &HashMap<u64, String>,
+        extensions: &HashMap<u64, String>,
+        paths: &HashMap<u64, String>,
+        tombstones: &HashSet<u64>,
+        next_id: u64,
+        quantization: VectorQuantization,
+        metric: VectorMetric,
+    ) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        for key in changed {
+            let id_entry = entry_key(ID_PREFIX, key);
+            match ids.get(&key) {
+                Some(id) => batch.insert(id_entry.as_slice(), id.as_bytes()),
+                None => batch.remove(id_entry.as_slice()),
+            }
+            for (prefix, values) in [(EXTENSION_PREFIX, extensions), (PATH_PREFIX, paths)] {
+                let entry = entry_key(prefix, key);
+                match values.get(&key).filter(|_| ids.contains_key(&key)) {
+                    Some(value) => batch.insert(entry.as_slice(), value.as_bytes()),
+                    None => batch.remove(entry.as_slice()),
+                }
+            }
+            let tombstone_entry = entry_key(TOMBSTONE_PREFIX, key);
+            if tombstones.contains(&key) {
+                batch.insert(tombstone_entry.as_slice(), b"".as_slice());
+            } else {
+                batch.remove(tombstone_entry.as_slice());
+            }
+        }
+        batch.insert(NEXT_ID_KEY, next_id.to_be_bytes().to_vec());
+        batch.insert(QUANTIZATION_KEY, serde_json::to_vec(&quantization)?);
+        batch.insert(METRIC_KEY, serde_json::to_vec(&metric)?);
+
+        self.tree.apply_batch(batch).map_err(storage_error)?;
+        self.db.flush().map_err(storage_error)?;
+
+        let pointer = mappings_path(index_path);
+        let previous = integrity::read_checked::<String>(&pointer, POINTER_FORMAT_VERSION).unwrap_or(None);
+        if previous.as_deref() != Some(self.name.as_str()) {
+            integrity::write_checked(&pointer, POINTER_FORMAT_VERSION, &self.name)?;
+            if let Some(previous) = previous.filter(|name| name.starts_with(TREE_PREFIX)) {
+                self.db.drop_tree(previous.as_bytes()).map_err(storage_error)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Names of the mapping trees the indexes in `index_dir` point to
+fn named_trees(index_dir: &Path) -> HashSet<String> {
+    let mut named = HashSet::new();
+    if let Ok(entries) = std::fs::read_dir(index_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|extension| extension == "mappings") {
+                if let Ok(Some(name)) = integrity::read_checked::<String>(&path, POINTER_FORMAT_VERSION) {
+                    named.insert(name);
+                }
+            }
+        }
+    }
+    named.retain(|name| name.starts_with(TREE_PREFIX));
+    named
+}
+
+/// Drop the mapping trees the indexes in `index_dir` point to, before the
+/// directory is removed
+pub(crate) fn drop_named(db: &sled::Db, index_dir: &Path) -> Result<()> {
+    for name in named_trees(index_dir) {
+        db.drop_tree(name.as_bytes()).map_err(storage_error)?;
+    }
+    Ok(())
+}
+
+/// Drop the mapping trees of `db` that no index in `index_dir` names, e.g.
+/// after the directory was removed or replaced by a staged one
+pub(crate) fn drop_unreferenced(db: &sled::Db, index_dir: &Path) -> Result<()> {
+    let named = named_trees(index_dir);
+    for tree in db.tree_names() {
+        let Ok(name) = std::str::from_utf8(&tree) else {
+            continue;
+        };
+        if name.starts_with(TREE_PREFIX) && !named.contains(name) {
+            db.drop_tree(&tree).map_err(storage_error)?;
+        }
+    }
+    Ok(())
+}
+
+fn entry_key(prefix: u8, key: u64) -> [u8; 9] {
+    let mut entry = [prefix; 9];
+    entry[1..].copy_from_slice(&key.to_be_bytes());
+    entry
+}
+
+fn decode_key(bytes: &[u8]) -> Result<u64> {
+    let bytes: [u8; 8] = bytes
+        .try_into()
+        .map_err(|_| Error::Corrupted(format!("invalid mapping key of {} bytes", bytes.len())))?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+fn storage_error(e: sled::Error) -> Error {
+    Error::Io(std::io::Error::other(format!("Vector mapping store error: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_changed_keys_update_saved_mappings() {
+        let dir = tempdir().unwrap();
+        let db = sled::open(dir.path().join("metadata")).unwrap();
+        let index_path = dir.path().join("index.usearch");
+        assert!(MappingStore::open(&db, &index_path).unwrap().is_none());
+
+        let store = MappingStore::create(&db).unwrap();
+        let mut ids = HashMap::from([(0, "a".to_string()), (1, "b".to_string()), (2, "c".to_string())]);
+        let mut extensions = HashMap::from([(0, "rs".to_string()), (1, "py".to_string())]);
+        let mut paths = HashMap::from([(0, "src/a.rs".to_string()), (1, "b.py".to_string())]);
+        store.save(&index_path, [0, 1, 2], &ids, &extensions, &paths, &HashSet::new(), 3, VectorQuantization::Int8, VectorMetric::Dot).unwrap();
+
+        let reopened = MappingStore::open(&db, &index_path).unwrap().unwrap();
+        ids.remove(&0);
+        extensions.remove(&0);
+        paths.remove(&0);
+        ids.insert(1, "renamed".to_string());
+        ids.insert(3, "d".to_string());
+        let tombstones = HashSet::from([0]);
+        reopened.save(&index_path, [0, 1, 3], &ids, &extensions, &paths, &tombstones, 4, VectorQuantization::Int8, VectorMetric::Dot).unwrap();
+
+        let mappings = store.load().unwrap().unwrap();
+        assert_eq!(mappings.ids, ids);
+        assert_eq!(mappings.extensions, HashMap::from([(1, "py".to_string())]));
+        assert_eq!(mappings.paths, HashMap::from([(1, "b.py".to_string())]));
+        assert_eq!(mappings.tombstones, tombstones);
+        assert_eq!(mappings.next_id, 4);
+        assert_eq!(mappings.quantization, VectorQuantization::Int8);
+        assert_eq!(mappings.metric, VectorMetric::Dot);
+    }
+
+    #[test]
+    fn test_rewrite_switches_to_a_new_tree() {
+        let dir = tempdir().unwrap();
+        let db = sled::open(dir.path().join("metadata")).unwrap();
+        let index_path = dir.path().join("vectors").join("index.usearch");
+        std::fs::create_dir_all(index_path.parent().unwrap()).unwrap();
+
+        let ids = HashMap::from([(0, "a".to_string())]);
+        let first = MappingStore::create(&db).unwrap();
+        first.save(&index_path, [0], &ids, &HashMap::new(), &HashMap::new(), &HashSet::new(), 1, VectorQuantization::None, VectorMetric::Cosine).unwrap();
+
+        let ids = HashMap::from([(5, "b".to_string())]);
+        let second = MappingStore::create(&db).unwrap();
+        second.save(&index_path, [5], &ids, &HashMap::new(), &HashMap::new(), &HashSet::new(), 6, VectorQuantization::None, VectorMetric::Cosine).unwrap();
+
+        // The first tree was dropped once the index named the second
+        let mapping_trees = || db.tree_names().into_iter().filter(|name| name.starts_with(TREE_PREFIX.as_bytes())).count();
+        assert_eq!(mapping_trees(), 1);
+        let mappings = MappingStore::open(&db, &index_path).unwrap().unwrap().load().unwrap().unwrap();
+        assert_eq!(mappings.ids, ids);
+
+        std::fs::remove_dir_all(index_path.parent().unwrap()).unwrap();
+        drop_unreferenced(&db, index_path.parent().unwrap()).unwrap();
+        assert_eq!(mapping_trees(), 0);
+    }
+}
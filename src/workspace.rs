@@ -0,0 +1,310 @@
+//! Monorepo workspace detection
+//!
+//! Reads the workspace manifests at a codebase root (Cargo `[workspace]`
+//! members, npm/yarn `workspaces`, `pnpm-workspace.yaml`, `go.work`) and
+//! lists the packages they declare, so searches and statistics can be scoped
+//! to one package of a large monorepo.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::warn;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Package {
+    pub name: String,
+    /// Directory relative to the codebase root, `/`-separated; empty for the root
+    pub path: String,
+    /// Manifest kind: "cargo", "npm" or "go"
+    pub kind: String,
+}
+
+impl Package {
+    /// Whether `relative_path` lies inside the package directory
+    fn contains(&self, relative_path: &str) -> bool {
+        self.path.is_empty()
+            || relative_path
+                .strip_prefix(self.path.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+}
+
+/// Packages declared by the workspace manifests at `root`, sorted by path
+pub fn detect_packages(root: &Path) -> Vec<Package> {
+    let mut packages: BTreeMap<String, Package> = BTreeMap::new();
+
+    for package in cargo_packages(root)
+        .into_iter()
+        .chain(npm_packages(root))
+        .chain(go_packages(root))
+    {
+        packages.entry(package.path.clone()).or_insert(package);
+    }
+
+    packages.into_values().collect()
+}
+
+/// The innermost package containing `relative_path`
+pub fn package_for<'a>(packages: &'a [Package], relative_path: &str) -> Option<&'a Package> {
+    let relative_path = relative_path.replace('\\', "/");
+    packages
+        .iter()
+        .filter(|package| package.contains(&relative_path))
+        .max_by_key(|package| package.path.len())
+}
+
+/// Package selected by a filter value: its name or its directory
+pub fn find_package<'a>(packages: &'a [Package], filter: &str) -> Option<&'a Package> {
+    let path = normalize_member(filter);
+    let filter = filter.trim();
+    packages
+        .iter()
+        .find(|package| package.name == filter)
+        .or_else(|| packages.iter().find(|package| package.path == path))
+}
+
+fn cargo_packages(root: &Path) -> Vec<Package> {
+    let Some(manifest) = read_toml(&root.join("Cargo.toml")) else {
+        return Vec::new();
+    };
+    let Some(workspace) = manifest.get("workspace") else {
+        return Vec::new();
+    };
+
+    let members = string_array(workspace.get("members"));
+    let excluded = string_array(workspace.get("exclude"));
+
+    let mut packages: Vec<Package> = expand_members(root, &members, "Cargo.toml")
+        .into_iter()
+        .filter(|path| !excluded.iter().any(|excluded| excluded.trim_end_matches('/') == path))
+        .filter_map(|path| {
+            let name = read_toml(&root.join(&path).join("Cargo.toml"))
+                .and_then(|member| member.get("package")?.get("name")?.as_str().map(str::to_string))?;
+            Some(Package { name, path, kind: "cargo".to_string() })
+        })
+        .collect();
+
+    // A workspace root can be a package itself
+    if let Some(name) = manifest.get("package").and_then(|p| p.get("name")).and_then(|n| n.as_str()) {
+        packages.push(Package { name: name.to_string(), path: String::new(), kind: "cargo".to_string() });
+    }
+
+    packages
+}
+
+fn npm_packages(root: &Path) -> Vec<Package> {
+    let package_json = read_json(&root.join("package.json"));
+    let workspaces = package_json.as_ref().and_then(|json| json.get("workspaces"));
+
+    // npm and yarn list members in package.json, pnpm in its own file
+    let mut members = match workspaces {
+        Some(serde_json::Value::Object(object)) => json_strings(object.get("packages")),
+        other => json_strings(other),
+    };
+    if let Ok(pnpm) = std::fs::read_to_string(root.join("pnpm-workspace.yaml")) {
+        members.extend(pnpm_members(&pnpm));
+    }
+
+    let (excluded, members): (Vec<String>, Vec<String>) =
+        members.into_iter().partition(|member| member.starts_with('!'));
+    let excluded: Vec<String> = excluded.iter().map(|member| member[1..].trim_end_matches('/').to_string()).collect();
+
+    expand_members(root, &members, "package.json")
+        .into_iter()
+        .filter(|path| !excluded.contains(path))
+        .map(|path| {
+            let name = read_json(&root.join(&path).join("package.json"))
+                .and_then(|json| json.get("name")?.as_str().map(str::to_string))
+                .unwrap_or_else(|| path.clone());
+            Package { name, path, kind: "npm".to_string() }
+        })
+        .collect()
+}
+
+/// Entries of the `packages:` list in pnpm-workspace.yaml
+fn pnpm_members(content: &str) -> Vec<String> {
+    let mut members = Vec::new();
+    let mut in_packages = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with([' ', '\t', '-']) {
+            in_packages = trimmed == "packages:";
+            continue;
+        }
+        if let Some(entry) = trimmed.strip_prefix('-').filter(|_| in_packages) {
+            members.push(entry.trim().trim_matches(['\'', '"']).to_string());
+        }
+    }
+
+    members
+}
+
+fn go_packages(root: &Path) -> Vec<Package> {
+    let Ok(content) = std::fs::read_to_string(root.join("go.work")) else {
+        return Vec::new();
+    };
+
+    go_work_dirs(&content)
+        .into_iter()
+        .filter_map(|dir| {
+            let path = normalize_member(&dir);
+            let go_mod = std::fs::read_to_string(root.join(&path).join("go.mod")).ok()?;
+            let name = go_mod
+                .lines()
+                .find_map(|line| line.trim().strip_prefix("module "))
+                .map(|module| module.trim().to_string())
+                .unwrap_or_else(|| path.clone());
+            Some(Package { name, path, kind: "go".to_string() })
+        })
+        .collect()
+}
+
+/// Directories named by `use` directives, single-line or in a `use ( ... )` block
+fn go_work_dirs(content: &str) -> Vec<String> {
+    let mut dirs = Vec::new();
+    let mut in_block = false;
+
+    for line in content.lines() {
+        let line = line.split("//").next().unwrap_or("").trim();
+        if in_block {
+            if line == ")" {
+                in_block = false;
+            } else if !line.is_empty() {
+                dirs.push(line.trim_matches('"').to_string());
+            }
+        } else if let Some(rest) = line.strip_prefix("use") {
+            let rest = rest.trim();
+            if rest == "(" {
+                in_block = true;
+            } else if !rest.is_empty() {
+                dirs.push(rest.trim_matches('"').to_string());
+            }
+        }
+    }
+
+    dirs
+}
+
+/// Member directories matching `patterns` that contain `manifest`
+fn expand_members(root: &Path, patterns: &[String], manifest: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+
+    for pattern in patterns {
+        let pattern = normalize_member(pattern);
+        let full_pattern = root.join(&pattern);
+        let Ok(matches) = glob::glob(&full_pattern.to_string_lossy()) else {
+            warn!("[WORKSPACE] Invalid member pattern '{}'", pattern);
+            continue;
+        };
+
+        for dir in matches.flatten() {
+            if !dir.join(manifest).is_file() {
+                continue;
+            }
+            if let Ok(relative) = dir.strip_prefix(root) {
+                let relative = relative.to_string_lossy().replace('\\', "/");
+                if !relative.is_empty() && !paths.contains(&relative) {
+                    paths.push(relative);
+                }
+            }
+        }
+    }
+
+    paths
+}
+
+/// `member` relative to the root without a leading `./`; empty for the root itself
+fn normalize_member(member: &str) -> String {
+    let member = member.trim().trim_end_matches('/');
+    if member == "." {
+        return String::new();
+    }
+    member.strip_prefix("./").unwrap_or(member).to_string()
+}
+
+fn read_toml(path: &Path) -> Option<toml::Value> {
+    let content = std::fs::read_to_string(path).ok()?;
+    match content.parse() {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("[WORKSPACE] Cannot parse {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn read_json(path: &Path) -> Option<serde_json::Value> {
+    let content = std::fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("[WORKSPACE] Cannot parse {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn string_array(value: Option<&toml::Value>) -> Vec<String> {
+    value
+        .and_then(|value| value.as_array())
+        .map(|items| items.iter().filter_map(|item| item.as_str().map(str::to_string)).collect())
+        .unwrap_or_default()
+}
+
+fn json_strings(value: Option<&serde_json::Value>) -> Vec<String> {
+    value
+        .and_then(|value| value.as_array())
+        .map(|items| items.iter().filter_map(|item| item.as_str().map(str::to_string)).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_detects_cargo_and_npm_workspaces() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(root, "Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"crates/scratch\"]\n");
+        write(root, "crates/core/Cargo.toml", "[package]\nname = \"core\"\n");
+        write(root, "crates/scratch/Cargo.toml", "[package]\nname = \"scratch\"\n");
+        write(root, "package.json", r#"{"workspaces": {"packages": ["packages/*"]}}"#);
+        write(root, "packages/auth/package.json", r#"{"name": "@acme/auth"}"#);
+
+        let packages = detect_packages(root);
+        let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["core", "@acme/auth"]);
+
+        assert_eq!(package_for(&packages, "packages/auth/src/login.ts").unwrap().name, "@acme/auth");
+        assert!(package_for(&packages, "packages/authz/index.ts").is_none());
+        assert_eq!(find_package(&packages, "packages/auth/").unwrap().name, "@acme/auth");
+    }
+
+    #[test]
+    fn test_parses_pnpm_and_go_work() {
+        assert_eq!(
+            pnpm_members("packages:\n  - 'apps/*'\n  - \"!apps/legacy\"\ncatalog:\n  - ignored\n"),
+            vec!["apps/*", "!apps/legacy"]
+        );
+        assert_eq!(
+            go_work_dirs("go 1.22\n\nuse (\n\t./svc/api // main API\n\t./libs/common\n)\nuse ./tools\n"),
+            vec!["./svc/api", "./libs/common", "./tools"]
+        );
+        // `use .` names the root module
+        assert_eq!(normalize_member("."), "");
+        assert_eq!(normalize_member("./"), "");
+        assert_eq!(normalize_member("./svc/api/"), "svc/api");
+    }
+}